@@ -0,0 +1,33 @@
+//! The game as a library: runs it as usual and prints the player's jumps, landings, coins
+//! and deaths as they happen, using nothing but the public `events` module.
+//! `cargo run --example event_log`
+
+use bevy::prelude::*;
+use bevy_platformer::events::{CoinCollected, Jumped, Landed, PlayerDied};
+
+fn main() {
+    let mut app = bevy_platformer::game_app();
+    // Every gameplay event is sent in `FixedUpdate`, so they're all in by `FixedPostUpdate`.
+    app.add_systems(FixedPostUpdate, log_gameplay);
+    app.run();
+}
+
+fn log_gameplay(
+    mut jumped: EventReader<Jumped>,
+    mut landed: EventReader<Landed>,
+    mut coins: EventReader<CoinCollected>,
+    mut died: EventReader<PlayerDied>,
+) {
+    for event in jumped.read() {
+        println!("{:?} jumped", event.entity);
+    }
+    for event in landed.read() {
+        println!("{:?} landed at {:.0} px/s", event.entity, event.speed);
+    }
+    for event in coins.read() {
+        println!("coin collected at {}", event.position);
+    }
+    for _ in died.read() {
+        println!("the player died");
+    }
+}
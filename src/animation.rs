@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use crate::movement::MovementMode;
+use crate::physics::{Up, Velocity};
+use crate::player::{Facing, Grounded, Player};
+
+/// Horizontal speed, in pixels per second, below which the player counts as standing still.
+const RUN_MIN_SPEED: f32 = 72.;
+const RUN_FRAME_SECS: f32 = 0.12;
+/// How much faster the stride animates while running.
+const SPRINT_FRAME_RATE: f32 = 1.5;
+
+pub struct SpriteAnimationPlugin;
+
+impl Plugin for SpriteAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (update_animation_state, animate_player).chain());
+    }
+}
+
+/// What the player's body is doing, as far as the sprite sheet cares.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    Run,
+    Jump,
+    Fall,
+}
+
+impl AnimationState {
+    /// Frames of `sprites/player.png`, a row of four 32x48 frames: standing, mid-stride,
+    /// stretched for the jump and spread for the fall. Running alternates the first two.
+    fn frames(self) -> &'static [usize] {
+        match self {
+            AnimationState::Idle => &[0],
+            AnimationState::Run => &[0, 1],
+            AnimationState::Jump => &[2],
+            AnimationState::Fall => &[3],
+        }
+    }
+}
+
+/// Steps through the current state's frames. Purely visual, so it runs on render time.
+#[derive(Component)]
+pub struct SpriteAnimation {
+    timer: Timer,
+    frame: usize,
+}
+
+impl Default for SpriteAnimation {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(RUN_FRAME_SECS, TimerMode::Repeating),
+            frame: 0,
+        }
+    }
+}
+
+/// Upside down the sprite is mirrored top to bottom, and rising means heading down the
+/// screen.
+fn update_animation_state(
+    mut player: Query<(&Velocity, &Grounded, &Up, &Facing, &mut AnimationState, &mut Sprite), With<Player>>,
+) {
+    for (velocity, grounded, up, facing, mut state, mut sprite) in &mut player {
+        let next = match (grounded.0, velocity.0) {
+            (true, velocity) if velocity.x.abs() > RUN_MIN_SPEED => AnimationState::Run,
+            (true, _) => AnimationState::Idle,
+            (false, velocity) if velocity.y * up.0 > 0. => AnimationState::Jump,
+            (false, _) => AnimationState::Fall,
+        };
+        state.set_if_neq(next);
+        if sprite.flip_x != (facing.x < 0.) {
+            sprite.flip_x = facing.x < 0.;
+        }
+        if sprite.flip_y != up.is_flipped() {
+            sprite.flip_y = up.is_flipped();
+        }
+    }
+}
+
+fn animate_player(
+    mut player: Query<(Ref<AnimationState>, &mut SpriteAnimation, &mut TextureAtlas, Option<&MovementMode>)>,
+    time: Res<Time>,
+) {
+    for (state, mut animation, mut atlas, mode) in &mut player {
+        let rate = if mode == Some(&MovementMode::Run) { SPRINT_FRAME_RATE } else { 1. };
+        if state.is_changed() {
+            animation.frame = 0;
+            animation.timer.reset();
+        } else if animation.timer.tick(time.delta().mul_f32(rate)).just_finished() {
+            animation.frame += 1;
+        }
+        let frames = state.frames();
+        atlas.index = frames[animation.frame % frames.len()];
+    }
+}
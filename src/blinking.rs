@@ -0,0 +1,113 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::level::ResetLevel;
+use crate::physics::{move_bodies, Collider, DynamicBody, Position, Shape};
+use crate::GameState;
+
+pub const BLINKING_COLOR: Color = Color::srgb(0.45, 0.7, 0.8);
+/// How long before it goes a blinking block starts flashing, in seconds.
+const WARN_SECS: f32 = 0.3;
+/// Flashes a second while it's warning.
+const FLASH_RATE: f32 = 10.;
+/// How much of a block still shows while it's gone, so the gap can be planned for.
+const OFF_ALPHA: f32 = 0.2;
+const FLASH_ALPHA: f32 = 0.5;
+
+pub struct BlinkingPlugin;
+
+impl Plugin for BlinkingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlinkClock>()
+            .add_systems(OnEnter(GameState::Restarting), reset_clock.in_set(ResetLevel))
+            .add_systems(FixedUpdate, blink.before(move_bodies))
+            .add_systems(Update, fade_blinking);
+    }
+}
+
+/// Seconds of play since the level started, which every blinking block keeps time by, so
+/// blocks with the same timings blink together and every attempt blinks the same.
+#[derive(Resource, Default)]
+pub struct BlinkClock(f32);
+
+fn reset_clock(mut clock: ResMut<BlinkClock>) {
+    clock.0 = 0.;
+}
+
+/// A block from `BlockKind::Blinking`. It's solid for the first `on_duration` seconds of
+/// every `period` and gone for the rest, `phase` of a period ahead of the clock.
+#[derive(Component)]
+pub struct Blinking {
+    period: f32,
+    on_duration: f32,
+    phase: f32,
+    solid: bool,
+}
+
+impl Blinking {
+    pub fn new(period: f32, on_duration: f32, phase: f32) -> Self {
+        Self { period, on_duration, phase, solid: true }
+    }
+
+    /// How far into its period the block is at `clock`, in seconds.
+    fn secs_in(&self, clock: f32) -> f32 {
+        let period = self.period.max(f32::EPSILON);
+        (clock + self.phase * period).rem_euclid(period)
+    }
+
+    fn due_on(&self, clock: f32) -> bool {
+        self.secs_in(clock) < self.on_duration
+    }
+}
+
+/// A block only turns solid once nothing is inside it, so it never closes around the
+/// player or a crate; until then it stays gone, however far into its on time it is.
+fn blink(
+    mut commands: Commands,
+    mut blocks: Query<(Entity, &mut Blinking, &Position, &Shape)>,
+    bodies: Query<(&Position, &Shape), (With<DynamicBody>, Without<Blinking>)>,
+    mut clock: ResMut<BlinkClock>,
+    time: Res<Time>,
+) {
+    clock.0 += time.delta_seconds();
+    for (entity, mut block, position, shape) in &mut blocks {
+        let due_on = block.due_on(clock.0);
+        if due_on == block.solid {
+            continue;
+        }
+        if due_on {
+            let footprint = Aabb2d::new(position.0, shape.0 / 2.);
+            let blocked = bodies.iter().any(|(body_pos, body_shape)| Aabb2d::new(body_pos.0, body_shape.0 / 2.).intersects(&footprint));
+            if blocked {
+                continue;
+            }
+            commands.entity(entity).insert(Collider);
+        } else {
+            commands.entity(entity).remove::<Collider>();
+        }
+        block.solid = due_on;
+    }
+}
+
+/// Each blinking block has a material of its own, like crumbling ones, so one held off
+/// by a player standing in it doesn't hold the rest.
+fn fade_blinking(
+    blocks: Query<(&Blinking, &Handle<ColorMaterial>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    clock: Res<BlinkClock>,
+) {
+    for (block, material) in &blocks {
+        let left = block.on_duration - block.secs_in(clock.0);
+        let alpha = if !block.solid {
+            OFF_ALPHA
+        } else if left < WARN_SECS && (left * FLASH_RATE).fract() < 0.5 {
+            FLASH_ALPHA
+        } else {
+            1.
+        };
+        let stale = materials.get(material).is_some_and(|material| material.color.alpha() != alpha);
+        if let Some(material) = materials.get_mut(material).filter(|_| stale) {
+            material.color.set_alpha(alpha);
+        }
+    }
+}
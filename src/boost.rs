@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::movement::ControlLock;
+use crate::physics::{Contacts, PostCollide, Shape, Velocity};
+use crate::player::{Player, VisShape};
+use crate::spring::facing_side;
+use crate::timer::GameTimer;
+
+pub const BOOST_PAD_COLOR: Color = Color::srgb(0.3, 0.8, 0.9);
+/// How quickly a pad eases back after a pulse, per second.
+pub const BOOST_PAD_SNAPPINESS: f32 = 18.;
+/// Seconds after a boost before the same pad can boost the same player again, so resting
+/// against it boosts once rather than every tick.
+const LOCKOUT_SECS: f32 = 0.2;
+/// How long a boost with any sideways push keeps the player's steering off, as with a
+/// spring.
+const SIDEWAYS_LOCK_SECS: f32 = 0.35;
+/// The pad's size relative to its `Shape` as it fires, stretched out along its arrow.
+const PULSE: Vec2 = Vec2::new(0.9, 1.3);
+
+pub struct BoostPlugin;
+
+impl Plugin for BoostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, boost_players.in_set(PostCollide));
+    }
+}
+
+/// Flings a player that touches it along `direction`. Unlike a spring it works from any
+/// side unless `face_only` is set, and with `additive` it adds to the player's velocity
+/// instead of replacing it. Nothing stops it pointing into the floor: it just slams the
+/// player down into a landing.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Deserialize)]
+pub struct BoostPad {
+    pub direction: Vec2,
+    /// Pixels per second.
+    pub strength: f32,
+    /// Only boosts from the side `direction` points out of, like a spring.
+    #[serde(default)]
+    pub face_only: bool,
+    #[serde(default)]
+    pub additive: bool,
+}
+
+impl BoostPad {
+    pub fn boost(&self) -> Vec2 {
+        self.direction.normalize_or_zero() * self.strength
+    }
+}
+
+/// On a player for a moment after a pad boosts them, keeping that pad off them.
+#[derive(Component)]
+struct BoostLockout {
+    pad: Entity,
+    timer: GameTimer,
+}
+
+fn boost_players(
+    mut commands: Commands,
+    mut players: Query<(Entity, &mut Velocity, Option<&mut BoostLockout>), With<Player>>,
+    mut pads: Query<(&BoostPad, &mut VisShape, &Shape), Without<Player>>,
+    contacts: Res<Contacts>,
+    time: Res<Time>,
+) {
+    for (entity, mut velocity, lockout) in &mut players {
+        let mut locked_out = None;
+        if let Some(mut lockout) = lockout {
+            if lockout.timer.tick(time.delta_seconds()).finished() {
+                commands.entity(entity).remove::<BoostLockout>();
+            } else {
+                locked_out = Some(lockout.pad);
+            }
+        }
+        let touched = contacts.of(entity).find(|contact| {
+            Some(contact.other) != locked_out
+                && pads.get(contact.other).is_ok_and(|(pad, ..)| !pad.face_only || facing_side(pad.direction) == contact.side)
+        });
+        let Some(pad_entity) = touched.map(|contact| contact.other) else {
+            continue;
+        };
+        let Ok((pad, mut pad_vis, pad_shape)) = pads.get_mut(pad_entity) else {
+            continue;
+        };
+        let boost = pad.boost();
+        velocity.0 = if pad.additive { velocity.0 + boost } else { boost };
+        if boost.x.abs() > f32::EPSILON {
+            commands.entity(entity).insert(ControlLock::new(SIDEWAYS_LOCK_SECS));
+        }
+        commands.entity(entity).insert(BoostLockout { pad: pad_entity, timer: GameTimer::once(LOCKOUT_SECS) });
+        // Pads are drawn pointing up and rotated, like springs, so this is along the arrow.
+        pad_vis.0 = pad_shape.0 * PULSE;
+    }
+}
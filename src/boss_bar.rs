@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+
+use crate::damage::Damageable;
+
+const BAR_WIDTH: f32 = 600.;
+const BAR_HEIGHT: f32 = 14.;
+/// How long the damage ghost holds its old value before draining toward the real one.
+const GHOST_HOLD_SECS: f32 = 0.4;
+const GHOST_DRAIN_SPEED: f32 = 0.5;
+const FILL_UP_SPEED: f32 = 1.5;
+const SLIDE_OUT_SECS: f32 = 0.4;
+const SLIDE_DISTANCE: f32 = 60.;
+
+pub struct BossBarPlugin;
+
+impl Plugin for BossBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_bar_stack)
+            .add_systems(Update, (spawn_boss_bars, track_boss_health, animate_boss_bars).chain());
+    }
+}
+
+/// Gives an entity a health bar at the top of the screen while it's alive. Insert it when
+/// the fight starts; bars for several entities stack in the order they were shown.
+#[derive(Component, Clone, Debug)]
+pub struct ShowBossBar {
+    pub name: String,
+    /// Number of segments the bar is divided into.
+    pub phases: u32,
+}
+
+#[derive(Component)]
+struct BarStack;
+
+#[derive(Component)]
+struct BossBar {
+    target: Entity,
+    fill: Entity,
+    ghost: Entity,
+    /// Health as a fraction of max, as of the last change.
+    health: f32,
+    /// Fill-up progress when the bar first appears.
+    shown: f32,
+    ghost_value: f32,
+    ghost_hold: Timer,
+    leaving: Option<Timer>,
+}
+
+fn spawn_bar_stack(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            top: Val::Px(16.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(6.),
+            ..default()
+        },
+        ..default()
+    }, BarStack));
+}
+
+fn health_fraction(damageable: &Damageable) -> f32 {
+    (damageable.health as f32 / damageable.max_health.max(1) as f32).clamp(0., 1.)
+}
+
+fn spawn_boss_bars(
+    mut commands: Commands,
+    bosses: Query<(Entity, &ShowBossBar, &Damageable), Added<ShowBossBar>>,
+    stack: Query<Entity, With<BarStack>>,
+) {
+    let Ok(stack) = stack.get_single() else {
+        return;
+    };
+    for (target, show, damageable) in &bosses {
+        let mut fill = Entity::PLACEHOLDER;
+        let mut ghost = Entity::PLACEHOLDER;
+        let panel = commands.spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(2.),
+                ..default()
+            },
+            ..default()
+        }).with_children(|panel| {
+            panel.spawn(TextBundle::from_section(show.name.clone(), TextStyle::default()));
+            panel.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Px(BAR_WIDTH),
+                    height: Val::Px(BAR_HEIGHT),
+                    ..default()
+                },
+                background_color: Color::srgb(0.15, 0.15, 0.15).into(),
+                ..default()
+            }).with_children(|track| {
+                ghost = track.spawn(bar_segment(Color::srgb(1., 0.85, 0.85))).id();
+                fill = track.spawn(bar_segment(Color::srgb(0.8, 0.1, 0.1))).id();
+                // Dividers between phases, drawn over both fills.
+                for pip in 1..show.phases.max(1) {
+                    track.spawn(NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            left: Val::Percent(100. * pip as f32 / show.phases as f32),
+                            width: Val::Px(2.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        background_color: Color::BLACK.into(),
+                        ..default()
+                    });
+                }
+            });
+        }).id();
+        let health = health_fraction(damageable);
+        commands.entity(panel).insert(BossBar {
+            target,
+            fill,
+            ghost,
+            health,
+            shown: 0.,
+            ghost_value: health,
+            ghost_hold: Timer::from_seconds(GHOST_HOLD_SECS, TimerMode::Once),
+            leaving: None,
+        });
+        commands.entity(stack).add_child(panel);
+    }
+}
+
+fn bar_segment(color: Color) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(0.),
+            height: Val::Percent(100.),
+            ..default()
+        },
+        background_color: color.into(),
+        ..default()
+    }
+}
+
+fn track_boss_health(
+    bosses: Query<(Entity, &Damageable), (With<ShowBossBar>, Changed<Damageable>)>,
+    mut bars: Query<&mut BossBar>,
+) {
+    for (entity, damageable) in &bosses {
+        for mut bar in bars.iter_mut().filter(|bar| bar.target == entity) {
+            let health = health_fraction(damageable);
+            if health < bar.health {
+                bar.ghost_hold.reset();
+            } else {
+                bar.ghost_value = bar.ghost_value.max(health);
+            }
+            bar.health = health;
+            if health <= 0. && bar.leaving.is_none() {
+                bar.leaving = Some(Timer::from_seconds(SLIDE_OUT_SECS, TimerMode::Once));
+            }
+        }
+    }
+}
+
+fn animate_boss_bars(
+    mut commands: Commands,
+    mut bars: Query<(Entity, &mut BossBar, &mut Style)>,
+    mut segments: Query<&mut Style, Without<BossBar>>,
+    targets: Query<(), With<ShowBossBar>>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut bar, mut style) in &mut bars {
+        // The boss went away mid-fight (player restart, level unload): nothing to animate toward.
+        if !targets.contains(bar.target) && bar.leaving.is_none() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        bar.shown = (bar.shown + FILL_UP_SPEED * dt).min(1.);
+        if bar.ghost_hold.tick(time.delta()).finished() {
+            bar.ghost_value = (bar.ghost_value - GHOST_DRAIN_SPEED * dt).max(bar.health);
+        }
+        let fill = bar.health.min(bar.shown);
+        let ghost = bar.ghost_value.min(bar.shown);
+        if let Ok(mut fill_style) = segments.get_mut(bar.fill) {
+            fill_style.width = Val::Percent(100. * fill);
+        }
+        if let Ok(mut ghost_style) = segments.get_mut(bar.ghost) {
+            ghost_style.width = Val::Percent(100. * ghost);
+        }
+
+        if let Some(leaving) = &mut bar.leaving {
+            leaving.tick(time.delta());
+            style.margin.top = Val::Px(-SLIDE_DISTANCE * leaving.fraction());
+            if leaving.finished() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
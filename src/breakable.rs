@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+
+use crate::cleanup::{fade_away, FadeStyle};
+use crate::events::BlockBroken;
+use crate::level::{LevelEntity, LevelState};
+use crate::particles::Particle;
+use crate::physics::{Collision, Contacts, PhysicsSet, Position, Rotation, Shape, ZOrder};
+use crate::player::Player;
+use crate::world::BlockIndex;
+
+pub const BREAKABLE_COLOR: Color = Color::srgb(0.75, 0.5, 0.3);
+const DEBRIS_SIZE: f32 = 10.;
+const DEBRIS_SECS: f32 = 1.;
+/// Gravity on debris, heavier than dust so the pieces tumble rather than float.
+const DEBRIS_GRAVITY: f32 = 1659.;
+/// Where each piece flies off to, in pixels per second: the top two pop up and out, the
+/// bottom two drop away to the sides.
+const DEBRIS_VELOCITIES: [Vec2; 4] = [
+    Vec2::new(-230., 461.),
+    Vec2::new(202., 518.),
+    Vec2::new(-317., 173.),
+    Vec2::new(288., 216.),
+];
+
+pub struct BreakablePlugin;
+
+impl Plugin for BreakablePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, break_blocks.after(PhysicsSet::Resolve));
+    }
+}
+
+/// A block from `BlockKind::Breakable`. The player jumping into it from below smashes it
+/// for the rest of the attempt, and so does a projectile.
+#[derive(Component)]
+pub struct Breakable;
+
+/// The contact still carries the velocity the player hit with, before
+/// `stop_at_collisions` took the upward part away.
+fn break_blocks(
+    mut commands: Commands,
+    player: Query<Entity, With<Player>>,
+    blocks: Query<(&Position, &Shape, Option<&BlockIndex>), With<Breakable>>,
+    contacts: Res<Contacts>,
+    mut level_state: ResMut<LevelState>,
+    mut broken: EventWriter<BlockBroken>,
+) {
+    let mut smashed = Vec::new();
+    let hits = player.iter()
+        .flat_map(|player| contacts.of(player))
+        .filter(|contact| contact.side == Collision::Top && contact.velocity.y > 0.);
+    for contact in hits {
+        // Both players can head-butt the same block on one tick.
+        if smashed.contains(&contact.other) {
+            continue;
+        }
+        let Ok((position, shape, index)) = blocks.get(contact.other) else {
+            continue;
+        };
+        smash_block(&mut commands, contact.other, position.0, shape.0, index, &mut level_state, &mut broken);
+        smashed.push(contact.other);
+    }
+}
+
+/// Breaks `block` for the rest of the attempt, wherever the hit came from.
+pub fn smash_block(
+    commands: &mut Commands,
+    block: Entity,
+    position: Vec2,
+    shape: Vec2,
+    index: Option<&BlockIndex>,
+    level_state: &mut LevelState,
+    broken: &mut EventWriter<BlockBroken>,
+) {
+    if let Some(index) = index {
+        level_state.consumed.insert(index.0);
+    }
+    spawn_debris(commands, position, shape);
+    commands.entity(block).despawn_recursive();
+    broken.send(BlockBroken { position });
+}
+
+/// One piece from each quarter of the block.
+fn spawn_debris(commands: &mut Commands, center: Vec2, shape: Vec2) {
+    let quarter = shape / 4.;
+    let offsets = [
+        Vec2::new(-quarter.x, quarter.y),
+        Vec2::new(quarter.x, quarter.y),
+        Vec2::new(-quarter.x, -quarter.y),
+        Vec2::new(quarter.x, -quarter.y),
+    ];
+    for (offset, velocity) in offsets.into_iter().zip(DEBRIS_VELOCITIES) {
+        commands.spawn((
+            Particle {
+                velocity,
+                gravity: DEBRIS_GRAVITY,
+            },
+            fade_away(DEBRIS_SECS, FadeStyle::Shrink),
+            Position(center + offset),
+            Rotation(0.),
+            ZOrder(0.3),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: BREAKABLE_COLOR,
+                    custom_size: Some(Vec2::splat(DEBRIS_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+            LevelEntity,
+        ));
+    }
+}
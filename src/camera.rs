@@ -0,0 +1,690 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::camera_zone::{blend_camera_zones, zoned_follow, zoned_zoom, CameraZones};
+use crate::cutscene::camera_scripted;
+use crate::damage::apply_damage;
+use crate::events::{CollisionEvent, DamageEvent, GroundPounded, Landed, OriginShifted, PlayerTeleported, Respawned};
+use crate::ground_pound::land_ground_pounds;
+use crate::level::{reset_level, shelter_respawn, Downed, GroundHeights, ResetLevel, SpawnSnapshot};
+use crate::origin::{FollowOrigin, WorldOrigin};
+use crate::physics::{move_bodies, project_transforms, stop_at_collisions, update_ground_contact, InterpolationDisabled, PhysicsSet, Position, PostCollide, Rotation, Shape, Up, Velocity, ZOrder};
+use crate::platform::Easing;
+use crate::player::{Player, PlayerId};
+use crate::portal::use_portals;
+use crate::slime::bounce_off_slime;
+use crate::timer::GameTimer;
+use crate::world::{init_world, LevelClearColor, WorldData};
+use crate::{ease_factor, flerp, GameState};
+
+/// How far the camera center may sit above a framed edge, a bit under half a screen.
+const CAMERA_FRAME_REACH: f32 = 280.;
+/// How far below the lowest ground the bottom of the view may go while the floor bias holds.
+const CAMERA_FLOOR_MARGIN: f32 = 120.;
+/// How quickly the floor bias lets go (or takes hold again), as an exponential rate per
+/// second.
+const CAMERA_FLOOR_EASE: f32 = 7.4;
+const LANDING_PUNCH: Vec2 = Vec2::new(0., -12.);
+const DAMAGE_PUNCH: f32 = 18.;
+const DAMAGE_KICK: f32 = 0.04;
+/// Downward speed, in pixels per second, a landing has to beat to shake the camera. A
+/// jump from flat ground lands well under it.
+const HARD_LANDING_SPEED: f32 = 1584.;
+/// Trauma per pixel per second the landing beats `HARD_LANDING_SPEED` by.
+const TRAUMA_PER_LANDING_SPEED: f32 = 0.0007;
+/// Trauma a ground pound's impact adds on top of whatever its landing speed gave.
+const GROUND_POUND_TRAUMA: f32 = 0.4;
+/// Trauma lost per second.
+const TRAUMA_DECAY: f32 = 1.5;
+const SHAKE_OFFSET: f32 = 14.;
+const SHAKE_ROTATION: f32 = 0.03;
+/// Room kept around two players, in unzoomed pixels, when zooming out to fit them.
+const SPREAD_MARGIN: Vec2 = Vec2::new(400., 300.);
+/// The furthest the camera zooms out for two players; past it one can leave the screen.
+const MAX_ZOOM: f32 = 2.;
+const ZOOM_EASE: f32 = 3.;
+/// Target speed, in pixels per second, past which the camera starts pulling back; a
+/// flat-out run stays under it, a dash or a long fall doesn't.
+const SPEED_ZOOM_START: f32 = 900.;
+/// Speed at which the pull-back reaches `SPEED_ZOOM`.
+const SPEED_ZOOM_FULL: f32 = 1800.;
+const SPEED_ZOOM: f32 = 1.15;
+/// How much one notch of the scroll wheel zooms by.
+#[cfg(any(feature = "editor", feature = "debug-tools"))]
+const SCROLL_ZOOM_STEP: f32 = 1.1;
+#[cfg(any(feature = "editor", feature = "debug-tools"))]
+const SCROLL_ZOOM_RANGE: (f32, f32) = (0.25, 4.);
+
+/// Spawns the camera and has it follow the player, or whatever `CameraTarget` names.
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraBounds>()
+            .add_systems(Startup, spawn_camera)
+            .add_systems(Update, update_camera_bounds)
+            // A level that was just loaded needs its bounds before `reset_camera` puts the
+            // camera back inside them.
+            .add_systems(OnEnter(GameState::Restarting), (update_camera_bounds, apply_clear_color).after(init_world).before(ResetLevel))
+            .add_systems(FixedUpdate, camera_follow.after(move_bodies).run_if(not(camera_scripted)))
+            .add_systems(FixedPostUpdate, shift_camera_bounds.in_set(FollowOrigin));
+        // Works in the paused editor too, since it sets the projection itself.
+        #[cfg(any(feature = "editor", feature = "debug-tools"))]
+        app.add_systems(Update, scroll_zoom.before(project_transforms));
+    }
+}
+
+pub struct CameraEffectsPlugin;
+
+impl Plugin for CameraEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraConfig>()
+            .init_resource::<CameraTarget>()
+            .add_event::<CameraPunch>()
+            .add_systems(FixedUpdate, (
+                (punch_on_landing.after(update_ground_contact), punch_on_damage.after(apply_damage), shake_on_ground_pound.after(land_ground_pounds)).in_set(PostCollide),
+                snap_to_respawn.after(shelter_respawn),
+                follow_teleports.after(use_portals),
+                // A slime bounce has already turned the fall around, so it doesn't count.
+                shake_on_hard_landing.in_set(PhysicsSet::Resolve).after(bounce_off_slime).before(stop_at_collisions),
+            ))
+            .add_systems(Update, (apply_camera_punch, camera_shake).after(project_transforms))
+            .add_systems(OnEnter(GameState::Restarting), reset_camera.in_set(ResetLevel).after(reset_level))
+            .add_systems(OnExit(GameState::Restarting), end_camera_transition);
+    }
+}
+
+#[derive(Resource)]
+pub struct CameraConfig {
+    pub punch_stiffness: f32,
+    pub punch_damping: f32,
+    /// Scales every camera effect; 0 turns them off for players who want reduced motion.
+    pub motion_multiplier: f32,
+    /// Keep the bottom of the view from dropping below the lowest ground on screen.
+    pub floor_bias: bool,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            punch_stiffness: 400.,
+            punch_damping: 25.,
+            motion_multiplier: 1.,
+            floor_bias: true,
+        }
+    }
+}
+
+/// What `camera_follow` tracks: an entity, or the players without one. Switching with
+/// `set` can blend the followed point across from the old target instead of jumping, and
+/// a target that's despawned is followed to where it was last seen.
+#[derive(Resource, Default)]
+pub struct CameraTarget {
+    entity: Option<Entity>,
+    blend: Option<TargetBlend>,
+    last_seen: Option<Vec2>,
+    /// Whether the current target going missing has been warned about.
+    lost: bool,
+}
+
+struct TargetBlend {
+    /// Filled in from the camera's position on the first tick of the blend.
+    from: Option<Vec2>,
+    timer: GameTimer,
+}
+
+impl CameraTarget {
+    pub fn entity(&self) -> Option<Entity> {
+        self.entity
+    }
+
+    /// Follows `entity`, or the players for `None`, blending over `secs` from wherever the
+    /// camera is pointed; 0 cuts straight over.
+    pub fn set(&mut self, entity: Option<Entity>, secs: f32) {
+        *self = Self {
+            entity,
+            blend: (secs > 0.).then(|| TargetBlend { from: None, timer: GameTimer::once(secs) }),
+            ..default()
+        };
+    }
+
+    /// Where to follow this tick: the live target, eased in from the blend's start.
+    fn blended(&mut self, target: Vec2, camera: Vec2, dt: f32) -> Vec2 {
+        let Some(blend) = &mut self.blend else {
+            return target;
+        };
+        let from = *blend.from.get_or_insert(camera);
+        let t = Easing::EaseInOut.apply(blend.timer.tick(dt).fraction());
+        if blend.timer.finished() {
+            self.blend = None;
+        }
+        from.lerp(target, t)
+    }
+}
+
+/// The rectangle around every block in the level, in live coordinates. The camera never
+/// shows past it; `None` until a level is loaded.
+#[derive(Resource, Default)]
+pub struct CameraBounds(pub Option<Rect>);
+
+impl CameraBounds {
+    fn build(world: &WorldData, origin: WorldOrigin) -> Self {
+        Self(world.0.iter()
+            .map(|block| Rect::from_center_size(origin.to_live(block.position), block.shape))
+            .reduce(|bounds, rect| bounds.union(rect)))
+    }
+
+    /// Where a camera with a view of `half_view` centered on `point` has to sit to stay
+    /// inside the bounds. On an axis where the level is smaller than the view, the level
+    /// is centered instead.
+    pub fn clamp(&self, point: Vec2, half_view: Vec2) -> Vec2 {
+        let Some(bounds) = self.0 else {
+            return point;
+        };
+        let min = bounds.min + half_view;
+        let max = bounds.max - half_view;
+        let center = bounds.center();
+        Vec2::new(
+            if min.x <= max.x { point.x.clamp(min.x, max.x) } else { center.x },
+            if min.y <= max.y { point.y.clamp(min.y, max.y) } else { center.y },
+        )
+    }
+}
+
+/// The camera's own zoom, 1 being none and more showing more of the level. It's the
+/// scale effects like hitstop zoom in from and return to. `camera_follow` eases `current`
+/// toward `target` at `smoothing` per second, or without a target works one out: far
+/// enough out to keep two players on screen, a little further at speed, and whatever the
+/// camera zones want on top.
+#[derive(Component)]
+pub struct CameraZoom {
+    pub current: f32,
+    pub target: Option<f32>,
+    pub smoothing: f32,
+}
+
+impl Default for CameraZoom {
+    fn default() -> Self {
+        Self { current: 1., target: None, smoothing: ZOOM_EASE }
+    }
+}
+
+/// The pull-back for a target moving at `speed`, from 1 up to `SPEED_ZOOM`.
+fn speed_zoom(speed: f32) -> f32 {
+    let t = ((speed - SPEED_ZOOM_START) / (SPEED_ZOOM_FULL - SPEED_ZOOM_START)).clamp(0., 1.);
+    flerp(1., SPEED_ZOOM, t)
+}
+
+/// Keeps the top edge of this entity's `Shape` in view while the camera follows its target.
+#[derive(Component)]
+pub struct CameraFrame;
+
+/// An instant camera offset (and rotation kick) that springs back to rest. Unlike shake
+/// it's directional, so the impact reads as coming from somewhere.
+#[derive(Event)]
+pub struct CameraPunch {
+    pub offset: Vec2,
+    pub rotation: f32,
+}
+
+#[derive(Component)]
+pub struct Camera;
+
+/// How shaken up the camera is, from 0 to 1. Shake grows with its square, so small
+/// amounts barely show, and it drains away on its own.
+#[derive(Component, Default)]
+pub struct Trauma(pub f32);
+
+/// How `camera_follow` leads its target. The camera aims ahead of the target in the
+/// direction it's moving so the player can see where they're headed.
+#[derive(Component)]
+pub struct CameraFollowConfig {
+    /// Seconds of the target's travel the camera leads it by.
+    pub lookahead_scale: f32,
+    /// The furthest the camera leads the target on each axis.
+    pub max_lookahead: Vec2,
+    /// How quickly the lookahead moves toward its goal, per second. Kept low so flipping
+    /// direction swings the view over instead of whipping it.
+    pub lookahead_smoothing: f32,
+}
+
+impl Default for CameraFollowConfig {
+    fn default() -> Self {
+        Self {
+            lookahead_scale: 0.21,
+            max_lookahead: Vec2::new(150., 80.),
+            lookahead_smoothing: 2.9,
+        }
+    }
+}
+
+/// The smoothed lookahead `camera_follow` is currently adding to the target's position.
+#[derive(Component, Default)]
+pub struct Lookahead(Vec2);
+
+/// Spring state for accumulated punches. Applied on top of the camera's `Transform` after
+/// it's projected, so the logical `Position` that follow logic works with never sees it.
+#[derive(Component, Default)]
+pub struct PunchOffset {
+    offset: Vec2,
+    velocity: Vec2,
+    rotation: f32,
+    angular_velocity: f32,
+}
+
+pub fn spawn_camera(
+    mut commands: Commands
+) {
+    commands.spawn((Camera2dBundle::default(),
+                    Position(Vec2::new(0., 0.)),
+                    Velocity(Vec2::new(0., 0.)),
+                    Camera,
+                    CameraFollowConfig::default(),
+                    Lookahead::default(),
+                    CameraZoom::default(),
+                    PunchOffset::default(),
+                    Trauma::default(),
+                    Rotation(0.),
+                    ZOrder(0.0)
+    ));
+}
+
+/// Follows the `CameraTarget`, or else the player, or the midpoint of both players when
+/// there are two, zooming out as they spread apart so both stay on screen. A downed
+/// player isn't followed, so the camera stays with the survivor.
+pub fn camera_follow(
+    mut camera_query: Query<(&mut Velocity, &Position, &mut OrthographicProjection, &mut CameraZoom, &CameraFollowConfig, &mut Lookahead), With<Camera>>,
+    targets: Query<(&Position, Option<&Velocity>, Option<&Up>), Without<Camera>>,
+    players: Query<(&Position, &Velocity, &Up, Has<Downed>), (With<Player>, Without<Camera>)>,
+    mut camera_target: ResMut<CameraTarget>,
+    framed: Query<(&Position, &Shape), With<CameraFrame>>,
+    ground: Res<GroundHeights>,
+    config: Res<CameraConfig>,
+    bounds: Res<CameraBounds>,
+    mut zones: CameraZones,
+    time: Res<Time>,
+    mut floor_bias: Local<f32>,
+) {
+    let dt = time.delta_seconds();
+    let target = match camera_target.entity.map(|target| (target, targets.get(target))) {
+        Some((_, Ok((position, velocity, up)))) => {
+            camera_target.last_seen = Some(position.0);
+            Some(FollowTarget {
+                position: position.0,
+                velocity: velocity.map_or(Vec2::ZERO, |velocity| velocity.0),
+                up: up.copied(),
+                spread: Vec2::ZERO,
+            })
+        }
+        Some((entity, Err(_))) => {
+            if !camera_target.lost {
+                warn!("camera target {entity} is gone, holding on where it was last seen");
+                camera_target.lost = true;
+            }
+            camera_target.last_seen.map(FollowTarget::still).or_else(|| FollowTarget::players(&players))
+        }
+        None => FollowTarget::players(&players),
+    };
+    if let Some(target) = target {
+        if !target.position.is_finite() {
+            warn_once!("camera target is at {}, not following it", target.position);
+            return;
+        }
+        let (target_vel, target_up) = (target.velocity, target.up);
+        let camera_at = camera_query.iter().next().map_or(target.position, |(_, position, ..)| position.0);
+        let target_pos = camera_target.blended(target.position, camera_at, dt);
+        blend_camera_zones(&mut zones, target_pos, dt);
+        for (mut camera_vel, camera_pos, mut projection, mut zoom, follow_config, mut lookahead) in camera_query.iter_mut() {
+            // Zoomed just far enough out to fit the spread and a margin around it.
+            let unzoomed = projection.area.size() / projection.scale;
+            let needed = ((target.spread + SPREAD_MARGIN) / unzoomed).max_element();
+            let goal = zoom.target.unwrap_or_else(|| {
+                let own = needed.clamp(1., MAX_ZOOM).max(speed_zoom(target_vel.length()));
+                zoned_zoom(&zones, own, needed).min(MAX_ZOOM)
+            });
+            zoom.current = flerp(zoom.current, goal, ease_factor(zoom.smoothing, dt));
+            projection.scale = zoom.current;
+            // The projection's area only catches up with the new scale after this tick.
+            let half_view = unzoomed * zoom.current / 2.;
+            // A stationary target has a zero goal, so the view settles back to centered.
+            let goal = (target_vel * follow_config.lookahead_scale)
+                .clamp(-follow_config.max_lookahead, follow_config.max_lookahead);
+            lookahead.0 = lookahead.0.lerp(goal, ease_factor(follow_config.lookahead_smoothing, dt));
+            let mut follow = target_pos + lookahead.0;
+            // Hold back from the target far enough to keep every framed edge on screen.
+            for (position, shape) in &framed {
+                follow.y = follow.y.min(position.0.y + shape.0.y / 2. + CAMERA_FRAME_REACH);
+            }
+            let floor = ground.lowest_in(camera_pos.0.x - half_view.x, camera_pos.0.x + half_view.x)
+                .filter(|_| config.floor_bias);
+            if let Some(floor) = floor {
+                // Dropping below the floor (into a pit) eases the bias off, so the target
+                // doesn't leave the screen. So does walking on the ceiling, where the
+                // ground below is what the target is falling toward.
+                let upright = target_up.is_none_or(|up| !up.is_flipped());
+                let engaged = if target_pos.y >= floor && upright { 1. } else { 0. };
+                *floor_bias = flerp(*floor_bias, engaged, ease_factor(CAMERA_FLOOR_EASE, dt));
+                let lowest = floor - CAMERA_FLOOR_MARGIN + half_view.y;
+                follow.y = flerp(follow.y, follow.y.max(lowest), *floor_bias);
+            }
+            let follow = bounds.clamp(zoned_follow(&zones, follow, half_view), half_view);
+
+            // Calculate the direction vector from the camera to its target
+            let direction = follow - camera_pos.0;
+
+            // Calculate the distance to the target
+            let distance = direction.length();
+
+            // If the distance is significant, update the camera's velocity
+            if distance > 0.1 {
+                // Adjust the damping factor to control the "weight" feel
+                let damping = 5.76;
+
+                // Calculate the new velocity with damping
+                let new_velocity = direction * damping;
+
+                // Update the camera's velocity
+                camera_vel.0 = camera_vel.0.lerp(new_velocity, ease_factor(15.2, dt));
+            } else {
+                // If the distance is small, stop the camera
+                camera_vel.0 = Vec2::ZERO;
+            }
+        }
+    }
+}
+
+/// What `camera_follow` is following this tick.
+struct FollowTarget {
+    position: Vec2,
+    velocity: Vec2,
+    up: Option<Up>,
+    /// How far apart the players are on each axis; zero for a single target.
+    spread: Vec2,
+}
+
+impl FollowTarget {
+    fn still(position: Vec2) -> Self {
+        Self { position, velocity: Vec2::ZERO, up: None, spread: Vec2::ZERO }
+    }
+
+    /// The players still standing, or all of them for the tick someone's downed with no
+    /// survivor left, before the level is put back.
+    fn players(players: &Query<(&Position, &Velocity, &Up, Has<Downed>), (With<Player>, Without<Camera>)>) -> Option<Self> {
+        let standing: Vec<_> = players.iter().filter(|(.., downed)| !downed).collect();
+        let followed = if standing.is_empty() { players.iter().collect() } else { standing };
+        let (_, _, up, _) = followed.first()?;
+        let count = followed.len() as f32;
+        let min = followed.iter().map(|(position, ..)| position.0).reduce(Vec2::min)?;
+        let max = followed.iter().map(|(position, ..)| position.0).reduce(Vec2::max)?;
+        Some(Self {
+            position: followed.iter().map(|(position, ..)| position.0).sum::<Vec2>() / count,
+            velocity: followed.iter().map(|(_, velocity, ..)| velocity.0).sum::<Vec2>() / count,
+            up: Some(**up),
+            spread: max - min,
+        })
+    }
+}
+
+fn update_camera_bounds(
+    world_data: Query<&WorldData, Changed<WorldData>>,
+    mut bounds: ResMut<CameraBounds>,
+    origin: Res<WorldOrigin>,
+) {
+    if let Ok(world) = world_data.get_single() {
+        *bounds = CameraBounds::build(world, *origin);
+    }
+}
+
+/// Clears the screen to the level's color, or bevy's default for a level without one.
+fn apply_clear_color(
+    world_data: Query<Option<&LevelClearColor>, With<WorldData>>,
+    mut cameras: Query<&mut bevy::render::camera::Camera, With<Camera>>,
+) {
+    let Ok(clear_color) = world_data.get_single() else {
+        return;
+    };
+    for mut camera in &mut cameras {
+        camera.clear_color = clear_color.map_or(ClearColorConfig::Default, |color| ClearColorConfig::Custom(color.0));
+    }
+}
+
+fn shift_camera_bounds(mut shifted: EventReader<OriginShifted>, mut bounds: ResMut<CameraBounds>) {
+    for event in shifted.read() {
+        if let Some(rect) = &mut bounds.0 {
+            *rect = Rect::from_corners(rect.min - event.offset, rect.max - event.offset);
+        }
+    }
+}
+
+/// Knocks the view the way the player landed, up for a landing on the ceiling.
+fn punch_on_landing(
+    mut landed: EventReader<Landed>,
+    player: Query<&Up, With<Player>>,
+    mut punches: EventWriter<CameraPunch>,
+) {
+    if let Some(up) = landed.read().find_map(|event| player.get(event.entity).ok()) {
+        punches.send(CameraPunch { offset: LANDING_PUNCH * up.0, rotation: 0. });
+    }
+}
+
+fn punch_on_damage(
+    mut damage: EventReader<DamageEvent>,
+    player: Query<&Position, With<Player>>,
+    mut punches: EventWriter<CameraPunch>,
+) {
+    for event in damage.read() {
+        let Ok(position) = player.get(event.target) else {
+            continue;
+        };
+        let away = event.source_position
+            .map_or(Vec2::NEG_Y, |source| (position.0 - source).normalize_or(Vec2::NEG_Y));
+        punches.send(CameraPunch {
+            offset: away * DAMAGE_PUNCH,
+            rotation: -away.x.signum() * DAMAGE_KICK,
+        });
+    }
+}
+
+fn apply_camera_punch(
+    mut punches: EventReader<CameraPunch>,
+    mut camera: Query<(&mut Transform, &mut PunchOffset), With<Camera>>,
+    config: Res<CameraConfig>,
+    time: Res<Time>,
+) {
+    let Ok((mut transform, mut punch)) = camera.get_single_mut() else {
+        return;
+    };
+
+    for event in punches.read() {
+        punch.offset += event.offset * config.motion_multiplier;
+        punch.rotation += event.rotation * config.motion_multiplier;
+    }
+
+    let dt = time.delta_seconds();
+    let acceleration = -config.punch_stiffness * punch.offset - config.punch_damping * punch.velocity;
+    punch.velocity += acceleration * dt;
+    let velocity = punch.velocity;
+    punch.offset += velocity * dt;
+    let angular = -config.punch_stiffness * punch.rotation - config.punch_damping * punch.angular_velocity;
+    punch.angular_velocity += angular * dt;
+    let angular_velocity = punch.angular_velocity;
+    punch.rotation += angular_velocity * dt;
+
+    transform.translation += punch.offset.extend(0.);
+    transform.rotate_z(punch.rotation);
+}
+
+/// Reads the player's velocity before `stop_at_collisions` takes the fall out of it.
+/// Standing on the ground still reports a bottom contact every tick, but only with one
+/// tick's worth of gravity behind it, so it never adds anything.
+fn shake_on_hard_landing(
+    mut collisions: EventReader<CollisionEvent>,
+    player: Query<(&Velocity, &Up), With<Player>>,
+    mut camera: Query<&mut Trauma, With<Camera>>,
+) {
+    let impact = collisions.read()
+        .filter_map(|event| player.get(event.entity).ok().filter(|(_, up)| event.side == up.feet()))
+        .map(|(velocity, up)| -velocity.0.y * up.0)
+        .fold(0., f32::max);
+    if impact <= HARD_LANDING_SPEED {
+        return;
+    }
+    for mut trauma in &mut camera {
+        trauma.0 = (trauma.0 + (impact - HARD_LANDING_SPEED) * TRAUMA_PER_LANDING_SPEED).min(1.);
+    }
+}
+
+fn shake_on_ground_pound(mut pounded: EventReader<GroundPounded>, mut camera: Query<&mut Trauma, With<Camera>>) {
+    if pounded.read().count() == 0 {
+        return;
+    }
+    for mut trauma in &mut camera {
+        trauma.0 = (trauma.0 + GROUND_POUND_TRAUMA).min(1.);
+    }
+}
+
+/// Jiggles the projected `Transform` with a few out-of-step sine waves, so the shake is
+/// irregular without needing a random source and `Position` never moves.
+fn camera_shake(
+    mut camera: Query<(&mut Transform, &mut Trauma), With<Camera>>,
+    config: Res<CameraConfig>,
+    time: Res<Time>,
+    mut elapsed: Local<f32>,
+) {
+    let dt = time.delta_seconds();
+    *elapsed += dt;
+    let t = *elapsed;
+    for (mut transform, mut trauma) in &mut camera {
+        trauma.0 = (trauma.0 - TRAUMA_DECAY * dt).max(0.);
+        let shake = trauma.0 * trauma.0 * config.motion_multiplier;
+        if shake <= 0. {
+            continue;
+        }
+        let wobble = Vec2::new(
+            (t * 61.).sin() + (t * 37.3).sin() * 0.5,
+            (t * 53.7).sin() + (t * 29.1).sin() * 0.5,
+        ) / 1.5;
+        transform.translation += (wobble * SHAKE_OFFSET * shake).extend(0.);
+        transform.rotate_z(((t * 47.9).sin() * SHAKE_ROTATION) * shake);
+    }
+}
+
+/// Snaps the camera onto the player's spawn instead of letting it drift back across the level.
+fn reset_camera(
+    mut commands: Commands,
+    mut camera: Query<(Entity, &mut Position, &mut Velocity, &mut Transform, &mut PunchOffset, &mut Trauma, &mut Lookahead, &mut CameraZoom, &OrthographicProjection), With<Camera>>,
+    mut punches: ResMut<Events<CameraPunch>>,
+    mut target: ResMut<CameraTarget>,
+    player: Query<(&SpawnSnapshot, &PlayerId)>,
+    bounds: Res<CameraBounds>,
+) {
+    punches.clear();
+    target.set(None, 0.);
+    let Some((spawn, _)) = player.iter().find(|(_, id)| **id == PlayerId::ONE) else {
+        return;
+    };
+    for (entity, mut position, mut velocity, mut transform, mut punch, mut trauma, mut lookahead, mut zoom, projection) in &mut camera {
+        let spawn = bounds.clamp(spawn.position, projection.area.half_size());
+        position.teleport(&mut commands, entity, spawn);
+        // Stays unsmoothed until the new attempt is running.
+        commands.entity(entity).insert(InterpolationDisabled);
+        velocity.0 = Vec2::ZERO;
+        *punch = PunchOffset::default();
+        trauma.0 = 0.;
+        lookahead.0 = Vec2::ZERO;
+        zoom.current = 1.;
+        transform.translation = spawn.extend(transform.translation.z);
+        transform.rotation = Quat::IDENTITY;
+    }
+}
+
+/// Cuts straight to the player after a death, the same way a restart does, instead of
+/// panning back from wherever they fell.
+fn snap_to_respawn(
+    mut commands: Commands,
+    mut respawned: EventReader<Respawned>,
+    mut camera: Query<(Entity, &mut Position, &mut Velocity, &mut Lookahead, &OrthographicProjection), With<Camera>>,
+    bounds: Res<CameraBounds>,
+) {
+    let Some(event) = respawned.read().last() else {
+        return;
+    };
+    for (entity, mut position, mut velocity, mut lookahead, projection) in &mut camera {
+        position.teleport(&mut commands, entity, bounds.clamp(event.position, projection.area.half_size()));
+        velocity.0 = Vec2::ZERO;
+        lookahead.0 = Vec2::ZERO;
+    }
+}
+
+/// Carries the camera through a portal along with whatever it's following, so it doesn't
+/// pan the whole way across the level to catch up. With two players the middle only
+/// moves by part of the trip.
+fn follow_teleports(
+    mut commands: Commands,
+    mut teleported: EventReader<PlayerTeleported>,
+    mut camera: Query<(Entity, &mut Position, &OrthographicProjection), With<Camera>>,
+    players: Query<(), (With<Player>, Without<Downed>, Without<Camera>)>,
+    camera_target: Res<CameraTarget>,
+    bounds: Res<CameraBounds>,
+) {
+    let followed = players.iter().count().max(1) as f32;
+    let mut shift = Vec2::ZERO;
+    for event in teleported.read() {
+        match camera_target.entity() {
+            None => shift += (event.to - event.from) / followed,
+            Some(target) if target == event.entity => shift += event.to - event.from,
+            Some(_) => {}
+        }
+    }
+    if shift == Vec2::ZERO {
+        return;
+    }
+    for (entity, mut position, projection) in &mut camera {
+        let to = bounds.clamp(position.0 + shift, projection.area.half_size());
+        position.teleport(&mut commands, entity, to);
+    }
+}
+
+fn end_camera_transition(
+    mut commands: Commands,
+    camera: Query<Entity, With<Camera>>,
+) {
+    for entity in &camera {
+        commands.entity(entity).remove::<InterpolationDisabled>();
+    }
+}
+
+/// Scrolling zooms by `SCROLL_ZOOM_STEP` a notch and holds it there; the middle button
+/// hands zoom back to `camera_follow`.
+#[cfg(any(feature = "editor", feature = "debug-tools"))]
+fn scroll_zoom(
+    mut wheel: EventReader<bevy::input::mouse::MouseWheel>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut camera: Query<(&mut CameraZoom, &mut OrthographicProjection), With<Camera>>,
+) {
+    let notches: f32 = wheel.read().map(|event| event.y.signum()).sum();
+    for (mut zoom, mut projection) in &mut camera {
+        if mouse.just_pressed(MouseButton::Middle) {
+            zoom.target = None;
+        }
+        if notches == 0. {
+            continue;
+        }
+        // Scrolling up zooms in.
+        let (min, max) = SCROLL_ZOOM_RANGE;
+        let scale = (zoom.current * SCROLL_ZOOM_STEP.powf(-notches)).clamp(min, max);
+        zoom.target = Some(scale);
+        zoom.current = scale;
+        projection.scale = scale;
+    }
+}
+
+/// Where the mouse is in the world, or `None` while it's outside the window.
+pub fn cursor_world_position(
+    window: &Query<&Window, With<PrimaryWindow>>,
+    camera: &Query<(&bevy::prelude::Camera, &GlobalTransform), With<Camera>>,
+) -> Option<Vec2> {
+    let cursor = window.get_single().ok()?.cursor_position()?;
+    let (camera, transform) = camera.get_single().ok()?;
+    camera.viewport_to_world_2d(transform, cursor)
+}
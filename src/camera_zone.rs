@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::debug::DebugLabel;
+use crate::events::OriginShifted;
+use crate::level::{LevelEntity, ResetLevel};
+use crate::origin::FollowOrigin;
+use crate::physics::{Position, Shape};
+use crate::world::WorldData;
+use crate::{ease_factor, flerp, GameState};
+
+/// How fast a zone takes over the camera and lets it go again: most of the way in half a
+/// second.
+const ZONE_BLEND_RATE: f32 = 6.;
+/// Below this a zone has let go of the camera and is skipped.
+const ZONE_MIN_WEIGHT: f32 = 0.001;
+
+pub struct CameraZonePlugin;
+
+impl Plugin for CameraZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Restarting), spawn_camera_zones.after(ResetLevel))
+            .add_systems(FixedPostUpdate, shift_locked_zones.in_set(FollowOrigin));
+    }
+}
+
+/// What a zone does to the camera while the target is inside it.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum CameraZoneMode {
+    /// Holds the camera still on `center`.
+    Locked { center: Vec2 },
+    /// Keeps the view between the zone's left and right edges.
+    ClampX,
+    /// Keeps the view between the zone's top and bottom edges.
+    ClampY,
+    /// Zooms to `scale`, 1 being none, though never so far in that two players no
+    /// longer fit.
+    ZoomTo { scale: f32 },
+}
+
+/// One camera zone from the level file.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct CameraZoneData {
+    pub position: Vec2,
+    pub shape: Vec2,
+    pub mode: CameraZoneMode,
+    /// Where zones overlap, the highest takes the camera.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// Camera zones placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct CameraZoneSpawns(pub Vec<CameraZoneData>);
+
+/// A region that changes how `camera_follow` behaves. `weight` is how much of a say it
+/// has right now, easing toward 1 while it's the zone the target is in and back to 0
+/// otherwise, so crossing an edge back and forth never jumps the camera.
+#[derive(Component)]
+pub struct CameraZone {
+    mode: CameraZoneMode,
+    priority: i32,
+    weight: f32,
+}
+
+pub type CameraZones<'w, 's> = Query<'w, 's, (Entity, &'static mut CameraZone, &'static Position, &'static Shape)>;
+
+fn spawn_camera_zones(mut commands: Commands, world_data: Query<&CameraZoneSpawns, With<WorldData>>) {
+    let Ok(spawns) = world_data.get_single() else {
+        return;
+    };
+    for data in &spawns.0 {
+        commands.spawn((
+            CameraZone { mode: data.mode, priority: data.priority, weight: 0. },
+            Position(data.position),
+            Shape(data.shape),
+            DebugLabel("camera zones"),
+            LevelEntity,
+        ));
+    }
+}
+
+/// A locked zone's center is a point of its own, apart from the zone's `Position`.
+fn shift_locked_zones(mut shifted: EventReader<OriginShifted>, mut zones: Query<&mut CameraZone>) {
+    for event in shifted.read() {
+        for mut zone in &mut zones {
+            if let CameraZoneMode::Locked { center } = &mut zone.mode {
+                *center -= event.offset;
+            }
+        }
+    }
+}
+
+/// Eases every zone's weight by a tick of `dt`, toward 1 for the highest priority zone
+/// holding `target` and 0 for the rest.
+pub fn blend_camera_zones(zones: &mut CameraZones, target: Vec2, dt: f32) {
+    let active = zones.iter()
+        .filter(|(_, _, position, shape)| Rect::from_center_size(position.0, shape.0).contains(target))
+        .max_by_key(|(entity, zone, ..)| (zone.priority, *entity))
+        .map(|(entity, ..)| entity);
+    for (entity, mut zone, ..) in zones.iter_mut() {
+        let goal = if Some(entity) == active { 1. } else { 0. };
+        zone.weight = flerp(zone.weight, goal, ease_factor(ZONE_BLEND_RATE, dt));
+    }
+}
+
+/// Zones with a say, lowest priority first, so the ones that matter most are blended in
+/// last.
+fn weighted(zones: &CameraZones) -> Vec<(f32, CameraZoneMode, Rect)> {
+    let mut weighted: Vec<_> = zones.iter()
+        .filter(|(_, zone, ..)| zone.weight > ZONE_MIN_WEIGHT)
+        .map(|(entity, zone, position, shape)| ((zone.priority, entity), (zone.weight, zone.mode, Rect::from_center_size(position.0, shape.0))))
+        .collect();
+    weighted.sort_by_key(|(order, _)| *order);
+    weighted.into_iter().map(|(_, zone)| zone).collect()
+}
+
+/// The zoom the zones want instead of `zoom`. `needed` is the least that fits every
+/// player.
+pub fn zoned_zoom(zones: &CameraZones, zoom: f32, needed: f32) -> f32 {
+    weighted(zones).into_iter().fold(zoom, |zoom, (weight, mode, _)| match mode {
+        CameraZoneMode::ZoomTo { scale } => flerp(zoom, scale.max(needed), weight),
+        _ => zoom,
+    })
+}
+
+/// Where the zones want the camera instead of `follow`, with a view of `half_view`. A
+/// clamped zone narrower than the view centers it instead.
+pub fn zoned_follow(zones: &CameraZones, follow: Vec2, half_view: Vec2) -> Vec2 {
+    weighted(zones).into_iter().fold(follow, |follow, (weight, mode, rect)| {
+        let goal = match mode {
+            CameraZoneMode::Locked { center } => center,
+            CameraZoneMode::ClampX => Vec2::new(clamp_axis(follow.x, rect.min.x, rect.max.x, half_view.x), follow.y),
+            CameraZoneMode::ClampY => Vec2::new(follow.x, clamp_axis(follow.y, rect.min.y, rect.max.y, half_view.y)),
+            CameraZoneMode::ZoomTo { .. } => follow,
+        };
+        follow.lerp(goal, weight)
+    })
+}
+
+fn clamp_axis(point: f32, min: f32, max: f32, half_view: f32) -> f32 {
+    if max - min <= half_view * 2. {
+        (min + max) / 2.
+    } else {
+        point.clamp(min + half_view, max - half_view)
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::ground_pound::GroundPound;
+use crate::input::{Action, Actions};
+use crate::physics::{gravitate, CollisionGrace, Contacts, Position, PostCollide, Velocity};
+use crate::player::{control_player, Player, PlayerId};
+use crate::sfx::{PlaySfxAt, SfxKind};
+use crate::timer::GameTimer;
+use crate::{ease_factor, vlerp};
+
+/// How quickly a loaded body settles into the barrel, as an exponential rate per second.
+const LOAD_SNAPPINESS: f32 = 32.;
+/// Long enough to clear the cannon's own collider before it becomes solid again.
+const LAUNCH_GRACE_SECS: f32 = 0.25;
+
+pub struct CannonPlugin;
+
+impl Plugin for CannonPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Cannon>()
+            .add_systems(FixedUpdate, (
+                load_into_cannons.in_set(PostCollide),
+                fire_cannons.after(control_player).before(gravitate),
+            ));
+    }
+}
+
+#[derive(Component, Reflect, Copy, Clone, Debug, PartialEq, Deserialize)]
+#[reflect(Component)]
+pub struct Cannon {
+    pub direction: Vec2,
+    /// Launch speed in pixels per second.
+    pub strength: f32,
+    /// Fires by itself after this many seconds; otherwise waits for a jump press.
+    pub auto_fire_delay: Option<f32>,
+}
+
+/// A body sitting in a cannon: its input and gravity are off until it's fired.
+#[derive(Component)]
+pub struct InCannon {
+    cannon: Entity,
+    timer: Option<GameTimer>,
+}
+
+/// Touching a cannon from any side loads it. The cannon that just fired a body is skipped
+/// by collision grace, so it never shows up here to catch it again.
+fn load_into_cannons(
+    mut commands: Commands,
+    player: Query<Entity, (With<Player>, Without<InCannon>)>,
+    cannons: Query<&Cannon>,
+    contacts: Res<Contacts>,
+) {
+    for entity in &player {
+        let hit = contacts.of(entity)
+            .find_map(|contact| cannons.get(contact.other).ok().map(|cannon| (contact.other, cannon)));
+        if let Some((cannon_entity, cannon)) = hit {
+            commands.entity(entity).remove::<GroundPound>().insert(InCannon {
+                cannon: cannon_entity,
+                timer: cannon.auto_fire_delay.map(GameTimer::once),
+            });
+        }
+    }
+}
+
+fn fire_cannons(
+    mut commands: Commands,
+    mut loaded: Query<(Entity, &mut Position, &mut Velocity, &mut InCannon, Option<&PlayerId>)>,
+    cannons: Query<(&Position, &Cannon), Without<InCannon>>,
+    actions: Actions,
+    mut sfx: EventWriter<PlaySfxAt>,
+    time: Res<Time>,
+) {
+    for (entity, mut position, mut velocity, mut in_cannon, id) in &mut loaded {
+        let jump_pressed = id.is_some_and(|id| actions.player(*id).just_pressed(Action::Jump));
+        let Ok((cannon_pos, cannon)) = cannons.get(in_cannon.cannon) else {
+            commands.entity(entity).remove::<InCannon>();
+            continue;
+        };
+        position.0 = vlerp(position.0, cannon_pos.0, ease_factor(LOAD_SNAPPINESS, time.delta_seconds()));
+        velocity.0 = Vec2::ZERO;
+
+        let timed_out = in_cannon.timer.as_mut()
+            .is_some_and(|timer| timer.tick(time.delta_seconds()).finished());
+        if timed_out || (in_cannon.timer.is_none() && jump_pressed) {
+            position.teleport(&mut commands, entity, cannon_pos.0);
+            sfx.send(PlaySfxAt::new(SfxKind::CannonFire, cannon_pos.0));
+            velocity.0 = cannon.direction.normalize_or_zero() * cannon.strength;
+            commands.entity(entity)
+                .remove::<InCannon>()
+                .insert(CollisionGrace {
+                    entity: in_cannon.cannon,
+                    timer: GameTimer::once(LAUNCH_GRACE_SECS),
+                });
+        }
+    }
+}
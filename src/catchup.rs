@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::time::TimeSystem;
+
+pub struct CatchupPlugin;
+
+impl Plugin for CatchupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MaxCatchupTicks>()
+            .add_systems(First, limit_catchup.after(TimeSystem));
+    }
+}
+
+/// How many fixed ticks one render frame may run. After a hitch (an asset load, a window
+/// drag) Bevy would otherwise run every missed tick back to back, with held inputs
+/// repeating the whole way; the time past the cap is dropped instead.
+#[derive(Resource, Debug)]
+pub struct MaxCatchupTicks {
+    /// ~35ms of simulation at 144Hz, so anything down to 29fps still runs at full speed.
+    pub ticks: u32,
+    /// Hitches longer than this also pause the game until the next key or button press.
+    pub pause_over: Option<Duration>,
+}
+
+impl Default for MaxCatchupTicks {
+    fn default() -> Self {
+        Self {
+            ticks: 5,
+            pause_over: None,
+        }
+    }
+}
+
+/// Caps catch-up by clamping `Time<Virtual>`'s delta, so at most `ticks` steps' worth of
+/// time reaches the fixed clock. Whatever is left over from the last frame is always less
+/// than one step, which keeps the total under `ticks + 1`.
+fn limit_catchup(
+    policy: Res<MaxCatchupTicks>,
+    real: Res<Time<Real>>,
+    fixed: Res<Time<Fixed>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    mut hitch_paused: Local<bool>,
+) {
+    let max_delta = fixed.timestep() * policy.ticks.max(1);
+    if policy.is_changed() {
+        virtual_time.set_max_delta(max_delta);
+    }
+
+    if *hitch_paused {
+        if !virtual_time.is_paused() {
+            // Something else unpaused us.
+            *hitch_paused = false;
+        } else if kb_input.get_just_pressed().next().is_some() || buttons.get_just_pressed().next().is_some() {
+            virtual_time.unpause();
+            *hitch_paused = false;
+        }
+        return;
+    }
+
+    let stall = real.delta();
+    if virtual_time.is_paused() || stall <= max_delta {
+        return;
+    }
+    let dropped = stall - max_delta;
+    info!("hitch of {}ms, dropped {}ms of catch-up", stall.as_millis(), dropped.as_millis());
+    if policy.pause_over.is_some_and(|threshold| stall > threshold) {
+        virtual_time.pause();
+        *hitch_paused = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+    use crate::headless::build_headless_app;
+    use crate::input::{Action, ScriptedInput};
+    use crate::physics::Position;
+    use crate::player::Player;
+    use crate::world::{BlockData, WorldData};
+
+    fn frame(app: &mut App, length: Duration) {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(length));
+        app.update();
+    }
+
+    fn x(app: &mut App) -> f32 {
+        let mut players = app.world_mut().query_filtered::<&Position, With<Player>>();
+        players.single(app.world()).0.x
+    }
+
+    #[test]
+    fn a_stall_only_runs_the_capped_ticks() {
+        let mut app = build_headless_app(WorldData(vec![BlockData::new(Vec2::new(0., -200.), Vec2::new(6000., 50.))]));
+        app.add_plugins(CatchupPlugin);
+        let tick = Duration::from_secs_f64(1. / 144.);
+        for _ in 0..144 {
+            frame(&mut app, tick);
+        }
+        // Up to full speed, so every tick covers the same ground.
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].press(Action::MoveRight);
+        for _ in 0..288 {
+            frame(&mut app, tick);
+        }
+        let before = x(&mut app);
+        frame(&mut app, tick);
+        let per_tick = x(&mut app) - before;
+        assert!(per_tick > 0.);
+
+        let before = x(&mut app);
+        frame(&mut app, Duration::from_millis(500));
+        let stalled = x(&mut app) - before;
+        let cap = MaxCatchupTicks::default().ticks as f32;
+        assert!(stalled <= per_tick * cap + 0.01, "moved {stalled}px in a stall, {per_tick}px a tick");
+    }
+}
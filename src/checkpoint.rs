@@ -0,0 +1,75 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::damage::Damageable;
+use crate::events::CheckpointActivated;
+use crate::level::SpawnSnapshot;
+use crate::physics::{PhysicsSet, Position, Shape};
+use crate::player::Player;
+
+pub const IDLE_COLOR: Color = Color::srgba(0.5, 0.55, 0.6, 0.35);
+const ACTIVE_COLOR: Color = Color::srgba(0.4, 0.9, 0.5, 0.5);
+
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CheckpointMaterials>()
+            .add_systems(FixedUpdate, touch_checkpoints.after(PhysicsSet::Resolve));
+    }
+}
+
+/// A trigger region from a `BlockKind::Checkpoint` block. Touching it makes its center
+/// the player's respawn point until another checkpoint is touched. It never collides.
+#[derive(Component, Default)]
+pub struct Checkpoint {
+    active: bool,
+}
+
+#[derive(Resource)]
+struct CheckpointMaterials {
+    idle: Handle<ColorMaterial>,
+    active: Handle<ColorMaterial>,
+}
+
+impl FromWorld for CheckpointMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+        Self {
+            idle: materials.add(IDLE_COLOR),
+            active: materials.add(ACTIVE_COLOR),
+        }
+    }
+}
+
+/// Only a checkpoint that isn't already the active one does anything, so standing in one
+/// doesn't keep re-committing the spawn or swapping materials. Either player touching one
+/// moves both players' spawns.
+fn touch_checkpoints(
+    mut checkpoints: Query<(Entity, &Position, &Shape, &mut Checkpoint, &mut Handle<ColorMaterial>)>,
+    mut players: Query<(&Position, &Shape, &mut SpawnSnapshot, &Damageable), With<Player>>,
+    materials: Res<CheckpointMaterials>,
+    mut activated: EventWriter<CheckpointActivated>,
+) {
+    let touched = checkpoints.iter()
+        .find(|(_, position, shape, checkpoint, _)| {
+            let checkpoint_aabb = Aabb2d::new(position.0, shape.0 / 2.);
+            !checkpoint.active && players.iter()
+                .any(|(player_pos, player_shape, ..)| Aabb2d::new(player_pos.0, player_shape.0 / 2.).intersects(&checkpoint_aabb))
+        })
+        .map(|(entity, position, ..)| (entity, position.0));
+    let Some((touched, spawn)) = touched else {
+        return;
+    };
+    for (entity, _, _, mut checkpoint, mut material) in &mut checkpoints {
+        let active = entity == touched;
+        if checkpoint.active != active {
+            checkpoint.active = active;
+            *material = if active { materials.active.clone() } else { materials.idle.clone() };
+        }
+    }
+    for (_, _, mut snapshot, damageable) in &mut players {
+        snapshot.commit(spawn, Vec2::ZERO, Some(damageable.health));
+    }
+    activated.send(CheckpointActivated);
+}
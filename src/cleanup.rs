@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+
+use crate::timer::GameTimer;
+
+pub struct CleanupPlugin;
+
+impl Plugin for CleanupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, (despawn_expired, fade_out).chain());
+    }
+}
+
+/// Despawns the entity, children and all, once the timer runs out. It's a `GameTimer`
+/// ticked in `FixedUpdate`, so it stops with the pause menu and hitstop like everything
+/// else in the simulation. Anything short-lived should also be a `LevelEntity`, so a
+/// restart or leaving the level takes it early.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Lifetime(pub GameTimer);
+
+impl Lifetime {
+    pub fn secs(secs: f32) -> Self {
+        Self(GameTimer::once(secs))
+    }
+}
+
+/// How a `FadeOut` goes away.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FadeStyle {
+    /// Turns transparent.
+    #[default]
+    Alpha,
+    /// Shrinks to nothing, staying solid; better for debris than a fade.
+    Shrink,
+}
+
+/// Fades an entity with a `Lifetime` out over its last `duration` seconds, so it doesn't
+/// pop out of existence. `Alpha` works on a sprite, text, or a `ColorMaterial` the entity
+/// has to itself.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FadeOut {
+    pub duration: f32,
+    pub style: FadeStyle,
+}
+
+/// A `Lifetime` of `secs` that fades the whole way through.
+pub fn fade_away(secs: f32, style: FadeStyle) -> (Lifetime, FadeOut) {
+    (Lifetime::secs(secs), FadeOut { duration: secs, style })
+}
+
+pub fn despawn_expired(mut commands: Commands, mut expiring: Query<(Entity, &mut Lifetime)>, time: Res<Time>) {
+    for (entity, mut lifetime) in &mut expiring {
+        if lifetime.0.tick(time.delta_seconds()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn fade_out(
+    mut fading: Query<(&Lifetime, &FadeOut, &mut Transform, Option<&mut Sprite>, Option<&mut Text>, Option<&Handle<ColorMaterial>>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (lifetime, fade, mut transform, sprite, text, material) in &mut fading {
+        let left = if fade.duration > 0. { (lifetime.0.remaining() / fade.duration).min(1.) } else { 1. };
+        match fade.style {
+            FadeStyle::Shrink => transform.scale = Vec3::splat(left),
+            FadeStyle::Alpha => {
+                if let Some(mut sprite) = sprite {
+                    sprite.color.set_alpha(left);
+                }
+                for section in text.into_iter().flat_map(|text| text.into_inner().sections.iter_mut()) {
+                    section.style.color.set_alpha(left);
+                }
+                if let Some(material) = material.and_then(|material| materials.get_mut(material)) {
+                    material.color.set_alpha(left);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::build_headless_app;
+    use crate::world::WorldData;
+
+    #[test]
+    fn an_expired_entity_is_gone_by_the_end_of_its_last_tick() {
+        let mut app = build_headless_app(WorldData(vec![]));
+        app.update();
+        let child = app.world_mut().spawn_empty().id();
+        let entity = app.world_mut().spawn(Lifetime::secs(0.5)).add_child(child).id();
+        // Half a second is 72 ticks.
+        for _ in 0..71 {
+            app.update();
+        }
+        assert!(app.world().get_entity(entity).is_some());
+        app.update();
+        assert!(app.world().get_entity(entity).is_none());
+        assert!(app.world().get_entity(child).is_none());
+    }
+
+    #[test]
+    fn a_lifetime_waits_out_the_pause() {
+        let mut app = build_headless_app(WorldData(vec![]));
+        app.update();
+        let entity = app.world_mut().spawn(Lifetime::secs(0.5)).id();
+        for _ in 0..36 {
+            app.update();
+        }
+        app.world_mut().resource_mut::<Time<Virtual>>().pause();
+        for _ in 0..288 {
+            app.update();
+        }
+        assert!(app.world().get_entity(entity).is_some());
+        app.world_mut().resource_mut::<Time<Virtual>>().unpause();
+        for _ in 0..35 {
+            app.update();
+        }
+        assert!(app.world().get_entity(entity).is_some());
+        app.update();
+        assert!(app.world().get_entity(entity).is_none());
+    }
+}
@@ -0,0 +1,114 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::events::CoinCollected;
+use crate::level::{Downed, LevelEntity, ResetLevel};
+use crate::particles::spawn_ring;
+use crate::physics::{PhysicsSet, Position, Rotation, Shape, ZOrder};
+use crate::pickup::Bobbing;
+use crate::player::Player;
+use crate::world::WorldData;
+use crate::GameState;
+
+const COIN_SIZE: f32 = 14.;
+pub const COIN_COLOR: Color = Color::srgb(1., 0.85, 0.2);
+const BOB_HEIGHT: f32 = 3.;
+/// Phase offset per pixel along x, so a row of coins bobs as a wave instead of in unison.
+const BOB_PHASE_PER_PX: f32 = 0.02;
+const COLLECT_RING_RADIUS: f32 = 20.;
+const COLLECT_RING_DOTS: usize = 8;
+const COLLECT_RING_SECS: f32 = 0.2;
+
+pub struct CoinPlugin;
+
+impl Plugin for CoinPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>()
+            .init_resource::<CoinAssets>()
+            .add_systems(OnEnter(GameState::Restarting), (
+                reset_score.in_set(ResetLevel),
+                spawn_coins.after(ResetLevel),
+            ))
+            .add_systems(FixedUpdate, collect_coins.after(PhysicsSet::Resolve));
+    }
+}
+
+/// Coin positions placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component, Default)]
+pub struct CoinSpawns(pub Vec<Vec2>);
+
+/// A coin waiting to be picked up. It never collides; the player collects it by
+/// overlapping it.
+#[derive(Component)]
+pub struct Coin;
+
+/// Coins collected this attempt. A restart puts every coin back and clears it.
+#[derive(Resource, Default)]
+pub struct Score(pub u32);
+
+/// The one mesh and material every coin is drawn with.
+#[derive(Resource)]
+pub struct CoinAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+}
+
+impl FromWorld for CoinAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(Circle::new(COIN_SIZE / 2.));
+        let material = world.resource_mut::<Assets<ColorMaterial>>().add(COIN_COLOR);
+        Self { mesh, material }
+    }
+}
+
+fn spawn_coins(mut commands: Commands, assets: Res<CoinAssets>, world_data: Query<&CoinSpawns, With<WorldData>>) {
+    let Ok(spawns) = world_data.get_single() else {
+        return;
+    };
+    for &position in &spawns.0 {
+        spawn_coin(&mut commands, &assets, position);
+    }
+}
+
+pub fn spawn_coin(commands: &mut Commands, assets: &CoinAssets, position: Vec2) {
+    commands.spawn((
+        Coin,
+        Position(position),
+        Shape(Vec2::splat(COIN_SIZE)),
+        Rotation(0.),
+        ZOrder(0.08),
+        Bobbing { phase: position.x * BOB_PHASE_PER_PX, ..Bobbing::new(position, BOB_HEIGHT) },
+        ColorMesh2dBundle {
+            mesh: assets.mesh.clone().into(),
+            material: assets.material.clone(),
+            ..default()
+        },
+        LevelEntity,
+    ));
+}
+
+/// The despawn lands before the next fixed tick runs, and each coin is visited once per
+/// tick, so a coin can only ever count once, even with both players on it. The players
+/// share one score.
+pub fn collect_coins(
+    mut commands: Commands,
+    players: Query<(&Position, &Shape), (With<Player>, Without<Downed>)>,
+    coins: Query<(Entity, &Position, &Shape), With<Coin>>,
+    mut score: ResMut<Score>,
+    mut collected: EventWriter<CoinCollected>,
+) {
+    for (entity, position, shape) in &coins {
+        let coin_aabb = Aabb2d::new(position.0, shape.0 / 2.);
+        if !players.iter().any(|(player_pos, player_shape)| Aabb2d::new(player_pos.0, player_shape.0 / 2.).intersects(&coin_aabb)) {
+            continue;
+        }
+        score.0 += 1;
+        collected.send(CoinCollected { position: position.0 });
+        spawn_ring(&mut commands, position.0, COLLECT_RING_RADIUS, COIN_COLOR, COLLECT_RING_DOTS, COLLECT_RING_SECS, false);
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn reset_score(mut score: ResMut<Score>) {
+    *score = Score::default();
+}
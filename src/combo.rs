@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+use crate::enemy::touch_player;
+use crate::events::Stomped;
+use crate::level::ResetLevel;
+use crate::player::{check_grounded, Grounded};
+use crate::popup::{popup_text, PopupConfig};
+use crate::timer::GameTimer;
+use crate::GameState;
+
+/// What the first stomp of a combo is worth; each one after it doubles.
+const BASE_POINTS: u32 = 100;
+/// Doubling stops here, at 12800 a stomp.
+const MAX_DOUBLINGS: u32 = 7;
+/// How much higher each stomp in a row bounces than the one before, as a share of the
+/// first.
+const BOUNCE_STEP: f32 = 0.15;
+/// Stomps past this many in a row keep bouncing as high as this one.
+const MAX_BOUNCE_TIER: u32 = 4;
+/// Holding jump through a stomp bounces this much higher than the tier would on its own.
+const HELD_BOUNCE: f32 = 1.3;
+/// A combo with no stomp for this long is over even without landing, so a long fall
+/// doesn't carry it onto an enemy far below.
+const COMBO_SECS: f32 = 2.;
+const POPUP_COLOR: Color = Color::srgb(1., 0.9, 0.4);
+
+pub struct ComboPlugin;
+
+impl Plugin for ComboPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StompPoints>()
+            .add_systems(OnEnter(GameState::Restarting), reset_points.in_set(ResetLevel))
+            .add_systems(FixedUpdate, (
+                end_combos.after(check_grounded).before(touch_player),
+                score_stomps.after(touch_player),
+            ));
+    }
+}
+
+/// Stomps in a row without landing. `count` is how many so far, and `timer` runs from the
+/// last one; landing or the timer running out ends the combo.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct StompCombo {
+    pub count: u32,
+    pub timer: GameTimer,
+}
+
+impl Default for StompCombo {
+    fn default() -> Self {
+        Self { count: 0, timer: GameTimer::once(COMBO_SECS) }
+    }
+}
+
+impl StompCombo {
+    /// Counts a stomp, returning how many times the first stomp's bounce this one gets and
+    /// what it scores. Holding jump gives the top of the tier's bounce.
+    pub fn stomp(&mut self, jump_held: bool) -> (f32, u32) {
+        self.count += 1;
+        self.timer.reset();
+        let tier = self.count.min(MAX_BOUNCE_TIER) - 1;
+        let bounce = (1. + BOUNCE_STEP * tier as f32) * if jump_held { HELD_BOUNCE } else { 1. };
+        (bounce, BASE_POINTS << (self.count - 1).min(MAX_DOUBLINGS))
+    }
+}
+
+/// Points from stomps this attempt, shared by both players. A restart clears it.
+#[derive(Resource, Default)]
+pub struct StompPoints(pub u32);
+
+fn reset_points(mut points: ResMut<StompPoints>) {
+    *points = StompPoints::default();
+}
+
+/// Runs between `check_grounded` and `touch_player`, so a stomp on the tick the player
+/// lands starts a new combo.
+fn end_combos(mut players: Query<(&Grounded, &mut StompCombo)>, time: Res<Time>) {
+    for (grounded, mut combo) in &mut players {
+        if combo.count == 0 {
+            continue;
+        }
+        if grounded.0 || combo.timer.tick(time.delta_seconds()).finished() {
+            combo.count = 0;
+        }
+    }
+}
+
+/// Adds each stomp's points and floats them up from where it landed, with the combo once
+/// there is one.
+fn score_stomps(mut commands: Commands, mut stomped: EventReader<Stomped>, mut points: ResMut<StompPoints>, config: Res<PopupConfig>) {
+    for event in stomped.read() {
+        points.0 = points.0.saturating_add(event.points);
+        let label = if event.combo > 1 { format!("x{} +{}", event.combo, event.points) } else { format!("+{}", event.points) };
+        popup_text(&mut commands, &config, event.position, label, POPUP_COLOR);
+    }
+}
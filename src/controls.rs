@@ -0,0 +1,252 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use crate::input::{button_name, end_tick_presses, hold_presses, key_name, start_tick_presses, Action, HeldPresses, InputConfig, InputMap, SecondPlayerMap, TickPresses};
+
+const TOGGLE_KEY: KeyCode = KeyCode::F2;
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputMap::load())
+            .init_resource::<InputConfig>()
+            .init_resource::<SecondPlayerMap>()
+            .init_resource::<ControlsScreen>()
+            .init_resource::<HeldPresses>()
+            .init_resource::<TickPresses>()
+            .add_systems(Startup, spawn_controls_screen)
+            .add_systems(PreUpdate, ((navigate_controls, refresh_controls_screen).chain(), hold_presses).after(InputSystem))
+            .add_systems(FixedPreUpdate, start_tick_presses)
+            .add_systems(FixedPostUpdate, end_tick_presses);
+    }
+}
+
+/// A key or gamepad button being bound to an action.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BoundInput {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+enum ControlsMode {
+    #[default]
+    Browsing,
+    /// Waiting for the key or button to bind to the selected action.
+    Capturing,
+    /// The captured input already belongs to `owner`; confirming swaps the two.
+    Confirming { input: BoundInput, owner: Action },
+}
+
+/// The F2 controls screen. While it's open the simulation is paused and every key and
+/// button press is consumed before gameplay runs.
+#[derive(Resource, Default)]
+struct ControlsScreen {
+    open: bool,
+    /// Row under the cursor: one per action, then "Reset to defaults".
+    selected: usize,
+    mode: ControlsMode,
+    /// Whether something else had already paused virtual time when the screen opened.
+    was_paused: bool,
+}
+
+#[derive(Component)]
+struct ControlsPanel;
+
+#[derive(Component)]
+struct ControlsText;
+
+fn spawn_controls_screen(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: Color::srgba(0., 0., 0., 0.6).into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Global(30),
+        ..default()
+    }, ControlsPanel)).with_children(|panel| {
+        panel.spawn((TextBundle::from_section("", TextStyle {
+            font_size: 20.,
+            ..default()
+        }), ControlsText));
+    });
+}
+
+/// First key or gamepad button pressed this frame.
+fn captured_input(keys: &ButtonInput<KeyCode>, buttons: &ButtonInput<GamepadButton>) -> Option<BoundInput> {
+    keys.get_just_pressed().next().map(|key| BoundInput::Key(*key))
+        .or_else(|| buttons.get_just_pressed().next().map(|button| BoundInput::Button(button.button_type)))
+}
+
+fn owner_of(map: &InputMap, input: BoundInput) -> Option<Action> {
+    map.0.iter().find(|(_, binding)| match input {
+        BoundInput::Key(key) => binding.keys.contains(&key),
+        BoundInput::Button(button) => binding.buttons.contains(&button),
+    }).map(|(action, _)| *action)
+}
+
+/// Makes `input` the selected action's only key (or button). With `swap`, the action that
+/// previously had it takes over the selected action's old bindings in its place.
+fn bind(map: &mut InputMap, selected: usize, input: BoundInput, swap: Option<Action>) {
+    let (_, binding) = &mut map.0[selected];
+    let (old_keys, old_buttons) = match input {
+        BoundInput::Key(key) => (std::mem::replace(&mut binding.keys, vec![key]), Vec::new()),
+        BoundInput::Button(button) => (Vec::new(), std::mem::replace(&mut binding.buttons, vec![button])),
+    };
+    let Some(owner) = swap else {
+        return;
+    };
+    for (action, binding) in &mut map.0 {
+        if *action != owner {
+            continue;
+        }
+        match input {
+            BoundInput::Key(key) => {
+                binding.keys.retain(|bound| *bound != key);
+                binding.keys.extend(old_keys.iter().filter(|old| **old != key));
+            }
+            BoundInput::Button(button) => {
+                binding.buttons.retain(|bound| *bound != button);
+                binding.buttons.extend(old_buttons.iter().filter(|old| **old != button));
+            }
+        }
+    }
+}
+
+fn navigate_controls(
+    mut screen: ResMut<ControlsScreen>,
+    mut map: ResMut<InputMap>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut buttons: ResMut<ButtonInput<GamepadButton>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut panel: Query<&mut Visibility, With<ControlsPanel>>,
+) {
+    if !screen.open {
+        if !keys.just_pressed(TOGGLE_KEY) {
+            return;
+        }
+        screen.open = true;
+        screen.selected = 0;
+        screen.mode = ControlsMode::Browsing;
+        screen.was_paused = time.is_paused();
+        time.pause();
+        for mut visibility in &mut panel {
+            *visibility = Visibility::Visible;
+        }
+        keys.reset_all();
+        buttons.reset_all();
+        return;
+    }
+
+    let pressed_button = |buttons: &ButtonInput<GamepadButton>, button_type: GamepadButtonType| {
+        buttons.get_just_pressed().any(|button| button.button_type == button_type)
+    };
+    let rows = map.0.len() + 1;
+    let mut close = false;
+    match screen.mode {
+        ControlsMode::Browsing => {
+            let confirm = keys.just_pressed(KeyCode::Enter) || pressed_button(&buttons, GamepadButtonType::South);
+            if keys.just_pressed(KeyCode::Escape) || keys.just_pressed(TOGGLE_KEY)
+                || pressed_button(&buttons, GamepadButtonType::East) {
+                close = true;
+            } else if keys.just_pressed(KeyCode::ArrowUp) || pressed_button(&buttons, GamepadButtonType::DPadUp) {
+                screen.selected = (screen.selected + rows - 1) % rows;
+            } else if keys.just_pressed(KeyCode::ArrowDown) || pressed_button(&buttons, GamepadButtonType::DPadDown) {
+                screen.selected = (screen.selected + 1) % rows;
+            } else if confirm && screen.selected == map.0.len() {
+                *map = InputMap::default();
+            } else if confirm {
+                screen.mode = ControlsMode::Capturing;
+            } else if keys.just_pressed(KeyCode::Backspace) && screen.selected < map.0.len() {
+                let (_, binding) = &mut map.0[screen.selected];
+                binding.keys.clear();
+                binding.buttons.clear();
+            }
+        }
+        ControlsMode::Capturing => {
+            if keys.just_pressed(KeyCode::Escape) {
+                screen.mode = ControlsMode::Browsing;
+            } else if let Some(input) = captured_input(&keys, &buttons) {
+                let selected = map.0[screen.selected].0;
+                screen.mode = match owner_of(&map, input) {
+                    Some(owner) if owner != selected => ControlsMode::Confirming { input, owner },
+                    _ => {
+                        bind(&mut map, screen.selected, input, None);
+                        ControlsMode::Browsing
+                    }
+                };
+            }
+        }
+        ControlsMode::Confirming { input, owner } => {
+            if keys.just_pressed(KeyCode::Enter) || pressed_button(&buttons, GamepadButtonType::South) {
+                let selected = screen.selected;
+                bind(&mut map, selected, input, Some(owner));
+                screen.mode = ControlsMode::Browsing;
+            } else if keys.just_pressed(KeyCode::Escape) || pressed_button(&buttons, GamepadButtonType::East) {
+                screen.mode = ControlsMode::Browsing;
+            }
+        }
+    }
+
+    if close {
+        screen.open = false;
+        if !screen.was_paused {
+            time.unpause();
+        }
+        for mut visibility in &mut panel {
+            *visibility = Visibility::Hidden;
+        }
+    }
+    // Nothing pressed on this screen reaches gameplay, including the key that closed it.
+    keys.reset_all();
+    buttons.reset_all();
+}
+
+fn input_name(input: BoundInput) -> String {
+    match input {
+        BoundInput::Key(key) => key_name(key),
+        BoundInput::Button(button) => button_name(button),
+    }
+}
+
+fn refresh_controls_screen(
+    screen: Res<ControlsScreen>,
+    map: Res<InputMap>,
+    mut text: Query<&mut Text, With<ControlsText>>,
+) {
+    if !screen.is_changed() && !map.is_changed() {
+        return;
+    }
+    let mut value = String::from("CONTROLS\n\n");
+    for (row, (action, binding)) in map.0.iter().enumerate() {
+        let cursor = if row == screen.selected { ">" } else { " " };
+        let keys = binding.keys.iter().map(|key| key_name(*key)).collect::<Vec<_>>().join(" / ");
+        let buttons = binding.buttons.iter().map(|button| button_name(*button)).collect::<Vec<_>>().join(" / ");
+        let keys = if row == screen.selected && screen.mode == ControlsMode::Capturing {
+            "press a key or button...".to_string()
+        } else {
+            format!("{:<12} {}", if keys.is_empty() { "-" } else { &keys }, if buttons.is_empty() { "-" } else { &buttons })
+        };
+        value += &format!("{cursor} {:<12} {keys}\n", action.name());
+    }
+    let cursor = if screen.selected == map.0.len() { ">" } else { " " };
+    value += &format!("{cursor} Reset to defaults\n\n");
+    value += &match screen.mode {
+        ControlsMode::Browsing => "Enter: rebind   Backspace: clear   Esc: close".to_string(),
+        ControlsMode::Capturing => "Esc: cancel".to_string(),
+        ControlsMode::Confirming { input, owner } => format!(
+            "{} is already bound to {}. Enter to swap, Esc to cancel",
+            input_name(input), owner.name(),
+        ),
+    };
+    for mut text in &mut text {
+        text.sections[0].value = value.clone();
+    }
+}
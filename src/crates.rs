@@ -0,0 +1,357 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::damage::{apply_damage, Damageable};
+use crate::enemy::Enemy;
+use crate::events::{CollisionEvent, CrateGrabbed, DamageEvent, InteractEvent};
+use crate::input::{Action, Actions};
+use crate::interact::{focus_interactable, Interactable};
+use crate::level::{LevelEntity, ResetLevel, SpawnSnapshot};
+use crate::movement::{MovementModifiers, StatId, StatModifier};
+use crate::physics::{handle_collisions, move_bodies, Collider, Collision, Contacts, DynamicBody, Gravitated, LayerMask, PhysicsSet, PhysicsWorld, Position, Rotation, Shape, Teleported, Velocity, ZOrder};
+use crate::player::{Facing, Player, PlayerId};
+use crate::world::WorldData;
+use crate::GameState;
+
+/// Crates bigger than this on either side are too heavy to pick up.
+const CARRY_MAX_SIZE: f32 = 60.;
+/// How far from a crate's center the player can pick it up.
+const GRAB_REACH: f32 = 60.;
+const THROW_VELOCITY: Vec2 = Vec2::new(864., 720.);
+/// How quickly a crate sliding on the ground stops, as an exponential rate per second.
+const GROUND_FRICTION: f32 = 32.;
+const BONK_DAMAGE: i32 = 1;
+/// The fastest a player can shove a crate along, and so walk while shoving.
+const PUSH_SPEED: f32 = 180.;
+/// How far past a pushed crate's leading edge to look for something in the way.
+const PUSH_PROBE: f32 = 2.;
+/// How much of the top and bottom of the leading edge to leave out of that look, so the
+/// floor under the crate and one stacked on it aren't in the way.
+const PUSH_PROBE_INSET: f32 = 4.;
+/// The most crates in a row one push moves.
+const MAX_PUSH_CHAIN: usize = 3;
+const CARRY_SLOWDOWN: [StatModifier; 2] = [
+    StatModifier { stat: StatId::MaxSpeed, multiplier: 0.7, duration: None },
+    StatModifier { stat: StatId::JumpStrength, multiplier: 0.85, duration: None },
+];
+
+pub struct CratePlugin;
+
+impl Plugin for CratePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Restarting), spawn_crates.after(ResetLevel))
+            .add_systems(FixedUpdate, (
+                bonk_enemies.after(move_bodies).before(handle_collisions),
+                slide_crates.in_set(PhysicsSet::Resolve).after(handle_collisions),
+                (grab_or_throw.after(focus_interactable),
+                 drop_when_hurt.after(apply_damage),
+                 carry_crates).chain().after(PhysicsSet::Resolve),
+                push_crates.after(PhysicsSet::Resolve),
+            ));
+    }
+}
+
+#[derive(Debug)]
+pub struct CrateData {
+    pub position: Vec2,
+    pub shape: Vec2,
+}
+
+/// Crates placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component)]
+pub struct CrateSpawns(pub Vec<CrateData>);
+
+/// A loose box that falls, can be stood on and, if it's small enough, carried and thrown.
+/// Crates stack on each other and on whatever else they land on.
+#[derive(Component)]
+pub struct Crate;
+
+/// On a crate the player can shove by walking into it. Friction stops it again once
+/// nothing pushes.
+#[derive(Component)]
+pub struct Pushable;
+
+/// On a crate held over the player's head. It has no `Collider`, `Gravitated` or
+/// `DynamicBody` while held.
+#[derive(Component)]
+pub struct Carried;
+
+/// On a crate flying from a throw, until it lands or hits something.
+#[derive(Component)]
+struct Thrown;
+
+/// On the player while holding a crate. Collisions treat the crate's `height` as part of
+/// the player, so a carried crate can't be shoved into a ceiling.
+#[derive(Component)]
+pub struct Carrying {
+    pub entity: Entity,
+    pub height: f32,
+}
+
+fn spawn_crates(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    world_data: Query<&CrateSpawns, With<WorldData>>,
+) {
+    let Ok(spawns) = world_data.get_single() else {
+        return;
+    };
+    for data in &spawns.0 {
+        let mut entity = commands.spawn((
+            Crate,
+            Pushable,
+            Collider,
+            Gravitated,
+            DynamicBody,
+            Position(data.position),
+            Velocity(Vec2::ZERO),
+            Shape(data.shape),
+            Rotation(0.),
+            ZOrder(0.05),
+            ColorMesh2dBundle {
+                mesh: meshes.add(Rectangle::new(data.shape.x, data.shape.y)).into(),
+                material: materials.add(Color::srgb(0.6, 0.42, 0.25)),
+                ..default()
+            },
+            SpawnSnapshot::new(data.position, Vec2::ZERO),
+            LevelEntity,
+        ));
+        if data.shape.max_element() <= CARRY_MAX_SIZE {
+            entity.insert(Interactable {
+                prompt: "Pick up".into(),
+                radius: GRAB_REACH,
+            });
+        }
+    }
+}
+
+/// Slows loose crates as they slide on whatever they landed on, and ends a throw.
+/// `handle_collisions` does the landing itself.
+fn slide_crates(
+    mut commands: Commands,
+    mut collisions: EventReader<CollisionEvent>,
+    mut crates: Query<&mut Velocity, With<Crate>>,
+    time: Res<Time>,
+) {
+    let keep = (-GROUND_FRICTION * time.delta_seconds()).exp();
+    for event in collisions.read().filter(|event| event.side == Collision::Bottom) {
+        if let Ok(mut velocity) = crates.get_mut(event.entity) {
+            velocity.0.x *= keep;
+            commands.entity(event.entity).remove::<Thrown>();
+        }
+    }
+}
+
+/// A thrown crate hurts the first enemy it hits and bounces back off it.
+fn bonk_enemies(
+    mut commands: Commands,
+    mut crates: Query<(Entity, &Position, &mut Velocity, &Shape), (With<Thrown>, Without<Enemy>)>,
+    enemies: Query<(Entity, &Position, &Shape), (With<Enemy>, With<Damageable>)>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    for (entity, position, mut velocity, shape) in &mut crates {
+        let aabb = Aabb2d::new(position.0, shape.0 / 2.);
+        let hit = enemies.iter()
+            .find(|(_, enemy_pos, enemy_shape)| aabb.intersects(&Aabb2d::new(enemy_pos.0, enemy_shape.0 / 2.)));
+        if let Some((enemy, ..)) = hit {
+            damage.send(DamageEvent {
+                target: enemy,
+                amount: BONK_DAMAGE,
+                source_position: Some(position.0),
+            });
+            velocity.0.x *= -0.3;
+            commands.entity(entity).remove::<Thrown>();
+        }
+    }
+}
+
+/// Where a crate of `crate_shape` sits over a player at `player_pos`.
+fn carry_position(player_pos: Vec2, player_shape: Vec2, crate_shape: Vec2) -> Vec2 {
+    player_pos + Vec2::new(0., (player_shape.y + crate_shape.y) / 2.)
+}
+
+fn grab_or_throw(
+    mut commands: Commands,
+    actions: Actions,
+    mut interactions: EventReader<InteractEvent>,
+    mut players: Query<(Entity, &PlayerId, &Position, &Velocity, &Facing, &Shape, &mut MovementModifiers, Option<&Carrying>), With<Player>>,
+    mut crates: Query<(Entity, &mut Velocity, &Position, &Shape), (With<Crate>, Without<Player>)>,
+    physics: PhysicsWorld,
+    mut grabbed: EventWriter<CrateGrabbed>,
+) {
+    // Nothing has interact focus while carrying, so the key press itself means throw.
+    for (player_entity, id, _, player_vel, facing, _, mut modifiers, carrying) in &mut players {
+        let Some(carrying) = carrying else {
+            continue;
+        };
+        if !actions.player(*id).just_pressed(Action::Interact) {
+            continue;
+        }
+        if let Ok((entity, mut velocity, ..)) = crates.get_mut(carrying.entity) {
+            velocity.0 = Vec2::new(facing.x * THROW_VELOCITY.x + player_vel.0.x, THROW_VELOCITY.y + player_vel.0.y.max(0.));
+            commands.entity(entity).remove::<Carried>().insert((Collider, Gravitated, DynamicBody, Thrown));
+        }
+        commands.entity(player_entity).remove::<Carrying>();
+        for modifier in CARRY_SLOWDOWN {
+            modifiers.remove(modifier);
+        }
+    }
+
+    for event in interactions.read() {
+        let Ok((entity, _, position, shape)) = crates.get(event.target) else {
+            continue;
+        };
+        let Ok((player_entity, _, player_pos, _, _, player_shape, mut modifiers, None)) = players.get_mut(event.player) else {
+            continue;
+        };
+        // No room to lift it under a low ceiling.
+        let overhead = Aabb2d::new(carry_position(player_pos.0, player_shape.0, shape.0), shape.0 / 2.);
+        if physics.overlap_aabb(overhead, LayerMask::ALL).iter().any(|other| *other != entity) {
+            continue;
+        }
+        pick_up(&mut commands, entity, shape.0, player_entity, &mut modifiers);
+        grabbed.send(CrateGrabbed { position: position.0 });
+        // Focus is shared, so one crate goes to one player.
+        break;
+    }
+}
+
+/// `player` lifts `crate_entity`, of `shape`, over their head and carries it from the
+/// next tick on.
+pub fn pick_up(commands: &mut Commands, crate_entity: Entity, shape: Vec2, player: Entity, modifiers: &mut MovementModifiers) {
+    commands.entity(crate_entity).remove::<(Collider, Gravitated, DynamicBody, Thrown)>().insert(Carried);
+    commands.entity(player).insert(Carrying { entity: crate_entity, height: shape.y });
+    for modifier in CARRY_SLOWDOWN {
+        modifiers.push(modifier);
+    }
+}
+
+/// Taking a hit, lethal or not, makes the player let go. The crate just falls from where
+/// it was; a death puts it back at its `SpawnSnapshot` anyway.
+fn drop_when_hurt(
+    mut commands: Commands,
+    mut damage: EventReader<DamageEvent>,
+    mut players: Query<(&Carrying, &mut MovementModifiers), With<Player>>,
+) {
+    for event in damage.read() {
+        let Ok((carrying, mut modifiers)) = players.get_mut(event.target) else {
+            continue;
+        };
+        commands.entity(carrying.entity).remove::<Carried>().insert((Collider, Gravitated, DynamicBody));
+        commands.entity(event.target).remove::<Carrying>();
+        for modifier in CARRY_SLOWDOWN {
+            modifiers.remove(modifier);
+        }
+    }
+}
+
+/// Keeps held crates on the player's head. They go wherever the player goes, teleports
+/// included, and nothing else gets to move them.
+fn carry_crates(
+    mut commands: Commands,
+    players: Query<(&Position, &Shape, &Carrying, Has<Teleported>), With<Player>>,
+    mut crates: Query<(Entity, &mut Position, &mut Velocity, &Shape), (With<Carried>, Without<Player>)>,
+) {
+    for (player_pos, player_shape, carrying, teleported) in &players {
+        let Ok((entity, mut position, mut velocity, shape)) = crates.get_mut(carrying.entity) else {
+            continue;
+        };
+        let target = carry_position(player_pos.0, player_shape.0, shape.0);
+        if teleported {
+            position.teleport(&mut commands, entity, target);
+        } else {
+            position.0 = target;
+        }
+        velocity.0 = Vec2::ZERO;
+    }
+}
+
+/// A player who walked into the side of a crate this tick takes it along at up to
+/// `PUSH_SPEED`, and walks at that speed too, so they keep in step. Crates in a row move
+/// together, up to `MAX_PUSH_CHAIN`; anything else in the way, or a longer row, blocks
+/// the push and the player just stays stopped against it.
+fn push_crates(
+    contacts: Res<Contacts>,
+    mut players: Query<&mut Velocity, With<Player>>,
+    mut crates: Query<(&Position, &Shape, &mut Velocity), (With<Pushable>, With<Collider>, Without<Player>)>,
+    physics: PhysicsWorld,
+) {
+    for contact in &contacts.0 {
+        let dir = -contact.side.normal().x;
+        if dir == 0. || contact.velocity.x * dir <= 0. || !crates.contains(contact.other) {
+            continue;
+        }
+        let Ok(mut player_vel) = players.get_mut(contact.body) else {
+            continue;
+        };
+        let mut chain = vec![contact.other];
+        let mut blocked = false;
+        let mut index = 0;
+        while index < chain.len() && !blocked {
+            let Ok((position, shape, _)) = crates.get(chain[index]) else {
+                break;
+            };
+            let half = shape.0 / 2.;
+            let edge = position.0.x + half.x * dir;
+            let probe = Aabb2d::new(
+                Vec2::new(edge + PUSH_PROBE / 2. * dir, position.0.y),
+                Vec2::new(PUSH_PROBE / 2., (half.y - PUSH_PROBE_INSET).max(1.)),
+            );
+            for other in physics.overlap_aabb(probe, LayerMask::ALL) {
+                if chain.contains(&other) {
+                    continue;
+                }
+                if crates.contains(other) && chain.len() < MAX_PUSH_CHAIN {
+                    chain.push(other);
+                } else {
+                    blocked = true;
+                }
+            }
+            index += 1;
+        }
+        if blocked {
+            continue;
+        }
+        let push = contact.velocity.x.clamp(-PUSH_SPEED, PUSH_SPEED);
+        for entity in chain {
+            if let Ok((_, _, mut velocity)) = crates.get_mut(entity) {
+                velocity.0.x = push;
+            }
+        }
+        player_vel.0.x = push;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::build_headless_app;
+    use crate::world::BlockData;
+
+    #[test]
+    fn dropped_crates_land_and_stack_on_a_block() {
+        let mut app = build_headless_app(WorldData(vec![
+            BlockData::new(Vec2::new(0., -200.), Vec2::new(400., 50.)),
+            // Top at -100, well away from the player.
+            BlockData::new(Vec2::new(600., -125.), Vec2::new(100., 50.)),
+        ]));
+        let mut levels = app.world_mut().query_filtered::<Entity, With<WorldData>>();
+        let level = levels.single(app.world());
+        app.world_mut().entity_mut(level).insert(CrateSpawns(vec![
+            CrateData { position: Vec2::new(600., 300.), shape: Vec2::splat(40.) },
+            CrateData { position: Vec2::new(600., 400.), shape: Vec2::splat(40.) },
+        ]));
+        for _ in 0..288 {
+            app.update();
+        }
+        let mut crates = app.world_mut().query_filtered::<(&Position, &Velocity), With<Crate>>();
+        let mut resting: Vec<_> = crates.iter(app.world()).map(|(position, velocity)| (position.0, velocity.0)).collect();
+        assert_eq!(resting.len(), 2);
+        resting.sort_by(|a, b| a.0.y.total_cmp(&b.0.y));
+        for ((position, velocity), y) in resting.into_iter().zip([-80., -40.]) {
+            assert!(position.distance(Vec2::new(600., y)) < 0.5, "crate at {position}, expected at y {y}");
+            assert_eq!(velocity.y, 0.);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::events::PlayerDied;
+use crate::physics::{project_transforms, update_ground_contact, Collider, DynamicBody, GroundContact, Position, PostCollide, Shape};
+use crate::player::Player;
+use crate::timer::GameTimer;
+
+pub const CRUMBLING_COLOR: Color = Color::srgb(0.7, 0.6, 0.45);
+/// How far a crumbling block rattles either way while it's about to go, in pixels.
+const SHAKE_OFFSET: f32 = 2.;
+const FADE_SECS: f32 = 0.25;
+
+pub struct CrumblingPlugin;
+
+impl Plugin for CrumblingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, (start_crumbling.after(update_ground_contact), crumble, restore_on_death).chain().in_set(PostCollide))
+            .add_systems(Update, (shake_crumbling.after(project_transforms), fade_crumbled));
+    }
+}
+
+#[derive(Clone, Debug)]
+enum CrumbleState {
+    Intact,
+    Shaking(GameTimer),
+    /// Not solid any more. `back_in` is `None` for a block that stays gone.
+    Gone { fade: GameTimer, back_in: Option<GameTimer> },
+}
+
+/// A block from `BlockKind::Crumbling`. Standing on it starts it shaking, and `delay`
+/// seconds later it stops being solid and fades away, coming back `respawn` seconds after
+/// that if it's set.
+#[derive(Component)]
+pub struct Crumbling {
+    delay: f32,
+    respawn: Option<f32>,
+    state: CrumbleState,
+}
+
+impl Crumbling {
+    pub fn new(delay: f32, respawn: Option<f32>) -> Self {
+        Self { delay, respawn, state: CrumbleState::Intact }
+    }
+}
+
+fn start_crumbling(players: Query<&GroundContact, With<Player>>, mut blocks: Query<&mut Crumbling>) {
+    for ground in &players {
+        let Some(mut block) = ground.0.and_then(|ground| blocks.get_mut(ground).ok()) else {
+            continue;
+        };
+        if matches!(block.state, CrumbleState::Intact) {
+            block.state = CrumbleState::Shaking(GameTimer::once(block.delay));
+        }
+    }
+}
+
+/// A block only comes back once nothing is inside where it would be, so it never
+/// closes around the player or a crate.
+fn crumble(
+    mut commands: Commands,
+    mut blocks: Query<(Entity, &mut Crumbling, &Position, &Shape)>,
+    bodies: Query<(&Position, &Shape), (With<DynamicBody>, Without<Crumbling>)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut block, position, shape) in &mut blocks {
+        let block = &mut *block;
+        match &mut block.state {
+            CrumbleState::Intact => {}
+            CrumbleState::Shaking(timer) => {
+                if timer.tick(dt).finished() {
+                    commands.entity(entity).remove::<Collider>();
+                    block.state = CrumbleState::Gone {
+                        fade: GameTimer::once(FADE_SECS),
+                        back_in: block.respawn.map(GameTimer::once),
+                    };
+                }
+            }
+            CrumbleState::Gone { fade, back_in } => {
+                fade.tick(dt);
+                let Some(back_in) = back_in else {
+                    continue;
+                };
+                if !back_in.tick(dt).finished() {
+                    continue;
+                }
+                let footprint = Aabb2d::new(position.0, shape.0 / 2.);
+                let blocked = bodies.iter().any(|(body_pos, body_shape)| Aabb2d::new(body_pos.0, body_shape.0 / 2.).intersects(&footprint));
+                if !blocked {
+                    commands.entity(entity).insert(Collider);
+                    block.state = CrumbleState::Intact;
+                }
+            }
+        }
+    }
+}
+
+/// Dying puts every crumbled block back, the ones that never respawn included, so a
+/// bridge that's fallen can't leave the level impossible. They still wait for the
+/// players to be clear of them.
+fn restore_on_death(mut died: EventReader<PlayerDied>, mut blocks: Query<&mut Crumbling>) {
+    if died.read().count() == 0 {
+        return;
+    }
+    for mut block in &mut blocks {
+        block.state = match block.state {
+            CrumbleState::Gone { .. } => CrumbleState::Gone {
+                fade: GameTimer::once(0.),
+                back_in: Some(GameTimer::once(0.)),
+            },
+            _ => CrumbleState::Intact,
+        };
+    }
+}
+
+/// Only the drawing shakes; `Position`, and so the collider, stays put. Irregular the same
+/// way the camera shake is, without a random source.
+fn shake_crumbling(mut blocks: Query<(&Crumbling, &mut Transform)>, time: Res<Time>) {
+    let t = time.elapsed_seconds();
+    for (block, mut transform) in &mut blocks {
+        if !matches!(block.state, CrumbleState::Shaking(_)) {
+            continue;
+        }
+        let wobble = Vec2::new(
+            (t * 71.).sin() + (t * 43.1).sin() * 0.5,
+            (t * 59.3).sin() + (t * 31.7).sin() * 0.5,
+        ) / 1.5;
+        transform.translation += (wobble * SHAKE_OFFSET).extend(0.);
+    }
+}
+
+/// Each crumbling block has a material of its own, so one fading leaves the rest alone.
+fn fade_crumbled(
+    mut blocks: Query<(&Crumbling, &Handle<ColorMaterial>, &mut Visibility)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (block, material, mut visibility) in &mut blocks {
+        let alpha = match &block.state {
+            CrumbleState::Gone { fade, .. } => 1. - fade.fraction(),
+            _ => 1.,
+        };
+        visibility.set_if_neq(if alpha > 0. { Visibility::Inherited } else { Visibility::Hidden });
+        let stale = materials.get(material).is_some_and(|material| material.color.alpha() != alpha);
+        if let Some(material) = materials.get_mut(material).filter(|_| stale) {
+            material.color.set_alpha(alpha);
+        }
+    }
+}
@@ -0,0 +1,432 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::camera::{Camera, CameraTarget, CameraZoom};
+use crate::events::{ScriptTriggerFired, TriggerEnter};
+use crate::flerp;
+use crate::origin::WorldOrigin;
+use crate::physics::{move_bodies, Position, Velocity};
+use crate::movement::MovementConfig;
+use crate::platform::Easing;
+use crate::player::{Facing, Player};
+use crate::recording::recording_or_replaying;
+use crate::script::fire_script_triggers;
+use crate::trigger::{detect_triggers, TriggerZone};
+use crate::world::WorldData;
+use crate::GameState;
+
+/// Players being walked by a cutscene move at this fraction of their running speed.
+const CUTSCENE_WALK_SPEED: f32 = 0.5;
+const WALK_ARRIVED: f32 = 4.;
+
+pub struct CutscenePlugin;
+
+impl Plugin for CutscenePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveCutscene>()
+            .init_resource::<SeenCutscenes>()
+            .add_systems(Startup, spawn_cutscene_overlay)
+            .add_systems(OnEnter(GameState::Playing), play_level_intro)
+            .add_systems(OnEnter(GameState::Menu), stop_cutscene)
+            .add_systems(FixedUpdate, (play_triggered_cutscenes.after(fire_script_triggers).after(detect_triggers), run_cutscene)
+                .chain()
+                .after(move_bodies))
+            .add_systems(Update, (skip_cutscene.run_if(cutscene_playing).run_if(not(recording_or_replaying)), sync_cutscene_overlay).chain());
+    }
+}
+
+/// One scripted action. Steps run one after another; each finishes before the next starts.
+/// Points are in level coordinates.
+#[derive(Clone, Debug)]
+pub enum CutsceneStep {
+    /// Pans the camera to `point` with ease in and out. The camera stays there until a
+    /// `SetCameraTarget` or the end of the cutscene hands it back.
+    MoveCameraTo { point: Vec2, secs: f32 },
+    Wait(f32),
+    /// Replaces the caption; an empty string hides it.
+    ShowText(String),
+    /// Walks the player over to `point.x`, or teleports them there when `walk` is off.
+    MovePlayerTo { point: Vec2, walk: bool },
+    /// Hands the camera back to `camera_follow`, tracking the entity (or the player on `None`).
+    SetCameraTarget(Option<Entity>),
+    FadeOut(f32),
+    FadeIn(f32),
+    /// Moves and zooms the camera to a keyframe and holds it there, like `MoveCameraTo`.
+    CameraKeyframe(CameraKeyframe),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Cutscene(pub Vec<CutsceneStep>);
+
+/// One stop of a `CameraSequence`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct CameraKeyframe {
+    /// Where the camera goes. Left out, it goes to the players.
+    #[serde(default)]
+    pub position: Option<Vec2>,
+    /// 1 is no zoom; more is further out.
+    #[serde(default = "no_zoom")]
+    pub zoom: f32,
+    /// Seconds to get there from wherever the camera was, or 0 to cut straight to it.
+    #[serde(default)]
+    pub secs: f32,
+    /// Seconds to stay once it's there.
+    #[serde(default)]
+    pub hold: f32,
+    #[serde(default = "smooth")]
+    pub ease: Easing,
+}
+
+fn no_zoom() -> f32 {
+    1.
+}
+
+fn smooth() -> Easing {
+    Easing::EaseInOut
+}
+
+/// A camera move from the level file: keyframes visited in order, after which the camera
+/// goes back to following the players. Input is locked while it plays, and it can be
+/// skipped like any cutscene.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct CameraSequence(pub Vec<CameraKeyframe>);
+
+impl CameraSequence {
+    pub fn cutscene(&self) -> Cutscene {
+        let keyframes = self.0.iter().copied().map(CutsceneStep::CameraKeyframe);
+        Cutscene(keyframes.chain([CutsceneStep::SetCameraTarget(None)]).collect())
+    }
+}
+
+/// A camera sequence from the level file that plays when a player walks into a trigger
+/// zone tagged `trigger`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TriggeredSequence {
+    pub trigger: String,
+    pub keyframes: CameraSequence,
+}
+
+/// A cutscene the level plays the first time it's entered.
+#[derive(Component)]
+pub struct LevelIntro {
+    pub id: String,
+    pub cutscene: Cutscene,
+}
+
+/// Cutscenes started by script triggers, as (trigger id, cutscene), or by trigger zones,
+/// as (zone tag, cutscene). Whether one from a script trigger plays again after a
+/// respawn is up to its trigger's `once`; one from a zone plays once per attempt.
+#[derive(Component, Default)]
+pub struct TriggeredCutscenes(pub Vec<(String, Cutscene)>);
+
+/// Intros that have already played and won't again.
+#[derive(Resource, Default)]
+pub struct SeenCutscenes(pub HashSet<String>);
+
+/// The cutscene being played, if any. Player input is locked while it runs; everything
+/// else keeps simulating.
+#[derive(Resource, Default)]
+pub struct ActiveCutscene {
+    steps: Vec<CutsceneStep>,
+    index: usize,
+    elapsed: f32,
+    /// Camera position, zoom and fade when the current step started, for steps that ease
+    /// from them.
+    step_start: Option<(Vec2, f32, f32)>,
+    camera_scripted: bool,
+    fade: f32,
+    text: String,
+}
+
+impl ActiveCutscene {
+    pub fn play(&mut self, cutscene: Cutscene) {
+        self.steps = cutscene.0;
+        self.index = 0;
+        self.elapsed = 0.;
+        self.step_start = None;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.index < self.steps.len()
+    }
+
+    fn finish(&mut self) {
+        self.index = self.steps.len();
+        self.camera_scripted = false;
+        self.text.clear();
+    }
+}
+
+pub fn cutscene_playing(cutscene: Res<ActiveCutscene>) -> bool {
+    cutscene.is_playing()
+}
+
+/// Whether a cutscene has taken the camera away from `camera_follow`.
+pub fn camera_scripted(cutscene: Res<ActiveCutscene>) -> bool {
+    cutscene.camera_scripted
+}
+
+#[derive(Component)]
+struct FadeOverlay;
+
+#[derive(Component)]
+struct Caption;
+
+fn spawn_cutscene_overlay(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            align_items: AlignItems::FlexEnd,
+            justify_content: JustifyContent::Center,
+            padding: UiRect::bottom(Val::Px(48.)),
+            ..default()
+        },
+        background_color: Color::NONE.into(),
+        z_index: ZIndex::Global(10),
+        ..default()
+    }, FadeOverlay)).with_children(|overlay| {
+        overlay.spawn((TextBundle::from_section("", TextStyle {
+            font_size: 32.,
+            ..default()
+        }), Caption));
+    });
+}
+
+fn play_level_intro(
+    world_data: Query<&LevelIntro, With<WorldData>>,
+    mut seen: ResMut<SeenCutscenes>,
+    mut active: ResMut<ActiveCutscene>,
+) {
+    let Ok(intro) = world_data.get_single() else {
+        return;
+    };
+    if seen.0.insert(intro.id.clone()) {
+        active.play(intro.cutscene.clone());
+    }
+}
+
+/// Leaving a level for the menu cuts its cutscene off, fade and caption included.
+fn stop_cutscene(mut active: ResMut<ActiveCutscene>) {
+    *active = ActiveCutscene::default();
+}
+
+/// A cutscene already running isn't interrupted; a trigger fired during it is dropped.
+/// Zones that have played theirs are remembered until the restart that replaces them.
+fn play_triggered_cutscenes(
+    mut fired: EventReader<ScriptTriggerFired>,
+    mut entered: EventReader<TriggerEnter>,
+    world_data: Query<&TriggeredCutscenes, With<WorldData>>,
+    zones: Query<&TriggerZone>,
+    players: Query<(), With<Player>>,
+    mut active: ResMut<ActiveCutscene>,
+    mut played_zones: Local<HashSet<Entity>>,
+) {
+    let Ok(cutscenes) = world_data.get_single() else {
+        return;
+    };
+    played_zones.retain(|zone| zones.contains(*zone));
+    let zone_tags = entered.read()
+        .filter(|event| players.contains(event.body))
+        .filter_map(|event| zones.get(event.zone).ok().map(|zone| (event.zone, zone.tag.as_str())))
+        .collect::<Vec<_>>();
+    let triggers = fired.read().map(|event| (None, event.id.as_str()))
+        .chain(zone_tags.into_iter().map(|(zone, tag)| (Some(zone), tag)));
+    for (zone, id) in triggers {
+        if active.is_playing() || zone.is_some_and(|zone| played_zones.contains(&zone)) {
+            continue;
+        }
+        if let Some((_, cutscene)) = cutscenes.0.iter().find(|(trigger, _)| trigger == id) {
+            active.play(cutscene.clone());
+            played_zones.extend(zone);
+        }
+    }
+}
+
+fn ease(t: f32) -> f32 {
+    t * t * (3. - 2. * t)
+}
+
+/// Where a keyframe sends the camera: its position, or between the players without one.
+fn keyframe_goal<'a>(keyframe: &CameraKeyframe, origin: WorldOrigin, players: impl Iterator<Item = &'a Position>) -> Vec2 {
+    keyframe.position.map_or_else(|| {
+        let (sum, count) = players.fold((Vec2::ZERO, 0.), |(sum, count), position| (sum + position.0, count + 1.));
+        if count > 0. { sum / count } else { Vec2::ZERO }
+    }, |position| origin.to_live(position))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_cutscene(
+    mut commands: Commands,
+    mut active: ResMut<ActiveCutscene>,
+    mut camera: Query<(Entity, &mut Position, &mut Velocity, &mut CameraZoom, &mut OrthographicProjection), With<Camera>>,
+    mut player: Query<(Entity, &mut Position, &mut Velocity, &mut Facing), (With<Player>, Without<Camera>)>,
+    mut camera_target: ResMut<CameraTarget>,
+    config: Res<MovementConfig>,
+    origin: Res<WorldOrigin>,
+    time: Res<Time>,
+) {
+    if !active.is_playing() {
+        return;
+    }
+    let active = &mut *active;
+    active.elapsed += time.delta_seconds();
+
+    while let Some(step) = active.steps.get(active.index) {
+        let (camera_at, zoom_at) = camera.get_single().map_or((Vec2::ZERO, 1.), |(_, position, _, zoom, _)| (position.0, zoom.current));
+        let (start_camera, start_zoom, start_fade) = *active.step_start.get_or_insert((camera_at, zoom_at, active.fade));
+        let progress = |secs: f32| if secs > 0. { (active.elapsed / secs).min(1.) } else { 1. };
+
+        let done = match step {
+            CutsceneStep::MoveCameraTo { point, secs } => {
+                let t = progress(*secs);
+                let point = origin.to_live(*point);
+                active.camera_scripted = true;
+                for (entity, mut position, mut velocity, ..) in &mut camera {
+                    if *secs > 0. {
+                        position.0 = start_camera.lerp(point, ease(t));
+                    } else {
+                        position.teleport(&mut commands, entity, point);
+                    }
+                    velocity.0 = Vec2::ZERO;
+                }
+                t >= 1.
+            }
+            CutsceneStep::Wait(secs) => active.elapsed >= *secs,
+            CutsceneStep::ShowText(text) => {
+                active.text.clone_from(text);
+                true
+            }
+            CutsceneStep::MovePlayerTo { point, walk } => {
+                // Both players go, and the step waits for the slower one.
+                let point = &origin.to_live(*point);
+                let mut arrived = true;
+                for (entity, mut position, mut velocity, mut facing) in &mut player {
+                    let dx = point.x - position.0.x;
+                    if !walk {
+                        position.teleport(&mut commands, entity, *point);
+                        velocity.0 = Vec2::ZERO;
+                    } else if dx.abs() <= WALK_ARRIVED {
+                        velocity.0.x = 0.;
+                    } else {
+                        velocity.0.x = dx.signum() * config.max_speed * CUTSCENE_WALK_SPEED;
+                        facing.x = dx.signum();
+                        arrived = false;
+                    }
+                }
+                arrived
+            }
+            CutsceneStep::SetCameraTarget(target) => {
+                camera_target.set(*target, 0.);
+                active.camera_scripted = false;
+                true
+            }
+            CutsceneStep::FadeOut(secs) => {
+                let t = progress(*secs);
+                active.fade = start_fade + (1. - start_fade) * t;
+                t >= 1.
+            }
+            CutsceneStep::FadeIn(secs) => {
+                let t = progress(*secs);
+                active.fade = start_fade * (1. - t);
+                t >= 1.
+            }
+            CutsceneStep::CameraKeyframe(keyframe) => {
+                let goal = keyframe_goal(keyframe, *origin, player.iter().map(|(_, position, ..)| position));
+                let t = keyframe.ease.apply(progress(keyframe.secs));
+                active.camera_scripted = true;
+                for (entity, mut position, mut velocity, mut zoom, mut projection) in &mut camera {
+                    if keyframe.secs > 0. {
+                        position.0 = start_camera.lerp(goal, t);
+                    } else {
+                        position.teleport(&mut commands, entity, goal);
+                    }
+                    velocity.0 = Vec2::ZERO;
+                    zoom.current = flerp(start_zoom, keyframe.zoom, t);
+                    projection.scale = zoom.current;
+                }
+                active.elapsed >= keyframe.secs + keyframe.hold
+            }
+        };
+        if !done {
+            return;
+        }
+        active.index += 1;
+        active.elapsed = 0.;
+        active.step_start = None;
+    }
+    active.finish();
+}
+
+/// Any key jumps straight to where the cutscene would have left things.
+fn skip_cutscene(
+    mut commands: Commands,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut active: ResMut<ActiveCutscene>,
+    mut camera: Query<(Entity, &mut Position, &mut Velocity, &mut CameraZoom, &mut OrthographicProjection), With<Camera>>,
+    mut player: Query<(Entity, &mut Position, &mut Velocity), (With<Player>, Without<Camera>)>,
+    mut camera_target: ResMut<CameraTarget>,
+    origin: Res<WorldOrigin>,
+) {
+    if kb_input.get_just_pressed().next().is_none() {
+        return;
+    }
+    let active = &mut *active;
+    for step in &active.steps[active.index..] {
+        match step {
+            CutsceneStep::MoveCameraTo { point, .. } => {
+                for (entity, mut position, mut velocity, ..) in &mut camera {
+                    position.teleport(&mut commands, entity, origin.to_live(*point));
+                    velocity.0 = Vec2::ZERO;
+                }
+            }
+            CutsceneStep::CameraKeyframe(keyframe) => {
+                let goal = keyframe_goal(keyframe, *origin, player.iter().map(|(_, position, _)| position));
+                for (entity, mut position, mut velocity, mut zoom, mut projection) in &mut camera {
+                    position.teleport(&mut commands, entity, goal);
+                    velocity.0 = Vec2::ZERO;
+                    zoom.current = keyframe.zoom;
+                    projection.scale = zoom.current;
+                }
+            }
+            CutsceneStep::MovePlayerTo { point, .. } => {
+                let point = origin.to_live(*point);
+                for (entity, mut position, mut velocity) in &mut player {
+                    // Walks only ever covered x; keep whatever height the player is at.
+                    let to = match step {
+                        CutsceneStep::MovePlayerTo { walk: true, .. } => Vec2::new(point.x, position.0.y),
+                        _ => point,
+                    };
+                    position.teleport(&mut commands, entity, to);
+                    velocity.0.x = 0.;
+                }
+            }
+            CutsceneStep::SetCameraTarget(target) => camera_target.set(*target, 0.),
+            _ => {}
+        }
+    }
+    active.fade = 0.;
+    active.finish();
+}
+
+fn sync_cutscene_overlay(
+    active: Res<ActiveCutscene>,
+    mut overlay: Query<&mut BackgroundColor, With<FadeOverlay>>,
+    mut caption: Query<&mut Text, With<Caption>>,
+) {
+    if !active.is_changed() {
+        return;
+    }
+    for mut background in &mut overlay {
+        background.0 = Color::BLACK.with_alpha(active.fade);
+    }
+    for mut text in &mut caption {
+        if text.sections[0].value != active.text {
+            text.sections[0].value.clone_from(&active.text);
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use bevy::prelude::*;
+
+use crate::events::{DamageEvent, Damaged, Died};
+use crate::movement::ControlLock;
+use crate::physics::{PhysicsSet, Position, Shape, Velocity};
+use crate::player::{Player, SquashStretch, VisShape};
+use crate::timer::GameTimer;
+
+const KNOCKBACK_STRENGTH: f32 = 864.;
+/// How long a knockback keeps the player's steering off.
+const KNOCKBACK_LOCK_SECS: f32 = 0.15;
+const HIT_FLASH_SECS: f32 = 0.1;
+/// How long each on and off phase of the invulnerability blink lasts.
+const BLINK_SECS: f32 = 0.08;
+const BLINK_ALPHA: f32 = 0.3;
+const DEATH_SQUASH_SECS: f32 = 0.25;
+/// Visual size relative to `Shape` right after taking a hit.
+const HIT_SQUASH: Vec2 = Vec2::new(1.2, 0.8);
+
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Damageable>()
+            .add_systems(FixedUpdate, (apply_damage,
+                                       expire_invulnerability,
+                                       start_dying,
+                                       fade_hit_flash,
+                                       finish_dying).chain()
+                .after(PhysicsSet::Resolve))
+            .add_systems(Update, unblink);
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Damageable {
+    pub health: i32,
+    pub max_health: i32,
+    pub flash_on_hit: bool,
+    /// How long a hit that doesn't kill makes the body `Invulnerable` for. Zero leaves it
+    /// open to the next hit straight away.
+    pub invulnerable_secs: f32,
+}
+
+impl Damageable {
+    pub fn new(health: i32) -> Self {
+        Self {
+            health,
+            max_health: health,
+            flash_on_hit: true,
+            invulnerable_secs: 0.,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct HitFlash {
+    timer: GameTimer,
+    color: Color,
+}
+
+/// Shrugs off every non-lethal hit until the timer runs out, blinking its material (or
+/// sprite) the whole time. Lethal damage (the kill plane, rising hazards) still goes through, so a
+/// body can't get stuck somewhere it should have died; spikes hold off on their own.
+#[derive(Component)]
+pub struct Invulnerable {
+    timer: GameTimer,
+    blink: GameTimer,
+    dimmed: bool,
+}
+
+impl Invulnerable {
+    pub fn new(secs: f32) -> Self {
+        Self {
+            timer: GameTimer::once(secs),
+            blink: GameTimer::repeating(BLINK_SECS),
+            dimmed: false,
+        }
+    }
+}
+
+/// Squashes flat and despawns once the timer runs out.
+#[derive(Component)]
+pub struct Dying(GameTimer);
+
+impl Dying {
+    /// Whether `finish_dying` has despawned it, or will once its commands are applied.
+    pub fn finished(&self) -> bool {
+        self.0.finished()
+    }
+}
+
+/// Knockback only ever sets velocity, never the position. This runs after
+/// `PhysicsSet::Resolve`, so the next tick's `move_bodies` carries the hit and
+/// `handle_collisions` stops it at whatever is in the way, however hard it was.
+pub fn apply_damage(
+    mut commands: Commands,
+    mut events: EventReader<DamageEvent>,
+    mut died: EventWriter<Died>,
+    mut damaged: EventWriter<Damaged>,
+    mut damageables: Query<(&mut Damageable, &Position, Option<&mut Velocity>, Option<&Handle<ColorMaterial>>, Option<&mut HitFlash>, Option<(&mut VisShape, &Shape)>, Has<Invulnerable>), Without<Dying>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for event in events.read() {
+        let Ok((mut damageable, position, velocity, material, flash, vis, invulnerable)) = damageables.get_mut(event.target) else {
+            continue;
+        };
+        if damageable.health <= 0 || (invulnerable && !event.is_lethal()) {
+            continue;
+        }
+        let before = damageable.health;
+        damageable.health = damageable.health.saturating_sub(event.amount);
+        damaged.send(Damaged { entity: event.target, amount: before - damageable.health.max(0) });
+
+        if let (Some(source), Some(mut velocity)) = (event.source_position, velocity) {
+            let away = (position.0 - source).normalize_or_zero();
+            velocity.0 = away * KNOCKBACK_STRENGTH;
+            commands.entity(event.target).insert(ControlLock::new(KNOCKBACK_LOCK_SECS));
+        }
+
+        if let Some((mut vis_shape, shape)) = vis {
+            vis_shape.0 = shape.0 * HIT_SQUASH;
+        }
+
+        if let Some(mut flash) = flash {
+            // Another hit mid-flash starts the flash over.
+            flash.timer.reset();
+        } else if damageable.flash_on_hit {
+            if let Some(material) = material.and_then(|handle| materials.get_mut(handle)) {
+                commands.entity(event.target).insert(HitFlash {
+                    timer: GameTimer::once(HIT_FLASH_SECS),
+                    color: material.color,
+                });
+                material.color = Color::WHITE;
+            }
+        }
+
+        if damageable.health <= 0 {
+            died.send(Died { entity: event.target });
+        } else if damageable.invulnerable_secs > 0. {
+            commands.entity(event.target).insert(Invulnerable::new(damageable.invulnerable_secs));
+        }
+    }
+}
+
+fn expire_invulnerability(
+    mut commands: Commands,
+    mut bodies: Query<(Entity, &mut Invulnerable, Option<&Handle<ColorMaterial>>, Option<&mut Sprite>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable, material, sprite) in &mut bodies {
+        if invulnerable.timer.tick(time.delta_seconds()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+            continue;
+        }
+        if !invulnerable.blink.tick(time.delta_seconds()).just_finished() {
+            continue;
+        }
+        invulnerable.dimmed = !invulnerable.dimmed;
+        let alpha = if invulnerable.dimmed { BLINK_ALPHA } else { 1. };
+        if let Some(material) = material.and_then(|handle| materials.get_mut(handle)) {
+            material.color.set_alpha(alpha);
+        }
+        if let Some(mut sprite) = sprite {
+            sprite.color.set_alpha(alpha);
+        }
+    }
+}
+
+/// Puts the alpha back however the protection ended, a restart included. Runs in `Update`
+/// since a restart happens while no fixed ticks do.
+fn unblink(
+    mut removed: RemovedComponents<Invulnerable>,
+    mut bodies: Query<(Option<&Handle<ColorMaterial>>, Option<&mut Sprite>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for entity in removed.read() {
+        let Ok((material, sprite)) = bodies.get_mut(entity) else {
+            continue;
+        };
+        if let Some(material) = material.and_then(|handle| materials.get_mut(handle)) {
+            material.color.set_alpha(1.);
+        }
+        if let Some(mut sprite) = sprite {
+            sprite.color.set_alpha(1.);
+        }
+    }
+}
+
+fn fade_hit_flash(
+    mut commands: Commands,
+    mut flashing: Query<(Entity, &mut HitFlash, &Handle<ColorMaterial>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, handle) in &mut flashing {
+        if flash.timer.tick(time.delta_seconds()).finished() {
+            if let Some(material) = materials.get_mut(handle) {
+                material.color = flash.color;
+            }
+            commands.entity(entity).remove::<HitFlash>();
+        }
+    }
+}
+
+/// Non-player entities that die get squashed flat before they disappear. The
+/// player is handled by the respawn flow instead.
+fn start_dying(
+    mut commands: Commands,
+    mut died: EventReader<Died>,
+    players: Query<(), With<Player>>,
+    mut squashable: Query<(&mut SquashStretch, &Shape)>,
+) {
+    for event in died.read() {
+        if players.contains(event.entity) {
+            continue;
+        }
+        if let Ok((mut squash, shape)) = squashable.get_mut(event.entity) {
+            squash.target = Vec2::new(shape.0.x * 1.3, shape.0.y * 0.1);
+        }
+        if let Some(mut entity) = commands.get_entity(event.entity) {
+            entity.insert(Dying(GameTimer::once(DEATH_SQUASH_SECS)));
+        }
+    }
+}
+
+pub fn finish_dying(
+    mut commands: Commands,
+    mut dying: Query<(Entity, &mut Dying)>,
+    time: Res<Time>,
+) {
+    for (entity, mut timer) in &mut dying {
+        if timer.0.tick(time.delta_seconds()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enemy::{Enemy, EnemyData, EnemySpawns, PatrolRoute};
+    use crate::headless::build_headless_app;
+    use crate::input::{Action, ScriptedInput};
+    use crate::physics::{Position, Velocity};
+    use crate::player::Player;
+    use crate::world::{BlockData, WorldData};
+
+    #[test]
+    fn three_shots_kill_a_three_health_enemy() {
+        let mut app = build_headless_app(WorldData(vec![BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.))]));
+        let mut levels = app.world_mut().query_filtered::<Entity, With<WorldData>>();
+        let level = levels.single(app.world());
+        // Standing still, as tall as the player so every shot at head height hits it.
+        app.world_mut().entity_mut(level).insert(EnemySpawns(vec![EnemyData {
+            position: Vec2::new(300., -125.),
+            shape: Vec2::new(50., 100.),
+            route: PatrolRoute::Range { min_x: 300., max_x: 300. },
+            speed: 0.,
+            chase: None,
+            boss_bar: None,
+        }]));
+        for _ in 0..144 {
+            app.update();
+        }
+        let mut enemies = app.world_mut().query_filtered::<(Entity, &Damageable, Has<Dying>), With<Enemy>>();
+        let (enemy, damageable, _) = enemies.single(app.world());
+        assert_eq!(damageable.health, 3);
+        for health in [2, 1, 0] {
+            app.world_mut().resource_mut::<ScriptedInput>().actions[0].press(Action::Fire);
+            app.update();
+            app.world_mut().resource_mut::<ScriptedInput>().actions[0].release(Action::Fire);
+            for _ in 0..36 {
+                app.update();
+            }
+            let (_, damageable, dying) = enemies.get(app.world(), enemy).unwrap();
+            assert_eq!(damageable.health, health);
+            assert_eq!(dying, health == 0, "dying at {health} health");
+        }
+        for _ in 0..72 {
+            app.update();
+        }
+        assert!(app.world().get_entity(enemy).is_none(), "never went away");
+    }
+
+    /// Where a player standing 5px left of a wall ends up after a hit from the left that
+    /// knocks them into it at `speed` pixels a tick.
+    fn knocked_into_a_wall(speed: f32) -> (f32, f32) {
+        let mut app = build_headless_app(WorldData(vec![
+            BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.)),
+            // Its face at 31, 5px from the hitbox's right edge at 26.
+            BlockData::new(Vec2::new(41., -75.), Vec2::new(20., 200.)),
+        ]));
+        for _ in 0..144 {
+            app.update();
+        }
+        let mut players = app.world_mut().query_filtered::<(Entity, &mut Velocity), With<Player>>();
+        let (player, _) = players.single(app.world());
+        app.world_mut().send_event(DamageEvent { target: player, amount: 1, source_position: Some(Vec2::new(-100., -125.)) });
+        app.update();
+        // Harder than any hit in the game, but it's moved the same way.
+        let (_, mut velocity) = players.single_mut(app.world_mut());
+        assert!(velocity.0.x > 0., "knocked {}", velocity.0);
+        velocity.0.x = speed * 144.;
+        for _ in 0..3 {
+            app.update();
+        }
+        let mut players = app.world_mut().query_filtered::<(&Position, &Velocity), With<Player>>();
+        let (position, velocity) = players.single(app.world());
+        (position.0.x, velocity.0.x)
+    }
+
+    #[test]
+    fn knockback_into_a_wall_stops_flush_against_it() {
+        for speed in [50., 400.] {
+            let (x, velocity) = knocked_into_a_wall(speed);
+            assert!((x - 5.).abs() < 0.01, "at {speed}px a tick, ended up at {x}");
+            assert_eq!(velocity, 0., "at {speed}px a tick");
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use bevy::prelude::*;
+
+#[cfg(feature = "debug-tools")]
+mod imp;
+
+/// The F4 entity overlay, the inspection tooltip and the F6 physics drawing. They only
+/// exist with the `debug-tools` feature; without it this adds nothing, and labels and
+/// tracked types are just never read.
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, _app: &mut App) {
+        #[cfg(feature = "debug-tools")]
+        _app.add_plugins(imp::DebugToolsPlugin);
+    }
+}
+
+/// Groups an entity under this name in the debug overlay.
+#[cfg_attr(not(feature = "debug-tools"), allow(dead_code))]
+#[derive(Component)]
+pub struct DebugLabel(pub &'static str);
+
+pub trait DebugTrackExt {
+    /// Shows a live count of entities with `T` in the debug overlay.
+    fn debug_track<T: Component>(&mut self, label: &'static str) -> &mut Self;
+}
+
+impl DebugTrackExt for App {
+    fn debug_track<T: Component>(&mut self, _label: &'static str) -> &mut Self {
+        #[cfg(feature = "debug-tools")]
+        imp::track::<T>(self, _label);
+        self
+    }
+}
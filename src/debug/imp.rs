@@ -0,0 +1,481 @@
+use std::collections::BTreeMap;
+
+use bevy::math::bounding::Aabb2d;
+use bevy::prelude::*;
+use bevy::reflect::ReflectRef;
+use bevy::window::PrimaryWindow;
+
+use crate::camera::cursor_world_position;
+use crate::events::CollisionEvent;
+use crate::movement::MovementConfig;
+use crate::origin::WorldOrigin;
+use crate::physics::{contains_point, Collider, Collision, PhysicsSet, Position, Shape, Velocity, ZOrder};
+use crate::player::{Grounded, Player, VisShape, GROUND_PROBE_DEPTH};
+use crate::script::ScriptTriggers;
+use crate::spawn_zone::Zone;
+use crate::spring::Spring;
+use crate::world::WorldData;
+
+use super::{DebugLabel, DebugTrackExt};
+
+const REFRESH_SECS: f32 = 0.5;
+/// Gap between the cursor and the inspection tooltip's corner, in logical pixels.
+const TOOLTIP_OFFSET: Vec2 = Vec2::new(16., 16.);
+/// Length of a spring's launch arrow per pixel per second of launch speed.
+const SPRING_ARROW_SCALE: f32 = 0.04;
+/// Gap between the top of a script trigger and its id.
+const TRIGGER_LABEL_GAP: f32 = 12.;
+const PHYSICS_TOGGLE_KEY: KeyCode = KeyCode::F6;
+/// Length of a velocity arrow per pixel per second.
+const VELOCITY_ARROW_SCALE: f32 = 0.055;
+/// Frames a contact marker stays up after the contact.
+const CONTACT_MARKER_FRAMES: u32 = 20;
+const CONTACT_MARKER_SIZE: f32 = 10.;
+const SELECT_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3,
+    KeyCode::Digit4, KeyCode::Digit5, KeyCode::Digit6,
+    KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+];
+
+pub struct DebugToolsPlugin;
+
+impl Plugin for DebugToolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlay>()
+            .init_resource::<Inspector>()
+            .init_resource::<DebugSettings>()
+            .init_resource::<ContactMarkers>()
+            .debug_track::<Collider>("colliders")
+            .debug_track::<Velocity>("dynamic bodies")
+            .add_systems(Startup, (spawn_overlay, spawn_tooltip))
+            .add_systems(Update, (
+                toggle_overlay,
+                (refresh_overlay, outline_selected, draw_spring_vectors).chain().run_if(overlay_open),
+                label_script_triggers,
+                (pick_inspected, refresh_tooltip).chain(),
+                toggle_physics_draw,
+                debug_draw.run_if(physics_draw_on),
+            ).chain())
+            .add_systems(FixedUpdate, record_contacts.after(PhysicsSet::Resolve).run_if(physics_draw_on));
+    }
+}
+
+/// Marker types the overlay counts, registered with `App::debug_track`.
+#[derive(Resource, Default)]
+struct DebugTracks(Vec<(&'static str, fn(&mut World) -> Vec<Entity>)>);
+
+/// Backs `DebugTrackExt::debug_track`.
+pub fn track<T: Component>(app: &mut App, label: &'static str) {
+    app.world_mut()
+        .get_resource_or_insert_with(DebugTracks::default)
+        .0
+        .push((label, entities_with::<T>));
+}
+
+fn entities_with<T: Component>(world: &mut World) -> Vec<Entity> {
+    world.query_filtered::<Entity, With<T>>().iter(world).collect()
+}
+
+/// Toggled with F4. Nothing but the toggle runs while it's closed.
+#[derive(Resource)]
+struct DebugOverlay {
+    open: bool,
+    refresh: Timer,
+    categories: Vec<(String, Vec<Entity>)>,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self {
+            open: false,
+            refresh: Timer::from_seconds(REFRESH_SECS, TimerMode::Repeating),
+            categories: Vec::new(),
+        }
+    }
+}
+
+fn overlay_open(overlay: Res<DebugOverlay>) -> bool {
+    overlay.open
+}
+
+#[derive(Component)]
+struct OverlayPanel;
+
+#[derive(Component)]
+struct OverlayText;
+
+fn spawn_overlay(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.),
+            right: Val::Px(8.),
+            padding: UiRect::all(Val::Px(8.)),
+            ..default()
+        },
+        background_color: Color::srgba(0., 0., 0., 0.7).into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Global(20),
+        ..default()
+    }, OverlayPanel)).with_children(|panel| {
+        panel.spawn((TextBundle::from_section("", TextStyle {
+            font_size: 16.,
+            ..default()
+        }), OverlayText));
+    });
+}
+
+fn toggle_overlay(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<DebugOverlay>,
+    mut panel: Query<&mut Visibility, With<OverlayPanel>>,
+) {
+    if !kb_input.just_pressed(KeyCode::F4) {
+        return;
+    }
+    overlay.open = !overlay.open;
+    // Refresh right away instead of showing stale counts for half a second.
+    let duration = overlay.refresh.duration();
+    overlay.refresh.set_elapsed(duration);
+    for mut visibility in &mut panel {
+        *visibility = if overlay.open { Visibility::Visible } else { Visibility::Hidden };
+    }
+}
+
+fn refresh_overlay(world: &mut World) {
+    let delta = world.resource::<Time<Real>>().delta();
+    if !world.resource_mut::<DebugOverlay>().refresh.tick(delta).just_finished() {
+        return;
+    }
+
+    let tracks = world.resource::<DebugTracks>().0.clone();
+    let mut categories: Vec<(String, Vec<Entity>)> = tracks.iter()
+        .map(|(label, collect)| (label.to_string(), collect(world)))
+        .collect();
+    let mut labeled: BTreeMap<&'static str, Vec<Entity>> = BTreeMap::new();
+    for (entity, label) in world.query::<(Entity, &DebugLabel)>().iter(world) {
+        labeled.entry(label.0).or_default().push(entity);
+    }
+    categories.extend(labeled.into_iter().map(|(label, entities)| (label.to_string(), entities)));
+
+    let mut text = format!("entities: {}\n", world.entities().len());
+    for (index, (label, entities)) in categories.iter().enumerate() {
+        let key = if index < SELECT_KEYS.len() { format!("[{}] ", index + 1) } else { String::new() };
+        text.push_str(&format!("{key}{label}: {}\n", entities.len()));
+    }
+    for mut overlay_text in world.query_filtered::<&mut Text, With<OverlayText>>().iter_mut(world) {
+        overlay_text.sections[0].value.clone_from(&text);
+    }
+    world.resource_mut::<DebugOverlay>().categories = categories;
+}
+
+/// Holding a category's number key outlines its entities.
+fn outline_selected(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    overlay: Res<DebugOverlay>,
+    bodies: Query<(&Position, Option<&Shape>)>,
+    mut gizmos: Gizmos,
+) {
+    for (key, (_, entities)) in SELECT_KEYS.iter().zip(&overlay.categories) {
+        if !kb_input.pressed(*key) {
+            continue;
+        }
+        for (position, shape) in bodies.iter_many(entities) {
+            let size = shape.map_or(Vec2::splat(8.), |shape| shape.0);
+            gizmos.rect_2d(position.0, 0., size, Color::srgb(0., 1., 0.));
+        }
+    }
+}
+
+fn draw_spring_vectors(springs: Query<(&Position, &Spring)>, mut gizmos: Gizmos) {
+    for (position, spring) in &springs {
+        let launch = spring.launch_velocity() * SPRING_ARROW_SCALE;
+        gizmos.arrow_2d(position.0, position.0 + launch, Color::srgb(1., 0.8, 0.2));
+    }
+}
+
+#[derive(Component)]
+struct TriggerLabel;
+
+/// Outlines every script trigger with its id over it. The labels only exist while the
+/// overlay is open.
+fn label_script_triggers(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    world_data: Query<&ScriptTriggers, With<WorldData>>,
+    mut labels: Query<(Entity, &mut Text, &mut Transform), With<TriggerLabel>>,
+    mut gizmos: Gizmos,
+    origin: Res<WorldOrigin>,
+) {
+    let triggers = world_data.get_single().ok().filter(|_| overlay.open).map_or(&[][..], |triggers| &triggers.0);
+    let color = Color::srgb(1., 0.3, 1.);
+    let mut labels = labels.iter_mut();
+    for trigger in triggers {
+        let top = match trigger.zone {
+            Zone::Region { position, size } => {
+                let position = origin.to_live(position);
+                gizmos.rect_2d(position, 0., size, color);
+                position + Vec2::new(0., size.y / 2.)
+            }
+            Zone::Radius { center, radius } => {
+                let center = origin.to_live(center);
+                gizmos.circle_2d(center, radius, color);
+                center + Vec2::new(0., radius)
+            }
+        };
+        let translation = (top + Vec2::new(0., TRIGGER_LABEL_GAP)).extend(10.);
+        match labels.next() {
+            Some((_, mut text, mut transform)) => {
+                if text.sections[0].value != trigger.id {
+                    text.sections[0].value.clone_from(&trigger.id);
+                }
+                transform.translation = translation;
+            }
+            None => {
+                commands.spawn((Text2dBundle {
+                    text: Text::from_section(trigger.id.clone(), TextStyle {
+                        font_size: 14.,
+                        color,
+                        ..default()
+                    }),
+                    transform: Transform::from_translation(translation),
+                    ..default()
+                }, TriggerLabel));
+            }
+        }
+    }
+    for (entity, ..) in labels {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// The entity under the mouse, or the one a right-click pinned, shown in a tooltip while
+/// the overlay or the editor is open.
+#[derive(Resource, Default)]
+struct Inspector {
+    hovered: Option<Entity>,
+    pinned: Option<Entity>,
+    /// Screen position the tooltip hangs off.
+    anchor: Vec2,
+}
+
+#[derive(Component)]
+struct TooltipPanel;
+
+#[derive(Component)]
+struct TooltipText;
+
+fn spawn_tooltip(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            padding: UiRect::all(Val::Px(6.)),
+            ..default()
+        },
+        background_color: Color::srgba(0., 0., 0., 0.8).into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Global(25),
+        ..default()
+    }, TooltipPanel)).with_children(|panel| {
+        panel.spawn((TextBundle::from_section("", TextStyle {
+            font_size: 14.,
+            ..default()
+        }), TooltipText));
+    });
+}
+
+fn inspecting(world: &World) -> bool {
+    #[cfg(feature = "editor")]
+    let editor_open = world.get_resource::<crate::editor::Editor>().is_some_and(|editor| editor.open);
+    #[cfg(not(feature = "editor"))]
+    let editor_open = false;
+    editor_open || world.resource::<DebugOverlay>().open
+}
+
+/// Finds what's under the cursor. When several things overlap, the highest `ZOrder` wins,
+/// then whichever was spawned last, so the pick never flickers between them.
+fn pick_inspected(
+    mouse: Res<ButtonInput<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<crate::camera::Camera>>,
+    bodies: Query<(Entity, &Position, &Shape, Option<&ZOrder>)>,
+    mut inspector: ResMut<Inspector>,
+) {
+    let cursor = cursor_world_position(&window, &camera);
+    inspector.hovered = cursor.and_then(|cursor| bodies.iter()
+        .filter(|(_, position, shape, _)| contains_point(Aabb2d::new(position.0, shape.0 / 2.), cursor))
+        .max_by(|(a, _, _, a_z), (b, _, _, b_z)| {
+            let (a_z, b_z) = (a_z.map_or(0., |z| z.0), b_z.map_or(0., |z| z.0));
+            a_z.total_cmp(&b_z).then(a.cmp(b))
+        })
+        .map(|(entity, ..)| entity));
+    if mouse.just_pressed(MouseButton::Right) {
+        // Right-clicking the pinned entity, or nothing, lets go.
+        inspector.pinned = inspector.hovered.filter(|hovered| inspector.pinned != Some(*hovered));
+    }
+    if inspector.pinned.is_none() {
+        if let Some(cursor) = window.get_single().ok().and_then(|window| window.cursor_position()) {
+            inspector.anchor = cursor;
+        }
+    }
+}
+
+/// One component's fields, read through reflection.
+fn field_values(value: &dyn Reflect) -> String {
+    match value.reflect_ref() {
+        ReflectRef::Struct(fields) => (0..fields.field_len())
+            .filter_map(|i| Some(format!("{}: {:.2?}", fields.name_at(i)?, fields.field_at(i)?)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        ReflectRef::TupleStruct(fields) => fields.iter_fields()
+            .map(|field| format!("{field:.2?}"))
+            .collect::<Vec<_>>()
+            .join(", "),
+        ReflectRef::Enum(variant) => {
+            let fields = variant.iter_fields()
+                .map(|field| match field.name() {
+                    Some(name) => format!("{name}: {:.2?}", field.value()),
+                    None => format!("{:.2?}", field.value()),
+                })
+                .collect::<Vec<_>>();
+            if fields.is_empty() {
+                variant.variant_name().to_string()
+            } else {
+                format!("{} {{ {} }}", variant.variant_name(), fields.join(", "))
+            }
+        }
+        _ => format!("{value:.2?}"),
+    }
+}
+
+/// Lists every component on `entity` that's registered for reflection, so a component
+/// shows up here as soon as it derives `Reflect` and its plugin calls `register_type`.
+fn describe(world: &World, entity: Entity) -> Option<String> {
+    let entity_ref = world.get_entity(entity)?;
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let mut text = format!("{entity}");
+    if let Some(label) = entity_ref.get::<DebugLabel>() {
+        text += &format!("  {}", label.0);
+    }
+    let mut components: Vec<(&str, String)> = entity_ref.archetype().components()
+        .filter_map(|id| {
+            let registration = registry.get(world.components().get_info(id)?.type_id()?)?;
+            let value = registration.data::<ReflectComponent>()?.reflect(entity_ref)?;
+            Some((registration.type_info().type_path_table().short_path(), field_values(value)))
+        })
+        .collect();
+    components.sort();
+    for (name, fields) in components {
+        text += &format!("\n{name}: {fields}");
+    }
+    Some(text)
+}
+
+/// Rewritten every frame, so a pinned entity's values stay live.
+fn refresh_tooltip(world: &mut World) {
+    let open = inspecting(world);
+    let inspector = world.resource::<Inspector>();
+    let anchor = inspector.anchor + TOOLTIP_OFFSET;
+    let text = inspector.pinned.or(inspector.hovered)
+        .filter(|_| open)
+        .and_then(|entity| describe(world, entity));
+
+    for (mut visibility, mut style) in world
+        .query_filtered::<(&mut Visibility, &mut Style), With<TooltipPanel>>()
+        .iter_mut(world) {
+        *visibility = if text.is_some() { Visibility::Visible } else { Visibility::Hidden };
+        style.left = Val::Px(anchor.x);
+        style.top = Val::Px(anchor.y);
+    }
+    if let Some(text) = text {
+        for mut tooltip_text in world.query_filtered::<&mut Text, With<TooltipText>>().iter_mut(world) {
+            tooltip_text.sections[0].value.clone_from(&text);
+        }
+    }
+}
+
+/// Debug switches that aren't part of the F4 overlay. `physics` (F6) draws colliders,
+/// velocities, ground probes and contacts over the level; none of that runs while it's off.
+#[derive(Resource, Default)]
+struct DebugSettings {
+    physics: bool,
+}
+
+fn physics_draw_on(settings: Res<DebugSettings>) -> bool {
+    settings.physics
+}
+
+/// Where recent contacts happened, on the body's side of each, with the render frames
+/// each has left on screen.
+#[derive(Resource, Default)]
+struct ContactMarkers(Vec<(Vec2, Collision, u32)>);
+
+fn toggle_physics_draw(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<DebugSettings>,
+    mut markers: ResMut<ContactMarkers>,
+) {
+    if kb_input.just_pressed(PHYSICS_TOGGLE_KEY) {
+        settings.physics = !settings.physics;
+        markers.0.clear();
+    }
+}
+
+/// Read in `FixedUpdate`, since several ticks can run in one frame.
+fn record_contacts(
+    mut collisions: EventReader<CollisionEvent>,
+    bodies: Query<(&Position, &Shape)>,
+    mut markers: ResMut<ContactMarkers>,
+) {
+    for event in collisions.read() {
+        if let Ok((position, shape)) = bodies.get(event.entity) {
+            let point = position.0 - event.side.normal() * shape.0 / 2.;
+            markers.0.push((point, event.side, CONTACT_MARKER_FRAMES));
+        }
+    }
+}
+
+/// Colliders are grey. The player's hitbox is green on the ground and red in the air,
+/// with the drawn `VisShape` in cyan around the same feet and the ground probe in yellow
+/// under them. Every moving body gets a velocity arrow. Contacts show as a tick along the
+/// side that touched, orange for floors and white for the rest, fading out over a few
+/// frames.
+fn debug_draw(
+    colliders: Query<(&Position, &Shape), With<Collider>>,
+    player: Query<(&Position, &Shape, &VisShape, &Grounded), With<Player>>,
+    bodies: Query<(&Position, &Velocity), Without<crate::camera::Camera>>,
+    config: Res<MovementConfig>,
+    mut markers: ResMut<ContactMarkers>,
+    mut gizmos: Gizmos,
+) {
+    for (position, shape) in &colliders {
+        gizmos.rect_2d(position.0, 0., shape.0, Color::srgba(0.7, 0.7, 0.7, 0.6));
+    }
+    for (position, shape, vis_shape, grounded) in &player {
+        let color = if grounded.0 { Color::srgb(0.2, 1., 0.2) } else { Color::srgb(1., 0.25, 0.25) };
+        gizmos.rect_2d(position.0, 0., shape.0, color);
+        let feet = position.0.y - shape.0.y / 2.;
+        gizmos.rect_2d(Vec2::new(position.0.x, feet + vis_shape.0.y / 2.), 0., vis_shape.0, Color::srgb(0.2, 0.9, 1.));
+        // The same probe `check_grounded` tests, inset hitbox and all.
+        let probe_width = (shape.0.x - 2. * config.hitbox_inset).max(2.);
+        gizmos.rect_2d(
+            Vec2::new(position.0.x, feet - GROUND_PROBE_DEPTH / 2.),
+            0.,
+            Vec2::new(probe_width, GROUND_PROBE_DEPTH),
+            Color::srgb(1., 0.9, 0.2),
+        );
+    }
+    for (position, velocity) in &bodies {
+        if velocity.0 != Vec2::ZERO {
+            gizmos.arrow_2d(position.0, position.0 + velocity.0 * VELOCITY_ARROW_SCALE, Color::srgb(0.4, 0.6, 1.));
+        }
+    }
+    for (point, side, frames) in &mut markers.0 {
+        let along = side.normal().perp() * CONTACT_MARKER_SIZE / 2.;
+        let base = if *side == Collision::Bottom { Color::srgb(1., 0.6, 0.1) } else { Color::WHITE };
+        let alpha = *frames as f32 / CONTACT_MARKER_FRAMES as f32;
+        gizmos.line_2d(*point - along, *point + along, base.with_alpha(alpha));
+        *frames -= 1;
+    }
+    markers.0.retain(|(.., frames)| *frames > 0);
+}
@@ -0,0 +1,1000 @@
+use std::collections::VecDeque;
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::{ButtonState, InputSystem};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::boost::BoostPad;
+use crate::camera::cursor_world_position;
+use crate::cannon::Cannon;
+use crate::level::LevelState;
+use crate::magnet::Magnet;
+use crate::origin::WorldOrigin;
+use crate::pendulum::PendulumRope;
+use crate::physics::{project_transforms, Collision, Position, Shape};
+use crate::platform::MovingPlatform;
+use crate::player::SquashStretch;
+use crate::script::{ScriptTrigger, ScriptTriggers};
+use crate::spawn_zone::Zone;
+use crate::spring::Spring;
+use crate::switch::ActivationBehavior;
+use crate::trap::{BladeSwing, CrusherTrack};
+use crate::world::{spawn_blocks, Block, BlockData, BlockIndex, BlockKind, MaterialPalette, MergedCollider, SurfaceKind, WorldData};
+use crate::GameState;
+
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+const GRID: f32 = 25.;
+/// How close to an edge of the selected block a press has to be to resize instead of move.
+const EDGE_GRAB: f32 = 8.;
+const HISTORY_LIMIT: usize = 100;
+/// Travel time between the speed ticks drawn along platform paths.
+const PATH_TICK_SECS: f32 = 0.1;
+const ANGLE_STEP: f32 = std::f32::consts::PI / 12.;
+const NEW_TRIGGER_SIZE: Vec2 = Vec2::new(100., 100.);
+const TRIGGER_COLOR: Color = Color::srgb(1., 0.3, 1.);
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Editor>()
+            .add_systems(Startup, spawn_property_panel)
+            .add_systems(PreUpdate, name_trigger.after(InputSystem).run_if(editor_open))
+            .add_systems(Update, (
+                toggle_editor.run_if(in_state(GameState::Playing)),
+                (drag_blocks,
+                 edit_shortcuts,
+                 edit_properties,
+                 rebuild_blocks,
+                 sync_selected.before(project_transforms),
+                 draw_selection,
+                 draw_script_triggers,
+                 draw_platform_paths,
+                 refresh_property_panel).chain().run_if(editor_open),
+            ).chain());
+    }
+}
+
+/// What the editor has selected, by index into `WorldData` or `ScriptTriggers`.
+#[derive(Clone, Copy, PartialEq)]
+enum Selection {
+    Block(usize),
+    Trigger(usize),
+}
+
+/// One undoable change to `WorldData` or `ScriptTriggers`.
+enum WorldEdit {
+    Change { index: usize, before: BlockData, after: BlockData },
+    Insert { index: usize, block: BlockData },
+    Remove { index: usize, block: BlockData },
+    ChangeTrigger { index: usize, before: ScriptTrigger, after: ScriptTrigger },
+    InsertTrigger { index: usize, trigger: ScriptTrigger },
+    RemoveTrigger { index: usize, trigger: ScriptTrigger },
+}
+
+impl WorldEdit {
+    /// Applies the edit and returns what it touched, if that still exists.
+    fn apply(&self, world: &mut WorldData, triggers: &mut ScriptTriggers) -> Option<Selection> {
+        match self {
+            WorldEdit::Change { index, after, .. } => {
+                world.0[*index] = after.clone();
+                Some(Selection::Block(*index))
+            }
+            WorldEdit::Insert { index, block } => {
+                world.0.insert(*index, block.clone());
+                Some(Selection::Block(*index))
+            }
+            WorldEdit::Remove { index, .. } => {
+                world.0.remove(*index);
+                None
+            }
+            WorldEdit::ChangeTrigger { index, after, .. } => {
+                triggers.0[*index] = after.clone();
+                Some(Selection::Trigger(*index))
+            }
+            WorldEdit::InsertTrigger { index, trigger } => {
+                triggers.0.insert(*index, trigger.clone());
+                Some(Selection::Trigger(*index))
+            }
+            WorldEdit::RemoveTrigger { index, .. } => {
+                triggers.0.remove(*index);
+                None
+            }
+        }
+    }
+
+    fn revert(&self, world: &mut WorldData, triggers: &mut ScriptTriggers) -> Option<Selection> {
+        match self {
+            WorldEdit::Change { index, before, .. } => {
+                world.0[*index] = before.clone();
+                Some(Selection::Block(*index))
+            }
+            WorldEdit::Insert { index, .. } => {
+                world.0.remove(*index);
+                None
+            }
+            WorldEdit::Remove { index, block } => {
+                world.0.insert(*index, block.clone());
+                Some(Selection::Block(*index))
+            }
+            WorldEdit::ChangeTrigger { index, before, .. } => {
+                triggers.0[*index] = before.clone();
+                Some(Selection::Trigger(*index))
+            }
+            WorldEdit::InsertTrigger { index, .. } => {
+                triggers.0.remove(*index);
+                None
+            }
+            WorldEdit::RemoveTrigger { index, trigger } => {
+                triggers.0.insert(*index, trigger.clone());
+                Some(Selection::Trigger(*index))
+            }
+        }
+    }
+}
+
+/// Undo and redo stacks. Only the last `HISTORY_LIMIT` edits can be undone.
+#[derive(Default)]
+struct History {
+    undo: VecDeque<WorldEdit>,
+    redo: Vec<WorldEdit>,
+}
+
+impl History {
+    fn push(&mut self, edit: WorldEdit) {
+        self.redo.clear();
+        self.undo.push_back(edit);
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Grab {
+    Move,
+    /// Which edges follow the cursor: -1 for the left/bottom one, 1 for the right/top one.
+    Resize { x: i8, y: i8 },
+}
+
+/// What was being dragged, as it was when the drag started.
+enum Dragged {
+    Block(BlockData),
+    Trigger(ScriptTrigger),
+}
+
+struct Drag {
+    grab: Grab,
+    start: Vec2,
+    before: Dragged,
+}
+
+/// The F3 level editor. Opening it pauses the simulation; every edit goes straight into
+/// `WorldData` and the spawned blocks follow, so what's saved is what's on screen.
+#[derive(Resource, Default)]
+pub struct Editor {
+    pub open: bool,
+    was_paused: bool,
+    selected: Option<Selection>,
+    drag: Option<Drag>,
+    /// The selected trigger's id is being typed. Holds the trigger as it was before, for
+    /// the undo history.
+    naming: Option<ScriptTrigger>,
+    /// Row of the property panel that the arrow keys adjust.
+    property: usize,
+    history: History,
+    /// Blocks were added, removed or changed kind, so every block entity is respawned.
+    rebuild: bool,
+    /// The selected block moved or changed size.
+    moved: bool,
+}
+
+impl Editor {
+    fn selected_block(&self) -> Option<usize> {
+        match self.selected {
+            Some(Selection::Block(index)) => Some(index),
+            _ => None,
+        }
+    }
+}
+
+fn editor_open(editor: Res<Editor>) -> bool {
+    editor.open
+}
+
+#[derive(Component)]
+struct PropertyPanel;
+
+#[derive(Component)]
+struct PropertyText;
+
+fn spawn_property_panel(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.),
+            left: Val::Px(8.),
+            padding: UiRect::all(Val::Px(8.)),
+            ..default()
+        },
+        background_color: Color::srgba(0., 0., 0., 0.7).into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Global(20),
+        ..default()
+    }, PropertyPanel)).with_children(|panel| {
+        panel.spawn((TextBundle::from_section("", TextStyle {
+            font_size: 16.,
+            ..default()
+        }), PropertyText));
+    });
+}
+
+fn toggle_editor(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    mut time: ResMut<Time<Virtual>>,
+    mut panel: Query<&mut Visibility, With<PropertyPanel>>,
+    origin: Res<WorldOrigin>,
+) {
+    if !kb_input.just_pressed(TOGGLE_KEY) {
+        return;
+    }
+    // The editor works on the level file's coordinates, which only match what's spawned
+    // until the origin first moves.
+    if !editor.open && origin.0 != Vec2::ZERO {
+        warn!("the editor can't open this far out; restart the level first");
+        return;
+    }
+    editor.open = !editor.open;
+    editor.drag = None;
+    editor.naming = None;
+    if editor.open {
+        editor.was_paused = time.is_paused();
+        time.pause();
+    } else if !editor.was_paused {
+        time.unpause();
+    }
+    for mut visibility in &mut panel {
+        *visibility = if editor.open { Visibility::Visible } else { Visibility::Hidden };
+    }
+}
+
+fn snap(value: Vec2) -> Vec2 {
+    (value / GRID).round() * GRID
+}
+
+fn contains(block: &BlockData, point: Vec2, margin: f32) -> bool {
+    (point - block.position).abs().cmple(block.shape / 2. + margin).all()
+}
+
+/// Edges of `block` within grabbing distance of `point`.
+fn grab_at(block: &BlockData, point: Vec2) -> Grab {
+    let offset = point - block.position;
+    let near = |offset: f32, half: f32| {
+        if offset >= half - EDGE_GRAB {
+            1
+        } else if offset <= -half + EDGE_GRAB {
+            -1
+        } else {
+            0
+        }
+    };
+    let half = block.shape / 2.;
+    match (near(offset.x, half.x), near(offset.y, half.y)) {
+        (0, 0) => Grab::Move,
+        (x, y) => Grab::Resize { x, y },
+    }
+}
+
+fn drag_blocks(
+    mouse: Res<ButtonInput<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<crate::camera::Camera>>,
+    mut world_data: Query<(&mut WorldData, &mut ScriptTriggers)>,
+    mut editor: ResMut<Editor>,
+) {
+    let Ok((mut world, mut triggers)) = world_data.get_single_mut() else {
+        return;
+    };
+    let cursor = cursor_world_position(&window, &camera);
+
+    if mouse.just_pressed(MouseButton::Left) {
+        let Some(cursor) = cursor else {
+            return;
+        };
+        let selected = editor.selected_block()
+            .filter(|index| world.0.get(*index).is_some_and(|block| contains(block, cursor, EDGE_GRAB)));
+        // Topmost block wins, which is the one spawned last. Triggers can only be picked
+        // where there's no block in the way.
+        let picked = selected
+            .or_else(|| world.0.iter().rposition(|block| contains(block, cursor, 0.)))
+            .map(Selection::Block)
+            .or_else(|| triggers.0.iter().rposition(|trigger| trigger.zone.contains(cursor)).map(Selection::Trigger));
+        if picked != editor.selected {
+            editor.property = 0;
+            editor.naming = None;
+        }
+        editor.selected = picked;
+        editor.drag = picked.map(|picked| match picked {
+            Selection::Block(index) => {
+                let block = &world.0[index];
+                Drag {
+                    grab: if selected.is_some() { grab_at(block, cursor) } else { Grab::Move },
+                    start: cursor,
+                    before: Dragged::Block(block.clone()),
+                }
+            }
+            Selection::Trigger(index) => Drag {
+                grab: Grab::Move,
+                start: cursor,
+                before: Dragged::Trigger(triggers.0[index].clone()),
+            },
+        });
+        return;
+    }
+
+    if mouse.just_released(MouseButton::Left) {
+        let edit = match (editor.drag.take().map(|drag| drag.before), editor.selected) {
+            (Some(Dragged::Block(before)), Some(Selection::Block(index))) => {
+                let after = world.0[index].clone();
+                (after != before).then_some(WorldEdit::Change { index, before, after })
+            }
+            (Some(Dragged::Trigger(before)), Some(Selection::Trigger(index))) => {
+                let after = triggers.0[index].clone();
+                (after != before).then_some(WorldEdit::ChangeTrigger { index, before, after })
+            }
+            _ => None,
+        };
+        if let Some(edit) = edit {
+            editor.history.push(edit);
+        }
+        return;
+    }
+
+    let (Some(cursor), Some(selected)) = (cursor, editor.selected) else {
+        return;
+    };
+    let Some(drag) = &editor.drag else {
+        return;
+    };
+    let delta = snap(cursor - drag.start);
+    let (before, index) = match (&drag.before, selected) {
+        (Dragged::Block(before), Selection::Block(index)) => (before, index),
+        (Dragged::Trigger(before), Selection::Trigger(index)) => {
+            triggers.0[index].zone = moved(before.zone, delta);
+            return;
+        }
+        _ => return,
+    };
+    let (position, shape) = match drag.grab {
+        Grab::Move => (before.position + delta, before.shape),
+        Grab::Resize { x, y } => {
+            let mut min = before.position - before.shape / 2.;
+            let mut max = before.position + before.shape / 2.;
+            match x {
+                1 => max.x = (max.x + delta.x).max(min.x + GRID),
+                -1 => min.x = (min.x + delta.x).min(max.x - GRID),
+                _ => {}
+            }
+            match y {
+                1 => max.y = (max.y + delta.y).max(min.y + GRID),
+                -1 => min.y = (min.y + delta.y).min(max.y - GRID),
+                _ => {}
+            }
+            ((min + max) / 2., max - min)
+        }
+    };
+    let block = &mut world.0[index];
+    if block.position != position || block.shape != shape {
+        block.position = position;
+        block.shape = shape;
+        editor.moved = true;
+    }
+}
+
+fn moved(zone: Zone, delta: Vec2) -> Zone {
+    match zone {
+        Zone::Radius { center, radius } => Zone::Radius { center: center + delta, radius },
+        Zone::Region { position, size } => Zone::Region { position: position + delta, size },
+    }
+}
+
+fn edit_shortcuts(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<crate::camera::Camera>>,
+    mut world_data: Query<(&mut WorldData, &mut ScriptTriggers)>,
+    mut editor: ResMut<Editor>,
+) {
+    let Ok((mut world, mut triggers)) = world_data.get_single_mut() else {
+        return;
+    };
+    if editor.drag.is_some() {
+        return;
+    }
+    let ctrl = kb_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = kb_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    let undo = ctrl && !shift && kb_input.just_pressed(KeyCode::KeyZ);
+    let redo = ctrl && (kb_input.just_pressed(KeyCode::KeyY) || (shift && kb_input.just_pressed(KeyCode::KeyZ)));
+    if undo {
+        if let Some(edit) = editor.history.undo.pop_back() {
+            editor.selected = edit.revert(&mut world, &mut triggers);
+            editor.history.redo.push(edit);
+            editor.rebuild = true;
+        }
+        return;
+    }
+    if redo {
+        if let Some(edit) = editor.history.redo.pop() {
+            editor.selected = edit.apply(&mut world, &mut triggers);
+            editor.history.undo.push_back(edit);
+            editor.rebuild = true;
+        }
+        return;
+    }
+
+    let delete = kb_input.just_pressed(KeyCode::Delete);
+    let duplicate = ctrl && kb_input.just_pressed(KeyCode::KeyD);
+    let place_trigger = !ctrl && kb_input.just_pressed(KeyCode::KeyT);
+    let edit = match editor.selected {
+        _ if place_trigger => {
+            let Some(cursor) = cursor_world_position(&window, &camera) else {
+                return;
+            };
+            let trigger = ScriptTrigger {
+                id: unused_trigger_id(&triggers),
+                zone: Zone::Region { position: snap(cursor), size: NEW_TRIGGER_SIZE },
+                once: true,
+            };
+            WorldEdit::InsertTrigger { index: triggers.0.len(), trigger }
+        }
+        Some(Selection::Block(index)) if delete => WorldEdit::Remove { index, block: world.0[index].clone() },
+        Some(Selection::Block(index)) if duplicate => {
+            let mut block = world.0[index].clone();
+            block.position += Vec2::new(GRID, -GRID);
+            WorldEdit::Insert { index: world.0.len(), block }
+        }
+        Some(Selection::Trigger(index)) if delete => WorldEdit::RemoveTrigger { index, trigger: triggers.0[index].clone() },
+        Some(Selection::Trigger(index)) if duplicate => {
+            // Same id, so the copy extends the original's volume.
+            let mut trigger = triggers.0[index].clone();
+            trigger.zone = moved(trigger.zone, Vec2::new(GRID, -GRID));
+            WorldEdit::InsertTrigger { index: triggers.0.len(), trigger }
+        }
+        _ => return,
+    };
+    editor.selected = edit.apply(&mut world, &mut triggers);
+    editor.history.push(edit);
+    editor.rebuild = true;
+}
+
+/// `trigger_1`, `trigger_2`, ... whichever is free first.
+fn unused_trigger_id(triggers: &ScriptTriggers) -> String {
+    (1..)
+        .map(|n| format!("trigger_{n}"))
+        .find(|id| triggers.0.iter().all(|trigger| trigger.id != *id))
+        .unwrap()
+}
+
+/// Enter on a selected trigger starts typing its id, and Enter again finishes. While
+/// typing, keys are taken away from everything else, so letters don't also jump, restart
+/// or run editor shortcuts.
+fn name_trigger(
+    mut keys: EventReader<KeyboardInput>,
+    mut kb_input: ResMut<ButtonInput<KeyCode>>,
+    mut world_data: Query<&mut ScriptTriggers>,
+    mut editor: ResMut<Editor>,
+) {
+    let (Ok(mut triggers), Some(Selection::Trigger(index))) = (world_data.get_single_mut(), editor.selected) else {
+        keys.clear();
+        return;
+    };
+    let was_naming = editor.naming.is_some();
+    for key in keys.read().filter(|key| key.state == ButtonState::Pressed) {
+        let trigger = &mut triggers.0[index];
+        match (editor.naming.take(), &key.logical_key) {
+            (None, Key::Enter) => editor.naming = Some(trigger.clone()),
+            (None, _) => {}
+            (Some(before), Key::Enter) => {
+                if *trigger != before {
+                    editor.history.push(WorldEdit::ChangeTrigger { index, before, after: trigger.clone() });
+                }
+            }
+            (Some(before), key) => {
+                match key {
+                    Key::Backspace => {
+                        trigger.id.pop();
+                    }
+                    Key::Character(text) => trigger.id.extend(text.chars().filter(|c| c.is_alphanumeric() || *c == '_')),
+                    _ => {}
+                }
+                editor.naming = Some(before);
+            }
+        }
+    }
+    // The Enter that finished typing shouldn't reach anything either.
+    if was_naming || editor.naming.is_some() {
+        kb_input.reset_all();
+    }
+}
+
+const SURFACES: [Option<SurfaceKind>; 5] = [
+    None,
+    Some(SurfaceKind::Stone),
+    Some(SurfaceKind::Metal),
+    Some(SurfaceKind::Ice),
+    Some(SurfaceKind::Slime),
+];
+const SIDES: [Collision; 4] = [Collision::Left, Collision::Top, Collision::Right, Collision::Bottom];
+
+/// Kinds in the order the "kind" property cycles through them, each with starting settings.
+fn kinds() -> [BlockKind; 25] {
+    [
+        BlockKind::Solid,
+        BlockKind::Gate { passable_from: Collision::Left },
+        BlockKind::Cannon(Cannon { direction: Vec2::Y, strength: 1728., auto_fire_delay: None }),
+        BlockKind::Magnet(Magnet { strength: 5760., radius: 150., polarity: 1 }),
+        BlockKind::Slime,
+        BlockKind::Spring(Spring { direction: Vec2::Y, strength: 1440. }),
+        BlockKind::OneWay,
+        BlockKind::Checkpoint,
+        BlockKind::Spikes,
+        BlockKind::Slope { rises_right: true },
+        BlockKind::Breakable,
+        BlockKind::Conveyor { speed: 288. },
+        BlockKind::Ladder,
+        BlockKind::Exit { press_up: false },
+        BlockKind::GravityZone { gravity: Vec2::new(0., 4147.2) },
+        BlockKind::GravityFlip,
+        BlockKind::Wind { force: Vec2::new(1200., 0.) },
+        BlockKind::Crusher(CrusherTrack { drop: 150., slam_speed: 720., retract_speed: 144., wait: 1.5, delay: 0. }),
+        BlockKind::Blade(BladeSwing { length: 120., arc: 60., period: 2., phase: 0. }),
+        BlockKind::Crumbling { delay: 0.5, respawn: Some(3.) },
+        BlockKind::Blinking { period: 3., on_duration: 2., phase: 0. },
+        BlockKind::Swinging(PendulumRope { length: 200., damping: 0.3 }),
+        BlockKind::PressurePlate { channel: 0, latching: false },
+        BlockKind::Activatable { channel: 0, behavior: ActivationBehavior::Door },
+        BlockKind::BoostPad(BoostPad { direction: Vec2::X, strength: 1152., face_only: false, additive: false }),
+    ]
+}
+
+fn kind_name(kind: BlockKind) -> &'static str {
+    match kind {
+        BlockKind::Solid => "solid",
+        BlockKind::Gate { .. } => "gate",
+        BlockKind::Cannon(_) => "cannon",
+        BlockKind::Magnet(_) => "magnet",
+        BlockKind::Slime => "slime",
+        BlockKind::Spring(_) => "spring",
+        BlockKind::OneWay => "one-way",
+        BlockKind::Checkpoint => "checkpoint",
+        BlockKind::Spikes => "spikes",
+        BlockKind::Slope { .. } => "slope",
+        BlockKind::Breakable => "breakable",
+        BlockKind::Conveyor { .. } => "conveyor",
+        BlockKind::Ladder => "ladder",
+        BlockKind::Exit { .. } => "exit",
+        BlockKind::GravityZone { .. } => "gravity zone",
+        BlockKind::GravityFlip => "gravity flip",
+        BlockKind::Wind { .. } => "wind",
+        BlockKind::Crusher(_) => "crusher",
+        BlockKind::Blade(_) => "blade",
+        BlockKind::Crumbling { .. } => "crumbling",
+        BlockKind::Blinking { .. } => "blinking",
+        BlockKind::Swinging(_) => "swinging",
+        BlockKind::PressurePlate { .. } => "pressure plate",
+        BlockKind::Activatable { .. } => "switched",
+        BlockKind::BoostPad(_) => "boost pad",
+    }
+}
+
+fn cycle<T: PartialEq + Copy>(options: &[T], current: impl Fn(&T) -> bool, step: i32) -> T {
+    let index = options.iter().position(current).unwrap_or(0) as i32;
+    options[(index + step).rem_euclid(options.len() as i32) as usize]
+}
+
+/// The selected block's editable properties as (name, value) rows.
+fn properties(block: &BlockData) -> Vec<(&'static str, String)> {
+    let surface = match block.surface {
+        Some(surface) => format!("{surface:?}"),
+        None => format!("{:?} (default)", block.kind.surface()),
+    };
+    let mut rows = vec![("kind", kind_name(block.kind).to_string()), ("surface", surface)];
+    match block.kind {
+        BlockKind::Gate { passable_from } => rows.push(("passable from", format!("{passable_from:?}"))),
+        BlockKind::Cannon(cannon) => {
+            rows.push(("angle", format!("{:.0}", cannon.direction.to_angle().to_degrees())));
+            rows.push(("strength", format!("{:.0}", cannon.strength)));
+            rows.push(("auto fire", cannon.auto_fire_delay.map_or("off".to_string(), |secs| format!("{secs:.1}s"))));
+        }
+        BlockKind::Magnet(magnet) => {
+            rows.push(("strength", format!("{:.0}", magnet.strength)));
+            rows.push(("radius", format!("{:.0}", magnet.radius)));
+            rows.push(("polarity", if magnet.polarity >= 0 { "attract" } else { "repel" }.to_string()));
+        }
+        BlockKind::Spring(spring) => {
+            rows.push(("angle", format!("{:.0}", spring.direction.to_angle().to_degrees())));
+            rows.push(("strength", format!("{:.0}", spring.strength)));
+        }
+        BlockKind::Slope { rises_right } => rows.push(("rises", if rises_right { "right" } else { "left" }.to_string())),
+        BlockKind::Conveyor { speed } => rows.push(("speed", format!("{speed:.0}"))),
+        BlockKind::Exit { press_up } => rows.push(("enter", if press_up { "press up" } else { "touch" }.to_string())),
+        BlockKind::GravityZone { gravity: force } | BlockKind::Wind { force } => {
+            rows.push(("angle", format!("{:.0}", force.to_angle().to_degrees())));
+            rows.push(("strength", format!("{:.0}", force.length())));
+        }
+        BlockKind::Crusher(track) => {
+            rows.push(("drop", format!("{:.0}", track.drop)));
+            rows.push(("slam speed", format!("{:.0}", track.slam_speed)));
+            rows.push(("retract speed", format!("{:.0}", track.retract_speed)));
+            rows.push(("wait", format!("{:.2}s", track.wait)));
+        }
+        BlockKind::Blade(swing) => {
+            rows.push(("length", format!("{:.0}", swing.length)));
+            rows.push(("arc", format!("{:.0}", swing.arc)));
+            rows.push(("period", format!("{:.2}s", swing.period)));
+            rows.push(("phase", format!("{:.2}", swing.phase)));
+        }
+        BlockKind::Crumbling { delay, respawn } => {
+            rows.push(("delay", format!("{delay:.1}s")));
+            rows.push(("respawn", respawn.map_or("never".to_string(), |secs| format!("{secs:.1}s"))));
+        }
+        BlockKind::Blinking { period, on_duration, phase } => {
+            rows.push(("period", format!("{period:.2}s")));
+            rows.push(("on for", format!("{on_duration:.2}s")));
+            rows.push(("phase", format!("{phase:.2}")));
+        }
+        BlockKind::Swinging(rope) => {
+            rows.push(("rope", format!("{:.0}", rope.length)));
+            rows.push(("damping", format!("{:.2}", rope.damping)));
+        }
+        BlockKind::PressurePlate { channel, latching } => {
+            rows.push(("channel", channel.to_string()));
+            rows.push(("latching", if latching { "yes" } else { "no" }.to_string()));
+        }
+        BlockKind::Activatable { channel, behavior } => {
+            rows.push(("channel", channel.to_string()));
+            match behavior {
+                ActivationBehavior::Door => rows.push(("behavior", "door".to_string())),
+                ActivationBehavior::Bridge { order } => {
+                    rows.push(("behavior", "bridge".to_string()));
+                    rows.push(("order", order.to_string()));
+                }
+            }
+        }
+        BlockKind::BoostPad(pad) => {
+            rows.push(("angle", format!("{:.0}", pad.direction.to_angle().to_degrees())));
+            rows.push(("strength", format!("{:.0}", pad.strength)));
+            rows.push(("boosts from", if pad.face_only { "face" } else { "any side" }.to_string()));
+            rows.push(("velocity", if pad.additive { "add" } else { "set" }.to_string()));
+        }
+        BlockKind::Solid | BlockKind::Slime | BlockKind::OneWay | BlockKind::Checkpoint | BlockKind::Spikes | BlockKind::Breakable | BlockKind::Ladder | BlockKind::GravityFlip => {}
+    }
+    rows
+}
+
+fn adjust(block: &mut BlockData, property: usize, step: i32) {
+    let sign = step as f32;
+    match (property, &mut block.kind) {
+        (0, kind) => *kind = cycle(&kinds(), |option| kind_name(*option) == kind_name(*kind), step),
+        (1, _) => block.surface = cycle(&SURFACES, |option| *option == block.surface, step),
+        (2, BlockKind::Gate { passable_from }) => *passable_from = cycle(&SIDES, |side| side == passable_from, step),
+        (2, BlockKind::Cannon(cannon)) => {
+            cannon.direction = Vec2::from_angle(cannon.direction.to_angle() + sign * ANGLE_STEP);
+        }
+        (3, BlockKind::Cannon(cannon)) => cannon.strength = (cannon.strength + sign * 144.).max(144.),
+        (4, BlockKind::Cannon(cannon)) => {
+            let delay = cannon.auto_fire_delay.unwrap_or(0.) + sign * 0.5;
+            cannon.auto_fire_delay = (delay > 0.).then_some(delay);
+        }
+        (2, BlockKind::Magnet(magnet)) => magnet.strength = (magnet.strength + sign * 720.).max(0.),
+        (3, BlockKind::Magnet(magnet)) => magnet.radius = (magnet.radius + sign * 10.).max(10.),
+        (4, BlockKind::Magnet(magnet)) => magnet.polarity = -magnet.polarity,
+        (2, BlockKind::Spring(spring)) => {
+            spring.direction = Vec2::from_angle(spring.direction.to_angle() + sign * ANGLE_STEP);
+        }
+        (3, BlockKind::Spring(spring)) => spring.strength = (spring.strength + sign * 144.).max(144.),
+        (2, BlockKind::Slope { rises_right }) => *rises_right = !*rises_right,
+        (2, BlockKind::Conveyor { speed }) => *speed += sign * 72.,
+        (2, BlockKind::Exit { press_up }) => *press_up = !*press_up,
+        (2, BlockKind::GravityZone { gravity: force } | BlockKind::Wind { force }) => {
+            *force = Vec2::from_angle(force.to_angle() + sign * ANGLE_STEP) * force.length();
+        }
+        (3, BlockKind::GravityZone { gravity: force } | BlockKind::Wind { force }) => {
+            *force = force.normalize_or(Vec2::Y) * (force.length() + sign * 200.).max(0.);
+        }
+        (2, BlockKind::Crusher(track)) => track.drop = (track.drop + sign * GRID).max(0.),
+        (3, BlockKind::Crusher(track)) => track.slam_speed = (track.slam_speed + sign * 72.).max(72.),
+        (4, BlockKind::Crusher(track)) => track.retract_speed = (track.retract_speed + sign * 36.).max(36.),
+        (5, BlockKind::Crusher(track)) => track.wait = (track.wait + sign * 0.25).max(0.),
+        (2, BlockKind::Blade(swing)) => swing.length = (swing.length + sign * 10.).max(10.),
+        (3, BlockKind::Blade(swing)) => swing.arc = (swing.arc + sign * 5.).clamp(0., 180.),
+        (4, BlockKind::Blade(swing)) => swing.period = (swing.period + sign * 0.25).max(0.25),
+        (5, BlockKind::Blade(swing)) => swing.phase = (swing.phase + sign * 0.05).rem_euclid(1.),
+        (2, BlockKind::Crumbling { delay, .. }) => *delay = (*delay + sign * 0.1).max(0.),
+        (3, BlockKind::Crumbling { respawn, .. }) => {
+            let secs = respawn.unwrap_or(0.) + sign * 0.5;
+            *respawn = (secs > 0.).then_some(secs);
+        }
+        (2, BlockKind::Blinking { period, on_duration, .. }) => {
+            *period = (*period + sign * 0.25).max(0.25);
+            *on_duration = on_duration.min(*period);
+        }
+        (3, BlockKind::Blinking { period, on_duration, .. }) => *on_duration = (*on_duration + sign * 0.25).clamp(0., *period),
+        (4, BlockKind::Blinking { phase, .. }) => *phase = (*phase + sign * 0.05).rem_euclid(1.),
+        (2, BlockKind::Swinging(rope)) => rope.length = (rope.length + sign * GRID).max(GRID),
+        (3, BlockKind::Swinging(rope)) => rope.damping = (rope.damping + sign * 0.1).max(0.),
+        (2, BlockKind::PressurePlate { channel, .. } | BlockKind::Activatable { channel, .. }) => *channel = channel.saturating_add_signed(step),
+        (3, BlockKind::PressurePlate { latching, .. }) => *latching = !*latching,
+        (3, BlockKind::Activatable { behavior, .. }) => {
+            *behavior = match behavior {
+                ActivationBehavior::Door => ActivationBehavior::Bridge { order: 0 },
+                ActivationBehavior::Bridge { .. } => ActivationBehavior::Door,
+            };
+        }
+        (4, BlockKind::Activatable { behavior: ActivationBehavior::Bridge { order }, .. }) => *order = order.saturating_add_signed(step),
+        (2, BlockKind::BoostPad(pad)) => pad.direction = Vec2::from_angle(pad.direction.to_angle() + sign * ANGLE_STEP),
+        (3, BlockKind::BoostPad(pad)) => pad.strength = (pad.strength + sign * 144.).max(144.),
+        (4, BlockKind::BoostPad(pad)) => pad.face_only = !pad.face_only,
+        (5, BlockKind::BoostPad(pad)) => pad.additive = !pad.additive,
+        _ => {}
+    }
+}
+
+/// The selected trigger's editable properties, like `properties`. The id is typed in
+/// rather than adjusted.
+fn trigger_properties(trigger: &ScriptTrigger, naming: bool) -> Vec<(&'static str, String)> {
+    let id = if naming { format!("{}_", trigger.id) } else { format!("{} (Enter to rename)", trigger.id) };
+    let mut rows = vec![("id", id), ("once", if trigger.once { "yes" } else { "no" }.to_string())];
+    match trigger.zone {
+        Zone::Region { size, .. } => {
+            rows.push(("shape", "region".to_string()));
+            rows.push(("width", format!("{:.0}", size.x)));
+            rows.push(("height", format!("{:.0}", size.y)));
+        }
+        Zone::Radius { radius, .. } => {
+            rows.push(("shape", "radius".to_string()));
+            rows.push(("radius", format!("{radius:.0}")));
+        }
+    }
+    rows
+}
+
+fn adjust_trigger(trigger: &mut ScriptTrigger, property: usize, step: i32) {
+    let delta = step as f32 * GRID;
+    match (property, &mut trigger.zone) {
+        (1, _) => trigger.once = !trigger.once,
+        (2, Zone::Region { position, size }) => {
+            trigger.zone = Zone::Radius { center: *position, radius: size.max_element() / 2. };
+        }
+        (2, Zone::Radius { center, radius }) => {
+            trigger.zone = Zone::Region { position: *center, size: Vec2::splat(*radius * 2.) };
+        }
+        (3, Zone::Region { size, .. }) => size.x = (size.x + delta).max(GRID),
+        (4, Zone::Region { size, .. }) => size.y = (size.y + delta).max(GRID),
+        (3, Zone::Radius { radius, .. }) => *radius = (*radius + delta).max(GRID),
+        _ => {}
+    }
+}
+
+fn edit_properties(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut world_data: Query<(&mut WorldData, &mut ScriptTriggers)>,
+    mut editor: ResMut<Editor>,
+) {
+    let Ok((mut world, mut triggers)) = world_data.get_single_mut() else {
+        return;
+    };
+    let Some(selected) = editor.selected else {
+        return;
+    };
+    if editor.drag.is_some() {
+        return;
+    }
+    let rows = match selected {
+        Selection::Block(index) => properties(&world.0[index]).len(),
+        Selection::Trigger(index) => trigger_properties(&triggers.0[index], false).len(),
+    };
+    if kb_input.just_pressed(KeyCode::ArrowUp) {
+        editor.property = (editor.property + rows - 1) % rows;
+    } else if kb_input.just_pressed(KeyCode::ArrowDown) {
+        editor.property = (editor.property + 1) % rows;
+    }
+    editor.property = editor.property.min(rows - 1);
+
+    let step = if kb_input.just_pressed(KeyCode::ArrowRight) {
+        1
+    } else if kb_input.just_pressed(KeyCode::ArrowLeft) {
+        -1
+    } else {
+        return;
+    };
+    match selected {
+        Selection::Block(index) => {
+            let before = world.0[index].clone();
+            adjust(&mut world.0[index], editor.property, step);
+            let after = world.0[index].clone();
+            if after != before {
+                editor.history.push(WorldEdit::Change { index, before, after });
+                editor.rebuild = true;
+            }
+        }
+        Selection::Trigger(index) => {
+            let before = triggers.0[index].clone();
+            adjust_trigger(&mut triggers.0[index], editor.property, step);
+            let after = triggers.0[index].clone();
+            if after != before {
+                editor.history.push(WorldEdit::ChangeTrigger { index, before, after });
+            }
+        }
+    }
+}
+
+fn rebuild_blocks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut palette: ResMut<MaterialPalette>,
+    blocks: Query<Entity, Or<(With<Block>, With<MergedCollider>)>>,
+    world_data: Query<&WorldData>,
+    level_state: Res<LevelState>,
+    mut editor: ResMut<Editor>,
+) {
+    if !editor.rebuild {
+        return;
+    }
+    editor.rebuild = false;
+    editor.moved = false;
+    let Ok(world) = world_data.get_single() else {
+        return;
+    };
+    for entity in &blocks {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_blocks(&mut commands, &mut meshes, &mut materials, &mut palette, world, &level_state);
+}
+
+/// Moves and resizes the selected block's entity to match its `BlockData`. A moving
+/// platform is put back at the start of its path, which moves with it. A plain block can
+/// join or leave a `MergedCollider` by moving, so the blocks are rebuilt to merge afresh.
+fn sync_selected(
+    mut blocks: Query<(&BlockIndex, &mut Position, &mut Shape, &mut Handle<Mesh>, Option<&mut MovingPlatform>, Option<&mut SquashStretch>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    world_data: Query<&WorldData>,
+    mut editor: ResMut<Editor>,
+) {
+    if !editor.moved {
+        return;
+    }
+    editor.moved = false;
+    let (Ok(world), Some(selected)) = (world_data.get_single(), editor.selected_block()) else {
+        return;
+    };
+    let block = &world.0[selected];
+    if block.kind == BlockKind::Solid && block.path.is_none() {
+        editor.rebuild = true;
+        return;
+    }
+    for (index, mut position, mut shape, mut mesh, platform, squash) in &mut blocks {
+        if index.0 != selected {
+            continue;
+        }
+        position.0 = block.position;
+        if let (Some(mut platform), Some(path)) = (platform, block.placed_path()) {
+            position.0 = path.waypoints[0];
+            *platform = MovingPlatform::new(path);
+        }
+        if shape.0 != block.shape {
+            shape.0 = block.shape;
+            *mesh = meshes.add(block.mesh());
+            if let Some(mut squash) = squash {
+                squash.target = block.shape;
+            }
+        }
+    }
+}
+
+fn draw_selection(
+    mut gizmos: Gizmos,
+    world_data: Query<(&WorldData, &ScriptTriggers)>,
+    editor: Res<Editor>,
+) {
+    let (Ok((world, triggers)), Some(selected)) = (world_data.get_single(), editor.selected) else {
+        return;
+    };
+    let color = Color::srgb(1., 0.8, 0.);
+    match selected {
+        Selection::Block(index) => {
+            if let Some(block) = world.0.get(index) {
+                gizmos.rect_2d(block.position, 0., block.shape + 4., color);
+            }
+        }
+        Selection::Trigger(index) => {
+            if let Some(trigger) = triggers.0.get(index) {
+                outline_zone(&mut gizmos, trigger.zone, 2., color);
+            }
+        }
+    }
+}
+
+fn outline_zone(gizmos: &mut Gizmos, zone: Zone, grow: f32, color: Color) {
+    match zone {
+        Zone::Region { position, size } => gizmos.rect_2d(position, 0., size + grow * 2., color),
+        Zone::Radius { center, radius } => {
+            gizmos.circle_2d(center, radius + grow, color);
+        }
+    }
+}
+
+/// Script triggers are invisible in play, so the editor outlines all of them.
+fn draw_script_triggers(mut gizmos: Gizmos, world_data: Query<&ScriptTriggers>) {
+    for trigger in world_data.iter().flat_map(|triggers| &triggers.0) {
+        outline_zone(&mut gizmos, trigger.zone, 0., TRIGGER_COLOR);
+    }
+}
+
+/// Each platform's path, with a tick every `PATH_TICK_SECS` of travel so the spacing
+/// shows where its easing speeds it up and slows it down.
+fn draw_platform_paths(
+    mut gizmos: Gizmos,
+    platforms: Query<&MovingPlatform>,
+) {
+    let color = Color::srgb(0.5, 0.8, 1.);
+    for platform in &platforms {
+        let path = &platform.path;
+        for leg in 0..path.distinct_legs() {
+            let (from, to) = path.leg(leg);
+            gizmos.line_2d(from, to, color);
+            let across = (to - from).normalize_or_zero().perp() * 4.;
+            let ticks = (path.leg_secs(leg) / PATH_TICK_SECS).floor() as usize;
+            for tick in 0..=ticks {
+                let point = path.point_on(leg, tick as f32 * PATH_TICK_SECS);
+                gizmos.line_2d(point - across, point + across, color);
+            }
+        }
+    }
+}
+
+fn refresh_property_panel(
+    world_data: Query<(&WorldData, &ScriptTriggers)>,
+    editor: Res<Editor>,
+    mut text: Query<&mut Text, With<PropertyText>>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+    let Ok((world, triggers)) = world_data.get_single() else {
+        return;
+    };
+    let mut value = String::from("EDITOR\ndrag: move / resize edges\nDel: delete   Ctrl+D: duplicate\nT: place script trigger\nCtrl+Z / Ctrl+Y: undo / redo\n\n");
+    let rows = match editor.selected {
+        Some(Selection::Block(index)) => world.0.get(index).map(|block| {
+            value += &format!("position  {:.0}, {:.0}\nsize      {:.0} x {:.0}\n",
+                              block.position.x, block.position.y, block.shape.x, block.shape.y);
+            properties(block)
+        }),
+        Some(Selection::Trigger(index)) => triggers.0.get(index).map(|trigger| {
+            let center = match trigger.zone {
+                Zone::Region { position, .. } => position,
+                Zone::Radius { center, .. } => center,
+            };
+            value += &format!("script trigger at {:.0}, {:.0}\n", center.x, center.y);
+            trigger_properties(trigger, editor.naming.is_some())
+        }),
+        None => None,
+    };
+    match rows {
+        Some(rows) => {
+            for (row, (name, property)) in rows.into_iter().enumerate() {
+                let cursor = if row == editor.property { ">" } else { " " };
+                value += &format!("{cursor} {name:<14} < {property} >\n");
+            }
+        }
+        None => value += "click a block or trigger to select it",
+    }
+    for mut text in &mut text {
+        text.sections[0].value = value.clone();
+    }
+}
@@ -0,0 +1,263 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+
+use crate::coin::{spawn_coin, Coin, CoinAssets};
+use crate::events::PlayerDied;
+use crate::level::{LevelState, ResetLevel};
+use crate::movement::MovementConfig;
+use crate::origin::WorldOrigin;
+use crate::physics::{PhysicsSet, Position, Shape};
+use crate::player::Player;
+use crate::world::{spawn_blocks, Block, BlockData, BlockKind, CurrentLevel, MaterialPalette, MergedCollider, WorldData};
+use crate::GameState;
+
+/// The `CurrentLevel` name that plays a generated run instead of a level file.
+pub const ENDLESS_LEVEL: &str = "endless";
+/// How far ahead of the player chunks are kept generated, in pixels.
+const GENERATE_AHEAD: f32 = 1600.;
+/// Chunks whose right edge is this far behind the player are despawned.
+const DESPAWN_BEHIND: f32 = 1200.;
+/// How much of the jump's reach a gap or a step up is allowed to use, so every jump can
+/// be made at walking speed with some room to spare.
+const REACH_MARGIN: f32 = 0.7;
+const MIN_GAP: f32 = 80.;
+/// The furthest a platform drops below the last one.
+const MAX_DROP: f32 = 250.;
+const MIN_PLATFORM_WIDTH: f32 = 160.;
+const MAX_PLATFORM_WIDTH: f32 = 520.;
+const PLATFORM_THICKNESS: f32 = 50.;
+/// Platform tops wander between these, well above the kill plane.
+const LOWEST_TOP: f32 = -700.;
+const HIGHEST_TOP: f32 = 300.;
+/// The start platform, the same as the floor a level without a file gets.
+const START_POSITION: Vec2 = Vec2::new(0., -300.);
+const START_SHAPE: Vec2 = Vec2::new(600., 50.);
+const SPIKE_SHAPE: Vec2 = Vec2::new(60., 20.);
+/// Platforms this narrow never get spikes, so there's always room either side to land.
+const MIN_SPIKED_WIDTH: f32 = 320.;
+const PIXELS_PER_METER: f32 = 64.;
+
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChunkGenerator::new(startup_seed()))
+            .add_systems(OnEnter(GameState::Restarting), start_endless_run.in_set(ResetLevel))
+            .add_systems(FixedUpdate, (
+                (generate_chunks, despawn_old_chunks, measure_distance).after(PhysicsSet::Resolve),
+                restart_on_death,
+            ).run_if(resource_exists::<EndlessDistance>));
+    }
+}
+
+/// A different run each time the game starts, unless a seed is picked on the menu.
+fn startup_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs() % 10_000)
+}
+
+/// SplitMix64: small, fast, and the same numbers from the same seed on every platform.
+struct ChunkRng(u64);
+
+impl ChunkRng {
+    /// Seeded from the run's seed and the chunk's number, so each chunk comes out the same
+    /// however far the run has got when it's generated.
+    fn for_chunk(seed: u64, index: u32) -> Self {
+        let mut rng = Self(seed ^ (u64::from(index) << 32));
+        rng.next_u64();
+        rng
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        crate::flerp(min, max, self.next_f32())
+    }
+
+    fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+}
+
+/// Lays an endless run out one chunk at a time: a gap, then a platform at a new height,
+/// in level coordinates however far the origin has moved. Gaps and steps are kept within what a full jump at walking speed clears, worked out
+/// from the movement config, so however the numbers fall every platform can be reached.
+#[derive(Resource)]
+pub struct ChunkGenerator {
+    pub seed: u64,
+    /// Chance that a gap gets an arc of coins over it.
+    pub coin_chance: f32,
+    /// Chance that a wide enough platform gets spikes in the middle.
+    pub spike_chance: f32,
+    next_chunk: u32,
+    /// Right edge of the last platform generated.
+    end_x: f32,
+    /// Top of the last platform generated.
+    top: f32,
+}
+
+impl ChunkGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            coin_chance: 0.5,
+            spike_chance: 0.25,
+            next_chunk: 0,
+            end_x: START_POSITION.x + START_SHAPE.x / 2.,
+            top: START_POSITION.y + START_SHAPE.y / 2.,
+        }
+    }
+
+    /// Back to the start platform, keeping the seed and tuning.
+    fn restart(&mut self) {
+        *self = Self {
+            coin_chance: self.coin_chance,
+            spike_chance: self.spike_chance,
+            ..Self::new(self.seed)
+        };
+    }
+
+    /// The next chunk's blocks and coins.
+    fn next(&mut self, config: &MovementConfig) -> (Vec<BlockData>, Vec<Vec2>) {
+        let mut rng = ChunkRng::for_chunk(self.seed, self.next_chunk);
+        self.next_chunk += 1;
+        let speed = config.max_speed;
+        let launch = config.jump_strength;
+        let gravity = -config.gravity;
+        let peak = launch * launch / (2. * gravity);
+
+        let rise = rng.range(-MAX_DROP, peak * REACH_MARGIN);
+        let top = (self.top + rise).clamp(LOWEST_TOP, HIGHEST_TOP);
+        // Time in the air from leaving the ledge to coming down onto one `top - self.top`
+        // higher, on a full jump.
+        let step = top - self.top;
+        let airborne = (launch + (launch * launch - 2. * gravity * step).max(0.).sqrt()) / gravity;
+        let reach = speed * airborne * REACH_MARGIN;
+        let gap = rng.range(MIN_GAP, reach.max(MIN_GAP));
+        let width = rng.range(MIN_PLATFORM_WIDTH, MAX_PLATFORM_WIDTH);
+
+        let start_x = self.end_x + gap;
+        let center = Vec2::new(start_x + width / 2., top - PLATFORM_THICKNESS / 2.);
+        let mut blocks = vec![BlockData::new(center, Vec2::new(width, PLATFORM_THICKNESS))];
+        if width >= MIN_SPIKED_WIDTH && rng.chance(self.spike_chance) {
+            blocks.push(BlockData {
+                kind: BlockKind::Spikes,
+                ..BlockData::new(Vec2::new(center.x, top + SPIKE_SHAPE.y / 2.), SPIKE_SHAPE)
+            });
+        }
+        let mut coins = Vec::new();
+        if rng.chance(self.coin_chance) {
+            // Along the middle of the jump, high enough to be caught on the way over.
+            let from = Vec2::new(self.end_x, self.top);
+            for i in 1..=3 {
+                let t = i as f32 / 4.;
+                let arc = peak * 0.6 * (1. - (2. * t - 1.).powi(2));
+                coins.push(Vec2::new(crate::flerp(from.x, start_x, t), crate::flerp(from.y, top, t) + arc + 40.));
+            }
+        }
+        self.end_x = start_x + width;
+        self.top = top;
+        (blocks, coins)
+    }
+}
+
+/// How far the player has got on this run, in meters. Only exists while one is being
+/// played, which is how everything else tells an endless run from a level.
+#[derive(Resource, Default)]
+pub struct EndlessDistance(pub u32);
+
+/// The endless "level" is an empty `WorldData` with only the start platform, spawned by
+/// `init_world`; the rest comes from `generate_chunks` as the player goes.
+fn start_endless_run(mut commands: Commands, level: Res<CurrentLevel>, mut generator: ResMut<ChunkGenerator>) {
+    if level.0 != ENDLESS_LEVEL {
+        commands.remove_resource::<EndlessDistance>();
+        return;
+    }
+    generator.restart();
+    commands.insert_resource(EndlessDistance::default());
+}
+
+/// The start platform for `init_world`.
+pub fn start_platform() -> WorldData {
+    WorldData(vec![BlockData::new(START_POSITION, START_SHAPE)])
+}
+
+/// How far right the player in front is, in level coordinates.
+fn furthest(players: &Query<&Position, With<Player>>, origin: WorldOrigin) -> Option<f32> {
+    players.iter().map(|position| origin.to_level(position.0).x).max_by(f32::total_cmp)
+}
+
+/// One chunk a tick at most, so running fast never stalls a frame on a burst of spawns.
+fn generate_chunks(
+    mut commands: Commands,
+    mut generator: ResMut<ChunkGenerator>,
+    player: Query<&Position, With<Player>>,
+    config: Res<MovementConfig>,
+    coin_assets: Res<CoinAssets>,
+    level_state: Res<LevelState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut palette: ResMut<MaterialPalette>,
+    origin: Res<WorldOrigin>,
+) {
+    let Some(front) = furthest(&player, *origin) else {
+        return;
+    };
+    if generator.end_x > front + GENERATE_AHEAD {
+        return;
+    }
+    let (mut blocks, coins) = generator.next(&config);
+    for block in &mut blocks {
+        block.position = origin.to_live(block.position);
+    }
+    spawn_blocks(&mut commands, &mut meshes, &mut materials, &mut palette, &WorldData(blocks), &level_state);
+    for position in coins {
+        spawn_coin(&mut commands, &coin_assets, origin.to_live(position));
+    }
+}
+
+fn despawn_old_chunks(
+    mut commands: Commands,
+    player: Query<&Position, With<Player>>,
+    old: Query<(Entity, &Position, &Shape), Or<(With<Block>, With<MergedCollider>, With<Coin>)>>,
+) {
+    // Kept for whichever player is further behind.
+    let Some(back) = player.iter().map(|position| position.0.x).min_by(f32::total_cmp) else {
+        return;
+    };
+    for (entity, position, shape) in &old {
+        if position.0.x + shape.0.x / 2. < back - DESPAWN_BEHIND {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// The score is the furthest either player has got to the right of where they started.
+fn measure_distance(mut distance: ResMut<EndlessDistance>, player: Query<&Position, With<Player>>, origin: Res<WorldOrigin>) {
+    let Some(front) = furthest(&player, *origin) else {
+        return;
+    };
+    let meters = (front.max(0.) / PIXELS_PER_METER) as u32;
+    if meters > distance.0 {
+        distance.0 = meters;
+    }
+}
+
+/// There are no checkpoints to go back to, and the ground behind is gone, so dying
+/// starts the run over from the start platform.
+fn restart_on_death(mut died: EventReader<PlayerDied>, mut next_state: ResMut<NextState<GameState>>) {
+    if died.read().count() > 0 {
+        next_state.set(GameState::Restarting);
+    }
+}
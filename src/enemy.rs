@@ -0,0 +1,437 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::boss_bar::ShowBossBar;
+use crate::damage::{apply_damage, finish_dying, Damageable, Dying, HitFlash, Invulnerable};
+use crate::debug::DebugTrackExt;
+use crate::combo::StompCombo;
+use crate::events::{DamageEvent, PlayerDied, Stomped};
+use crate::ground_pound::GroundPound;
+use crate::input::{Action, Actions};
+use crate::level::{restore_snapshots, shelter_respawn, Downed, LevelEntity, ResetLevel, SpawnSnapshot};
+use crate::origin::WorldOrigin;
+use crate::physics::{handle_collisions, move_bodies, segment_hits_aabb, Collider, Collision, Contacts, DynamicBody, Gravitated, LayerMask, PhysicsSet, PhysicsWorld, Position, Rotation, Shape, Velocity, ZOrder};
+use crate::player::{Player, PlayerId, SquashStretch, VisShape};
+use crate::world::WorldData;
+use crate::GameState;
+
+const ENEMY_HEALTH: i32 = 3;
+const LOSE_SIGHT_SECS: f32 = 2.;
+const WAYPOINT_REACHED: f32 = 2.;
+const ENEMY_SQUASH_SNAPPINESS: f32 = 15.;
+const CONTACT_DAMAGE: i32 = 1;
+/// Upward speed the player gets from the first stomp of a combo, in pixels per second.
+const STOMP_BOUNCE: f32 = 864.;
+/// How far below an enemy's top the player's feet can have been last tick and still
+/// count as coming down on it.
+const STOMP_TOLERANCE: f32 = 4.;
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.debug_track::<Enemy>("enemies")
+            .register_type::<AiState>()
+            .add_systems(OnEnter(GameState::Restarting), spawn_enemies.after(ResetLevel))
+            .add_systems(FixedUpdate, ((look_for_player,
+                                       (patrol, chase),
+                                       respect_edges,
+                                       tint_ai_state).chain()
+                .before(move_bodies),
+                turn_at_walls.in_set(PhysicsSet::Resolve).after(handle_collisions),
+                touch_player.after(PhysicsSet::Resolve).before(apply_damage),
+                revive_enemies.after(restore_snapshots).after(finish_dying).before(shelter_respawn),
+            ));
+    }
+}
+
+#[derive(Component)]
+pub struct Enemy;
+
+/// Where an enemy walks while it isn't chasing anything.
+#[derive(Clone, Debug)]
+pub enum PatrolRoute {
+    /// Walk back and forth between two x coordinates.
+    Range { min_x: f32, max_x: f32 },
+    /// Visit each x coordinate in order, then start over.
+    Waypoints(Vec<f32>),
+}
+
+#[derive(Component)]
+pub struct Patrol {
+    pub route: PatrolRoute,
+    pub speed: f32,
+    next: usize,
+    direction: f32,
+}
+
+impl Patrol {
+    pub fn new(route: PatrolRoute, speed: f32) -> Self {
+        Self {
+            route,
+            speed,
+            next: 0,
+            direction: 1.,
+        }
+    }
+}
+
+/// Switches an enemy to pursuing the player once it has an unobstructed line of sight.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ChaseBehavior {
+    pub sight_range: f32,
+    pub speed: f32,
+    /// Reckless chasers happily run off ledges.
+    pub reckless: bool,
+}
+
+/// Stops ground walkers at ledges by probing just ahead of and below their front foot.
+#[derive(Component)]
+pub struct EdgeSensor {
+    pub depth: f32,
+}
+
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub enum AiState {
+    Patrolling,
+    Chasing { unseen_for: f32 },
+}
+
+#[derive(Clone, Debug)]
+pub struct EnemyData {
+    pub position: Vec2,
+    pub shape: Vec2,
+    pub route: PatrolRoute,
+    pub speed: f32,
+    pub chase: Option<ChaseBehavior>,
+    pub boss_bar: Option<ShowBossBar>,
+}
+
+/// Enemies placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component)]
+pub struct EnemySpawns(pub Vec<EnemyData>);
+
+/// Which of the level's `EnemySpawns` an enemy was placed from. Enemies a spawn trigger
+/// brings in don't have one; their trigger decides whether they come back.
+#[derive(Component)]
+struct PlacedEnemy(usize);
+
+#[derive(Bundle)]
+struct EnemyBundle {
+    enemy: Enemy,
+    position: Position,
+    velocity: Velocity,
+    shape: Shape,
+    vis_shape: VisShape,
+    squash_stretch: SquashStretch,
+    rotation: Rotation,
+    z_order: ZOrder,
+    gravitated: Gravitated,
+    dynamic_body: DynamicBody,
+    patrol: Patrol,
+    edge_sensor: EdgeSensor,
+    ai_state: AiState,
+    damageable: Damageable,
+    snapshot: SpawnSnapshot,
+}
+
+impl EnemyBundle {
+    fn new(data: &EnemyData) -> Self {
+        Self {
+            enemy: Enemy,
+            position: Position(data.position),
+            velocity: Velocity(Vec2::ZERO),
+            shape: Shape(data.shape),
+            vis_shape: VisShape(data.shape),
+            squash_stretch: SquashStretch::new(data.shape, ENEMY_SQUASH_SNAPPINESS),
+            rotation: Rotation(0.),
+            z_order: ZOrder(0.05),
+            gravitated: Gravitated,
+            dynamic_body: DynamicBody,
+            patrol: Patrol::new(data.route.clone(), data.speed),
+            edge_sensor: EdgeSensor { depth: 4. },
+            ai_state: AiState::Patrolling,
+            damageable: Damageable::new(ENEMY_HEALTH),
+            snapshot: SpawnSnapshot::new(data.position, Vec2::ZERO).with_health(ENEMY_HEALTH),
+        }
+    }
+}
+
+fn spawn_enemies(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    world_data: Query<&EnemySpawns, With<WorldData>>,
+) {
+    let Ok(spawns) = world_data.get_single() else {
+        return;
+    };
+    for (index, data) in spawns.0.iter().enumerate() {
+        let enemy = spawn_enemy(&mut commands, &mut meshes, &mut materials, data);
+        commands.entity(enemy).insert(PlacedEnemy(index));
+    }
+}
+
+/// Placed enemies go back to their spawn snapshot on respawn like everything else, the
+/// ones the player killed included: one still squashing flat gets back up with the health
+/// `restore_snapshots` gave it, and one that's already gone is spawned again where it was
+/// placed. Runs before `shelter_respawn`, so one placed near the checkpoint is cleared off
+/// it with the rest.
+fn revive_enemies(
+    mut commands: Commands,
+    mut died: EventReader<PlayerDied>,
+    mut placed: Query<(Entity, &PlacedEnemy, Option<&Dying>, &mut SquashStretch, &Shape)>,
+    world_data: Query<&EnemySpawns, With<WorldData>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    origin: Res<WorldOrigin>,
+) {
+    if died.read().count() == 0 {
+        return;
+    }
+    let Ok(spawns) = world_data.get_single() else {
+        return;
+    };
+    let mut standing = vec![false; spawns.0.len()];
+    for (entity, placed, dying, mut squash, shape) in &mut placed {
+        match dying {
+            Some(dying) if dying.finished() => continue,
+            Some(_) => {
+                squash.target = shape.0;
+                commands.entity(entity).remove::<Dying>();
+            }
+            None => {}
+        }
+        if let Some(standing) = standing.get_mut(placed.0) {
+            *standing = true;
+        }
+    }
+    for (index, data) in spawns.0.iter().enumerate().filter(|(index, _)| !standing[*index]) {
+        let data = EnemyData { position: origin.to_live(data.position), ..data.clone() };
+        let enemy = spawn_enemy(&mut commands, &mut meshes, &mut materials, &data);
+        commands.entity(enemy).insert(PlacedEnemy(index));
+    }
+}
+
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    data: &EnemyData,
+) -> Entity {
+    let mut enemy = commands.spawn((
+        EnemyBundle::new(data),
+        ColorMesh2dBundle {
+            mesh: meshes.add(Rectangle::new(data.shape.x, data.shape.y)).into(),
+            // Every enemy owns its material so state tints and hit flashes stay local.
+            material: materials.add(patrol_color()),
+            ..default()
+        },
+        LevelEntity,
+    ));
+    if let Some(chase) = data.chase {
+        enemy.insert(chase);
+    }
+    if let Some(boss_bar) = &data.boss_bar {
+        enemy.insert(boss_bar.clone());
+    }
+    enemy.id()
+}
+
+fn look_for_player(
+    mut enemies: Query<(&Position, &ChaseBehavior, &mut AiState), With<Enemy>>,
+    players: Query<&Position, (With<Player>, Without<Downed>)>,
+    colliders: Query<(&Position, &Shape), With<Collider>>,
+    time: Res<Time>,
+) {
+    for (position, chase, mut state) in &mut enemies {
+        let sees_player = players.iter().any(|player_pos| {
+            position.0.distance(player_pos.0) <= chase.sight_range && colliders.iter().all(|(block_pos, block_shape)| {
+                !segment_hits_aabb(position.0, player_pos.0, Aabb2d::new(block_pos.0, block_shape.0 / 2.))
+            })
+        });
+
+        let next = match (*state, sees_player) {
+            (_, true) => AiState::Chasing { unseen_for: 0. },
+            (AiState::Chasing { unseen_for }, false) => {
+                let unseen_for = unseen_for + time.delta_seconds();
+                if unseen_for >= LOSE_SIGHT_SECS {
+                    AiState::Patrolling
+                } else {
+                    AiState::Chasing { unseen_for }
+                }
+            }
+            (AiState::Patrolling, false) => AiState::Patrolling,
+        };
+        state.set_if_neq(next);
+    }
+}
+
+/// Routes are x coordinates in the level, whatever the origin.
+fn patrol(
+    mut enemies: Query<(&Position, &mut Velocity, &mut Patrol, &AiState), With<Enemy>>,
+    origin: Res<WorldOrigin>,
+) {
+    for (position, mut velocity, mut patrol, state) in &mut enemies {
+        if *state != AiState::Patrolling {
+            continue;
+        }
+        let patrol = &mut *patrol;
+        let x = origin.to_level(position.0).x;
+        match &patrol.route {
+            PatrolRoute::Range { min_x, max_x } => {
+                if x <= *min_x {
+                    patrol.direction = 1.;
+                } else if x >= *max_x {
+                    patrol.direction = -1.;
+                }
+            }
+            PatrolRoute::Waypoints(points) => {
+                if points.is_empty() {
+                    velocity.0.x = 0.;
+                    continue;
+                }
+                let mut target = points[patrol.next % points.len()];
+                if (target - x).abs() < WAYPOINT_REACHED {
+                    patrol.next = (patrol.next + 1) % points.len();
+                    target = points[patrol.next];
+                }
+                patrol.direction = (target - x).signum();
+            }
+        }
+        velocity.0.x = patrol.direction * patrol.speed;
+    }
+}
+
+/// Goes after whichever player is nearest.
+fn chase(
+    mut enemies: Query<(&Position, &mut Velocity, &ChaseBehavior, &AiState), With<Enemy>>,
+    players: Query<&Position, (With<Player>, Without<Downed>)>,
+) {
+    for (position, mut velocity, chase, state) in &mut enemies {
+        let nearest = players.iter()
+            .map(|player_pos| player_pos.0)
+            .min_by(|a, b| a.distance_squared(position.0).total_cmp(&b.distance_squared(position.0)));
+        let Some(player_pos) = nearest else {
+            continue;
+        };
+        if let AiState::Chasing { .. } = state {
+            let offset = player_pos.x - position.0.x;
+            let speed = if offset.abs() < 1. { 0. } else { offset.signum() * chase.speed };
+            velocity.0.x = speed;
+        }
+    }
+}
+
+fn respect_edges(
+    mut enemies: Query<(&Position, &Shape, &mut Velocity, &EdgeSensor, Option<&mut Patrol>, Option<&ChaseBehavior>, &AiState), With<Enemy>>,
+    physics: PhysicsWorld,
+) {
+    for (position, shape, mut velocity, sensor, patrol, chase, state) in &mut enemies {
+        if velocity.0.x == 0. {
+            continue;
+        }
+        let chasing = matches!(state, AiState::Chasing { .. });
+        if chasing && chase.is_some_and(|chase| chase.reckless) {
+            continue;
+        }
+        let half = shape.0 / 2.;
+        let probe = Vec2::new(
+            position.0.x + velocity.0.x.signum() * (half.x + 1.),
+            position.0.y - half.y - sensor.depth,
+        );
+        if physics.overlap_point(probe, LayerMask::ALL).is_some() {
+            continue;
+        }
+        match patrol {
+            Some(mut patrol) if !chasing => {
+                patrol.direction = -patrol.direction;
+                velocity.0.x = patrol.direction * patrol.speed;
+            }
+            _ => velocity.0.x = 0.,
+        }
+    }
+}
+
+/// A patroller walking into a wall turns around, the same as at the end of its range.
+/// Chasers just stay pressed against it.
+fn turn_at_walls(
+    mut enemies: Query<(Entity, &mut Patrol, &AiState), With<Enemy>>,
+    contacts: Res<Contacts>,
+) {
+    for (entity, mut patrol, state) in &mut enemies {
+        if *state != AiState::Patrolling {
+            continue;
+        }
+        let wall = contacts.of(entity)
+            .filter(|contact| matches!(contact.side, Collision::Left | Collision::Right))
+            .map(|contact| contact.side.normal().x)
+            .find(|away| *away * patrol.direction < 0.);
+        if let Some(away) = wall {
+            patrol.direction = away.signum();
+        }
+    }
+}
+
+/// Coming down on an enemy from above kills it and bounces the player off, higher for
+/// each one in a row without landing; touching it any other way hurts the player and
+/// knocks them back.
+pub fn touch_player(
+    mut players: Query<(Entity, &PlayerId, &Position, &mut Velocity, &Shape, &mut StompCombo, Option<&GroundPound>, Has<Invulnerable>), (With<Player>, Without<Downed>)>,
+    enemies: Query<(Entity, &Position, &Shape), (With<Enemy>, Without<Dying>, Without<Player>)>,
+    mut damage: EventWriter<DamageEvent>,
+    mut stomped: EventWriter<Stomped>,
+    actions: Actions,
+    time: Res<Time>,
+) {
+    for (player_entity, id, player_pos, mut velocity, player_shape, mut combo, pound, invulnerable) in &mut players {
+        let player_aabb = Aabb2d::new(player_pos.0, player_shape.0 / 2.);
+        let previous_feet = player_pos.0.y - velocity.0.y * time.delta_seconds() - player_shape.0.y / 2.;
+        for (enemy, position, shape) in &enemies {
+            if !player_aabb.intersects(&Aabb2d::new(position.0, shape.0 / 2.)) {
+                continue;
+            }
+            let top = position.0.y + shape.0.y / 2.;
+            if velocity.0.y < 0. && previous_feet >= top - STOMP_TOLERANCE {
+                damage.send(DamageEvent::lethal(enemy));
+                let (bounce, points) = combo.stomp(actions.player(*id).pressed(Action::Jump));
+                // A ground pound ploughs on through.
+                if !pound.is_some_and(GroundPound::plummeting) {
+                    velocity.0.y = STOMP_BOUNCE * bounce;
+                }
+                stomped.send(Stomped { position: Vec2::new(position.0.x, top), combo: combo.count, points });
+            } else if !invulnerable {
+                damage.send(DamageEvent {
+                    target: player_entity,
+                    amount: CONTACT_DAMAGE,
+                    source_position: Some(position.0),
+                });
+                // One hit per tick, even when wedged between two enemies.
+                break;
+            }
+        }
+    }
+}
+
+fn patrol_color() -> Color {
+    Color::srgb(0.9, 0.6, 0.2)
+}
+
+fn chase_color() -> Color {
+    Color::srgb(0.9, 0.15, 0.15)
+}
+
+fn tint_ai_state(
+    enemies: Query<(&AiState, &Handle<ColorMaterial>), (With<Enemy>, Changed<AiState>, Without<HitFlash>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (state, handle) in &enemies {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = match state {
+                AiState::Patrolling => patrol_color(),
+                AiState::Chasing { .. } => chase_color(),
+            };
+        }
+    }
+}
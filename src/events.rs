@@ -0,0 +1,280 @@
+use bevy::prelude::*;
+
+use crate::physics::Collision;
+
+/// Registers every gameplay event. All of them are sent from `FixedUpdate`, so read them
+/// there too, ordered after the sender listed on each event; a reader in `Update` can see
+/// an event late or miss it when several fixed ticks run in one frame. Presentation
+/// requests (`CameraPunch`, `PlaySfxAt`) live with the systems that serve them.
+pub struct GameEventsPlugin;
+
+impl Plugin for GameEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Jumped>()
+            .add_event::<Landed>()
+            .add_event::<CollisionEvent>()
+            .add_event::<DamageEvent>()
+            .add_event::<Died>()
+            .add_event::<Damaged>()
+            .add_event::<CoinCollected>()
+            .add_event::<PlayerDied>()
+            .add_event::<CheckpointActivated>()
+            .add_event::<InteractEvent>()
+            .add_event::<Respawned>()
+            .add_event::<ScriptTriggerFired>()
+            .add_event::<BlockBroken>()
+            .add_event::<TriggerEnter>()
+            .add_event::<TriggerExit>()
+            .add_event::<LevelComplete>()
+            .add_event::<NewBestTime>()
+            .add_event::<PlayerTeleported>()
+            .add_event::<OriginShifted>()
+            .add_event::<Stomped>()
+            .add_event::<GroundPounded>()
+            .add_event::<ActivationEvent>()
+            .add_event::<DoorOpened>()
+            .add_event::<CrateGrabbed>();
+        #[cfg(feature = "debug-tools")]
+        app.init_resource::<EventLogVerbosity>()
+            .add_systems(FixedPostUpdate, (
+                log_events::<Jumped>,
+                log_events::<Landed>,
+                log_events::<CollisionEvent>,
+                log_events::<DamageEvent>,
+                log_events::<Died>,
+                log_events::<Damaged>,
+                log_events::<CoinCollected>,
+                log_events::<PlayerDied>,
+                log_events::<CheckpointActivated>,
+                log_events::<InteractEvent>,
+                log_events::<Respawned>,
+                log_events::<ScriptTriggerFired>,
+                log_events::<BlockBroken>,
+                log_events::<TriggerEnter>,
+                log_events::<TriggerExit>,
+                log_events::<LevelComplete>,
+                log_events::<NewBestTime>,
+                log_events::<PlayerTeleported>,
+                log_events::<OriginShifted>,
+                (log_events::<Stomped>, log_events::<GroundPounded>, log_events::<ActivationEvent>, log_events::<DoorOpened>, log_events::<CrateGrabbed>),
+            ).run_if(|verbosity: Res<EventLogVerbosity>| verbosity.0 > 0));
+    }
+}
+
+/// A player left the ground under their own power. Sent by `control_player`, before
+/// `gravitate`.
+#[derive(Event, Debug)]
+pub struct Jumped {
+    pub entity: Entity,
+}
+
+/// A body came down on something after being in the air. Sent by `update_ground_contact`
+/// in `PostCollide`.
+#[derive(Event, Debug)]
+pub struct Landed {
+    pub entity: Entity,
+    /// How fast it was falling when it hit, in pixels per second.
+    pub speed: f32,
+}
+
+/// `entity` was pushed out of `other`, touching it on `side`, by `offset`. Sent by
+/// `handle_collisions` for every resolved contact, including snapping down onto the
+/// ground; react to it later in `PhysicsSet::Resolve` or after that set.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CollisionEvent {
+    pub entity: Entity,
+    pub other: Entity,
+    pub side: Collision,
+    #[allow(dead_code)] // Nothing reads how far yet; it's here for breakables and the like.
+    pub offset: Vec2,
+}
+
+/// Every source of harm, player or not, goes through this event. Send it after
+/// `handle_collisions` and before `apply_damage`, which consumes it.
+#[derive(Event, Debug)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: i32,
+    /// Where the hit came from; bodies with a `Velocity` get knocked away from it.
+    pub source_position: Option<Vec2>,
+}
+
+impl DamageEvent {
+    /// Damage that kills whatever it hits, no matter how much health is left.
+    pub fn lethal(target: Entity) -> Self {
+        Self {
+            target,
+            amount: i32::MAX,
+            source_position: None,
+        }
+    }
+
+    pub fn is_lethal(&self) -> bool {
+        self.amount == i32::MAX
+    }
+}
+
+/// A hit got through and took health off `entity`, whether or not it survived. Sent by
+/// `apply_damage`.
+#[derive(Event, Debug)]
+pub struct Damaged {
+    pub entity: Entity,
+    /// The health it took, no more than there was.
+    pub amount: i32,
+}
+
+/// The player picked up the coin at `position`. Sent by `collect_coins`, after
+/// `PhysicsSet::Resolve`.
+#[derive(Event, Debug)]
+pub struct CoinCollected {
+    pub position: Vec2,
+}
+
+/// A player came down on an enemy at `position`, the `combo`th stomp in a row without
+/// landing, for `points`. Sent by `touch_player`, after `PhysicsSet::Resolve`.
+#[derive(Event, Debug)]
+pub struct Stomped {
+    pub position: Vec2,
+    pub combo: u32,
+    pub points: u32,
+}
+
+/// A player's ground pound hit the ground at `position`, where their feet came down. Sent
+/// by `land_ground_pounds` in `PostCollide`.
+#[derive(Event, Debug)]
+pub struct GroundPounded {
+    pub position: Vec2,
+}
+
+/// Something ran out of health. Sent by `apply_damage`.
+#[derive(Event, Debug)]
+pub struct Died {
+    pub entity: Entity,
+}
+
+/// The player died and everything with a `SpawnSnapshot` is about to be put back. Sent
+/// after `apply_damage`, right before the snapshots are restored.
+#[derive(Event, Debug)]
+pub struct PlayerDied;
+
+/// Sent when the player reaches a checkpoint, a safe room or a checkpoint block, after
+/// `PhysicsSet::Resolve`. Anything that respawns with its own state instead of a
+/// `SpawnSnapshot` should re-snapshot itself on this.
+#[derive(Event, Debug)]
+pub struct CheckpointActivated;
+
+/// A player pressed interact while `target`'s prompt was showing. Sent by
+/// `focus_interactable` in `PostCollide`; whatever `target` is decides what happens.
+#[derive(Event, Debug)]
+pub struct InteractEvent {
+    pub target: Entity,
+    /// The player who pressed it.
+    pub player: Entity,
+}
+
+/// The player is back at `position` after dying, clear of whatever was parked on the
+/// checkpoint and briefly protected. Sent by `shelter_respawn`, right after the snapshots
+/// are restored.
+#[derive(Event, Debug)]
+pub struct Respawned {
+    pub position: Vec2,
+}
+
+/// The player walked into a `ScriptTrigger`. Sent by `fire_script_triggers`, after
+/// `move_bodies`. Anything scripted (hints, hazards, cutscenes) reacts by matching `id`.
+#[derive(Event, Debug)]
+pub struct ScriptTriggerFired {
+    pub id: String,
+}
+
+/// A `Breakable` block was smashed, by the player from below, by a projectile or by a
+/// ground pound. Sent by `break_blocks`, after `PhysicsSet::Resolve`, by
+/// `bounce_projectiles` and by `land_ground_pounds`; the block is already queued for despawning.
+#[derive(Event, Debug)]
+pub struct BlockBroken {
+    pub position: Vec2,
+}
+
+/// A dynamic body started overlapping a `TriggerZone`. Sent by `detect_triggers`, after
+/// `PhysicsSet::Resolve`.
+#[derive(Event, Debug)]
+pub struct TriggerEnter {
+    pub zone: Entity,
+    pub body: Entity,
+}
+
+/// A dynamic body stopped overlapping a `TriggerZone` it had entered. Sent by
+/// `detect_triggers`, after `PhysicsSet::Resolve`.
+#[derive(Event, Debug)]
+pub struct TriggerExit {
+    pub zone: Entity,
+    pub body: Entity,
+}
+
+/// The player reached an `Exit` of `level`. Sent by `touch_exits`, after
+/// `PhysicsSet::Resolve`; the fade to the next level starts on the same tick.
+#[derive(Event, Debug)]
+pub struct LevelComplete {
+    pub level: String,
+}
+
+/// The player finished `level` faster than ever before, in `time` seconds. Sent by
+/// `finish_level_timer`, in `FixedPostUpdate`, once the time is stored.
+#[derive(Event, Debug)]
+pub struct NewBestTime {
+    pub level: String,
+    #[allow(dead_code)] // Read by the event log; the ghost only needs to know which level.
+    pub time: f32,
+}
+
+/// A player went through a portal from `from` to `to`. Sent by `use_portals`, after
+/// `detect_triggers`; the player's `Position` is already at `to`.
+#[derive(Event, Debug)]
+pub struct PlayerTeleported {
+    pub entity: Entity,
+    pub from: Vec2,
+    pub to: Vec2,
+}
+
+/// Everything in the world moved by `-offset` to bring the players back near zero, and
+/// `WorldOrigin` moved by `offset`. Sent by `rebase_origin`, in `FixedPostUpdate`, for the
+/// `FollowOrigin` systems to move what they keep outside `Position`.
+#[derive(Event, Debug)]
+pub struct OriginShifted {
+    pub offset: Vec2,
+}
+
+/// Every `Activatable` on `channel` should switch on or off. Sent by `press_plates`, after
+/// `PhysicsSet::Resolve`, when the plates wired to the channel change between all up and
+/// any down.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ActivationEvent {
+    pub channel: u32,
+    pub active: bool,
+}
+
+/// A player with the key unlocked the `Door` at `position`. Sent by `unlock_doors` in
+/// `PostCollide`; the door has already stopped being solid.
+#[derive(Event, Debug)]
+pub struct DoorOpened {
+    pub position: Vec2,
+}
+
+/// A player picked up the crate at `position`. Sent by `grab_or_throw`, after
+/// `focus_interactable`.
+#[derive(Event, Debug)]
+pub struct CrateGrabbed {
+    pub position: Vec2,
+}
+
+/// Raise above 0 to log every gameplay event with its payload.
+#[cfg(feature = "debug-tools")]
+#[derive(Resource, Default)]
+pub struct EventLogVerbosity(pub u8);
+
+#[cfg(feature = "debug-tools")]
+fn log_events<E: Event + std::fmt::Debug>(mut events: EventReader<E>) {
+    for event in events.read() {
+        info!("{event:?}");
+    }
+}
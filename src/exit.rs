@@ -0,0 +1,156 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::events::LevelComplete;
+use crate::input::{Action, Actions};
+use crate::level::Downed;
+use crate::physics::{PhysicsSet, Position, Shape};
+use crate::player::{Player, PlayerId};
+use crate::world::{CurrentLevel, LevelManager, WorldData};
+use crate::GameState;
+
+pub const EXIT_COLOR: Color = Color::srgba(0.95, 0.85, 0.4, 0.5);
+/// Seconds for each half of the fade: to black before the swap, back from it after.
+const FADE_SECS: f32 = 0.4;
+
+pub struct ExitPlugin;
+
+impl Plugin for ExitPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelTransition>()
+            .add_systems(Startup, spawn_transition_overlay)
+            .add_systems(FixedUpdate, touch_exits.after(PhysicsSet::Resolve))
+            .add_systems(Update, run_level_transition)
+            .add_systems(OnEnter(GameState::Menu), cancel_transition);
+    }
+}
+
+/// A level exit from `BlockKind::Exit`. With `press_up` the player has to press up while
+/// inside it, like a door; otherwise touching it is enough. It never collides.
+#[derive(Component)]
+pub struct Exit {
+    pub press_up: bool,
+}
+
+/// The fade between finishing a level and playing the next. Runs on real time, since
+/// the simulation is frozen for the fade out and the next level is playing for the fade in.
+#[derive(Resource, Default)]
+enum LevelTransition {
+    #[default]
+    Idle,
+    FadingOut { elapsed: f32 },
+    FadingIn { elapsed: f32 },
+}
+
+#[derive(Component)]
+struct TransitionOverlay;
+
+fn spawn_transition_overlay(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            ..default()
+        },
+        background_color: Color::NONE.into(),
+        z_index: ZIndex::Global(35),
+        ..default()
+    }, TransitionOverlay));
+}
+
+fn touch_exits(
+    players: Query<(&Position, &Shape, &PlayerId), (With<Player>, Without<Downed>)>,
+    exits: Query<(&Position, &Shape, &Exit)>,
+    actions: Actions,
+    level: Res<CurrentLevel>,
+    mut transition: ResMut<LevelTransition>,
+    mut complete: EventWriter<LevelComplete>,
+) {
+    if !matches!(*transition, LevelTransition::Idle) {
+        return;
+    }
+    // Either player reaching it finishes the level for both.
+    let reached = players.iter().any(|(player_pos, player_shape, id)| {
+        let player_aabb = Aabb2d::new(player_pos.0, player_shape.0 / 2.);
+        exits.iter().any(|(position, shape, exit)| {
+            player_aabb.intersects(&Aabb2d::new(position.0, shape.0 / 2.))
+                && (!exit.press_up || actions.player(*id).just_pressed(Action::MoveUp))
+        })
+    });
+    if reached {
+        *transition = LevelTransition::FadingOut { elapsed: 0. };
+        complete.send(LevelComplete { level: level.0.clone() });
+    }
+}
+
+/// Freezes the level while it fades out, then swaps in the next one: the old level's data
+/// and player are despawned and a restart loads the new level and spawns a fresh player
+/// at its spawn, with the camera bounds and ground heights rebuilt from its `WorldData`.
+/// After the last level there's nothing to swap to, and the win screen takes over instead.
+#[allow(clippy::too_many_arguments)]
+fn run_level_transition(
+    mut commands: Commands,
+    mut transition: ResMut<LevelTransition>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    manager: Res<LevelManager>,
+    mut level: ResMut<CurrentLevel>,
+    loaded: Query<Entity, Or<(With<WorldData>, With<Player>)>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    real_time: Res<Time<Real>>,
+    mut overlay: Query<&mut BackgroundColor, With<TransitionOverlay>>,
+) {
+    // The pause menu holds the fade where it is.
+    if *state.get() != GameState::Playing {
+        return;
+    }
+    let step = real_time.delta_seconds() / FADE_SECS;
+    let alpha = match &mut *transition {
+        LevelTransition::Idle => return,
+        LevelTransition::FadingOut { elapsed } => {
+            virtual_time.pause();
+            *elapsed += step;
+            if *elapsed >= 1. {
+                if let Some(next) = manager.next_after(&level) {
+                    *level = next;
+                    for entity in &loaded {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                    // The restart unpauses the simulation once the new level is in.
+                    next_state.set(GameState::Restarting);
+                    *transition = LevelTransition::FadingIn { elapsed: 0. };
+                } else {
+                    next_state.set(GameState::Won);
+                    *transition = LevelTransition::Idle;
+                }
+                1.
+            } else {
+                *elapsed
+            }
+        }
+        LevelTransition::FadingIn { elapsed } => {
+            *elapsed += step;
+            let alpha = 1. - *elapsed;
+            if alpha <= 0. {
+                *transition = LevelTransition::Idle;
+            }
+            alpha
+        }
+    };
+    for mut color in &mut overlay {
+        color.0 = Color::BLACK.with_alpha(alpha.clamp(0., 1.));
+    }
+}
+
+/// Quitting to the menu mid-fade drops the fade, so the next level starts clear. This
+/// also clears the black left behind by the win screen.
+fn cancel_transition(
+    mut transition: ResMut<LevelTransition>,
+    mut overlay: Query<&mut BackgroundColor, With<TransitionOverlay>>,
+) {
+    *transition = LevelTransition::Idle;
+    for mut color in &mut overlay {
+        color.0 = Color::NONE;
+    }
+}
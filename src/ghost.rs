@@ -0,0 +1,194 @@
+use std::path::PathBuf;
+use std::{fs, io};
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::events::NewBestTime;
+use crate::level::{LevelEntity, ResetLevel};
+use crate::origin::WorldOrigin;
+use crate::physics::{Position, Rotation, ZOrder};
+use crate::player::{Player, PlayerId, VisShape};
+use crate::speedrun::{finish_level_timer, LevelTimer};
+use crate::world::CurrentLevel;
+use crate::GameState;
+
+/// Each level's best run is kept here as `<level>.ron`, next to `saves/times.ron`.
+const GHOST_DIR: &str = "saves/ghosts";
+const GHOST_COLOR: Color = Color::srgba(0.7, 0.85, 1., 0.35);
+/// The longest stretch of an attempt that's recorded. A run past it still counts for the
+/// time, but its ghost stops where the recording does.
+const MAX_REPLAY_SECS: f32 = 600.;
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayBuffer>()
+            .init_resource::<GhostRuns>()
+            .add_systems(OnEnter(GameState::Restarting), spawn_ghost.after(ResetLevel))
+            .add_systems(FixedPostUpdate, (
+                record_replay.before(finish_level_timer),
+                keep_best_replay.after(finish_level_timer),
+                play_ghost,
+            ));
+    }
+}
+
+/// Where the player was on one fixed tick, and how they were drawn.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct ReplayFrame {
+    position: Vec2,
+    size: Vec2,
+    rotation: f32,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Replay {
+    /// Seconds between frames, so a run recorded at one tick rate plays back at the
+    /// right speed at another.
+    tick_secs: f32,
+    frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    /// The frame for `elapsed` seconds into the attempt, or `None` once the recording has
+    /// run out.
+    fn frame_at(&self, elapsed: f32) -> Option<ReplayFrame> {
+        if self.tick_secs <= 0. {
+            return None;
+        }
+        let index = ((elapsed / self.tick_secs).round() as usize).saturating_sub(1);
+        self.frames.get(index).copied()
+    }
+}
+
+/// The attempt in progress, one frame per tick the level timer runs.
+#[derive(Resource, Default)]
+struct ReplayBuffer(Replay);
+
+/// Each level's best replay, read from disk the first time the level is played. `None`
+/// is a level without one.
+#[derive(Resource, Default)]
+struct GhostRuns(HashMap<String, Option<Replay>>);
+
+/// The translucent copy of the player that races them through their best run.
+#[derive(Component)]
+struct Ghost(Replay);
+
+fn ghost_path(level: &str) -> PathBuf {
+    PathBuf::from(GHOST_DIR).join(format!("{level}.ron"))
+}
+
+fn read_ghost(level: &str) -> Option<Replay> {
+    let text = match fs::read_to_string(ghost_path(level)) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return None,
+        Err(error) => {
+            warn!("couldn't read the ghost for {level}: {error}");
+            return None;
+        }
+    };
+    ron::from_str(&text)
+        .inspect_err(|error| warn!("the ghost for {level} is broken: {error}"))
+        .ok()
+}
+
+/// Writes through a temporary file, like the save slots.
+fn write_ghost(level: &str, replay: &Replay) -> io::Result<()> {
+    let text = ron::to_string(replay).map_err(io::Error::other)?;
+    fs::create_dir_all(GHOST_DIR)?;
+    let path = ghost_path(level);
+    let temp = path.with_extension("ron.tmp");
+    fs::write(&temp, text)?;
+    fs::rename(temp, path)
+}
+
+fn spawn_ghost(mut commands: Commands, level: Res<CurrentLevel>, mut runs: ResMut<GhostRuns>) {
+    let replay = runs.0.entry(level.0.clone()).or_insert_with(|| read_ghost(&level.0));
+    let Some(replay) = replay.clone() else {
+        return;
+    };
+    let Some(first) = replay.frames.first().copied() else {
+        return;
+    };
+    commands.spawn((
+        Position(first.position),
+        Rotation(first.rotation),
+        // Just behind the player, so it never covers them.
+        ZOrder(0.09),
+        SpriteBundle {
+            sprite: Sprite {
+                color: GHOST_COLOR,
+                custom_size: Some(first.size),
+                ..default()
+            },
+            ..default()
+        },
+        Ghost(replay),
+        LevelEntity,
+    ));
+}
+
+/// Records while the level timer runs and starts over whenever it does, so the replay
+/// always lines up with the time it would be saved with.
+fn record_replay(
+    mut buffer: ResMut<ReplayBuffer>,
+    timer: Res<LevelTimer>,
+    player: Query<(&Position, &VisShape, &Rotation, &PlayerId), With<Player>>,
+    time: Res<Time>,
+    origin: Res<WorldOrigin>,
+) {
+    if timer.is_waiting() {
+        buffer.0.frames.clear();
+        return;
+    }
+    if !timer.is_running() || timer.elapsed > MAX_REPLAY_SECS {
+        return;
+    }
+    // Only the first player's run is recorded.
+    let Some((position, vis_shape, rotation, _)) = player.iter().find(|(.., id)| **id == PlayerId::ONE) else {
+        return;
+    };
+    buffer.0.tick_secs = time.delta_seconds();
+    buffer.0.frames.push(ReplayFrame {
+        position: origin.to_level(position.0),
+        size: vis_shape.0,
+        rotation: rotation.0,
+    });
+}
+
+fn keep_best_replay(
+    mut new_best: EventReader<NewBestTime>,
+    buffer: Res<ReplayBuffer>,
+    mut runs: ResMut<GhostRuns>,
+) {
+    for event in new_best.read() {
+        if let Err(error) = write_ghost(&event.level, &buffer.0) {
+            warn!("couldn't save the ghost for {}: {error}", event.level);
+        }
+        runs.0.insert(event.level.clone(), Some(buffer.0.clone()));
+    }
+}
+
+/// Follows the level timer rather than counting ticks of its own, so the ghost waits at
+/// the start with the player and carries on through their deaths. It disappears once its
+/// recording runs out, and a recording longer than the attempt is simply cut short.
+fn play_ghost(
+    mut ghosts: Query<(&Ghost, &mut Position, &mut Rotation, &mut Sprite, &mut Visibility)>,
+    timer: Res<LevelTimer>,
+    origin: Res<WorldOrigin>,
+) {
+    for (ghost, mut position, mut rotation, mut sprite, mut visibility) in &mut ghosts {
+        let frame = if timer.is_waiting() { ghost.0.frames.first().copied() } else { ghost.0.frame_at(timer.elapsed) };
+        let Some(frame) = frame else {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        };
+        visibility.set_if_neq(Visibility::Inherited);
+        position.0 = origin.to_live(frame.position);
+        rotation.0 = frame.rotation;
+        sprite.custom_size = Some(frame.size);
+    }
+}
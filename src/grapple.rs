@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::camera::cursor_world_position;
+use crate::cannon::InCannon;
+use crate::cutscene::cutscene_playing;
+use crate::events::OriginShifted;
+use crate::ground_pound::GroundPound;
+use crate::input::{Action, Actions, ScriptedInput};
+use crate::ladder::Climbing;
+use crate::level::Downed;
+use crate::movement::Jumping;
+use crate::origin::FollowOrigin;
+use crate::physics::{project_transforms, LayerMask, PhysicsSet, PhysicsWorld, Position, Velocity};
+use crate::player::{control_player, Facing, Player, PlayerId};
+
+const ROPE_COLOR: Color = Color::srgb(0.85, 0.75, 0.55);
+const ROPE_WIDTH: f32 = 2.;
+/// How far the hook reaches, in pixels.
+const GRAPPLE_RANGE: f32 = 480.;
+/// A hook landing closer than this would leave nothing to swing on, so it doesn't catch.
+const MIN_ROPE_LENGTH: f32 = 24.;
+/// How hard holding left or right pumps a swing, in pixels per second squared. Only the
+/// part along the swing counts, so it can't pull the player off the circle.
+const SWING_PUMP: f32 = 900.;
+
+pub struct GrapplePlugin;
+
+impl Plugin for GrapplePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_rope)
+            .add_systems(FixedUpdate, (
+                fire_grapple.run_if(not(cutscene_playing)).before(control_player),
+                swing.after(PhysicsSet::Integrate).before(PhysicsSet::Resolve),
+            ))
+            .add_systems(FixedPostUpdate, shift_anchors.in_set(FollowOrigin))
+            .add_systems(Update, draw_rope.after(project_transforms));
+    }
+}
+
+/// The player is hanging from a rope hooked at `anchor`. They can't get further than
+/// `length` from it, but can get closer, so the rope goes slack rather than pushing.
+/// `control_player` stands aside while it's attached, and a grappling body lands without
+/// the usual squash.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Grapple {
+    pub anchor: Vec2,
+    pub length: f32,
+}
+
+/// The rope sprite for one player's grapple.
+#[derive(Component)]
+struct Rope(PlayerId);
+
+fn spawn_rope(mut commands: Commands) {
+    for id in [PlayerId::ONE, PlayerId::TWO] {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: ROPE_COLOR,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            Rope(id),
+        ));
+    }
+}
+
+/// Pressing grapple fires the hook toward the mouse, or up and ahead when the mouse is
+/// outside the window, and it catches on the first block in range. Only player one has
+/// the mouse; player two always fires up and the way they face. Letting go drops the rope; the
+/// velocity the swing left is kept as it was.
+pub fn fire_grapple(
+    mut commands: Commands,
+    players: Query<(Entity, &PlayerId, &Position, &Facing, Has<Grapple>), (With<Player>, Without<InCannon>, Without<Climbing>, Without<GroundPound>, Without<Downed>)>,
+    physics: PhysicsWorld,
+    actions: Actions,
+    scripted: Option<Res<ScriptedInput>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<crate::camera::Camera>>,
+) {
+    for (entity, id, position, facing, grappling) in &players {
+        let input = actions.player(*id);
+        if grappling {
+            if !input.pressed(Action::Grapple) {
+                commands.entity(entity).remove::<Grapple>();
+            }
+            continue;
+        }
+        if !input.just_pressed(Action::Grapple) {
+            continue;
+        }
+        let cursor = match &scripted {
+            Some(scripted) => scripted.cursor,
+            None => cursor_world_position(&window, &camera),
+        };
+        let cursor = cursor.filter(|_| *id == PlayerId::ONE);
+        let direction = match cursor {
+            Some(cursor) => (cursor - position.0).normalize_or_zero(),
+            None => Vec2::new(facing.x, 1.).normalize(),
+        };
+        if direction == Vec2::ZERO {
+            continue;
+        }
+        let Some((_, anchor)) = physics.raycast(position.0, position.0 + direction * GRAPPLE_RANGE, LayerMask::BLOCKS) else {
+            continue;
+        };
+        let length = anchor.distance(position.0);
+        if length < MIN_ROPE_LENGTH {
+            continue;
+        }
+        // The rope takes over from a jump still rising.
+        commands.entity(entity).remove::<Jumping>().insert(Grapple { anchor, length });
+    }
+}
+
+fn shift_anchors(mut shifted: EventReader<OriginShifted>, mut grapples: Query<&mut Grapple>) {
+    for event in shifted.read() {
+        for mut grapple in &mut grapples {
+            grapple.anchor -= event.offset;
+        }
+    }
+}
+
+/// Runs after bodies move and before collisions push them out, so the rope and the level
+/// never disagree for more than a tick. A body past the end of its rope is brought back
+/// onto the circle and loses only the speed it had moving away from the anchor.
+fn swing(mut bodies: Query<(&mut Position, &mut Velocity, &Grapple, Option<&PlayerId>)>, actions: Actions, time: Res<Time>) {
+    let dt = time.delta_seconds();
+    for (mut position, mut velocity, grapple, id) in &mut bodies {
+        let offset = position.0 - grapple.anchor;
+        let Some(outward) = offset.try_normalize() else {
+            continue;
+        };
+        let move_x = id.map_or(0., |id| actions.player(*id).move_x());
+        let pump = Vec2::X * move_x * SWING_PUMP;
+        velocity.0 += (pump - outward * pump.dot(outward)) * dt;
+        if offset.length() <= grapple.length {
+            continue;
+        }
+        position.0 = grapple.anchor + outward * grapple.length;
+        let away = velocity.0.dot(outward).max(0.);
+        velocity.0 -= outward * away;
+    }
+}
+
+/// Stretches each rope sprite from its player's drawn position to the anchor.
+fn draw_rope(
+    players: Query<(&PlayerId, &Transform, Option<&Grapple>), (With<Player>, Without<Rope>)>,
+    mut ropes: Query<(&Rope, &mut Transform, &mut Sprite, &mut Visibility)>,
+) {
+    for (rope, mut transform, mut sprite, mut visibility) in &mut ropes {
+        let grappling = players.iter()
+            .find(|(id, ..)| **id == rope.0)
+            .and_then(|(_, player, grapple)| grapple.map(|grapple| (player, grapple)));
+        let Some((player, grapple)) = grappling else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let from = player.translation.truncate();
+        let span = grapple.anchor - from;
+        *visibility = Visibility::Inherited;
+        sprite.custom_size = Some(Vec2::new(span.length(), ROPE_WIDTH));
+        transform.translation = (from + span / 2.).extend(player.translation.z - 0.01);
+        transform.rotation = Quat::from_rotation_z(span.to_angle());
+    }
+}
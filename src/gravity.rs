@@ -0,0 +1,141 @@
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::cutscene::cutscene_playing;
+use crate::events::{TriggerEnter, TriggerExit};
+use crate::input::{Action, Actions};
+use crate::movement::Jumping;
+use crate::physics::{Contacts, Gravitated, Gravity, GravityFlipped, PostCollide};
+use crate::player::{Grounded, Player, PlayerId};
+use crate::trigger::detect_triggers;
+
+pub const GRAVITY_ZONE_COLOR: Color = Color::srgba(0.55, 0.4, 0.9, 0.25);
+pub const GRAVITY_FLIP_COLOR: Color = Color::srgb(0.55, 0.35, 0.85);
+/// The `TriggerZone` tag gravity zones spawn with.
+pub const GRAVITY_ZONE_TAG: &str = "gravity";
+
+pub struct GravityPlugin;
+
+impl Plugin for GravityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, (
+            apply_gravity_zones.after(detect_triggers),
+            flip_gravity.in_set(PostCollide).run_if(not(cutscene_playing)),
+        ));
+    }
+}
+
+/// A trigger region from a `BlockKind::GravityZone` block. Every `Gravitated` body inside
+/// it falls with `gravity` instead of its own, and gets its own back on leaving.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GravityZone {
+    pub gravity: Vec2,
+}
+
+/// A solid block from `BlockKind::GravityFlip` that flips the player's gravity when they
+/// touch it.
+#[derive(Component)]
+pub struct GravityFlipper;
+
+/// The gravity zones a body is inside, the most recently entered last, and the `Gravity`
+/// it had before the first of them, to put back once it's out of them all. The last zone
+/// entered is the one that pulls.
+#[derive(Component, Clone, Debug)]
+pub struct GravityOverride {
+    previous: Option<Vec2>,
+    zones: Vec<(Entity, Vec2)>,
+}
+
+impl GravityOverride {
+    /// Hands the body its own gravity back, for when its zones have gone away without it
+    /// leaving them, as on a restart.
+    pub fn restore(&self, entity: &mut EntityCommands) {
+        match self.previous {
+            Some(gravity) => entity.insert(Gravity(gravity)),
+            None => entity.remove::<Gravity>(),
+        };
+        entity.remove::<GravityOverride>();
+    }
+}
+
+/// A body can cross into one zone and out of another on the same tick, so every change is
+/// worked out on a copy of its override before any of them are applied.
+fn apply_gravity_zones(
+    mut commands: Commands,
+    mut entered: EventReader<TriggerEnter>,
+    mut exited: EventReader<TriggerExit>,
+    zones: Query<&GravityZone>,
+    bodies: Query<(Option<&Gravity>, Option<&GravityOverride>), With<Gravitated>>,
+) {
+    let mut changed: HashMap<Entity, GravityOverride> = HashMap::new();
+    for event in exited.read() {
+        if let Some(working) = working_copy(&mut changed, &bodies, event.body) {
+            working.zones.retain(|(zone, _)| *zone != event.zone);
+        }
+    }
+    for event in entered.read() {
+        let Ok(zone) = zones.get(event.zone) else {
+            continue;
+        };
+        if let Some(working) = working_copy(&mut changed, &bodies, event.body) {
+            working.zones.push((event.zone, zone.gravity));
+        }
+    }
+    for (body, working) in changed {
+        let mut entity = commands.entity(body);
+        match working.zones.last() {
+            Some(&(_, gravity)) => {
+                entity.insert((Gravity(gravity), working));
+            }
+            None => working.restore(&mut entity),
+        }
+    }
+}
+
+/// The override `apply_gravity_zones` is building up for `body`, starting from the one it
+/// has, if it's a body gravity zones act on at all.
+fn working_copy<'a>(
+    changed: &'a mut HashMap<Entity, GravityOverride>,
+    bodies: &Query<(Option<&Gravity>, Option<&GravityOverride>), With<Gravitated>>,
+    body: Entity,
+) -> Option<&'a mut GravityOverride> {
+    let (gravity, current) = bodies.get(body).ok()?;
+    Some(changed.entry(body).or_insert_with(|| current.cloned().unwrap_or(GravityOverride {
+        previous: gravity.map(|gravity| gravity.0),
+        zones: Vec::new(),
+    })))
+}
+
+/// The flip action turns the player's gravity around while they're standing on something,
+/// so it can't be used to hang in mid-air. A flip block does it on touch, once per touch:
+/// staying against one doesn't flip the player back.
+fn flip_gravity(
+    mut commands: Commands,
+    players: Query<(Entity, &PlayerId, &Grounded, Has<GravityFlipped>), With<Player>>,
+    flippers: Query<(), With<GravityFlipper>>,
+    contacts: Res<Contacts>,
+    actions: Actions,
+    mut was_touching: Local<Vec<Entity>>,
+) {
+    for (entity, id, grounded, flipped) in &players {
+        let touching = contacts.of(entity).any(|contact| flippers.contains(contact.other));
+        let touched = touching && !was_touching.contains(&entity);
+        was_touching.retain(|other| *other != entity);
+        if touching {
+            was_touching.push(entity);
+        }
+        let pressed = grounded.0 && actions.player(*id).just_pressed(Action::FlipGravity);
+        if !touched && !pressed {
+            continue;
+        }
+        // A jump in progress was going the old way up, so it's over.
+        let mut entity = commands.entity(entity);
+        entity.remove::<Jumping>();
+        if flipped {
+            entity.remove::<GravityFlipped>();
+        } else {
+            entity.insert(GravityFlipped);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::math::bounding::{Aabb2d, BoundingCircle, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::breakable::{smash_block, Breakable};
+use crate::cannon::InCannon;
+use crate::cutscene::cutscene_playing;
+use crate::damage::{apply_damage, Dying};
+use crate::enemy::Enemy;
+use crate::events::{BlockBroken, DamageEvent, GroundPounded};
+use crate::grapple::{fire_grapple, Grapple};
+use crate::input::{Action, Actions};
+use crate::ladder::{climb, Climbing};
+use crate::level::{Downed, LevelState};
+use crate::movement::Dash;
+use crate::physics::{Contacts, Position, PostCollide, Shape, Up, Velocity};
+use crate::player::{control_player, Grounded, Player, PlayerId, VisShape};
+use crate::spring::launch_from_springs;
+use crate::timer::GameTimer;
+use crate::water::InWater;
+use crate::world::BlockIndex;
+
+/// How long the player hangs in the air before dropping.
+const HANG_SECS: f32 = 0.1;
+/// The plummet's speed in pixels per second, as fast as anything falls.
+const PLUMMET_SPEED: f32 = 1728.;
+/// What the player squashes to on landing from one, flatter than a normal landing.
+const POUND_SQUASH: Vec2 = Vec2::new(100., 60.);
+/// Enemies this close to where the feet hit are flattened too, in pixels.
+pub const IMPACT_RADIUS: f32 = 90.;
+
+pub struct GroundPoundPlugin;
+
+impl Plugin for GroundPoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, (
+            // After anything else that can take the player over in the air, so grabbing a
+            // ladder or a rope on the same press wins.
+            ground_pound.run_if(not(cutscene_playing)).after(climb).after(fire_grapple).before(control_player),
+            land_ground_pounds.in_set(PostCollide).after(launch_from_springs).before(apply_damage),
+        ));
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum PoundPhase {
+    /// Held still in the air with gravity off, for a moment's wind-up.
+    Hang(GameTimer),
+    /// Dropping straight down at `PLUMMET_SPEED` until the feet touch something.
+    Plummet,
+}
+
+/// A ground pound in progress, started by pressing down in the air. `control_player` and
+/// `gravitate` leave a pounding player alone; there's no steering out of one, and once
+/// it's plummeting only landing (or the kill plane) ends it.
+#[derive(Component, Clone, Debug)]
+pub struct GroundPound {
+    pub phase: PoundPhase,
+}
+
+impl GroundPound {
+    pub fn plummeting(&self) -> bool {
+        matches!(self.phase, PoundPhase::Plummet)
+    }
+}
+
+/// Starts a pound on a down press in the air, and drives the one in progress: no sideways
+/// speed ever, none at all while hanging, and the full plummet speed after. Down is the
+/// way the feet point, so upside down it pounds the ceiling.
+fn ground_pound(
+    mut commands: Commands,
+    mut players: Query<(Entity, &PlayerId, &mut Velocity, &Grounded, &Up, &Dash, Option<&mut GroundPound>), (With<Player>, Without<InCannon>, Without<Climbing>, Without<Grapple>, Without<InWater>, Without<Downed>)>,
+    actions: Actions,
+    time: Res<Time>,
+) {
+    for (entity, id, mut velocity, grounded, up, dash, pound) in &mut players {
+        let Some(mut pound) = pound else {
+            if actions.player(*id).just_pressed(Action::MoveDown) && !grounded.0 && !dash.is_dashing() {
+                velocity.0 = Vec2::ZERO;
+                commands.entity(entity).insert(GroundPound { phase: PoundPhase::Hang(GameTimer::once(HANG_SECS)) });
+            }
+            continue;
+        };
+        if let PoundPhase::Hang(timer) = &mut pound.phase {
+            if timer.tick(time.delta_seconds()).finished() {
+                pound.phase = PoundPhase::Plummet;
+            }
+        }
+        velocity.0 = match pound.phase {
+            PoundPhase::Hang(_) => Vec2::ZERO,
+            PoundPhase::Plummet => Vec2::new(0., -PLUMMET_SPEED * up.0),
+        };
+    }
+}
+
+/// Ends a pound on the first tick the feet touch anything. The impact smashes breakable
+/// blocks underfoot and flattens enemies around it; a spring it lands on has already fired
+/// harder for it. Whatever the feet came down on, a slime bounce included, keeps the
+/// velocity it gave.
+#[allow(clippy::too_many_arguments)]
+pub fn land_ground_pounds(
+    mut commands: Commands,
+    mut players: Query<(Entity, &Position, &Shape, &Up, &GroundPound, &mut VisShape), With<Player>>,
+    blocks: Query<(&Position, &Shape, Option<&BlockIndex>), With<Breakable>>,
+    enemies: Query<(Entity, &Position, &Shape), (With<Enemy>, Without<Dying>)>,
+    contacts: Res<Contacts>,
+    mut level_state: ResMut<LevelState>,
+    mut broken: EventWriter<BlockBroken>,
+    mut damage: EventWriter<DamageEvent>,
+    mut pounded: EventWriter<GroundPounded>,
+) {
+    let mut smashed = Vec::new();
+    for (entity, position, shape, up, pound, mut vis_shape) in &mut players {
+        let underfoot: Vec<Entity> = contacts.of(entity).filter(|contact| contact.side == up.feet()).map(|contact| contact.other).collect();
+        if underfoot.is_empty() {
+            continue;
+        }
+        commands.entity(entity).remove::<GroundPound>();
+        // Touching down out of the hang, say onto a platform rising into it, isn't a pound.
+        if !pound.plummeting() {
+            continue;
+        }
+        vis_shape.0 = POUND_SQUASH;
+        for block in underfoot {
+            if smashed.contains(&block) {
+                continue;
+            }
+            let Ok((block_pos, block_shape, index)) = blocks.get(block) else {
+                continue;
+            };
+            smash_block(&mut commands, block, block_pos.0, block_shape.0, index, &mut level_state, &mut broken);
+            smashed.push(block);
+        }
+        let impact = position.0 - Vec2::new(0., shape.0.y / 2. * up.0);
+        let blast = BoundingCircle::new(impact, IMPACT_RADIUS);
+        for (enemy, enemy_pos, enemy_shape) in &enemies {
+            if blast.intersects(&Aabb2d::new(enemy_pos.0, enemy_shape.0 / 2.)) {
+                damage.send(DamageEvent::lethal(enemy));
+            }
+        }
+        pounded.send(GroundPounded { position: impact });
+    }
+}
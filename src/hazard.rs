@@ -0,0 +1,193 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::camera::CameraFrame;
+use crate::damage::{apply_damage, Invulnerable};
+use crate::events::{CheckpointActivated, DamageEvent, PlayerDied, ScriptTriggerFired};
+use crate::level::{LevelEntity, ResetLevel};
+use crate::origin::WorldOrigin;
+use crate::physics::{handle_collisions, move_bodies, Contacts, Position, PostCollide, Rotation, Shape, ZOrder};
+use crate::player::Player;
+use crate::script::fire_script_triggers;
+use crate::world::WorldData;
+use crate::GameState;
+
+/// Hazards span the whole level horizontally.
+const HAZARD_WIDTH: f32 = 20000.;
+/// How far below `start_y` the hazard's bottom edge sits.
+const HAZARD_DEPTH: f32 = 1000.;
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Restarting), spawn_hazards.after(ResetLevel))
+            .add_systems(FixedUpdate, (
+                (trigger_hazards, rise_hazards, hazard_contact.before(apply_damage))
+                    .chain()
+                    .after(move_bodies)
+                    .after(fire_script_triggers)
+                    .before(handle_collisions),
+                (snapshot_hazards, restore_hazards).after(apply_damage),
+                spike_contact.in_set(PostCollide).before(apply_damage),
+            ));
+    }
+}
+
+/// A solid block that kills the player on any side it's touched from, like spikes. A
+/// freshly respawned player gets to touch it safely while `Invulnerable`.
+#[derive(Component)]
+pub struct Hazard;
+
+/// A level-wide hazard whose top edge rises once triggered and kills the player on touch.
+#[derive(Component, Clone, Debug)]
+pub struct RisingHazard {
+    /// Pixels per second.
+    pub speed: f32,
+    pub start_y: f32,
+    pub max_y: Option<f32>,
+    /// The id of the script trigger that starts it rising.
+    pub trigger: String,
+    /// Keep the hazard's surface on screen while it's rising.
+    pub frame_camera: bool,
+}
+
+/// Hazards placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component)]
+pub struct HazardSpawns(pub Vec<RisingHazard>);
+
+/// `top` is a height in the level, like everything in `RisingHazard`; only the hazard's
+/// `Position` is live.
+#[derive(Component, Clone, Copy)]
+struct HazardState {
+    top: f32,
+    active: bool,
+}
+
+/// What a respawn puts the hazard back to; moved forward by checkpoints.
+#[derive(Component)]
+struct HazardSnapshot(HazardState);
+
+fn spawn_hazards(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    world_data: Query<&HazardSpawns, With<WorldData>>,
+) {
+    let Ok(spawns) = world_data.get_single() else {
+        return;
+    };
+    // One unit square per hazard, scaled to its `Shape` as it grows.
+    let mesh = meshes.add(Rectangle::new(1., 1.));
+    let material = materials.add(Color::srgb(1., 0.35, 0.1));
+    for hazard in &spawns.0 {
+        let state = HazardState { top: hazard.start_y, active: false };
+        commands.spawn((
+            hazard.clone(),
+            state,
+            HazardSnapshot(state),
+            Position(Vec2::ZERO),
+            Shape(Vec2::ZERO),
+            Rotation(0.),
+            ZOrder(0.2),
+            ColorMesh2dBundle {
+                mesh: mesh.clone().into(),
+                material: material.clone(),
+                ..default()
+            },
+            LevelEntity,
+        ));
+    }
+}
+
+fn trigger_hazards(
+    mut commands: Commands,
+    mut hazards: Query<(Entity, &RisingHazard, &mut HazardState)>,
+    mut scripts: EventReader<ScriptTriggerFired>,
+) {
+    let fired: Vec<_> = scripts.read().map(|event| event.id.as_str()).collect();
+    for (entity, hazard, mut state) in &mut hazards {
+        if state.active {
+            continue;
+        }
+        if fired.contains(&hazard.trigger.as_str()) {
+            state.active = true;
+            if hazard.frame_camera {
+                commands.entity(entity).insert(CameraFrame);
+            }
+        }
+    }
+}
+
+fn rise_hazards(
+    mut hazards: Query<(&RisingHazard, &mut HazardState, &mut Position, &mut Shape, &mut Transform)>,
+    time: Res<Time>,
+    origin: Res<WorldOrigin>,
+) {
+    for (hazard, mut state, mut position, mut shape, mut transform) in &mut hazards {
+        if state.active {
+            state.top += hazard.speed * time.delta_seconds();
+            if let Some(max_y) = hazard.max_y {
+                state.top = state.top.min(max_y);
+            }
+        }
+        let bottom = hazard.start_y - HAZARD_DEPTH;
+        shape.0 = Vec2::new(HAZARD_WIDTH, state.top - bottom);
+        position.0 = origin.to_live(Vec2::new(0., (state.top + bottom) / 2.));
+        transform.scale = shape.0.extend(1.);
+    }
+}
+
+fn hazard_contact(
+    hazards: Query<(&Position, &Shape), With<RisingHazard>>,
+    players: Query<(Entity, &Position, &Shape), With<Player>>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    for (entity, player_pos, player_shape) in &players {
+        let player_aabb = Aabb2d::new(player_pos.0, player_shape.0 / 2.);
+        if hazards.iter().any(|(position, shape)| Aabb2d::new(position.0, shape.0 / 2.).intersects(&player_aabb)) {
+            damage.send(DamageEvent::lethal(entity));
+        }
+    }
+}
+
+fn spike_contact(
+    players: Query<Entity, (With<Player>, Without<Invulnerable>)>,
+    hazards: Query<(), With<Hazard>>,
+    contacts: Res<Contacts>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    for entity in &players {
+        if contacts.of(entity).any(|contact| hazards.contains(contact.other)) {
+            damage.send(DamageEvent::lethal(entity));
+        }
+    }
+}
+
+fn snapshot_hazards(
+    mut checkpoints: EventReader<CheckpointActivated>,
+    mut hazards: Query<(&HazardState, &mut HazardSnapshot)>,
+) {
+    if checkpoints.read().count() == 0 {
+        return;
+    }
+    for (state, mut snapshot) in &mut hazards {
+        snapshot.0 = *state;
+    }
+}
+
+fn restore_hazards(
+    mut commands: Commands,
+    mut died: EventReader<PlayerDied>,
+    mut hazards: Query<(Entity, &RisingHazard, &mut HazardState, &HazardSnapshot)>,
+) {
+    if died.read().count() == 0 {
+        return;
+    }
+    for (entity, hazard, mut state, snapshot) in &mut hazards {
+        *state = snapshot.0;
+        if !(state.active && hazard.frame_camera) {
+            commands.entity(entity).remove::<CameraFrame>();
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::input::{clear_scripted_input, ScriptedInput};
+use crate::player::PlayerSpawn;
+use crate::sfx::PlaySfxAt;
+use crate::world::WorldData;
+use crate::{GameState, GameplayPlugins};
+
+/// A windowless app running the game's `GameplayPlugins` and nothing that draws, plays
+/// sound or saves, for driving a tick at a time from tests and headless replays. Every
+/// `update` is one 144 Hz fixed tick, and the players only do what `ScriptedInput` says.
+/// Meshes, materials and images are registered as plain assets with no renderer behind
+/// them, so the usual spawning code runs unchanged and its handles just never get drawn.
+///
+/// It sits in `GameState::Loading` with nothing spawned; going to `Restarting` starts the
+/// `CurrentLevel` the way the menu would.
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), InputPlugin, StatesPlugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1. / 144.)))
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .init_asset::<Image>()
+        .init_asset::<TextureAtlasLayout>()
+        .init_state::<GameState>()
+        .init_resource::<ScriptedInput>()
+        // Sent by gameplay for `SfxPlugin`, which isn't here to hear them.
+        .add_event::<PlaySfxAt>()
+        .add_plugins(GameplayPlugins)
+        .add_systems(Last, clear_scripted_input);
+    app
+}
+
+/// `headless_app` playing just `level`'s blocks, with the player at the origin. The
+/// first update spawns it and the next one starts play. Integration tests and tools reach
+/// it as `bevy_platformer::headless::build_headless_app`.
+pub fn build_headless_app(level: WorldData) -> App {
+    let mut app = headless_app();
+    // Already there, so `init_world` leaves it be rather than loading the `CurrentLevel`.
+    app.world_mut().spawn((level, PlayerSpawn::default()));
+    app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Restarting);
+    app
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Action;
+    use crate::physics::{Position, Velocity};
+    use crate::player::{Grounded, Player};
+    use crate::world::BlockData;
+
+    fn player(app: &mut App) -> (Vec2, Vec2, bool) {
+        let mut players = app.world_mut().query_filtered::<(&Position, &Velocity, &Grounded), With<Player>>();
+        let (position, velocity, grounded) = players.single(app.world());
+        (position.0, velocity.0, grounded.0)
+    }
+
+    fn actions(app: &mut App) -> &mut ButtonInput<Action> {
+        &mut app.world_mut().resource_mut::<ScriptedInput>().into_inner().actions[0]
+    }
+
+    #[test]
+    fn lands_on_a_platform() {
+        let mut app = build_headless_app(WorldData(vec![BlockData::new(Vec2::new(0., -200.), Vec2::new(400., 50.))]));
+        for _ in 0..288 {
+            app.update();
+        }
+        let (position, velocity, grounded) = player(&mut app);
+        assert!(grounded);
+        // Half the player's 100px height above the platform's top at -175.
+        assert!((position.y + 125.).abs() < 1., "resting at {position}");
+        assert_eq!(velocity.y, 0.);
+    }
+
+    #[test]
+    fn jumps_over_a_block() {
+        let mut app = build_headless_app(WorldData(vec![
+            BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.)),
+            BlockData::new(Vec2::new(300., -150.), Vec2::new(50., 50.)),
+        ]));
+        for _ in 0..144 {
+            app.update();
+        }
+        actions(&mut app).press(Action::MoveRight);
+        let mut jumped = false;
+        for _ in 0..432 {
+            app.update();
+            let (position, _, grounded) = player(&mut app);
+            if position.x > 400. {
+                actions(&mut app).release(Action::MoveRight);
+            }
+            if !jumped && grounded && position.x > 150. {
+                actions(&mut app).press(Action::Jump);
+                jumped = true;
+            } else {
+                actions(&mut app).release(Action::Jump);
+            }
+        }
+        assert!(jumped);
+        let (position, _, grounded) = player(&mut app);
+        // Past the block's right edge at 325, back on the floor.
+        assert!(position.x > 400., "stopped at {position}");
+        assert!(grounded);
+    }
+}
@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+
+use crate::camera::{Camera, CameraZoom};
+use crate::damage::apply_damage;
+use crate::enemy::Enemy;
+use crate::events::DamageEvent;
+use crate::level::ResetLevel;
+use crate::physics::{project_transforms, Position};
+use crate::GameState;
+
+const IMPACT_FREEZE_FRAMES: u32 = 4;
+/// Rapid hits share this many frozen frames per second instead of stacking into a slideshow.
+const MAX_FROZEN_FRAMES_PER_SEC: u32 = 10;
+const IMPACT_ZOOM: f32 = 0.95;
+/// How far the camera leans toward the impact while frozen.
+const IMPACT_LEAN: f32 = 0.05;
+
+pub struct HitstopPlugin;
+
+impl Plugin for HitstopPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hitstop>()
+            .add_systems(FixedUpdate, hitstop_on_impact.after(apply_damage))
+            .add_systems(Update, run_hitstop.after(project_transforms).run_if(not(in_state(GameState::Paused))))
+            .add_systems(OnEnter(GameState::Restarting), reset_hitstop.in_set(ResetLevel));
+    }
+}
+
+/// Freezes the simulation for a few render frames. Everything on `Time<Virtual>` (and so
+/// every fixed tick) stops; anything reading `Time<Real>` keeps going.
+#[derive(Resource, Default)]
+pub struct Hitstop {
+    frames_left: u32,
+    pending: Option<(Vec2, u32)>,
+    budget_used: u32,
+    budget_window: f32,
+    active: bool,
+    /// The impact the camera leans toward. Re-applied every frozen frame, since
+    /// `project_transforms` puts the camera back on its `Position` each frame.
+    lean_toward: Vec2,
+}
+
+impl Hitstop {
+    pub fn trigger(&mut self, at: Vec2, frames: u32) {
+        let frames = frames.min(MAX_FROZEN_FRAMES_PER_SEC.saturating_sub(self.budget_used));
+        if frames == 0 {
+            return;
+        }
+        let queued = self.pending.map_or(0, |(_, queued)| queued);
+        if frames > queued.max(self.frames_left) {
+            self.pending = Some((at, frames));
+        }
+    }
+}
+
+fn hitstop_on_impact(
+    mut damage: EventReader<DamageEvent>,
+    enemies: Query<&Position, With<Enemy>>,
+    mut hitstop: ResMut<Hitstop>,
+) {
+    for event in damage.read() {
+        if let Ok(position) = enemies.get(event.target) {
+            hitstop.trigger(event.source_position.unwrap_or(position.0), IMPACT_FREEZE_FRAMES);
+        }
+    }
+}
+
+fn reset_hitstop(
+    mut hitstop: ResMut<Hitstop>,
+    mut projection: Query<&mut OrthographicProjection, With<Camera>>,
+) {
+    *hitstop = Hitstop::default();
+    for mut projection in &mut projection {
+        projection.scale = 1.;
+    }
+}
+
+fn run_hitstop(
+    mut hitstop: ResMut<Hitstop>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    real_time: Res<Time<Real>>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection, &CameraZoom, &Position), With<Camera>>,
+) {
+    hitstop.budget_window += real_time.delta_seconds();
+    if hitstop.budget_window >= 1. {
+        hitstop.budget_window = 0.;
+        hitstop.budget_used = 0;
+    }
+
+    if let Some((at, frames)) = hitstop.pending.take() {
+        let extra = frames.saturating_sub(hitstop.frames_left);
+        hitstop.budget_used += extra;
+        hitstop.frames_left += extra;
+        virtual_time.pause();
+        hitstop.active = true;
+        hitstop.lean_toward = at;
+        for (mut transform, mut projection, zoom, position) in &mut camera {
+            projection.scale = IMPACT_ZOOM * zoom.current;
+            lean(&mut transform, position.0, at);
+        }
+        return;
+    }
+
+    if !hitstop.active {
+        return;
+    }
+    for (mut transform, _, _, position) in &mut camera {
+        lean(&mut transform, position.0, hitstop.lean_toward);
+    }
+    hitstop.frames_left = hitstop.frames_left.saturating_sub(1);
+    if hitstop.frames_left == 0 {
+        hitstop.active = false;
+        virtual_time.unpause();
+        for (_, mut projection, zoom, _) in &mut camera {
+            projection.scale = zoom.current;
+        }
+    }
+}
+
+fn lean(transform: &mut Transform, from: Vec2, at: Vec2) {
+    let lean = from.lerp(at, IMPACT_LEAN);
+    transform.translation.x = lean.x;
+    transform.translation.y = lean.y;
+}
@@ -0,0 +1,119 @@
+use std::time::SystemTime;
+
+use bevy::math::bounding::Aabb2d;
+use bevy::prelude::*;
+
+use crate::level::{LevelState, SpawnSnapshot};
+use crate::origin::WorldOrigin;
+use crate::physics::{overlaps, Position, Shape, Velocity};
+use crate::player::Player;
+use crate::trigger::{spawn_trigger_zone, TriggerZone, TriggerZoneData, TriggerZoneSpawns};
+use crate::world::{read_level_file, spawn_blocks, Block, BlockData, CurrentLevel, MaterialPalette, MergedCollider, WorldData};
+use crate::GameState;
+
+/// Seconds between checks of the level file's modification time.
+const POLL_SECS: f32 = 0.5;
+
+/// Picks up edits to the level file being played, made in a text editor while the game
+/// runs, without restarting the attempt.
+pub struct HotReloadPlugin;
+
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelFileWatch>()
+            .add_systems(Update, reload_changed_level.run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))));
+    }
+}
+
+#[derive(Resource, Default)]
+struct LevelFileWatch {
+    /// The file being watched, and when it was last changed as of the last look.
+    path: String,
+    modified: Option<SystemTime>,
+    since_poll: f32,
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Respawns the level's blocks and trigger zones from the file whenever it changes. The
+/// rest of the level (enemies, coins, background) carries on as it is, and blocks broken
+/// this attempt come back, since the file's indices may not mean the same blocks any
+/// more. A file that doesn't parse is logged and the level already spawned is kept.
+///
+/// The players stay where they are, unless a block now stands there; then they go back to
+/// where they'd respawn. The file is in level coordinates, so it's spawned moved by however
+/// far the origin has got.
+fn reload_changed_level(
+    mut commands: Commands,
+    mut watch: ResMut<LevelFileWatch>,
+    level: Res<CurrentLevel>,
+    mut world: Query<(Entity, &mut WorldData, &TriggerZoneSpawns)>,
+    blocks: Query<Entity, Or<(With<Block>, With<MergedCollider>)>>,
+    trigger_zones: Query<Entity, (With<TriggerZone>, Without<Block>)>,
+    mut player: Query<(Entity, &mut Position, &mut Velocity, &Shape, &SpawnSnapshot), With<Player>>,
+    mut level_state: ResMut<LevelState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut palette: ResMut<MaterialPalette>,
+    time: Res<Time<Real>>,
+    origin: Res<WorldOrigin>,
+) {
+    let path = level.path();
+    if watch.path != path {
+        // A different level was started; its file as it is now is what's been spawned.
+        watch.modified = modified_time(&path);
+        watch.path = path;
+        return;
+    }
+    watch.since_poll += time.delta_seconds();
+    if watch.since_poll < POLL_SECS {
+        return;
+    }
+    watch.since_poll = 0.;
+    let modified = modified_time(&path);
+    if modified == watch.modified {
+        return;
+    }
+    watch.modified = modified;
+    let Ok((world_entity, mut world_data, old_triggers)) = world.get_single_mut() else {
+        return;
+    };
+    let (new_blocks, background, triggers) = match read_level_file(&path) {
+        Ok(contents) => (contents.blocks, contents.background, contents.triggers),
+        Err(error) => {
+            error!("couldn't reload {path}, keeping the level as it was: {error}");
+            return;
+        }
+    };
+    // Saving from the block editor writes the file too, with what's already spawned.
+    if new_blocks.0 == world_data.0 && triggers == *old_triggers {
+        return;
+    }
+    info!("reloading {path}");
+    for entity in blocks.iter().chain(&trigger_zones) {
+        commands.entity(entity).despawn_recursive();
+    }
+    level_state.consumed.clear();
+    let live_blocks = WorldData(new_blocks.0.iter()
+        .map(|block| BlockData { position: origin.to_live(block.position), ..block.clone() })
+        .collect());
+    spawn_blocks(&mut commands, &mut meshes, &mut materials, &mut palette, &live_blocks, &level_state);
+    for data in &triggers.0 {
+        spawn_trigger_zone(&mut commands, &TriggerZoneData { position: origin.to_live(data.position), ..data.clone() });
+    }
+    for (entity, mut position, mut velocity, shape, snapshot) in &mut player {
+        let body = Aabb2d::new(position.0, shape.0 / 2.);
+        let buried = live_blocks.0.iter()
+            .filter(|block| block.kind.is_solid())
+            .any(|block| overlaps(body, Aabb2d::new(block.position, block.shape / 2.)));
+        if buried {
+            position.teleport(&mut commands, entity, snapshot.position);
+            velocity.0 = Vec2::ZERO;
+        }
+    }
+    *world_data = new_blocks;
+    // The background is only drawn from on the next restart.
+    commands.entity(world_entity).insert((background, triggers));
+}
@@ -0,0 +1,277 @@
+use bevy::prelude::*;
+
+use crate::coin::{Score, COIN_COLOR};
+use crate::combo::{StompCombo, StompPoints};
+use crate::damage::Damageable;
+use crate::endless::EndlessDistance;
+use crate::key::{key_color, Inventory};
+use crate::player::{Player, PlayerId};
+use crate::speedrun::{format_time, BestTimes, LevelTimer, RECORD_COLOR};
+use crate::world::CurrentLevel;
+use crate::GameState;
+
+/// Room between the HUD and the edges of the window, and between its rows.
+const HUD_MARGIN: f32 = 8.;
+const HUD_ROW_GAP: f32 = 4.;
+const HEART_SIZE: f32 = 16.;
+const HEART_COLOR: Color = Color::srgb(0.9, 0.25, 0.3);
+const EMPTY_HEART_COLOR: Color = Color::srgba(0.3, 0.1, 0.1, 0.6);
+const KEY_ICON_SIZE: Vec2 = Vec2::new(12., 20.);
+/// Extra room between one player's hearts or keys and the other's.
+const ICON_PLAYER_GAP: f32 = 12.;
+const COMBO_COLOR: Color = Color::srgb(1., 0.9, 0.4);
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), spawn_hud)
+            .add_systems(OnExit(GameState::Playing), despawn_hud)
+            .add_systems(Update, (
+                show_hud,
+                update_hearts,
+                update_coins,
+                update_timer,
+                update_key_icons,
+                update_combo,
+            ).run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// On every entity of the HUD, which is only up while playing: the menus, the pause
+/// screen and the gap of a restart tear it down, and the editor hides it. Each slot is
+/// filled from a resource or a player component it reads if it's there, and hidden when
+/// it isn't, so a feature that's left out or not set up yet just leaves no gap.
+#[derive(Component)]
+pub struct HudElement;
+
+#[derive(Component)]
+struct HudRoot;
+
+/// A row of one heart per point of `max_health` on each player that can be hurt.
+#[derive(Component)]
+struct HeartSlot;
+
+#[derive(Component)]
+struct CoinSlot;
+
+#[derive(Component)]
+struct TimerSlot;
+
+/// The row of held keys, each in its door's color.
+#[derive(Component)]
+struct KeySlot;
+
+/// The stomp combo under way, in the top right.
+#[derive(Component)]
+struct ComboSlot;
+
+fn spawn_hud(mut commands: Commands) {
+    let text = |font_size: f32, color: Color| TextBundle::from_section("", TextStyle { font_size, color, ..default() });
+    let row = || NodeBundle {
+        style: Style {
+            column_gap: Val::Px(4.),
+            ..default()
+        },
+        ..default()
+    };
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::SpaceBetween,
+            align_items: AlignItems::FlexStart,
+            padding: UiRect::all(Val::Px(HUD_MARGIN)),
+            ..default()
+        },
+        ..default()
+    }, HudRoot, HudElement)).with_children(|root| {
+        root.spawn((NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(HUD_ROW_GAP),
+                ..default()
+            },
+            ..default()
+        }, HudElement)).with_children(|column| {
+            column.spawn((row(), HeartSlot, HudElement));
+            column.spawn((text(24., COIN_COLOR), CoinSlot, HudElement));
+            let style = TextStyle { font_size: 20., ..default() };
+            column.spawn((TextBundle::from_sections([
+                TextSection::new("", style.clone()),
+                TextSection::new("", TextStyle { color: Color::srgba(1., 1., 1., 0.6), ..style.clone() }),
+                TextSection::new("", TextStyle { color: RECORD_COLOR, ..style }),
+            ]), TimerSlot, HudElement));
+            column.spawn((row(), KeySlot, HudElement));
+        });
+        root.spawn((text(28., COMBO_COLOR), ComboSlot, HudElement));
+    });
+}
+
+fn despawn_hud(mut commands: Commands, hud: Query<Entity, With<HudRoot>>) {
+    for entity in &hud {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Takes a slot out of the layout, rather than just not drawing it, so the rows under it
+/// move up.
+fn show_slot(style: &mut Style, shown: bool) {
+    let display = if shown { Display::Flex } else { Display::None };
+    if style.display != display {
+        style.display = display;
+    }
+}
+
+fn show_hud(
+    #[cfg(feature = "editor")] editor: Option<Res<crate::editor::Editor>>,
+    mut root: Query<&mut Visibility, With<HudRoot>>,
+) {
+    #[cfg(feature = "editor")]
+    let hidden = editor.is_some_and(|editor| editor.open);
+    #[cfg(not(feature = "editor"))]
+    let hidden = false;
+    for mut visibility in &mut root {
+        visibility.set_if_neq(if hidden { Visibility::Hidden } else { Visibility::Inherited });
+    }
+}
+
+/// Rebuilt whenever health changes, or the slot was just spawned. Player one's first.
+fn update_hearts(
+    mut commands: Commands,
+    players: Query<(&PlayerId, &Damageable), With<Player>>,
+    mut slot: Query<(Entity, Ref<HeartSlot>, &mut Style)>,
+    mut shown: Local<Vec<(PlayerId, i32, i32)>>,
+) {
+    let Ok((slot, added, mut style)) = slot.get_single_mut() else {
+        return;
+    };
+    let mut hearts: Vec<(PlayerId, i32, i32)> = players.iter()
+        .map(|(id, damageable)| (*id, damageable.health.max(0), damageable.max_health))
+        .collect();
+    hearts.sort_by_key(|(id, ..)| id.0);
+    show_slot(&mut style, !hearts.is_empty());
+    if hearts == *shown && !added.is_added() {
+        return;
+    }
+    commands.entity(slot).despawn_descendants().with_children(|row| {
+        for (index, (_, health, max)) in hearts.iter().enumerate() {
+            for heart in 0..*max {
+                let gap = if index > 0 && heart == 0 { ICON_PLAYER_GAP } else { 0. };
+                row.spawn((NodeBundle {
+                    style: Style {
+                        width: Val::Px(HEART_SIZE),
+                        height: Val::Px(HEART_SIZE),
+                        margin: UiRect::left(Val::Px(gap)),
+                        ..default()
+                    },
+                    border_radius: BorderRadius::all(Val::Px(HEART_SIZE / 4.)),
+                    background_color: if heart < *health { HEART_COLOR } else { EMPTY_HEART_COLOR }.into(),
+                    ..default()
+                }, HudElement));
+            }
+        }
+    });
+    *shown = hearts;
+}
+
+/// An endless run shows how far it's got, with the coins picked up on the way. Stomp
+/// points only show up once there are some.
+fn update_coins(
+    score: Option<Res<Score>>,
+    points: Option<Res<StompPoints>>,
+    distance: Option<Res<EndlessDistance>>,
+    mut slot: Query<(&mut Text, &mut Style), With<CoinSlot>>,
+) {
+    let Ok((mut text, mut style)) = slot.get_single_mut() else {
+        return;
+    };
+    show_slot(&mut style, score.is_some());
+    let Some(score) = score else {
+        return;
+    };
+    let mut value = match &distance {
+        Some(distance) => format!("{}m  coins {}", distance.0, score.0),
+        None => format!("coins {}", score.0),
+    };
+    if let Some(points) = points.filter(|points| points.0 > 0) {
+        value += &format!("  points {}", points.0);
+    }
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+fn update_timer(
+    timer: Option<Res<LevelTimer>>,
+    best: Option<Res<BestTimes>>,
+    level: Option<Res<CurrentLevel>>,
+    mut slot: Query<(&mut Text, &mut Style), With<TimerSlot>>,
+) {
+    let Ok((mut text, mut style)) = slot.get_single_mut() else {
+        return;
+    };
+    show_slot(&mut style, timer.is_some());
+    let Some(timer) = timer else {
+        return;
+    };
+    text.sections[0].value = format_time(timer.elapsed);
+    text.sections[1].value = match level.and_then(|level| best?.0.get(&level.0).copied()) {
+        Some(best) => format!("  best {}", format_time(best)),
+        None => String::new(),
+    };
+    // Blinks four times a second until the flash runs out.
+    let blink_on = timer.record_flash() > 0. && (timer.record_flash() * 4.).fract() > 0.5;
+    text.sections[2].value = if blink_on { "  NEW RECORD".into() } else { String::new() };
+}
+
+/// Rebuilt whenever what's held changes, or the slot was just spawned: each player's keys
+/// in turn, player one's first.
+fn update_key_icons(
+    mut commands: Commands,
+    inventories: Query<(&PlayerId, &Inventory)>,
+    mut slot: Query<(Entity, Ref<KeySlot>, &mut Style)>,
+    mut shown: Local<Vec<(PlayerId, String)>>,
+) {
+    let Ok((slot, added, mut style)) = slot.get_single_mut() else {
+        return;
+    };
+    let mut held: Vec<(PlayerId, String)> = inventories.iter()
+        .flat_map(|(id, inventory)| inventory.key_ids().map(|key| (*id, key.to_string())))
+        .collect();
+    held.sort_by_key(|(id, _)| id.0);
+    show_slot(&mut style, !held.is_empty());
+    if held == *shown && !added.is_added() {
+        return;
+    }
+    commands.entity(slot).despawn_descendants().with_children(|row| {
+        for (index, (player, id)) in held.iter().enumerate() {
+            let new_player = index > 0 && held[index - 1].0 != *player;
+            row.spawn((NodeBundle {
+                style: Style {
+                    width: Val::Px(KEY_ICON_SIZE.x),
+                    height: Val::Px(KEY_ICON_SIZE.y),
+                    margin: UiRect::left(Val::Px(if new_player { ICON_PLAYER_GAP } else { 0. })),
+                    ..default()
+                },
+                background_color: key_color(id).into(),
+                ..default()
+            }, HudElement));
+        }
+    });
+    *shown = held;
+}
+
+/// Only a combo of two stomps or more shows; the longest going if both players have one.
+fn update_combo(combos: Query<&StompCombo, With<Player>>, mut slot: Query<(&mut Text, &mut Style), With<ComboSlot>>) {
+    let Ok((mut text, mut style)) = slot.get_single_mut() else {
+        return;
+    };
+    let count = combos.iter().map(|combo| combo.count).max().unwrap_or(0);
+    show_slot(&mut style, count >= 2);
+    let value = format!("x{count} combo");
+    if count >= 2 && text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::{fs, io};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::{DynamicEnum, DynamicVariant, TypeInfo, Typed};
+use serde::{Deserialize, Serialize};
+
+use crate::player::PlayerId;
+
+/// Bindings that override the defaults, read once at startup.
+const CONFIG_PATH: &str = "config/input.ron";
+
+/// Something the player can do, bound to keys and gamepad buttons through `InputMap`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Jump,
+    Run,
+    Dash,
+    Fire,
+    Interact,
+    FlipGravity,
+    Grapple,
+    Restart,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Jump,
+        Action::Run,
+        Action::Dash,
+        Action::Fire,
+        Action::Interact,
+        Action::FlipGravity,
+        Action::Grapple,
+        Action::Restart,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::Jump => "Jump",
+            Action::Run => "Run",
+            Action::Dash => "Dash",
+            Action::Fire => "Fire",
+            Action::Interact => "Interact",
+            Action::FlipGravity => "Flip gravity",
+            Action::Grapple => "Grapple",
+            Action::Restart => "Restart",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Binding {
+    pub keys: Vec<KeyCode>,
+    pub buttons: Vec<GamepadButtonType>,
+}
+
+/// Every action's bindings, in the order the controls screen lists them.
+#[derive(Resource, Clone, Debug)]
+pub struct InputMap(pub Vec<(Action, Binding)>);
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let bind = |keys: &[KeyCode], buttons: &[GamepadButtonType]| Binding {
+            keys: keys.to_vec(),
+            buttons: buttons.to_vec(),
+        };
+        Self(vec![
+            (Action::MoveLeft, bind(&[KeyCode::KeyA], &[GamepadButtonType::DPadLeft])),
+            (Action::MoveRight, bind(&[KeyCode::KeyD], &[GamepadButtonType::DPadRight])),
+            (Action::MoveUp, bind(&[KeyCode::KeyW], &[GamepadButtonType::DPadUp])),
+            (Action::MoveDown, bind(&[KeyCode::KeyS], &[GamepadButtonType::DPadDown])),
+            (Action::Jump, bind(&[KeyCode::Space], &[GamepadButtonType::South])),
+            (Action::Run, bind(&[KeyCode::ShiftLeft, KeyCode::ShiftRight], &[GamepadButtonType::LeftTrigger])),
+            (Action::Dash, bind(&[KeyCode::KeyC], &[GamepadButtonType::RightTrigger])),
+            (Action::Fire, bind(&[KeyCode::KeyF], &[GamepadButtonType::West])),
+            (Action::Interact, bind(&[KeyCode::KeyE], &[GamepadButtonType::North])),
+            (Action::FlipGravity, bind(&[KeyCode::KeyG], &[GamepadButtonType::LeftTrigger2])),
+            (Action::Grapple, bind(&[KeyCode::KeyQ], &[GamepadButtonType::RightTrigger2])),
+            (Action::Restart, bind(&[KeyCode::KeyR], &[GamepadButtonType::Select])),
+        ])
+    }
+}
+
+impl InputMap {
+    /// The second player's keys in two-player mode: the arrows to move and the number
+    /// pad for everything else, clear of the first player's side of the keyboard. They
+    /// aren't rebindable.
+    pub fn second_player() -> Self {
+        let bind = |keys: &[KeyCode]| Binding {
+            keys: keys.to_vec(),
+            buttons: Vec::new(),
+        };
+        Self(vec![
+            (Action::MoveLeft, bind(&[KeyCode::ArrowLeft])),
+            (Action::MoveRight, bind(&[KeyCode::ArrowRight])),
+            (Action::MoveUp, bind(&[KeyCode::ArrowUp])),
+            (Action::MoveDown, bind(&[KeyCode::ArrowDown])),
+            (Action::Jump, bind(&[KeyCode::Numpad0, KeyCode::ControlRight])),
+            (Action::Run, bind(&[KeyCode::Numpad1])),
+            (Action::Dash, bind(&[KeyCode::Numpad2])),
+            (Action::Fire, bind(&[KeyCode::Numpad3])),
+            (Action::Interact, bind(&[KeyCode::Numpad5])),
+            (Action::FlipGravity, bind(&[KeyCode::Numpad4])),
+            (Action::Grapple, bind(&[KeyCode::Numpad6])),
+        ])
+    }
+}
+
+/// The second player's keys; the gamepad buttons come from the first player's map.
+#[derive(Resource, Clone, Debug)]
+pub struct SecondPlayerMap(pub InputMap);
+
+impl Default for SecondPlayerMap {
+    fn default() -> Self {
+        Self(InputMap::second_player())
+    }
+}
+
+/// Input tunables that aren't bindings.
+#[derive(Resource, Clone, Debug)]
+pub struct InputConfig {
+    /// How far a stick has to tilt, from 0 to 1, before it moves the player. Tilt past it
+    /// is rescaled so walking still ramps up smoothly from a standstill.
+    pub stick_dead_zone: f32,
+    /// Two players share the machine: the first connected gamepad only drives the first
+    /// player and the second one the second, who also has `SecondPlayerMap`'s keys.
+    pub two_players: bool,
+    /// The first player aims at the mouse cursor instead of the way they're facing.
+    pub mouse_aim: bool,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self { stick_dead_zone: 0.2, two_players: false, mouse_aim: false }
+    }
+}
+
+/// Presses for `Actions` to read instead of the keyboard and gamepads, one set per player,
+/// for driving the game from code. Nothing real is read while it exists. Holding a move
+/// action pushes all the way, like its key would.
+#[derive(Resource, Default)]
+pub struct ScriptedInput {
+    pub actions: [ButtonInput<Action>; 2],
+    /// Each player's stick, for `move_x` and `move_y` while no move action is held.
+    pub sticks: [Vec2; 2],
+    /// Where the first player's mouse points in the world, in place of the real cursor.
+    pub cursor: Option<Vec2>,
+}
+
+/// Ends the frame's presses, the way bevy does for keys, so `just_pressed` only lasts one.
+/// A replay that's run out takes the resource away to hand back to live input.
+pub fn clear_scripted_input(scripted: Option<ResMut<ScriptedInput>>) {
+    let Some(mut scripted) = scripted else {
+        return;
+    };
+    for player in &mut scripted.actions {
+        player.clear();
+    }
+}
+
+/// Key and button presses from frames that ran no fixed tick, which happens all the time
+/// in slow motion, kept for the next tick so it still sees them as just pressed.
+#[derive(Resource, Default)]
+pub struct HeldPresses {
+    keys: HashSet<KeyCode>,
+    buttons: HashSet<GamepadButton>,
+}
+
+/// What `HeldPresses` had when the current fixed tick started. It's only filled in
+/// during one, so the menus and everything else in `Update` never see a press twice.
+#[derive(Resource, Default)]
+pub struct TickPresses(HeldPresses);
+
+/// Runs in `PreUpdate`, after bevy's input. A simulation that's stopped outright has no
+/// tick to hold presses for; they're the pause menu's, and gone once it closes.
+pub fn hold_presses(
+    mut held: ResMut<HeldPresses>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    virtual_time: Res<Time<Virtual>>,
+) {
+    if virtual_time.is_paused() {
+        *held = HeldPresses::default();
+        return;
+    }
+    held.keys.extend(keys.get_just_pressed().copied());
+    held.buttons.extend(buttons.get_just_pressed().copied());
+}
+
+pub fn start_tick_presses(mut held: ResMut<HeldPresses>, mut tick: ResMut<TickPresses>) {
+    tick.0 = std::mem::take(&mut *held);
+}
+
+pub fn end_tick_presses(mut tick: ResMut<TickPresses>) {
+    tick.0 = HeldPresses::default();
+}
+
+/// One action's entry in `config/input.ron`, by name: keys as `key_name` writes them
+/// ("A", "Space", "1") or in full ("KeyA"), buttons as bevy spells them ("South"). The
+/// settings file stores rebinds made in game the same way.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BindingNames {
+    keys: Vec<String>,
+    buttons: Vec<String>,
+}
+
+impl InputMap {
+    pub fn binding(&self, action: Action) -> Option<&Binding> {
+        self.0.iter().find(|(a, _)| *a == action).map(|(_, binding)| binding)
+    }
+
+    /// The defaults with any actions listed in `config/input.ron` rebound, e.g.
+    /// `{ Jump: (keys: ["Space"], buttons: ["South"]) }`. A missing file is just the
+    /// defaults; a broken one, or a name that isn't a key or button, gets a warning.
+    pub fn load() -> Self {
+        let mut map = Self::default();
+        let text = match fs::read_to_string(CONFIG_PATH) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return map,
+            Err(error) => {
+                warn!("couldn't read {CONFIG_PATH}: {error}");
+                return map;
+            }
+        };
+        let overrides: HashMap<Action, BindingNames> = match ron::from_str(&text) {
+            Ok(overrides) => overrides,
+            Err(error) => {
+                warn!("{CONFIG_PATH} is broken, using the default bindings: {error}");
+                return map;
+            }
+        };
+        map.rebind(|action| overrides.get(&action), CONFIG_PATH);
+        map
+    }
+
+    /// Rebinds every action `overrides` has names for, warning about ones from `source`
+    /// that aren't a key or button.
+    pub fn rebind<'a>(&mut self, overrides: impl Fn(Action) -> Option<&'a BindingNames>, source: &str) {
+        for (action, binding) in &mut self.0 {
+            let Some(names) = overrides(*action) else {
+                continue;
+            };
+            binding.keys = names.keys.iter().filter_map(|name| {
+                let key = parse_key(name);
+                if key.is_none() {
+                    warn!("{source}: {action:?} is bound to unknown key {name:?}, ignoring it");
+                }
+                key
+            }).collect();
+            binding.buttons = names.buttons.iter().filter_map(|name| {
+                let button = unit_variant::<GamepadButtonType>(name);
+                if button.is_none() {
+                    warn!("{source}: {action:?} is bound to unknown button {name:?}, ignoring it");
+                }
+                button
+            }).collect();
+        }
+    }
+
+    /// Every binding by its full name, for `rebind` to read back.
+    pub fn names(&self) -> BTreeMap<Action, BindingNames> {
+        self.0.iter().map(|(action, binding)| (*action, BindingNames {
+            keys: binding.keys.iter().map(|key| format!("{key:?}")).collect(),
+            buttons: binding.buttons.iter().map(|button| format!("{button:?}")).collect(),
+        })).collect()
+    }
+}
+
+/// The field-less variant of `T` called `name`.
+fn unit_variant<T: FromReflect + Typed>(name: &str) -> Option<T> {
+    let TypeInfo::Enum(info) = T::type_info() else {
+        return None;
+    };
+    info.variant(name)?;
+    T::from_reflect(&DynamicEnum::new(name, DynamicVariant::Unit))
+}
+
+/// The inverse of `key_name`, which also takes the full variant name.
+fn parse_key(name: &str) -> Option<KeyCode> {
+    unit_variant(name)
+        .or_else(|| unit_variant(&format!("Key{name}")))
+        .or_else(|| unit_variant(&format!("Digit{name}")))
+}
+
+/// Reads actions instead of raw keys, across the keyboard and every connected gamepad.
+/// In two-player mode that's the first player's half of them; `player` reads either.
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    map: Res<'w, InputMap>,
+    second_map: Res<'w, SecondPlayerMap>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    buttons: Res<'w, ButtonInput<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    gamepads: Res<'w, Gamepads>,
+    config: Res<'w, InputConfig>,
+    tick_presses: Res<'w, TickPresses>,
+    scripted: Option<Res<'w, ScriptedInput>>,
+}
+
+impl Actions<'_> {
+    /// The first player's press, for actions that aren't any one player's, like restart.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.player(PlayerId::ONE).just_pressed(action)
+    }
+
+    /// One player's keys and gamepad. Playing alone, the first player has every gamepad;
+    /// with two, the gamepads are handed out in the order they were connected.
+    pub fn player(&self, id: PlayerId) -> PlayerInput<'_> {
+        let map = if id == PlayerId::ONE { &self.map } else { &self.second_map.0 };
+        let gamepads = if self.config.two_players {
+            let mut connected: Vec<Gamepad> = self.gamepads.iter().collect();
+            connected.sort_by_key(|gamepad| gamepad.id);
+            PlayerGamepads::One(connected.get(id.0 as usize).copied())
+        } else if id == PlayerId::ONE {
+            PlayerGamepads::All(&self.gamepads)
+        } else {
+            PlayerGamepads::One(None)
+        };
+        PlayerInput {
+            map,
+            buttons_from: &self.map,
+            keys: &self.keys,
+            buttons: &self.buttons,
+            axes: &self.axes,
+            gamepads,
+            config: &self.config,
+            held: &self.tick_presses.0,
+            scripted: self.scripted.as_ref().and_then(|scripted| Some((scripted.actions.get(id.0 as usize)?, *scripted.sticks.get(id.0 as usize)?))),
+        }
+    }
+}
+
+enum PlayerGamepads<'a> {
+    All(&'a Gamepads),
+    One(Option<Gamepad>),
+}
+
+impl PlayerGamepads<'_> {
+    fn iter(&self) -> impl Iterator<Item = Gamepad> + '_ {
+        let (all, one) = match self {
+            PlayerGamepads::All(gamepads) => (Some(gamepads.iter()), None),
+            PlayerGamepads::One(gamepad) => (None, *gamepad),
+        };
+        all.into_iter().flatten().chain(one)
+    }
+}
+
+/// The actions of one player, from `Actions::player`.
+pub struct PlayerInput<'a> {
+    map: &'a InputMap,
+    /// Gamepad buttons are the same for both players.
+    buttons_from: &'a InputMap,
+    keys: &'a ButtonInput<KeyCode>,
+    buttons: &'a ButtonInput<GamepadButton>,
+    axes: &'a Axis<GamepadAxis>,
+    gamepads: PlayerGamepads<'a>,
+    config: &'a InputConfig,
+    held: &'a HeldPresses,
+    scripted: Option<(&'a ButtonInput<Action>, Vec2)>,
+}
+
+impl PlayerInput<'_> {
+    pub fn pressed(&self, action: Action) -> bool {
+        if let Some((scripted, _)) = self.scripted {
+            return scripted.pressed(action);
+        }
+        self.any(action, |keys, key| keys.pressed(key), |buttons, button| buttons.pressed(button))
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        if let Some((scripted, _)) = self.scripted {
+            return scripted.just_pressed(action);
+        }
+        self.any(action, |keys, key| keys.just_pressed(key) || self.held.keys.contains(&key),
+            |buttons, button| buttons.just_pressed(button) || self.held.buttons.contains(&button))
+    }
+
+    /// Horizontal movement from -1 to 1. The move bindings push all the way; otherwise the
+    /// left stick tilted furthest, on any of the player's gamepads, pushes as far as it's
+    /// tilted past the dead zone.
+    pub fn move_x(&self) -> f32 {
+        self.move_axis(Action::MoveRight, Action::MoveLeft, GamepadAxisType::LeftStickX)
+    }
+
+    /// Vertical movement from -1 (down) to 1 (up), read the same way as `move_x`.
+    pub fn move_y(&self) -> f32 {
+        self.move_axis(Action::MoveUp, Action::MoveDown, GamepadAxisType::LeftStickY)
+    }
+
+    fn move_axis(&self, positive: Action, negative: Action, axis: GamepadAxisType) -> f32 {
+        let (positive, negative) = (self.pressed(positive), self.pressed(negative));
+        if positive || negative {
+            return (positive as i8 - negative as i8) as f32;
+        }
+        if let Some((_, stick)) = self.scripted {
+            let stick = if axis == GamepadAxisType::LeftStickX { stick.x } else { stick.y };
+            return stick.clamp(-1., 1.);
+        }
+        let stick = self.gamepads.iter()
+            .filter_map(|gamepad| self.axes.get(GamepadAxis::new(gamepad, axis)))
+            .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+            .unwrap_or(0.);
+        let dead_zone = self.config.stick_dead_zone.clamp(0., 0.99);
+        let tilt = ((stick.abs() - dead_zone) / (1. - dead_zone)).clamp(0., 1.);
+        tilt * stick.signum()
+    }
+
+    fn any(
+        &self,
+        action: Action,
+        key_check: impl Fn(&ButtonInput<KeyCode>, KeyCode) -> bool,
+        button_check: impl Fn(&ButtonInput<GamepadButton>, GamepadButton) -> bool,
+    ) -> bool {
+        let keys = self.map.binding(action).is_some_and(|binding| binding.keys.iter().any(|key| key_check(self.keys, *key)));
+        keys || self.buttons_from.binding(action).is_some_and(|binding| self.gamepads.iter().any(|gamepad| binding.buttons.iter()
+            .any(|button| button_check(self.buttons, GamepadButton::new(gamepad, *button)))))
+    }
+}
+
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+pub fn button_name(button: GamepadButtonType) -> String {
+    let name = match button {
+        GamepadButtonType::South => "A / Cross",
+        GamepadButtonType::East => "B / Circle",
+        GamepadButtonType::North => "Y / Triangle",
+        GamepadButtonType::West => "X / Square",
+        GamepadButtonType::C => "C",
+        GamepadButtonType::Z => "Z",
+        GamepadButtonType::LeftTrigger => "LB / L1",
+        GamepadButtonType::LeftTrigger2 => "LT / L2",
+        GamepadButtonType::RightTrigger => "RB / R1",
+        GamepadButtonType::RightTrigger2 => "RT / R2",
+        GamepadButtonType::Select => "Select",
+        GamepadButtonType::Start => "Start",
+        GamepadButtonType::Mode => "Home",
+        GamepadButtonType::LeftThumb => "Left stick",
+        GamepadButtonType::RightThumb => "Right stick",
+        GamepadButtonType::DPadUp => "D-pad up",
+        GamepadButtonType::DPadDown => "D-pad down",
+        GamepadButtonType::DPadLeft => "D-pad left",
+        GamepadButtonType::DPadRight => "D-pad right",
+        GamepadButtonType::Other(id) => return format!("Button {id}"),
+    };
+    name.to_string()
+}
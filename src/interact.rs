@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+
+use crate::crates::Carrying;
+use crate::cutscene::ActiveCutscene;
+use crate::events::InteractEvent;
+use crate::input::{button_name, key_name, Action, Actions, InputMap};
+use crate::physics::{Position, PostCollide, Shape};
+use crate::level::Downed;
+use crate::player::{Player, PlayerId};
+
+/// How much closer another interactable has to be before it takes the prompt over, so
+/// two equally distant ones don't trade it back and forth.
+const FOCUS_HYSTERESIS: f32 = 10.;
+/// Gap between the top of the focused entity and its prompt.
+const PROMPT_GAP: f32 = 24.;
+const PROMPT_Z: f32 = 5.;
+
+pub struct InteractPlugin;
+
+impl Plugin for InteractPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InteractFocus>()
+            .add_systems(Startup, spawn_prompt)
+            .add_systems(FixedUpdate, focus_interactable.in_set(PostCollide))
+            .add_systems(Update, show_prompt);
+    }
+}
+
+/// Something the player can use with the interact key from within `radius` of it.
+#[derive(Component, Clone, Debug)]
+pub struct Interactable {
+    /// What interacting does, e.g. "Pick up". The bound key is added in front.
+    pub prompt: String,
+    pub radius: f32,
+}
+
+/// The one interactable whose prompt is showing, if any.
+#[derive(Resource, Default)]
+pub struct InteractFocus(pub Option<Entity>);
+
+#[derive(Component)]
+struct Prompt;
+
+fn spawn_prompt(mut commands: Commands) {
+    commands.spawn((Text2dBundle {
+        text: Text::from_section("", TextStyle {
+            font_size: 18.,
+            ..default()
+        }),
+        visibility: Visibility::Hidden,
+        ..default()
+    }, Prompt));
+}
+
+/// Picks the interactable nearest to either player and turns interact presses into
+/// `InteractEvent`s for it, from whichever player pressed in range of it. Nothing gets
+/// focus from a player whose hands are full.
+pub fn focus_interactable(
+    players: Query<(Entity, &Position, &PlayerId, Has<Carrying>), (With<Player>, Without<Downed>)>,
+    interactables: Query<(Entity, &Position, &Interactable)>,
+    mut focus: ResMut<InteractFocus>,
+    mut events: EventWriter<InteractEvent>,
+    actions: Actions,
+) {
+    let free: Vec<_> = players.iter()
+        .filter(|(.., carrying)| !carrying)
+        .map(|(entity, position, id, _)| (entity, position.0, *id))
+        .collect();
+    let reach = |entity: Entity, player_pos: Vec2| {
+        interactables.get(entity).ok()
+            .map(|(_, position, interactable)| (position.0.distance(player_pos), interactable.radius))
+            .filter(|(distance, radius)| distance <= radius)
+            .map(|(distance, _)| distance)
+    };
+    let in_range = |entity: Entity| {
+        free.iter()
+            .filter_map(|(_, player_pos, _)| reach(entity, *player_pos))
+            .min_by(f32::total_cmp)
+    };
+    let nearest = interactables.iter()
+        .filter_map(|(entity, ..)| in_range(entity).map(|distance| (entity, distance)))
+        .min_by(|(a, a_distance), (b, b_distance)| a_distance.total_cmp(b_distance).then(a.cmp(b)));
+    let current = focus.0.and_then(|entity| in_range(entity).map(|distance| (entity, distance)));
+    let next = match (current, nearest) {
+        (Some((current, distance)), Some((_, nearest))) if distance <= nearest + FOCUS_HYSTERESIS => Some(current),
+        (_, nearest) => nearest.map(|(entity, _)| entity),
+    };
+    focus.0 = next;
+
+    let Some(target) = focus.0 else {
+        return;
+    };
+    let user = free.iter().find(|(_, player_pos, id)| {
+        actions.player(*id).just_pressed(Action::Interact) && reach(target, *player_pos).is_some()
+    });
+    if let Some((player, ..)) = user {
+        events.send(InteractEvent { target, player: *player });
+    }
+}
+
+/// Floats the prompt over the focused entity. It's hidden during cutscenes and while
+/// anything (a menu, the editor) has paused the game.
+fn show_prompt(
+    focus: Res<InteractFocus>,
+    targets: Query<(&Transform, &Interactable, Option<&Shape>), Without<Prompt>>,
+    mut prompt: Query<(&mut Text, &mut Transform, &mut Visibility), With<Prompt>>,
+    map: Res<InputMap>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    time: Res<Time<Virtual>>,
+    cutscene: Res<ActiveCutscene>,
+    mut using_gamepad: Local<bool>,
+) {
+    if buttons.get_just_pressed().next().is_some() {
+        *using_gamepad = true;
+    } else if keys.get_just_pressed().next().is_some() {
+        *using_gamepad = false;
+    }
+    let Ok((mut text, mut transform, mut visibility)) = prompt.get_single_mut() else {
+        return;
+    };
+    let shown = focus.0
+        .and_then(|entity| targets.get(entity).ok())
+        .filter(|_| !time.is_paused() && !cutscene.is_playing());
+    let Some((target, interactable, shape)) = shown else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let binding = map.binding(Action::Interact);
+    let input = if *using_gamepad {
+        binding.and_then(|binding| binding.buttons.first()).map(|button| button_name(*button))
+    } else {
+        binding.and_then(|binding| binding.keys.first()).map(|key| key_name(*key))
+    };
+    let value = match input {
+        Some(input) => format!("[{input}] {}", interactable.prompt),
+        None => interactable.prompt.clone(),
+    };
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+    let height = shape.map_or(0., |shape| shape.0.y);
+    transform.translation = target.translation.truncate()
+        .extend(PROMPT_Z) + Vec3::new(0., height / 2. + PROMPT_GAP, 0.);
+    *visibility = Visibility::Visible;
+}
@@ -0,0 +1,281 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::events::{DoorOpened, OriginShifted, PlayerDied};
+use crate::level::{Downed, LevelEntity, ResetLevel};
+use crate::origin::{FollowOrigin, WorldOrigin};
+use crate::particles::spawn_ring;
+use crate::physics::{Collider, Contacts, PhysicsSet, PostCollide, Position, Rotation, Shape, ZOrder};
+use crate::pickup::Bobbing;
+use crate::player::Player;
+use crate::timer::GameTimer;
+use crate::world::WorldData;
+use crate::GameState;
+
+const KEY_SIZE: Vec2 = Vec2::new(16., 28.);
+const BOB_HEIGHT: f32 = 4.;
+const COLLECT_RING_RADIUS: f32 = 30.;
+const COLLECT_RING_DOTS: usize = 10;
+const COLLECT_RING_SECS: f32 = 0.25;
+/// How long a door takes to slide up out of the way once it's unlocked.
+const DOOR_OPEN_SECS: f32 = 0.4;
+
+pub struct KeyPlugin;
+
+impl Plugin for KeyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Restarting), (
+                clear_inventories.in_set(ResetLevel),
+                spawn_keys_and_doors.after(ResetLevel),
+            ))
+            .add_systems(FixedUpdate, (
+                collect_keys.after(PhysicsSet::Resolve),
+                unlock_doors.in_set(PostCollide),
+                open_doors,
+                return_lost_keys,
+            ))
+            .add_systems(FixedPostUpdate, shift_openings.in_set(FollowOrigin));
+    }
+}
+
+/// One key from the level file. It opens every door with the same `id`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct KeyData {
+    pub position: Vec2,
+    pub id: String,
+    /// Goes back where it was found when the players die, unless it's already opened a
+    /// door. Otherwise it's kept until the level is restarted.
+    #[serde(default)]
+    pub lost_on_death: bool,
+}
+
+/// One locked door from the level file.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct DoorData {
+    pub position: Vec2,
+    pub shape: Vec2,
+    pub id: String,
+}
+
+/// Keys placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct KeySpawns(pub Vec<KeyData>);
+
+/// Locked doors placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct DoorSpawns(pub Vec<DoorData>);
+
+/// A key waiting to be picked up. Like a coin, it's collected by overlapping it.
+#[derive(Component)]
+pub struct Key(KeyData);
+
+/// A solid block until a player holding its key touches it.
+#[derive(Component)]
+pub struct Door {
+    pub id: String,
+}
+
+/// An unlocked door sliding up into its top edge, despawned once it's gone.
+#[derive(Component)]
+struct Opening {
+    top: f32,
+    height: f32,
+    timer: GameTimer,
+}
+
+/// A key a player is carrying.
+#[derive(Clone, Debug)]
+struct HeldKey {
+    data: KeyData,
+    /// Whether it's opened a door yet, after which dying never takes it back.
+    used: bool,
+}
+
+/// The keys a player has picked up this attempt, in the order they got them.
+#[derive(Component, Default)]
+pub struct Inventory {
+    keys: Vec<HeldKey>,
+}
+
+impl Inventory {
+    /// The id of every key held, in the order they were picked up.
+    pub fn key_ids(&self) -> impl Iterator<Item = &str> {
+        self.keys.iter().map(|key| key.data.id.as_str())
+    }
+}
+
+/// A key and its doors share a color worked out from their id, so a level can say
+/// which key goes where without saying it. FNV-1a, to land the same on every machine.
+fn key_hue(id: &str) -> f32 {
+    let hash = id.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193));
+    (hash % 360) as f32
+}
+
+pub fn key_color(id: &str) -> Color {
+    Color::hsl(key_hue(id), 0.8, 0.6)
+}
+
+fn door_color(id: &str) -> Color {
+    Color::hsl(key_hue(id), 0.45, 0.35)
+}
+
+fn clear_inventories(mut inventories: Query<&mut Inventory>) {
+    for mut inventory in &mut inventories {
+        *inventory = Inventory::default();
+    }
+}
+
+fn spawn_keys_and_doors(mut commands: Commands, world_data: Query<(&KeySpawns, &DoorSpawns), With<WorldData>>) {
+    let Ok((keys, doors)) = world_data.get_single() else {
+        return;
+    };
+    for data in &keys.0 {
+        spawn_key(&mut commands, data, data.position);
+    }
+    for data in &doors.0 {
+        commands.spawn((
+            Door { id: data.id.clone() },
+            Collider,
+            Position(data.position),
+            Shape(data.shape),
+            Rotation(0.),
+            ZOrder(0.),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: door_color(&data.id),
+                    custom_size: Some(data.shape),
+                    ..default()
+                },
+                ..default()
+            },
+            LevelEntity,
+        ));
+    }
+}
+
+/// `data` keeps the level's coordinates for the key; `position` is where it goes live.
+fn spawn_key(commands: &mut Commands, data: &KeyData, position: Vec2) {
+    commands.spawn((
+        Key(data.clone()),
+        Bobbing::new(position, BOB_HEIGHT),
+        Position(position),
+        Shape(KEY_SIZE),
+        Rotation(0.),
+        ZOrder(0.08),
+        SpriteBundle {
+            sprite: Sprite {
+                color: key_color(&data.id),
+                custom_size: Some(KEY_SIZE),
+                ..default()
+            },
+            ..default()
+        },
+        LevelEntity,
+    ));
+}
+
+/// The first player to reach a key gets it.
+fn collect_keys(
+    mut commands: Commands,
+    mut players: Query<(&Position, &Shape, &mut Inventory), (With<Player>, Without<Downed>)>,
+    keys: Query<(Entity, &Position, &Shape, &Key)>,
+) {
+    for (entity, position, shape, key) in &keys {
+        let key_aabb = Aabb2d::new(position.0, shape.0 / 2.);
+        let collector = players.iter_mut()
+            .find(|(player_pos, player_shape, _)| Aabb2d::new(player_pos.0, player_shape.0 / 2.).intersects(&key_aabb));
+        let Some((_, _, mut inventory)) = collector else {
+            continue;
+        };
+        inventory.keys.push(HeldKey { data: key.0.clone(), used: false });
+        spawn_ring(&mut commands, position.0, COLLECT_RING_RADIUS, key_color(&key.0.id), COLLECT_RING_DOTS, COLLECT_RING_SECS, false);
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Bumping into a door with its key stops it being solid straight away; the key is kept
+/// for any other doors with the same id.
+fn unlock_doors(
+    mut commands: Commands,
+    mut players: Query<(Entity, &mut Inventory), (With<Player>, Without<Downed>)>,
+    doors: Query<(&Door, &Position, &Shape), Without<Opening>>,
+    contacts: Res<Contacts>,
+    mut opened_doors: EventWriter<DoorOpened>,
+) {
+    for (entity, mut inventory) in &mut players {
+        for contact in contacts.of(entity) {
+            let Ok((door, position, shape)) = doors.get(contact.other) else {
+                continue;
+            };
+            let mut opened = false;
+            for key in inventory.keys.iter_mut().filter(|key| key.data.id == door.id) {
+                key.used = true;
+                opened = true;
+            }
+            if !opened {
+                continue;
+            }
+            open_door(&mut commands, contact.other, position.0, shape.0);
+            opened_doors.send(DoorOpened { position: position.0 });
+        }
+    }
+}
+
+/// Stops `door`, at `position` and of `shape`, being solid, and slides it up out of the way.
+pub fn open_door(commands: &mut Commands, door: Entity, position: Vec2, shape: Vec2) {
+    commands.entity(door).remove::<Collider>().insert(Opening {
+        top: position.y + shape.y / 2.,
+        height: shape.y,
+        timer: GameTimer::once(DOOR_OPEN_SECS),
+    });
+}
+
+fn open_doors(
+    mut commands: Commands,
+    mut doors: Query<(Entity, &mut Opening, &mut Position, &mut Shape, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut opening, mut position, mut shape, mut sprite) in &mut doors {
+        if opening.timer.tick(time.delta_seconds()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let height = opening.height * (1. - opening.timer.fraction());
+        shape.0.y = height;
+        position.0.y = opening.top - height / 2.;
+        sprite.custom_size = Some(shape.0);
+    }
+}
+
+fn shift_openings(mut shifted: EventReader<OriginShifted>, mut doors: Query<&mut Opening>) {
+    for event in shifted.read() {
+        for mut opening in &mut doors {
+            opening.top -= event.offset.y;
+        }
+    }
+}
+
+/// Keys that are `lost_on_death` and haven't opened anything yet go back to where they
+/// were found. Opened doors stay open either way, since nothing on a death respawns them.
+fn return_lost_keys(
+    mut commands: Commands,
+    mut died: EventReader<PlayerDied>,
+    mut inventories: Query<&mut Inventory>,
+    origin: Res<WorldOrigin>,
+) {
+    if died.read().count() == 0 {
+        return;
+    }
+    for mut inventory in &mut inventories {
+        inventory.keys.retain(|key| {
+            let lost = key.data.lost_on_death && !key.used;
+            if lost {
+                spawn_key(&mut commands, &key.data, origin.to_live(key.data.position));
+            }
+            !lost
+        });
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::cannon::InCannon;
+use crate::cutscene::cutscene_playing;
+use crate::ground_pound::GroundPound;
+use crate::input::{Action, Actions};
+use crate::movement::{MovementConfig, MovementModifiers, StatId};
+use crate::physics::{Collision, Contacts, Position, Shape, Velocity};
+use crate::player::{control_player, CoyoteTimer, Grounded, Player, PlayerId};
+
+pub const LADDER_COLOR: Color = Color::srgba(0.6, 0.45, 0.3, 0.6);
+/// Climbing speed up and down, in pixels per second.
+const CLIMB_SPEED: f32 = 360.;
+/// Share of the normal walking speed the player can shuffle sideways on a ladder.
+const CLIMB_SIDE_SPEED: f32 = 0.5;
+/// Upward speed given when climbing out over the top, enough to clear the ledge it leads to.
+const CLIMB_OFF_HOP: f32 = 432.;
+/// Coyote time granted by jumping off, so `control_player` takes it as a normal jump.
+const JUMP_OFF_SECS: f32 = 0.05;
+
+pub struct LadderPlugin;
+
+impl Plugin for LadderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, climb.run_if(not(cutscene_playing)).before(control_player));
+    }
+}
+
+/// A region from `BlockKind::Ladder` that the player can climb. It never collides.
+#[derive(Component)]
+pub struct Ladder;
+
+/// The player is on a ladder. Gravity skips them and `control_player` stands aside:
+/// move up and down drives them directly, sideways at reduced speed. A climbing body is
+/// never `Grounded`, so reaching the bottom or stepping off doesn't squash it like a landing.
+#[derive(Component)]
+pub struct Climbing;
+
+/// Grabs a ladder on up or down (only up from the ground, since down would just press
+/// into the floor), and lets go on a jump, on climbing out past either end or on
+/// walking off the side.
+pub fn climb(
+    mut commands: Commands,
+    mut players: Query<(Entity, &PlayerId, &Position, &Shape, &mut Velocity, &mut Grounded, &mut CoyoteTimer, &MovementModifiers, Has<Climbing>), (With<Player>, Without<InCannon>, Without<GroundPound>)>,
+    ladders: Query<(&Position, &Shape), With<Ladder>>,
+    contacts: Res<Contacts>,
+    actions: Actions,
+    config: Res<MovementConfig>,
+    time: Res<Time>,
+) {
+    for (entity, id, position, shape, mut velocity, mut grounded, mut coyote, modifiers, climbing) in &mut players {
+        let body = Aabb2d::new(position.0, shape.0 / 2.);
+        // The player's middle has to be over the ladder, not just a shoulder brushing it.
+        let ladder_top = ladders.iter()
+            .filter(|(ladder_pos, ladder_shape)| {
+                (position.0.x - ladder_pos.0.x).abs() <= ladder_shape.0.x / 2.
+                    && body.intersects(&Aabb2d::new(ladder_pos.0, ladder_shape.0 / 2.))
+            })
+            .map(|(ladder_pos, ladder_shape)| ladder_pos.0.y + ladder_shape.0.y / 2.)
+            .max_by(f32::total_cmp);
+        let input = actions.player(*id);
+        let climb_input = input.move_y();
+
+        if !climbing {
+            let grabbing = climb_input > 0. || (climb_input < 0. && !grounded.0);
+            if ladder_top.is_some() && grabbing {
+                commands.entity(entity).insert(Climbing);
+                grounded.0 = false;
+                velocity.0 = Vec2::ZERO;
+            }
+            continue;
+        }
+
+        if input.just_pressed(Action::Jump) {
+            commands.entity(entity).remove::<Climbing>();
+            coyote.0 = JUMP_OFF_SECS;
+            continue;
+        }
+        let feet = position.0.y - shape.0.y / 2.;
+        let Some(top) = ladder_top.filter(|top| feet < *top) else {
+            commands.entity(entity).remove::<Climbing>();
+            if velocity.0.y > 0. {
+                velocity.0.y = CLIMB_OFF_HOP;
+            }
+            continue;
+        };
+        let on_floor = contacts.of(entity).any(|contact| contact.side == Collision::Bottom);
+        if on_floor && climb_input < 0. {
+            // Climbed down onto the ground: stand on it without a landing.
+            commands.entity(entity).remove::<Climbing>();
+            grounded.0 = true;
+            velocity.0 = Vec2::ZERO;
+            continue;
+        }
+        let side_speed = config.max_speed * CLIMB_SIDE_SPEED * modifiers.get(StatId::MaxSpeed);
+        velocity.0 = Vec2::new(input.move_x() * side_speed, climb_input * CLIMB_SPEED);
+        // Don't climb out past the top in one tick; the next one lets go there.
+        velocity.0.y = velocity.0.y.min((top - feet + 0.01) / time.delta_seconds());
+    }
+}
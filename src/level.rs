@@ -0,0 +1,642 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::ecs::query::QueryFilter;
+use bevy::math::bounding::Aabb2d;
+use bevy::prelude::*;
+
+use crate::cannon::InCannon;
+use crate::crates::Carrying;
+use crate::damage::{apply_damage, Damageable, Invulnerable};
+use crate::enemy::Enemy;
+use crate::events::{CheckpointActivated, DamageEvent, Died, OriginShifted, PlayerDied, Respawned};
+use crate::grapple::Grapple;
+use crate::gravity::GravityOverride;
+use crate::ground_pound::GroundPound;
+use crate::input::{Action, Actions};
+use crate::ladder::Climbing;
+use crate::movement::{MovementModifiers, WallRun, WallRunner, WallSlide};
+use crate::origin::{FollowOrigin, WorldOrigin};
+use crate::physics::{Collider, CollisionGrace, Contacts, GravityFlipped, GroundContact, LayerMask, PhysicsSet, PhysicsWorld, Position, Shape, Up, Velocity};
+use crate::player::{Crouching, Grounded, Player, VisShape};
+use crate::sfx::{PlaySfxAt, SfxKind};
+use crate::timer::GameTimer;
+use crate::wind::WindDrift;
+use crate::world::WorldData;
+use crate::GameState;
+
+const KILL_PLANE_Y: f32 = -2000.;
+pub const GROUND_COLUMN_WIDTH: f32 = 50.;
+/// How long a fresh respawn can't be hurt and keeps enemies off the checkpoint.
+const RESPAWN_PROTECTION_SECS: f32 = 1.5;
+/// How far from the checkpoint to look for room when something is parked on it.
+const RESPAWN_SEARCH_RADIUS: f32 = 200.;
+/// How long a player who dies in two-player mode waits before coming back beside the
+/// other.
+const DOWNED_SECS: f32 = 3.;
+/// Enemies closer than this to the respawn point get nudged away while it's protected.
+const REPEL_RADIUS: f32 = 120.;
+/// Pixels per second an enemy inside the bubble is pushed out.
+const REPEL_SPEED: f32 = 432.;
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelState>()
+            .init_resource::<GroundHeights>()
+            .add_systems(FixedUpdate, (check_kill_plane.before(apply_damage),
+                                       (report_player_death,
+                                        restore_snapshots,
+                                        shelter_respawn,
+                                        carry_downed,
+                                        (repel_from_respawn, chime_on_respawn)).chain().after(apply_damage))
+                .after(PhysicsSet::Resolve))
+            .add_systems(Update, (update_ground_heights, request_restart.run_if(in_state(GameState::Playing))))
+            .add_systems(FixedPostUpdate, (shift_ground_heights, shift_snapshots).in_set(FollowOrigin))
+            .add_systems(OnEnter(GameState::Restarting), reset_level.in_set(ResetLevel))
+            .add_systems(OnEnter(GameState::Menu), unload_level)
+            .add_systems(Update, finish_restart.run_if(in_state(GameState::Restarting)));
+    }
+}
+
+/// Teardown on `OnEnter(GameState::Restarting)`. Plugins put their own resets in here and
+/// respawn level content after it, so the new attempt never sees the old one.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResetLevel;
+
+/// Anything spawned for the current attempt. Restarting despawns all of it and rebuilds
+/// the level from `WorldData`.
+#[derive(Component)]
+pub struct LevelEntity;
+
+/// What survives a respawn. Anything recorded here stays the way the player left it
+/// (collected coins, broken blocks, opened doors, fired one-shot triggers, activated
+/// checkpoints); everything carrying a `SpawnSnapshot` is put back where it started.
+/// A full level restart throws the whole thing away.
+#[derive(Resource, Default)]
+pub struct LevelState {
+    /// Indices into `WorldData` of entries that are gone for good this attempt.
+    pub consumed: HashSet<usize>,
+    /// Indices into `SpawnTriggers` of one-shot triggers that have already fired.
+    pub fired_triggers: HashSet<usize>,
+    /// Ids of script triggers that have fired at least once.
+    pub fired_scripts: HashSet<String>,
+}
+
+/// For each 50px column of the live world, the top of the lowest fixed block in it. Rebuilt
+/// whenever `WorldData` changes, editor changes included.
+#[derive(Resource, Default)]
+pub struct GroundHeights(HashMap<i32, f32>);
+
+impl GroundHeights {
+    fn build(world: &WorldData, origin: WorldOrigin) -> Self {
+        let mut columns = HashMap::new();
+        for block in world.0.iter().filter(|block| block.kind.is_static_ground() && block.path.is_none()) {
+            let position = origin.to_live(block.position);
+            let top = position.y + block.shape.y / 2.;
+            let first = ((position.x - block.shape.x / 2.) / GROUND_COLUMN_WIDTH).floor() as i32;
+            let last = ((position.x + block.shape.x / 2.) / GROUND_COLUMN_WIDTH).ceil() as i32 - 1;
+            for column in first..=last {
+                columns.entry(column)
+                    .and_modify(|lowest: &mut f32| *lowest = lowest.min(top))
+                    .or_insert(top);
+            }
+        }
+        Self(columns)
+    }
+
+    /// Lowest ground anywhere between `min_x` and `max_x`. Columns with no ground at all
+    /// (pits, the level's edges) don't count.
+    pub fn lowest_in(&self, min_x: f32, max_x: f32) -> Option<f32> {
+        let first = (min_x / GROUND_COLUMN_WIDTH).floor() as i32;
+        let last = (max_x / GROUND_COLUMN_WIDTH).floor() as i32;
+        (first..=last)
+            .filter_map(|column| self.0.get(&column).copied())
+            .reduce(f32::min)
+    }
+}
+
+/// State a resettable entity goes back to on respawn: where it was spawned, unless a
+/// checkpoint has since committed something newer. A full restart rewinds to the spawn.
+#[derive(Component)]
+pub struct SpawnSnapshot {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub health: Option<i32>,
+    initial: Option<(Vec2, Vec2, Option<i32>)>,
+}
+
+impl SpawnSnapshot {
+    pub fn new(position: Vec2, velocity: Vec2) -> Self {
+        Self {
+            position,
+            velocity,
+            health: None,
+            initial: None,
+        }
+    }
+
+    /// Makes the current state the one respawns restore, keeping the spawn for restarts.
+    pub fn commit(&mut self, position: Vec2, velocity: Vec2, health: Option<i32>) {
+        self.initial.get_or_insert((self.position, self.velocity, self.health));
+        self.position = position;
+        self.velocity = velocity;
+        if self.health.is_some() {
+            self.health = health.or(self.health);
+        }
+    }
+
+    fn shift(&mut self, offset: Vec2) {
+        self.position -= offset;
+        if let Some((position, ..)) = &mut self.initial {
+            *position -= offset;
+        }
+    }
+
+    fn rewind(&mut self) {
+        if let Some((position, velocity, health)) = self.initial.take() {
+            self.position = position;
+            self.velocity = velocity;
+            self.health = health;
+        }
+    }
+
+    pub fn with_health(mut self, health: i32) -> Self {
+        self.health = Some(health);
+        self
+    }
+}
+
+/// Re-snapshots everything whose position is inside `region`, so a respawn puts it back
+/// where it is now (at rest) instead of where it was spawned.
+pub fn commit_snapshots_in<F: QueryFilter>(
+    region: Aabb2d,
+    snapshots: &mut Query<(&Position, &mut SpawnSnapshot, Option<&Damageable>), F>,
+) {
+    for (position, mut snapshot, damageable) in snapshots.iter_mut() {
+        if region.closest_point(position.0) == position.0 {
+            snapshot.commit(position.0, Vec2::ZERO, damageable.map(|damageable| damageable.health));
+        }
+    }
+}
+
+fn update_ground_heights(
+    world_data: Query<&WorldData, Changed<WorldData>>,
+    mut heights: ResMut<GroundHeights>,
+    origin: Res<WorldOrigin>,
+) {
+    if let Ok(world) = world_data.get_single() {
+        *heights = GroundHeights::build(world, *origin);
+    }
+}
+
+/// Rebases only ever move by whole columns, so each one keeps its height and just moves
+/// to another key.
+fn shift_ground_heights(mut shifted: EventReader<OriginShifted>, mut heights: ResMut<GroundHeights>) {
+    for event in shifted.read() {
+        let columns = (event.offset.x / GROUND_COLUMN_WIDTH).round() as i32;
+        heights.0 = heights.0.drain().map(|(column, top)| (column - columns, top - event.offset.y)).collect();
+    }
+}
+
+fn shift_snapshots(mut shifted: EventReader<OriginShifted>, mut snapshots: Query<&mut SpawnSnapshot>) {
+    for event in shifted.read() {
+        for mut snapshot in &mut snapshots {
+            snapshot.shift(event.offset);
+        }
+    }
+}
+
+/// The kill plane is a height in the level, wherever the origin has got to.
+fn check_kill_plane(
+    player: Query<(Entity, &Position), With<Player>>,
+    mut damage: EventWriter<DamageEvent>,
+    origin: Res<WorldOrigin>,
+) {
+    for (entity, position) in &player {
+        if origin.to_level(position.0).y < KILL_PLANE_Y {
+            damage.send(DamageEvent::lethal(entity));
+        }
+    }
+}
+
+/// A player who dies while the other one is still going is only `Downed`; the level is
+/// put back once nobody's left standing.
+fn report_player_death(
+    mut commands: Commands,
+    mut died: EventReader<Died>,
+    mut players: Query<(Entity, Has<Downed>, &mut Visibility), With<Player>>,
+    mut player_died: EventWriter<PlayerDied>,
+) {
+    let mut downed = HashSet::new();
+    for event in died.read() {
+        if !players.contains(event.entity) {
+            continue;
+        }
+        let survivor = players.iter().any(|(entity, is_downed, _)| entity != event.entity && !is_downed && !downed.contains(&entity));
+        if !survivor {
+            player_died.send(PlayerDied);
+            continue;
+        }
+        downed.insert(event.entity);
+        if let Ok((_, _, mut visibility)) = players.get_mut(event.entity) {
+            *visibility = Visibility::Hidden;
+        }
+        commands.entity(event.entity).remove::<(Grapple, GroundPound, Climbing, Carrying)>().insert(Downed { timer: GameTimer::once(DOWNED_SECS) });
+    }
+}
+
+/// A player who died in two-player mode while the other was still going. They're hidden
+/// and carried along with the survivor until the timer runs out, then come back beside
+/// them. Their health is at zero meanwhile, so nothing can hurt them.
+#[derive(Component)]
+pub struct Downed {
+    timer: GameTimer,
+}
+
+/// Downed players follow the survivor around, so that's where they come back.
+fn carry_downed(
+    mut commands: Commands,
+    mut downed: Query<(Entity, &mut Position, &mut Velocity, &mut Downed, &mut Damageable, &mut Visibility), With<Player>>,
+    survivors: Query<&Position, (With<Player>, Without<Downed>)>,
+    mut sfx: EventWriter<PlaySfxAt>,
+    time: Res<Time>,
+) {
+    let Some(survivor) = survivors.iter().next().map(|position| position.0) else {
+        return;
+    };
+    for (entity, mut position, mut velocity, mut downed, mut damageable, mut visibility) in &mut downed {
+        velocity.0 = Vec2::ZERO;
+        if !downed.timer.tick(time.delta_seconds()).finished() {
+            position.0 = survivor;
+            continue;
+        }
+        position.teleport(&mut commands, entity, survivor);
+        damageable.health = damageable.max_health;
+        *visibility = Visibility::Inherited;
+        commands.entity(entity).remove::<Downed>().insert(Invulnerable::new(RESPAWN_PROTECTION_SECS));
+        sfx.send(PlaySfxAt::new(SfxKind::Respawn, survivor));
+    }
+}
+
+pub fn restore_snapshots(
+    mut commands: Commands,
+    mut died: EventReader<PlayerDied>,
+    mut resettable: Query<(Entity, &mut Position, &mut Velocity, &SpawnSnapshot, Option<&mut Damageable>)>,
+    mut player: Query<(Entity, &mut Grounded, &mut VisShape, &mut Shape, &mut Visibility, Option<&Crouching>), With<Player>>,
+) {
+    if died.read().count() == 0 {
+        return;
+    }
+    for (entity, mut position, mut velocity, snapshot, damageable) in &mut resettable {
+        position.teleport(&mut commands, entity, snapshot.position);
+        velocity.0 = snapshot.velocity;
+        if let (Some(health), Some(mut damageable)) = (snapshot.health, damageable) {
+            damageable.health = health;
+        }
+    }
+    for (entity, mut grounded, mut vis_shape, mut shape, mut visibility, crouching) in &mut player {
+        grounded.0 = false;
+        *visibility = Visibility::Inherited;
+        // Snapshots hold the standing middle, so the player comes back standing.
+        if let Some(crouching) = crouching {
+            shape.0.y = crouching.standing_height;
+        }
+        vis_shape.0 = shape.0;
+        // Power-ups don't outlive the attempt they were collected in, and the player comes
+        // back the right way up. Gravity zones let go on their own once they see the
+        // player has left them.
+        commands.entity(entity).remove::<(Crouching, GravityFlipped, Grapple, GroundPound, Downed)>().insert((MovementModifiers::default(), Up::default(), WindDrift::default()));
+    }
+}
+
+/// The area around a respawned player that enemies are kept out of until it fades.
+#[derive(Component)]
+struct RespawnBubble {
+    center: Vec2,
+    timer: GameTimer,
+}
+
+/// Moves the player off anything that was sitting on the checkpoint when they died, a
+/// parked moving platform or a crate, then moves enemies sideways out of `REPEL_RADIUS`
+/// and protects the spot for a moment. An enemy with nowhere free outside the radius is
+/// left to `repel_from_respawn`.
+pub fn shelter_respawn(
+    mut commands: Commands,
+    mut died: EventReader<PlayerDied>,
+    mut player: Query<(Entity, &mut Position, &Shape), (With<Player>, Without<Collider>)>,
+    mut enemies: Query<(Entity, &mut Position, &Shape), (With<Enemy>, Without<Player>, Without<Collider>)>,
+    physics: PhysicsWorld,
+    mut respawned: EventWriter<Respawned>,
+) {
+    if died.read().count() == 0 {
+        return;
+    }
+    for (entity, mut position, shape) in &mut player {
+        let blocked = !physics.overlap_aabb(Aabb2d::new(position.0, shape.0 / 2.), LayerMask::ALL).is_empty();
+        if blocked {
+            match physics.free_space_near(position.0, shape.0, RESPAWN_SEARCH_RADIUS) {
+                Some(free) => position.teleport(&mut commands, entity, free),
+                None => warn!("no room to respawn within {RESPAWN_SEARCH_RADIUS}px of {}", position.0),
+            }
+        }
+        for (enemy, mut enemy_position, enemy_shape) in &mut enemies {
+            let offset = enemy_position.0 - position.0;
+            if offset.length() >= REPEL_RADIUS {
+                continue;
+            }
+            // Sideways at its own height, so a walker stays on its floor rather than being
+            // put inside it, and a pixel past the edge so rounding can't leave it just inside.
+            let side = if offset.x >= 0. { 1. } else { -1. };
+            let reach = (REPEL_RADIUS * REPEL_RADIUS - offset.y * offset.y).sqrt() + 1.;
+            let clear = Vec2::new(position.0.x + side * reach, enemy_position.0.y);
+            let free = physics.free_space_near(clear, enemy_shape.0, RESPAWN_SEARCH_RADIUS)
+                .filter(|free| free.distance(position.0) >= REPEL_RADIUS);
+            if let Some(free) = free {
+                enemy_position.teleport(&mut commands, enemy, free);
+            }
+        }
+        commands.entity(entity).insert((
+            Invulnerable::new(RESPAWN_PROTECTION_SECS),
+            RespawnBubble {
+                center: position.0,
+                timer: GameTimer::once(RESPAWN_PROTECTION_SECS),
+            },
+        ));
+        respawned.send(Respawned { position: position.0 });
+    }
+}
+
+/// Pushes enemies that walk back into the bubble out again a little each tick, straight
+/// away from its center, so nothing is waiting on top of the player when the protection
+/// wears off. Hazards stay where they are.
+fn repel_from_respawn(
+    mut commands: Commands,
+    mut bubbles: Query<(Entity, &mut RespawnBubble)>,
+    mut enemies: Query<&mut Position, (With<Enemy>, Without<Player>)>,
+    time: Res<Time>,
+) {
+    let step = REPEL_SPEED * time.delta_seconds();
+    for (entity, mut bubble) in &mut bubbles {
+        if bubble.timer.tick(time.delta_seconds()).finished() {
+            commands.entity(entity).remove::<RespawnBubble>();
+            continue;
+        }
+        for mut position in &mut enemies {
+            let offset = position.0 - bubble.center;
+            let distance = offset.length();
+            if distance >= REPEL_RADIUS {
+                continue;
+            }
+            let away = offset.try_normalize().unwrap_or(Vec2::X);
+            position.0 += away * step.min(REPEL_RADIUS - distance);
+        }
+    }
+}
+
+fn chime_on_respawn(mut respawned: EventReader<Respawned>, mut sfx: EventWriter<PlaySfxAt>) {
+    for event in respawned.read() {
+        sfx.send(PlaySfxAt::new(SfxKind::Respawn, event.position));
+    }
+}
+
+fn request_restart(
+    actions: Actions,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if actions.just_pressed(Action::Restart) {
+        next_state.set(GameState::Restarting);
+    }
+}
+
+/// Throws the current attempt away. Simulation stays paused until `finish_restart`, so no
+/// fixed tick runs between the old level going away and the new one being spawned.
+pub fn reset_level(
+    mut commands: Commands,
+    level_entities: Query<Entity, With<LevelEntity>>,
+    mut level_state: ResMut<LevelState>,
+    mut contacts: ResMut<Contacts>,
+    mut damage: ResMut<Events<DamageEvent>>,
+    mut died: ResMut<Events<Died>>,
+    mut player_died: ResMut<Events<PlayerDied>>,
+    mut checkpoints: ResMut<Events<CheckpointActivated>>,
+    // Level content is despawned and spawned fresh instead.
+    mut resettable: Query<(Entity, &mut Position, &mut Velocity, &mut SpawnSnapshot, Option<&mut Damageable>), Without<LevelEntity>>,
+    mut player: Query<(Entity, &mut Grounded, &mut GroundContact, &mut VisShape, &mut Shape, &mut Visibility, Option<&Crouching>, Option<&GravityOverride>), With<Player>>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    time.pause();
+    for entity in &level_entities {
+        commands.entity(entity).despawn_recursive();
+    }
+    *level_state = LevelState::default();
+    contacts.0.clear();
+    damage.clear();
+    died.clear();
+    player_died.clear();
+    checkpoints.clear();
+
+    for (entity, mut position, mut velocity, mut snapshot, damageable) in &mut resettable {
+        snapshot.rewind();
+        position.teleport(&mut commands, entity, snapshot.position);
+        velocity.0 = snapshot.velocity;
+        if let (Some(health), Some(mut damageable)) = (snapshot.health, damageable) {
+            damageable.health = health;
+        }
+    }
+    for (entity, mut grounded, mut ground_contact, mut vis_shape, mut shape, mut visibility, crouching, gravity_override) in &mut player {
+        grounded.0 = false;
+        *visibility = Visibility::Inherited;
+        ground_contact.0 = None;
+        if let Some(crouching) = crouching {
+            shape.0.y = crouching.standing_height;
+        }
+        vis_shape.0 = shape.0;
+        let mut player = commands.entity(entity);
+        // The zones are despawned with the level, before they can see the player leave.
+        if let Some(gravity_override) = gravity_override {
+            gravity_override.restore(&mut player);
+        }
+        player
+            .remove::<(InCannon, CollisionGrace, WallRun, WallSlide, Carrying, Invulnerable, RespawnBubble, Climbing, Crouching, GravityFlipped, Grapple, GroundPound, Downed)>()
+            .insert((WallRunner::default(), MovementModifiers::default(), Up::default(), WindDrift::default()));
+    }
+}
+
+/// Back on the menu: the level, its data and the player all go, so the next level
+/// started is loaded and spawned from scratch.
+fn unload_level(
+    mut commands: Commands,
+    level: Query<Entity, Or<(With<LevelEntity>, With<WorldData>, With<Player>)>>,
+    mut level_state: ResMut<LevelState>,
+) {
+    for entity in &level {
+        commands.entity(entity).despawn_recursive();
+    }
+    *level_state = LevelState::default();
+}
+
+fn finish_restart(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    time.unpause();
+    next_state.set(GameState::Playing);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coin::{Coin, CoinSpawns, Score};
+    use crate::crates::{Crate, CrateData, CrateSpawns};
+    use crate::damage::Dying;
+    use crate::enemy::{EnemyData, EnemySpawns, PatrolRoute};
+    use crate::headless::build_headless_app;
+    use crate::input::ScriptedInput;
+    use crate::world::BlockData;
+
+    /// A long floor with its top at -175, under the player's spawn at the origin, and
+    /// `extra` placed alongside it.
+    fn level(extra: impl Bundle) -> App {
+        let mut app = build_headless_app(WorldData(vec![BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.))]));
+        let mut levels = app.world_mut().query_filtered::<Entity, With<WorldData>>();
+        let level = levels.single(app.world());
+        app.world_mut().entity_mut(level).insert(extra);
+        app
+    }
+
+    fn run(app: &mut App, ticks: usize) {
+        for _ in 0..ticks {
+            app.update();
+        }
+    }
+
+    fn walk_right(app: &mut App, ticks: usize) {
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].press(Action::MoveRight);
+        run(app, ticks);
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].release(Action::MoveRight);
+    }
+
+    fn die(app: &mut App) {
+        let mut players = app.world_mut().query_filtered::<Entity, With<Player>>();
+        let player = players.single(app.world());
+        app.world_mut().send_event(DamageEvent::lethal(player));
+        run(app, 144);
+        let position = app.world().get::<Position>(player).unwrap().0;
+        assert!(position.x.abs() < 1., "didn't respawn at the start: {position}");
+    }
+
+    fn count<F: QueryFilter>(app: &mut App) -> usize {
+        app.world_mut().query_filtered::<(), F>().iter(app.world()).count()
+    }
+
+    #[test]
+    fn a_collected_coin_stays_collected_after_dying() {
+        let mut app = level(CoinSpawns(vec![Vec2::new(200., -140.)]));
+        run(&mut app, 144);
+        walk_right(&mut app, 144);
+        assert_eq!(count::<With<Coin>>(&mut app), 0, "never reached the coin");
+        die(&mut app);
+        assert_eq!(count::<With<Coin>>(&mut app), 0);
+        assert_eq!(app.world().resource::<Score>().0, 1);
+    }
+
+    #[test]
+    fn a_pushed_crate_goes_back_after_dying() {
+        let start = Vec2::new(150., -155.);
+        let mut app = level(CrateSpawns(vec![CrateData { position: start, shape: Vec2::splat(40.) }]));
+        run(&mut app, 144);
+        walk_right(&mut app, 288);
+        let mut crates = app.world_mut().query_filtered::<&Position, With<Crate>>();
+        let pushed = crates.single(app.world()).0;
+        assert!(pushed.x > start.x + 100., "only pushed to {pushed}");
+        die(&mut app);
+        let back = crates.single(app.world()).0;
+        assert!(back.distance(start) < 1., "back at {back}");
+    }
+
+    /// What a level leaves behind once it's up and running: how many entities there are
+    /// and the state that restarts are meant to reset.
+    fn settled(app: &mut App) -> (u32, u32, usize, usize) {
+        let state = app.world().resource::<LevelState>();
+        let consumed = state.consumed.len() + state.fired_triggers.len() + state.fired_scripts.len();
+        (app.world().entities().len(), app.world().resource::<Score>().0, consumed, count::<With<LevelEntity>>(app))
+    }
+
+    #[test]
+    fn fifty_restarts_leave_the_level_as_a_fresh_one() {
+        let busy = || (
+            CoinSpawns(vec![Vec2::new(100., -140.), Vec2::new(150., -140.)]),
+            CrateSpawns(vec![CrateData { position: Vec2::new(-150., -155.), shape: Vec2::splat(40.) }]),
+            EnemySpawns(vec![EnemyData {
+                position: Vec2::new(-400., -150.),
+                shape: Vec2::splat(50.),
+                route: PatrolRoute::Range { min_x: -500., max_x: -300. },
+                speed: 100.,
+                chase: None,
+                boss_bar: None,
+            }]),
+        );
+        let mut fresh = level(busy());
+        run(&mut fresh, 144);
+        let expected = settled(&mut fresh);
+
+        let mut restarted = level(busy());
+        run(&mut restarted, 144);
+        for _ in 0..50 {
+            // Picks up the coins and leaves particles behind on the way.
+            walk_right(&mut restarted, 72);
+            assert_eq!(restarted.world().resource::<Score>().0, 2);
+            restarted.world_mut().resource_mut::<ScriptedInput>().actions[0].press(Action::Restart);
+            restarted.update();
+            restarted.world_mut().resource_mut::<ScriptedInput>().actions[0].release(Action::Restart);
+            run(&mut restarted, 2);
+        }
+        run(&mut restarted, 142);
+        assert_eq!(settled(&mut restarted), expected);
+    }
+
+    #[test]
+    fn an_enemy_on_the_checkpoint_is_cleared_off_before_the_player_is_back() {
+        // The floor right under the spawn this time, so there's ground to park on.
+        let mut app = build_headless_app(WorldData(vec![BlockData::new(Vec2::new(0., -75.), Vec2::new(3000., 50.))]));
+        let mut levels = app.world_mut().query_filtered::<Entity, With<WorldData>>();
+        let level = levels.single(app.world());
+        app.world_mut().entity_mut(level).insert(EnemySpawns(vec![EnemyData {
+            // Parked inside the bubble but clear of where the player lands, and placed
+            // there, so a respawn puts it back there too.
+            position: Vec2::new(70., -25.),
+            shape: Vec2::splat(50.),
+            route: PatrolRoute::Waypoints(vec![70.]),
+            speed: 100.,
+            chase: None,
+            boss_bar: None,
+        }]));
+        run(&mut app, 144);
+        assert_eq!(count::<(With<Enemy>, Without<Dying>)>(&mut app), 1, "the enemy didn't survive the warm-up");
+        let mut players = app.world_mut().query_filtered::<Entity, With<Player>>();
+        let player = players.single(app.world());
+        app.world_mut().send_event(DamageEvent::lethal(player));
+        while count::<With<RespawnBubble>>(&mut app) == 0 {
+            app.update();
+        }
+        let mut bubbles = app.world_mut().query::<&RespawnBubble>();
+        let center = bubbles.single(app.world()).center;
+        let mut enemies = app.world_mut().query_filtered::<&Position, With<Enemy>>();
+        let enemy = enemies.single(app.world()).0;
+        assert!(enemy.distance(center) >= REPEL_RADIUS, "still at {enemy} as the player comes back at {center}");
+
+        let mut damage = app.world().resource::<Events<DamageEvent>>().get_reader_current();
+        let mut ticks = 0;
+        while count::<With<RespawnBubble>>(&mut app) > 0 {
+            app.update();
+            ticks += 1;
+            let events = app.world().resource::<Events<DamageEvent>>();
+            assert!(damage.read(events).all(|event| event.target != player), "hit {ticks} ticks in");
+        }
+        assert!(ticks as f32 >= RESPAWN_PROTECTION_SECS * 144. - 1.);
+        let enemy = enemies.single(app.world()).0;
+        // Allowing for the one step it walks back in as the bubble lifts.
+        assert!(enemy.distance(center) >= REPEL_RADIUS - 1., "at {enemy} when the bubble lifted at {center}");
+    }
+}
@@ -0,0 +1,282 @@
+use bevy::app::PluginGroupBuilder;
+use bevy::prelude::*;
+
+use animation::SpriteAnimationPlugin;
+use blinking::BlinkingPlugin;
+use boost::BoostPlugin;
+use boss_bar::BossBarPlugin;
+use breakable::BreakablePlugin;
+use camera::{CameraEffectsPlugin, CameraPlugin};
+use camera_zone::CameraZonePlugin;
+use cannon::CannonPlugin;
+use catchup::CatchupPlugin;
+use checkpoint::CheckpointPlugin;
+use cleanup::CleanupPlugin;
+use coin::CoinPlugin;
+use combo::ComboPlugin;
+use controls::ControlsPlugin;
+use crates::CratePlugin;
+use crumbling::CrumblingPlugin;
+use cutscene::CutscenePlugin;
+use damage::DamagePlugin;
+use debug::DebugOverlayPlugin;
+#[cfg(feature = "editor")]
+use editor::EditorPlugin;
+use endless::EndlessPlugin;
+use enemy::EnemyPlugin;
+use events::GameEventsPlugin;
+use exit::ExitPlugin;
+use ghost::GhostPlugin;
+use grapple::GrapplePlugin;
+use gravity::GravityPlugin;
+use ground_pound::GroundPoundPlugin;
+use hazard::HazardPlugin;
+use hitstop::HitstopPlugin;
+use hot_reload::HotReloadPlugin;
+use hud::HudPlugin;
+use interact::InteractPlugin;
+use key::KeyPlugin;
+use ladder::LadderPlugin;
+use level::LevelPlugin;
+use loading::LoadingPlugin;
+use magnet::MagnetPlugin;
+use menu::MenuPlugin;
+use minimap::MinimapPlugin;
+use movement::MovementPlugin;
+use music::MusicPlugin;
+use origin::OriginPlugin;
+use parallax::ParallaxPlugin;
+use particles::ParticlePlugin;
+use pendulum::PendulumPlugin;
+use pause::PausePlugin;
+use perf::PerfPlugin;
+use physics::PhysicsPlugin;
+use pickup::PickupPlugin;
+use platform::PlatformPlugin;
+use portal::PortalPlugin;
+use player::PlayerPlugin;
+use popup::PopupPlugin;
+use projectile::ProjectilePlugin;
+use recording::RecordingPlugin;
+use safe_room::SafeRoomPlugin;
+use save::SavePlugin;
+use script::ScriptPlugin;
+use settings::SettingsPlugin;
+use sfx::SfxPlugin;
+use slime::SlimePlugin;
+use spawn_zone::SpawnZonePlugin;
+use speedrun::SpeedrunPlugin;
+use spring::SpringPlugin;
+use stats::StatsPlugin;
+use switch::SwitchPlugin;
+use tiles::TilePlugin;
+use time_scale::TimeScalePlugin;
+use trap::TrapPlugin;
+use trigger::TriggerPlugin;
+#[cfg(feature = "debug-tools")]
+use tuning::TuningPlugin;
+use water::WaterPlugin;
+use wind::WindPlugin;
+use world::WorldPlugin;
+
+mod animation;
+mod blinking;
+mod boost;
+mod boss_bar;
+mod breakable;
+mod camera;
+mod camera_zone;
+mod cannon;
+mod catchup;
+mod checkpoint;
+mod cleanup;
+mod coin;
+mod combo;
+mod controls;
+mod crates;
+mod crumbling;
+mod cutscene;
+mod damage;
+mod debug;
+#[cfg(feature = "editor")]
+mod editor;
+mod endless;
+mod enemy;
+pub mod events;
+mod exit;
+mod ghost;
+mod grapple;
+mod gravity;
+mod ground_pound;
+mod hazard;
+pub mod headless;
+mod hitstop;
+mod hot_reload;
+mod hud;
+pub mod input;
+mod interact;
+mod key;
+mod ladder;
+mod level;
+mod loading;
+mod magnet;
+mod menu;
+mod minimap;
+mod movement;
+mod music;
+mod origin;
+mod parallax;
+mod particles;
+mod pendulum;
+mod pause;
+mod perf;
+pub mod physics;
+mod pickup;
+mod platform;
+mod portal;
+pub mod player;
+mod popup;
+mod projectile;
+mod recording;
+mod safe_room;
+mod save;
+mod script;
+mod settings;
+mod sfx;
+mod slime;
+mod spatial;
+mod spawn_zone;
+mod speedrun;
+mod spring;
+mod stats;
+mod switch;
+mod tiles;
+mod time_scale;
+mod timer;
+mod trap;
+mod trigger;
+#[cfg(feature = "debug-tools")]
+mod tuning;
+mod water;
+mod wind;
+pub mod world;
+
+/// The whole game in a window: `GameplayPlugins` with the menus, HUD, audio, saves and,
+/// with their features, the editor and tuning panel on top. Add systems to it before
+/// running it to build on the game.
+pub fn game_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((DefaultPlugins, GameplayPlugins, LoadingPlugin))
+        .init_state::<GameState>()
+        .add_plugins((BossBarPlugin, SfxPlugin, DebugOverlayPlugin, StatsPlugin, MinimapPlugin, CatchupPlugin, SavePlugin, MusicPlugin, PerfPlugin, SpriteAnimationPlugin, PausePlugin, MenuPlugin, ParallaxPlugin))
+        .add_plugins((SettingsPlugin, SpeedrunPlugin, GhostPlugin, HotReloadPlugin, HudPlugin, RecordingPlugin));
+    #[cfg(feature = "editor")]
+    app.add_plugins(EditorPlugin);
+    #[cfg(feature = "debug-tools")]
+    app.add_plugins(TuningPlugin);
+    app
+}
+
+/// Runs what the command line asks for in place of the game, a `--compare` or a headless
+/// `--replay`, and returns the process's exit code. `None` when it asks for the game.
+pub fn run_from_args() -> Option<i32> {
+    perf::compare_from_args().or_else(recording::replay_headless_from_args)
+}
+
+/// Everything that decides what happens in a level, including the camera and the effects
+/// that gameplay reads back, and nothing that needs a window, a sound device or the disk.
+/// `game_app` runs it with the menus, HUD, audio and saves on top;
+/// `headless::headless_app` runs it alone.
+pub struct GameplayPlugins;
+
+impl PluginGroup for GameplayPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(GameEventsPlugin)
+            .add(PhysicsPlugin)
+            .add(WorldPlugin)
+            .add(PlayerPlugin)
+            .add(CameraPlugin)
+            .add(LevelPlugin)
+            .add(DamagePlugin)
+            .add(EnemyPlugin)
+            .add(ProjectilePlugin)
+            .add(HitstopPlugin)
+            .add(CannonPlugin)
+            .add(CameraEffectsPlugin)
+            .add(ParticlePlugin)
+            .add(MovementPlugin)
+            .add(MagnetPlugin)
+            .add(SlimePlugin)
+            .add(WaterPlugin)
+            .add(SafeRoomPlugin)
+            .add(CutscenePlugin)
+            .add(HazardPlugin)
+            .add(ControlsPlugin)
+            .add(PickupPlugin)
+            .add(TilePlugin)
+            .add(SpawnZonePlugin)
+            .add(CratePlugin)
+            .add(PlatformPlugin)
+            .add(SpringPlugin)
+            .add(InteractPlugin)
+            .add(ScriptPlugin)
+            .add(CheckpointPlugin)
+            .add(CoinPlugin)
+            .add(BreakablePlugin)
+            .add(LadderPlugin)
+            .add(ExitPlugin)
+            .add(TriggerPlugin)
+            .add(GravityPlugin)
+            .add(WindPlugin)
+            .add(GrapplePlugin)
+            .add(EndlessPlugin)
+            .add(TrapPlugin)
+            .add(KeyPlugin)
+            .add(PortalPlugin)
+            .add(CrumblingPlugin)
+            .add(CameraZonePlugin)
+            .add(OriginPlugin)
+            .add(ComboPlugin)
+            .add(BlinkingPlugin)
+            .add(GroundPoundPlugin)
+            .add(PendulumPlugin)
+            .add(CleanupPlugin)
+            .add(PopupPlugin)
+            .add(TimeScalePlugin)
+            .add(SwitchPlugin)
+            .add(BoostPlugin)
+    }
+}
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    Loading,
+    /// The main menu, with no level loaded.
+    Menu,
+    Playing,
+    /// The one-frame gap between tearing a level down and spawning it again.
+    Restarting,
+    /// The pause menu is open and the simulation is frozen.
+    Paused,
+    /// The last level is finished.
+    Won,
+}
+
+fn flerp(a: f32, b: f32, t: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// How far an exponential ease at `rate` per second gets in `dt` seconds, as a `flerp`
+/// factor, so smoothing covers the same ground per second at any tick rate.
+fn ease_factor(rate: f32, dt: f32) -> f32 {
+    1. - (-rate * dt).exp()
+}
+
+fn vlerp(a: Vec2, b: Vec2, t: f32) -> Vec2 {
+    Vec2::new(
+        flerp(a.x, b.x, t),
+        flerp(a.y, b.y, t),
+    )
+}
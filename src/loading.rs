@@ -0,0 +1,172 @@
+use bevy::asset::{LoadState, LoadedUntypedAsset, RecursiveDependencyLoadState};
+use bevy::prelude::*;
+
+use crate::GameState;
+
+const BAR_WIDTH: f32 = 400.;
+pub const PLAYER_SHEET: &str = "sprites/player.png";
+pub const TILE_ATLAS: &str = "sprites/tiles.png";
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        let mut manifest = AssetManifest::default();
+        manifest.require(PLAYER_SHEET);
+        manifest.optional(TILE_ATLAS);
+        app.insert_resource(manifest)
+            .init_resource::<PreloadedAssets>()
+            .add_systems(OnEnter(GameState::Loading), (freeze_simulation, start_loading, spawn_loading_screen))
+            .add_systems(Update, poll_loading.run_if(in_state(GameState::Loading)))
+            .add_systems(OnExit(GameState::Loading), (resume_simulation, despawn_loading_screen));
+    }
+}
+
+pub struct ManifestEntry {
+    pub path: String,
+    /// Optional assets that fail to load are logged and skipped instead of blocking play.
+    pub required: bool,
+}
+
+/// Everything that has to be in memory before play starts. Levels add their own assets
+/// and re-enter `GameState::Loading` to stream them in through the same screen.
+#[derive(Resource, Default)]
+pub struct AssetManifest(pub Vec<ManifestEntry>);
+
+impl AssetManifest {
+    pub fn require(&mut self, path: impl Into<String>) {
+        self.0.push(ManifestEntry { path: path.into(), required: true });
+    }
+
+    pub fn optional(&mut self, path: impl Into<String>) {
+        self.0.push(ManifestEntry { path: path.into(), required: false });
+    }
+}
+
+/// Handles for everything in the manifest, held here so preloaded assets stay alive.
+#[derive(Resource, Default)]
+pub struct PreloadedAssets(Vec<(String, bool, Handle<LoadedUntypedAsset>)>);
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct ProgressFill;
+
+#[derive(Component)]
+struct LoadingText;
+
+fn freeze_simulation(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn resume_simulation(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn start_loading(
+    manifest: Res<AssetManifest>,
+    mut preloaded: ResMut<PreloadedAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    for entry in &manifest.0 {
+        if preloaded.0.iter().any(|(path, _, _)| *path == entry.path) {
+            continue;
+        }
+        let handle = asset_server.load_untyped(entry.path.clone());
+        preloaded.0.push((entry.path.clone(), entry.required, handle));
+    }
+}
+
+fn spawn_loading_screen(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(12.),
+            ..default()
+        },
+        background_color: Color::BLACK.into(),
+        ..default()
+    }, LoadingScreen)).with_children(|screen| {
+        screen.spawn((TextBundle::from_section("Loading...", TextStyle::default()), LoadingText));
+        screen.spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(BAR_WIDTH),
+                height: Val::Px(16.),
+                ..default()
+            },
+            background_color: Color::srgb(0.2, 0.2, 0.2).into(),
+            ..default()
+        }).with_children(|bar| {
+            bar.spawn((NodeBundle {
+                style: Style {
+                    width: Val::Percent(0.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                background_color: Color::WHITE.into(),
+                ..default()
+            }, ProgressFill));
+        });
+    });
+}
+
+fn despawn_loading_screen(mut commands: Commands, screens: Query<Entity, With<LoadingScreen>>) {
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn poll_loading(
+    preloaded: Res<PreloadedAssets>,
+    asset_server: Res<AssetServer>,
+    mut fill: Query<&mut Style, With<ProgressFill>>,
+    mut text: Query<&mut Text, With<LoadingText>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut reported: Local<bool>,
+) {
+    let mut done = 0;
+    let mut failed_required = Vec::new();
+    let mut failed_optional = Vec::new();
+    for (path, required, handle) in &preloaded.0 {
+        let failed = matches!(asset_server.get_load_state(handle), Some(LoadState::Failed(_)))
+            || matches!(asset_server.get_recursive_dependency_load_state(handle), Some(RecursiveDependencyLoadState::Failed));
+        if failed {
+            if *required {
+                failed_required.push(path.as_str());
+            } else {
+                failed_optional.push(path.as_str());
+            }
+            done += 1;
+        } else if asset_server.is_loaded_with_dependencies(handle) {
+            done += 1;
+        }
+    }
+
+    let total = preloaded.0.len().max(1);
+    for mut style in &mut fill {
+        style.width = Val::Percent(100. * done as f32 / total as f32);
+    }
+
+    if !failed_required.is_empty() {
+        if !*reported {
+            error!("Required assets failed to load: {:?}", failed_required);
+            for mut text in &mut text {
+                text.sections[0].value = format!("Failed to load:\n{}", failed_required.join("\n"));
+            }
+            *reported = true;
+        }
+        return;
+    }
+    if done == preloaded.0.len() {
+        if !failed_optional.is_empty() {
+            warn!("Optional assets failed to load: {:?}", failed_optional);
+        }
+        *reported = false;
+        next_state.set(GameState::Menu);
+    }
+}
@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::cannon::InCannon;
+use crate::particles::spawn_ring;
+use crate::physics::{gravitate, Position, Velocity};
+use crate::player::Player;
+use crate::timer::GameTimer;
+
+const PULSE_SECS: f32 = 1.2;
+const PULSE_DOTS: usize = 24;
+
+pub struct MagnetPlugin;
+
+impl Plugin for MagnetPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Magnet>()
+            .add_systems(FixedUpdate, (apply_magnetism.before(gravitate), pulse_magnets));
+    }
+}
+
+/// Pulls (positive polarity) or pushes (negative) the player and anything `Metallic`
+/// within `radius`, strongest at the center and fading to nothing at the edge.
+#[derive(Component, Reflect, Copy, Clone, Debug, PartialEq, Deserialize)]
+#[reflect(Component)]
+pub struct Magnet {
+    /// Acceleration at the magnet's center, in pixels per second squared.
+    pub strength: f32,
+    pub radius: f32,
+    pub polarity: i8,
+}
+
+impl Magnet {
+    pub fn color(self) -> Color {
+        if self.polarity >= 0 {
+            Color::srgb(0.3, 0.4, 0.9)
+        } else {
+            Color::srgb(0.9, 0.3, 0.3)
+        }
+    }
+
+    /// Acceleration this magnet applies to a body at `at`.
+    fn force(self, magnet_at: Vec2, at: Vec2) -> Vec2 {
+        let offset = magnet_at - at;
+        let distance_squared = offset.length_squared();
+        if distance_squared >= self.radius * self.radius || distance_squared == 0. {
+            return Vec2::ZERO;
+        }
+        let distance = distance_squared.sqrt();
+        let falloff = 1. - distance / self.radius;
+        offset / distance * self.strength * falloff * self.polarity.signum() as f32
+    }
+}
+
+/// Bodies besides the player that magnets act on.
+#[derive(Component)]
+pub struct Metallic;
+
+/// Telegraphs a magnet's radius with a periodic ring of particles.
+#[derive(Component)]
+pub struct MagnetPulse(GameTimer);
+
+impl Default for MagnetPulse {
+    fn default() -> Self {
+        Self(GameTimer::repeating(PULSE_SECS))
+    }
+}
+
+fn apply_magnetism(
+    magnets: Query<(&Position, &Magnet)>,
+    mut bodies: Query<(&Position, &mut Velocity), (Or<(With<Player>, With<Metallic>)>, Without<InCannon>)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (position, mut velocity) in &mut bodies {
+        let force: Vec2 = magnets.iter()
+            .map(|(magnet_pos, magnet)| magnet.force(magnet_pos.0, position.0))
+            .sum();
+        velocity.0 += force * dt;
+    }
+}
+
+fn pulse_magnets(
+    mut commands: Commands,
+    mut magnets: Query<(&Position, &Magnet, &mut MagnetPulse)>,
+    time: Res<Time>,
+) {
+    for (position, magnet, mut pulse) in &mut magnets {
+        if pulse.0.tick(time.delta_seconds()).just_finished() {
+            let color = magnet.color().with_alpha(0.6);
+            spawn_ring(&mut commands, position.0, magnet.radius, color, PULSE_DOTS, PULSE_SECS, magnet.polarity >= 0);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+
+use crate::endless::{ChunkGenerator, ENDLESS_LEVEL};
+use crate::save::Profile;
+use crate::world::{level_names, CurrentLevel, LevelManager};
+use crate::GameState;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MainMenu>()
+            .add_systems(OnEnter(GameState::Menu), (list_levels, spawn_main_menu))
+            .add_systems(Update, (navigate_menu, refresh_main_menu).chain().run_if(in_state(GameState::Menu)))
+            .add_systems(OnExit(GameState::Menu), despawn_main_menu)
+            .add_systems(OnEnter(GameState::Won), spawn_win_screen)
+            .add_systems(Update, leave_win_screen.run_if(in_state(GameState::Won)))
+            .add_systems(OnExit(GameState::Won), despawn_win_screen);
+    }
+}
+
+/// The title screen. The first row plays the `CurrentLevel`, then come every level in
+/// the `LevelManager`'s play order, and last an endless run.
+#[derive(Resource, Default)]
+struct MainMenu {
+    selected: usize,
+}
+
+#[derive(Component)]
+struct MenuScreen;
+
+#[derive(Component)]
+struct MenuText;
+
+#[derive(Component)]
+struct WinScreen;
+
+/// Re-read each time the menu opens, so level files added while the game runs show up.
+fn list_levels(mut menu: ResMut<MainMenu>, mut manager: ResMut<LevelManager>) {
+    manager.levels = level_names();
+    menu.selected = 0;
+}
+
+fn spawn_main_menu(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(24.),
+            ..default()
+        },
+        background_color: Color::BLACK.into(),
+        z_index: ZIndex::Global(40),
+        ..default()
+    }, MenuScreen)).with_children(|screen| {
+        screen.spawn(TextBundle::from_section("Bevy Platformer", TextStyle {
+            font_size: 48.,
+            ..default()
+        }));
+        screen.spawn((TextBundle::from_section("", TextStyle {
+            font_size: 20.,
+            ..default()
+        }), MenuText));
+    });
+}
+
+fn despawn_main_menu(mut commands: Commands, screens: Query<Entity, With<MenuScreen>>) {
+    for screen in &screens {
+        commands.entity(screen).despawn_recursive();
+    }
+}
+
+/// Starting a level goes through `GameState::Restarting`, which loads it and spawns it
+/// the same way a restart would.
+fn navigate_menu(
+    mut menu: ResMut<MainMenu>,
+    manager: Res<LevelManager>,
+    mut current: ResMut<CurrentLevel>,
+    mut generator: ResMut<ChunkGenerator>,
+    mut next_state: ResMut<NextState<GameState>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+) {
+    let pressed_button = |button_type: GamepadButtonType| {
+        buttons.get_just_pressed().any(|button| button.button_type == button_type)
+    };
+    let rows = manager.levels.len() + 2;
+    let endless_row = rows - 1;
+    if menu.selected == endless_row {
+        // Left and right pick the seed, so a run can be shared or played again.
+        if keys.just_pressed(KeyCode::ArrowLeft) || pressed_button(GamepadButtonType::DPadLeft) {
+            generator.seed = generator.seed.saturating_sub(1);
+        } else if keys.just_pressed(KeyCode::ArrowRight) || pressed_button(GamepadButtonType::DPadRight) {
+            generator.seed += 1;
+        }
+    }
+    if keys.just_pressed(KeyCode::ArrowUp) || pressed_button(GamepadButtonType::DPadUp) {
+        menu.selected = (menu.selected + rows - 1) % rows;
+    } else if keys.just_pressed(KeyCode::ArrowDown) || pressed_button(GamepadButtonType::DPadDown) {
+        menu.selected = (menu.selected + 1) % rows;
+    } else if keys.just_pressed(KeyCode::Enter) || pressed_button(GamepadButtonType::South) {
+        if menu.selected == endless_row {
+            current.set_if_neq(CurrentLevel(ENDLESS_LEVEL.into()));
+        } else if let Some(level) = menu.selected.checked_sub(1).map(|row| manager.levels[row].clone()) {
+            current.set_if_neq(CurrentLevel(level));
+        }
+        next_state.set(GameState::Restarting);
+    }
+}
+
+fn refresh_main_menu(
+    menu: Res<MainMenu>,
+    manager: Res<LevelManager>,
+    current: Res<CurrentLevel>,
+    generator: Res<ChunkGenerator>,
+    profile: Res<Profile>,
+    mut text: Query<&mut Text, With<MenuText>>,
+) {
+    if !menu.is_changed() && !manager.is_changed() && !current.is_changed() && !generator.is_changed() && !profile.is_changed() {
+        return;
+    }
+    let cursor = |row: usize| if row == menu.selected { ">" } else { " " };
+    let mut value = format!("{} Play ({})\n\n", cursor(0), current.0);
+    if manager.levels.is_empty() {
+        value += "  No levels found in assets/levels/\n";
+    }
+    for (row, level) in manager.levels.iter().enumerate() {
+        let done = if profile.completed(level) { "[x]" } else { "[ ]" };
+        let coins = match profile.coins.get(level) {
+            Some(coins) => format!("   coins {coins}"),
+            None => String::new(),
+        };
+        value += &format!("{} {done} {level}{coins}\n", cursor(row + 1));
+    }
+    value += &format!("\n{} Endless run < seed {} >\n", cursor(manager.levels.len() + 1), generator.seed);
+    value += "\nArrows: choose   Enter: play";
+    for mut text in &mut text {
+        text.sections[0].value = value.clone();
+    }
+}
+
+fn spawn_win_screen(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(24.),
+            ..default()
+        },
+        background_color: Color::BLACK.into(),
+        z_index: ZIndex::Global(40),
+        ..default()
+    }, WinScreen)).with_children(|screen| {
+        screen.spawn(TextBundle::from_section("You win!", TextStyle {
+            font_size: 48.,
+            ..default()
+        }));
+        screen.spawn(TextBundle::from_section("Press Enter to return to the menu", TextStyle {
+            font_size: 20.,
+            ..default()
+        }));
+    });
+}
+
+fn leave_win_screen(
+    mut next_state: ResMut<NextState<GameState>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+) {
+    let confirm = keys.just_pressed(KeyCode::Enter)
+        || buttons.get_just_pressed().any(|button| button.button_type == GamepadButtonType::South);
+    if confirm {
+        next_state.set(GameState::Menu);
+    }
+}
+
+fn despawn_win_screen(mut commands: Commands, screens: Query<Entity, With<WinScreen>>) {
+    for screen in &screens {
+        commands.entity(screen).despawn_recursive();
+    }
+}
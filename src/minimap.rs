@@ -0,0 +1,397 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+
+use crate::camera::Camera;
+use crate::origin::WorldOrigin;
+use crate::physics::Position;
+use crate::player::{Player, PlayerId};
+use crate::safe_room::SafeRoom;
+use crate::world::{BlockKind, WorldData};
+
+/// Shows and hides the minimap; with shift held, switches `MinimapMode`.
+const TOGGLE_KEY: KeyCode = KeyCode::KeyM;
+/// World pixels per minimap pixel, half a grid cell. Levels too big for `MAX_IMAGE_SIZE`
+/// at this resolution get a coarser one.
+const MAP_RESOLUTION: f32 = 25.;
+const MAX_IMAGE_SIZE: f32 = 1024.;
+/// Empty minimap pixels kept around the level's bounds.
+const MAP_PADDING: f32 = 2.;
+/// Side length of a fog-of-war cell, in world pixels.
+const FOG_CELL: f32 = 400.;
+const PANEL_SIZE: Vec2 = Vec2::new(240., 160.);
+/// Screen pixels per minimap pixel in `MinimapMode::Follow`.
+const FOLLOW_ZOOM: f32 = 2.;
+const PLAYER_DOT: f32 = 6.;
+const CHECKPOINT_ICON: f32 = 8.;
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MinimapSettings>()
+            .init_resource::<ExploredCells>()
+            .add_systems(Startup, spawn_minimap)
+            .add_systems(Update, (
+                toggle_minimap,
+                rasterize_level,
+                explore,
+                draw_minimap,
+                (spawn_icons, frame_minimap, place_icons).chain(),
+            ).chain());
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MinimapMode {
+    /// Shrink the whole level to fit the panel.
+    #[default]
+    Fit,
+    /// Keep a fixed zoom and scroll the map around the player.
+    Follow,
+}
+
+#[derive(Resource, Debug)]
+pub struct MinimapSettings {
+    pub visible: bool,
+    pub mode: MinimapMode,
+    /// Only show the parts of the level the camera has been over.
+    pub fog_of_war: bool,
+}
+
+impl Default for MinimapSettings {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            mode: MinimapMode::Fit,
+            fog_of_war: true,
+        }
+    }
+}
+
+/// `FOG_CELL` cells the camera has had in view, in world coordinates so they stay put when
+/// the editor changes the level's bounds.
+#[derive(Resource, Default)]
+pub struct ExploredCells(pub HashSet<IVec2>);
+
+/// The level's collision layer, rasterized. `pixels` is the whole level; fog of war only
+/// decides which of them make it into the image.
+#[derive(Resource)]
+struct MinimapImage {
+    image: Handle<Image>,
+    pixels: Vec<[u8; 4]>,
+    size: UVec2,
+    /// World position of the image's top-left corner.
+    origin: Vec2,
+    /// World pixels per image pixel.
+    resolution: f32,
+}
+
+impl MinimapImage {
+    /// Where `world` falls on the image, from (0, 0) at the top-left to (1, 1).
+    fn fraction(&self, world: Vec2) -> Vec2 {
+        let extent = self.size.as_vec2() * self.resolution;
+        Vec2::new(world.x - self.origin.x, self.origin.y - world.y) / extent
+    }
+}
+
+#[derive(Component)]
+struct MinimapPanel;
+
+#[derive(Component)]
+struct MinimapMap;
+
+/// A dot on the map following `target` around, despawned along with it.
+#[derive(Component)]
+struct MinimapIcon {
+    target: Entity,
+    size: f32,
+}
+
+fn map_image(size: UVec2) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d { width: size.x, height: size.y, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+fn spawn_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    settings: Res<MinimapSettings>,
+) {
+    let image = images.add(map_image(UVec2::ONE));
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(16.),
+            bottom: Val::Px(16.),
+            width: Val::Px(PANEL_SIZE.x),
+            height: Val::Px(PANEL_SIZE.y),
+            overflow: Overflow::clip(),
+            ..default()
+        },
+        background_color: Color::srgba(0., 0., 0., 0.5).into(),
+        visibility: if settings.visible { Visibility::Inherited } else { Visibility::Hidden },
+        ..default()
+    }, MinimapPanel)).with_children(|panel| {
+        panel.spawn((ImageBundle {
+            image: UiImage::new(image.clone()),
+            style: Style {
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            ..default()
+        }, MinimapMap));
+    });
+    commands.insert_resource(MinimapImage {
+        image,
+        pixels: vec![[0; 4]],
+        size: UVec2::ONE,
+        origin: Vec2::ZERO,
+        resolution: MAP_RESOLUTION,
+    });
+}
+
+fn toggle_minimap(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<MinimapSettings>,
+    mut panel: Query<&mut Visibility, With<MinimapPanel>>,
+) {
+    let shift = kb_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if kb_input.just_pressed(TOGGLE_KEY) && shift {
+        settings.mode = match settings.mode {
+            MinimapMode::Fit => MinimapMode::Follow,
+            MinimapMode::Follow => MinimapMode::Fit,
+        };
+    } else if kb_input.just_pressed(TOGGLE_KEY) {
+        settings.visible = !settings.visible;
+    }
+    if !settings.is_changed() {
+        return;
+    }
+    for mut visibility in &mut panel {
+        *visibility = if settings.visible { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+fn block_color(kind: BlockKind) -> [u8; 4] {
+    match kind {
+        BlockKind::Solid | BlockKind::Slope { .. } => [200, 200, 210, 255],
+        BlockKind::Gate { .. } => [140, 140, 170, 150],
+        BlockKind::Cannon(_) | BlockKind::Magnet(_) | BlockKind::Spring(_) => [230, 190, 90, 255],
+        BlockKind::BoostPad(_) => [80, 205, 230, 255],
+        BlockKind::Conveyor { .. } => [120, 120, 140, 255],
+        BlockKind::Slime => [110, 220, 90, 255],
+        BlockKind::OneWay => [170, 150, 130, 200],
+        BlockKind::Checkpoint => [110, 200, 130, 90],
+        BlockKind::Spikes => [220, 70, 70, 255],
+        BlockKind::Breakable => [190, 130, 80, 255],
+        BlockKind::Ladder => [160, 120, 80, 120],
+        BlockKind::Exit { .. } => [240, 220, 120, 200],
+        BlockKind::GravityZone { .. } => [140, 100, 230, 70],
+        BlockKind::GravityFlip => [140, 90, 220, 255],
+        BlockKind::Wind { .. } => [220, 230, 255, 50],
+        BlockKind::Crusher(_) => [150, 150, 165, 255],
+        BlockKind::Blade(_) => [220, 70, 70, 160],
+        BlockKind::Crumbling { .. } => [180, 155, 115, 255],
+        BlockKind::Blinking { .. } => [115, 180, 205, 200],
+        BlockKind::Swinging(_) => [140, 150, 180, 255],
+        BlockKind::PressurePlate { .. } => [215, 140, 65, 255],
+        BlockKind::Activatable { .. } => [150, 130, 100, 200],
+    }
+}
+
+/// Redraws the map from scratch whenever `WorldData` changes, editor changes included.
+fn rasterize_level(
+    world_data: Query<&WorldData, Changed<WorldData>>,
+    mut map: ResMut<MinimapImage>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Ok(world) = world_data.get_single() else {
+        return;
+    };
+    let Some((min, max)) = world.0.iter()
+        .map(|block| (block.position - block.shape / 2., block.position + block.shape / 2.))
+        .reduce(|(min, max), (lo, hi)| (min.min(lo), max.max(hi)))
+    else {
+        return;
+    };
+
+    let extent = max - min;
+    let resolution = MAP_RESOLUTION.max(extent.max_element() / MAX_IMAGE_SIZE);
+    let size = ((extent / resolution).ceil() + 2. * MAP_PADDING).as_uvec2().max(UVec2::ONE);
+    let origin = Vec2::new(min.x, max.y) + Vec2::new(-MAP_PADDING, MAP_PADDING) * resolution;
+
+    let mut pixels = vec![[0; 4]; (size.x * size.y) as usize];
+    // Moving platforms would be drawn wherever they start, so they're left off.
+    for block in world.0.iter().filter(|block| block.path.is_none()) {
+        let top_left = Vec2::new(block.position.x - block.shape.x / 2., block.position.y + block.shape.y / 2.);
+        let first = (Vec2::new(top_left.x - origin.x, origin.y - top_left.y) / resolution).floor().as_uvec2();
+        let last = ((Vec2::new(top_left.x - origin.x, origin.y - top_left.y) + block.shape) / resolution)
+            .ceil().as_uvec2().min(size);
+        let color = block_color(block.kind);
+        for y in first.y..last.y {
+            for x in first.x..last.x {
+                let pixel = &mut pixels[(y * size.x + x) as usize];
+                // Gates are see-through, so don't let one paint over solid ground.
+                if pixel[3] < color[3] {
+                    *pixel = color;
+                }
+            }
+        }
+    }
+
+    let map = &mut *map;
+    map.pixels = pixels;
+    map.size = size;
+    map.origin = origin;
+    map.resolution = resolution;
+    images.insert(&map.image, map_image(size));
+}
+
+fn fog_cell(world: Vec2) -> IVec2 {
+    (world / FOG_CELL).floor().as_ivec2()
+}
+
+fn explore(
+    camera: Query<(&Position, &OrthographicProjection), With<Camera>>,
+    mut explored: ResMut<ExploredCells>,
+    origin: Res<WorldOrigin>,
+) {
+    let Ok((position, projection)) = camera.get_single() else {
+        return;
+    };
+    let position = origin.to_level(position.0);
+    let first = fog_cell(position + projection.area.min);
+    let last = fog_cell(position + projection.area.max);
+    for x in first.x..=last.x {
+        for y in first.y..=last.y {
+            let cell = IVec2::new(x, y);
+            if !explored.0.contains(&cell) {
+                explored.0.insert(cell);
+            }
+        }
+    }
+}
+
+/// Copies the explored part of the level into the image, or all of it without fog of war.
+fn draw_minimap(
+    map: Res<MinimapImage>,
+    explored: Res<ExploredCells>,
+    settings: Res<MinimapSettings>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !(map.is_changed() || explored.is_changed() || settings.is_changed()) {
+        return;
+    }
+    let Some(image) = images.get_mut(&map.image) else {
+        return;
+    };
+    for (index, pixel) in map.pixels.iter().enumerate() {
+        let (x, y) = (index as u32 % map.size.x, index as u32 / map.size.x);
+        let world = map.origin + Vec2::new(x as f32 + 0.5, -(y as f32 + 0.5)) * map.resolution;
+        let shown = !settings.fog_of_war || explored.0.contains(&fog_cell(world));
+        let color = if shown { *pixel } else { [0; 4] };
+        image.data[index * 4..index * 4 + 4].copy_from_slice(&color);
+    }
+}
+
+fn spawn_icons(
+    mut commands: Commands,
+    players: Query<Entity, Added<Player>>,
+    checkpoints: Query<Entity, Added<SafeRoom>>,
+    icons: Query<(Entity, &MinimapIcon)>,
+    alive: Query<(), With<Position>>,
+    map: Query<Entity, With<MinimapMap>>,
+) {
+    for (icon, MinimapIcon { target, .. }) in &icons {
+        if !alive.contains(*target) {
+            commands.entity(icon).despawn_recursive();
+        }
+    }
+    let Ok(map) = map.get_single() else {
+        return;
+    };
+    let icon = |target: Entity, size: f32, color: Color, radius: BorderRadius| (NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Px(size),
+            height: Val::Px(size),
+            ..default()
+        },
+        background_color: color.into(),
+        border_radius: radius,
+        ..default()
+    }, MinimapIcon { target, size });
+    commands.entity(map).with_children(|map| {
+        for target in &checkpoints {
+            map.spawn(icon(target, CHECKPOINT_ICON, Color::srgb(0.95, 0.6, 0.3), BorderRadius::ZERO));
+        }
+        // Spawned last so it draws over the checkpoints.
+        for target in &players {
+            map.spawn(icon(target, PLAYER_DOT, Color::WHITE, BorderRadius::MAX));
+        }
+    });
+}
+
+/// Sizes and scrolls the map inside the panel according to `MinimapSettings::mode`.
+fn frame_minimap(
+    map: Res<MinimapImage>,
+    settings: Res<MinimapSettings>,
+    player: Query<(&Position, &PlayerId), With<Player>>,
+    mut node: Query<&mut Style, With<MinimapMap>>,
+    origin: Res<WorldOrigin>,
+) {
+    let Ok(mut style) = node.get_single_mut() else {
+        return;
+    };
+    let image_size = map.size.as_vec2();
+    let (scale, offset) = match settings.mode {
+        MinimapMode::Fit => {
+            let scale = (PANEL_SIZE / image_size).min_element();
+            (scale, (PANEL_SIZE - image_size * scale) / 2.)
+        }
+        MinimapMode::Follow => {
+            // Follows the first player; the second is only a dot.
+            let center = player.iter()
+                .find(|(_, id)| **id == PlayerId::ONE)
+                .map(|(position, _)| map.fraction(origin.to_level(position.0)))
+                .unwrap_or(Vec2::splat(0.5));
+            (FOLLOW_ZOOM, PANEL_SIZE / 2. - center * image_size * FOLLOW_ZOOM)
+        }
+    };
+    style.width = Val::Px(image_size.x * scale);
+    style.height = Val::Px(image_size.y * scale);
+    style.left = Val::Px(offset.x);
+    style.top = Val::Px(offset.y);
+}
+
+fn place_icons(
+    map: Res<MinimapImage>,
+    targets: Query<&Position>,
+    mut icons: Query<(&MinimapIcon, &mut Style)>,
+    origin: Res<WorldOrigin>,
+) {
+    for (icon, mut style) in &mut icons {
+        let Ok(position) = targets.get(icon.target) else {
+            continue;
+        };
+        let fraction = map.fraction(origin.to_level(position.0)) * 100.;
+        style.left = Val::Percent(fraction.x);
+        style.top = Val::Percent(fraction.y);
+        style.margin = UiRect {
+            left: Val::Px(-icon.size / 2.),
+            top: Val::Px(-icon.size / 2.),
+            ..default()
+        };
+    }
+}
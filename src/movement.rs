@@ -0,0 +1,479 @@
+use std::{fs, io};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cutscene::cutscene_playing;
+use crate::input::{Action, Actions, PlayerInput};
+use crate::physics::{gravitate, Collision, Contacts, GlobalGravity, PostCollide, Up, Velocity};
+use crate::player::{control_player, Grounded, Player, PlayerId};
+use crate::timer::GameTimer;
+use crate::world::SurfaceKind;
+
+/// Letting go of jump while still rising keeps this much of the upward speed.
+const JUMP_CUT: f32 = 0.4;
+/// How long into a jump letting go still cuts it short. Holding past this, or letting go
+/// after the apex, leaves the jump alone, so a full hold always reaches the same height.
+const JUMP_HOLD_SECS: f32 = 0.25;
+const CONFIG_PATH: &str = "config/movement.ron";
+
+pub struct MovementPlugin;
+
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MovementConfig::load())
+            .add_systems(FixedUpdate, (
+                apply_movement_config.before(gravitate).run_if(resource_changed::<MovementConfig>),
+                (expire_modifiers, expire_control_lock, tick_dash).before(control_player),
+                apply_jump_modulation.after(control_player).before(gravitate).run_if(not(cutscene_playing)),
+                (update_wall_contact, wall_run).chain().in_set(PostCollide),
+                update_standing_on.in_set(PostCollide),
+            ));
+    }
+}
+
+/// Tunables for the player's movement. Speeds are in pixels per second. Loaded from
+/// `config/movement.ron` when there is one, where any field left out keeps its default.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MovementConfig {
+    /// Walking speed with the stick or keys held all the way.
+    pub max_speed: f32,
+    /// The same while holding run.
+    pub run_speed: f32,
+    /// How quickly the player speeds up toward the target speed, as an exponential rate
+    /// per second: at 7.4 about half the gap closes in a tenth of a second.
+    pub accel: f32,
+    /// The same when slowing down or turning around.
+    pub decel: f32,
+    /// The accel rate while running, lower so a sprint takes a moment to build.
+    pub run_accel: f32,
+    /// The accel and decel rates in the air, lower than on the ground so a jump keeps
+    /// its momentum and can only be steered, not turned around on the spot.
+    pub air_accel: f32,
+    pub air_decel: f32,
+    /// Upward speed of a grounded jump; wall, air and swim jumps are a share of it.
+    pub jump_strength: f32,
+    /// World gravity in pixels per second squared.
+    pub gravity: f32,
+    /// How quickly the player's squash and stretch eases back to its shape, per second.
+    pub squash_snappiness: f32,
+    /// Horizontal speed going into a wall needed to start a wall-run.
+    pub wall_run_min_speed: f32,
+    /// Upward speed at the start of a wall-run; it decays to zero over the run.
+    pub wall_run_speed: f32,
+    pub wall_run_secs: f32,
+    /// Fastest the player falls while sliding down a wall.
+    pub wall_slide_speed: f32,
+    /// Pulled in from the left and right of the player's shape for collisions, so gaps
+    /// and ledges as wide as the sprite are forgiving.
+    pub hitbox_inset: f32,
+    /// How far below the feet a grounded player looks for ground to stay stuck to when
+    /// walking down steps. Anything deeper is a ledge.
+    pub ground_snap_distance: f32,
+    /// Widest overlap with a block's corner that gets nudged past instead of stopping
+    /// the body: sideways around a ceiling on the way up, up onto a ledge on the way down.
+    pub corner_correction: f32,
+    pub dash_speed: f32,
+    pub dash_secs: f32,
+    /// Wait after a dash ends before the next one.
+    pub dash_cooldown_secs: f32,
+    /// The accel rate when speeding up on ice.
+    pub ice_accel: f32,
+    /// The same when slowing down on ice; small, so the player slides.
+    pub ice_decel: f32,
+    /// Steepest slope, in degrees, that can be stood on and walked up. Anything steeper is
+    /// a wall to slide down.
+    pub max_slope_degrees: f32,
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        Self {
+            max_speed: 720.,
+            run_speed: 1152.,
+            accel: 7.4,
+            decel: 12.,
+            run_accel: 5.,
+            air_accel: 4.,
+            air_decel: 2.,
+            jump_strength: 1152.,
+            gravity: -4147.2,
+            squash_snappiness: 7.4,
+            wall_run_min_speed: 504.,
+            wall_run_speed: 576.,
+            wall_run_secs: 0.6,
+            wall_slide_speed: 216.,
+            hitbox_inset: 4.,
+            ground_snap_distance: 8.,
+            corner_correction: 12.,
+            dash_speed: 2160.,
+            dash_secs: 0.15,
+            dash_cooldown_secs: 0.8,
+            ice_accel: 2.9,
+            ice_decel: 1.15,
+            max_slope_degrees: 45.,
+        }
+    }
+}
+
+impl MovementConfig {
+    /// `max_slope_degrees` in radians, as `Slope::too_steep` takes it.
+    pub fn max_slope(&self) -> f32 {
+        self.max_slope_degrees.to_radians()
+    }
+
+    /// The defaults, overridden by `config/movement.ron` if it exists. A broken file gets
+    /// a warning and is ignored.
+    pub fn load() -> Self {
+        let text = match fs::read_to_string(CONFIG_PATH) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(error) => {
+                warn!("couldn't read {CONFIG_PATH}: {error}");
+                return Self::default();
+            }
+        };
+        ron::from_str(&text).unwrap_or_else(|error| {
+            warn!("{CONFIG_PATH} is broken, using the default movement: {error}");
+            Self::default()
+        })
+    }
+
+    /// Writes every value out to `config/movement.ron`, for `load` to pick up next run.
+    #[cfg(feature = "debug-tools")]
+    pub fn save(&self) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, default()).map_err(io::Error::other)?;
+        if let Some(dir) = std::path::Path::new(CONFIG_PATH).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(CONFIG_PATH, text)
+    }
+}
+
+/// Gravity lives in `GlobalGravity` for `gravitate`, so a change to the config reaches
+/// every body already in the level, not just ones spawned after it.
+fn apply_movement_config(config: Res<MovementConfig>, mut gravity: ResMut<GlobalGravity>) {
+    gravity.0.y = config.gravity;
+}
+
+/// A movement stat that `StatModifier`s can scale, resolved by the system that reads it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatId {
+    MaxSpeed,
+    Accel,
+    JumpStrength,
+    GravityScale,
+    WallRunSpeed,
+}
+
+/// Scales one stat by `multiplier`, for `duration` seconds or until the player respawns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatModifier {
+    pub stat: StatId,
+    pub multiplier: f32,
+    pub duration: Option<f32>,
+}
+
+/// Modifiers currently applied to the player. Stacking ones multiply together.
+#[derive(Component, Default)]
+pub struct MovementModifiers(Vec<(StatModifier, Option<GameTimer>)>);
+
+impl MovementModifiers {
+    pub fn push(&mut self, modifier: StatModifier) {
+        let timer = modifier.duration.map(GameTimer::once);
+        self.0.push((modifier, timer));
+    }
+
+    /// Takes back one `push` of `modifier`, for ones whose source ends early.
+    pub fn remove(&mut self, modifier: StatModifier) {
+        if let Some(index) = self.0.iter().position(|(pushed, _)| *pushed == modifier) {
+            self.0.remove(index);
+        }
+    }
+
+    pub fn get(&self, stat: StatId) -> f32 {
+        self.0.iter()
+            .filter(|(modifier, _)| modifier.stat == stat)
+            .map(|(modifier, _)| modifier.multiplier)
+            .product()
+    }
+}
+
+/// Takes steering away from the player for a moment, so a scripted launch or a knockback
+/// carries them instead of being lerped away by held (or released) movement keys.
+#[derive(Component)]
+pub struct ControlLock(GameTimer);
+
+impl ControlLock {
+    pub fn new(secs: f32) -> Self {
+        Self(GameTimer::once(secs))
+    }
+}
+
+fn expire_control_lock(mut commands: Commands, mut locks: Query<(Entity, &mut ControlLock)>, time: Res<Time>) {
+    for (entity, mut lock) in &mut locks {
+        if lock.0.tick(time.delta_seconds()).finished() {
+            commands.entity(entity).remove::<ControlLock>();
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DashState {
+    #[default]
+    Ready,
+    Dashing,
+    Cooldown,
+}
+
+/// Whether the player is walking or running, for the animation and sounds to follow.
+/// `control_player` only changes it on the ground, so letting go of run in mid-air keeps
+/// the speed the jump took off with.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MovementMode {
+    #[default]
+    Walk,
+    Run,
+}
+
+/// The player's dash. `control_player` starts one; while it runs, steering is off and
+/// `gravitate` leaves the body alone, so it flies dead level until a wall stops it.
+#[derive(Component, Default)]
+pub struct Dash {
+    pub state: DashState,
+    timer: GameTimer,
+    cooldown: GameTimer,
+    /// Whether the one dash allowed per trip through the air has been spent.
+    air_dash_used: bool,
+    /// Set while the save being played hasn't unlocked dashing.
+    pub locked: bool,
+}
+
+impl Dash {
+    pub fn is_dashing(&self) -> bool {
+        self.state == DashState::Dashing
+    }
+
+    pub fn can_dash(&self, grounded: bool) -> bool {
+        !self.locked && self.state == DashState::Ready && (grounded || !self.air_dash_used)
+    }
+
+    pub fn start(&mut self, grounded: bool, config: &MovementConfig) {
+        self.state = DashState::Dashing;
+        self.timer = GameTimer::once(config.dash_secs);
+        self.air_dash_used |= !grounded;
+    }
+}
+
+/// Runs even during cutscenes, so a dash that one interrupts can't leave gravity off.
+fn tick_dash(mut dashes: Query<(&mut Dash, &Grounded)>, config: Res<MovementConfig>, time: Res<Time>) {
+    let dt = time.delta_seconds();
+    for (mut dash, grounded) in &mut dashes {
+        let dash = &mut *dash;
+        if grounded.0 {
+            dash.air_dash_used = false;
+        }
+        match dash.state {
+            DashState::Dashing if dash.timer.tick(dt).finished() => {
+                dash.state = DashState::Cooldown;
+                dash.cooldown = GameTimer::once(config.dash_cooldown_secs);
+            }
+            DashState::Cooldown if dash.cooldown.tick(dt).finished() => {
+                dash.state = DashState::Ready;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A jump still rising under the player's control. Inserted by `control_player`.
+#[derive(Component)]
+pub struct Jumping {
+    pub time_held: f32,
+    /// Upward speed the jump started with. Anything that throws the player faster than
+    /// this (a spring, a cannon) takes over and ends the jump.
+    pub speed: f32,
+}
+
+/// Cuts a jump short when the key comes up early, for short hops.
+fn apply_jump_modulation(
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut Velocity, &mut Jumping, Option<&Up>, Option<&PlayerId>)>,
+    actions: Actions,
+    time: Res<Time>,
+) {
+    for (entity, mut velocity, mut jumping, up, id) in &mut player {
+        let held = id.is_some_and(|id| actions.player(*id).pressed(Action::Jump));
+        let rise = velocity.0.y * up.map_or(1., |up| up.0);
+        let rising = rise > 0. && rise <= jumping.speed;
+        if !rising || jumping.time_held >= JUMP_HOLD_SECS {
+            commands.entity(entity).remove::<Jumping>();
+        } else if !held {
+            velocity.0.y *= JUMP_CUT;
+            commands.entity(entity).remove::<Jumping>();
+        } else {
+            jumping.time_held += time.delta_seconds();
+        }
+    }
+}
+
+fn expire_modifiers(mut modifiers: Query<&mut MovementModifiers>, time: Res<Time>) {
+    for mut modifiers in &mut modifiers {
+        modifiers.0.retain_mut(|(_, timer)| {
+            timer.as_mut().is_none_or(|timer| !timer.tick(time.delta_seconds()).finished())
+        });
+    }
+}
+
+/// The side of the player a wall is touching this tick, if any, for wall jumps.
+#[derive(Component, Default, PartialEq)]
+pub struct WallContact(pub Option<Collision>);
+
+fn update_wall_contact(mut bodies: Query<(Entity, &mut WallContact)>, contacts: Res<Contacts>) {
+    for (entity, mut wall) in &mut bodies {
+        let side = contacts.of(entity)
+            .map(|contact| contact.side)
+            .find(|side| matches!(side, Collision::Left | Collision::Right));
+        wall.set_if_neq(WallContact(side));
+    }
+}
+
+/// A belt along the top of a block that carries whatever stands on it, in pixels per second.
+/// Positive runs to the right.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Conveyor {
+    pub speed: f32,
+}
+
+/// How a piece of ground handles underfoot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SurfaceMaterial {
+    Normal,
+    Ice,
+    Conveyor { speed: f32 },
+}
+
+/// What the player stood on this tick, or `None` in the air.
+#[derive(Component, Default, PartialEq)]
+pub struct StandingOn(pub Option<SurfaceMaterial>);
+
+/// Walking across the seam between two different surfaces touches both for a tick or
+/// two; the one already underfoot wins until the player is off it entirely.
+fn update_standing_on(
+    mut players: Query<(Entity, &mut StandingOn, &Up), With<Player>>,
+    ground: Query<(Option<&SurfaceKind>, Option<&Conveyor>)>,
+    contacts: Res<Contacts>,
+) {
+    for (entity, mut standing_on, up) in &mut players {
+        let underfoot: Vec<SurfaceMaterial> = contacts.of(entity)
+            .filter(|contact| contact.side == up.feet())
+            .filter_map(|contact| ground.get(contact.other).ok())
+            .map(|(surface, conveyor)| match (surface, conveyor) {
+                (_, Some(conveyor)) => SurfaceMaterial::Conveyor { speed: conveyor.speed },
+                (Some(SurfaceKind::Ice), None) => SurfaceMaterial::Ice,
+                _ => SurfaceMaterial::Normal,
+            })
+            .collect();
+        let next = standing_on.0.filter(|current| underfoot.contains(current)).or(underfoot.first().copied());
+        standing_on.set_if_neq(StandingOn(next));
+    }
+}
+
+/// Which wall sides have been run on since the player last stood on the ground.
+#[derive(Component, Default)]
+pub struct WallRunner {
+    used_left: bool,
+    used_right: bool,
+}
+
+impl WallRunner {
+    fn used(&mut self, side: Collision) -> &mut bool {
+        match side {
+            Collision::Left => &mut self.used_left,
+            _ => &mut self.used_right,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct WallRun {
+    /// Side of the player the wall is on.
+    pub side: Collision,
+    timer: GameTimer,
+}
+
+/// Sliding down a wall the player is holding toward, once a run has run out or when
+/// there was no run to be had.
+#[derive(Component)]
+pub struct WallSlide {
+    pub side: Collision,
+}
+
+fn holding_toward(side: Collision, input: &PlayerInput) -> bool {
+    match side {
+        Collision::Left => input.move_x() < 0.,
+        Collision::Right => input.move_x() > 0.,
+        _ => false,
+    }
+}
+
+fn wall_run(
+    mut commands: Commands,
+    mut players: Query<(Entity, &PlayerId, &mut Velocity, &Grounded, &mut WallRunner, Option<&mut WallRun>, Option<&WallSlide>, &MovementModifiers, &Up), With<Player>>,
+    contacts: Res<Contacts>,
+    config: Res<MovementConfig>,
+    actions: Actions,
+    time: Res<Time>,
+) {
+    for (entity, id, mut velocity, grounded, mut runner, run, slide, modifiers, up) in &mut players {
+        // Runs go up the wall and slides down it, both the other way upside down.
+        let run_speed = config.wall_run_speed * modifiers.get(StatId::WallRunSpeed) * up.0;
+        if grounded.0 {
+            *runner = WallRunner::default();
+            commands.entity(entity).remove::<(WallRun, WallSlide)>();
+            continue;
+        }
+        let wall = contacts.of(entity)
+            .find(|contact| matches!(contact.side, Collision::Left | Collision::Right))
+            .filter(|contact| holding_toward(contact.side, &actions.player(*id)));
+
+        if let Some(mut run) = run {
+            run.timer.tick(time.delta_seconds());
+            match wall {
+                Some(contact) if contact.side == run.side && !run.timer.finished() => {
+                    velocity.0.y = run_speed * (1. - run.timer.fraction());
+                }
+                Some(contact) if contact.side == run.side => {
+                    commands.entity(entity).remove::<WallRun>().insert(WallSlide { side: run.side });
+                }
+                _ => {
+                    commands.entity(entity).remove::<WallRun>();
+                }
+            }
+            continue;
+        }
+
+        let Some(contact) = wall else {
+            if slide.is_some() {
+                commands.entity(entity).remove::<WallSlide>();
+            }
+            continue;
+        };
+        let used = runner.used(contact.side);
+        if !*used && contact.velocity.x.abs() >= config.wall_run_min_speed {
+            *used = true;
+            velocity.0.y = run_speed;
+            commands.entity(entity).remove::<WallSlide>().insert(WallRun {
+                side: contact.side,
+                timer: GameTimer::once(config.wall_run_secs),
+            });
+        } else if velocity.0.y * up.0 <= 0. {
+            // Out of wall-runs, or too slow for one: hug the wall and slide down it.
+            if slide.is_none_or(|slide| slide.side != contact.side) {
+                commands.entity(entity).insert(WallSlide { side: contact.side });
+            }
+            velocity.0.y = (velocity.0.y * up.0).max(-config.wall_slide_speed) * up.0;
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use crate::boss_bar::ShowBossBar;
+use crate::enemy::AiState;
+use crate::physics::Velocity;
+use crate::player::Player;
+use crate::water::InWater;
+
+#[cfg(feature = "audio")]
+mod imp;
+
+/// Horizontal speed, in pixels per second, that counts as moving fast.
+const FAST_SPEED: f32 = 576.;
+const FAST_INTENSITY: f32 = 0.5;
+const WATER_INTENSITY: f32 = 0.5;
+const BOSS_INTENSITY: f32 = 1.;
+/// How quickly `MusicIntensity::level` climbs toward what gameplay asks for, per second.
+const RISE_RATE: f32 = 1.;
+/// How quickly it falls back. Drums fully in at 0.5 take two seconds to fade out.
+const FALL_RATE: f32 = 0.25;
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MusicIntensity>()
+            .add_systems(Update, (gauge_intensity, smooth_intensity).chain());
+        #[cfg(feature = "audio")]
+        app.add_plugins(imp::StemPlaybackPlugin);
+    }
+}
+
+/// Which part of the arrangement a stem plays. The base loop is always on; the others fade
+/// in as the music gets more intense.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MusicLayer {
+    Base,
+    Drums,
+    Lead,
+}
+
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+#[derive(Debug)]
+pub struct MusicStem {
+    pub path: String,
+    pub layer: MusicLayer,
+}
+
+/// The level's music, stored next to the `WorldData` it belongs to. Stems that fail to
+/// load are left out and the rest play without them.
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+#[derive(Component)]
+pub struct MusicStems(pub Vec<MusicStem>);
+
+/// How intense the music should be, from 0 to 1. Gameplay calls `raise` every frame the
+/// situation holds; the music system eases `level` toward the highest request and starts
+/// over the next frame.
+#[derive(Resource, Default)]
+pub struct MusicIntensity {
+    target: f32,
+    pub level: f32,
+}
+
+impl MusicIntensity {
+    pub fn raise(&mut self, to: f32) {
+        self.target = self.target.max(to);
+    }
+}
+
+fn gauge_intensity(
+    mut intensity: ResMut<MusicIntensity>,
+    players: Query<(&Velocity, Has<InWater>), With<Player>>,
+    bosses: Query<&AiState, With<ShowBossBar>>,
+) {
+    for (velocity, in_water) in &players {
+        if velocity.0.x.abs() >= FAST_SPEED {
+            intensity.raise(FAST_INTENSITY);
+        }
+        if in_water {
+            intensity.raise(WATER_INTENSITY);
+        }
+    }
+    if bosses.iter().any(|state| matches!(state, AiState::Chasing { .. })) {
+        intensity.raise(BOSS_INTENSITY);
+    }
+}
+
+fn smooth_intensity(mut intensity: ResMut<MusicIntensity>, time: Res<Time>) {
+    let dt = time.delta_seconds();
+    let intensity = &mut *intensity;
+    intensity.level = if intensity.target > intensity.level {
+        (intensity.level + RISE_RATE * dt).min(intensity.target)
+    } else {
+        (intensity.level - FALL_RATE * dt).max(intensity.target)
+    };
+    intensity.target = 0.;
+}
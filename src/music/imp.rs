@@ -0,0 +1,117 @@
+use bevy::asset::LoadState;
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+
+use crate::settings::Settings;
+use crate::world::WorldData;
+use crate::GameState;
+
+use super::{smooth_intensity, MusicIntensity, MusicLayer, MusicStems};
+
+const MUSIC_VOLUME: f32 = 0.6;
+
+pub struct StemPlaybackPlugin;
+
+impl Plugin for StemPlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Restarting), load_stems.after(crate::world::init_world))
+            .add_systems(OnEnter(GameState::Menu), stop_stems)
+            .add_systems(OnEnter(GameState::Paused), pause_stems)
+            .add_systems(OnExit(GameState::Paused), resume_stems)
+            .add_systems(Update, (start_stems, mix_stems).chain().after(smooth_intensity));
+    }
+}
+
+impl MusicLayer {
+    /// This layer's volume, 0 to 1, at `intensity`.
+    fn volume(self, intensity: f32) -> f32 {
+        match self {
+            MusicLayer::Base => 1.,
+            MusicLayer::Drums => (intensity / 0.5).clamp(0., 1.),
+            MusicLayer::Lead => ((intensity - 0.5) / 0.5).clamp(0., 1.),
+        }
+    }
+}
+
+/// Stems still loading. They all start playing together once every one has either
+/// loaded or failed.
+#[derive(Resource)]
+struct PendingStems(Vec<(MusicLayer, Handle<AudioSource>)>);
+
+/// One playing stem. Every stem runs from the same instant at all times, muted or not,
+/// and only its volume ever changes, so the layers can't drift apart.
+#[derive(Component)]
+struct StemPlayer(MusicLayer);
+
+/// Only for a level that was just loaded, which takes over from the last level's music;
+/// restarting one keeps its music going.
+fn load_stems(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    world_data: Query<Option<&MusicStems>, Added<WorldData>>,
+    playing: Query<Entity, With<StemPlayer>>,
+) {
+    let Ok(stems) = world_data.get_single() else {
+        return;
+    };
+    for stem in &playing {
+        commands.entity(stem).despawn();
+    }
+    let Some(stems) = stems else {
+        commands.remove_resource::<PendingStems>();
+        return;
+    };
+    commands.insert_resource(PendingStems(stems.0.iter()
+        .map(|stem| (stem.layer, asset_server.load(stem.path.clone())))
+        .collect()));
+}
+
+fn start_stems(mut commands: Commands, pending: Option<Res<PendingStems>>, asset_server: Res<AssetServer>) {
+    let Some(pending) = pending else {
+        return;
+    };
+    let states: Vec<_> = pending.0.iter().map(|(_, handle)| asset_server.load_state(handle)).collect();
+    if states.iter().any(|state| matches!(state, LoadState::NotLoaded | LoadState::Loading)) {
+        return;
+    }
+    for ((layer, handle), state) in pending.0.iter().zip(states) {
+        if state != LoadState::Loaded {
+            warn!("music stem {:?} didn't load, playing without it", asset_server.get_path(handle));
+            continue;
+        }
+        commands.spawn((
+            AudioBundle {
+                source: handle.clone(),
+                settings: PlaybackSettings::LOOP.with_volume(Volume::new(0.)),
+            },
+            StemPlayer(*layer),
+        ));
+    }
+    commands.remove_resource::<PendingStems>();
+}
+
+fn stop_stems(mut commands: Commands, stems: Query<Entity, With<StemPlayer>>) {
+    commands.remove_resource::<PendingStems>();
+    for stem in &stems {
+        commands.entity(stem).despawn();
+    }
+}
+
+/// The music holds its place under the pause menu and carries on from there.
+fn pause_stems(stems: Query<&AudioSink, With<StemPlayer>>) {
+    for sink in &stems {
+        sink.pause();
+    }
+}
+
+fn resume_stems(stems: Query<&AudioSink, With<StemPlayer>>) {
+    for sink in &stems {
+        sink.play();
+    }
+}
+
+fn mix_stems(intensity: Res<MusicIntensity>, settings: Res<Settings>, stems: Query<(&StemPlayer, &AudioSink)>) {
+    for (stem, sink) in &stems {
+        sink.set_volume(stem.0.volume(intensity.level) * MUSIC_VOLUME * settings.music_gain());
+    }
+}
@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+
+use crate::events::OriginShifted;
+use crate::level::{ResetLevel, GROUND_COLUMN_WIDTH};
+use crate::physics::{Position, PreviousPosition};
+use crate::player::Player;
+use crate::GameState;
+
+/// How far from the origin the players can get before the world is moved back under
+/// them. Well short of where f32 steps get big enough to see.
+const REBASE_DISTANCE: f32 = 10_000.;
+
+pub struct OriginPlugin;
+
+impl Plugin for OriginPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldOrigin>()
+            .configure_sets(FixedPostUpdate, FollowOrigin.after(rebase_origin))
+            .add_systems(OnEnter(GameState::Restarting), reset_origin.in_set(ResetLevel))
+            .add_systems(FixedPostUpdate, rebase_origin);
+    }
+}
+
+/// Where the live world's zero is in level coordinates. Level data (`WorldData`, the spawn
+/// lists beside it, endless chunks) is always in level coordinates; every `Position` is
+/// relative to this, which stays zero until the players go far enough out to move it.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct WorldOrigin(pub Vec2);
+
+impl WorldOrigin {
+    /// A live position in level coordinates, for scores, recordings and level data.
+    pub fn to_level(self, live: Vec2) -> Vec2 {
+        live + self.0
+    }
+
+    /// Where a point from level data is in the live world.
+    pub fn to_live(self, level: Vec2) -> Vec2 {
+        level - self.0
+    }
+}
+
+/// Systems that keep world positions of their own, outside `Position`, shifting them by
+/// each `OriginShifted`. Runs in `FixedPostUpdate` straight after the bodies move, so
+/// nothing sees the two out of step.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FollowOrigin;
+
+/// A restart spawns everything straight from the level data.
+fn reset_origin(mut origin: ResMut<WorldOrigin>) {
+    origin.0 = Vec2::ZERO;
+}
+
+/// Moves everything with a `Position` by whole ground columns, so the players end up
+/// back near zero, all in one tick. `PreviousPosition` moves too, so nothing drawn in
+/// between ticks slides.
+pub fn rebase_origin(
+    mut bodies: Query<(&mut Position, Option<&mut PreviousPosition>, Has<Player>)>,
+    mut origin: ResMut<WorldOrigin>,
+    mut shifted: EventWriter<OriginShifted>,
+) {
+    let players: Vec<Vec2> = bodies.iter().filter(|(.., player)| *player).map(|(position, ..)| position.0).collect();
+    if players.is_empty() {
+        return;
+    }
+    let center = players.iter().sum::<Vec2>() / players.len() as f32;
+    if center.length() < REBASE_DISTANCE {
+        return;
+    }
+    let offset = (center / GROUND_COLUMN_WIDTH).round() * GROUND_COLUMN_WIDTH;
+    for (mut position, previous, _) in &mut bodies {
+        position.0 -= offset;
+        if let Some(mut previous) = previous {
+            previous.0 -= offset;
+        }
+    }
+    origin.0 += offset;
+    shifted.send(OriginShifted { offset });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::query::QueryFilter;
+    use bevy::math::bounding::Aabb2d;
+
+    use super::*;
+    use crate::checkpoint::Checkpoint;
+    use crate::events::DamageEvent;
+    use crate::headless::build_headless_app;
+    use crate::input::{Action, ScriptedInput};
+    use crate::physics::Collider;
+    use crate::platform::{MovingPlatform, PlatformPath};
+    use crate::player::PlayerSpawn;
+    use crate::spatial::SpatialGrid;
+    use crate::world::{BlockData, BlockKind, WorldData};
+
+    /// Where things are in the level, all just short of `REBASE_DISTANCE` out. The floor's
+    /// top is at -175, so the player stands with its center at -125.
+    const FLOOR: Vec2 = Vec2::new(10_000., -200.);
+    const CHECKPOINT: Vec2 = Vec2::new(9_800., -125.);
+    const PLATFORM: Vec2 = Vec2::new(10_400., 100.);
+
+    fn level() -> App {
+        let mut app = build_headless_app(WorldData(vec![
+            BlockData::new(FLOOR, Vec2::new(2000., 50.)),
+            BlockData { kind: BlockKind::Checkpoint, ..BlockData::new(CHECKPOINT, Vec2::new(50., 100.)) },
+            BlockData {
+                path: Some(PlatformPath {
+                    waypoints: vec![Vec2::ZERO, Vec2::new(0., 100.)],
+                    speed: 100.,
+                    easing: default(),
+                    mode: default(),
+                    dwell: 0.,
+                }),
+                ..BlockData::new(PLATFORM, Vec2::new(100., 25.))
+            },
+        ]));
+        let mut spawns = app.world_mut().query::<&mut PlayerSpawn>();
+        spawns.single_mut(app.world_mut()).0 = Vec2::new(9_600., -125.);
+        app
+    }
+
+    fn run(app: &mut App, ticks: usize) {
+        for _ in 0..ticks {
+            app.update();
+        }
+    }
+
+    fn origin(app: &App) -> WorldOrigin {
+        *app.world().resource::<WorldOrigin>()
+    }
+
+    fn player(app: &mut App) -> (Entity, Vec2) {
+        let mut players = app.world_mut().query_filtered::<(Entity, &Position), With<Player>>();
+        let (player, position) = players.single(app.world());
+        (player, position.0)
+    }
+
+    fn level_position<F: QueryFilter>(app: &mut App) -> (Entity, Vec2) {
+        let origin = origin(app);
+        let mut bodies = app.world_mut().query_filtered::<(Entity, &Position), F>();
+        let (entity, position) = bodies.single(app.world());
+        (entity, origin.to_level(position.0))
+    }
+
+    #[test]
+    fn rebase_keeps_the_level_where_it_was() {
+        let mut app = level();
+        run(&mut app, 144);
+        assert_eq!(origin(&app).0, Vec2::ZERO);
+
+        // Walk right, through the checkpoint, until the player is far enough out to rebase.
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].press(Action::MoveRight);
+        let mut before = origin(&app).to_level(player(&mut app).1);
+        for _ in 0..720 {
+            app.update();
+            let after = origin(&app).to_level(player(&mut app).1);
+            if origin(&app).0 != Vec2::ZERO {
+                // The rebase tick moved the player through the level no further than a walking
+                // step, which is 5 px at full speed.
+                let step = after - before;
+                assert!(step.x >= 0. && step.x < 6. && step.y.abs() < 0.01, "jumped {step} while rebasing");
+                break;
+            }
+            before = after;
+        }
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].release(Action::MoveRight);
+        assert_eq!(origin(&app).0.x, 10_000., "didn't rebase by the player's distance out");
+
+        let (floor, floor_at) = level_position::<(With<Collider>, Without<MovingPlatform>, Without<Player>)>(&mut app);
+        assert_eq!(floor_at, FLOOR);
+        let (_, checkpoint_at) = level_position::<With<Checkpoint>>(&mut app);
+        assert_eq!(checkpoint_at, CHECKPOINT);
+        let (_, platform_at) = level_position::<With<MovingPlatform>>(&mut app);
+        assert!((platform_at.x - PLATFORM.x).abs() < 0.01, "platform moved sideways to {platform_at}");
+        assert!((PLATFORM.y - 0.01..=PLATFORM.y + 100.01).contains(&platform_at.y), "platform left its path at {platform_at}");
+
+        // Once the grid has been refiled, the floor is still found under the player's feet.
+        run(&mut app, 1);
+        let (player_entity, position) = player(&mut app);
+        let feet = Aabb2d::new(position - Vec2::new(0., 55.), Vec2::new(30., 10.));
+        assert!(app.world().resource::<SpatialGrid>().query(feet).contains(&floor), "the grid lost the floor under {position}");
+
+        // Dying now respawns at the checkpoint passed before the rebase, in level coordinates.
+        app.world_mut().send_event(DamageEvent::lethal(player_entity));
+        run(&mut app, 144);
+        let (_, position) = player(&mut app);
+        let respawned_at = origin(&app).to_level(position);
+        assert!((respawned_at - CHECKPOINT).length() < 1., "respawned at {respawned_at}, not the checkpoint");
+    }
+}
@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::camera::{camera_follow, Camera};
+use crate::level::{LevelEntity, ResetLevel};
+use crate::origin::WorldOrigin;
+use crate::physics::{Position, Rotation, ZOrder};
+use crate::world::WorldData;
+use crate::GameState;
+
+/// Depth of the rearmost layer. Each later layer in the list sits a little in front of
+/// the one before, all of them well behind blocks and the player.
+const BACKGROUND_Z: f32 = -1.;
+const LAYER_Z_STEP: f32 = 0.01;
+/// The widest the view can get, in world pixels. Every layer repeats far enough either
+/// side of its middle copy to cover this, plus a copy to wrap into.
+const MAX_VIEW_WIDTH: f32 = 4000.;
+/// Keeps a layer with tiny tiles from asking for thousands of copies.
+const MAX_COPIES_PER_SIDE: i32 = 32;
+
+pub struct ParallaxPlugin;
+
+impl Plugin for ParallaxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Restarting), spawn_parallax.after(ResetLevel))
+            .add_systems(FixedUpdate, parallax_scroll.after(camera_follow));
+    }
+}
+
+/// How a background layer is drawn.
+#[derive(Clone, Debug, Deserialize)]
+pub enum LayerFill {
+    Color { r: f32, g: f32, b: f32 },
+    /// An image under `assets/`, stretched over each copy.
+    Texture(String),
+}
+
+/// One background layer from the level file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ParallaxLayerData {
+    /// How much the layer scrolls with the world: 0 stays fixed on screen, 1 stays fixed
+    /// in the world like a block, and anything between drifts behind the camera.
+    pub factor: f32,
+    /// Where the layer's middle copy sits relative to the camera when the camera is at
+    /// the origin.
+    #[serde(default)]
+    pub offset: Vec2,
+    pub size: Vec2,
+    /// Distance from one copy to the next; defaults to the width, for an unbroken band.
+    #[serde(default)]
+    pub spacing: Option<f32>,
+    pub fill: LayerFill,
+}
+
+/// Background layers in the level, back to front, stored next to the `WorldData` they
+/// belong to.
+#[derive(Component, Clone, Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct ParallaxSpawns(pub Vec<ParallaxLayerData>);
+
+/// A row of copies of one background image, moved every tick to follow the camera at its
+/// own rate and stepped a whole copy at a time so it never runs out.
+#[derive(Component)]
+pub struct ParallaxLayer {
+    pub factor: f32,
+    pub offset: Vec2,
+    spacing: f32,
+    /// Copies the layer is currently stepped by from its unwrapped position.
+    wrap: i32,
+}
+
+fn spawn_parallax(
+    mut commands: Commands,
+    world_data: Query<&ParallaxSpawns, With<WorldData>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok(layers) = world_data.get_single() else {
+        return;
+    };
+    for (index, data) in layers.0.iter().enumerate() {
+        let spacing = data.spacing.unwrap_or(data.size.x).max(1.);
+        let copies = ((MAX_VIEW_WIDTH / 2. / spacing).ceil() as i32 + 1).min(MAX_COPIES_PER_SIDE);
+        let sprite = match &data.fill {
+            LayerFill::Color { r, g, b } => SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgb(*r, *g, *b),
+                    custom_size: Some(data.size),
+                    ..default()
+                },
+                ..default()
+            },
+            LayerFill::Texture(path) => SpriteBundle {
+                texture: asset_server.load(path.clone()),
+                sprite: Sprite {
+                    custom_size: Some(data.size),
+                    ..default()
+                },
+                ..default()
+            },
+        };
+        commands.spawn((
+            ParallaxLayer {
+                factor: data.factor,
+                offset: data.offset,
+                spacing,
+                wrap: 0,
+            },
+            Position(data.offset),
+            Rotation(0.),
+            ZOrder(BACKGROUND_Z + index as f32 * LAYER_Z_STEP),
+            SpatialBundle::default(),
+            LevelEntity,
+        )).with_children(|layer| {
+            for copy in -copies..=copies {
+                let mut sprite = sprite.clone();
+                sprite.transform.translation.x = copy as f32 * spacing;
+                layer.spawn(sprite);
+            }
+        });
+    }
+}
+
+/// Puts each layer at `offset + camera * (1 - factor)`, then steps it by whole copies to
+/// the copy nearest the camera. Stepping teleports the layer, so interpolation doesn't
+/// slide it a whole copy across the screen. Worked out in level coordinates, so moving
+/// the origin doesn't move the background.
+fn parallax_scroll(
+    mut commands: Commands,
+    camera: Query<&Position, (With<Camera>, Without<ParallaxLayer>)>,
+    mut layers: Query<(Entity, &mut Position, &mut ParallaxLayer)>,
+    origin: Res<WorldOrigin>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    for (entity, mut position, mut layer) in &mut layers {
+        let unwrapped = origin.to_live(layer.offset + origin.to_level(camera.0) * (1. - layer.factor));
+        let wrap = ((unwrapped.x - camera.0.x) / layer.spacing).round() as i32;
+        let target = Vec2::new(unwrapped.x - wrap as f32 * layer.spacing, unwrapped.y);
+        if wrap != layer.wrap {
+            layer.wrap = wrap;
+            position.teleport(&mut commands, entity, target);
+        } else {
+            position.0 = target;
+        }
+    }
+}
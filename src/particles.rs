@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::cleanup::{despawn_expired, fade_away, FadeStyle, Lifetime};
+use crate::debug::DebugTrackExt;
+use crate::events::GroundPounded;
+use crate::ground_pound::{land_ground_pounds, IMPACT_RADIUS};
+use crate::level::LevelEntity;
+use crate::physics::{GroundContact, Position, PostCollide, Rotation, Shape, Up, Velocity, ZOrder};
+use crate::movement::Dash;
+use crate::player::{AirJumps, Grounded, Player, PlayerId, Skidding};
+use crate::sfx::{LoopingSfx, SfxKind};
+use crate::world::SurfaceKind;
+
+/// Horizontal distance walked between footstep puffs.
+const FOOTSTEP_STRIDE: f32 = 40.;
+const FOOTSTEP_MIN_SPEED: f32 = 144.;
+const LANDING_MIN_SPEED: f32 = 432.;
+/// Landing this fast or faster throws up the surface's full `landing_count`; slower hard
+/// landings throw up as little as half of it.
+const LANDING_FULL_SPEED: f32 = 1440.;
+/// Ticks between dust puffs while skidding.
+const SKID_PUFF_TICKS: u32 = 6;
+/// The most particles alive at once. Past it, the ones nearest the end of their lifetime
+/// make way for new ones.
+const MAX_PARTICLES: usize = 600;
+const MOVE_RING_COLOR: Color = Color::srgba(1., 1., 1., 0.7);
+const MOVE_RING_RADIUS: f32 = 36.;
+const MOVE_RING_DOTS: usize = 10;
+const MOVE_RING_SECS: f32 = 0.2;
+const POUND_RING_DOTS: usize = 16;
+const POUND_RING_SECS: f32 = 0.25;
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SurfaceEffects>()
+            .debug_track::<Particle>("particles")
+            .add_systems(FixedUpdate, (
+                (surface_feedback, skid_sound, movement_rings, pound_rings.after(land_ground_pounds)).in_set(PostCollide),
+                simulate_particles,
+                cap_particles.after(despawn_expired),
+            ));
+    }
+}
+
+/// A bit of dust or debris that drifts by its own velocity, in pixels per second, under
+/// its own gravity, in pixels per second squared, without colliding with anything. It
+/// goes away by its `Lifetime`, fading the whole way.
+#[derive(Component)]
+pub struct Particle {
+    pub velocity: Vec2,
+    pub gravity: f32,
+}
+
+/// A fan of particles for `spawn_particles`: `count` of them sent out from `origin`,
+/// spread evenly over `spread` radians around the `angle` they're aimed at.
+#[derive(Clone, Copy, Debug)]
+pub struct ParticleBurst {
+    pub origin: Vec2,
+    pub count: usize,
+    pub angle: f32,
+    pub spread: f32,
+    /// The fastest any of them goes; the rest go a little slower.
+    pub speed: f32,
+    pub gravity: f32,
+    pub lifetime: f32,
+    pub color: Color,
+    pub size: f32,
+    pub fade: FadeStyle,
+}
+
+/// What stepping, landing or skidding on a surface looks like, and what a skid sounds like.
+#[derive(Clone, Copy)]
+pub struct SurfaceFx {
+    pub color: Color,
+    pub size: f32,
+    pub landing_count: usize,
+    pub footstep_count: usize,
+    pub skid_count: usize,
+    pub skid_sound: SfxKind,
+    pub speed: f32,
+    pub gravity: f32,
+    pub lifetime: f32,
+}
+
+/// Surface to feedback mapping. Insert entries to give new surfaces their own look;
+/// surfaces without an entry fall back to `Stone`.
+#[derive(Resource)]
+pub struct SurfaceEffects(pub HashMap<SurfaceKind, SurfaceFx>);
+
+impl Default for SurfaceEffects {
+    fn default() -> Self {
+        let dust = SurfaceFx {
+            color: Color::srgba(0.8, 0.75, 0.65, 0.8),
+            size: 6.,
+            landing_count: 8,
+            footstep_count: 2,
+            skid_count: 1,
+            skid_sound: SfxKind::SkidScrape,
+            speed: 216.,
+            gravity: 415.,
+            lifetime: 0.4,
+        };
+        Self(HashMap::from([
+            (SurfaceKind::Stone, dust),
+            (SurfaceKind::Metal, SurfaceFx {
+                color: Color::srgb(1., 0.85, 0.4),
+                size: 3.,
+                skid_count: 2,
+                speed: 432.,
+                gravity: 2074.,
+                lifetime: 0.25,
+                ..dust
+            }),
+            (SurfaceKind::Ice, SurfaceFx {
+                color: Color::srgb(0.6, 0.85, 1.),
+                size: 4.,
+                landing_count: 10,
+                skid_count: 2,
+                skid_sound: SfxKind::SkidSqueal,
+                speed: 360.,
+                gravity: 1659.,
+                ..dust
+            }),
+            (SurfaceKind::Slime, SurfaceFx {
+                color: Color::srgba(0.4, 0.9, 0.3, 0.9),
+                size: 7.,
+                landing_count: 6,
+                speed: 144.,
+                gravity: 1037.,
+                ..dust
+            }),
+        ]))
+    }
+}
+
+impl SurfaceEffects {
+    pub fn get(&self, surface: SurfaceKind) -> Option<&SurfaceFx> {
+        self.0.get(&surface).or_else(|| self.0.get(&SurfaceKind::Stone))
+    }
+}
+
+impl SurfaceFx {
+    /// `count` of this surface's particles kicked up away from the ground at `feet`.
+    pub fn burst(&self, feet: Vec2, up: Up, count: usize, spread: f32) -> ParticleBurst {
+        ParticleBurst {
+            origin: feet,
+            count,
+            angle: std::f32::consts::FRAC_PI_2 * up.0,
+            spread,
+            speed: self.speed,
+            gravity: self.gravity * up.0,
+            lifetime: self.lifetime,
+            color: self.color,
+            size: self.size,
+            fade: FadeStyle::Alpha,
+        }
+    }
+}
+
+/// Spawns the particles `burst` describes. Any system can throw up its own effects
+/// through this.
+pub fn spawn_particles(commands: &mut Commands, burst: &ParticleBurst) {
+    let count = burst.count;
+    for i in 0..count {
+        let t = if count > 1 { i as f32 / (count - 1) as f32 } else { 0.5 };
+        let angle = burst.angle + (t - 0.5) * burst.spread;
+        commands.spawn((
+            Particle {
+                velocity: Vec2::from_angle(angle) * burst.speed * (0.6 + 0.4 * (i % 3) as f32 / 2.),
+                gravity: burst.gravity,
+            },
+            fade_away(burst.lifetime, burst.fade),
+            Position(burst.origin),
+            Rotation(0.),
+            ZOrder(0.3),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: burst.color,
+                    custom_size: Some(Vec2::splat(burst.size)),
+                    ..default()
+                },
+                ..default()
+            },
+            LevelEntity,
+        ));
+    }
+}
+
+/// A ring of `count` dots that expands out to `radius` over `lifetime` seconds, or
+/// collapses in from it when `inward`.
+pub fn spawn_ring(commands: &mut Commands, center: Vec2, radius: f32, color: Color, count: usize, lifetime: f32, inward: bool) {
+    let speed = radius / lifetime;
+    for i in 0..count {
+        let direction = Vec2::from_angle(std::f32::consts::TAU * i as f32 / count as f32);
+        let (start, velocity) = if inward {
+            (center + direction * radius, -direction * speed)
+        } else {
+            (center, direction * speed)
+        };
+        commands.spawn((
+            Particle {
+                velocity,
+                gravity: 0.,
+            },
+            fade_away(lifetime, FadeStyle::Alpha),
+            Position(start),
+            Rotation(0.),
+            ZOrder(0.3),
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(4.)),
+                    ..default()
+                },
+                ..default()
+            },
+            LevelEntity,
+        ));
+    }
+}
+
+fn simulate_particles(mut particles: Query<(&mut Particle, &mut Position)>, time: Res<Time>) {
+    let dt = time.delta_seconds();
+    for (mut particle, mut position) in &mut particles {
+        particle.velocity.y -= particle.gravity * dt;
+        position.0 += particle.velocity * dt;
+    }
+}
+
+/// Runs after `despawn_expired`, so the ones it just despawned aren't counted.
+fn cap_particles(mut commands: Commands, particles: Query<(Entity, &Lifetime), With<Particle>>) {
+    let mut live: Vec<_> = particles.iter()
+        .filter(|(_, lifetime)| !lifetime.0.finished())
+        .map(|(entity, lifetime)| (lifetime.0.fraction(), entity))
+        .collect();
+    let excess = live.len().saturating_sub(MAX_PARTICLES);
+    if excess == 0 {
+        return;
+    }
+    // The furthest through their lifetime first.
+    live.select_nth_unstable_by(excess - 1, |a, b| b.0.total_cmp(&a.0));
+    for (_, entity) in &live[..excess] {
+        commands.entity(*entity).despawn();
+    }
+}
+
+/// What `surface_feedback` remembers about one player between ticks.
+#[derive(Default)]
+struct FootingState {
+    last_fall_speed: f32,
+    was_grounded: bool,
+    stride: f32,
+    skid_ticks: u32,
+}
+
+/// Landing bursts, footstep puffs and skid dust coloured by whatever each player is
+/// standing on.
+fn surface_feedback(
+    mut commands: Commands,
+    players: Query<(&PlayerId, &Position, &Velocity, &Grounded, &GroundContact, &Shape, &Skidding, &Up), With<Player>>,
+    surfaces: Query<&SurfaceKind>,
+    effects: Res<SurfaceEffects>,
+    mut footing: Local<HashMap<PlayerId, FootingState>>,
+    time: Res<Time>,
+) {
+    for (id, position, velocity, grounded, ground, shape, skidding, up) in &players {
+        let state = footing.entry(*id).or_default();
+        let feet = position.0 - Vec2::new(0., shape.0.y / 2. * up.0);
+        let fx = ground.0
+            .and_then(|entity| surfaces.get(entity).ok())
+            .and_then(|surface| effects.get(*surface));
+
+        if let Some(fx) = fx {
+            if grounded.0 && !state.was_grounded && state.last_fall_speed > LANDING_MIN_SPEED {
+                let hardness = ((state.last_fall_speed - LANDING_MIN_SPEED) / (LANDING_FULL_SPEED - LANDING_MIN_SPEED)).clamp(0., 1.);
+                let count = (fx.landing_count as f32 * (0.5 + 0.5 * hardness)).round() as usize;
+                spawn_particles(&mut commands, &fx.burst(feet, *up, count, 2.4));
+            }
+            if grounded.0 && velocity.0.x.abs() > FOOTSTEP_MIN_SPEED {
+                state.stride += velocity.0.x.abs() * time.delta_seconds();
+                if state.stride >= FOOTSTEP_STRIDE {
+                    state.stride = 0.;
+                    spawn_particles(&mut commands, &fx.burst(feet, *up, fx.footstep_count, 1.));
+                }
+            }
+            if grounded.0 && skidding.0 {
+                state.skid_ticks += 1;
+                if state.skid_ticks >= SKID_PUFF_TICKS {
+                    state.skid_ticks = 0;
+                    let behind = feet - Vec2::new(velocity.0.x.signum() * shape.0.x / 2., 0.);
+                    spawn_particles(&mut commands, &fx.burst(behind, *up, fx.skid_count, 0.8));
+                }
+            }
+        }
+        state.was_grounded = grounded.0;
+        state.last_fall_speed = -velocity.0.y * up.0;
+    }
+}
+
+/// A ring around a player for each air jump and each dash they start, told apart from
+/// ground jumps by the air jump being spent.
+fn movement_rings(
+    mut commands: Commands,
+    players: Query<(&PlayerId, &Position, &AirJumps, &Dash), With<Player>>,
+    mut last: Local<HashMap<PlayerId, (u8, bool)>>,
+) {
+    for (id, position, air_jumps, dash) in &players {
+        let now = (air_jumps.remaining, dash.is_dashing());
+        let (remaining, dashing) = last.insert(*id, now).unwrap_or(now);
+        if air_jumps.remaining < remaining || (dash.is_dashing() && !dashing) {
+            spawn_ring(&mut commands, position.0, MOVE_RING_RADIUS, MOVE_RING_COLOR, MOVE_RING_DOTS, MOVE_RING_SECS, false);
+        }
+    }
+}
+
+/// A ground pound's ring goes out as far as it flattens enemies.
+fn pound_rings(mut commands: Commands, mut pounded: EventReader<GroundPounded>) {
+    for event in pounded.read() {
+        spawn_ring(&mut commands, event.position, IMPACT_RADIUS, MOVE_RING_COLOR, POUND_RING_DOTS, POUND_RING_SECS, false);
+    }
+}
+
+/// Loops the surface's skid sound on the player for as long as the skid lasts.
+fn skid_sound(
+    mut commands: Commands,
+    players: Query<(Entity, &Grounded, &GroundContact, &Skidding, Option<&LoopingSfx>), With<Player>>,
+    surfaces: Query<&SurfaceKind>,
+    effects: Res<SurfaceEffects>,
+) {
+    for (entity, grounded, ground, skidding, looping) in &players {
+        let sound = ground.0
+            .and_then(|entity| surfaces.get(entity).ok())
+            .and_then(|surface| effects.get(*surface))
+            .map(|fx| fx.skid_sound)
+            .filter(|_| grounded.0 && skidding.0);
+        match (sound, looping) {
+            (Some(kind), None) => {
+                commands.entity(entity).insert(LoopingSfx::new(kind));
+            }
+            // Stopped skidding, or slid onto a surface that sounds different; a new loop
+            // starts next tick if need be.
+            (sound, Some(looping)) if sound != Some(looping.kind) => {
+                commands.entity(entity).remove::<LoopingSfx>();
+            }
+            _ => {}
+        }
+    }
+}
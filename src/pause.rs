@@ -0,0 +1,100 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::GameState;
+
+const TOGGLE_KEY: KeyCode = KeyCode::Escape;
+const QUIT_KEY: KeyCode = KeyCode::KeyQ;
+const MENU_KEY: KeyCode = KeyCode::KeyM;
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PauseMenu>()
+            .add_systems(Update, toggle_pause)
+            .add_systems(OnEnter(GameState::Paused), (freeze_simulation, spawn_pause_menu))
+            .add_systems(OnExit(GameState::Paused), (resume_simulation, despawn_pause_menu));
+    }
+}
+
+#[derive(Resource, Default)]
+struct PauseMenu {
+    /// Whether something else had already paused virtual time when the menu opened.
+    was_paused: bool,
+}
+
+#[derive(Component)]
+struct PauseScreen;
+
+/// Esc (or Start) pauses and resumes; from the menu, M goes back to the main menu and
+/// Q quits. The F2, F5 and settings screens eat their own key presses in `PreUpdate`, so
+/// closing one of them with Esc doesn't land here.
+fn toggle_pause(
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let pressed_button = |button_type: GamepadButtonType| {
+        buttons.get_just_pressed().any(|button| button.button_type == button_type)
+    };
+    let toggle = keys.just_pressed(TOGGLE_KEY) || pressed_button(GamepadButtonType::Start);
+    match state.get() {
+        GameState::Playing if toggle => next_state.set(GameState::Paused),
+        GameState::Paused if toggle || pressed_button(GamepadButtonType::East) => next_state.set(GameState::Playing),
+        GameState::Paused if keys.just_pressed(MENU_KEY) => next_state.set(GameState::Menu),
+        GameState::Paused if keys.just_pressed(QUIT_KEY) => {
+            exit.send(AppExit::Success);
+        }
+        _ => {}
+    }
+}
+
+/// Pausing virtual time stops the fixed clock as well, so physics, enemies, `control_player`
+/// and every `GameTimer` hold still while `project_transforms` keeps drawing the frozen
+/// frame. Nothing accumulates while paused, so resuming doesn't run a burst of catch-up ticks.
+fn freeze_simulation(mut menu: ResMut<PauseMenu>, mut time: ResMut<Time<Virtual>>) {
+    menu.was_paused = time.is_paused();
+    time.pause();
+}
+
+fn resume_simulation(menu: Res<PauseMenu>, mut time: ResMut<Time<Virtual>>) {
+    if !menu.was_paused {
+        time.unpause();
+    }
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(12.),
+            ..default()
+        },
+        background_color: Color::srgba(0., 0., 0., 0.6).into(),
+        z_index: ZIndex::Global(30),
+        ..default()
+    }, PauseScreen)).with_children(|screen| {
+        screen.spawn(TextBundle::from_section("Paused", TextStyle {
+            font_size: 40.,
+            ..default()
+        }));
+        screen.spawn(TextBundle::from_section("Press Esc to resume, S for settings, M for the main menu, Q to quit", TextStyle {
+            font_size: 20.,
+            ..default()
+        }));
+    });
+}
+
+fn despawn_pause_menu(mut commands: Commands, screens: Query<Entity, With<PauseScreen>>) {
+    for screen in &screens {
+        commands.entity(screen).despawn_recursive();
+    }
+}
@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::events::{Jumped, Landed, OriginShifted};
+use crate::origin::FollowOrigin;
+use crate::physics::{gravitate, move_bodies, update_ground_contact, GlobalGravity, GroundContact, Position, PostCollide, Rotation, Velocity};
+use crate::player::{control_player, Player};
+
+/// How much of a landing player's sideways speed, relative to the platform, it picks up.
+const LANDING_PUSH: f32 = 0.8;
+pub const ROPE_COLOR: Color = Color::srgb(0.6, 0.5, 0.35);
+pub const ROPE_WIDTH: f32 = 3.;
+
+pub struct PendulumPlugin;
+
+impl Plugin for PendulumPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, (
+            (swing_platforms, carry_riders).chain().before(move_bodies),
+            push_on_landing.in_set(PostCollide).after(update_ground_contact),
+            fling_jumpers.after(control_player).before(gravitate),
+        ))
+            .add_systems(FixedPostUpdate, shift_pivots.in_set(FollowOrigin));
+    }
+}
+
+/// How a swinging platform hangs. The block's position is the pivot, and the platform
+/// hangs straight down from it at rest.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct PendulumRope {
+    /// From the pivot to the middle of the platform, in pixels.
+    pub length: f32,
+    /// How quickly a swing dies away, as an exponential rate per second.
+    pub damping: f32,
+}
+
+impl PendulumRope {
+    /// Where the middle of the platform is at `angle` from hanging straight down.
+    pub fn point_at(&self, pivot: Vec2, angle: f32) -> Vec2 {
+        pivot + Vec2::new(angle.sin(), -angle.cos()) * self.length
+    }
+}
+
+/// A platform from `BlockKind::Swinging`. It only swings once landed on, pushed along
+/// by the player's sideways speed. Its `Velocity` is set each tick to carry it to the next
+/// point of the swing, so `move_bodies` moves it and anything reading its speed sees the
+/// real thing. It tilts with the rope, though it still collides as an upright box.
+#[derive(Component)]
+pub struct SwingingPlatform {
+    pub rope: PendulumRope,
+    pivot: Vec2,
+    angle: f32,
+    /// Radians per second.
+    angular_velocity: f32,
+}
+
+impl SwingingPlatform {
+    pub fn new(rope: PendulumRope, pivot: Vec2) -> Self {
+        Self { rope, pivot, angle: 0., angular_velocity: 0. }
+    }
+
+    /// Where the platform hangs at rest.
+    pub fn rest(&self) -> Vec2 {
+        self.rope.point_at(self.pivot, 0.)
+    }
+}
+
+fn shift_pivots(mut shifted: EventReader<OriginShifted>, mut platforms: Query<&mut SwingingPlatform>) {
+    for event in shifted.read() {
+        for mut platform in &mut platforms {
+            platform.pivot -= event.offset;
+        }
+    }
+}
+
+/// Swings each platform under world gravity, whichever way it points, then damps it.
+fn swing_platforms(
+    mut platforms: Query<(&mut SwingingPlatform, &Position, &mut Velocity, &mut Rotation)>,
+    gravity: Res<GlobalGravity>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    if dt <= 0. {
+        return;
+    }
+    let g = gravity.0.length();
+    for (mut platform, position, mut velocity, mut rotation) in &mut platforms {
+        let length = platform.rope.length.max(1.);
+        platform.angular_velocity -= g / length * platform.angle.sin() * dt;
+        platform.angular_velocity *= (-platform.rope.damping * dt).exp();
+        platform.angle += platform.angular_velocity * dt;
+        let next = platform.rope.point_at(platform.pivot, platform.angle);
+        velocity.0 = (next - position.0) / dt;
+        rotation.0 = platform.angle;
+    }
+}
+
+/// Moves whatever stood on a swinging platform last tick along with it, the way moving
+/// platforms carry their riders.
+fn carry_riders(
+    platforms: Query<&Velocity, With<SwingingPlatform>>,
+    mut riders: Query<(&mut Position, &GroundContact), (With<Player>, Without<SwingingPlatform>)>,
+    time: Res<Time>,
+) {
+    for (mut position, ground) in &mut riders {
+        if let Some(velocity) = ground.0.and_then(|ground| platforms.get(ground).ok()) {
+            position.0 += velocity.0 * time.delta_seconds();
+        }
+    }
+}
+
+fn push_on_landing(
+    mut landed: EventReader<Landed>,
+    players: Query<(&Velocity, &GroundContact), With<Player>>,
+    mut platforms: Query<(&mut SwingingPlatform, &Velocity), Without<Player>>,
+) {
+    for event in landed.read() {
+        let Ok((velocity, ground)) = players.get(event.entity) else {
+            continue;
+        };
+        let Some((mut platform, platform_velocity)) = ground.0.and_then(|ground| platforms.get_mut(ground).ok()) else {
+            continue;
+        };
+        let push = (velocity.0.x - platform_velocity.0.x) * LANDING_PUSH;
+        platform.angular_velocity += push / platform.rope.length.max(1.);
+    }
+}
+
+/// Jumping off a swinging platform keeps its swing on top of the jump.
+fn fling_jumpers(
+    mut jumped: EventReader<Jumped>,
+    mut players: Query<(&mut Velocity, &GroundContact), With<Player>>,
+    platforms: Query<&Velocity, (With<SwingingPlatform>, Without<Player>)>,
+) {
+    for event in jumped.read() {
+        let Ok((mut velocity, ground)) = players.get_mut(event.entity) else {
+            continue;
+        };
+        if let Some(platform) = ground.0.and_then(|ground| platforms.get(ground).ok()) {
+            velocity.0 += platform.0;
+        }
+    }
+}
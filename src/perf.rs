@@ -0,0 +1,225 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::enemy::Enemy;
+use crate::physics::{Collider, CollisionStats, Velocity};
+
+const REPORT_FLAG: &str = "--perf-report";
+const COMPARE_FLAG: &str = "--perf-compare";
+/// Seconds of samples a report can hold. The buffer is allocated once up front; a run
+/// longer than this stops recording instead of growing it.
+const MAX_SECONDS: usize = 3600;
+
+/// How far each metric may grow over the baseline before `--perf-compare` fails, as a
+/// ratio. Frame times are noisy, so they get the most slack.
+const REGRESSION_LIMITS: [(&str, fn(&PerfReport) -> f32, f32); 5] = [
+    ("mean frame ms", PerfReport::mean_frame_ms, 1.15),
+    ("worst frame ms", PerfReport::worst_frame_ms, 1.5),
+    ("narrow-phase tests per tick", PerfReport::narrow_phase_per_tick, 1.05),
+    ("peak entities", PerfReport::peak_entities, 1.1),
+    ("peak assets", PerfReport::peak_assets, 1.1),
+];
+
+pub struct PerfPlugin;
+
+impl Plugin for PerfPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(path) = flag_value(REPORT_FLAG) else {
+            return;
+        };
+        app.insert_resource(PerfRecorder::new(path.into()))
+            .add_systems(FixedFirst, count_fixed_ticks)
+            .add_systems(Last, (record_frame, write_report).chain());
+    }
+}
+
+/// One second of a run.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct PerfSample {
+    pub frames: u32,
+    pub mean_frame_ms: f32,
+    pub worst_frame_ms: f32,
+    pub fixed_ticks: u32,
+    pub narrow_phase_tests: u64,
+    pub entities: u32,
+    pub colliders: u32,
+    pub dynamic_bodies: u32,
+    pub enemies: u32,
+    pub meshes: u32,
+    pub materials: u32,
+    pub images: u32,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct PerfReport {
+    pub samples: Vec<PerfSample>,
+}
+
+impl PerfReport {
+    fn mean_frame_ms(&self) -> f32 {
+        let frames: u32 = self.samples.iter().map(|sample| sample.frames).sum();
+        let total: f32 = self.samples.iter().map(|sample| sample.mean_frame_ms * sample.frames as f32).sum();
+        total / frames.max(1) as f32
+    }
+
+    fn worst_frame_ms(&self) -> f32 {
+        self.samples.iter().map(|sample| sample.worst_frame_ms).fold(0., f32::max)
+    }
+
+    fn narrow_phase_per_tick(&self) -> f32 {
+        let ticks: u32 = self.samples.iter().map(|sample| sample.fixed_ticks).sum();
+        let tests: u64 = self.samples.iter().map(|sample| sample.narrow_phase_tests).sum();
+        tests as f32 / ticks.max(1) as f32
+    }
+
+    fn peak_entities(&self) -> f32 {
+        self.samples.iter().map(|sample| sample.entities).max().unwrap_or(0) as f32
+    }
+
+    fn peak_assets(&self) -> f32 {
+        self.samples.iter().map(|sample| sample.meshes + sample.materials + sample.images).max().unwrap_or(0) as f32
+    }
+}
+
+/// Aggregates the current second in place and pushes it into a buffer that never
+/// reallocates, so recording costs a few additions per frame.
+#[derive(Resource)]
+struct PerfRecorder {
+    path: PathBuf,
+    report: PerfReport,
+    current: PerfSample,
+    elapsed: f32,
+    frame_ms_sum: f32,
+    last_narrow_phase_tests: u64,
+    full: bool,
+    written: bool,
+}
+
+impl PerfRecorder {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            report: PerfReport { samples: Vec::with_capacity(MAX_SECONDS) },
+            current: PerfSample::default(),
+            elapsed: 0.,
+            frame_ms_sum: 0.,
+            last_narrow_phase_tests: 0,
+            full: false,
+            written: false,
+        }
+    }
+}
+
+/// The value after `flag` on the command line.
+pub fn flag_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    args.next()?;
+    args.next()
+}
+
+fn count_fixed_ticks(mut recorder: ResMut<PerfRecorder>) {
+    recorder.current.fixed_ticks += 1;
+}
+
+fn record_frame(
+    mut recorder: ResMut<PerfRecorder>,
+    time: Res<Time<Real>>,
+    collision_stats: Res<CollisionStats>,
+    entities: Query<()>,
+    colliders: Query<(), With<Collider>>,
+    dynamic_bodies: Query<(), With<Velocity>>,
+    enemies: Query<(), With<Enemy>>,
+    meshes: Res<Assets<Mesh>>,
+    materials: Res<Assets<ColorMaterial>>,
+    images: Res<Assets<Image>>,
+) {
+    let recorder = &mut *recorder;
+    let frame_ms = time.delta_seconds() * 1000.;
+    recorder.current.frames += 1;
+    recorder.frame_ms_sum += frame_ms;
+    recorder.current.worst_frame_ms = recorder.current.worst_frame_ms.max(frame_ms);
+    recorder.elapsed += time.delta_seconds();
+    if recorder.elapsed < 1. {
+        return;
+    }
+
+    recorder.elapsed -= 1.;
+    let mut sample = std::mem::take(&mut recorder.current);
+    sample.mean_frame_ms = recorder.frame_ms_sum / sample.frames as f32;
+    sample.narrow_phase_tests = collision_stats.narrow_phase_tests - recorder.last_narrow_phase_tests;
+    sample.entities = entities.iter().count() as u32;
+    sample.colliders = colliders.iter().count() as u32;
+    sample.dynamic_bodies = dynamic_bodies.iter().count() as u32;
+    sample.enemies = enemies.iter().count() as u32;
+    sample.meshes = meshes.len() as u32;
+    sample.materials = materials.len() as u32;
+    sample.images = images.len() as u32;
+    recorder.frame_ms_sum = 0.;
+    recorder.last_narrow_phase_tests = collision_stats.narrow_phase_tests;
+    if recorder.report.samples.len() < MAX_SECONDS {
+        recorder.report.samples.push(sample);
+    } else if !recorder.full {
+        warn!("perf report is full after {MAX_SECONDS} seconds, the rest of the run isn't recorded");
+        recorder.full = true;
+    }
+}
+
+fn write_report(mut exit: EventReader<AppExit>, mut recorder: ResMut<PerfRecorder>) {
+    if exit.read().count() == 0 || recorder.written {
+        return;
+    }
+    recorder.written = true;
+    let result = serde_json::to_string_pretty(&recorder.report)
+        .map_err(std::io::Error::from)
+        .and_then(|json| fs::write(&recorder.path, json));
+    match result {
+        Ok(()) => info!("wrote perf report to {}", recorder.path.display()),
+        Err(error) => error!("couldn't write perf report to {}: {error}", recorder.path.display()),
+    }
+}
+
+fn read_report(path: &Path) -> Result<PerfReport, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: {error}", path.display()))?;
+    serde_json::from_str(&text).map_err(|error| format!("{}: {error}", path.display()))
+}
+
+/// Every metric in `current` that grew past its limit over `baseline`.
+pub fn regressions(baseline: &PerfReport, current: &PerfReport) -> Vec<String> {
+    REGRESSION_LIMITS.iter()
+        .filter_map(|(name, metric, limit)| {
+            let (before, after) = (metric(baseline), metric(current));
+            (after > before * limit).then(|| format!("{name}: {before:.2} -> {after:.2} (limit {limit}x)"))
+        })
+        .collect()
+}
+
+/// Handles `--perf-compare <baseline.json> <current.json>`: prints any regressions and
+/// returns the process exit code, or `None` to start the game as usual.
+pub fn compare_from_args() -> Option<i32> {
+    let mut args = std::env::args().skip_while(|arg| arg != COMPARE_FLAG);
+    args.next()?;
+    let (Some(baseline), Some(current)) = (args.next(), args.next()) else {
+        eprintln!("usage: {COMPARE_FLAG} <baseline.json> <current.json>");
+        return Some(2);
+    };
+    let reports = read_report(Path::new(&baseline)).and_then(|baseline| Ok((baseline, read_report(Path::new(&current))?)));
+    let (baseline, current) = match reports {
+        Ok(reports) => reports,
+        Err(error) => {
+            eprintln!("{error}");
+            return Some(2);
+        }
+    };
+    let regressions = regressions(&baseline, &current);
+    if regressions.is_empty() {
+        println!("no regressions");
+        return Some(0);
+    }
+    for regression in &regressions {
+        eprintln!("regressed: {regression}");
+    }
+    Some(1)
+}
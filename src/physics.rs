@@ -0,0 +1,1432 @@
+use std::f32::consts::{PI, TAU};
+use std::ops::BitOr;
+
+use bevy::ecs::system::SystemParam;
+use bevy::math::bounding::{
+    Aabb2d,
+    BoundingVolume,
+    IntersectsVolume,
+};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use serde::Deserialize;
+use smallvec::SmallVec;
+
+use crate::cannon::InCannon;
+use crate::crates::{Carrying, Crate};
+use crate::events::{CollisionEvent, Landed};
+use crate::ground_pound::GroundPound;
+use crate::ladder::Climbing;
+use crate::movement::{Dash, MovementConfig, MovementModifiers, StatId};
+use crate::player::{Grounded, Player};
+use crate::spatial::{prune_spatial_grid, update_spatial_grid, SpatialGrid};
+use crate::timer::GameTimer;
+
+/// Gap left between a corner-corrected body and the corner it slipped past, so the next
+/// tick doesn't see them touching.
+const CORNER_CLEARANCE: f32 = 0.01;
+/// The tallest lip a walking player steps up onto instead of stopping at, in pixels.
+const STEP_HEIGHT: f32 = 12.;
+/// The most pieces a fast body's tick is split into for resolving collisions.
+const MAX_SUB_STEPS: u32 = 8;
+/// How far into a collider a body can start a sweep and still count as touching its face,
+/// for the rounding left in a position resolved flush against it.
+const TOUCH_TOLERANCE: f32 = 0.01;
+/// Distance between the rings `free_space_near` tries.
+const FREE_SPACE_STEP: f32 = 5.;
+/// Directions tried on each ring, starting straight up.
+const FREE_SPACE_DIRECTIONS: usize = 16;
+
+/// Gravity, movement and collision for every body, on the 144 Hz fixed tick, and drawing
+/// bodies smoothly in between.
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_hz(144.))
+            .init_resource::<Contacts>()
+            .init_resource::<CollisionStats>()
+            .init_resource::<GlobalGravity>()
+            .init_resource::<SpatialGrid>()
+            .register_type::<Position>()
+            .register_type::<Rotation>()
+            .register_type::<ZOrder>()
+            .register_type::<Shape>()
+            .register_type::<Velocity>()
+            .configure_sets(FixedUpdate, (PhysicsSet::Integrate, PhysicsSet::Resolve, PostCollide).chain())
+            .add_systems(FixedUpdate, (
+                (gravitate, clamp_velocity, move_bodies).chain().in_set(PhysicsSet::Integrate),
+                update_spatial_grid.after(PhysicsSet::Integrate).before(PhysicsSet::Resolve),
+                (tick_collision_grace, handle_collisions, stop_at_collisions).chain().in_set(PhysicsSet::Resolve),
+                update_ground_contact.in_set(PostCollide),
+            ))
+            .add_systems(FixedFirst, (remember_previous_transforms, clear_teleported).chain())
+            .add_systems(Update, (project_transforms, prune_spatial_grid));
+    }
+}
+
+/// The steps of a physics tick, in order. Steering that sets velocities runs before
+/// `Integrate`.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PhysicsSet {
+    /// Applies gravity, then moves every body by its velocity.
+    Integrate,
+    /// Pushes bodies back out of colliders, rebuilds `Contacts` and sends a
+    /// `CollisionEvent` for each, then reacts to those events. Responders go in here after
+    /// `handle_collisions`; anything that only needs the settled result goes after the set.
+    Resolve,
+}
+
+/// Running totals from collision resolution, for profiling.
+#[derive(Resource, Default, Debug)]
+pub struct CollisionStats {
+    /// Exact box-against-box tests since startup.
+    pub narrow_phase_tests: u64,
+}
+
+/// Which colliders a `PhysicsWorld` query sees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayerMask(u8);
+
+impl LayerMask {
+    /// Level geometry: plain blocks, cannons, magnets and slime.
+    pub const BLOCKS: Self = Self(1);
+    /// One-way gates, which are only solid from some sides.
+    pub const GATES: Self = Self(1 << 1);
+    pub const CRATES: Self = Self(1 << 2);
+    pub const ALL: Self = Self(u8::MAX);
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for LayerMask {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Read-only questions about where colliders are. This is the supported way for gameplay
+/// code (and anything built on top of the game) to ask "what's here?" instead of walking
+/// collider queries by hand; collision resolution itself still lives in `handle_collisions`.
+///
+/// Each question only reads the colliders the `SpatialGrid` has filed in the cells it
+/// covers, so it costs the same on a level of ten thousand blocks as on a small one. The
+/// grid is filed once a tick, after `PhysicsSet::Integrate`.
+///
+/// Holds a read-only view of every `Collider`'s `Position`, so a system using it can't
+/// also mutably query the `Position` of colliders.
+#[derive(SystemParam)]
+pub struct PhysicsWorld<'w, 's> {
+    colliders: Query<'w, 's, (Entity, &'static Position, &'static Shape, Has<Gate>, Has<Crate>), With<Collider>>,
+    grid: Res<'w, SpatialGrid>,
+}
+
+impl PhysicsWorld<'_, '_> {
+    /// The colliders in `filter` the grid has near `region`, with their boxes.
+    fn near(&self, region: Aabb2d, filter: LayerMask) -> impl Iterator<Item = (Entity, Aabb2d)> + '_ {
+        self.grid.query(region).into_iter().filter_map(move |entity| {
+            let (entity, position, shape, gate, crate_box) = self.colliders.get(entity).ok()?;
+            let layer = match (gate, crate_box) {
+                (true, _) => LayerMask::GATES,
+                (_, true) => LayerMask::CRATES,
+                _ => LayerMask::BLOCKS,
+            };
+            filter.intersects(layer).then(|| (entity, Aabb2d::new(position.0, shape.0 / 2.)))
+        })
+    }
+
+    /// Every collider in `filter` that overlaps `aabb`. Colliders that only touch its edge
+    /// don't count.
+    pub fn overlap_aabb(&self, aabb: Aabb2d, filter: LayerMask) -> SmallVec<[Entity; 8]> {
+        self.near(aabb, filter)
+            .filter(|(_, other)| overlaps(aabb, *other))
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    /// A collider in `filter` containing `point`, edges included.
+    pub fn overlap_point(&self, point: Vec2, filter: LayerMask) -> Option<Entity> {
+        self.near(Aabb2d::new(point, Vec2::ZERO), filter)
+            .find(|(_, aabb)| contains_point(*aabb, point))
+            .map(|(entity, _)| entity)
+    }
+
+    /// The first collider in `filter` the segment from `from` to `to` runs into, and the
+    /// point where it does.
+    pub fn raycast(&self, from: Vec2, to: Vec2, filter: LayerMask) -> Option<(Entity, Vec2)> {
+        let span = Aabb2d { min: from.min(to), max: from.max(to) };
+        self.near(span, filter)
+            .filter_map(|(entity, aabb)| segment_entry(from, to, aabb).map(|t| (entity, t)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entity, t)| (entity, from.lerp(to, t)))
+    }
+
+    /// The nearest spot within `radius` of `position` where a box of `size` overlaps no
+    /// collider at all, or `None` if there isn't one. `position` itself wins when it's free.
+    pub fn free_space_near(&self, position: Vec2, size: Vec2, radius: f32) -> Option<Vec2> {
+        // Everything any spot could hit, read from the grid once rather than per spot.
+        let reach = Aabb2d::new(position, size / 2. + Vec2::splat(radius));
+        let near: Vec<Aabb2d> = self.near(reach, LayerMask::ALL).map(|(_, aabb)| aabb).collect();
+        let free = |center: Vec2| {
+            let body = Aabb2d::new(center, size / 2.);
+            !near.iter().any(|other| overlaps(body, *other))
+        };
+        if free(position) {
+            return Some(position);
+        }
+        let rings = (radius / FREE_SPACE_STEP).floor() as usize;
+        (1..=rings).find_map(|ring| {
+            (0..FREE_SPACE_DIRECTIONS)
+                .map(|i| {
+                    let angle = std::f32::consts::FRAC_PI_2 + std::f32::consts::TAU * i as f32 / FREE_SPACE_DIRECTIONS as f32;
+                    position + Vec2::from_angle(angle) * ring as f32 * FREE_SPACE_STEP
+                })
+                .find(|candidate| free(*candidate))
+        })
+    }
+}
+
+/// Whether `point` is inside `aabb` or on its edge, the test `overlap_point` uses.
+pub fn contains_point(aabb: Aabb2d, point: Vec2) -> bool {
+    point.cmpge(aabb.min).all() && point.cmple(aabb.max).all()
+}
+
+/// Whether `a` and `b` overlap by more than a shared edge.
+pub fn overlaps(a: Aabb2d, b: Aabb2d) -> bool {
+    a.min.cmplt(b.max).all() && b.min.cmplt(a.max).all()
+}
+
+/// Systems that react to this tick's `Contacts`.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PostCollide;
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Position(pub Vec2);
+
+impl Position {
+    /// Moves the body without it visibly travelling there.
+    pub fn teleport(&mut self, commands: &mut Commands, entity: Entity, to: Vec2) {
+        self.0 = to;
+        commands.entity(entity).insert(Teleported);
+    }
+}
+
+/// Marks a body that jumped this tick, so render interpolation snaps it instead of
+/// drawing it somewhere between the old and new positions. Cleared at the start of the
+/// next tick.
+#[derive(Component)]
+pub struct Teleported;
+
+/// Always drawn exactly at its `Position`, never interpolated.
+#[derive(Component)]
+pub struct InterpolationDisabled;
+
+/// Where the body was at the end of the last fixed tick, for drawing it between ticks.
+/// Added to anything drawn from a `Position` the first tick after it spawns.
+#[derive(Component)]
+pub struct PreviousPosition(pub Vec2);
+
+/// `PreviousPosition`'s counterpart for `Rotation`.
+#[derive(Component)]
+pub struct PreviousRotation(pub f32);
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Rotation(pub f32);
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct ZOrder(pub f32);
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Shape(pub Vec2);
+
+/// In pixels per second; `move_bodies` scales it by the fixed timestep.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Velocity(pub Vec2);
+
+/// Per-entity gravity that ignores `GlobalGravity` and `GravityScale` entirely. Gravity
+/// zones set it on whatever is inside them; anything else that only wants to weaken or
+/// strengthen the pull should use `GravityScale`, which keeps following world gravity.
+#[derive(Component)]
+pub struct Gravity(pub Vec2);
+
+/// Turns a body's gravity around, whichever gravity it's under, so it falls toward the
+/// ceiling and walks on it.
+#[derive(Component)]
+pub struct GravityFlipped;
+
+/// Which way is up for a body: 1 normally, -1 while its gravity pulls it up the screen and
+/// it stands on the undersides of blocks. Only the vertical pull counts; a sideways one
+/// leaves it as it was. Kept up to date by `gravitate`. Bodies without one always land
+/// on floors.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Up(pub f32);
+
+impl Default for Up {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+impl Up {
+    pub fn is_flipped(self) -> bool {
+        self.0 < 0.
+    }
+
+    /// The side of the body that lands: `Bottom`, or `Top` upside down.
+    pub fn feet(self) -> Collision {
+        if self.is_flipped() { Collision::Top } else { Collision::Bottom }
+    }
+
+    /// The side that bumps into ceilings.
+    pub fn head(self) -> Collision {
+        if self.is_flipped() { Collision::Bottom } else { Collision::Top }
+    }
+}
+
+/// World gravity applied to every `Gravitated` body, kept in step with
+/// `MovementConfig::gravity` by `apply_movement_config`.
+#[derive(Resource)]
+pub struct GlobalGravity(pub Vec2);
+
+impl Default for GlobalGravity {
+    fn default() -> Self {
+        Self(Vec2::new(0., MovementConfig::default().gravity))
+    }
+}
+
+/// Multiplies `GlobalGravity` for one entity; low-grav zones, gliding and water adjust this.
+#[derive(Component)]
+pub struct GravityScale(pub f32);
+
+impl Default for GravityScale {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// The fastest a body can fall or rise, in pixels per second. Applied right before bodies
+/// move, so stacked boosts (a spring launch plus a jump) and long drops can't outrun the
+/// camera or skip through blocks. Bodies without it aren't capped.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TerminalVelocity {
+    pub fall: f32,
+    pub rise: f32,
+}
+
+impl TerminalVelocity {
+    /// Vertical speed `y` held to the caps, with falling and rising taken relative to `up`.
+    pub fn cap(self, y: f32, up: f32) -> f32 {
+        (y * up).clamp(-self.fall, self.rise) * up
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+pub enum Collision {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Collision {
+    /// Surface normal of the thing that was hit, pointing back at the body.
+    pub fn normal(self) -> Vec2 {
+        match self {
+            Collision::Top => Vec2::NEG_Y,
+            Collision::Bottom => Vec2::Y,
+            Collision::Left => Vec2::X,
+            Collision::Right => Vec2::NEG_X,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Contact {
+    pub body: Entity,
+    pub other: Entity,
+    pub side: Collision,
+    /// The body's velocity going into the contact, before resolution zeroed it.
+    pub velocity: Vec2,
+}
+
+/// Everything each dynamic body touched during the current fixed tick, rebuilt by
+/// `handle_collisions`. Read it from the `PostCollide` set instead of redoing AABB tests.
+#[derive(Resource, Default)]
+pub struct Contacts(pub Vec<Contact>);
+
+impl Contacts {
+    pub fn of(&self, body: Entity) -> impl Iterator<Item = &Contact> {
+        self.0.iter().filter(move |contact| contact.body == body)
+    }
+}
+
+#[derive(Component)]
+pub struct Gravitated;
+
+/// Pushed out of colliders by `handle_collisions`. Bodies with `Grounded` also land.
+#[derive(Component)]
+pub struct DynamicBody;
+
+/// The collider a body is standing on this tick, if any.
+#[derive(Component, Default, PartialEq)]
+pub struct GroundContact(pub Option<Entity>);
+
+#[derive(Component)]
+pub struct Collider;
+
+/// Lets a body pass through one specific collider until the timer runs out.
+#[derive(Component)]
+pub struct CollisionGrace {
+    pub entity: Entity,
+    pub timer: GameTimer,
+}
+
+/// Passable from one side and solid from every other, for no-backtracking sections.
+#[derive(Component, Clone, Copy)]
+pub struct Gate {
+    pub passable_from: Collision,
+}
+
+/// Solid only to a body landing on it from above, so it can be jumped through from below
+/// and walked through from the side.
+#[derive(Component)]
+pub struct OneWayPlatform;
+
+/// A ramp filling the lower half of its block, cut corner to corner: the surface rises
+/// across the `Shape` toward the right or the left. Bodies stand on the surface, the
+/// tall end is a wall and the underside is flat. `Shape` stays its bounding box.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Slope {
+    pub rises_right: bool,
+}
+
+impl Slope {
+    /// Height of the surface above `x`, held level past either end.
+    pub fn surface_at(self, aabb: Aabb2d, x: f32) -> f32 {
+        let along = ((x - aabb.min.x) / (aabb.max.x - aabb.min.x).max(f32::EPSILON)).clamp(0., 1.);
+        let rise = if self.rises_right { along } else { 1. - along };
+        aabb.min.y + (aabb.max.y - aabb.min.y) * rise
+    }
+
+    /// The surface's angle from flat, in radians: positive rising to the right.
+    pub fn angle(self, aabb: Aabb2d) -> f32 {
+        let size = aabb.max - aabb.min;
+        let angle = size.y.atan2(size.x);
+        if self.rises_right { angle } else { -angle }
+    }
+
+    /// Too steep to stand on at a limit of `max_angle` radians, so a wall to slide down.
+    pub fn too_steep(self, aabb: Aabb2d, max_angle: f32) -> bool {
+        self.angle(aabb).abs() > max_angle
+    }
+}
+
+/// What a body at `x` would stand on: the top of a box, or a slope's surface.
+pub fn top_at(aabb: Aabb2d, slope: Option<Slope>, x: f32) -> f32 {
+    slope.map_or(aabb.max.y, |slope| slope.surface_at(aabb, x))
+}
+
+/// How far `handle_collisions` moves a body out of a `collide` overlap.
+fn push_out(collision: Collision, offset: Vec2) -> Vec2 {
+    match collision {
+        Collision::Top => Vec2::new(0., -offset.y),
+        Collision::Bottom => Vec2::new(0., offset.y),
+        Collision::Left => Vec2::new(offset.x, 0.),
+        Collision::Right => Vec2::new(-offset.x, 0.),
+    }
+}
+
+/// Like `collide` for a slope, but returns the push itself. A body with its center over
+/// the ramp stands on the surface under that center, so its feet follow the slope as it
+/// walks; one below the ramp hits the flat underside. Past the tall end the block is an
+/// ordinary box, and past the low end there's nothing to hit. A slope steeper than
+/// `max_angle` is a wall instead: the body is pushed sideways off the surface, down the
+/// slope, so it slides down as it falls.
+fn collide_slope(body: Aabb2d, slope_aabb: Aabb2d, slope: Slope, max_angle: f32) -> Option<(Collision, Vec2)> {
+    if !body.intersects(&slope_aabb) {
+        return None;
+    }
+    let x = body.center().x;
+    if x < slope_aabb.min.x || x > slope_aabb.max.x {
+        let past_tall_end = (x > slope_aabb.max.x) == slope.rises_right;
+        return past_tall_end
+            .then(|| collide(body, slope_aabb))
+            .flatten()
+            .map(|(collision, offset)| (collision, push_out(collision, offset)));
+    }
+    if body.center().y < slope_aabb.min.y {
+        return Some((Collision::Top, Vec2::new(0., slope_aabb.min.y - body.max.y)));
+    }
+    let surface = slope.surface_at(slope_aabb, x);
+    if body.min.y >= surface {
+        return None;
+    }
+    if slope.too_steep(slope_aabb, max_angle) {
+        let run = (surface - body.min.y) / slope.angle(slope_aabb).tan();
+        let side = if slope.rises_right { Collision::Right } else { Collision::Left };
+        return Some((side, Vec2::new(-run, 0.)));
+    }
+    Some((Collision::Bottom, Vec2::new(0., surface - body.min.y)))
+}
+
+/// Which side of `body1` is pushed into `body2`, and how deep it is on each axis: the
+/// shortest way out along x and along y, so a body sunk right inside a block still gets
+/// out by the nearest face. The side is on whichever axis is shallower, vertical on a tie
+/// so landing exactly on a corner lands. It's named for the body, so `Bottom` means
+/// `body1` is resting on top of `body2`.
+pub fn collide(
+    body1: Aabb2d,
+    body2: Aabb2d,
+) -> Option<(Collision, Vec2)> {
+    if !well_formed(body1) || !well_formed(body2) {
+        warn_once!("collide got a non-finite or inverted bounding box, treating it as no collision");
+        return None;
+    }
+    if !body1.intersects(&body2) {
+        return None;
+    }
+    // How far `body1` would have to move up or right, and down or left, to be clear.
+    let out_positive = body2.max - body1.min;
+    let out_negative = body1.max - body2.min;
+    let depth = out_positive.min(out_negative);
+    let side = if depth.x < depth.y {
+        if out_positive.x <= out_negative.x { Collision::Left } else { Collision::Right }
+    } else if out_positive.y <= out_negative.y {
+        Collision::Bottom
+    } else {
+        Collision::Top
+    };
+    Some((side, depth))
+}
+
+/// How far two boxes overlap on each axis. Negative on an axis where they're apart.
+fn overlap_extents(a: Aabb2d, b: Aabb2d) -> Vec2 {
+    a.max.min(b.max) - a.min.max(b.min)
+}
+
+fn well_formed(aabb: Aabb2d) -> bool {
+    aabb.min.is_finite() && aabb.max.is_finite() && aabb.min.cmple(aabb.max).all()
+}
+
+/// When a body moving by `step` this tick first touches `target`, as a fraction of
+/// the move, and which of its sides hits. Sides follow `collide`, so a body coming down
+/// onto `target` hits with its `Bottom`. A corner hit on both axes at once counts as
+/// vertical, so landing exactly on an edge still lands. Bodies that start out overlapping
+/// `target` or only slide along its face aren't hits; `collide` handles those.
+fn collide_swept(body: Aabb2d, step: Vec2, target: Aabb2d) -> Option<(Collision, f32)> {
+    // Sweep the body's center against the target grown by the body's size.
+    let start = body.center();
+    let min = target.min - body.half_size();
+    let max = target.max + body.half_size();
+    let mut enter = [f32::NEG_INFINITY; 2];
+    let mut exit = [f32::INFINITY; 2];
+    for axis in 0..2 {
+        let (from, dir, lo, hi) = (start[axis], step[axis], min[axis], max[axis]);
+        if dir.abs() < f32::EPSILON {
+            if from <= lo || from >= hi {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (lo - from) / dir;
+        let t2 = (hi - from) / dir;
+        enter[axis] = t1.min(t2);
+        exit[axis] = t1.max(t2);
+        // Resting against the face rather than in it; otherwise a body stopped flush
+        // against a thin wall is let through on its next fast move.
+        if enter[axis] < 0. && -enter[axis] * dir.abs() < TOUCH_TOLERANCE {
+            enter[axis] = 0.;
+        }
+    }
+    let t_enter = enter[0].max(enter[1]);
+    let t_exit = exit[0].min(exit[1]);
+    if t_enter >= t_exit || !(0. ..=1.).contains(&t_enter) {
+        return None;
+    }
+    let side = if enter[0] > enter[1] {
+        if step.x > 0. { Collision::Right } else { Collision::Left }
+    } else if step.y > 0. {
+        Collision::Top
+    } else {
+        Collision::Bottom
+    };
+    Some((side, t_enter))
+}
+
+/// Whether `collide` would push a body that just moved by `step` into `target` out
+/// further along the move rather than back the way it came, having got more than halfway
+/// through. Only a move too big for `sub_step_count` to split small enough gets there.
+fn pushed_through(body: Aabb2d, step: Vec2, target: Aabb2d) -> bool {
+    collide(body, target).is_some_and(|(side, depth)| push_out(side, depth).dot(step) > 0.)
+}
+
+/// Which side of `target` the body was entirely on, or `None` if they already overlapped.
+fn side_of(body: Aabb2d, target: Aabb2d) -> Option<Collision> {
+    if body.max.x <= target.min.x {
+        Some(Collision::Left)
+    } else if body.min.x >= target.max.x {
+        Some(Collision::Right)
+    } else if body.min.y >= target.max.y {
+        Some(Collision::Top)
+    } else if body.max.y <= target.min.y {
+        Some(Collision::Bottom)
+    } else {
+        None
+    }
+}
+
+/// A body passes a gate if it started the tick on the passable side. One that was already
+/// inside is let out whichever way it goes, so a gate never launches anyone standing in it.
+pub fn gate_lets_through(gate: &Gate, body_at_tick_start: Aabb2d, gate_aabb: Aabb2d) -> bool {
+    side_of(body_at_tick_start, gate_aabb).is_none_or(|side| side == gate.passable_from)
+}
+
+/// A one-way platform only catches a body that's falling or resting and started the tick
+/// with its feet at or above the platform's top.
+fn one_way_lets_through(body_at_tick_start: Aabb2d, velocity: Vec2, platform_aabb: Aabb2d) -> bool {
+    velocity.y > 0. || body_at_tick_start.min.y < platform_aabb.max.y - 0.01
+}
+
+/// The direction of travel a gate allows.
+pub fn pass_direction(passable_from: Collision) -> Vec2 {
+    match passable_from {
+        Collision::Left => Vec2::X,
+        Collision::Right => Vec2::NEG_X,
+        Collision::Top => Vec2::NEG_Y,
+        Collision::Bottom => Vec2::Y,
+    }
+}
+
+/// Whether the segment from `from` to `to` passes through `aabb`, using the slab method.
+pub fn segment_hits_aabb(from: Vec2, to: Vec2, aabb: Aabb2d) -> bool {
+    segment_entry(from, to, aabb).is_some()
+}
+
+/// How far along the segment from `from` to `to` it first enters `aabb`, from 0 at `from`
+/// to 1 at `to`, or `None` if it misses. A segment starting inside enters at 0.
+fn segment_entry(from: Vec2, to: Vec2, aabb: Aabb2d) -> Option<f32> {
+    let delta = to - from;
+    let mut t_min = 0f32;
+    let mut t_max = 1f32;
+    for axis in 0..2 {
+        let (start, dir, lo, hi) = (from[axis], delta[axis], aabb.min[axis], aabb.max[axis]);
+        if dir.abs() < f32::EPSILON {
+            if start < lo || start > hi {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (lo - start) / dir;
+        let t2 = (hi - start) / dir;
+        t_min = t_min.max(t1.min(t2));
+        t_max = t_max.min(t1.max(t2));
+        if t_min > t_max {
+            return None;
+        }
+    }
+    Some(t_min)
+}
+
+/// `move_bodies` moves by the velocity at the end of the tick, which would put half a
+/// tick's worth of gravity too much into every step and make jumps lower at low tick
+/// rates. Taking that half back off the position here makes each step the exact arc under
+/// constant gravity, so a jump peaks at the same height at any tick rate. Dashes and
+/// ground pounds set their own speed and get none.
+pub fn gravitate(
+    mut body: Query<(&mut Position, &mut Velocity, Option<&GravityScale>, Option<&Gravity>, Option<&MovementModifiers>, Option<&Dash>, Has<GravityFlipped>, Option<&mut Up>, Option<&TerminalVelocity>), (With<Gravitated>, Without<InCannon>, Without<Climbing>, Without<GroundPound>)>,
+    global: Res<GlobalGravity>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (mut position, mut velocity, scale, gravity, modifiers, dash, flipped, mut up, terminal) in &mut body {
+        if dash.is_some_and(Dash::is_dashing) {
+            continue;
+        }
+        let mut acceleration = match gravity {
+            Some(gravity) => gravity.0,
+            None => global.0 * scale.map_or(1., |scale| scale.0)
+                * modifiers.map_or(1., |modifiers| modifiers.get(StatId::GravityScale)),
+        };
+        if flipped {
+            acceleration = -acceleration;
+        }
+        if let Some(up) = up.as_mut().filter(|_| acceleration.y != 0.) {
+            up.set_if_neq(Up(-acceleration.y.signum()));
+        }
+        let before = velocity.0;
+        velocity.0 += acceleration * dt;
+        // Speed that `clamp_velocity` is about to take away again was never gained, so
+        // none of it comes off the move either.
+        let mut gained = acceleration * dt;
+        if let Some(terminal) = terminal {
+            let up = up.map_or(1., |up| up.0);
+            let (from, to) = (terminal.cap(before.y, up), terminal.cap(velocity.0.y, up));
+            if (from, to) != (before.y, velocity.0.y) {
+                gained.y = to - from;
+            }
+        }
+        position.0 -= gained * dt / 2.;
+    }
+}
+
+/// A collider as `handle_collisions` saw it at the start of the tick, or once it was
+/// resolved if it's a dynamic body too.
+struct ColliderSnapshot {
+    entity: Entity,
+    aabb: Aabb2d,
+    gate: Option<Gate>,
+    one_way: bool,
+    dynamic: bool,
+    slope: Option<Slope>,
+    /// A body that isn't a collider, like the player, which only holds up a crate coming
+    /// down on it.
+    body: bool,
+}
+
+/// The face of a collider a body with `up` stands on at `x`: its top (or a slope's
+/// surface) normally, its underside upside down.
+fn surface_at(collider: &ColliderSnapshot, up: Up, x: f32) -> f32 {
+    if up.is_flipped() { collider.aabb.min.y } else { top_at(collider.aabb, collider.slope, x) }
+}
+
+/// How many pieces `handle_collisions` splits a body's `step` into, so none moves it more
+/// than half its smaller half-extent.
+fn sub_step_count(step: Vec2, half_size: Vec2) -> u32 {
+    let piece = half_size.min_element() / 2.;
+    ((step.abs().max_element() / piece).ceil() as u32).clamp(1, MAX_SUB_STEPS)
+}
+
+/// Resolves every `DynamicBody` against the `Collider`s the `SpatialGrid` has near it,
+/// other than itself. Colliders are read once up front, since a body can be a collider too
+/// (a crate the player stands on). Bodies go lowest first and a dynamic collider's
+/// snapshot moves to where it settled, so a crate resolves against what's under it after
+/// that has landed: crates stack, and one falling on the player's head rests there. One
+/// dynamic collider never resolves against another still to come; that one does it in its
+/// own turn.
+pub fn handle_collisions(
+    mut bodies: ParamSet<(
+        Query<(Entity, &Position, &Shape, Option<&Gate>, Option<&Slope>, Has<OneWayPlatform>, Has<DynamicBody>), With<Collider>>,
+        Query<(Entity, &mut Position, &Velocity, &Shape, Option<&Grounded>, Option<&CollisionGrace>, Option<&Carrying>, Option<&Up>, Has<Player>, Has<Collider>), (With<DynamicBody>, Without<InCannon>)>,
+    )>,
+    mut contacts: ResMut<Contacts>,
+    mut collisions: EventWriter<CollisionEvent>,
+    mut collision_stats: ResMut<CollisionStats>,
+    config: Res<MovementConfig>,
+    grid: Res<SpatialGrid>,
+    time: Res<Time>,
+) {
+    contacts.0.clear();
+    let dt = time.delta_seconds();
+    // Only colliders the grid has near some body are read, once up front. The reach
+    // covers everything the body's tick can touch: its move, the ground snap below it
+    // and a corner nudge to the side.
+    let margin = Vec2::splat(config.ground_snap_distance + config.corner_correction);
+    let mut nearby: Vec<(Entity, f32, Vec<Entity>)> = bodies.p1().iter()
+        .map(|(body, position, velocity, shape, _, _, carrying, up, ..)| {
+            let lift = Vec2::new(0., carrying.map_or(0., |carrying| carrying.height) / 2.);
+            let reach = Aabb2d::new(position.0 + lift, shape.0 / 2. + lift).grow(velocity.0.abs() * dt + margin);
+            let up = up.copied().unwrap_or_default();
+            let feet = (position.0.y - shape.0.y / 2. * up.0) * up.0;
+            (body, feet, grid.query(reach))
+        })
+        .collect();
+    nearby.sort_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+    let collider_query = bodies.p0();
+    let mut snapshots: HashMap<Entity, ColliderSnapshot> = nearby.iter()
+        .flat_map(|(_, _, near)| near.iter().copied())
+        .filter_map(|entity| collider_query.get(entity).ok())
+        .map(|(entity, position, shape, gate, slope, one_way, dynamic)| (entity, ColliderSnapshot {
+            entity,
+            aabb: Aabb2d::new(position.0, shape.0 / 2.0),
+            gate: gate.copied(),
+            one_way,
+            dynamic,
+            slope: slope.copied(),
+            body: false,
+        }))
+        .collect();
+    // Dynamic colliders already settled this tick, and where the other bodies settled.
+    let mut resolved: HashSet<Entity> = HashSet::new();
+    let mut settled_bodies: Vec<ColliderSnapshot> = Vec::new();
+
+    let mut body_query = bodies.p1();
+    for (body, _, near) in &nearby {
+        let Ok((body, mut p_position, p_velocity, p_shape, grounded, grace, carrying, up, is_player, is_collider)) = body_query.get_mut(*body) else {
+            continue;
+        };
+        let up = up.copied().unwrap_or_default();
+        let mut colliders: Vec<&ColliderSnapshot> = near.iter().filter_map(|entity| snapshots.get(entity)).collect();
+        if is_collider {
+            colliders.extend(&settled_bodies);
+        }
+        let collides_with = |collider: &ColliderSnapshot| {
+            collider.entity != body
+                && !(is_collider && collider.dynamic && !resolved.contains(&collider.entity))
+                && grace.is_none_or(|grace| grace.entity != collider.entity)
+        };
+        // Hits left to the other side: a dynamic collider still to come lands on this
+        // body's head in its own turn instead of shoving it into the floor, and a body
+        // only holds things up.
+        let defers = |collider: &ColliderSnapshot, side: Collision| {
+            collider.dynamic && side == up.head() && !resolved.contains(&collider.entity)
+                || collider.body && side != up.feet()
+        };
+        // Always the real shape: `VisShape` squashes every landing and would shove the
+        // player out of walls it's standing next to. A carried crate extends it upward.
+        let inset = if is_player { config.hitbox_inset } else { 0. };
+        let lift = carrying.map_or(0., |carrying| carrying.height);
+        let half_size = (p_shape.0 / 2.0 - Vec2::new(inset, 0.)).max(Vec2::ONE) + Vec2::new(0., lift / 2.);
+        let center_offset = Vec2::new(0., lift / 2.);
+        // How far `move_bodies` carried the body this tick. A fast body is taken back and
+        // moved again in pieces, resolving after each, so a corner or a wall running into
+        // a floor is met in the order the body reaches them. Slower bodies move in one.
+        let step = p_velocity.0 * dt;
+        let sub_steps = sub_step_count(step, half_size);
+        let mut sub_step = step / sub_steps as f32;
+        if sub_steps > 1 {
+            p_position.0 -= step - sub_step;
+        }
+        let was_grounded = grounded.is_some_and(|grounded| grounded.0);
+        // One per collider touched, whichever pieces touched it.
+        let mut hits: Vec<(Contact, Vec2)> = Vec::new();
+        let mut p_aabb = Aabb2d::new(p_position.0 + center_offset, half_size);
+        for index in 0..sub_steps {
+            if index > 0 {
+                p_position.0 += sub_step;
+            }
+            let start_aabb = Aabb2d::new(p_position.0 + center_offset - sub_step, half_size);
+            let passes = |collider: &ColliderSnapshot, velocity: Vec2| {
+                collider.gate.as_ref().is_some_and(|gate| gate_lets_through(gate, start_aabb, collider.aabb))
+                    || collider.one_way && one_way_lets_through(start_aabb, velocity, collider.aabb)
+            };
+
+            // A fast fall or dash can carry a body clean past a thin block within one tick,
+            // where the overlap pass below never sees it, or past the middle of one, where
+            // that pass would push it on out the far side. Pull the move back to the first
+            // block it went into that way, on the axis that hit it, and let that pass
+            // resolve the contact as usual.
+            let end_aabb = Aabb2d::new(p_position.0 + center_offset, half_size);
+            let mut tunnelled: Option<(Collision, f32)> = None;
+            // Slopes are left out: sweeping their bounding box would stop a body on the empty
+            // half above the ramp.
+            for collider in colliders.iter().filter(|collider| collides_with(collider) && collider.slope.is_none()) {
+                if passes(collider, p_velocity.0) || end_aabb.intersects(&collider.aabb) && !pushed_through(end_aabb, sub_step, collider.aabb) {
+                    continue;
+                }
+                collision_stats.narrow_phase_tests += 1;
+                if let Some((side, t)) = collide_swept(start_aabb, sub_step, collider.aabb).filter(|(side, _)| !defers(collider, *side)) {
+                    if tunnelled.is_none_or(|(_, earliest)| t < earliest) {
+                        tunnelled = Some((side, t));
+                    }
+                }
+            }
+            match tunnelled {
+                Some((Collision::Top | Collision::Bottom, t)) => {
+                    p_position.0.y -= sub_step.y * (1. - t);
+                    sub_step.y = 0.;
+                }
+                Some((Collision::Left | Collision::Right, t)) => {
+                    p_position.0.x -= sub_step.x * (1. - t);
+                    sub_step.x = 0.;
+                }
+                None => {}
+            }
+            p_aabb = Aabb2d::new(p_position.0 + center_offset, half_size);
+
+            for collider in colliders.iter().filter(|collider| collides_with(collider)) {
+                if passes(collider, p_velocity.0) {
+                    continue;
+                }
+                collision_stats.narrow_phase_tests += 1;
+                let hit = match collider.slope {
+                    Some(slope) => collide_slope(p_aabb, collider.aabb, slope, config.max_slope()),
+                    None => collide(p_aabb, collider.aabb).map(|(collision, offset)| (collision, push_out(collision, offset))),
+                };
+                if let Some((mut collision, mut push)) = hit {
+                    // Clipping a one-way platform's corner on the way down isn't a landing.
+                    if collider.one_way && collision != Collision::Bottom || defers(collider, collision) {
+                        continue;
+                    }
+                    let overlap = overlap_extents(p_aabb, collider.aabb);
+                    match collision {
+                        // Grazing a ceiling's corner on the way up slips past it instead of
+                        // ending the jump, as long as there's room to the side. Upside down
+                        // the ceiling is below.
+                        head if head == up.head() && p_velocity.0.y * up.0 > 0. && overlap.x <= config.corner_correction => {
+                            let away = (p_aabb.center().x - collider.aabb.center().x).signum();
+                            let nudge = Vec2::new(away * (overlap.x + CORNER_CLEARANCE), 0.);
+                            let nudged = Aabb2d::new(p_aabb.center() + nudge, half_size);
+                            let blocked = colliders.iter()
+                                .filter(|other| other.entity != collider.entity && collides_with(other) && !passes(other, p_velocity.0))
+                                .any(|other| overlap_extents(nudged, other.aabb).cmpgt(Vec2::ZERO).all());
+                            if !blocked {
+                                p_position.0 += nudge;
+                                continue;
+                            }
+                        }
+                        // Walking into a lip that's barely above the feet, like the seam between
+                        // two misaligned blocks, steps up onto it and keeps going, as long as
+                        // there's room overhead. Standing on it straight away isn't a landing.
+                        // Partway up a ramp the leading edge is already over higher ground than
+                        // the feet, so the lip at the top counts from the ramp under that edge.
+                        Collision::Left | Collision::Right if is_player && was_grounded && collider.slope.is_none() => {
+                            let rise = if up.is_flipped() { p_aabb.max.y - collider.aabb.min.y } else { collider.aabb.max.y - p_aabb.min.y };
+                            let feet = if up.is_flipped() { p_aabb.max.y } else { p_aabb.min.y };
+                            let lead = if p_velocity.0.x > 0. { p_aabb.max.x } else { p_aabb.min.x };
+                            let ramp = colliders.iter()
+                                .filter(|other| other.slope.is_some() && collides_with(other) && other.aabb.intersects(&p_aabb))
+                                .map(|other| (surface_at(other, up, lead) - feet) * up.0)
+                                .fold(0., f32::max);
+                            let toward = p_velocity.0.x * (collider.aabb.center().x - p_aabb.center().x) > 0.;
+                            let lift = Vec2::new(0., (rise + CORNER_CLEARANCE) * up.0);
+                            let lifted = Aabb2d::new(p_aabb.center() + lift, half_size);
+                            let blocked = colliders.iter()
+                                .filter(|other| collides_with(other) && !passes(other, p_velocity.0))
+                                .any(|other| overlap_extents(lifted, other.aabb).cmpgt(Vec2::ZERO).all());
+                            if toward && rise > 0. && rise - ramp <= STEP_HEIGHT && !blocked {
+                                collision = up.feet();
+                                push = lift;
+                            }
+                        }
+                        // Coming down just past a ledge's edge lands on it rather than being
+                        // shoved off the side.
+                        Collision::Left | Collision::Right if p_velocity.0.y * up.0 < 0. && !was_grounded && overlap.y <= config.corner_correction => {
+                            collision = up.feet();
+                            push = Vec2::new(0., overlap.y * up.0);
+                        }
+                        _ => {}
+                    }
+                    p_position.0 += push;
+                    // The rest of the tick's move doesn't carry on into what it just hit.
+                    let into = sub_step.dot(collision.normal());
+                    if into < 0. {
+                        sub_step -= collision.normal() * into;
+                    }
+                    match hits.iter_mut().find(|(contact, _)| contact.other == collider.entity) {
+                        Some((contact, offset)) => {
+                            contact.side = collision;
+                            *offset += push;
+                        }
+                        None => hits.push((Contact {
+                            body,
+                            other: collider.entity,
+                            side: collision,
+                            velocity: p_velocity.0,
+                        }, push)),
+                    }
+                }
+            }
+        }
+        for (contact, offset) in hits {
+            collisions.send(CollisionEvent { entity: body, other: contact.other, side: contact.side, offset });
+            contacts.0.push(contact);
+        }
+
+        let on_ground = contacts.of(body).any(|contact| contact.side == up.feet());
+        if !on_ground && was_grounded && p_velocity.0.y * up.0 <= 0. {
+            // Stepping down a stair or off a sinking block shouldn't count as leaving the
+            // ground, so look a little way below the feet for something to stand on.
+            // Upside down that's above them, against the undersides of blocks.
+            let feet = p_position.0.y + center_offset.y - half_size.y * up.0;
+            let probe = Aabb2d::new(
+                Vec2::new(p_position.0.x, feet - config.ground_snap_distance / 2. * up.0),
+                Vec2::new(half_size.x, config.ground_snap_distance / 2.),
+            );
+            let below = colliders.iter()
+                .filter(|collider| collides_with(collider))
+                .map(|collider| (collider, surface_at(collider, up, p_position.0.x)))
+                .filter(|(collider, surface)| {
+                    let drop = (feet - surface) * up.0;
+                    let over_ramp = (collider.aabb.min.x..=collider.aabb.max.x).contains(&p_position.0.x);
+                    let steep = collider.slope.is_some_and(|slope| over_ramp && slope.too_steep(collider.aabb, config.max_slope()));
+                    probe.intersects(&collider.aabb) && !steep && (-0.01..=config.ground_snap_distance).contains(&drop)
+                        && !collider.gate.as_ref().is_some_and(|gate| gate_lets_through(gate, p_aabb, collider.aabb))
+                })
+                .max_by(|(_, a), (_, b)| (a * up.0).total_cmp(&(b * up.0)));
+            if let Some((collider, surface)) = below {
+                let push = Vec2::new(0., surface - feet);
+                p_position.0 += push;
+                contacts.0.push(Contact {
+                    body,
+                    other: collider.entity,
+                    side: up.feet(),
+                    velocity: p_velocity.0,
+                });
+                collisions.send(CollisionEvent { entity: body, other: collider.entity, side: up.feet(), offset: push });
+            }
+        }
+
+        let settled = Aabb2d::new(p_position.0 + center_offset, half_size);
+        if is_collider {
+            if let Some(snapshot) = snapshots.get_mut(&body) {
+                snapshot.aabb = settled;
+            }
+            resolved.insert(body);
+        } else {
+            settled_bodies.push(ColliderSnapshot {
+                entity: body,
+                aabb: settled,
+                gate: None,
+                one_way: false,
+                dynamic: true,
+                slope: None,
+                body: true,
+            });
+        }
+    }
+}
+
+/// Takes the velocity heading into each surface a body was pushed out of, so it rests
+/// against it instead of pressing on. Velocity already heading away, like a slime bounce
+/// set earlier in `PhysicsSet::Resolve`, is left alone.
+pub fn stop_at_collisions(
+    mut collisions: EventReader<CollisionEvent>,
+    mut bodies: Query<&mut Velocity>,
+) {
+    for event in collisions.read() {
+        let Ok(mut velocity) = bodies.get_mut(event.entity) else {
+            continue;
+        };
+        let normal = event.side.normal();
+        let into = velocity.0.dot(normal);
+        if into < 0. {
+            velocity.0 -= normal * into;
+        }
+    }
+}
+
+pub fn update_ground_contact(
+    mut bodies: Query<(Entity, &mut GroundContact, Option<&Up>)>,
+    contacts: Res<Contacts>,
+    mut landed: EventWriter<Landed>,
+) {
+    for (entity, mut ground, up) in &mut bodies {
+        let up = up.copied().unwrap_or_default();
+        let floor = contacts.of(entity).find(|contact| contact.side == up.feet());
+        let standing_on = floor.map(|contact| contact.other);
+        if let (None, Some(floor)) = (ground.0, floor) {
+            landed.send(Landed { entity, speed: (-floor.velocity.y * up.0).max(0.) });
+        }
+        ground.set_if_neq(GroundContact(standing_on));
+    }
+}
+
+fn remember_previous_transforms(
+    mut commands: Commands,
+    mut bodies: Query<(Entity, &Position, &Rotation, Option<&mut PreviousPosition>, Option<&mut PreviousRotation>), With<Transform>>,
+) {
+    for (entity, position, rotation, previous_position, previous_rotation) in &mut bodies {
+        match (previous_position, previous_rotation) {
+            (Some(mut previous_position), Some(mut previous_rotation)) => {
+                previous_position.0 = position.0;
+                previous_rotation.0 = rotation.0;
+            }
+            _ => {
+                commands.entity(entity).insert((PreviousPosition(position.0), PreviousRotation(rotation.0)));
+            }
+        }
+    }
+}
+
+fn clear_teleported(
+    mut commands: Commands,
+    teleported: Query<Entity, With<Teleported>>,
+) {
+    for entity in &teleported {
+        commands.entity(entity).remove::<Teleported>();
+    }
+}
+
+fn tick_collision_grace(
+    mut commands: Commands,
+    mut bodies: Query<(Entity, &mut CollisionGrace)>,
+    time: Res<Time>,
+) {
+    for (entity, mut grace) in &mut bodies {
+        if grace.timer.tick(time.delta_seconds()).finished() {
+            commands.entity(entity).remove::<CollisionGrace>();
+        }
+    }
+}
+
+/// Falling and rising are taken relative to `Up`, so a body upside down falls upward.
+pub fn clamp_velocity(mut bodies: Query<(&mut Velocity, &TerminalVelocity, Option<&Up>)>) {
+    for (mut velocity, terminal, up) in &mut bodies {
+        velocity.0.y = terminal.cap(velocity.0.y, up.map_or(1., |up| up.0));
+    }
+}
+
+pub fn move_bodies(
+    mut body: Query<(&mut Position, &Velocity)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (mut position, velocity) in &mut body {
+        position.0 += velocity.0 * dt
+    }
+}
+
+/// Draws every body partway between its last two fixed ticks, by how far the clock has
+/// run into the next one, so motion stays even when the frame rate doesn't divide 144.
+/// Teleported bodies and anything with `InterpolationDisabled` snap, and so does
+/// everything while the game is paused, so the editor shows blocks right where it puts
+/// them.
+pub fn project_transforms(
+    mut transformables: Query<(&mut Transform, &Position, &Rotation, &ZOrder, Option<&PreviousPosition>, Option<&PreviousRotation>, Has<Teleported>, Has<InterpolationDisabled>)>,
+    fixed: Res<Time<Fixed>>,
+    virtual_time: Res<Time<Virtual>>,
+) {
+    let alpha = if virtual_time.is_paused() { 1. } else { fixed.overstep_fraction() };
+    for (mut transform, position, rotation, z_order, previous_position, previous_rotation, teleported, disabled) in &mut transformables {
+        let (mut drawn_at, mut angle) = (position.0, rotation.0);
+        if let (Some(previous_position), Some(previous_rotation), false) = (previous_position, previous_rotation, teleported || disabled) {
+            drawn_at = previous_position.0.lerp(position.0, alpha);
+            // The short way round, for anything that spins past a full turn.
+            let turn = (rotation.0 - previous_rotation.0 + PI).rem_euclid(TAU) - PI;
+            angle = previous_rotation.0 + turn * alpha;
+        }
+        transform.translation = drawn_at.extend(z_order.0);
+        transform.rotation = Quat::from_axis_angle(Vec3::Z, angle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::SystemState;
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+    use crate::headless::build_headless_app;
+    use crate::input::{Action, ScriptedInput};
+    use crate::player::{Grounded, Player, VisShape};
+    use crate::world::{BlockData, BlockIndex, BlockKind, WorldData};
+
+    #[test]
+    fn collide_names_the_side_pushed_in() {
+        let block = Aabb2d::new(Vec2::ZERO, Vec2::splat(50.));
+        let body = |x: f32, y: f32| Aabb2d::new(Vec2::new(x, y), Vec2::splat(10.));
+        // Sunk 5px in through each face of the block.
+        assert_eq!(collide(body(0., 55.), block), Some((Collision::Bottom, Vec2::new(60., 5.))));
+        assert_eq!(collide(body(0., -55.), block), Some((Collision::Top, Vec2::new(60., 5.))));
+        assert_eq!(collide(body(-55., 0.), block), Some((Collision::Right, Vec2::new(5., 60.))));
+        assert_eq!(collide(body(55., 0.), block), Some((Collision::Left, Vec2::new(5., 60.))));
+        // Just as deep both ways on a corner, and that lands.
+        assert_eq!(collide(body(55., 55.), block), Some((Collision::Bottom, Vec2::splat(5.))));
+        assert_eq!(collide(body(0., 61.), block), None);
+    }
+
+    #[test]
+    fn collide_goes_by_depth_not_by_shape() {
+        let block = Aabb2d::new(Vec2::ZERO, Vec2::splat(50.));
+        // Tall and thin, sunk 5px into the top: deeper across than down, but it landed.
+        let tall = Aabb2d::new(Vec2::new(0., 95.), Vec2::new(5., 50.));
+        assert_eq!(collide(tall, block), Some((Collision::Bottom, Vec2::new(55., 5.))));
+        // Just as deep both ways over the corner, and that lands too.
+        let corner = Aabb2d::new(Vec2::new(50., 95.), Vec2::new(5., 50.));
+        assert_eq!(collide(corner, block), Some((Collision::Bottom, Vec2::splat(5.))));
+        // Wide and flat, 5px into the right face.
+        let wide = Aabb2d::new(Vec2::new(145., 0.), Vec2::new(100., 5.));
+        assert_eq!(collide(wide, block), Some((Collision::Left, Vec2::new(5., 55.))));
+        // Wholly inside, right of center: out by the right face, the nearest.
+        let inside = Aabb2d::new(Vec2::new(30., 0.), Vec2::splat(10.));
+        assert_eq!(collide(inside, block), Some((Collision::Left, Vec2::new(30., 60.))));
+        assert_eq!(push_out(Collision::Left, Vec2::new(30., 60.)), Vec2::new(30., 0.));
+    }
+
+    #[test]
+    fn a_conveyor_into_a_wall_is_two_contacts() {
+        let conveyor = BlockData { kind: BlockKind::Conveyor { speed: 200. }, ..BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.)) };
+        let wall = BlockData::new(Vec2::new(300., 0.), Vec2::new(50., 400.));
+        let mut app = build_headless_app(WorldData(vec![conveyor, wall]));
+        // Long enough to land and be carried along into the wall.
+        for _ in 0..576 {
+            app.update();
+        }
+        let mut players = app.world_mut().query_filtered::<Entity, With<Player>>();
+        let player = players.single(app.world());
+        let mut blocks = app.world_mut().query::<(Entity, &BlockIndex)>();
+        let mut blocks: Vec<_> = blocks.iter(app.world()).map(|(entity, index)| (index.0, entity)).collect();
+        blocks.sort();
+        let [(_, conveyor), (_, wall)] = blocks[..] else {
+            panic!("expected two blocks, got {blocks:?}");
+        };
+        let mut touching: Vec<_> = app.world().resource::<Contacts>().of(player).map(|contact| (contact.other, contact.side)).collect();
+        touching.sort_by_key(|(other, _)| *other != conveyor);
+        assert_eq!(touching, [(conveyor, Collision::Bottom), (wall, Collision::Right)]);
+    }
+
+    #[test]
+    fn switching_world_gravity_pulls_every_body_the_new_way() {
+        let mut app = build_headless_app(WorldData(Vec::new()));
+        let bodies = [1., 0.5, 2.].map(|scale| {
+            app.world_mut().spawn((Position(Vec2::ZERO), Velocity(Vec2::ZERO), GravityScale(scale), Gravitated)).id()
+        });
+        // Falling under the usual gravity first.
+        for _ in 0..10 {
+            app.update();
+        }
+        let before = bodies.map(|body| app.world().get::<Velocity>(body).unwrap().0);
+        assert!(before.iter().all(|velocity| velocity.y < 0.));
+        let sideways = Vec2::new(-300., 0.);
+        app.world_mut().resource_mut::<GlobalGravity>().0 = sideways;
+        for _ in 0..144 {
+            app.update();
+        }
+        for (body, before) in bodies.into_iter().zip(before) {
+            let scale = app.world().get::<GravityScale>(body).unwrap().0;
+            let gained = app.world().get::<Velocity>(body).unwrap().0 - before;
+            // A second's worth of the new pull, and nothing more of the old one.
+            assert!(gained.distance(sideways * scale) < 0.01, "scale {scale} body gained {gained}");
+        }
+    }
+
+    /// Set to send the player off to `x` on the next tick.
+    #[derive(Resource, Default)]
+    struct SendTo(Option<f32>);
+
+    fn send_player(mut commands: Commands, mut players: Query<(Entity, &mut Position), With<Player>>, mut send: ResMut<SendTo>) {
+        if let Some(x) = send.0.take() {
+            let (entity, mut position) = players.single_mut();
+            let to = Vec2::new(x, position.0.y);
+            position.teleport(&mut commands, entity, to);
+        }
+    }
+
+    #[test]
+    fn a_teleport_is_never_drawn_partway() {
+        let mut app = build_headless_app(WorldData(vec![BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.))]));
+        // Frames that don't line up with the ticks, so most are drawn between two of them.
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1. / 200.)))
+            .init_resource::<SendTo>()
+            .add_systems(FixedUpdate, send_player.after(PhysicsSet::Resolve));
+        for _ in 0..200 {
+            app.update();
+        }
+        app.world_mut().resource_mut::<SendTo>().0 = Some(1000.);
+        let mut players = app.world_mut().query_filtered::<&Transform, With<Player>>();
+        let mut arrived = false;
+        for _ in 0..100 {
+            app.update();
+            let x = players.single(app.world()).translation.x;
+            assert!(x.abs() < 1. || (x - 1000.).abs() < 1., "drawn at {x}");
+            arrived |= x > 999.;
+        }
+        assert!(arrived);
+    }
+
+    /// Overrides the player's velocity after the speed caps, every tick it's set.
+    #[derive(Resource)]
+    struct Fling(Vec2);
+
+    fn fling_player(mut players: Query<&mut Velocity, With<Player>>, fling: Res<Fling>) {
+        players.single_mut().0 = fling.0;
+    }
+
+    /// Where the player ends up flung at a 1px thick block with `velocity`, set after the
+    /// speed caps every tick. At 1000px a tick that's too fast for sub-steps to catch.
+    fn fling_at(block: BlockData, velocity: Vec2) -> Vec2 {
+        let mut app = build_headless_app(WorldData(vec![block]));
+        app.insert_resource(Fling(velocity))
+            .add_systems(FixedUpdate, fling_player.after(clamp_velocity).before(move_bodies));
+        for _ in 0..20 {
+            app.update();
+        }
+        let mut players = app.world_mut().query_filtered::<&Position, With<Player>>();
+        players.single(app.world()).0
+    }
+
+    #[test]
+    fn a_fast_fall_lands_on_a_one_pixel_block() {
+        let position = fling_at(BlockData::new(Vec2::new(0., -180.), Vec2::new(400., 1.)), Vec2::new(0., -1000. * 144.));
+        // Half the player's height above the block's top at -179.5.
+        assert!((position.y + 129.5).abs() < 0.01, "ended up at {position}");
+    }
+
+    #[test]
+    fn a_fast_run_stops_at_a_one_pixel_wall() {
+        let position = fling_at(BlockData::new(Vec2::new(300., 0.), Vec2::new(1., 400.)), Vec2::new(1000. * 144., 0.));
+        // The hitbox's right edge, 26px from the middle, against the wall's face at 299.5.
+        assert!((position.x - 273.5).abs() < 0.01, "ended up at {position}");
+    }
+
+    #[test]
+    fn landing_against_a_wall_leaves_the_player_where_they_were() {
+        // The hitbox's right edge, 4px in from the sprite's, touches the wall's face at 26.
+        let mut app = build_headless_app(WorldData(vec![
+            BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.)),
+            BlockData::new(Vec2::new(51., 0.), Vec2::new(50., 400.)),
+        ]));
+        let mut players = app.world_mut().query_filtered::<(&Position, &VisShape, &Grounded), With<Player>>();
+        let mut squashed = false;
+        for _ in 0..288 {
+            app.update();
+            let (position, vis_shape, grounded) = players.single(app.world());
+            squashed |= grounded.0 && vis_shape.0.x > 60.;
+            assert_eq!(position.0.x, 0., "pushed off the wall");
+        }
+        assert!(squashed, "never squashed on landing");
+    }
+
+    #[test]
+    fn a_gap_as_wide_as_the_sprite_can_be_dropped_down() {
+        // A 60px shaft from the floor up to just under the player's feet.
+        let wall = |x| BlockData::new(Vec2::new(x, -117.5), Vec2::new(50., 115.));
+        let mut app = build_headless_app(WorldData(vec![
+            BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.)),
+            wall(-55.),
+            wall(55.),
+        ]));
+        for _ in 0..288 {
+            app.update();
+        }
+        let mut players = app.world_mut().query_filtered::<(&Position, &Grounded), With<Player>>();
+        let (position, grounded) = players.single(app.world());
+        assert!(grounded.0 && (position.0.y + 125.).abs() < 1., "stuck at {}", position.0);
+    }
+
+    /// Walks the player right across `level` until their center passes `end`, calling
+    /// `check` with where they are and whether they're grounded after every tick.
+    fn walk_right(level: Vec<BlockData>, end: f32, mut check: impl FnMut(Vec2, bool)) {
+        let mut app = build_headless_app(WorldData(level));
+        for _ in 0..144 {
+            app.update();
+        }
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].press(Action::MoveRight);
+        let mut players = app.world_mut().query_filtered::<(&Position, &Grounded), With<Player>>();
+        for _ in 0..1440 {
+            app.update();
+            let (position, grounded) = players.single(app.world());
+            if position.0.x > end {
+                return;
+            }
+            check(position.0, grounded.0);
+        }
+        panic!("never got to {end}");
+    }
+
+    #[test]
+    fn walking_down_small_steps_stays_grounded() {
+        // 100px treads, each 6px lower than the last.
+        let steps = (0..10).map(|step| BlockData::new(Vec2::new(step as f32 * 100., -200. - step as f32 * 6.), Vec2::new(100., 50.)));
+        walk_right(steps.collect(), 900., |position, grounded| assert!(grounded, "left the ground at {position}"));
+    }
+
+    #[test]
+    fn walking_off_a_ledge_leaves_the_ground_straight_away() {
+        // The upper floor ends at 300, with a 100px drop after it.
+        let level = vec![
+            BlockData::new(Vec2::new(-200., -200.), Vec2::new(1000., 50.)),
+            BlockData::new(Vec2::new(1300., -300.), Vec2::new(2000., 50.)),
+        ];
+        let mut over_the_edge = 0;
+        walk_right(level, 400., |position, grounded| {
+            // The hitbox is 52px wide, so its back edge has left the floor.
+            if position.x - 26. > 300. {
+                assert!(!grounded, "still grounded at {position}");
+                over_the_edge += 1;
+            }
+        });
+        assert!(over_the_edge > 0);
+    }
+
+    /// A world with a block centered on the origin, 100px square, and a crate of the same
+    /// size just right of it, sharing its edge at 50, both filed in the grid.
+    struct Scene {
+        world: World,
+        block: Entity,
+        crate_box: Entity,
+    }
+
+    impl Scene {
+        fn new() -> Self {
+            let mut world = World::new();
+            let block = world.spawn((Collider, Position(Vec2::ZERO), Shape(Vec2::splat(100.)))).id();
+            let crate_box = world.spawn((Collider, Crate, Position(Vec2::new(100., 0.)), Shape(Vec2::splat(100.)))).id();
+            let mut grid = SpatialGrid::default();
+            grid.insert(block, Aabb2d::new(Vec2::ZERO, Vec2::splat(50.)));
+            grid.insert(crate_box, Aabb2d::new(Vec2::new(100., 0.), Vec2::splat(50.)));
+            world.insert_resource(grid);
+            Self { world, block, crate_box }
+        }
+
+        fn ask<T>(&mut self, question: impl FnOnce(&PhysicsWorld) -> T) -> T {
+            let mut state = SystemState::<PhysicsWorld>::new(&mut self.world);
+            question(&state.get(&self.world))
+        }
+
+        fn overlapping(&mut self, center: Vec2, size: f32, filter: LayerMask) -> Vec<Entity> {
+            let mut found = self.ask(|physics| physics.overlap_aabb(Aabb2d::new(center, Vec2::splat(size / 2.)), filter)).to_vec();
+            found.sort();
+            found
+        }
+    }
+
+    #[test]
+    fn overlap_queries_find_what_overlaps() {
+        let mut scene = Scene::new();
+        assert_eq!(scene.overlapping(Vec2::new(40., 0.), 40., LayerMask::ALL), [scene.block, scene.crate_box]);
+        assert_eq!(scene.ask(|physics| physics.overlap_point(Vec2::new(-20., 30.), LayerMask::ALL)), Some(scene.block));
+        assert_eq!(scene.ask(|physics| physics.overlap_point(Vec2::new(0., 80.), LayerMask::ALL)), None);
+    }
+
+    #[test]
+    fn touching_counts_for_points_but_not_boxes() {
+        let mut scene = Scene::new();
+        // Flush against the block's left edge.
+        assert_eq!(scene.overlapping(Vec2::new(-60., 0.), 20., LayerMask::ALL), []);
+        assert_eq!(scene.ask(|physics| physics.overlap_point(Vec2::new(-50., 0.), LayerMask::ALL)), Some(scene.block));
+    }
+
+    #[test]
+    fn layers_leave_out_what_they_dont_ask_for() {
+        let mut scene = Scene::new();
+        let join = Vec2::new(50., 0.);
+        assert_eq!(scene.overlapping(join, 40., LayerMask::BLOCKS), [scene.block]);
+        assert_eq!(scene.overlapping(join, 40., LayerMask::CRATES), [scene.crate_box]);
+        assert_eq!(scene.overlapping(join, 40., LayerMask::GATES), []);
+        assert_eq!(scene.overlapping(join, 40., LayerMask::BLOCKS | LayerMask::CRATES), [scene.block, scene.crate_box]);
+    }
+
+    #[test]
+    fn walking_and_falling_take_a_single_step() {
+        let config = MovementConfig::default();
+        // Half the player's hitbox.
+        let half_size = Vec2::new(26., 50.);
+        for speed in [config.max_speed, config.run_speed, -config.run_speed] {
+            assert_eq!(sub_step_count(Vec2::new(speed / 144., 0.), half_size), 1, "at {speed}");
+        }
+        assert_eq!(sub_step_count(Vec2::new(0., -1728. / 144.), half_size), 1);
+        assert_eq!(sub_step_count(Vec2::new(1000., 0.), half_size), MAX_SUB_STEPS);
+    }
+
+    #[test]
+    fn resolving_a_walk_leaves_the_move_untouched() {
+        let mut app = build_headless_app(WorldData(vec![BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.))]));
+        for _ in 0..144 {
+            app.update();
+        }
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].press(Action::MoveRight);
+        let mut players = app.world_mut().query_filtered::<(&Position, &Velocity), With<Player>>();
+        let mut last = players.single(app.world()).0.0.x;
+        for _ in 0..144 {
+            app.update();
+            let dt = app.world().resource::<Time<Fixed>>().delta_seconds();
+            let (position, velocity) = players.single(app.world());
+            // Exactly where `move_bodies` put it, to the bit.
+            assert_eq!(position.0.x.to_bits(), (last + velocity.0.x * dt).to_bits());
+            last = position.0.x;
+        }
+        assert!(last > 500., "only walked to {last}");
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+
+use crate::events::OriginShifted;
+use crate::level::{Downed, LevelEntity, ResetLevel};
+use crate::movement::{MovementModifiers, StatModifier};
+use crate::origin::FollowOrigin;
+use crate::particles::spawn_ring;
+use crate::physics::{PhysicsSet, Position, Rotation, Shape, ZOrder};
+use crate::player::Player;
+use crate::world::WorldData;
+use crate::GameState;
+
+const PICKUP_SIZE: f32 = 24.;
+const BOB_HEIGHT: f32 = 4.;
+const BOB_SPEED: f32 = 3.;
+const COLLECT_RING_RADIUS: f32 = 40.;
+const COLLECT_RING_DOTS: usize = 12;
+const COLLECT_RING_SECS: f32 = 0.3;
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Restarting), spawn_pickups.after(ResetLevel))
+            .add_systems(FixedUpdate, (bob, collect_pickups.after(PhysicsSet::Resolve)))
+            .add_systems(FixedPostUpdate, shift_bobbing.in_set(FollowOrigin));
+    }
+}
+
+/// A collectible power-up. Everything it does is in `modifiers`, so a new power-up is just
+/// another entry in the level data.
+#[derive(Clone, Debug)]
+pub struct PickupData {
+    pub position: Vec2,
+    pub color: Color,
+    pub modifiers: Vec<StatModifier>,
+}
+
+/// Pickups placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component)]
+pub struct PickupSpawns(pub Vec<PickupData>);
+
+#[derive(Component)]
+pub struct Pickup {
+    modifiers: Vec<StatModifier>,
+    color: Color,
+}
+
+/// Floats an entity up and down around `home` while it waits to be collected.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Bobbing {
+    pub home: Vec2,
+    pub amplitude: f32,
+    pub phase: f32,
+}
+
+impl Bobbing {
+    pub fn new(home: Vec2, amplitude: f32) -> Self {
+        Self { home, amplitude, phase: 0. }
+    }
+}
+
+fn spawn_pickups(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    world_data: Query<&PickupSpawns, With<WorldData>>,
+) {
+    let Ok(spawns) = world_data.get_single() else {
+        return;
+    };
+    for data in &spawns.0 {
+        spawn_pickup(&mut commands, &mut meshes, &mut materials, data);
+    }
+}
+
+pub fn spawn_pickup(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    data: &PickupData,
+) -> Entity {
+    commands.spawn((
+        Pickup {
+            modifiers: data.modifiers.clone(),
+            color: data.color,
+        },
+        Bobbing::new(data.position, BOB_HEIGHT),
+        Position(data.position),
+        Shape(Vec2::splat(PICKUP_SIZE)),
+        Rotation(0.),
+        ZOrder(0.08),
+        ColorMesh2dBundle {
+            mesh: meshes.add(Rhombus::new(PICKUP_SIZE, PICKUP_SIZE)).into(),
+            material: materials.add(data.color),
+            ..default()
+        },
+        LevelEntity,
+    )).id()
+}
+
+fn bob(mut bobbing: Query<(&mut Position, &mut Bobbing)>, time: Res<Time>) {
+    for (mut position, mut bobbing) in &mut bobbing {
+        bobbing.phase += time.delta_seconds() * BOB_SPEED;
+        position.0 = bobbing.home + Vec2::Y * bobbing.phase.sin() * bobbing.amplitude;
+    }
+}
+
+fn shift_bobbing(mut shifted: EventReader<OriginShifted>, mut bobbing: Query<&mut Bobbing>) {
+    for event in shifted.read() {
+        for mut bobbing in &mut bobbing {
+            bobbing.home -= event.offset;
+        }
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    mut players: Query<(&Position, &Shape, &mut MovementModifiers), (With<Player>, Without<Downed>)>,
+    pickups: Query<(Entity, &Position, &Shape, &Pickup)>,
+) {
+    for (entity, position, shape, pickup) in &pickups {
+        // The first player to reach it gets it.
+        let collector = players.iter_mut().find(|(player_pos, player_shape, _)| {
+            let reach = (player_shape.0 + shape.0) / 2.;
+            (player_pos.0 - position.0).abs().cmple(reach).all()
+        });
+        let Some((_, _, mut modifiers)) = collector else {
+            continue;
+        };
+        for modifier in &pickup.modifiers {
+            modifiers.push(*modifier);
+        }
+        spawn_ring(&mut commands, position.0, COLLECT_RING_RADIUS, pickup.color, COLLECT_RING_DOTS, COLLECT_RING_SECS, false);
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::events::OriginShifted;
+use crate::origin::FollowOrigin;
+use crate::physics::{move_bodies, GroundContact, Position};
+use crate::player::Player;
+use crate::timer::GameTimer;
+
+pub struct PlatformPlugin;
+
+impl Plugin for PlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, (move_platforms, carry_riders).chain().before(move_bodies))
+            .add_systems(FixedPostUpdate, shift_paths.in_set(FollowOrigin));
+    }
+}
+
+/// How a platform speeds up and slows down over each leg of its path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Starts and stops gently.
+    EaseInOut,
+    /// Leaves at full speed and brakes into the next waypoint.
+    SmoothStop,
+}
+
+impl Easing {
+    /// Maps time along a leg, 0 to 1, to distance along it.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInOut => t * t * (3. - 2. * t),
+            Easing::SmoothStop => 1. - (1. - t) * (1. - t),
+        }
+    }
+}
+
+/// What a platform does after reaching its last waypoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize)]
+pub enum PathMode {
+    /// Retraces the waypoints back to the first.
+    #[default]
+    PingPong,
+    /// Heads straight from the last waypoint to the first and goes round again.
+    Loop,
+}
+
+/// Waypoints a platform visits in order, then back again or round again.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct PlatformPath {
+    pub waypoints: Vec<Vec2>,
+    /// Average speed along each leg, in pixels per second.
+    pub speed: f32,
+    #[serde(default)]
+    pub easing: Easing,
+    #[serde(default)]
+    pub mode: PathMode,
+    /// Seconds spent waiting at either end of the path, or at the first waypoint of a loop.
+    #[serde(default)]
+    pub dwell: f32,
+}
+
+impl PlatformPath {
+    /// The same path with every waypoint shifted by `offset`.
+    pub fn placed_at(&self, offset: Vec2) -> Self {
+        Self {
+            waypoints: self.waypoints.iter().map(|waypoint| *waypoint + offset).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Legs in one full trip: out along the waypoints and back, or once round the loop.
+    fn leg_count(&self) -> usize {
+        let last = self.waypoints.len().saturating_sub(1);
+        match self.mode {
+            PathMode::PingPong => 2 * last,
+            PathMode::Loop => last + 1,
+        }
+    }
+
+    /// Legs that cover different ground. A ping-pong path's way back retraces these.
+    #[cfg_attr(not(feature = "editor"), allow(dead_code))]
+    pub fn distinct_legs(&self) -> usize {
+        match self.mode {
+            PathMode::PingPong => self.leg_count() / 2,
+            PathMode::Loop => self.leg_count(),
+        }
+    }
+
+    /// Start and end of `leg`, counting the way back after the way out.
+    pub fn leg(&self, leg: usize) -> (Vec2, Vec2) {
+        let count = self.waypoints.len();
+        let last = count - 1;
+        let (from, to) = match self.mode {
+            PathMode::Loop => (leg, (leg + 1) % count),
+            PathMode::PingPong if leg < last => (leg, leg + 1),
+            PathMode::PingPong => (2 * last - leg, 2 * last - leg - 1),
+        };
+        (self.waypoints[from], self.waypoints[to])
+    }
+
+    /// Whether the platform waits before setting off on `leg`.
+    fn dwells_before(&self, leg: usize) -> bool {
+        match self.mode {
+            PathMode::PingPong => leg == 0 || leg == self.leg_count() / 2,
+            PathMode::Loop => leg == 0,
+        }
+    }
+
+    pub fn leg_secs(&self, leg: usize) -> f32 {
+        let (from, to) = self.leg(leg);
+        from.distance(to) / self.speed.max(f32::EPSILON)
+    }
+
+    /// Where the platform is `secs` into `leg`.
+    pub fn point_on(&self, leg: usize, secs: f32) -> Vec2 {
+        let (from, to) = self.leg(leg);
+        from.lerp(to, self.easing.apply(secs / self.leg_secs(leg).max(f32::EPSILON)))
+    }
+}
+
+/// A block that follows a `PlatformPath`. Its position comes straight from the eased
+/// path every tick instead of from a velocity, so `delta` is exactly how far it moved.
+#[derive(Component)]
+pub struct MovingPlatform {
+    pub path: PlatformPath,
+    leg: usize,
+    secs: f32,
+    dwell: Option<GameTimer>,
+    /// How far the platform moved this tick.
+    pub delta: Vec2,
+}
+
+impl MovingPlatform {
+    pub fn new(path: PlatformPath) -> Self {
+        Self {
+            path,
+            leg: 0,
+            secs: 0.,
+            dwell: None,
+            delta: Vec2::ZERO,
+        }
+    }
+}
+
+fn shift_paths(mut shifted: EventReader<OriginShifted>, mut platforms: Query<&mut MovingPlatform>) {
+    for event in shifted.read() {
+        for mut platform in &mut platforms {
+            platform.path = platform.path.placed_at(-event.offset);
+        }
+    }
+}
+
+fn move_platforms(
+    mut platforms: Query<(&mut MovingPlatform, &mut Position)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (mut platform, mut position) in &mut platforms {
+        let platform = &mut *platform;
+        platform.delta = Vec2::ZERO;
+        if let Some(dwell) = &mut platform.dwell {
+            if !dwell.tick(dt).finished() {
+                continue;
+            }
+            platform.dwell = None;
+        }
+
+        platform.secs += dt;
+        let mut next = platform.path.point_on(platform.leg, platform.secs);
+        if platform.secs >= platform.path.leg_secs(platform.leg) {
+            next = platform.path.leg(platform.leg).1;
+            platform.leg = (platform.leg + 1) % platform.path.leg_count();
+            platform.secs = 0.;
+            if platform.path.dwells_before(platform.leg) && platform.path.dwell > 0. {
+                platform.dwell = Some(GameTimer::once(platform.path.dwell));
+            }
+        }
+        platform.delta = next - position.0;
+        position.0 = next;
+    }
+}
+
+/// Moves whatever stood on a platform last tick along with it.
+fn carry_riders(
+    platforms: Query<&MovingPlatform>,
+    mut riders: Query<(&mut Position, &GroundContact), (With<Player>, Without<MovingPlatform>)>,
+) {
+    for (mut position, ground) in &mut riders {
+        if let Some(platform) = ground.0.and_then(|ground| platforms.get(ground).ok()) {
+            position.0 += platform.delta;
+        }
+    }
+}
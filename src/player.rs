@@ -0,0 +1,883 @@
+use bevy::math::bounding::{Aabb2d, BoundingVolume, IntersectsVolume};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::animation::{AnimationState, SpriteAnimation};
+use crate::camera::cursor_world_position;
+use crate::cannon::InCannon;
+use crate::combo::StompCombo;
+use crate::crates::Carrying;
+use crate::cutscene::cutscene_playing;
+use crate::damage::Damageable;
+use crate::events::Jumped;
+use crate::grapple::Grapple;
+use crate::ground_pound::GroundPound;
+use crate::input::{Action, Actions, InputConfig, ScriptedInput};
+use crate::key::Inventory;
+use crate::ladder::Climbing;
+use crate::level::{Downed, ResetLevel, SpawnSnapshot};
+use crate::loading::PLAYER_SHEET;
+use crate::movement::{ControlLock, Dash, Jumping, MovementConfig, MovementMode, MovementModifiers, StandingOn, StatId, StatModifier, SurfaceMaterial, WallContact, WallRun, WallRunner};
+use crate::physics::{gate_lets_through, project_transforms, stop_at_collisions, top_at, Collider, CollisionGrace, DynamicBody, Gate, Gravitated, GravityScale, GroundContact, LayerMask, OneWayPlatform, PhysicsSet, PhysicsWorld, Position, Rotation, Shape, Slope, TerminalVelocity, Up, Velocity, ZOrder};
+use crate::projectile::Weapon;
+use crate::spatial::SpatialGrid;
+use crate::timer::GameTimer;
+use crate::water::InWater;
+use crate::wind::WindDrift;
+use crate::world::{init_world, WorldData};
+use crate::{ease_factor, flerp, vlerp, GameState};
+
+/// Speed caps in pixels per second. Rising leaves room for the strongest spring and cannon
+/// launches but not for boosts stacked on top of them.
+const MAX_FALL_SPEED: f32 = 1728.;
+const MAX_RISE_SPEED: f32 = 2016.;
+/// How long after walking off a ledge a jump still works.
+const COYOTE_SECS: f32 = 0.1;
+/// How long a jump pressed in the air is held, to fire on landing.
+const JUMP_BUFFER_SECS: f32 = 0.15;
+/// A wall jump's upward speed relative to a normal jump; it also pushes away from the
+/// wall at full running speed.
+const WALL_JUMP_LIFT: f32 = 0.9;
+/// An air jump's upward speed relative to a normal jump.
+const AIR_JUMP_LIFT: f32 = 0.85;
+/// Jump strength multiplier for a swim stroke.
+const SWIM_STROKE_LIFT: f32 = 0.5;
+/// How much of the player has to be under water for a jump to be a swim stroke.
+const SWIM_DEPTH: f32 = 0.7;
+/// How long after a wall jump steering stays off, so holding toward the wall doesn't
+/// pull the player straight back onto it.
+const WALL_JUMP_LOCK_SECS: f32 = 0.2;
+/// How long dropping through a one-way platform ignores it. Long enough to fall clear of
+/// its top, after which it lets the player through from below anyway.
+const DROP_THROUGH_SECS: f32 = 0.15;
+/// How far below the feet `check_grounded` looks for something to stand on.
+pub const GROUND_PROBE_DEPTH: f32 = 2.;
+const PLAYER_HEALTH: i32 = 3;
+/// How long the player shrugs off further hits after taking one.
+const HURT_INVULNERABLE_SECS: f32 = 1.;
+/// Size of one frame of the player's sprite sheet. Frames are stretched to the player's
+/// `Shape` when drawn.
+const PLAYER_FRAME: UVec2 = UVec2::new(32, 48);
+/// Tints the second player's sprite so the two can be told apart.
+const PLAYER_TWO_TINT: Color = Color::srgb(0.6, 0.85, 1.);
+/// Where the second player stands relative to the first when they join or start a level.
+const SECOND_PLAYER_OFFSET: Vec2 = Vec2::new(80., 0.);
+
+/// What the player squashes to on touching down.
+const LANDING_SQUASH: Vec2 = Vec2::new(80., 80.);
+/// What the player stretches to for the length of a dash.
+const DASH_STRETCH: Vec2 = Vec2::new(95., 75.);
+const WALL_RUN_LEAN: f32 = 0.25;
+/// Reversing on the ground faster than this skids.
+const SKID_MIN_SPEED: f32 = 360.;
+const SKID_LEAN: f32 = 0.15;
+/// How quickly the sprite tips to lie along a slope underfoot, and back upright off it,
+/// as an exponential rate per second.
+const SLOPE_TILT_RATE: f32 = 12.;
+/// Height of the player's `Shape` while crouched.
+const CROUCH_HEIGHT: f32 = 55.;
+/// Crouching walks at half speed and turns a jump into a hop.
+const CROUCH_SLOWDOWN: [StatModifier; 2] = [
+    StatModifier { stat: StatId::MaxSpeed, multiplier: 0.5, duration: None },
+    StatModifier { stat: StatId::JumpStrength, multiplier: 0.7, duration: None },
+];
+
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        // Starting a level from the menu goes through a restart; the player is spawned
+        // ahead of its teardown so it's reset to the spawn like on any other restart.
+        app.add_systems(OnEnter(GameState::Restarting), spawn_player.run_if(not(any_with_component::<Player>)).after(init_world).before(ResetLevel))
+            .add_systems(FixedUpdate, (
+                crouch.run_if(not(cutscene_playing)).before(control_player),
+                control_player.run_if(not(cutscene_playing)).before(PhysicsSet::Integrate),
+                aim.after(control_player),
+                check_grounded.in_set(PhysicsSet::Resolve).after(stop_at_collisions),
+                player_effects,
+                ease_squash_stretch,
+            ))
+            .add_systems(Update, (fit_to_shape, draw_squash_stretch.after(project_transforms)).chain())
+            .add_systems(Update, sync_second_player.run_if(in_state(GameState::Playing)));
+    }
+}
+
+#[derive(Component)]
+pub struct VisShape(pub Vec2);
+
+/// Eases an entity's `VisShape` back to `target` (normally its `Shape`) and draws it by
+/// scaling the transform, so squash effects never touch the mesh.
+#[derive(Component)]
+pub struct SquashStretch {
+    pub target: Vec2,
+    pub snappiness: f32,
+}
+
+impl SquashStretch {
+    pub fn new(target: Vec2, snappiness: f32) -> Self {
+        Self {
+            target,
+            snappiness,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Player;
+
+/// Which player a `Player` is, for reading the right input. There's always a first
+/// player; the second only joins in two-player mode.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PlayerId(pub u8);
+
+impl PlayerId {
+    pub const ONE: Self = Self(0);
+    pub const TWO: Self = Self(1);
+}
+
+#[derive(Component)]
+pub struct Grounded(pub bool);
+
+/// The angle of the ground under a grounded body, in radians from flat: positive rising
+/// to the right, zero on the flat and in the air. Set by `check_grounded`.
+#[derive(Component, Default)]
+pub struct GroundAngle(pub f32);
+
+/// How far the player's sprite is tipped to lie along the slope underfoot, on top of its
+/// lean. Eased by `player_effects`.
+#[derive(Component, Default)]
+struct SlopeTilt(f32);
+
+/// Seconds left in which the player can still jump after leaving the ground. Topped up
+/// by `check_grounded` every tick the player is grounded.
+#[derive(Component, Default)]
+pub struct CoyoteTimer(pub f32);
+
+/// Seconds left on a jump press that couldn't fire yet. `control_player` jumps as soon as
+/// the player can while it's running.
+#[derive(Component, Default)]
+pub struct JumpBuffer(pub f32);
+
+/// Extra jumps the player can make without touching the ground. `check_grounded` refills
+/// `remaining` on landing; a power-up can raise `max` for more than a double jump.
+#[derive(Component)]
+pub struct AirJumps {
+    pub remaining: u8,
+    pub max: u8,
+}
+
+impl AirJumps {
+    pub fn new(max: u8) -> Self {
+        Self { remaining: max, max }
+    }
+}
+
+/// The player is crouched, with `Shape` cut down to `CROUCH_HEIGHT`. Respawns stand the
+/// player back up to `standing_height`.
+#[derive(Component)]
+pub struct Crouching {
+    pub standing_height: f32,
+}
+
+/// Set by `control_player` while the player is braking out of a run in the other
+/// direction on the ground, for the effects that go with it.
+#[derive(Component, Default, PartialEq)]
+pub struct Skidding(pub bool);
+
+/// Which way the player is facing: the way they last steered, rather than the way they're
+/// moving, so standing still or being pushed by wind or a conveyor never turns them round.
+/// Anything that needs a direction from the player reads it here.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Facing {
+    /// 1 facing right, -1 facing left.
+    pub x: f32,
+    /// Where aimed actions go, as a unit vector: straight ahead, or at the cursor for the
+    /// first player with `InputConfig::mouse_aim` on.
+    pub aim: Vec2,
+}
+
+impl Default for Facing {
+    fn default() -> Self {
+        Self { x: 1., aim: Vec2::X }
+    }
+}
+
+#[derive(Bundle)]
+pub struct PlayerBundle {
+    player: Player,
+    id: PlayerId,
+    position: Position,
+    shape: Shape,
+    vis_shape: VisShape,
+    squash_stretch: SquashStretch,
+    gravity_scale: GravityScale,
+    terminal_velocity: TerminalVelocity,
+    velocity: Velocity,
+    gravitated: Gravitated,
+    dynamic_body: DynamicBody,
+    grounded: Grounded,
+    ground_angle: GroundAngle,
+    slope_tilt: SlopeTilt,
+    ground_contact: GroundContact,
+    skidding: Skidding,
+    facing: Facing,
+    stomp_combo: StompCombo,
+    coyote: CoyoteTimer,
+    jump_buffer: JumpBuffer,
+    dash: Dash,
+    air_jumps: AirJumps,
+    rotation: Rotation,
+    z_order: ZOrder,
+    damageable: Damageable,
+    wall_runner: WallRunner,
+    wall_contact: WallContact,
+    standing_on: StandingOn,
+    mode: MovementMode,
+    modifiers: MovementModifiers,
+    up: Up,
+    wind_drift: WindDrift,
+    inventory: Inventory,
+}
+
+impl PlayerBundle {
+    pub fn new(id: PlayerId, position: Vec2, shape: Vec2, config: &MovementConfig) -> Self {
+        Self {
+            player: Player,
+            id,
+            gravitated: Gravitated,
+            dynamic_body: DynamicBody,
+            position: Position(position),
+            shape: Shape(shape),
+            vis_shape: VisShape(shape),
+            squash_stretch: SquashStretch::new(shape, config.squash_snappiness),
+            gravity_scale: GravityScale::default(),
+            terminal_velocity: TerminalVelocity { fall: MAX_FALL_SPEED, rise: MAX_RISE_SPEED },
+            velocity: Velocity(Vec2::new(0., 288.)),
+            grounded: Grounded(false),
+            ground_angle: GroundAngle::default(),
+            slope_tilt: SlopeTilt::default(),
+            ground_contact: GroundContact::default(),
+            skidding: Skidding::default(),
+            facing: Facing::default(),
+            stomp_combo: StompCombo::default(),
+            coyote: CoyoteTimer::default(),
+            jump_buffer: JumpBuffer::default(),
+            dash: Dash::default(),
+            air_jumps: AirJumps::new(1),
+            rotation: Rotation(0.),
+            z_order: ZOrder(0.1),
+            damageable: Damageable { invulnerable_secs: HURT_INVULNERABLE_SECS, ..Damageable::new(PLAYER_HEALTH) },
+            wall_runner: WallRunner::default(),
+            wall_contact: WallContact::default(),
+            standing_on: StandingOn::default(),
+            mode: MovementMode::default(),
+            modifiers: MovementModifiers::default(),
+            up: Up::default(),
+            wind_drift: WindDrift::default(),
+            inventory: Inventory::default(),
+        }
+    }
+}
+
+/// Where the player starts the level, stored next to the `WorldData` it belongs to.
+/// Levels that don't say start at the origin.
+#[derive(Component, Clone, Copy, Default)]
+pub struct PlayerSpawn(pub Vec2);
+
+fn spawn_player(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    config: Res<MovementConfig>,
+    spawn: Query<&PlayerSpawn, With<WorldData>>,
+) {
+    let spawn = spawn.get_single().map_or(Vec2::ZERO, |spawn| spawn.0);
+    spawn_player_body(&mut commands, PlayerId::ONE, spawn, spawn, &asset_server, &mut layouts, &config);
+}
+
+/// A player at `position`, who respawns at `spawn` until a checkpoint says otherwise.
+fn spawn_player_body(
+    commands: &mut Commands,
+    id: PlayerId,
+    position: Vec2,
+    spawn: Vec2,
+    asset_server: &AssetServer,
+    layouts: &mut Assets<TextureAtlasLayout>,
+    config: &MovementConfig,
+) {
+    let shape = Vec2::new(60., 100.);
+    let player = PlayerBundle::new(id, position, shape, config);
+    let snapshot = SpawnSnapshot::new(spawn, player.velocity.0)
+        .with_health(player.damageable.health);
+    commands.spawn((player,
+                    snapshot,
+                    Weapon::default(),
+                    AnimationState::default(),
+                    SpriteAnimation::default(),
+                    SpriteBundle {
+                        texture: asset_server.load(PLAYER_SHEET),
+                        sprite: Sprite {
+                            color: if id == PlayerId::ONE { Color::WHITE } else { PLAYER_TWO_TINT },
+                            custom_size: Some(shape),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    TextureAtlas {
+                        layout: layouts.add(TextureAtlasLayout::from_grid(PLAYER_FRAME, 4, 1, None, None)),
+                        index: 0,
+                    }));
+}
+
+/// Brings the second player in beside the first when two-player mode is turned on, and
+/// takes them out again when it's turned off.
+fn sync_second_player(
+    mut commands: Commands,
+    input_config: Res<InputConfig>,
+    players: Query<(Entity, &PlayerId, &Position)>,
+    spawn: Query<&PlayerSpawn, With<WorldData>>,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    config: Res<MovementConfig>,
+) {
+    let second = players.iter().find(|(_, id, _)| **id == PlayerId::TWO);
+    match second {
+        Some((entity, ..)) if !input_config.two_players => commands.entity(entity).despawn_recursive(),
+        None if input_config.two_players => {
+            let Some((_, _, first)) = players.iter().find(|(_, id, _)| **id == PlayerId::ONE) else {
+                return;
+            };
+            let spawn = spawn.get_single().map_or(Vec2::ZERO, |spawn| spawn.0);
+            spawn_player_body(&mut commands, PlayerId::TWO, first.0 + SECOND_PLAYER_OFFSET, spawn + SECOND_PLAYER_OFFSET, &asset_server, &mut layouts, &config);
+        }
+        _ => {}
+    }
+}
+
+/// Holding down on the ground crouches, and letting go stands back up once the full height
+/// fits overhead. Only the ground starts a crouch, so pressing down in the air leaves the
+/// collider alone. The shape grows and shrinks from the feet, so they stay planted, on the
+/// ceiling too when the player is upside down.
+fn crouch(
+    mut commands: Commands,
+    mut player: Query<(Entity, &PlayerId, &mut Position, &mut Shape, &Grounded, &mut MovementModifiers, &Up, Option<&Crouching>, Option<&Carrying>), (With<Player>, Without<Collider>, Without<InCannon>, Without<Climbing>, Without<Downed>)>,
+    one_way: Query<(), With<OneWayPlatform>>,
+    physics: PhysicsWorld,
+    input: Actions,
+    config: Res<MovementConfig>,
+) {
+    for (entity, id, mut position, mut shape, grounded, mut modifiers, up, crouching, carrying) in &mut player {
+        let holding_down = input.player(*id).pressed(Action::MoveDown);
+        match crouching {
+            // A crate on the head has nowhere to go, so there's no crouching under one.
+            None if holding_down && grounded.0 && carrying.is_none() => {
+                let standing_height = shape.0.y;
+                position.0.y -= (standing_height - CROUCH_HEIGHT) / 2. * up.0;
+                shape.0.y = CROUCH_HEIGHT;
+                commands.entity(entity).insert(Crouching { standing_height });
+                for modifier in CROUCH_SLOWDOWN {
+                    modifiers.push(modifier);
+                }
+            }
+            Some(crouching) if !holding_down => {
+                let feet = position.0.y - shape.0.y / 2. * up.0;
+                let standing = Aabb2d::new(
+                    Vec2::new(position.0.x, feet + crouching.standing_height / 2. * up.0),
+                    Vec2::new((shape.0.x / 2. - config.hitbox_inset).max(1.), crouching.standing_height / 2.),
+                );
+                // One-way platforms let the head through, and a crate picked up while
+                // crouched rides up with it.
+                let blocked = physics.overlap_aabb(standing, LayerMask::ALL).iter()
+                    .any(|other| !one_way.contains(*other) && carrying.is_none_or(|carrying| carrying.entity != *other));
+                if blocked {
+                    continue;
+                }
+                position.0.y = standing.center().y;
+                shape.0.y = crouching.standing_height;
+                commands.entity(entity).remove::<Crouching>();
+                for modifier in CROUCH_SLOWDOWN {
+                    modifiers.remove(modifier);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn control_player(
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut Velocity, &mut VisShape, &MovementModifiers, (&Grounded, &GroundAngle), &GroundContact, &mut CoyoteTimer, &mut JumpBuffer, &WallContact, &mut Skidding, &mut Dash, &mut AirJumps, (&StandingOn, &mut MovementMode, &Up, &WindDrift, &PlayerId, &mut Facing), Has<ControlLock>, Option<&InWater>), (With<Player>, Without<InCannon>, Without<Climbing>, Without<Grapple>, Without<GroundPound>, Without<Downed>)>,
+    one_way: Query<(), With<OneWayPlatform>>,
+    input: Actions,
+    config: Res<MovementConfig>,
+    mut jumped: EventWriter<Jumped>,
+    time: Res<Time>,
+) {
+    for (entity, mut velocity, mut vis_shape, modifiers, (grounded, ground_angle), ground, mut coyote, mut jump_buffer, wall, mut skidding, mut dash, mut air_jumps, (standing_on, mut mode, up, wind, id, mut facing), mut locked, in_water) in &mut player {
+        let actions = input.player(*id);
+        if actions.move_x() != 0. && !locked && !dash.is_dashing() {
+            facing.x = actions.move_x().signum();
+        }
+        if grounded.0 {
+            let next = if actions.pressed(Action::Run) && actions.move_x() != 0. { MovementMode::Run } else { MovementMode::Walk };
+            mode.set_if_neq(next);
+        }
+        let running = *mode == MovementMode::Run;
+        let speed = if running { config.run_speed } else { config.max_speed } * modifiers.get(StatId::MaxSpeed);
+        // A stick tilted partway walks at partway speed.
+        let target_x_speed = actions.move_x() * speed;
+        let dt = time.delta_seconds();
+        coyote.0 = (coyote.0 - dt).max(0.);
+        jump_buffer.0 = (jump_buffer.0 - dt).max(0.);
+        if actions.just_pressed(Action::Jump) {
+            jump_buffer.0 = JUMP_BUFFER_SECS;
+        }
+        // A dash ignores input until it's over; a jump pressed near its end stays
+        // buffered for afterwards.
+        if dash.is_dashing() {
+            skidding.set_if_neq(Skidding(false));
+            vis_shape.0 = DASH_STRETCH;
+            continue;
+        }
+        // Swimming is jumping, so water never needs ground underfoot. Down in it a jump is
+        // a weaker stroke, repeatable; once the head is out it's a full jump, to climb out.
+        let can_jump = grounded.0 || coyote.0 > 0. || in_water.is_some();
+        let stroke = !grounded.0 && in_water.is_some_and(|water| water.submerged >= SWIM_DEPTH);
+        // Set by a jump, which leaves a slope's surface behind on this tick already.
+        let mut took_off = false;
+        let drop_through = ground.0.filter(|platform| one_way.contains(*platform) && actions.pressed(Action::MoveDown));
+        if let Some(platform) = drop_through.filter(|_| jump_buffer.0 > 0.) {
+            jump_buffer.0 = 0.;
+            coyote.0 = 0.;
+            commands.entity(entity).insert(CollisionGrace {
+                entity: platform,
+                timer: GameTimer::once(DROP_THROUGH_SECS),
+            });
+        } else if jump_buffer.0 > 0. && can_jump {
+            jump_buffer.0 = 0.;
+            coyote.0 = 0.;
+            took_off = true;
+            let lift = if stroke { SWIM_STROKE_LIFT } else { 1. };
+            let speed = config.jump_strength * lift * modifiers.get(StatId::JumpStrength);
+            // Jumps push away from gravity, which is down the screen upside down.
+            velocity.0.y = speed * up.0;
+            vis_shape.0 = Vec2::new(80., 70.);
+            // A stroke is over as soon as it's made, so there's nothing to cut short.
+            if !stroke {
+                commands.entity(entity).insert(Jumping { time_held: 0., speed });
+            }
+            jumped.send(Jumped { entity });
+        } else if let Some(side) = wall.0.filter(|_| jump_buffer.0 > 0.) {
+            jump_buffer.0 = 0.;
+            let speed = config.jump_strength * WALL_JUMP_LIFT * modifiers.get(StatId::JumpStrength);
+            velocity.0 = Vec2::new(side.normal().x * config.max_speed, speed * up.0);
+            facing.x = side.normal().x;
+            vis_shape.0 = Vec2::new(70., 80.);
+            commands.entity(entity).insert((Jumping { time_held: 0., speed }, ControlLock::new(WALL_JUMP_LOCK_SECS)));
+            locked = true;
+            jumped.send(Jumped { entity });
+        } else if jump_buffer.0 > 0. && air_jumps.remaining > 0 {
+            jump_buffer.0 = 0.;
+            air_jumps.remaining -= 1;
+            let speed = config.jump_strength * AIR_JUMP_LIFT * modifiers.get(StatId::JumpStrength);
+            velocity.0.y = speed * up.0;
+            vis_shape.0 = Vec2::new(80., 70.);
+            commands.entity(entity).insert(Jumping { time_held: 0., speed });
+            jumped.send(Jumped { entity });
+        }
+
+        if locked {
+            skidding.set_if_neq(Skidding(false));
+            continue;
+        }
+
+        if actions.just_pressed(Action::Dash) && dash.can_dash(grounded.0) {
+            dash.start(grounded.0, &config);
+            velocity.0 = Vec2::new(facing.x * config.dash_speed, 0.);
+            vis_shape.0 = DASH_STRETCH;
+            skidding.set_if_neq(Skidding(false));
+            continue;
+        }
+
+        let reversing = target_x_speed != 0. && target_x_speed.signum() != velocity.0.x.signum();
+        skidding.set_if_neq(Skidding(grounded.0 && reversing && velocity.0.x.abs() > SKID_MIN_SPEED));
+
+        // The ground underfoot changes how the player handles. In the air it's always the
+        // weaker air control, and both ease, so taking off and landing never snap the speed.
+        let accel = if running { config.run_accel } else { config.accel };
+        let (accel, decel, target_x_speed) = match standing_on.0.filter(|_| grounded.0) {
+            _ if !grounded.0 => (config.air_accel, config.air_decel, target_x_speed),
+            Some(SurfaceMaterial::Ice) => (config.ice_accel, config.ice_decel, target_x_speed),
+            Some(SurfaceMaterial::Conveyor { speed }) => (accel, config.decel, target_x_speed + speed),
+            Some(SurfaceMaterial::Normal) | None => (accel, config.decel, target_x_speed),
+        };
+        // Steering only eases the player's own speed, with the wind's share added back.
+        // On a slope it's the speed along the surface, so walking up one or down it goes
+        // as quickly as on the flat. It's read back from the horizontal part, which
+        // gravity doesn't pull on, so an uphill walk doesn't fight it.
+        let on_slope = grounded.0 && ground_angle.0 != 0. && !took_off;
+        let tangent = Vec2::from_angle(if on_slope { ground_angle.0 } else { 0. });
+        let own_speed = (velocity.0.x - wind.0) / tangent.x;
+        let rate = if target_x_speed.abs() < own_speed.abs() { decel } else { accel * modifiers.get(StatId::Accel) };
+        let speed = flerp(own_speed, target_x_speed, ease_factor(rate, dt));
+        if on_slope {
+            velocity.0 = tangent * speed + Vec2::new(wind.0, 0.);
+        } else {
+            velocity.0.x = speed + wind.0;
+        }
+    }
+}
+
+/// Points `Facing::aim`. With mouse aim on, the first player aims at the cursor while it's
+/// in the window and anywhere but right on top of them. Scripted input brings its own
+/// cursor.
+fn aim(
+    mut players: Query<(&PlayerId, &Position, &mut Facing), With<Player>>,
+    input_config: Res<InputConfig>,
+    scripted: Option<Res<ScriptedInput>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<crate::camera::Camera>>,
+) {
+    let cursor = match scripted {
+        Some(scripted) => scripted.cursor,
+        None => cursor_world_position(&window, &camera),
+    };
+    let cursor = cursor.filter(|_| input_config.mouse_aim);
+    for (id, position, mut facing) in &mut players {
+        let at_cursor = cursor.filter(|_| *id == PlayerId::ONE).and_then(|cursor| (cursor - position.0).try_normalize());
+        facing.aim = at_cursor.unwrap_or(Vec2::new(facing.x, 0.));
+    }
+}
+
+fn player_effects(
+    mut player: Query<(&mut Rotation, &mut SlopeTilt, &Velocity, &Skidding, Option<&WallRun>, &mut SquashStretch, &MovementModifiers, &Up, (&Grounded, &GroundAngle), &StandingOn, &WindDrift), With<Player>>,
+    config: Res<MovementConfig>,
+    time: Res<Time>,
+) {
+    for (mut rotation, mut tilt, velocity, skidding, wall_run, mut squash_stretch, modifiers, up, (grounded, ground_angle), standing_on, wind) in &mut player {
+        squash_stretch.snappiness = config.squash_snappiness;
+        //Rotation
+        // Full walking speed leans the usual amount and a sprint leans further, up to
+        // the fastest the player can run; launches and boosts past that don't tip it over.
+        // Only the speed the player is steering counts, so wind and conveyors carry an
+        // idle player along standing upright.
+        let conveyor = match standing_on.0.filter(|_| grounded.0) {
+            Some(SurfaceMaterial::Conveyor { speed }) => speed,
+            _ => 0.,
+        };
+        let own_speed = velocity.0.x - wind.0 - conveyor;
+        let top_speed = config.run_speed * modifiers.get(StatId::MaxSpeed);
+        let lean = own_speed.clamp(-top_speed, top_speed) / config.max_speed;
+        let angle = match wall_run {
+            // Lean into the wall being run along.
+            Some(wall_run) => WALL_RUN_LEAN * wall_run.side.normal().x,
+            // Pitch further forward while braking, as if the feet stopped first.
+            None if skidding.0 => flerp(0., -0.3, lean) - SKID_LEAN * own_speed.signum(),
+            None => flerp(0., -0.3, lean),
+        };
+        // The sprite settles onto a slope underfoot and rights itself off it, leaning
+        // all the while. Upside down the sprite is drawn mirrored top to bottom, which
+        // mirrors the lean too, so turning the other way still tips it forward.
+        let max_slope = config.max_slope();
+        let surface = if grounded.0 { ground_angle.0.clamp(-max_slope, max_slope) } else { 0. };
+        tilt.0 = flerp(tilt.0, surface, ease_factor(SLOPE_TILT_RATE, time.delta_seconds()));
+        rotation.0 = angle * up.0 + tilt.0
+    }
+}
+
+/// Grounds any body with a collider right under its feet and restarts its coyote time and
+/// air jumps. Looking for the ground, rather than going by which side an overlap was
+/// pushed out of, still counts a body resting exactly on a block with nothing to resolve,
+/// and lets go on the tick it walks off an edge. It skips what `handle_collisions` would:
+/// a grace entity or an open gate. Only the colliders the `SpatialGrid` has around the
+/// feet are looked at.
+/// A `VisShape` squashes on the tick the body goes from airborne to standing. A slime
+/// bounce already heading back up refills coyote time but doesn't count as standing, and
+/// a body on a ladder is never grounded. Upside down, "under its feet" is above it.
+pub fn check_grounded(
+    colliders: Query<(Entity, &Position, &Shape, Option<&Gate>, Option<&Slope>), With<Collider>>,
+    grid: Res<SpatialGrid>,
+    mut bodies: Query<(Entity, &Position, &mut Velocity, &Shape, (&mut Grounded, Option<&mut GroundAngle>), Option<&CollisionGrace>, Option<&mut CoyoteTimer>, Option<&mut AirJumps>, Option<&mut VisShape>, Option<&Up>, Has<Player>, Has<Climbing>, Has<Grapple>), (With<DynamicBody>, Without<InCannon>)>,
+    config: Res<MovementConfig>,
+) {
+    for (entity, position, mut velocity, shape, (mut grounded, ground_angle), grace, coyote, air_jumps, vis_shape, up, is_player, climbing, grappling) in &mut bodies {
+        if climbing {
+            grounded.0 = false;
+            if let Some(mut ground_angle) = ground_angle {
+                ground_angle.0 = 0.;
+            }
+            continue;
+        }
+        // The same hitbox `handle_collisions` uses, so the inset edges can't stand on air.
+        let inset = if is_player { config.hitbox_inset } else { 0. };
+        let half_size = (shape.0 / 2. - Vec2::new(inset, 0.)).max(Vec2::ONE);
+        let up = up.copied().unwrap_or_default();
+        let body_aabb = Aabb2d::new(position.0, half_size);
+        let feet = position.0.y - half_size.y * up.0;
+        let probe = Aabb2d::new(
+            Vec2::new(position.0.x, feet - GROUND_PROBE_DEPTH / 2. * up.0),
+            Vec2::new(half_size.x, GROUND_PROBE_DEPTH / 2.),
+        );
+        // What's underfoot, and the angle of it, out of the colliders the grid has under the
+        // probe. A slope only has one with the body's center over the ramp, and one too
+        // steep to stand on there is a wall.
+        let ground = grid.query(probe).into_iter().filter_map(|other| colliders.get(other).ok()).find_map(|(other, other_pos, other_shape, gate, slope)| {
+            let aabb = Aabb2d::new(other_pos.0, other_shape.0 / 2.);
+            let surface = if up.is_flipped() { aabb.min.y } else { top_at(aabb, slope.copied(), position.0.x) };
+            let drop = (feet - surface) * up.0;
+            let ramp = slope.copied().filter(|_| !up.is_flipped() && (aabb.min.x..=aabb.max.x).contains(&position.0.x));
+            let stands = other != entity
+                && grace.is_none_or(|grace| grace.entity != other)
+                && probe.intersects(&aabb)
+                && (-0.01..=GROUND_PROBE_DEPTH).contains(&drop)
+                && !gate.is_some_and(|gate| gate_lets_through(gate, body_aabb, aabb))
+                && !ramp.is_some_and(|ramp| ramp.too_steep(aabb, config.max_slope()));
+            stands.then(|| ramp.map_or(0., |ramp| ramp.angle(aabb)))
+        });
+        let on_ground = ground.is_some();
+        if let Some(mut coyote) = coyote.filter(|_| on_ground) {
+            coyote.0 = COYOTE_SECS;
+        }
+        // Not heading away from the ground, which on a slope is walking up it. Walking off
+        // the top of a ramp onto flatter ground heads away from the new ground but not the
+        // ramp, and the part of the climb that would lift it off the new ground goes.
+        let normal = Vec2::from_angle(ground.unwrap_or(0.)).perp() * up.0;
+        let was_on = grounded.0.then(|| ground_angle.as_ref().map_or(0., |angle| angle.0));
+        let off_new = velocity.0.dot(normal);
+        let off_old = was_on.map(|angle| velocity.0.dot(Vec2::from_angle(angle).perp() * up.0));
+        let standing = on_ground && (off_new <= 0. || off_old.is_some_and(|off| off <= 0.));
+        if standing && off_new > 0. {
+            velocity.0 -= normal * off_new;
+        }
+        if let Some(mut air_jumps) = air_jumps.filter(|_| standing) {
+            air_jumps.remaining = air_jumps.max;
+        }
+        if let Some(mut vis_shape) = vis_shape.filter(|_| standing && !grounded.0 && !grappling) {
+            vis_shape.0 = LANDING_SQUASH;
+        }
+        grounded.0 = standing;
+        if let Some(mut ground_angle) = ground_angle {
+            ground_angle.0 = ground.filter(|_| standing).unwrap_or(0.);
+        }
+    }
+}
+
+fn ease_squash_stretch(
+    mut bodies: Query<(&mut VisShape, &SquashStretch)>,
+    time: Res<Time>,
+) {
+    for (mut vis_shape, squash) in &mut bodies {
+        vis_shape.0 = vlerp(vis_shape.0, squash.target, ease_factor(squash.snappiness, time.delta_seconds()));
+    }
+}
+
+/// Follows a change to the player's `Shape`, such as a crouch, with the sprite and the size
+/// the squash and stretch eases back to. The `VisShape` is left at the old size, so the
+/// change plays as a squash.
+fn fit_to_shape(mut player: Query<(&Shape, &mut SquashStretch, &mut Sprite), (With<Player>, Changed<Shape>)>) {
+    for (shape, mut squash, mut sprite) in &mut player {
+        squash.target = shape.0;
+        sprite.custom_size = Some(shape.0);
+    }
+}
+
+/// Runs after `project_transforms` and keeps the bottom edge where `Shape` has it (the top
+/// edge upside down), so a squash reads as landing on the ground rather than shrinking in
+/// mid-air.
+fn draw_squash_stretch(
+    mut bodies: Query<(&VisShape, &Shape, &mut Transform, Option<&Up>)>,
+) {
+    for (vis_shape, shape, mut transform, up) in &mut bodies {
+        let up = up.map_or(1., |up| up.0);
+        transform.scale = (vis_shape.0 / shape.0).extend(1.);
+        let feet = transform.rotation * Vec3::new(0., (vis_shape.0.y - shape.0.y) / 2. * up, 0.);
+        transform.translation += feet;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+    use crate::headless::build_headless_app;
+    use crate::world::{BlockData, BlockKind, WorldData};
+
+    const FLOOR_TOP: f32 = -175.;
+    /// Where the run being timed starts, well after the player is up to speed.
+    const RUN_START: f32 = 800.;
+    const RUN_LENGTH: f32 = 400.;
+
+    fn floor() -> BlockData {
+        BlockData::new(Vec2::new(0., FLOOR_TOP - 25.), Vec2::new(6000., 50.))
+    }
+
+    fn slope(min: Vec2, size: Vec2) -> BlockData {
+        BlockData { kind: BlockKind::Slope { rises_right: true }, ..BlockData::new(min + size / 2., size) }
+    }
+
+    fn player(app: &mut App) -> (Vec2, bool) {
+        let mut players = app.world_mut().query_filtered::<(&Position, &Grounded), With<Player>>();
+        let (position, grounded) = players.single(app.world());
+        (position.0, grounded.0)
+    }
+
+    /// Ticks spent walking right from `RUN_START` until the player's center gets to `end`.
+    fn run_ticks(level: Vec<BlockData>, end: f32) -> u32 {
+        let mut app = build_headless_app(WorldData(level));
+        for _ in 0..144 {
+            app.update();
+        }
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].press(Action::MoveRight);
+        let mut ticks = 0;
+        for _ in 0..1440 {
+            app.update();
+            let (position, _) = player(&mut app);
+            if position.x >= end {
+                return ticks;
+            }
+            if position.x >= RUN_START {
+                ticks += 1;
+            }
+        }
+        panic!("never got to {end}");
+    }
+
+    #[test]
+    fn walking_up_a_slope_is_as_quick_as_the_flat() {
+        let flat = run_ticks(vec![floor()], RUN_START + RUN_LENGTH);
+        // A 30° ramp as long as the flat run, starting where it does, and a ledge at the top.
+        let size = Vec2::from_angle(30f32.to_radians()) * RUN_LENGTH;
+        let top = Vec2::new(RUN_START + size.x, FLOOR_TOP + size.y);
+        let uphill = run_ticks(vec![
+            floor(),
+            slope(Vec2::new(RUN_START, FLOOR_TOP), size),
+            BlockData::new(Vec2::new(top.x + 500., (FLOOR_TOP + top.y) / 2.), Vec2::new(1000., size.y)),
+        ], top.x);
+        let ratio = uphill as f32 / flat as f32;
+        assert!((0.95..=1.05).contains(&ratio), "{uphill} ticks up the slope against {flat} on the flat");
+    }
+
+    #[test]
+    fn a_slope_too_steep_to_stand_on_is_slid_down() {
+        // 60°, over the 45° limit; dropped onto its middle.
+        let size = Vec2::new(100., 173.);
+        let mut app = build_headless_app(WorldData(vec![floor(), slope(Vec2::new(-50., FLOOR_TOP), size)]));
+        for _ in 0..288 {
+            app.update();
+            let (position, grounded) = player(&mut app);
+            assert!(!grounded || position.x < -50., "stood on the slope at {position}");
+        }
+        let (position, grounded) = player(&mut app);
+        assert!(grounded && position.x < -50., "didn't slide off the low end: {position}");
+    }
+
+    /// Most the player's height can change in a tick going `cap` pixels a second, with a
+    /// hair over for rounding.
+    fn step_limit(cap: f32) -> f32 {
+        cap / 144. + 0.01
+    }
+
+    #[test]
+    fn a_long_fall_never_outruns_the_cap() {
+        // Nothing to land on: the player falls from the origin towards the kill plane.
+        let mut app = build_headless_app(WorldData(vec![]));
+        app.update();
+        let (mut last, _) = player(&mut app);
+        for _ in 0..180 {
+            app.update();
+            let (position, _) = player(&mut app);
+            assert!(last.y - position.y <= step_limit(MAX_FALL_SPEED), "fell {} in a tick", last.y - position.y);
+            last = position;
+        }
+        // Long enough to have got up to the cap.
+        assert!(last.y < -1000., "only fell to {last}");
+    }
+
+    fn launch(mut players: Query<&mut Velocity, With<Player>>, mut launched: Local<bool>) {
+        if !std::mem::replace(&mut *launched, true) {
+            // A spring and a boost pad on top of each other, and then some.
+            players.single_mut().0.y += 10_000.;
+        }
+    }
+
+    #[test]
+    fn a_stacked_launch_never_outruns_the_cap() {
+        let mut app = build_headless_app(WorldData(vec![floor()]));
+        for _ in 0..144 {
+            app.update();
+        }
+        app.add_systems(FixedUpdate, launch.before(PhysicsSet::Integrate));
+        let (mut last, _) = player(&mut app);
+        for _ in 0..144 {
+            app.update();
+            let (position, _) = player(&mut app);
+            assert!(position.y - last.y <= step_limit(MAX_RISE_SPEED), "rose {} in a tick", position.y - last.y);
+            last = position;
+        }
+    }
+
+    /// How high a held jump off the floor peaks, with the game ticking at `hz`.
+    fn jump_height(hz: f64) -> f32 {
+        let mut app = build_headless_app(WorldData(vec![floor()]));
+        app.insert_resource(Time::<Fixed>::from_hz(hz))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1. / hz)));
+        for _ in 0..hz as usize {
+            app.update();
+        }
+        let (start, grounded) = player(&mut app);
+        assert!(grounded);
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].press(Action::Jump);
+        let mut peak = start.y;
+        for _ in 0..hz as usize {
+            app.update();
+            peak = peak.max(player(&mut app).0.y);
+        }
+        peak - start.y
+    }
+
+    #[test]
+    fn a_jump_peaks_as_high_at_any_tick_rate() {
+        let (slow, fast) = (jump_height(60.), jump_height(240.));
+        assert!((slow - fast).abs() <= fast * 0.01, "{slow} high at 60Hz, {fast} at 240Hz");
+    }
+
+    fn actions(app: &mut App) -> &mut ButtonInput<Action> {
+        &mut app.world_mut().resource_mut::<ScriptedInput>().into_inner().actions[0]
+    }
+
+    /// The player's x speed after running right at full speed, then holding left for a
+    /// tenth of a second, jumping as they turn if `airborne`.
+    fn turnaround(airborne: bool) -> f32 {
+        let mut app = build_headless_app(WorldData(vec![floor()]));
+        for _ in 0..144 {
+            app.update();
+        }
+        actions(&mut app).press(Action::MoveRight);
+        for _ in 0..144 {
+            app.update();
+        }
+        actions(&mut app).release(Action::MoveRight);
+        actions(&mut app).press(Action::MoveLeft);
+        if airborne {
+            actions(&mut app).press(Action::Jump);
+        }
+        for _ in 0..14 {
+            app.update();
+        }
+        assert_eq!(player(&mut app).1, !airborne);
+        let mut players = app.world_mut().query_filtered::<&Velocity, With<Player>>();
+        players.single(app.world()).0.x
+    }
+
+    #[test]
+    fn turning_around_is_slower_in_the_air() {
+        let top_speed = MovementConfig::default().max_speed;
+        let (ground, air) = (turnaround(false), turnaround(true));
+        // Most of the way round on the ground, still going right in the air.
+        assert!(ground < 0., "still {ground} on the ground");
+        assert!(air > top_speed / 4., "down to {air} in the air");
+    }
+}
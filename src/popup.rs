@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+
+use crate::cleanup::{despawn_expired, fade_away, FadeStyle, Lifetime};
+use crate::coin::{collect_coins, COIN_COLOR};
+use crate::damage::{apply_damage, Damageable};
+use crate::events::{CoinCollected, Damaged, NewBestTime};
+use crate::level::LevelEntity;
+use crate::physics::{Position, Rotation, Shape, Velocity, ZOrder};
+use crate::player::Player;
+
+const DAMAGE_COLOR: Color = Color::srgb(1., 0.35, 0.3);
+const RECORD_COLOR: Color = Color::srgb(0.5, 1., 0.6);
+/// How far over the middle of a player "New Record!" shows up.
+const RECORD_HEIGHT: f32 = 60.;
+const POPUP_FONT_SIZE: f32 = 20.;
+
+pub struct PopupPlugin;
+
+impl Plugin for PopupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PopupConfig>()
+            .add_systems(FixedUpdate, (
+                coin_popups.after(collect_coins),
+                damage_popups.after(apply_damage),
+                record_popups,
+                cap_popups.after(despawn_expired),
+            ));
+    }
+}
+
+/// How every floating text popup behaves.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PopupConfig {
+    /// Pixels per second a popup drifts up.
+    pub rise_speed: f32,
+    /// Seconds a popup stays up, fading the whole time.
+    pub lifetime: f32,
+    /// The most popups up at once; past it the oldest go first.
+    pub max_popups: usize,
+}
+
+impl Default for PopupConfig {
+    fn default() -> Self {
+        Self { rise_speed: 60., lifetime: 0.8, max_popups: 16 }
+    }
+}
+
+/// A bit of floating text from `popup_text`.
+#[derive(Component)]
+pub struct Popup;
+
+/// Floats `text` up from `position`, in world space over blocks and players, fading as it
+/// goes. Any system can put up its own feedback through this.
+pub fn popup_text(commands: &mut Commands, config: &PopupConfig, position: Vec2, text: impl Into<String>, color: Color) {
+    commands.spawn((
+        Popup,
+        fade_away(config.lifetime, FadeStyle::Alpha),
+        Position(position),
+        Velocity(Vec2::new(0., config.rise_speed)),
+        Rotation(0.),
+        ZOrder(5.),
+        Text2dBundle {
+            text: Text::from_section(text, TextStyle {
+                font_size: POPUP_FONT_SIZE,
+                color,
+                ..default()
+            }),
+            ..default()
+        },
+        LevelEntity,
+    ));
+}
+
+fn coin_popups(mut commands: Commands, mut collected: EventReader<CoinCollected>, config: Res<PopupConfig>) {
+    for event in collected.read() {
+        popup_text(&mut commands, &config, event.position, "+1", COIN_COLOR);
+    }
+}
+
+/// Only hits something lives through get a number; a kill has its own squash, and a
+/// stomp its points.
+fn damage_popups(
+    mut commands: Commands,
+    mut damaged: EventReader<Damaged>,
+    targets: Query<(&Damageable, &Position, &Shape)>,
+    config: Res<PopupConfig>,
+) {
+    for event in damaged.read() {
+        let Ok((damageable, position, shape)) = targets.get(event.entity) else {
+            continue;
+        };
+        if damageable.health > 0 {
+            let top = position.0 + Vec2::new(0., shape.0.y / 2.);
+            popup_text(&mut commands, &config, top, format!("-{}", event.amount), DAMAGE_COLOR);
+        }
+    }
+}
+
+fn record_popups(
+    mut commands: Commands,
+    mut new_best: EventReader<NewBestTime>,
+    players: Query<&Position, With<Player>>,
+    config: Res<PopupConfig>,
+) {
+    if new_best.read().last().is_none() {
+        return;
+    }
+    if let Some(position) = players.iter().next() {
+        popup_text(&mut commands, &config, position.0 + Vec2::new(0., RECORD_HEIGHT), "New Record!", RECORD_COLOR);
+    }
+}
+
+/// Runs after `despawn_expired`, so the ones it just despawned aren't counted.
+fn cap_popups(mut commands: Commands, popups: Query<(Entity, &Lifetime), With<Popup>>, config: Res<PopupConfig>) {
+    let mut live: Vec<_> = popups.iter()
+        .filter(|(_, lifetime)| !lifetime.0.finished())
+        .map(|(entity, lifetime)| (lifetime.0.fraction(), entity))
+        .collect();
+    let excess = live.len().saturating_sub(config.max_popups);
+    if excess == 0 {
+        return;
+    }
+    // The furthest through their lifetime first.
+    live.select_nth_unstable_by(excess - 1, |a, b| b.0.total_cmp(&a.0));
+    for (_, entity) in &live[..excess] {
+        commands.entity(*entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::events::{PlayerTeleported, TriggerEnter, TriggerExit};
+use crate::grapple::Grapple;
+use crate::level::{Downed, LevelEntity, ResetLevel};
+use crate::particles::spawn_ring;
+use crate::physics::{Position, Rotation, Shape, Velocity, ZOrder};
+use crate::player::{Player, VisShape};
+use crate::trigger::{detect_triggers, TriggerZone};
+use crate::world::WorldData;
+use crate::GameState;
+
+const PORTAL_COLOR: Color = Color::srgba(0.6, 0.3, 0.95, 0.45);
+/// The player comes out of a portal squeezed thin, and springs back to shape.
+const TELEPORT_POP: Vec2 = Vec2::new(0.5, 1.3);
+const RING_RADIUS: f32 = 50.;
+const RING_DOTS: usize = 12;
+const RING_SECS: f32 = 0.3;
+
+pub struct PortalPlugin;
+
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Restarting), spawn_portals.after(ResetLevel))
+            .add_systems(FixedUpdate, use_portals.after(detect_triggers));
+    }
+}
+
+/// One end of a portal from the level file. The two portals sharing an `id` lead to each
+/// other.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct PortalData {
+    pub position: Vec2,
+    pub shape: Vec2,
+    pub id: String,
+    /// The way bodies come out of this end. When both ends have one, velocity is turned
+    /// from going into the first to coming out of the second; otherwise it's kept as is.
+    #[serde(default)]
+    pub facing: Option<Vec2>,
+}
+
+/// Portals placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct PortalSpawns(pub Vec<PortalData>);
+
+impl PortalSpawns {
+    /// Logs every id that doesn't have exactly two ends. A lone portal does nothing, and
+    /// past the first two the rest are ignored.
+    pub fn check_pairs(&self, path: &str) {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for portal in &self.0 {
+            *counts.entry(&portal.id).or_default() += 1;
+        }
+        let mut ids: Vec<_> = counts.into_iter().filter(|(_, count)| *count != 2).collect();
+        ids.sort();
+        for (id, count) in ids {
+            if count == 1 {
+                warn!("{path}: portal {id:?} has no partner, it won't go anywhere");
+            } else {
+                warn!("{path}: {count} portals share the id {id:?}, only the first two are linked");
+            }
+        }
+    }
+}
+
+/// A `TriggerZone` that sends the player to `partner`.
+#[derive(Component)]
+pub struct Portal {
+    partner: Option<Entity>,
+    facing: Option<Vec2>,
+}
+
+/// The portal a player just came out of. Entering it doesn't count until they've left it
+/// once, so they don't bounce straight back.
+#[derive(Component)]
+pub struct ExitingPortal(Entity);
+
+fn spawn_portals(mut commands: Commands, world_data: Query<&PortalSpawns, With<WorldData>>) {
+    let Ok(spawns) = world_data.get_single() else {
+        return;
+    };
+    let mut ends: HashMap<&str, Vec<(Entity, &PortalData)>> = HashMap::new();
+    for data in &spawns.0 {
+        let entity = commands.spawn((
+            TriggerZone { tag: format!("portal {}", data.id) },
+            Position(data.position),
+            Shape(data.shape),
+            Rotation(0.),
+            ZOrder(-0.01),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: PORTAL_COLOR,
+                    custom_size: Some(data.shape),
+                    ..default()
+                },
+                ..default()
+            },
+            LevelEntity,
+        )).id();
+        ends.entry(&data.id).or_default().push((entity, data));
+    }
+    for pair in ends.values() {
+        for (index, (entity, data)) in pair.iter().enumerate() {
+            let partner = match index {
+                0 => pair.get(1),
+                1 => pair.first(),
+                _ => None,
+            };
+            commands.entity(*entity).insert(Portal {
+                partner: partner.map(|(partner, _)| *partner),
+                facing: data.facing.map(|facing| facing.normalize_or_zero()),
+            });
+        }
+    }
+}
+
+/// Moves a player who walks into a portal to the middle of its partner, keeping their
+/// speed. A grapple doesn't survive the trip.
+pub fn use_portals(
+    mut commands: Commands,
+    mut entered: EventReader<TriggerEnter>,
+    mut exited: EventReader<TriggerExit>,
+    portals: Query<(&Portal, &Position), Without<Player>>,
+    mut players: Query<(&mut Position, &mut Velocity, &mut VisShape, &Shape, Option<&ExitingPortal>), (With<Player>, Without<Downed>)>,
+    mut teleported: EventWriter<PlayerTeleported>,
+) {
+    for event in exited.read() {
+        if let Ok((.., Some(exiting))) = players.get(event.body) {
+            if exiting.0 == event.zone {
+                commands.entity(event.body).remove::<ExitingPortal>();
+            }
+        }
+    }
+    for event in entered.read() {
+        let Ok((portal, portal_pos)) = portals.get(event.zone) else {
+            continue;
+        };
+        let Ok((mut position, mut velocity, mut vis_shape, shape, exiting)) = players.get_mut(event.body) else {
+            continue;
+        };
+        if exiting.is_some_and(|exiting| exiting.0 == event.zone) {
+            continue;
+        }
+        let Some((partner, (exit, exit_pos))) = portal.partner.and_then(|partner| Some((partner, portals.get(partner).ok()?))) else {
+            continue;
+        };
+        if let (Some(entry_facing), Some(exit_facing)) = (portal.facing, exit.facing) {
+            let turn = exit_facing.to_angle() - (-entry_facing).to_angle();
+            velocity.0 = Vec2::from_angle(turn).rotate(velocity.0);
+        }
+        let from = position.0;
+        position.teleport(&mut commands, event.body, exit_pos.0);
+        vis_shape.0 = shape.0 * TELEPORT_POP;
+        commands.entity(event.body).remove::<Grapple>().insert(ExitingPortal(partner));
+        for end in [portal_pos.0, exit_pos.0] {
+            spawn_ring(&mut commands, end, RING_RADIUS, PORTAL_COLOR, RING_DOTS, RING_SECS, false);
+        }
+        teleported.send(PlayerTeleported { entity: event.body, from, to: exit_pos.0 });
+    }
+}
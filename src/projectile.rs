@@ -0,0 +1,254 @@
+use bevy::math::bounding::Aabb2d;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::breakable::{smash_block, Breakable};
+use crate::cleanup::{FadeOut, FadeStyle, Lifetime};
+use crate::cutscene::cutscene_playing;
+use crate::damage::{apply_damage, Damageable};
+use crate::debug::DebugTrackExt;
+use crate::enemy::Enemy;
+use crate::events::{BlockBroken, DamageEvent};
+use crate::input::{Action, Actions};
+use crate::level::{Downed, LevelEntity, LevelState};
+use crate::magnet::Metallic;
+use crate::physics::{collide, move_bodies, Collider, Collision, Gravitated, Position, Rotation, Shape, Velocity, ZOrder};
+use crate::player::{Facing, Player, PlayerId};
+use crate::sfx::{PlaySfxAt, SfxKind};
+use crate::world::BlockIndex;
+
+const PROJECTILE_SPEED: f32 = 1440.;
+const PROJECTILE_SIZE: f32 = 12.;
+const PROJECTILE_LIFETIME: f32 = 2.;
+/// A shot that runs out of time shrinks away over its last this many seconds.
+const PROJECTILE_FADE_SECS: f32 = 0.2;
+/// Bounces slower than this just stop the projectile instead of dribbling along the floor.
+const MIN_BOUNCE_SPEED: f32 = 144.;
+/// Velocity of a lobbed shot fired to the right, in pixels per second; gravity bends it
+/// into an arc from there.
+const LOB_VELOCITY: Vec2 = Vec2::new(720., 720.);
+/// The most projectiles alive at once. Firing past it does nothing until one is gone.
+const MAX_LIVE_PROJECTILES: usize = 8;
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.debug_track::<Projectile>("projectiles")
+            .add_systems(FixedUpdate, (
+            fire_projectiles.before(move_bodies).run_if(not(cutscene_playing)),
+            (bounce_projectiles,
+             hit_enemies.before(apply_damage)).chain().after(move_bodies),
+        ));
+    }
+}
+
+#[derive(Component)]
+pub struct Projectile {
+    pub damage: i32,
+    /// Fraction of speed kept when reflecting off a block; zero means it breaks on impact.
+    pub bounciness: f32,
+    /// How many enemies it passes through before it's used up.
+    pub pierce: u8,
+    hits: Vec<Entity>,
+}
+
+/// The projectile variant an entity fires.
+#[derive(Component, Clone, Copy)]
+pub struct Weapon {
+    pub damage: i32,
+    pub bounciness: f32,
+    pub pierce: u8,
+    /// Seconds after a shot before the next one can be fired.
+    pub cooldown: f32,
+    /// Shots are thrown up in an arc and fall under gravity instead of flying straight.
+    pub lobbed: bool,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Self {
+            damage: 1,
+            bounciness: 0.7,
+            pierce: 1,
+            cooldown: 0.2,
+            lobbed: false,
+        }
+    }
+}
+
+impl Projectile {
+    pub fn new(weapon: Weapon) -> Self {
+        Self {
+            damage: weapon.damage,
+            bounciness: weapon.bounciness,
+            pierce: weapon.pierce,
+            hits: Vec::new(),
+        }
+    }
+}
+
+/// Straight shots go where the player aims; lobbed ones are thrown the way they face.
+fn fire_projectiles(
+    mut commands: Commands,
+    players: Query<(&PlayerId, &Position, &Facing, &Weapon), (With<Player>, Without<Downed>)>,
+    live: Query<(), With<Projectile>>,
+    actions: Actions,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut cooldowns: Local<HashMap<PlayerId, f32>>,
+    time: Res<Time>,
+) {
+    // The live cap is shared between both players.
+    let mut live = live.iter().len();
+    for (id, position, facing, weapon) in &players {
+        let cooldown = cooldowns.entry(*id).or_default();
+        *cooldown = (*cooldown - time.delta_seconds()).max(0.);
+        if !actions.player(*id).just_pressed(Action::Fire) || *cooldown > 0. || live >= MAX_LIVE_PROJECTILES {
+            continue;
+        }
+        *cooldown = weapon.cooldown;
+        live += 1;
+        spawn_projectile(&mut commands, position.0, *facing, weapon, &mut meshes, &mut materials);
+    }
+}
+
+fn spawn_projectile(
+    commands: &mut Commands,
+    position: Vec2,
+    facing: Facing,
+    weapon: &Weapon,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) {
+    let velocity = if weapon.lobbed {
+        Vec2::new(LOB_VELOCITY.x * facing.x, LOB_VELOCITY.y)
+    } else {
+        facing.aim * PROJECTILE_SPEED
+    };
+    let mut projectile = commands.spawn((
+        Projectile::new(*weapon),
+        Lifetime::secs(PROJECTILE_LIFETIME),
+        FadeOut { duration: PROJECTILE_FADE_SECS, style: FadeStyle::Shrink },
+        Metallic,
+        Position(position),
+        Velocity(velocity),
+        Shape(Vec2::splat(PROJECTILE_SIZE)),
+        Rotation(0.),
+        ZOrder(0.2),
+        ColorMesh2dBundle {
+            mesh: meshes.add(Circle::new(PROJECTILE_SIZE / 2.)).into(),
+            material: materials.add(Color::srgb(1., 0.9, 0.3)),
+            ..default()
+        },
+        LevelEntity,
+    ));
+    if weapon.lobbed {
+        projectile.insert(Gravitated);
+    }
+}
+
+/// Reflects projectiles off blocks along the contact normal, losing energy on each bounce.
+/// A `Breakable` block is smashed instead, and the projectile goes with it.
+fn bounce_projectiles(
+    mut commands: Commands,
+    mut projectiles: Query<(Entity, &mut Position, &mut Velocity, &Shape, &Projectile)>,
+    colliders: Query<(Entity, &Position, &Shape, Option<&BlockIndex>, Has<Breakable>), (With<Collider>, Without<Projectile>)>,
+    mut level_state: ResMut<LevelState>,
+    mut broken: EventWriter<BlockBroken>,
+    mut sfx: EventWriter<PlaySfxAt>,
+) {
+    for (entity, mut position, mut velocity, shape, projectile) in &mut projectiles {
+        for (block, block_pos, block_shape, index, breakable) in &colliders {
+            let aabb = Aabb2d::new(position.0, shape.0 / 2.);
+            let Some((side, offset)) = collide(aabb, Aabb2d::new(block_pos.0, block_shape.0 / 2.)) else {
+                continue;
+            };
+            if breakable {
+                smash_block(&mut commands, block, block_pos.0, block_shape.0, index, &mut level_state, &mut broken);
+                commands.entity(entity).despawn_recursive();
+                break;
+            }
+            match side {
+                Collision::Top => position.0.y -= offset.y,
+                Collision::Bottom => position.0.y += offset.y,
+                Collision::Left => position.0.x += offset.x,
+                Collision::Right => position.0.x -= offset.x,
+            }
+            let normal = side.normal();
+            let into_surface = velocity.0.dot(normal);
+            // Grazing a face or overlapping a block already bounced off costs nothing.
+            if into_surface >= 0. {
+                continue;
+            }
+            velocity.0 = (velocity.0 - 2. * into_surface * normal) * projectile.bounciness;
+            sfx.send(PlaySfxAt::new(SfxKind::ProjectileBounce, position.0));
+            if velocity.0.length() < MIN_BOUNCE_SPEED {
+                commands.entity(entity).despawn_recursive();
+                break;
+            }
+        }
+    }
+}
+
+fn hit_enemies(
+    mut commands: Commands,
+    mut projectiles: Query<(Entity, &Position, &Shape, &mut Projectile)>,
+    enemies: Query<(Entity, &Position, &Shape), (With<Enemy>, With<Damageable>)>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    for (entity, position, shape, mut projectile) in &mut projectiles {
+        let aabb = Aabb2d::new(position.0, shape.0 / 2.);
+        for (enemy, enemy_pos, enemy_shape) in &enemies {
+            if projectile.hits.contains(&enemy) || collide(aabb, Aabb2d::new(enemy_pos.0, enemy_shape.0 / 2.)).is_none() {
+                continue;
+            }
+            damage.send(DamageEvent {
+                target: enemy,
+                amount: projectile.damage,
+                source_position: Some(position.0),
+            });
+            projectile.hits.push(enemy);
+            if projectile.hits.len() > projectile.pierce as usize {
+                commands.entity(entity).despawn_recursive();
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::build_headless_app;
+    use crate::world::{BlockData, WorldData};
+
+    #[test]
+    fn a_shot_into_a_corner_comes_back_out() {
+        // A floor with its top at -175 meeting a wall with its face at 275.
+        let mut app = build_headless_app(WorldData(vec![
+            BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.)),
+            BlockData::new(Vec2::new(300., 0.), Vec2::new(50., 400.)),
+        ]));
+        for _ in 0..2 {
+            app.update();
+        }
+        let start = Vec2::new(200., -100.);
+        let velocity = Vec2::new(720., -720.);
+        let shot = app.world_mut().spawn((
+            Projectile::new(Weapon::default()),
+            Position(start),
+            Velocity(velocity),
+            Shape(Vec2::splat(PROJECTILE_SIZE)),
+        )).id();
+        for _ in 0..72 {
+            app.update();
+        }
+        let position = app.world().get::<Position>(shot).expect("broke on the corner").0;
+        let bounced = app.world().get::<Velocity>(shot).unwrap().0;
+        // Off both faces, each costing its share of speed, back the way it came.
+        let bounciness = Weapon::default().bounciness;
+        assert!(bounced.distance(-velocity * bounciness * bounciness) < 1., "left the corner at {bounced}");
+        assert!(position.x < start.x && position.y > start.y, "ended up at {position}");
+    }
+}
@@ -0,0 +1,745 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fs, io};
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::breakable::{smash_block, Breakable};
+use crate::camera::cursor_world_position;
+use crate::crates::{pick_up, Carried, Carrying, Crate};
+use crate::cutscene::ActiveCutscene;
+use crate::endless::{ChunkGenerator, ENDLESS_LEVEL};
+use crate::events::{ActivationEvent, BlockBroken, CrateGrabbed, DoorOpened};
+use crate::headless::headless_app;
+use crate::input::{start_tick_presses, Action, Actions, InputConfig, ScriptedInput};
+use crate::key::{open_door, Door};
+use crate::level::{LevelState, ResetLevel};
+use crate::movement::{MovementConfig, MovementModifiers};
+use crate::origin::{rebase_origin, WorldOrigin};
+use crate::perf::flag_value;
+use crate::physics::{Collider, Position, Shape, Velocity};
+use crate::player::{Player, PlayerId};
+use crate::save::{Abilities, Profile};
+use crate::switch::Channels;
+use crate::world::{read_level_file, BlockIndex, CurrentLevel};
+use crate::GameState;
+
+const RECORD_FLAG: &str = "--record";
+const REPLAY_FLAG: &str = "--replay";
+const HEADLESS_FLAG: &str = "--headless";
+/// With `--replay`, only checks the world against the recording and leaves it be.
+const VERIFY_FLAG: &str = "--verify";
+/// How close a block, door or crate has to be to where the recording says it was for a
+/// replay to act on it.
+const MATCH_DISTANCE: f32 = 1.;
+#[cfg(feature = "debug-tools")]
+const TOGGLE_KEY: KeyCode = KeyCode::F9;
+/// Where a recording started with `TOGGLE_KEY` goes.
+#[cfg(feature = "debug-tools")]
+const RECORDING_PATH: &str = "saves/recording.ron";
+
+/// Records the first player's input a fixed tick at a time, with `--record <path>` or
+/// `TOGGLE_KEY`, and plays it back with `--replay <path>`, so a collision bug happens
+/// again exactly as it did. That takes the same inputs giving the same ticks:
+///
+/// - Fixed systems only see `Time<Fixed>`, whose delta is always one timestep; hitstop,
+///   slow motion and catch-up only change how many ticks a frame runs. The tick rate is a
+///   setting, so it's recorded.
+/// - Input is what `Actions` read during the tick, presses held over from tick-less
+///   frames included. The mouse is kept in world space, since the camera moves on render
+///   frames.
+/// - The endless seed comes from the wall clock, and the movement tunables and mouse aim
+///   from files; all recorded. Abilities come from the save slot, which a replay leaves
+///   alone, so a mismatch only gets a warning.
+/// - Skipping a cutscene lands on whatever tick the key does, so it's off while recording
+///   or replaying, and a level intro the recording didn't have is stopped.
+/// - A restart, or the second player joining, happens on a render frame after however
+///   many ticks that one has left. So a recording runs from a level start to the next
+///   restart, and only single-player runs are recorded.
+///
+/// Should a replay drift anyway, the world shouldn't drift with it, so every `WorldEvent`
+/// is recorded with its tick too. A replay does what the recording says it did, whatever
+/// the replayed ticks say, and warns about the first tick they disagree on. With
+/// `--verify` it leaves the world to the replayed ticks and only reports that tick, which
+/// makes it a check on all the interactive blocks running the same every time.
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        let mut recorder = InputRecorder::default();
+        if let Some(path) = flag_value(RECORD_FLAG) {
+            recorder.path = path.into();
+            recorder.state = RecorderState::Armed;
+        }
+        app.insert_resource(recorder)
+            .init_resource::<TickWorldEvents>()
+            .add_systems(OnEnter(GameState::Restarting), restart_recording.before(ResetLevel))
+            .add_systems(OnEnter(GameState::Menu), finish_recording)
+            .add_systems(OnEnter(GameState::Won), finish_recording)
+            .add_systems(FixedPreUpdate, (record_tick, play_tick).after(start_tick_presses).run_if(in_state(GameState::Playing)))
+            .add_systems(FixedPostUpdate, (gather_world_events, record_world_events, check_world_events, apply_world_events)
+                .chain()
+                .before(rebase_origin)
+                .run_if(in_state(GameState::Playing)))
+            .add_systems(Last, finish_recording_on_exit);
+        #[cfg(feature = "debug-tools")]
+        app.add_systems(Update, toggle_recording);
+        // A headless replay never gets this far; see `replay_headless_from_args`.
+        if let Some(path) = flag_value(REPLAY_FLAG) {
+            match read_recording(Path::new(&path)) {
+                Ok(recording) => {
+                    app.insert_resource(Playback::new(recording, verifying()))
+                        .add_systems(OnEnter(GameState::Menu), start_playback);
+                }
+                Err(error) => error!("couldn't replay {path}: {error}"),
+            }
+        }
+    }
+}
+
+/// Where a player was, and how fast it was going, at the start or end of a recording.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BodyState {
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+/// The first player's input for one tick, as `Actions` read it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct TickInput {
+    pressed: Vec<Action>,
+    /// Actions pressed this tick; they aren't always still in `pressed`, since a tap can
+    /// start and end inside a frame that ran no tick.
+    just_pressed: Vec<Action>,
+    /// `move_x` and `move_y`, stick tilt included.
+    movement: Vec2,
+    cursor: Option<Vec2>,
+}
+
+/// Something a tick did to the level that stays done, with positions in level coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+enum WorldEvent {
+    BlockBroken(Vec2),
+    ChannelSwitched { channel: u32, active: bool },
+    DoorOpened(Vec2),
+    CrateGrabbed(Vec2),
+}
+
+/// A `WorldEvent` and the tick it happened on, counted from the recording's first.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct TickEvent {
+    tick: usize,
+    event: WorldEvent,
+}
+
+/// One attempt at a level, from its start, with everything else it takes to play it the
+/// same again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputRecording {
+    level: String,
+    seed: u64,
+    tick: Duration,
+    mouse_aim: bool,
+    movement: MovementConfig,
+    abilities: Abilities,
+    /// Whether a cutscene was playing as the attempt started.
+    cutscene: bool,
+    start: BodyState,
+    /// Where the player ended up after the last tick, to check a replay against.
+    end: Option<BodyState>,
+    ticks: Vec<TickInput>,
+    /// In the order they happened, ticks included.
+    #[serde(default)]
+    events: Vec<TickEvent>,
+}
+
+impl InputRecording {
+    fn new(level: &CurrentLevel, seed: u64, tick: Duration, input: &InputConfig, movement: &MovementConfig, abilities: Abilities) -> Self {
+        Self {
+            level: level.0.clone(),
+            seed,
+            tick,
+            mouse_aim: input.mouse_aim,
+            movement: movement.clone(),
+            abilities,
+            cutscene: false,
+            start: BodyState::default(),
+            end: None,
+            ticks: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+}
+
+fn read_recording(path: &Path) -> Result<InputRecording, String> {
+    let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    ron::from_str(&text).map_err(|error| error.to_string())
+}
+
+fn write_recording(path: &Path, recording: &InputRecording) -> io::Result<()> {
+    let text = ron::to_string(recording).map_err(io::Error::other)?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, text)
+}
+
+#[derive(Default)]
+enum RecorderState {
+    #[default]
+    Idle,
+    /// Starts recording when the next attempt does.
+    Armed,
+    Recording(Box<InputRecording>),
+}
+
+#[derive(Resource, Default)]
+pub struct InputRecorder {
+    path: PathBuf,
+    state: RecorderState,
+}
+
+impl InputRecorder {
+    /// Writes out the recording under way, if there is one, ending on `end`.
+    fn finish(&mut self, end: Option<BodyState>) {
+        let RecorderState::Recording(mut recording) = std::mem::take(&mut self.state) else {
+            return;
+        };
+        recording.end = end;
+        match write_recording(&self.path, &recording) {
+            Ok(()) => info!("recorded {} ticks of {} to {}", recording.ticks.len(), recording.level, self.path.display()),
+            Err(error) => error!("couldn't write the recording to {}: {error}", self.path.display()),
+        }
+    }
+}
+
+/// Skipping a cutscene would land on a different tick on every run; see `RecordingPlugin`.
+pub fn recording_or_replaying(recorder: Option<Res<InputRecorder>>, playback: Option<Res<Playback>>) -> bool {
+    playback.is_some() || recorder.is_some_and(|recorder| !matches!(recorder.state, RecorderState::Idle))
+}
+
+fn verifying() -> bool {
+    std::env::args().any(|arg| arg == VERIFY_FLAG)
+}
+
+fn first_player(players: &Query<(&PlayerId, &Position, &Velocity), With<Player>>) -> Option<BodyState> {
+    players.iter()
+        .find(|(id, ..)| **id == PlayerId::ONE)
+        .map(|(_, position, velocity)| BodyState { position: position.0, velocity: velocity.0 })
+}
+
+/// Runs ahead of the teardown, so an attempt that's ending still has its player where the
+/// last tick left them.
+#[allow(clippy::too_many_arguments)]
+fn restart_recording(
+    mut recorder: ResMut<InputRecorder>,
+    level: Res<CurrentLevel>,
+    generator: Res<ChunkGenerator>,
+    fixed: Res<Time<Fixed>>,
+    input: Res<InputConfig>,
+    movement: Res<MovementConfig>,
+    profile: Res<Profile>,
+    players: Query<(&PlayerId, &Position, &Velocity), With<Player>>,
+) {
+    match recorder.state {
+        RecorderState::Idle => {}
+        RecorderState::Recording(_) => recorder.finish(first_player(&players)),
+        RecorderState::Armed if input.two_players => {
+            warn!("two-player runs can't be recorded; turn two-player mode off to record");
+            recorder.state = RecorderState::Idle;
+        }
+        RecorderState::Armed => {
+            let recording = InputRecording::new(&level, generator.seed, fixed.timestep(), &input, &movement, profile.abilities);
+            recorder.state = RecorderState::Recording(Box::new(recording));
+        }
+    }
+}
+
+fn finish_recording(mut recorder: ResMut<InputRecorder>, players: Query<(&PlayerId, &Position, &Velocity), With<Player>>) {
+    recorder.finish(first_player(&players));
+}
+
+fn finish_recording_on_exit(
+    mut exit: EventReader<AppExit>,
+    mut recorder: ResMut<InputRecorder>,
+    players: Query<(&PlayerId, &Position, &Velocity), With<Player>>,
+) {
+    if exit.read().count() > 0 {
+        recorder.finish(first_player(&players));
+    }
+}
+
+/// Starts a recording from a fresh attempt at the level being played, or, while one is
+/// going, stops it where it is.
+#[cfg(feature = "debug-tools")]
+fn toggle_recording(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut recorder: ResMut<InputRecorder>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    players: Query<(&PlayerId, &Position, &Velocity), With<Player>>,
+) {
+    if !kb_input.just_pressed(TOGGLE_KEY) {
+        return;
+    }
+    match recorder.state {
+        RecorderState::Recording(_) => recorder.finish(first_player(&players)),
+        RecorderState::Armed => recorder.state = RecorderState::Idle,
+        RecorderState::Idle => {
+            recorder.path = RECORDING_PATH.into();
+            recorder.state = RecorderState::Armed;
+            if *state.get() == GameState::Playing {
+                next_state.set(GameState::Restarting);
+            }
+        }
+    }
+}
+
+fn record_tick(
+    mut recorder: ResMut<InputRecorder>,
+    actions: Actions,
+    players: Query<(&PlayerId, &Position, &Velocity), With<Player>>,
+    cutscene: Res<ActiveCutscene>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<crate::camera::Camera>>,
+) {
+    let RecorderState::Recording(recording) = &mut recorder.state else {
+        return;
+    };
+    if recording.ticks.is_empty() {
+        recording.start = first_player(&players).unwrap_or_default();
+        recording.cutscene = cutscene.is_playing();
+    }
+    let input = actions.player(PlayerId::ONE);
+    recording.ticks.push(TickInput {
+        pressed: Action::ALL.into_iter().filter(|action| input.pressed(*action)).collect(),
+        just_pressed: Action::ALL.into_iter().filter(|action| input.just_pressed(*action)).collect(),
+        movement: Vec2::new(input.move_x(), input.move_y()),
+        cursor: cursor_world_position(&window, &camera),
+    });
+}
+
+/// A recording being played back through `ScriptedInput`, one tick of it per fixed tick.
+#[derive(Resource)]
+pub struct Playback {
+    recording: InputRecording,
+    next_tick: usize,
+    /// Only report the world going another way, rather than putting it back.
+    verify: bool,
+    /// The first of the recording's `events` not yet checked.
+    next_event: usize,
+    /// What the recording did on the tick just checked that the replay didn't, for
+    /// `apply_world_events` to do.
+    missed: Vec<WorldEvent>,
+    /// Events `apply_world_events` sent, which come back on the next tick without it
+    /// having done them.
+    echoes: Vec<WorldEvent>,
+    divergence: Option<Divergence>,
+}
+
+/// The first tick a replay's world didn't do what the recording's did.
+#[derive(Clone, Debug, PartialEq)]
+struct Divergence {
+    tick: usize,
+    /// What the recording did that the replay didn't.
+    recorded: Vec<WorldEvent>,
+    /// What the replay did that the recording didn't.
+    replayed: Vec<WorldEvent>,
+}
+
+impl Playback {
+    fn new(recording: InputRecording, verify: bool) -> Self {
+        Self { recording, next_tick: 0, verify, next_event: 0, missed: Vec::new(), echoes: Vec::new(), divergence: None }
+    }
+
+    /// Everything in the recording besides its input, put back before the level starts.
+    fn apply_settings(&self, world: &mut World) {
+        let recording = &self.recording;
+        if let Some(mut level) = world.get_resource_mut::<CurrentLevel>() {
+            level.0 = recording.level.clone();
+        }
+        if let Some(mut generator) = world.get_resource_mut::<ChunkGenerator>() {
+            generator.seed = recording.seed;
+        }
+        world.resource_mut::<Time<Fixed>>().set_timestep(recording.tick);
+        let mut input = world.resource_mut::<InputConfig>();
+        input.two_players = false;
+        input.mouse_aim = recording.mouse_aim;
+        world.insert_resource(recording.movement.clone());
+        if let Some(profile) = world.get_resource::<Profile>().filter(|profile| profile.abilities != recording.abilities) {
+            warn!("this save has {:?} but the recording was made with {:?}, so the replay may differ", profile.abilities, recording.abilities);
+        }
+        world.insert_resource(ScriptedInput::default());
+    }
+}
+
+/// Goes straight from the menu into the recorded level.
+fn start_playback(world: &mut World) {
+    let Some(playback) = world.remove_resource::<Playback>() else {
+        return;
+    };
+    playback.apply_settings(world);
+    world.insert_resource(playback);
+    world.resource_mut::<NextState<GameState>>().set(GameState::Restarting);
+}
+
+/// Hands over to live input once the recording runs out, saying where the player ended up
+/// next to where they did when it was recorded.
+fn play_tick(
+    mut commands: Commands,
+    playback: Option<ResMut<Playback>>,
+    mut scripted: Option<ResMut<ScriptedInput>>,
+    mut players: Query<(&PlayerId, &mut Position, &mut Velocity), With<Player>>,
+    mut cutscene: ResMut<ActiveCutscene>,
+) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+    let Some(scripted) = scripted.as_mut() else {
+        return;
+    };
+    let playback = &mut *playback;
+    let recording = &playback.recording;
+    let mut player = players.iter_mut().find(|(id, ..)| **id == PlayerId::ONE);
+    if playback.next_tick == 0 {
+        if let Some((_, position, velocity)) = &mut player {
+            position.0 = recording.start.position;
+            velocity.0 = recording.start.velocity;
+        }
+        if !recording.cutscene && cutscene.is_playing() {
+            *cutscene = ActiveCutscene::default();
+        }
+    }
+    let Some(tick) = recording.ticks.get(playback.next_tick) else {
+        let end = player.map(|(_, position, velocity)| BodyState { position: position.0, velocity: velocity.0 });
+        println!("{}", replay_report(recording, end, playback.divergence.as_ref()));
+        commands.insert_resource(ReplayEnd { body: end, divergence: playback.divergence.take() });
+        commands.remove_resource::<Playback>();
+        commands.remove_resource::<ScriptedInput>();
+        return;
+    };
+    playback.next_tick += 1;
+    let actions = &mut scripted.actions[PlayerId::ONE.0 as usize];
+    actions.reset_all();
+    for action in Action::ALL {
+        let (pressed, just_pressed) = (tick.pressed.contains(&action), tick.just_pressed.contains(&action));
+        if pressed || just_pressed {
+            actions.press(action);
+        }
+        if pressed && !just_pressed {
+            actions.clear_just_pressed(action);
+        } else if just_pressed && !pressed {
+            actions.release(action);
+        }
+    }
+    scripted.sticks[PlayerId::ONE.0 as usize] = tick.movement;
+    scripted.cursor = tick.cursor;
+}
+
+/// Where a finished replay left the player, if there was one, and where its world first
+/// went another way, if it did.
+#[derive(Resource)]
+struct ReplayEnd {
+    body: Option<BodyState>,
+    divergence: Option<Divergence>,
+}
+
+/// Where the replay left the player, whether that's where the recording did, and the
+/// first tick the world didn't go the same way.
+fn replay_report(recording: &InputRecording, end: Option<BodyState>, divergence: Option<&Divergence>) -> String {
+    let mut report = match end {
+        Some(end) => format!("replayed {} ticks: player at {}, velocity {}", recording.ticks.len(), end.position, end.velocity),
+        None => format!("replayed {} ticks, but there's no player", recording.ticks.len()),
+    };
+    match (recording.end, end) {
+        (Some(recorded), Some(end)) if recorded == end => report += ", the same as recorded",
+        (Some(recorded), Some(_)) => report += &format!(", recorded at {}, velocity {}", recorded.position, recorded.velocity),
+        _ => {}
+    }
+    if let Some(divergence) = divergence {
+        report += &format!("; the world went another way on tick {}, where the recording did {:?} and the replay {:?}", divergence.tick, divergence.recorded, divergence.replayed);
+    }
+    report
+}
+
+/// The `WorldEvent`s of the tick just run, for `record_world_events` and
+/// `check_world_events`.
+#[derive(Resource, Default)]
+struct TickWorldEvents(Vec<WorldEvent>);
+
+fn gather_world_events(
+    mut gathered: ResMut<TickWorldEvents>,
+    origin: Res<WorldOrigin>,
+    mut broken: EventReader<BlockBroken>,
+    mut activations: EventReader<ActivationEvent>,
+    mut doors: EventReader<DoorOpened>,
+    mut grabbed: EventReader<CrateGrabbed>,
+) {
+    let origin = *origin;
+    gathered.0.clear();
+    gathered.0.extend(broken.read().map(|event| WorldEvent::BlockBroken(origin.to_level(event.position))));
+    gathered.0.extend(activations.read().map(|event| WorldEvent::ChannelSwitched { channel: event.channel, active: event.active }));
+    gathered.0.extend(doors.read().map(|event| WorldEvent::DoorOpened(origin.to_level(event.position))));
+    gathered.0.extend(grabbed.read().map(|event| WorldEvent::CrateGrabbed(origin.to_level(event.position))));
+}
+
+fn record_world_events(recorder: Option<ResMut<InputRecorder>>, gathered: Res<TickWorldEvents>) {
+    let Some(mut recorder) = recorder else {
+        return;
+    };
+    let RecorderState::Recording(recording) = &mut recorder.state else {
+        return;
+    };
+    let Some(tick) = recording.ticks.len().checked_sub(1) else {
+        return;
+    };
+    recording.events.extend(gathered.0.iter().map(|&event| TickEvent { tick, event }));
+}
+
+/// Matches the tick's events against the ones recorded for it. Order within a tick
+/// doesn't matter, only that each happened.
+fn check_world_events(playback: Option<ResMut<Playback>>, gathered: Res<TickWorldEvents>) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+    let playback = &mut *playback;
+    let Some(tick) = playback.next_tick.checked_sub(1) else {
+        return;
+    };
+    let mut replayed = gathered.0.clone();
+    for echo in playback.echoes.drain(..) {
+        if let Some(index) = replayed.iter().position(|event| *event == echo) {
+            replayed.swap_remove(index);
+        }
+    }
+    let recorded = &playback.recording.events[playback.next_event..];
+    let count = recorded.iter().take_while(|event| event.tick == tick).count();
+    let mut missed = Vec::new();
+    for TickEvent { event, .. } in &recorded[..count] {
+        match replayed.iter().position(|replayed| replayed == event) {
+            Some(index) => {
+                replayed.swap_remove(index);
+            }
+            None => missed.push(*event),
+        }
+    }
+    playback.next_event += count;
+    if (!missed.is_empty() || !replayed.is_empty()) && playback.divergence.is_none() {
+        warn!("the replay's world went another way on tick {tick}: the recording did {missed:?} and the replay {replayed:?}");
+        playback.divergence = Some(Divergence { tick, recorded: missed.clone(), replayed });
+    }
+    if !playback.verify {
+        playback.missed = missed;
+    }
+}
+
+/// Does what the recording did on the tick just run and the replay didn't: breaks the
+/// block or opens the door where the recording did, switches the channel, or has the
+/// first player grab the crate closest to where the recording's did. Something the replay did that the recording
+/// didn't stays done; there's no unbreaking a block.
+fn apply_world_events(
+    mut commands: Commands,
+    playback: Option<ResMut<Playback>>,
+    origin: Res<WorldOrigin>,
+    blocks: Query<(Entity, &Position, &Shape, Option<&BlockIndex>), With<Breakable>>,
+    mut level_state: ResMut<LevelState>,
+    mut broken: EventWriter<BlockBroken>,
+    mut channels: ResMut<Channels>,
+    mut activations: EventWriter<ActivationEvent>,
+    doors: Query<(Entity, &Position, &Shape), (With<Door>, With<Collider>)>,
+    crates: Query<(Entity, &Position, &Shape), (With<Crate>, Without<Carried>)>,
+    mut players: Query<(Entity, &PlayerId, &mut MovementModifiers), (With<Player>, Without<Carrying>)>,
+) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+    let playback = &mut *playback;
+    let near = |position: &Position, at: Vec2| position.0.distance(origin.to_live(at)) < MATCH_DISTANCE;
+    for event in playback.missed.drain(..) {
+        match event {
+            WorldEvent::BlockBroken(at) => {
+                if let Some((entity, position, shape, index)) = blocks.iter().find(|(_, position, ..)| near(position, at)) {
+                    smash_block(&mut commands, entity, position.0, shape.0, index, &mut level_state, &mut broken);
+                    playback.echoes.push(WorldEvent::BlockBroken(origin.to_level(position.0)));
+                }
+            }
+            WorldEvent::ChannelSwitched { channel, active } => {
+                if channels.switch(channel, active, &mut activations) {
+                    playback.echoes.push(event);
+                }
+            }
+            WorldEvent::DoorOpened(at) => {
+                if let Some((entity, position, shape)) = doors.iter().find(|(_, position, _)| near(position, at)) {
+                    open_door(&mut commands, entity, position.0, shape.0);
+                }
+            }
+            WorldEvent::CrateGrabbed(at) => {
+                let nearest = crates.iter().min_by(|(_, a, _), (_, b, _)| {
+                    a.0.distance_squared(origin.to_live(at)).total_cmp(&b.0.distance_squared(origin.to_live(at)))
+                });
+                let player = players.iter_mut().find(|(_, id, _)| **id == PlayerId::ONE);
+                if let (Some((entity, _, shape)), Some((player, _, mut modifiers))) = (nearest, player) {
+                    pick_up(&mut commands, entity, shape.0, player, &mut modifiers);
+                }
+            }
+        }
+    }
+}
+
+/// Handles `--replay <recording.ron> --headless`: plays the recording through the
+/// headless app, which has every gameplay system the window does, prints where it leaves
+/// the player and returns the process exit code: 1 if that isn't where the recording
+/// ended or the world went another way, 2 if it couldn't be replayed. `None` starts the
+/// game as usual, replaying in a window if `--replay` was given on its own.
+pub fn replay_headless_from_args() -> Option<i32> {
+    let path = flag_value(REPLAY_FLAG)?;
+    if !std::env::args().any(|arg| arg == HEADLESS_FLAG) {
+        return None;
+    }
+    let recording = match read_recording(Path::new(&path)) {
+        Ok(recording) => recording,
+        Err(error) => {
+            eprintln!("{path}: {error}");
+            return Some(2);
+        }
+    };
+    if recording.level != ENDLESS_LEVEL {
+        if let Err(error) = read_level_file(&CurrentLevel(recording.level.clone()).path()) {
+            eprintln!("couldn't load level {}: {error}", recording.level);
+            return Some(2);
+        }
+    }
+    let expected = recording.end;
+    let Some(end) = replay_headless(&mut headless_app(), Playback::new(recording, verifying())) else {
+        eprintln!("the replay didn't finish");
+        return Some(2);
+    };
+    let matched = (expected.is_none() || end.body == expected) && end.divergence.is_none();
+    Some(if matched { 0 } else { 1 })
+}
+
+/// Plays `playback` through `app`, a headless one about to start its level, for where it
+/// leaves the player, or `None` if it never reaches its last tick.
+fn replay_headless(app: &mut App, playback: Playback) -> Option<ReplayEnd> {
+    let ticks = playback.recording.ticks.len();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(playback.recording.tick))
+        .init_resource::<TickWorldEvents>()
+        .add_systems(FixedPreUpdate, play_tick.after(start_tick_presses).run_if(in_state(GameState::Playing)))
+        .add_systems(FixedPostUpdate, (gather_world_events, check_world_events, apply_world_events)
+            .chain()
+            .before(rebase_origin)
+            .run_if(in_state(GameState::Playing)));
+    playback.apply_settings(app.world_mut());
+    app.insert_resource(playback);
+    app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Restarting);
+    // Starting the level, hitstop and restarts all take updates that run no tick.
+    for _ in 0..ticks * 2 + 144 {
+        app.update();
+        // Read from what the replay saw with the ticks run out, as the rest of that
+        // update goes on to run another physics tick.
+        if let Some(end) = app.world_mut().remove_resource::<ReplayEnd>() {
+            return Some(end);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::build_headless_app;
+    use crate::world::{BlockData, BlockKind, WorldData};
+
+    fn recording(level: &str, ticks: impl IntoIterator<Item = TickInput>) -> InputRecording {
+        let tick = Duration::from_secs_f64(1. / 144.);
+        let mut recording = InputRecording::new(&CurrentLevel(level.into()), 0, tick, &InputConfig::default(), &MovementConfig::default(), Abilities::default());
+        recording.ticks = ticks.into_iter().collect();
+        recording
+    }
+
+    fn input(pressed: &[Action], just_pressed: &[Action]) -> TickInput {
+        let movement = Vec2::new(if pressed.contains(&Action::MoveRight) { 1. } else { 0. }, 0.);
+        TickInput { pressed: pressed.to_vec(), just_pressed: just_pressed.to_vec(), movement, cursor: None }
+    }
+
+    /// Running right through the demo level, jumping every second, with its enemies,
+    /// water and springs in the way.
+    fn demo_run() -> InputRecording {
+        recording("level1", (0..720).map(|tick| match tick % 144 == 72 {
+            true => input(&[Action::MoveRight, Action::Jump], &[Action::Jump]),
+            false => input(&[Action::MoveRight], &[]),
+        }))
+    }
+
+    const BLOCK: Vec2 = Vec2::new(0., 40.);
+
+    /// Standing under a breakable block, jumping into it from the `jump`th tick if there is
+    /// one, and holding jump for a full one.
+    fn under_a_block(jump: Option<i32>) -> (App, InputRecording) {
+        let app = build_headless_app(WorldData(vec![
+            BlockData::new(Vec2::new(0., -200.), Vec2::new(2000., 50.)),
+            BlockData { kind: BlockKind::Breakable, ..BlockData::new(BLOCK, Vec2::new(50., 50.)) },
+        ]));
+        let ticks = (0..288).map(|tick| match jump.map(|jump| tick - jump) {
+            Some(0) => input(&[Action::Jump], &[Action::Jump]),
+            Some(1..=60) => input(&[Action::Jump], &[]),
+            _ => input(&[], &[]),
+        });
+        let mut recording = recording("level1", ticks);
+        // On the floor, with its head 90px under the block.
+        recording.start.position = Vec2::new(0., -125.);
+        (app, recording)
+    }
+
+    fn breakables(app: &mut App) -> usize {
+        app.world_mut().query_filtered::<(), With<Breakable>>().iter(app.world()).count()
+    }
+
+    #[test]
+    fn replays_the_same_every_time() {
+        let replay = || replay_headless(&mut headless_app(), Playback::new(demo_run(), true)).expect("the replay didn't finish");
+        let first = replay();
+        let end = first.body.expect("there's no player");
+        assert!(end.position.x > 100., "the player didn't get anywhere: {}", end.position);
+        assert_eq!(first.divergence, None);
+        for _ in 0..2 {
+            let again = replay();
+            assert_eq!((again.body, again.divergence), (Some(end), None));
+        }
+    }
+
+    #[test]
+    fn verifying_reports_the_first_tick_the_world_differs() {
+        let (mut app, recording) = under_a_block(Some(100));
+        let end = replay_headless(&mut app, Playback::new(recording, true)).unwrap();
+        let divergence = end.divergence.expect("breaking the block wasn't recorded, so it should differ");
+        assert!(divergence.tick > 100, "broke before the jump, on tick {}", divergence.tick);
+        assert_eq!(divergence.recorded, []);
+        assert_eq!(divergence.replayed, [WorldEvent::BlockBroken(BLOCK)]);
+
+        // Recorded where it happens, it doesn't.
+        let (mut app, mut recording) = under_a_block(Some(100));
+        recording.events.push(TickEvent { tick: divergence.tick, event: WorldEvent::BlockBroken(BLOCK) });
+        let end = replay_headless(&mut app, Playback::new(recording, true)).unwrap();
+        assert_eq!(end.divergence, None);
+        assert_eq!(breakables(&mut app), 0);
+    }
+
+    #[test]
+    fn replaying_does_what_the_recording_did() {
+        let (mut app, mut recording) = under_a_block(None);
+        recording.events.push(TickEvent { tick: 10, event: WorldEvent::BlockBroken(BLOCK) });
+        let end = replay_headless(&mut app, Playback::new(recording.clone(), false)).unwrap();
+        assert_eq!(breakables(&mut app), 0);
+        let divergence = end.divergence.unwrap();
+        assert_eq!((divergence.tick, divergence.recorded), (10, vec![WorldEvent::BlockBroken(BLOCK)]));
+
+        // Only told about it, the block stays.
+        let (mut app, _) = under_a_block(None);
+        replay_headless(&mut app, Playback::new(recording, true)).unwrap();
+        assert_eq!(breakables(&mut app), 1);
+    }
+}
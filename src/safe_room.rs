@@ -0,0 +1,184 @@
+use bevy::color::Mix;
+use bevy::math::bounding::Aabb2d;
+use bevy::prelude::*;
+
+use crate::damage::Damageable;
+use crate::debug::DebugLabel;
+use crate::enemy::Enemy;
+use crate::events::CheckpointActivated;
+use crate::level::{commit_snapshots_in, LevelEntity, ResetLevel, SpawnSnapshot};
+use crate::physics::{move_bodies, Position, Rotation, Shape, ZOrder};
+use crate::player::Player;
+use crate::world::WorldData;
+use crate::GameState;
+
+const COOL_TINT: Color = Color::srgba(0.3, 0.35, 0.45, 0.25);
+const WARM_TINT: Color = Color::srgba(0.95, 0.6, 0.3, 0.3);
+/// How fast the tint moves between cool and warm, per second.
+const TINT_SPEED: f32 = 3.;
+
+pub struct SafeRoomPlugin;
+
+impl Plugin for SafeRoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Restarting), spawn_safe_rooms.after(ResetLevel))
+            .add_systems(FixedUpdate, (enter_safe_rooms.after(move_bodies), tint_safe_rooms));
+    }
+}
+
+#[derive(Debug)]
+pub struct SafeRoomData {
+    pub position: Vec2,
+    pub shape: Vec2,
+}
+
+/// Safe rooms placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component)]
+pub struct SafeRoomSpawns(pub Vec<SafeRoomData>);
+
+/// A checkpoint region. Walking in refills the player's health and commits everything
+/// resettable inside it, and walking out commits it again, so puzzles solved here stay
+/// solved after a death.
+#[derive(Component, Default)]
+pub struct SafeRoom {
+    occupied: bool,
+    warmth: f32,
+}
+
+fn spawn_safe_rooms(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    world_data: Query<&SafeRoomSpawns, With<WorldData>>,
+) {
+    let Ok(spawns) = world_data.get_single() else {
+        return;
+    };
+    for data in &spawns.0 {
+        commands.spawn((
+            SafeRoom::default(),
+            Position(data.position),
+            Shape(data.shape),
+            Rotation(0.),
+            ZOrder(-0.1),
+            ColorMesh2dBundle {
+                mesh: meshes.add(Rectangle::new(data.shape.x, data.shape.y)).into(),
+                // Own material per room, since each one tints on its own.
+                material: materials.add(COOL_TINT),
+                ..default()
+            },
+            LevelEntity,
+            DebugLabel("safe rooms"),
+        ));
+    }
+}
+
+fn enter_safe_rooms(
+    mut rooms: Query<(&mut SafeRoom, &Position, &Shape)>,
+    mut bodies: ParamSet<(
+        Query<(&Position, &mut Damageable), With<Player>>,
+        Query<(&Position, &mut SpawnSnapshot, Option<&Damageable>), Without<Enemy>>,
+    )>,
+    mut checkpoints: EventWriter<CheckpointActivated>,
+) {
+    for (mut room, position, shape) in &mut rooms {
+        let region = Aabb2d::new(position.0, shape.0 / 2.);
+        let mut players = bodies.p0();
+        let contains = |position: &Position| region.closest_point(position.0) == position.0;
+        let inside = players.iter().any(|(player_pos, _)| contains(player_pos));
+        if inside && !room.occupied {
+            for (_, mut health) in players.iter_mut().filter(|(player_pos, _)| contains(player_pos)) {
+                health.health = health.max_health;
+            }
+        }
+        // Again on the way out, to keep whatever was solved while inside.
+        if inside != room.occupied {
+            // Enemies always respawn at their posts; only the player and props get committed.
+            commit_snapshots_in(region, &mut bodies.p1());
+            checkpoints.send(CheckpointActivated);
+        }
+        room.occupied = inside;
+    }
+}
+
+fn tint_safe_rooms(
+    mut rooms: Query<(&mut SafeRoom, &Handle<ColorMaterial>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    time: Res<Time>,
+) {
+    let step = TINT_SPEED * time.delta_seconds();
+    for (mut room, material) in &mut rooms {
+        let target = if room.occupied { 1. } else { 0. };
+        let warmth = room.warmth + (target - room.warmth).clamp(-step, step);
+        if warmth == room.warmth {
+            continue;
+        }
+        room.warmth = warmth;
+        if let Some(material) = materials.get_mut(material) {
+            material.color = COOL_TINT.mix(&WARM_TINT, warmth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crates::{Crate, CrateData, CrateSpawns};
+    use crate::events::DamageEvent;
+    use crate::headless::build_headless_app;
+    use crate::input::{Action, ScriptedInput};
+    use crate::world::{BlockData, BlockKind};
+
+    /// Holds `action` until the player's center passes `x` going that way.
+    fn walk_to(app: &mut App, action: Action, x: f32) {
+        let mut players = app.world_mut().query_filtered::<&Position, With<Player>>();
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].press(action);
+        let right = action == Action::MoveRight;
+        for _ in 0..1440 {
+            app.update();
+            let at = players.single(app.world()).0.x;
+            if right && at >= x || !right && at <= x {
+                app.world_mut().resource_mut::<ScriptedInput>().actions[0].release(action);
+                return;
+            }
+        }
+        panic!("never got to {x}");
+    }
+
+    #[test]
+    fn a_crate_left_on_a_plate_stays_there_after_dying() {
+        // The room spans 300 to 700; the plate sits on the floor at 560.
+        let plate = BlockData {
+            kind: BlockKind::PressurePlate { channel: 1, latching: false },
+            ..BlockData::new(Vec2::new(560., -170.), Vec2::new(60., 10.))
+        };
+        let mut app = build_headless_app(WorldData(vec![BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.)), plate]));
+        let mut levels = app.world_mut().query_filtered::<Entity, With<WorldData>>();
+        let level = levels.single(app.world());
+        app.world_mut().entity_mut(level).insert((
+            SafeRoomSpawns(vec![SafeRoomData { position: Vec2::new(500., 0.), shape: Vec2::splat(400.) }]),
+            CrateSpawns(vec![CrateData { position: Vec2::new(360., -155.), shape: Vec2::splat(40.) }]),
+        ));
+        for _ in 0..144 {
+            app.update();
+        }
+        // Into the room, shoving the crate along onto the plate, and back out.
+        walk_to(&mut app, Action::MoveRight, 514.);
+        for _ in 0..72 {
+            app.update();
+        }
+        let mut crates = app.world_mut().query_filtered::<&Position, With<Crate>>();
+        let pushed = crates.single(app.world()).0;
+        assert!((pushed.x - 560.).abs() < 30., "pushed it to {pushed}, off the plate");
+        walk_to(&mut app, Action::MoveLeft, 200.);
+
+        let mut players = app.world_mut().query_filtered::<Entity, With<Player>>();
+        let player = players.single(app.world());
+        app.world_mut().send_event(DamageEvent::lethal(player));
+        for _ in 0..144 {
+            app.update();
+        }
+        let respawned = crates.single(app.world()).0;
+        assert!(respawned.distance(pushed) < 1., "back at {respawned} instead of {pushed}");
+    }
+}
@@ -0,0 +1,596 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::{fs, io};
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::coin::Score;
+use crate::events::{CheckpointActivated, LevelComplete};
+use crate::movement::Dash;
+use crate::player::{AirJumps, Player};
+use crate::stats::{GlobalStats, LevelStats, Stats};
+use crate::GameState;
+
+const TOGGLE_KEY: KeyCode = KeyCode::F5;
+pub const SLOT_COUNT: usize = 3;
+/// Levels a slot has to finish for 100%.
+const LEVEL_COUNT: usize = 2;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveSlot>()
+            .init_resource::<Profile>()
+            .init_resource::<SlotScreen>()
+            .add_systems(Startup, (load_active_slot, spawn_slot_screen))
+            .add_systems(PreUpdate, (navigate_slots, refresh_slot_screen).chain().after(InputSystem))
+            .add_systems(FixedPostUpdate, (record_completed_levels, save_on_checkpoint).chain())
+            .add_systems(Update, apply_abilities)
+            .add_systems(Last, save_on_exit);
+    }
+}
+
+/// Which of the `SLOT_COUNT` profiles is being played. Everything written to or read from
+/// disk about the player's progress goes through this; settings don't, they're shared.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ActiveSlot(pub usize);
+
+/// Moves the player can only use once a save has unlocked them.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct Abilities {
+    pub double_jump: bool,
+    pub dash: bool,
+}
+
+/// Both come unlocked on a new save for now; nothing in the levels hands them out yet.
+impl Default for Abilities {
+    fn default() -> Self {
+        Self { double_jump: true, dash: true }
+    }
+}
+
+/// What the active slot has got through: the levels finished, the most coins picked up
+/// on a finishing run of each, and the abilities unlocked. Best times are kept apart, in
+/// `saves/times.ron`, across every slot.
+#[derive(Resource, Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct Profile {
+    pub levels_completed: Vec<String>,
+    pub coins: BTreeMap<String, u32>,
+    pub abilities: Abilities,
+}
+
+impl Profile {
+    pub fn completed(&self, level: &str) -> bool {
+        self.levels_completed.iter().any(|completed| completed == level)
+    }
+}
+
+/// Bumped whenever `SaveData` changes shape, with a migration from the old one in
+/// `read_slot`.
+const SAVE_VERSION: u32 = 2;
+
+/// One slot's file on disk.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveData {
+    pub version: u32,
+    #[serde(default)]
+    pub stats: Stats,
+    #[serde(default)]
+    pub profile: Profile,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            stats: Stats::default(),
+            profile: Profile::default(),
+        }
+    }
+}
+
+impl SaveData {
+    pub fn completion(&self) -> f32 {
+        (self.profile.levels_completed.len() as f32 / LEVEL_COUNT as f32).min(1.)
+    }
+}
+
+/// The first save format, from before versions: no version field, and only the finished
+/// levels next to the stats.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SaveDataV1 {
+    stats: Stats,
+    levels_completed: Vec<String>,
+}
+
+impl From<SaveDataV1> for SaveData {
+    /// Every move was always available back then, so an old save keeps them all.
+    fn from(old: SaveDataV1) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            stats: old.stats,
+            profile: Profile {
+                levels_completed: old.levels_completed,
+                ..default()
+            },
+        }
+    }
+}
+
+/// Just enough of a save to tell which format the rest is in.
+#[derive(Deserialize)]
+struct VersionProbe {
+    #[serde(default = "first_version")]
+    version: u32,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+fn parse_save(text: &str) -> Result<SaveData, String> {
+    let probe: VersionProbe = ron::from_str(text).map_err(|error| error.to_string())?;
+    match probe.version {
+        1 => ron::from_str::<SaveDataV1>(text).map(SaveData::from).map_err(|error| error.to_string()),
+        SAVE_VERSION => ron::from_str(text).map_err(|error| error.to_string()),
+        version => Err(format!("it's version {version}, and this build reads up to {SAVE_VERSION}")),
+    }
+}
+
+#[derive(Clone, Debug)]
+enum SlotState {
+    Empty,
+    Saved(SaveData),
+    /// The file exists but couldn't be read, and couldn't be moved out of the way either.
+    /// It's left alone until the player deletes it, and the other slots don't care.
+    Corrupt,
+}
+
+/// Tests save under the temp dir instead, so they never touch the player's slots.
+fn save_dir() -> PathBuf {
+    if cfg!(test) {
+        std::env::temp_dir().join(format!("platformer-saves-{}", std::process::id()))
+    } else {
+        PathBuf::from("saves")
+    }
+}
+
+fn slot_path(slot: usize) -> PathBuf {
+    save_dir().join(format!("slot_{}.ron", slot + 1))
+}
+
+/// A file that doesn't parse, or is from a newer build, is renamed to `.ron.bak` and the
+/// slot starts over empty, so a bad save never stops the game and is never lost.
+fn read_slot(slot: usize) -> SlotState {
+    let path = slot_path(slot);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return SlotState::Empty,
+        Err(error) => {
+            warn!("couldn't read save slot {}: {error}", slot + 1);
+            return SlotState::Corrupt;
+        }
+    };
+    let error = match parse_save(&text) {
+        Ok(data) => return SlotState::Saved(data),
+        Err(error) => error,
+    };
+    let backup = path.with_extension("ron.bak");
+    match fs::rename(&path, &backup) {
+        Ok(()) => {
+            warn!("save slot {} can't be loaded ({error}); moved it to {} and starting fresh", slot + 1, backup.display());
+            SlotState::Empty
+        }
+        Err(rename_error) => {
+            warn!("save slot {} can't be loaded ({error}) or backed up: {rename_error}", slot + 1);
+            SlotState::Corrupt
+        }
+    }
+}
+
+/// Writes through a temporary file so a crash mid-write never leaves half a save behind.
+fn write_slot(slot: usize, data: &SaveData) -> io::Result<()> {
+    let text = ron::ser::to_string_pretty(data, default()).map_err(io::Error::other)?;
+    fs::create_dir_all(save_dir())?;
+    let path = slot_path(slot);
+    let temp = path.with_extension("ron.tmp");
+    fs::write(&temp, text)?;
+    fs::rename(temp, path)
+}
+
+fn delete_slot(slot: usize) -> io::Result<()> {
+    match fs::remove_file(slot_path(slot)) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+fn save_active(slot: ActiveSlot, global: &GlobalStats, profile: &Profile) {
+    let data = SaveData {
+        stats: global.0,
+        profile: profile.clone(),
+        ..default()
+    };
+    if let Err(error) = write_slot(slot.0, &data) {
+        warn!("couldn't save slot {}: {error}", slot.0 + 1);
+    }
+}
+
+fn load_active_slot(slot: Res<ActiveSlot>, mut global: ResMut<GlobalStats>, mut profile: ResMut<Profile>) {
+    if let SlotState::Saved(data) = read_slot(slot.0) {
+        global.0 = data.stats;
+        *profile = data.profile;
+    }
+}
+
+/// Hands the player the moves the profile has unlocked, when they spawn and whenever the
+/// profile changes, so a power-up raising `AirJumps::max` in between is left alone.
+fn apply_abilities(profile: Res<Profile>, mut player: Query<(Ref<Player>, &mut AirJumps, &mut Dash)>) {
+    for (added, mut air_jumps, mut dash) in &mut player {
+        if !profile.is_changed() && !added.is_added() {
+            continue;
+        }
+        air_jumps.max = u8::from(profile.abilities.double_jump);
+        air_jumps.remaining = air_jumps.remaining.min(air_jumps.max);
+        dash.locked = !profile.abilities.dash;
+    }
+}
+
+fn save_on_checkpoint(
+    mut checkpoints: EventReader<CheckpointActivated>,
+    slot: Res<ActiveSlot>,
+    global: Res<GlobalStats>,
+    profile: Res<Profile>,
+) {
+    if checkpoints.read().count() > 0 {
+        save_active(*slot, &global, &profile);
+    }
+}
+
+/// Finishing a level saves right away, like reaching a checkpoint, along with the coins
+/// picked up on the way if they beat the level's best.
+fn record_completed_levels(
+    mut complete: EventReader<LevelComplete>,
+    slot: Res<ActiveSlot>,
+    global: Res<GlobalStats>,
+    score: Res<Score>,
+    mut profile: ResMut<Profile>,
+) {
+    let mut finished_any = false;
+    for event in complete.read() {
+        if !profile.completed(&event.level) {
+            profile.levels_completed.push(event.level.clone());
+        }
+        let coins = profile.coins.entry(event.level.clone()).or_default();
+        *coins = (*coins).max(score.0);
+        finished_any = true;
+    }
+    if finished_any {
+        save_active(*slot, &global, &profile);
+    }
+}
+
+fn save_on_exit(
+    mut exit: EventReader<AppExit>,
+    slot: Res<ActiveSlot>,
+    global: Res<GlobalStats>,
+    profile: Res<Profile>,
+) {
+    if exit.read().count() > 0 {
+        save_active(*slot, &global, &profile);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+enum SlotMode {
+    #[default]
+    Browsing,
+    /// Picking where to copy `from` to.
+    CopyingFrom(usize),
+    ConfirmCopy { from: usize, to: usize },
+    ConfirmDelete(usize),
+}
+
+/// The F5 save-slot screen. Like the controls screen it pauses the game and swallows
+/// every key and button press while it's open.
+#[derive(Resource, Default)]
+struct SlotScreen {
+    open: bool,
+    selected: usize,
+    mode: SlotMode,
+    /// What each slot's file held when the screen last looked.
+    slots: Vec<SlotState>,
+    /// Shown under the slots until the next action.
+    message: Option<String>,
+    was_paused: bool,
+}
+
+impl SlotScreen {
+    fn reread(&mut self) {
+        self.slots = (0..SLOT_COUNT).map(read_slot).collect();
+    }
+}
+
+#[derive(Component)]
+struct SlotPanel;
+
+#[derive(Component)]
+struct SlotText;
+
+fn spawn_slot_screen(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: Color::srgba(0., 0., 0., 0.6).into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Global(30),
+        ..default()
+    }, SlotPanel)).with_children(|panel| {
+        panel.spawn((TextBundle::from_section("", TextStyle {
+            font_size: 20.,
+            ..default()
+        }), SlotText));
+    });
+}
+
+fn navigate_slots(
+    mut screen: ResMut<SlotScreen>,
+    mut slot: ResMut<ActiveSlot>,
+    mut global: ResMut<GlobalStats>,
+    mut level: ResMut<LevelStats>,
+    mut profile: ResMut<Profile>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut buttons: ResMut<ButtonInput<GamepadButton>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut panel: Query<&mut Visibility, With<SlotPanel>>,
+) {
+    if !screen.open {
+        if !keys.just_pressed(TOGGLE_KEY) {
+            return;
+        }
+        // Save first so the active slot's summary is current.
+        save_active(*slot, &global, &profile);
+        screen.open = true;
+        screen.selected = slot.0;
+        screen.mode = SlotMode::Browsing;
+        screen.message = None;
+        screen.reread();
+        screen.was_paused = time.is_paused();
+        time.pause();
+        for mut visibility in &mut panel {
+            *visibility = Visibility::Visible;
+        }
+        keys.reset_all();
+        buttons.reset_all();
+        return;
+    }
+
+    let pressed_button = |buttons: &ButtonInput<GamepadButton>, button_type: GamepadButtonType| {
+        buttons.get_just_pressed().any(|button| button.button_type == button_type)
+    };
+    let confirm = keys.just_pressed(KeyCode::Enter) || pressed_button(&buttons, GamepadButtonType::South);
+    let cancel = keys.just_pressed(KeyCode::Escape) || pressed_button(&buttons, GamepadButtonType::East);
+    // Switching slots restarts the level for the new profile; on the main menu there's
+    // no level to restart.
+    let in_level = matches!(state.get(), GameState::Playing | GameState::Paused);
+    let mut close = false;
+    match screen.mode {
+        SlotMode::Browsing | SlotMode::CopyingFrom(_) => {
+            let selected = screen.selected;
+            if keys.just_pressed(KeyCode::ArrowUp) || pressed_button(&buttons, GamepadButtonType::DPadUp) {
+                screen.selected = (selected + SLOT_COUNT - 1) % SLOT_COUNT;
+            } else if keys.just_pressed(KeyCode::ArrowDown) || pressed_button(&buttons, GamepadButtonType::DPadDown) {
+                screen.selected = (selected + 1) % SLOT_COUNT;
+            } else if let SlotMode::CopyingFrom(from) = screen.mode {
+                if cancel {
+                    screen.mode = SlotMode::Browsing;
+                } else if confirm && selected != from {
+                    screen.mode = SlotMode::ConfirmCopy { from, to: selected };
+                }
+            } else if cancel || keys.just_pressed(TOGGLE_KEY) {
+                close = true;
+            } else if confirm && matches!(screen.slots[selected], SlotState::Corrupt) {
+                screen.message = Some(format!("Slot {} can't be read. Delete it to start over.", selected + 1));
+            } else if confirm {
+                if selected != slot.0 {
+                    // Nothing from the old slot carries over, saved or not.
+                    *global = GlobalStats::default();
+                    *level = LevelStats::default();
+                    *profile = Profile::default();
+                    if let SlotState::Saved(data) = &screen.slots[selected] {
+                        global.0 = data.stats;
+                        *profile = data.profile.clone();
+                    }
+                    slot.0 = selected;
+                    if in_level {
+                        next_state.set(GameState::Restarting);
+                    }
+                }
+                close = true;
+            } else if keys.just_pressed(KeyCode::KeyC) || pressed_button(&buttons, GamepadButtonType::West) {
+                screen.mode = if matches!(screen.slots[selected], SlotState::Saved(_)) {
+                    SlotMode::CopyingFrom(selected)
+                } else {
+                    screen.message = Some(format!("Slot {} has nothing to copy.", selected + 1));
+                    SlotMode::Browsing
+                };
+            } else if (keys.just_pressed(KeyCode::Delete) || pressed_button(&buttons, GamepadButtonType::North))
+                && !matches!(screen.slots[selected], SlotState::Empty) {
+                screen.mode = SlotMode::ConfirmDelete(selected);
+            }
+        }
+        SlotMode::ConfirmCopy { from, to } => {
+            if confirm {
+                let result = match &screen.slots[from] {
+                    SlotState::Saved(data) => write_slot(to, data),
+                    _ => Ok(()),
+                };
+                screen.message = Some(match result {
+                    Ok(()) => format!("Copied slot {} to slot {}.", from + 1, to + 1),
+                    Err(error) => format!("Couldn't copy: {error}"),
+                });
+                if to == slot.0 {
+                    if let SlotState::Saved(data) = &screen.slots[from] {
+                        global.0 = data.stats;
+                        *profile = data.profile.clone();
+                    }
+                }
+                screen.mode = SlotMode::Browsing;
+                screen.reread();
+            } else if cancel {
+                screen.mode = SlotMode::Browsing;
+            }
+        }
+        SlotMode::ConfirmDelete(target) => {
+            if confirm {
+                screen.message = Some(match delete_slot(target) {
+                    Ok(()) => format!("Deleted slot {}.", target + 1),
+                    Err(error) => format!("Couldn't delete: {error}"),
+                });
+                if target == slot.0 {
+                    *global = GlobalStats::default();
+                    *level = LevelStats::default();
+                    *profile = Profile::default();
+                    if in_level {
+                        next_state.set(GameState::Restarting);
+                    }
+                }
+                screen.mode = SlotMode::Browsing;
+                screen.reread();
+            } else if cancel {
+                screen.mode = SlotMode::Browsing;
+            }
+        }
+    }
+
+    if close {
+        screen.open = false;
+        if !screen.was_paused {
+            time.unpause();
+        }
+        for mut visibility in &mut panel {
+            *visibility = Visibility::Hidden;
+        }
+    }
+    keys.reset_all();
+    buttons.reset_all();
+}
+
+fn format_playtime(secs: f32) -> String {
+    let secs = secs as u32;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn refresh_slot_screen(
+    screen: Res<SlotScreen>,
+    slot: Res<ActiveSlot>,
+    mut text: Query<&mut Text, With<SlotText>>,
+) {
+    if !screen.is_changed() && !slot.is_changed() {
+        return;
+    }
+    let mut value = String::from("SAVE SLOTS\n\n");
+    for (index, state) in screen.slots.iter().enumerate() {
+        let cursor = if index == screen.selected { ">" } else { " " };
+        let active = if index == slot.0 { "*" } else { " " };
+        let summary = match state {
+            SlotState::Empty => "empty".to_string(),
+            SlotState::Corrupt => "unreadable".to_string(),
+            SlotState::Saved(data) => format!(
+                "{} level(s) done   {}   {:.0}%",
+                data.profile.levels_completed.len(),
+                format_playtime(data.stats.playtime),
+                data.completion() * 100.,
+            ),
+        };
+        value += &format!("{cursor}{active} Slot {}   {summary}\n", index + 1);
+    }
+    value += "\n";
+    if let Some(message) = &screen.message {
+        value += &format!("{message}\n\n");
+    }
+    value += &match screen.mode {
+        SlotMode::Browsing => "Enter: play   C: copy   Delete: delete   Esc: close".to_string(),
+        SlotMode::CopyingFrom(from) => format!("Copy slot {} to which slot? Enter to pick, Esc to cancel", from + 1),
+        SlotMode::ConfirmCopy { from, to } => format!(
+            "Overwrite slot {} with slot {}? Enter to confirm, Esc to cancel", to + 1, from + 1,
+        ),
+        SlotMode::ConfirmDelete(target) => format!(
+            "Delete slot {} for good? Enter to confirm, Esc to cancel", target + 1,
+        ),
+    };
+    for mut text in &mut text {
+        text.sections[0].value = value.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::state::app::StatesPlugin;
+
+    use super::*;
+
+    fn slot_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .init_state::<GameState>()
+            .init_resource::<ActiveSlot>()
+            .init_resource::<Profile>()
+            .init_resource::<SlotScreen>()
+            .init_resource::<GlobalStats>()
+            .init_resource::<LevelStats>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<GamepadButton>>()
+            .add_systems(Update, navigate_slots);
+        app
+    }
+
+    fn press(app: &mut App, key: KeyCode) {
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(key);
+        app.update();
+    }
+
+    #[test]
+    fn switching_to_an_empty_slot_starts_from_defaults() {
+        for slot in 0..SLOT_COUNT {
+            delete_slot(slot).unwrap();
+        }
+        let mut app = slot_app();
+        app.world_mut().resource_mut::<Profile>().levels_completed.push("level1".into());
+        app.world_mut().resource_mut::<GlobalStats>().0.deaths = 7;
+        app.world_mut().resource_mut::<LevelStats>().0.jumps = 3;
+
+        // Opening the screen saves slot 1; the second slot down is empty.
+        press(&mut app, TOGGLE_KEY);
+        press(&mut app, KeyCode::ArrowDown);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(*app.world().resource::<ActiveSlot>(), ActiveSlot(1));
+        assert!(app.world().resource::<Profile>().levels_completed.is_empty());
+        assert_eq!(app.world().resource::<GlobalStats>().0.deaths, 0);
+        assert_eq!(app.world().resource::<LevelStats>().0.jumps, 0);
+
+        // And slot 1 still has everything to go back to.
+        press(&mut app, TOGGLE_KEY);
+        press(&mut app, KeyCode::ArrowUp);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(*app.world().resource::<ActiveSlot>(), ActiveSlot(0));
+        assert!(app.world().resource::<Profile>().completed("level1"));
+        assert_eq!(app.world().resource::<GlobalStats>().0.deaths, 7);
+
+        fs::remove_dir_all(save_dir()).unwrap();
+    }
+}
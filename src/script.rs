@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+
+use crate::events::ScriptTriggerFired;
+use crate::level::{Downed, LevelState};
+use crate::origin::WorldOrigin;
+use crate::physics::{handle_collisions, move_bodies, Position};
+use crate::player::Player;
+use crate::spawn_zone::Zone;
+use crate::timer::GameTimer;
+use crate::world::WorldData;
+
+const HINT_SECS: f32 = 4.;
+
+pub struct ScriptPlugin;
+
+impl Plugin for ScriptPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveHint>()
+            .add_systems(Startup, spawn_hint)
+            .add_systems(FixedUpdate, (
+                fire_script_triggers.after(move_bodies).before(handle_collisions),
+                show_trigger_hints.after(fire_script_triggers),
+            ))
+            .add_systems(Update, sync_hint);
+    }
+}
+
+/// Sends `ScriptTriggerFired` with `id` when the player's center enters `zone`. Several
+/// volumes can share an id to cover an odd shape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptTrigger {
+    pub id: String,
+    pub zone: Zone,
+    /// Only fire the first time per level load, even across respawns.
+    pub once: bool,
+}
+
+/// Script triggers placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component, Default)]
+pub struct ScriptTriggers(pub Vec<ScriptTrigger>);
+
+/// Tutorial text popped up by script triggers, as (trigger id, text). Hints don't stop
+/// the player the way a cutscene caption does.
+#[derive(Component, Default)]
+pub struct TriggerHints(pub Vec<(String, String)>);
+
+/// Fires each trigger on the tick a player steps into it. `inside` remembers which ones,
+/// by index into `ScriptTriggers`, a player was already in, so the second player
+/// following the first in doesn't fire it again.
+pub fn fire_script_triggers(
+    world_data: Query<&ScriptTriggers, With<WorldData>>,
+    players: Query<&Position, (With<Player>, Without<Downed>)>,
+    mut level_state: ResMut<LevelState>,
+    mut fired: EventWriter<ScriptTriggerFired>,
+    mut inside: Local<Vec<bool>>,
+    origin: Res<WorldOrigin>,
+) {
+    let Ok(triggers) = world_data.get_single() else {
+        return;
+    };
+    inside.resize(triggers.0.len(), false);
+    for (trigger, was_inside) in triggers.0.iter().zip(inside.iter_mut()) {
+        let now_inside = players.iter().any(|player_pos| trigger.zone.contains(origin.to_level(player_pos.0)));
+        let entered = now_inside && !*was_inside;
+        *was_inside = now_inside;
+        if !entered {
+            continue;
+        }
+        let first = level_state.fired_scripts.insert(trigger.id.clone());
+        if first || !trigger.once {
+            fired.send(ScriptTriggerFired { id: trigger.id.clone() });
+        }
+    }
+}
+
+/// The tutorial hint on screen, if any.
+#[derive(Resource, Default)]
+struct ActiveHint {
+    text: String,
+    timer: GameTimer,
+}
+
+#[derive(Component)]
+struct HintText;
+
+fn spawn_hint(mut commands: Commands) {
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            top: Val::Px(64.),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    }).with_children(|root| {
+        root.spawn((TextBundle::from_section("", TextStyle {
+            font_size: 24.,
+            ..default()
+        }), HintText));
+    });
+}
+
+fn show_trigger_hints(
+    mut fired: EventReader<ScriptTriggerFired>,
+    world_data: Query<&TriggerHints, With<WorldData>>,
+    mut hint: ResMut<ActiveHint>,
+    time: Res<Time>,
+) {
+    if hint.timer.tick(time.delta_seconds()).just_finished() {
+        hint.text.clear();
+    }
+    let Ok(hints) = world_data.get_single() else {
+        return;
+    };
+    for event in fired.read() {
+        if let Some((_, text)) = hints.0.iter().find(|(id, _)| *id == event.id) {
+            hint.text.clone_from(text);
+            hint.timer = GameTimer::once(HINT_SECS);
+        }
+    }
+}
+
+fn sync_hint(hint: Res<ActiveHint>, mut text: Query<&mut Text, With<HintText>>) {
+    if !hint.is_changed() {
+        return;
+    }
+    for mut text in &mut text {
+        if text.sections[0].value != hint.text {
+            text.sections[0].value.clone_from(&hint.text);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::{fs, io};
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow, WindowMode};
+use serde::{Deserialize, Serialize};
+
+use crate::input::{Action, BindingNames, InputConfig, InputMap};
+use crate::GameState;
+
+const SETTINGS_PATH: &str = "config/settings.ron";
+/// Opens the settings screen from the pause menu.
+const OPEN_KEY: KeyCode = KeyCode::KeyS;
+const VOLUME_STEP: f32 = 0.1;
+/// Tick rates the settings screen steps through.
+const PHYSICS_RATES: [f64; 4] = [60., 120., 144., 240.];
+const ROWS: usize = 9;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Settings::load())
+            .init_resource::<SettingsScreen>()
+            .add_systems(Startup, (apply_saved_bindings, spawn_settings_screen))
+            .add_systems(PreUpdate, (navigate_settings, refresh_settings_screen).chain().after(InputSystem))
+            .add_systems(Update, (
+                record_bindings,
+                apply_settings.run_if(resource_changed::<Settings>),
+                save_settings.run_if(resource_changed::<Settings>).run_if(not(resource_added::<Settings>)),
+            ).chain());
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl DisplayMode {
+    fn next(self) -> Self {
+        match self {
+            DisplayMode::Windowed => DisplayMode::Borderless,
+            DisplayMode::Borderless => DisplayMode::Fullscreen,
+            DisplayMode::Fullscreen => DisplayMode::Windowed,
+        }
+    }
+
+    fn previous(self) -> Self {
+        self.next().next()
+    }
+}
+
+/// Options the player picks, as opposed to the tuning that ships with the game. Saved to
+/// `config/settings.ron` whenever one changes and read back at startup; anything the
+/// file leaves out keeps its default.
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Scales every sound and the music, from 0 (silent) to 1.
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub vsync: bool,
+    pub display_mode: DisplayMode,
+    /// Fixed ticks per second for physics and gameplay.
+    pub physics_hz: f64,
+    /// Dying puts the level timer back to zero, so only deathless runs set a time.
+    pub reset_timer_on_death: bool,
+    /// A second player joins, on the arrow keys and number pad or the second gamepad.
+    pub two_players: bool,
+    /// The first player aims shots and the grapple at the mouse.
+    pub mouse_aim: bool,
+    /// Bindings changed on the controls screen, layered over the defaults and
+    /// `config/input.ron`.
+    pub bindings: BTreeMap<Action, BindingNames>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.,
+            music_volume: 1.,
+            sfx_volume: 1.,
+            vsync: true,
+            display_mode: DisplayMode::Windowed,
+            physics_hz: 144.,
+            reset_timer_on_death: false,
+            two_players: false,
+            mouse_aim: false,
+            bindings: BTreeMap::new(),
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+impl Settings {
+    pub fn music_gain(&self) -> f32 {
+        self.master_volume * self.music_volume
+    }
+
+    pub fn sfx_gain(&self) -> f32 {
+        self.master_volume * self.sfx_volume
+    }
+
+    /// A missing file is just the defaults; a broken one gets a warning.
+    fn load() -> Self {
+        let text = match fs::read_to_string(SETTINGS_PATH) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(error) => {
+                warn!("couldn't read {SETTINGS_PATH}: {error}");
+                return Self::default();
+            }
+        };
+        ron::from_str(&text).unwrap_or_else(|error| {
+            warn!("{SETTINGS_PATH} is broken, using the default settings: {error}");
+            Self::default()
+        })
+    }
+
+    /// Writes through a temporary file, like the save slots, so a crash mid-write keeps
+    /// the old settings.
+    fn save(&self) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, default()).map_err(io::Error::other)?;
+        let path = Path::new(SETTINGS_PATH);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp = path.with_extension("ron.tmp");
+        fs::write(&temp, text)?;
+        fs::rename(temp, path)
+    }
+}
+
+/// Runs once the controls plugin has loaded `config/input.ron`, so rebinds made in game
+/// win over it.
+fn apply_saved_bindings(settings: Res<Settings>, mut map: ResMut<InputMap>) {
+    if !settings.bindings.is_empty() {
+        map.rebind(|action| settings.bindings.get(&action), SETTINGS_PATH);
+    }
+}
+
+/// Keeps `Settings::bindings` in step with the controls screen. The map as loaded at
+/// startup is already what the settings say, so it isn't written back.
+fn record_bindings(map: Res<InputMap>, mut settings: ResMut<Settings>) {
+    if !map.is_changed() || map.is_added() {
+        return;
+    }
+    let names = map.names();
+    if settings.bindings != names {
+        settings.bindings = names;
+    }
+}
+
+/// Changing the tick rate keeps the clock's accumulated time, so no tick is lost or
+/// doubled on the switch.
+fn apply_settings(
+    settings: Res<Settings>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+    mut fixed: ResMut<Time<Fixed>>,
+    mut input: ResMut<InputConfig>,
+) {
+    input.two_players = settings.two_players;
+    input.mouse_aim = settings.mouse_aim;
+    if let Ok(mut window) = window.get_single_mut() {
+        window.mode = match settings.display_mode {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Borderless => WindowMode::BorderlessFullscreen,
+            DisplayMode::Fullscreen => WindowMode::Fullscreen,
+        };
+        window.present_mode = if settings.vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync };
+    }
+    let hz = settings.physics_hz.clamp(30., 480.);
+    if (fixed.timestep().as_secs_f64() * hz - 1.).abs() > 1e-9 {
+        fixed.set_timestep_hz(hz);
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    if let Err(error) = settings.save() {
+        warn!("couldn't save {SETTINGS_PATH}: {error}");
+    }
+}
+
+/// The settings screen, opened with S from the pause menu.
+#[derive(Resource, Default)]
+struct SettingsScreen {
+    open: bool,
+    selected: usize,
+}
+
+#[derive(Component)]
+struct SettingsPanel;
+
+#[derive(Component)]
+struct SettingsText;
+
+fn spawn_settings_screen(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: Color::srgba(0., 0., 0., 0.9).into(),
+        visibility: Visibility::Hidden,
+        // Above the pause menu it opens from.
+        z_index: ZIndex::Global(31),
+        ..default()
+    }, SettingsPanel)).with_children(|panel| {
+        panel.spawn((TextBundle::from_section("", TextStyle {
+            font_size: 20.,
+            ..default()
+        }), SettingsText));
+    });
+}
+
+fn step_volume(volume: &mut f32, direction: f32) {
+    *volume = ((*volume + direction * VOLUME_STEP) * 10.).round().clamp(0., 10.) / 10.;
+}
+
+/// Like the controls screen, every key and button pressed while it's open stops here, so
+/// Esc closing it doesn't also resume the game.
+fn navigate_settings(
+    mut screen: ResMut<SettingsScreen>,
+    mut settings: ResMut<Settings>,
+    state: Res<State<GameState>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut buttons: ResMut<ButtonInput<GamepadButton>>,
+    mut panel: Query<&mut Visibility, With<SettingsPanel>>,
+) {
+    let pressed_button = |buttons: &ButtonInput<GamepadButton>, button_type: GamepadButtonType| {
+        buttons.get_just_pressed().any(|button| button.button_type == button_type)
+    };
+    if !screen.open {
+        let open = keys.just_pressed(OPEN_KEY) || pressed_button(&buttons, GamepadButtonType::North);
+        if *state.get() != GameState::Paused || !open {
+            return;
+        }
+        screen.open = true;
+        screen.selected = 0;
+        for mut visibility in &mut panel {
+            *visibility = Visibility::Visible;
+        }
+        keys.reset_all();
+        buttons.reset_all();
+        return;
+    }
+
+    let direction = if keys.just_pressed(KeyCode::ArrowRight) || pressed_button(&buttons, GamepadButtonType::DPadRight) {
+        1.
+    } else if keys.just_pressed(KeyCode::ArrowLeft) || pressed_button(&buttons, GamepadButtonType::DPadLeft) {
+        -1.
+    } else {
+        0.
+    };
+    if keys.just_pressed(KeyCode::Escape) || keys.just_pressed(OPEN_KEY) || pressed_button(&buttons, GamepadButtonType::East) {
+        screen.open = false;
+        for mut visibility in &mut panel {
+            *visibility = Visibility::Hidden;
+        }
+    } else if keys.just_pressed(KeyCode::ArrowUp) || pressed_button(&buttons, GamepadButtonType::DPadUp) {
+        screen.selected = (screen.selected + ROWS - 1) % ROWS;
+    } else if keys.just_pressed(KeyCode::ArrowDown) || pressed_button(&buttons, GamepadButtonType::DPadDown) {
+        screen.selected = (screen.selected + 1) % ROWS;
+    } else if direction != 0. {
+        match screen.selected {
+            0 => step_volume(&mut settings.master_volume, direction),
+            1 => step_volume(&mut settings.music_volume, direction),
+            2 => step_volume(&mut settings.sfx_volume, direction),
+            3 => settings.vsync = !settings.vsync,
+            4 => {
+                settings.display_mode = if direction > 0. { settings.display_mode.next() } else { settings.display_mode.previous() };
+            }
+            6 => settings.reset_timer_on_death = !settings.reset_timer_on_death,
+            7 => settings.two_players = !settings.two_players,
+            8 => settings.mouse_aim = !settings.mouse_aim,
+            _ => {
+                // A rate from the file that isn't in the list steps to its neighbours.
+                let current = settings.physics_hz;
+                let next = if direction > 0. {
+                    PHYSICS_RATES.iter().find(|rate| **rate > current).or(PHYSICS_RATES.first())
+                } else {
+                    PHYSICS_RATES.iter().rev().find(|rate| **rate < current).or(PHYSICS_RATES.last())
+                };
+                settings.physics_hz = *next.unwrap_or(&current);
+            }
+        }
+    }
+    keys.reset_all();
+    buttons.reset_all();
+}
+
+fn refresh_settings_screen(
+    screen: Res<SettingsScreen>,
+    settings: Res<Settings>,
+    mut text: Query<&mut Text, With<SettingsText>>,
+) {
+    if !screen.is_changed() && !settings.is_changed() {
+        return;
+    }
+    let percent = |volume: f32| format!("{:.0}%", volume * 100.);
+    let rows = [
+        ("Master volume", percent(settings.master_volume)),
+        ("Music volume", percent(settings.music_volume)),
+        ("Effects volume", percent(settings.sfx_volume)),
+        ("VSync", if settings.vsync { "on" } else { "off" }.to_string()),
+        ("Display", format!("{:?}", settings.display_mode)),
+        ("Physics rate", format!("{} Hz", settings.physics_hz)),
+        ("Timer resets on death", if settings.reset_timer_on_death { "on" } else { "off" }.to_string()),
+        ("Two players", if settings.two_players { "on" } else { "off" }.to_string()),
+        ("Mouse aim", if settings.mouse_aim { "on" } else { "off" }.to_string()),
+    ];
+    let mut value = String::from("SETTINGS\n\n");
+    for (row, (name, setting)) in rows.iter().enumerate() {
+        let cursor = if row == screen.selected { ">" } else { " " };
+        value += &format!("{cursor} {name:<22} < {setting} >\n");
+    }
+    value += "\nArrows: choose and change   Esc: back";
+    for mut text in &mut text {
+        text.sections[0].value = value.clone();
+    }
+}
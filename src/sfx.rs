@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+
+use crate::coin::collect_coins;
+use crate::damage::apply_damage;
+use crate::events::{BlockBroken, CoinCollected, Damaged, Jumped, Landed};
+use crate::physics::{PostCollide, Position};
+
+#[cfg(feature = "audio")]
+mod imp;
+
+/// Landings slower than this, in pixels per second, don't make a sound.
+const LAND_SOUND_MIN_SPEED: f32 = 432.;
+/// Landing speed that plays the thud at full volume; slower ones are quieter.
+const LAND_SOUND_FULL_SPEED: f32 = 1728.;
+
+pub struct SfxPlugin;
+
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlaySfxAt>()
+            .add_systems(FixedUpdate, sfx_for_gameplay_events
+                .after(PostCollide)
+                .after(apply_damage)
+                .after(collect_coins));
+        // Without audio, sounds are still requested but nothing plays them.
+        #[cfg(feature = "audio")]
+        app.add_plugins(imp::AudioPlaybackPlugin);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SfxKind {
+    CannonFire,
+    ProjectileBounce,
+    MagnetHum,
+    SkidScrape,
+    SkidSqueal,
+    Respawn,
+    Jump,
+    Land,
+    Coin,
+    Hurt,
+    BlockBreak,
+}
+
+/// A sound emitted somewhere in the world, panned and attenuated relative to the camera.
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+#[derive(Event)]
+pub struct PlaySfxAt {
+    pub kind: SfxKind,
+    pub position: Vec2,
+    /// Scales the volume before distance attenuation, from 0 to 1.
+    pub volume: f32,
+}
+
+impl PlaySfxAt {
+    pub fn new(kind: SfxKind, position: Vec2) -> Self {
+        Self { kind, position, volume: 1. }
+    }
+}
+
+/// Keeps a sound looping on this entity for as long as it exists, or until it's removed.
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+#[derive(Component)]
+pub struct LoopingSfx {
+    pub kind: SfxKind,
+    update_timer: Timer,
+}
+
+impl LoopingSfx {
+    pub fn new(kind: SfxKind) -> Self {
+        Self {
+            kind,
+            update_timer: Timer::from_seconds(0., TimerMode::Repeating),
+        }
+    }
+}
+
+/// Turns gameplay events into sounds where they happened. A landing thuds louder the
+/// harder it hits, and soft ones, like stepping down a stair, stay quiet.
+fn sfx_for_gameplay_events(
+    mut jumped: EventReader<Jumped>,
+    mut landed: EventReader<Landed>,
+    mut coins: EventReader<CoinCollected>,
+    mut damaged: EventReader<Damaged>,
+    mut broken: EventReader<BlockBroken>,
+    positions: Query<&Position>,
+    mut sfx: EventWriter<PlaySfxAt>,
+) {
+    for event in jumped.read() {
+        if let Ok(position) = positions.get(event.entity) {
+            sfx.send(PlaySfxAt::new(SfxKind::Jump, position.0));
+        }
+    }
+    for event in landed.read() {
+        let Ok(position) = positions.get(event.entity) else {
+            continue;
+        };
+        if event.speed >= LAND_SOUND_MIN_SPEED {
+            sfx.send(PlaySfxAt {
+                volume: (event.speed / LAND_SOUND_FULL_SPEED).min(1.),
+                ..PlaySfxAt::new(SfxKind::Land, position.0)
+            });
+        }
+    }
+    for event in coins.read() {
+        sfx.send(PlaySfxAt::new(SfxKind::Coin, event.position));
+    }
+    for event in damaged.read() {
+        if let Ok(position) = positions.get(event.entity) {
+            sfx.send(PlaySfxAt::new(SfxKind::Hurt, position.0));
+        }
+    }
+    for event in broken.read() {
+        sfx.send(PlaySfxAt::new(SfxKind::BlockBreak, event.position));
+    }
+}
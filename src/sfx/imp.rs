@@ -0,0 +1,287 @@
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::audio::{AddAudioSource, AudioSinkPlayback, Decodable, Source, SpatialAudioSink, SpatialScale, Volume};
+use bevy::prelude::*;
+
+use crate::camera::Camera;
+use crate::physics::Position;
+use crate::settings::Settings;
+
+use super::{LoopingSfx, PlaySfxAt, SfxKind};
+
+const SAMPLE_RATE: u32 = 44_100;
+/// Spatial playback only provides the stereo pan. Shrinking world distances to almost
+/// nothing keeps rodio's own distance attenuation out of the way of `AudioConfig`'s.
+const PAN_ONLY_SCALE: f32 = 1e-4;
+/// Where recorded sounds that replace the synthesized ones live, under `assets/`.
+const SOUND_DIR: &str = "sounds";
+
+pub struct AudioPlaybackPlugin;
+
+impl Plugin for AudioPlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_source::<Tone>()
+            .init_resource::<AudioConfig>()
+            .add_systems(Startup, (load_sfx, attach_listener.after(crate::camera::spawn_camera)))
+            .add_systems(Update, (play_positional_sfx, stop_looping_sfx, start_looping_sfx, attenuate_looping_sfx).chain());
+    }
+}
+
+/// How world sounds fade with distance from the camera. Distances are in screen widths.
+#[derive(Resource)]
+pub struct AudioConfig {
+    pub screen_width: f32,
+    /// Sounds closer than this play at full volume.
+    pub full_volume_within: f32,
+    /// Sounds further than this aren't heard at all.
+    pub silent_beyond: f32,
+    /// Shape of the fade between the two; 1 is linear, higher drops off sooner.
+    pub falloff_exponent: f32,
+    /// Seconds between volume updates for looping emitters.
+    pub loop_update_interval: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            screen_width: 1280.,
+            full_volume_within: 0.5,
+            silent_beyond: 1.5,
+            falloff_exponent: 2.,
+            loop_update_interval: 0.1,
+        }
+    }
+}
+
+impl AudioConfig {
+    /// Volume for a sound `offset` away from the camera.
+    pub fn attenuation(&self, offset: Vec2) -> f32 {
+        let screens = offset.length() / self.screen_width;
+        let fade = (screens - self.full_volume_within) / (self.silent_beyond - self.full_volume_within);
+        (1. - fade.clamp(0., 1.)).powf(self.falloff_exponent)
+    }
+}
+
+impl SfxKind {
+    fn tone(self) -> Tone {
+        match self {
+            SfxKind::CannonFire => Tone { frequency: 90., secs: 0.35, decay: 10. },
+            SfxKind::ProjectileBounce => Tone { frequency: 660., secs: 0.08, decay: 40. },
+            // A whole number of cycles per second, so the loop seam is silent.
+            SfxKind::MagnetHum => Tone { frequency: 110., secs: 1., decay: 0. },
+            SfxKind::SkidScrape => Tone { frequency: 220., secs: 1., decay: 0. },
+            SfxKind::SkidSqueal => Tone { frequency: 880., secs: 1., decay: 0. },
+            SfxKind::Respawn => Tone { frequency: 520., secs: 0.4, decay: 6. },
+            SfxKind::Jump => Tone { frequency: 440., secs: 0.12, decay: 25. },
+            SfxKind::Land => Tone { frequency: 70., secs: 0.15, decay: 25. },
+            SfxKind::Coin => Tone { frequency: 1320., secs: 0.15, decay: 20. },
+            SfxKind::Hurt => Tone { frequency: 180., secs: 0.25, decay: 12. },
+            SfxKind::BlockBreak => Tone { frequency: 130., secs: 0.2, decay: 18. },
+        }
+    }
+
+    /// The OGG under `assets/sounds/` that plays instead of the tone, for the sounds
+    /// that can have one.
+    fn file(self) -> Option<&'static str> {
+        match self {
+            SfxKind::Jump => Some("jump.ogg"),
+            SfxKind::Land => Some("land.ogg"),
+            SfxKind::Coin => Some("coin.ogg"),
+            SfxKind::Hurt => Some("hurt.ogg"),
+            _ => None,
+        }
+    }
+}
+
+/// A synthesized sine blip, so sounds work without any audio files.
+#[derive(Asset, TypePath, Clone, Copy)]
+pub struct Tone {
+    pub frequency: f32,
+    pub secs: f32,
+    /// Exponential fade-out rate; zero holds the volume steady.
+    pub decay: f32,
+}
+
+pub struct ToneDecoder {
+    tone: Tone,
+    sample: u32,
+}
+
+impl Iterator for ToneDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let t = self.sample as f32 / SAMPLE_RATE as f32;
+        if t >= self.tone.secs {
+            return None;
+        }
+        self.sample += 1;
+        Some((std::f32::consts::TAU * self.tone.frequency * t).sin() * (-self.tone.decay * t).exp() * 0.3)
+    }
+}
+
+impl Source for ToneDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(self.tone.secs))
+    }
+}
+
+impl Decodable for Tone {
+    type DecoderItem = f32;
+    type Decoder = ToneDecoder;
+
+    fn decoder(&self) -> ToneDecoder {
+        ToneDecoder { tone: *self, sample: 0 }
+    }
+}
+
+#[derive(Resource)]
+struct SfxLibrary(Vec<(SfxKind, Handle<Tone>)>);
+
+impl SfxLibrary {
+    fn get(&self, kind: SfxKind) -> Option<Handle<Tone>> {
+        self.0.iter().find(|(k, _)| *k == kind).map(|(_, handle)| handle.clone())
+    }
+}
+
+/// Recorded sounds found in `assets/sounds/` at startup. Any kind without one here plays
+/// its tone from the `SfxLibrary`.
+#[derive(Resource)]
+struct SoundHandles(Vec<(SfxKind, Handle<AudioSource>)>);
+
+impl SoundHandles {
+    fn get(&self, kind: SfxKind) -> Option<Handle<AudioSource>> {
+        self.0.iter().find(|(k, _)| *k == kind).map(|(_, handle)| handle.clone())
+    }
+}
+
+/// Only files that exist are loaded, so a game shipped without recordings starts without
+/// an asset error for each one.
+fn load_sfx(mut commands: Commands, mut tones: ResMut<Assets<Tone>>, asset_server: Res<AssetServer>) {
+    let kinds = [
+        SfxKind::CannonFire, SfxKind::ProjectileBounce, SfxKind::MagnetHum, SfxKind::SkidScrape, SfxKind::SkidSqueal, SfxKind::Respawn,
+        SfxKind::Jump, SfxKind::Land, SfxKind::Coin, SfxKind::Hurt, SfxKind::BlockBreak,
+    ];
+    commands.insert_resource(SfxLibrary(kinds.iter()
+        .map(|kind| (*kind, tones.add(kind.tone())))
+        .collect()));
+    commands.insert_resource(SoundHandles(kinds.iter()
+        .filter_map(|kind| kind.file().map(|file| (*kind, format!("{SOUND_DIR}/{file}"))))
+        .filter(|(_, path)| Path::new("assets").join(path).exists())
+        .map(|(kind, path)| (kind, asset_server.load(path)))
+        .collect()));
+}
+
+fn attach_listener(
+    mut commands: Commands,
+    camera: Query<Entity, With<Camera>>,
+    config: Res<AudioConfig>,
+) {
+    for entity in &camera {
+        // Ears a screen apart, so pan follows horizontal offset across the whole view.
+        commands.entity(entity).insert(SpatialListener::new(config.screen_width));
+    }
+}
+
+fn spatial_settings(mode: PlaybackSettings, volume: f32) -> PlaybackSettings {
+    mode.with_spatial(true)
+        .with_spatial_scale(SpatialScale::new_2d(PAN_ONLY_SCALE))
+        .with_volume(Volume::new(volume))
+}
+
+fn play_positional_sfx(
+    mut commands: Commands,
+    mut events: EventReader<PlaySfxAt>,
+    camera: Query<&Position, With<Camera>>,
+    library: Res<SfxLibrary>,
+    sounds: Res<SoundHandles>,
+    config: Res<AudioConfig>,
+    settings: Res<Settings>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        let volume = config.attenuation(event.position - camera.0) * event.volume * settings.sfx_gain();
+        if volume <= 0. {
+            continue;
+        }
+        let settings = spatial_settings(PlaybackSettings::DESPAWN, volume);
+        let transform = TransformBundle::from_transform(Transform::from_translation(event.position.extend(0.)));
+        if let Some(source) = sounds.get(event.kind) {
+            commands.spawn((AudioSourceBundle { source, settings }, transform));
+        } else if let Some(source) = library.get(event.kind) {
+            commands.spawn((AudioSourceBundle { source, settings }, transform));
+        }
+    }
+}
+
+fn start_looping_sfx(
+    mut commands: Commands,
+    emitters: Query<(Entity, &LoopingSfx, &Position), Added<LoopingSfx>>,
+    camera: Query<&Position, With<Camera>>,
+    library: Res<SfxLibrary>,
+    config: Res<AudioConfig>,
+    settings: Res<Settings>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    for (entity, looping, position) in &emitters {
+        let Some(source) = library.get(looping.kind) else {
+            continue;
+        };
+        let volume = config.attenuation(position.0 - camera.0) * settings.sfx_gain();
+        commands.entity(entity).insert(AudioSourceBundle {
+            source,
+            settings: spatial_settings(PlaybackSettings::LOOP, volume),
+        });
+    }
+}
+
+/// Looping emitters move relative to the camera, so their volume is re-evaluated every
+/// few frames. Pan follows their transform on its own.
+fn attenuate_looping_sfx(
+    mut emitters: Query<(&mut LoopingSfx, &Position, &SpatialAudioSink)>,
+    camera: Query<&Position, With<Camera>>,
+    config: Res<AudioConfig>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    for (mut looping, position, sink) in &mut emitters {
+        looping.update_timer.set_duration(Duration::from_secs_f32(config.loop_update_interval));
+        if looping.update_timer.tick(time.delta()).just_finished() {
+            sink.set_volume(config.attenuation(position.0 - camera.0) * settings.sfx_gain());
+        }
+    }
+}
+
+fn stop_looping_sfx(
+    mut commands: Commands,
+    mut removed: RemovedComponents<LoopingSfx>,
+    sinks: Query<&SpatialAudioSink>,
+) {
+    for entity in removed.read() {
+        let Ok(sink) = sinks.get(entity) else {
+            continue;
+        };
+        sink.stop();
+        commands.entity(entity).remove::<(AudioSourceBundle<Tone>, SpatialAudioSink)>();
+    }
+}
@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::events::CollisionEvent;
+use crate::input::{Action, Actions};
+use crate::physics::{handle_collisions, stop_at_collisions, Collision, Contacts, GroundContact, PhysicsSet, PostCollide, Velocity};
+use crate::player::{Grounded, Player, PlayerId};
+
+/// Fraction of the fall speed a slime bounce gives back.
+const SLIME_RESTITUTION: f32 = 0.9;
+/// Landings slower than this stick instead of bouncing.
+const SLIME_MIN_BOUNCE: f32 = 216.;
+/// Jumping this close before a bounce gets the timing bonus.
+const TIMING_WINDOW_SECS: f32 = 0.12;
+const TIMING_BONUS: f32 = 1.15;
+/// Top walking speed while standing on slime.
+const SLIME_MAX_SPEED: f32 = 216.;
+
+pub struct SlimePlugin;
+
+impl Plugin for SlimePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, (
+            bounce_off_slime.in_set(PhysicsSet::Resolve).after(handle_collisions).before(stop_at_collisions),
+            slime_feel.in_set(PostCollide),
+        ));
+    }
+}
+
+/// Reflects vertical velocity on landing instead of absorbing it, and is sticky to walk on.
+#[derive(Component)]
+pub struct Slime;
+
+/// Slime keeps the player airborne and throws a fast fall back up. Runs before
+/// `stop_at_collisions`, which then leaves the upward bounce alone.
+pub fn bounce_off_slime(
+    mut collisions: EventReader<CollisionEvent>,
+    mut player: Query<&mut Velocity, With<Player>>,
+    slimes: Query<(), With<Slime>>,
+) {
+    for event in collisions.read() {
+        if event.side != Collision::Bottom || !slimes.contains(event.other) {
+            continue;
+        }
+        let Ok(mut velocity) = player.get_mut(event.entity) else {
+            continue;
+        };
+        if velocity.0.y < -SLIME_MIN_BOUNCE {
+            velocity.0.y = -velocity.0.y * SLIME_RESTITUTION;
+        }
+    }
+}
+
+fn slime_feel(
+    mut players: Query<(Entity, &PlayerId, &mut Velocity, &Grounded, &GroundContact), With<Player>>,
+    slimes: Query<(), With<Slime>>,
+    contacts: Res<Contacts>,
+    actions: Actions,
+    time: Res<Time>,
+    mut since_jump: Local<HashMap<PlayerId, f32>>,
+) {
+    for (entity, id, mut velocity, grounded, ground) in &mut players {
+        let input = actions.player(*id);
+        let since_jump = since_jump.entry(*id).or_default();
+        *since_jump += time.delta_seconds();
+        if input.just_pressed(Action::Jump) {
+            *since_jump = 0.;
+        }
+
+        let bounced = contacts.of(entity).any(|contact| {
+            contact.side == Collision::Bottom
+                && slimes.contains(contact.other)
+                && contact.velocity.y < -SLIME_MIN_BOUNCE
+        });
+        let holding_jump = input.pressed(Action::Jump);
+        if bounced && holding_jump && *since_jump <= TIMING_WINDOW_SECS {
+            velocity.0.y *= TIMING_BONUS;
+        }
+
+        if grounded.0 && ground.0.is_some_and(|ground| slimes.contains(ground)) {
+            velocity.0.x = velocity.0.x.clamp(-SLIME_MAX_SPEED, SLIME_MAX_SPEED);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::build_headless_app;
+    use crate::movement::MovementConfig;
+    use crate::world::{BlockData, BlockKind, WorldData};
+
+    /// How fast the player hits slime `drop` pixels under their feet, and how fast they
+    /// leave it again.
+    fn bounce(drop: f32) -> (f32, f32) {
+        let top = -50. - drop;
+        let slime = BlockData { kind: BlockKind::Slime, ..BlockData::new(Vec2::new(0., top - 25.), Vec2::new(400., 50.)) };
+        let mut app = build_headless_app(WorldData(vec![slime]));
+        let mut players = app.world_mut().query_filtered::<&Velocity, With<Player>>();
+        let gravity = MovementConfig::default().gravity;
+        let dt = app.world().resource::<Time<Fixed>>().timestep().as_secs_f32();
+        let mut falling = 0.;
+        for _ in 0..432 {
+            app.update();
+            let speed = players.single(app.world()).0.y;
+            if speed > 0. && falling < 0. {
+                // The bounce threw back the speed of the tick it landed on, one more
+                // tick's worth of gravity on from the last one seen falling.
+                return (falling + gravity * dt, speed);
+            }
+            falling = speed;
+        }
+        panic!("never bounced off slime {drop}px down");
+    }
+
+    #[test]
+    fn bounces_are_in_proportion_to_the_drop() {
+        for drop in [100., 300.] {
+            let (landing, bounce) = bounce(drop);
+            assert!(landing < -SLIME_MIN_BOUNCE, "only landed at {landing} from {drop}px");
+            assert!((bounce + landing * SLIME_RESTITUTION).abs() < 0.1, "landed at {landing} from {drop}px and bounced at {bounce}");
+        }
+    }
+}
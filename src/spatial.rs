@@ -0,0 +1,189 @@
+use bevy::math::bounding::Aabb2d;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::physics::{Collider, Position, Shape};
+
+/// Colliders spanning more cells than this skip the grid and get tested against
+/// everything, so one huge floor doesn't fill thousands of buckets.
+const MAX_CELLS_PER_COLLIDER: i32 = 256;
+
+/// Broad phase for `handle_collisions`: every `Collider` filed under the grid cells its
+/// box covers, so a body only has to be tested against colliders in the cells around it.
+/// Kept up to date by `update_spatial_grid` before each tick resolves collisions, which
+/// picks up spawned, moved and resized colliders, moving platforms included.
+#[derive(Resource)]
+pub struct SpatialGrid {
+    pub cell_size: f32,
+    pub buckets: HashMap<IVec2, Vec<Entity>>,
+    /// The cells each collider is filed under, to take it back out when it moves.
+    cells: HashMap<Entity, IRect>,
+    /// Colliders too big to file, checked by every query.
+    oversized: HashSet<Entity>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self {
+            cell_size: 128.,
+            buckets: HashMap::default(),
+            cells: HashMap::default(),
+            oversized: HashSet::default(),
+        }
+    }
+}
+
+impl SpatialGrid {
+    fn cell_range(&self, aabb: Aabb2d) -> IRect {
+        IRect::from_corners(
+            (aabb.min / self.cell_size).floor().as_ivec2(),
+            (aabb.max / self.cell_size).floor().as_ivec2(),
+        )
+    }
+
+    fn cells_in(range: IRect) -> impl Iterator<Item = IVec2> {
+        (range.min.y..=range.max.y).flat_map(move |y| (range.min.x..=range.max.x).map(move |x| IVec2::new(x, y)))
+    }
+
+    pub fn insert(&mut self, entity: Entity, aabb: Aabb2d) {
+        let range = self.cell_range(aabb);
+        if self.cells.get(&entity) == Some(&range) {
+            return;
+        }
+        self.remove(entity);
+        let size = range.size() + IVec2::ONE;
+        if size.x * size.y > MAX_CELLS_PER_COLLIDER {
+            self.oversized.insert(entity);
+            return;
+        }
+        for cell in Self::cells_in(range) {
+            self.buckets.entry(cell).or_default().push(entity);
+        }
+        self.cells.insert(entity, range);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        self.oversized.remove(&entity);
+        let Some(range) = self.cells.remove(&entity) else {
+            return;
+        };
+        for cell in Self::cells_in(range) {
+            if let Some(bucket) = self.buckets.get_mut(&cell) {
+                bucket.retain(|other| *other != entity);
+                if bucket.is_empty() {
+                    self.buckets.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// Every collider filed in a cell `aabb` touches, once each, in entity order. These
+    /// only might overlap it; the narrow phase decides.
+    pub fn query(&self, aabb: Aabb2d) -> Vec<Entity> {
+        let range = self.cell_range(aabb);
+        let mut found: Vec<Entity> = self.oversized.iter().copied().collect();
+        let size = range.size() + IVec2::ONE;
+        if size.x * size.y > self.buckets.len() as i32 {
+            // Querying a region bigger than the grid is filled: walk the buckets instead.
+            for (cell, bucket) in &self.buckets {
+                if range.contains(*cell) {
+                    found.extend_from_slice(bucket);
+                }
+            }
+        } else {
+            for cell in Self::cells_in(range) {
+                if let Some(bucket) = self.buckets.get(&cell) {
+                    found.extend_from_slice(bucket);
+                }
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+}
+
+/// Files colliders that spawned, moved or changed size since the last tick. Runs after
+/// `PhysicsSet::Integrate`, so moving platforms and crates are filed where they'll be
+/// when collisions are resolved.
+pub fn update_spatial_grid(
+    colliders: Query<(Entity, &Position, &Shape), (With<Collider>, Or<(Added<Collider>, Changed<Position>, Changed<Shape>)>)>,
+    mut grid: ResMut<SpatialGrid>,
+) {
+    for (entity, position, shape) in &colliders {
+        grid.insert(entity, Aabb2d::new(position.0, shape.0 / 2.));
+    }
+}
+
+/// Takes despawned colliders (and ones that stopped being colliders) out of the grid.
+/// Runs in `Update` since a restart despawns the level while no fixed ticks run.
+pub fn prune_spatial_grid(
+    mut removed: RemovedComponents<Collider>,
+    colliders: Query<(), With<Collider>>,
+    mut grid: ResMut<SpatialGrid>,
+) {
+    for entity in removed.read() {
+        if !colliders.contains(entity) {
+            grid.remove(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::headless::build_headless_app;
+    use crate::physics::CollisionStats;
+    use crate::world::{BlockData, WorldData};
+
+    /// Most exact collider tests a tick can take with one body in the level.
+    const TESTS_PER_TICK: u64 = 20;
+
+    /// Wall time spent in the fixed schedule, from the start of `FixedFirst` to the end of
+    /// `FixedLast`, summed over every tick.
+    #[derive(Resource, Default)]
+    struct FixedTickTime {
+        started: Option<Instant>,
+        spent: Duration,
+    }
+
+    fn start_tick(mut time: ResMut<FixedTickTime>) {
+        time.started = Some(Instant::now());
+    }
+
+    fn end_tick(mut time: ResMut<FixedTickTime>) {
+        if let Some(started) = time.started.take() {
+            time.spent += started.elapsed();
+        }
+    }
+
+    #[test]
+    fn ten_thousand_blocks_cost_no_more_than_the_ones_nearby() {
+        // Spaced out so none of them merge, with one under the player's spawn.
+        let blocks = (0..100)
+            .flat_map(|x| (0..100).map(move |y| Vec2::new((x - 50) as f32 * 200., -200. - y as f32 * 200.)))
+            .map(|position| BlockData::new(position, Vec2::splat(50.)))
+            .collect();
+        let mut app = build_headless_app(WorldData(blocks));
+        app.init_resource::<FixedTickTime>()
+            .add_systems(FixedFirst, start_tick)
+            .add_systems(FixedLast, end_tick);
+        for _ in 0..144 {
+            app.update();
+        }
+        let before = app.world().resource::<CollisionStats>().narrow_phase_tests;
+        app.world_mut().resource_mut::<FixedTickTime>().spent = Duration::ZERO;
+        for _ in 0..144 {
+            app.update();
+        }
+        let tests = app.world().resource::<CollisionStats>().narrow_phase_tests - before;
+        // Still testing the block the player is standing on.
+        assert!(tests > 0);
+        assert!(tests <= TESTS_PER_TICK * 144, "{} tests a tick", tests / 144);
+        // Every system in the tick, not just the narrow phase, inside a second of ticks.
+        let spent = app.world().resource::<FixedTickTime>().spent;
+        assert!(spent < Duration::from_secs(1), "{:?} a tick, over the 1/144 s budget", spent / 144);
+    }
+}
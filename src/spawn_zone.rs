@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+
+use crate::enemy::{spawn_enemy, EnemyData};
+use crate::level::{Downed, LevelEntity, LevelState, ResetLevel};
+use crate::origin::WorldOrigin;
+use crate::physics::{move_bodies, PhysicsWorld, Position};
+use crate::pickup::{spawn_pickup, PickupData};
+use crate::player::Player;
+use crate::world::WorldData;
+use crate::GameState;
+
+/// How far from its placed position a triggered enemy may be moved to find open space.
+const SPAWN_SEARCH_RADIUS: f32 = 100.;
+
+pub struct SpawnZonePlugin;
+
+impl Plugin for SpawnZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Restarting), spawn_zones.after(ResetLevel))
+            .add_systems(FixedUpdate, update_spawn_zones.after(move_bodies));
+    }
+}
+
+#[derive(Debug)]
+pub enum EntitySpawn {
+    Enemy(EnemyData),
+    Pickup(PickupData),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Zone {
+    Radius { center: Vec2, radius: f32 },
+    Region { position: Vec2, size: Vec2 },
+}
+
+impl Zone {
+    pub fn contains(self, point: Vec2) -> bool {
+        match self {
+            Zone::Radius { center, radius } => point.distance(center) <= radius,
+            Zone::Region { position, size } => (point - position).abs().cmple(size / 2.).all(),
+        }
+    }
+}
+
+/// Spawns `entities` when the player's center enters `zone`, so they don't wander off or
+/// cost anything before the player gets there.
+#[derive(Debug)]
+pub struct SpawnTrigger {
+    pub entities: Vec<EntitySpawn>,
+    pub zone: Zone,
+    /// Only fire once per level load, even across respawns.
+    pub once: bool,
+    /// Despawn whatever was spawned, state and all, once the player leaves the zone.
+    pub despawn_on_leave: bool,
+}
+
+/// Spawn triggers placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component)]
+pub struct SpawnTriggers(pub Vec<SpawnTrigger>);
+
+#[derive(Component)]
+struct SpawnZone {
+    /// Index into `SpawnTriggers`.
+    index: usize,
+    inside: bool,
+    spawned: Vec<Entity>,
+}
+
+fn spawn_zones(
+    mut commands: Commands,
+    world_data: Query<&SpawnTriggers, With<WorldData>>,
+) {
+    let Ok(triggers) = world_data.get_single() else {
+        return;
+    };
+    for index in 0..triggers.0.len() {
+        commands.spawn((
+            SpawnZone { index, inside: false, spawned: Vec::new() },
+            LevelEntity,
+        ));
+    }
+}
+
+fn update_spawn_zones(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut zones: Query<&mut SpawnZone>,
+    alive: Query<(), With<LevelEntity>>,
+    world_data: Query<&SpawnTriggers, With<WorldData>>,
+    players: Query<&Position, (With<Player>, Without<Downed>)>,
+    mut level_state: ResMut<LevelState>,
+    physics: PhysicsWorld,
+    origin: Res<WorldOrigin>,
+) {
+    let Ok(triggers) = world_data.get_single() else {
+        return;
+    };
+    for mut zone in &mut zones {
+        let trigger = &triggers.0[zone.index];
+        let inside = players.iter().any(|player_pos| trigger.zone.contains(origin.to_level(player_pos.0)));
+        zone.spawned.retain(|entity| alive.contains(*entity));
+
+        if inside && !zone.inside {
+            let spent = trigger.once && level_state.fired_triggers.contains(&zone.index);
+            if !spent && zone.spawned.is_empty() {
+                level_state.fired_triggers.insert(zone.index);
+                zone.spawned = trigger.entities.iter().map(|spawn| match spawn {
+                    EntitySpawn::Enemy(data) => {
+                        // The level may have been edited since the trigger was placed; don't
+                        // drop the enemy inside a block.
+                        let placed = origin.to_live(data.position);
+                        let position = physics.free_space_near(placed, data.shape, SPAWN_SEARCH_RADIUS)
+                            .unwrap_or(placed);
+                        spawn_enemy(&mut commands, &mut meshes, &mut materials, &EnemyData { position, ..data.clone() })
+                    }
+                    EntitySpawn::Pickup(data) => {
+                        let position = origin.to_live(data.position);
+                        spawn_pickup(&mut commands, &mut meshes, &mut materials, &PickupData { position, ..data.clone() })
+                    }
+                }).collect();
+            }
+        } else if !inside && zone.inside && trigger.despawn_on_leave {
+            for entity in zone.spawned.drain(..) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        zone.inside = inside;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enemy::{Enemy, PatrolRoute};
+    use crate::headless::build_headless_app;
+    use crate::input::{Action, ScriptedInput};
+    use crate::world::BlockData;
+
+    fn enemies(app: &mut App) -> usize {
+        app.world_mut().query_filtered::<(), With<Enemy>>().iter(app.world()).count()
+    }
+
+    fn walk(app: &mut App, action: Action, ticks: usize) {
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].press(action);
+        for _ in 0..ticks {
+            app.update();
+        }
+        app.world_mut().resource_mut::<ScriptedInput>().actions[0].release(action);
+    }
+
+    #[test]
+    fn walking_into_a_zone_brings_its_enemies_in_and_out() {
+        let mut app = build_headless_app(WorldData(vec![BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.))]));
+        let enemy = |x| EntitySpawn::Enemy(EnemyData {
+            position: Vec2::new(x, -150.),
+            shape: Vec2::splat(50.),
+            route: PatrolRoute::Range { min_x: x, max_x: x },
+            speed: 0.,
+            chase: None,
+            boss_bar: None,
+        });
+        let mut levels = app.world_mut().query_filtered::<Entity, With<WorldData>>();
+        let level = levels.single(app.world());
+        app.world_mut().entity_mut(level).insert(SpawnTriggers(vec![SpawnTrigger {
+            entities: vec![enemy(700.), enemy(800.)],
+            zone: Zone::Region { position: Vec2::new(400., 0.), size: Vec2::new(200., 400.) },
+            once: false,
+            despawn_on_leave: true,
+        }]));
+        for _ in 0..144 {
+            app.update();
+        }
+        assert_eq!(enemies(&mut app), 0);
+        // Far enough right to be in the zone, which starts at 300.
+        walk(&mut app, Action::MoveRight, 108);
+        assert_eq!(enemies(&mut app), 2);
+        walk(&mut app, Action::MoveLeft, 288);
+        assert_eq!(enemies(&mut app), 0);
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::{fs, io};
+
+use bevy::prelude::*;
+
+use crate::events::{LevelComplete, NewBestTime, Respawned};
+use crate::input::{Action, Actions};
+use crate::level::ResetLevel;
+use crate::player::{Player, PlayerId};
+use crate::settings::Settings;
+use crate::GameState;
+
+const TIMES_PATH: &str = "saves/times.ron";
+/// Seconds the "new record" flash stays up after beating a best time.
+const RECORD_FLASH_SECS: f32 = 3.;
+pub const RECORD_COLOR: Color = Color::srgb(1., 0.85, 0.3);
+
+pub struct SpeedrunPlugin;
+
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelTimer>()
+            .insert_resource(BestTimes::load())
+            .add_systems(OnEnter(GameState::Restarting), reset_level_timer.in_set(ResetLevel))
+            .add_systems(FixedUpdate, run_level_timer)
+            .add_systems(FixedPostUpdate, finish_level_timer)
+            .add_systems(Update, run_record_flash);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum TimerState {
+    /// Waiting for the player's first move.
+    #[default]
+    Waiting,
+    Running,
+    /// The exit was reached; `elapsed` is the final time.
+    Finished,
+}
+
+/// The time on the current attempt at the level. It only runs on fixed ticks, so it
+/// stops with the simulation whenever the game is paused.
+#[derive(Resource, Default)]
+pub struct LevelTimer {
+    pub elapsed: f32,
+    state: TimerState,
+    /// Seconds left of the "new record" flash, counted on real time.
+    record_flash: f32,
+}
+
+impl LevelTimer {
+    pub fn is_running(&self) -> bool {
+        self.state == TimerState::Running
+    }
+
+    /// Reset and not yet started by the player's first move.
+    pub fn is_waiting(&self) -> bool {
+        self.state == TimerState::Waiting
+    }
+
+    /// Seconds left of the "new record" flash.
+    pub fn record_flash(&self) -> f32 {
+        self.record_flash
+    }
+}
+
+/// The fastest finish of each level, by name, kept in `saves/times.ron` across every
+/// save slot.
+#[derive(Resource, Default)]
+pub struct BestTimes(pub BTreeMap<String, f32>);
+
+impl BestTimes {
+    /// A missing file means no level has been finished yet; a broken one gets a warning.
+    fn load() -> Self {
+        let text = match fs::read_to_string(TIMES_PATH) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(error) => {
+                warn!("couldn't read {TIMES_PATH}: {error}");
+                return Self::default();
+            }
+        };
+        match ron::from_str(&text) {
+            Ok(times) => Self(times),
+            Err(error) => {
+                warn!("{TIMES_PATH} is broken, starting without best times: {error}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes through a temporary file, like the save slots.
+    fn save(&self) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(&self.0, default()).map_err(io::Error::other)?;
+        let path = Path::new(TIMES_PATH);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp = path.with_extension("ron.tmp");
+        fs::write(&temp, text)?;
+        fs::rename(temp, path)
+    }
+}
+
+/// A level loading or restarting starts the clock over; a new record's flash stays up
+/// through the load into the next level.
+fn reset_level_timer(mut timer: ResMut<LevelTimer>) {
+    timer.elapsed = 0.;
+    timer.state = TimerState::Waiting;
+}
+
+/// The clock starts on the first tick either player tries to move, so the time spent
+/// looking at a fresh level doesn't count. Dying puts it back to waiting only with
+/// `Settings::reset_timer_on_death`; otherwise the time lost to a death counts.
+fn run_level_timer(
+    mut timer: ResMut<LevelTimer>,
+    mut respawned: EventReader<Respawned>,
+    settings: Res<Settings>,
+    players: Query<&PlayerId, With<Player>>,
+    actions: Actions,
+    time: Res<Time>,
+) {
+    if respawned.read().count() > 0 && settings.reset_timer_on_death && timer.state == TimerState::Running {
+        timer.elapsed = 0.;
+        timer.state = TimerState::Waiting;
+        return;
+    }
+    if timer.state == TimerState::Waiting {
+        let moved = players.iter().any(|id| {
+            let input = actions.player(*id);
+            input.move_x() != 0.
+                || [Action::Jump, Action::Dash, Action::MoveUp, Action::MoveDown].into_iter().any(|action| input.pressed(action))
+        });
+        if !moved {
+            return;
+        }
+        timer.state = TimerState::Running;
+    }
+    if timer.state == TimerState::Running {
+        timer.elapsed += time.delta_seconds();
+    }
+}
+
+/// Stops the clock at the exit, on the tick it was reached, and keeps the time if it beats the level's best.
+pub fn finish_level_timer(
+    mut complete: EventReader<LevelComplete>,
+    mut timer: ResMut<LevelTimer>,
+    mut best: ResMut<BestTimes>,
+    mut new_best: EventWriter<NewBestTime>,
+) {
+    for event in complete.read() {
+        if timer.state != TimerState::Running {
+            continue;
+        }
+        timer.state = TimerState::Finished;
+        if best.0.get(&event.level).is_some_and(|best| *best <= timer.elapsed) {
+            continue;
+        }
+        best.0.insert(event.level.clone(), timer.elapsed);
+        timer.record_flash = RECORD_FLASH_SECS;
+        if let Err(error) = best.save() {
+            warn!("couldn't save {TIMES_PATH}: {error}");
+        }
+        new_best.send(NewBestTime { level: event.level.clone(), time: timer.elapsed });
+    }
+}
+
+/// Minutes, seconds and milliseconds, as `mm:ss.mmm`.
+pub fn format_time(secs: f32) -> String {
+    let millis = (secs.max(0.) * 1000.).round() as u64;
+    format!("{:02}:{:02}.{:03}", millis / 60_000, millis / 1000 % 60, millis % 1000)
+}
+
+/// Runs on real time so the flash blinks on even while the next level fades in.
+fn run_record_flash(mut timer: ResMut<LevelTimer>, time: Res<Time<Real>>) {
+    if timer.record_flash > 0. {
+        timer.record_flash = (timer.record_flash - time.delta_seconds()).max(0.);
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::ground_pound::GroundPound;
+use crate::input::{Action, Actions};
+use crate::movement::ControlLock;
+use crate::physics::{Collision, Contacts, PostCollide, Shape, Velocity};
+use crate::player::{Player, PlayerId, VisShape};
+
+/// How long a launch with any sideways push keeps the player's steering off, so the
+/// horizontal lerp doesn't eat the flight.
+const SIDEWAYS_LOCK_SECS: f32 = 0.35;
+/// Launch speed multiplier for holding jump as the spring fires.
+const HELD_JUMP_BOOST: f32 = 1.15;
+/// Launch speed multiplier for coming down on it in a ground pound's plummet, which
+/// reaches the top rising speed off the strongest spring.
+const POUND_BOOST: f32 = 1.4;
+/// The player's size relative to `Shape` right after a launch, long along the launch.
+const LAUNCH_STRETCH: Vec2 = Vec2::new(0.7, 1.35);
+/// The spring's size relative to its `Shape` as it fires, pressed down toward its base.
+const SPRING_COMPRESS: Vec2 = Vec2::new(1.1, 0.5);
+/// How quickly a fired spring eases back out, per second.
+pub const SPRING_SNAPPINESS: f32 = 23.;
+
+pub struct SpringPlugin;
+
+impl Plugin for SpringPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Spring>()
+            .add_systems(FixedUpdate, launch_from_springs.in_set(PostCollide));
+    }
+}
+
+/// Flings whatever touches its face along `direction`. The face is the side `direction`
+/// points out of, so a spring on a wall pointing right launches from its right side.
+#[derive(Component, Reflect, Copy, Clone, Debug, PartialEq, Deserialize)]
+#[reflect(Component)]
+pub struct Spring {
+    pub direction: Vec2,
+    /// Launch speed in pixels per second.
+    pub strength: f32,
+}
+
+impl Spring {
+    /// The side of a body touching the spring's face. A spring pointing up is landed on
+    /// with the body's bottom.
+    pub fn trigger_side(&self) -> Collision {
+        facing_side(self.direction)
+    }
+
+    pub fn launch_velocity(&self) -> Vec2 {
+        self.direction.normalize_or_zero() * self.strength
+    }
+}
+
+/// The side of a body that touches the face of a block pointing along `direction`: the
+/// face is whichever side `direction` points out of most.
+pub fn facing_side(direction: Vec2) -> Collision {
+    if direction.y.abs() >= direction.x.abs() {
+        if direction.y >= 0. { Collision::Bottom } else { Collision::Top }
+    } else if direction.x > 0. {
+        Collision::Left
+    } else {
+        Collision::Right
+    }
+}
+
+/// Launches replace the player's velocity outright, so a chain of springs always flies
+/// exactly the way it was laid out no matter how fast the player came in. Holding jump
+/// as it fires goes a little further, and ground pounding onto it a lot further.
+pub fn launch_from_springs(
+    mut commands: Commands,
+    mut players: Query<(Entity, &PlayerId, &mut Velocity, &mut VisShape, &Shape, Option<&GroundPound>), With<Player>>,
+    mut springs: Query<(&Spring, &mut VisShape, &Shape), Without<Player>>,
+    contacts: Res<Contacts>,
+    actions: Actions,
+) {
+    for (entity, id, mut velocity, mut vis_shape, shape, pound) in &mut players {
+        let spring = contacts.of(entity).find(|contact| {
+            springs.get(contact.other).is_ok_and(|(spring, ..)| spring.trigger_side() == contact.side)
+        });
+        let Some(Ok((spring, mut spring_vis, spring_shape))) = spring.map(|contact| springs.get_mut(contact.other)) else {
+            continue;
+        };
+        let mut boost = if actions.player(*id).pressed(Action::Jump) { HELD_JUMP_BOOST } else { 1. };
+        if pound.is_some_and(GroundPound::plummeting) {
+            boost *= POUND_BOOST;
+        }
+        velocity.0 = spring.launch_velocity() * boost;
+        if velocity.0.x.abs() > f32::EPSILON {
+            commands.entity(entity).insert(ControlLock::new(SIDEWAYS_LOCK_SECS));
+        }
+        let stretch = if spring.direction.y.abs() >= spring.direction.x.abs() { LAUNCH_STRETCH } else { LAUNCH_STRETCH.yx() };
+        vis_shape.0 = shape.0 * stretch;
+        // Springs are drawn pointing up and rotated, so this squashes along their own axis.
+        spring_vis.0 = spring_shape.0 * SPRING_COMPRESS;
+    }
+}
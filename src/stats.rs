@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::damage::apply_damage;
+use crate::enemy::Enemy;
+use crate::events::{Died, Jumped, PlayerDied};
+use crate::level::ResetLevel;
+use crate::origin::WorldOrigin;
+use crate::physics::{Position, Teleported};
+use crate::player::{Player, PlayerId};
+use crate::GameState;
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelStats>()
+            .init_resource::<GlobalStats>()
+            .add_systems(Startup, spawn_stats_panel)
+            .add_systems(FixedUpdate, track_stats.after(apply_damage))
+            .add_systems(OnEnter(GameState::Restarting), reset_level_stats.in_set(ResetLevel))
+            .add_systems(Update, show_stats_panel);
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub struct Stats {
+    pub playtime: f32,
+    pub jumps: u32,
+    pub deaths: u32,
+    /// Pixels the player has moved, not counting teleports.
+    pub distance: f32,
+    pub enemies_defeated: u32,
+}
+
+/// Stats for the current attempt at the level; a restart clears them.
+#[derive(Resource, Default)]
+pub struct LevelStats(pub Stats);
+
+/// Stats accumulated over every attempt this session.
+#[derive(Resource, Default)]
+pub struct GlobalStats(pub Stats);
+
+/// The only place stats are counted: everything is derived from gameplay events and the
+/// player's movement, so features never touch the counters themselves.
+fn track_stats(
+    mut jumped: EventReader<Jumped>,
+    mut player_died: EventReader<PlayerDied>,
+    mut died: EventReader<Died>,
+    player: Query<(&Position, &PlayerId, Has<Teleported>), With<Player>>,
+    enemies: Query<(), With<Enemy>>,
+    mut level: ResMut<LevelStats>,
+    mut global: ResMut<GlobalStats>,
+    mut last_position: Local<Option<Vec2>>,
+    time: Res<Time>,
+    origin: Res<WorldOrigin>,
+) {
+    let mut tick = Stats {
+        playtime: time.delta_seconds(),
+        jumps: jumped.read().count() as u32,
+        deaths: player_died.read().count() as u32,
+        enemies_defeated: died.read().filter(|event| enemies.contains(event.entity)).count() as u32,
+        ..default()
+    };
+    // Distance is the first player's; the second's isn't counted.
+    if let Some((position, _, teleported)) = player.iter().find(|(_, id, _)| **id == PlayerId::ONE) {
+        // In level coordinates, so a rebase isn't counted as ground covered.
+        let position = origin.to_level(position.0);
+        if let Some(last) = last_position.filter(|_| !teleported) {
+            tick.distance = position.distance(last);
+        }
+        *last_position = Some(position);
+    }
+    for stats in [&mut level.0, &mut global.0] {
+        stats.playtime += tick.playtime;
+        stats.jumps += tick.jumps;
+        stats.deaths += tick.deaths;
+        stats.distance += tick.distance;
+        stats.enemies_defeated += tick.enemies_defeated;
+    }
+}
+
+fn reset_level_stats(mut level: ResMut<LevelStats>) {
+    *level = LevelStats::default();
+}
+
+#[derive(Component)]
+struct StatsText;
+
+fn spawn_stats_panel(mut commands: Commands) {
+    commands.spawn((TextBundle::from_section("", TextStyle::default())
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.),
+            left: Val::Px(8.),
+            ..default()
+        }), StatsText));
+}
+
+fn format_stats(stats: &Stats) -> String {
+    format!(
+        "time {:.0}s  jumps {}  deaths {}  distance {:.0}  defeated {}",
+        stats.playtime, stats.jumps, stats.deaths, stats.distance, stats.enemies_defeated,
+    )
+}
+
+/// Holding Tab shows this attempt's stats next to the session totals.
+fn show_stats_panel(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    level: Res<LevelStats>,
+    global: Res<GlobalStats>,
+    mut text: Query<&mut Text, With<StatsText>>,
+) {
+    let value = if kb_input.pressed(KeyCode::Tab) {
+        format!("level   {}\ntotal   {}", format_stats(&level.0), format_stats(&global.0))
+    } else {
+        String::new()
+    };
+    for mut text in &mut text {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
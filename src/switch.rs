@@ -0,0 +1,197 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+use crate::crates::Pushable;
+use crate::events::ActivationEvent;
+use crate::level::ResetLevel;
+use crate::physics::{Collider, DynamicBody, PhysicsSet, Position, Shape};
+use crate::player::{Player, SquashStretch};
+use crate::GameState;
+
+pub const PLATE_COLOR: Color = Color::srgb(0.85, 0.55, 0.25);
+pub const PLATE_SNAPPINESS: f32 = 30.;
+const DOOR_COLOR: Color = Color::srgb(0.5, 0.45, 0.4);
+const BRIDGE_COLOR: Color = Color::srgb(0.6, 0.5, 0.35);
+/// How much of its height a plate keeps while it's pressed.
+const PRESSED_HEIGHT: f32 = 0.4;
+/// Seconds between one bridge segment and the next coming out or going back in.
+const BRIDGE_STEP_SECS: f32 = 0.15;
+/// How much of a door or bridge segment still shows while it isn't solid.
+const OFF_ALPHA: f32 = 0.2;
+
+pub struct SwitchPlugin;
+
+impl Plugin for SwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Channels>()
+            .add_systems(OnEnter(GameState::Restarting), reset_channels.in_set(ResetLevel))
+            .add_systems(FixedUpdate, (press_plates, follow_channels, switch_blocks).chain().after(PhysicsSet::Resolve))
+            .add_systems(Update, fade_switched);
+    }
+}
+
+/// Which channels are on, as of the last `ActivationEvent` sent for each. A channel is
+/// on while any plate wired to it is down.
+#[derive(Resource, Default)]
+pub struct Channels(HashMap<u32, bool>);
+
+impl Channels {
+    /// Turns `channel` on or off, sending the `ActivationEvent` for it if that's a change.
+    /// Returns whether it was.
+    pub fn switch(&mut self, channel: u32, active: bool, activations: &mut EventWriter<ActivationEvent>) -> bool {
+        if self.0.get(&channel).copied().unwrap_or(false) == active {
+            return false;
+        }
+        self.0.insert(channel, active);
+        activations.send(ActivationEvent { channel, active });
+        true
+    }
+}
+
+fn reset_channels(mut channels: ResMut<Channels>) {
+    channels.0.clear();
+}
+
+/// A thin plate from `BlockKind::PressurePlate`, which things pass through. It's down
+/// while a player or a pushable crate is on it, turning its channel on. A latching plate
+/// stays down once pressed, through deaths too, until the level restarts.
+#[derive(Component)]
+pub struct PressurePlate {
+    pub channel: u32,
+    latching: bool,
+    pressed: bool,
+}
+
+impl PressurePlate {
+    pub fn new(channel: u32, latching: bool) -> Self {
+        Self { channel, latching, pressed: false }
+    }
+}
+
+/// What an `Activatable` block does when its channel switches.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum ActivationBehavior {
+    /// Solid while the channel is off, open while it's on.
+    Door,
+    /// One segment of a bridge, solid while the channel is on. Segments come out `order`
+    /// steps after the channel comes on, lowest first, and go back in the other way
+    /// round, so a bridge extends and retracts block by block.
+    Bridge { order: u32 },
+}
+
+impl ActivationBehavior {
+    pub fn color(self) -> Color {
+        match self {
+            ActivationBehavior::Door => DOOR_COLOR,
+            ActivationBehavior::Bridge { .. } => BRIDGE_COLOR,
+        }
+    }
+}
+
+/// A block from `BlockKind::Activatable`, switched by every plate on `channel`. It has a
+/// material of its own, like a blinking block, to fade while it isn't solid.
+#[derive(Component)]
+pub struct Activatable {
+    pub channel: u32,
+    pub behavior: ActivationBehavior,
+    active: bool,
+    solid: bool,
+    /// Seconds since the channel last switched.
+    since: f32,
+}
+
+impl Activatable {
+    pub fn new(channel: u32, behavior: ActivationBehavior) -> Self {
+        Self { channel, behavior, active: false, solid: behavior == ActivationBehavior::Door, since: 0. }
+    }
+
+    pub fn is_solid(&self) -> bool {
+        self.solid
+    }
+}
+
+/// Sends an `ActivationEvent` for every channel whose plates, taken together, went down
+/// or came up this tick.
+fn press_plates(
+    mut plates: Query<(&mut PressurePlate, &Position, &Shape, &mut SquashStretch)>,
+    bodies: Query<(&Position, &Shape), (With<DynamicBody>, Or<(With<Player>, With<Pushable>)>, Without<PressurePlate>)>,
+    mut channels: ResMut<Channels>,
+    mut activations: EventWriter<ActivationEvent>,
+) {
+    let mut on: HashMap<u32, bool> = HashMap::new();
+    for (mut plate, position, shape, mut squash) in &mut plates {
+        let footprint = Aabb2d::new(position.0, shape.0 / 2.);
+        let weighed = bodies.iter().any(|(body_pos, body_shape)| Aabb2d::new(body_pos.0, body_shape.0 / 2.).intersects(&footprint));
+        plate.pressed = weighed || plate.latching && plate.pressed;
+        squash.target = if plate.pressed { Vec2::new(shape.0.x, shape.0.y * PRESSED_HEIGHT) } else { shape.0 };
+        *on.entry(plate.channel).or_default() |= plate.pressed;
+    }
+    for (channel, active) in on {
+        channels.switch(channel, active, &mut activations);
+    }
+}
+
+fn follow_channels(mut activations: EventReader<ActivationEvent>, mut blocks: Query<&mut Activatable>) {
+    for event in activations.read() {
+        for mut block in blocks.iter_mut().filter(|block| block.channel == event.channel && block.active != event.active) {
+            block.active = event.active;
+            block.since = 0.;
+        }
+    }
+}
+
+/// A block only turns solid once nothing is inside it, like a blinking one, so it never
+/// closes around the player or a crate. Going, it just stops being there, and whatever
+/// stood on it falls.
+fn switch_blocks(
+    mut commands: Commands,
+    mut blocks: Query<(Entity, &mut Activatable, &Position, &Shape)>,
+    bodies: Query<(&Position, &Shape), (With<DynamicBody>, Without<Activatable>)>,
+    time: Res<Time>,
+) {
+    // The last segment of each channel's bridge, which goes back in first.
+    let mut last: HashMap<u32, u32> = HashMap::new();
+    for (_, block, ..) in &blocks {
+        if let ActivationBehavior::Bridge { order } = block.behavior {
+            let last = last.entry(block.channel).or_default();
+            *last = (*last).max(order);
+        }
+    }
+    for (entity, mut block, position, shape) in &mut blocks {
+        block.since += time.delta_seconds();
+        let due_solid = match block.behavior {
+            ActivationBehavior::Door => !block.active,
+            ActivationBehavior::Bridge { order } if block.active => block.solid || block.since >= order as f32 * BRIDGE_STEP_SECS,
+            ActivationBehavior::Bridge { order } => {
+                let steps = last.get(&block.channel).copied().unwrap_or(order) - order;
+                block.solid && block.since < steps as f32 * BRIDGE_STEP_SECS
+            }
+        };
+        if due_solid == block.solid {
+            continue;
+        }
+        if due_solid {
+            let footprint = Aabb2d::new(position.0, shape.0 / 2.);
+            let blocked = bodies.iter().any(|(body_pos, body_shape)| Aabb2d::new(body_pos.0, body_shape.0 / 2.).intersects(&footprint));
+            if blocked {
+                continue;
+            }
+            commands.entity(entity).insert(Collider);
+        } else {
+            commands.entity(entity).remove::<Collider>();
+        }
+        block.solid = due_solid;
+    }
+}
+
+fn fade_switched(blocks: Query<(&Activatable, &Handle<ColorMaterial>)>, mut materials: ResMut<Assets<ColorMaterial>>) {
+    for (block, material) in &blocks {
+        let alpha = if block.solid { 1. } else { OFF_ALPHA };
+        let stale = materials.get(material).is_some_and(|material| material.color.alpha() != alpha);
+        if let Some(material) = materials.get_mut(material).filter(|_| stale) {
+            material.color.set_alpha(alpha);
+        }
+    }
+}
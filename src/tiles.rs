@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::breakable::Breakable;
+use crate::cannon::Cannon;
+use crate::checkpoint::Checkpoint;
+use crate::exit::Exit;
+use crate::ladder::Ladder;
+use crate::loading::TILE_ATLAS;
+use crate::magnet::Magnet;
+use crate::physics::{Gate, Position, Shape, Slope};
+use crate::platform::MovingPlatform;
+use crate::world::{Block, SurfaceKind};
+
+/// Side length of one grid cell, in pixels.
+const TILE_SIZE: f32 = 50.;
+/// Center of the cell at (0, 0); the level's 50px blocks line up with cells around it.
+const GRID_ORIGIN: Vec2 = Vec2::new(25., 0.);
+
+pub struct TilePlugin;
+
+impl Plugin for TilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_tile_atlas)
+            .add_systems(Update, autotile_blocks);
+    }
+}
+
+/// `sprites/tiles.png` is a 4x4 grid of 50px frames, read left to right and top to bottom.
+/// A cell's frame is the bitmask of which of its neighbors are ground too: 1 above,
+/// 2 to the right, 4 below and 8 to the left. Frame 0 is a lone tile, 15 is fully buried.
+#[derive(Resource)]
+struct TileAtlas {
+    texture: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+}
+
+/// One cell's visual, spawned as a child of the block it belongs to. The block's own
+/// flat mesh stays underneath and keeps being its collider.
+#[derive(Component)]
+struct TileSprite;
+
+fn load_tile_atlas(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    commands.insert_resource(TileAtlas {
+        texture: asset_server.load(TILE_ATLAS),
+        layout: layouts.add(TextureAtlasLayout::from_grid(UVec2::splat(TILE_SIZE as u32), 4, 4, None, None)),
+    });
+}
+
+/// The cells a block covers, or `None` for freeform blocks that don't line up with the grid.
+fn grid_cells(position: Vec2, shape: Vec2) -> Option<Vec<IVec2>> {
+    let size = shape / TILE_SIZE;
+    let first = (position - shape / 2. + TILE_SIZE / 2. - GRID_ORIGIN) / TILE_SIZE;
+    let aligned = |value: Vec2| (value - value.round()).abs().max_element() < 1e-3;
+    if !aligned(size) || !aligned(first) || size.min_element() < 1. {
+        return None;
+    }
+    let (first, size) = (first.round().as_ivec2(), size.round().as_ivec2());
+    Some((0..size.x).flat_map(|x| (0..size.y).map(move |y| first + IVec2::new(x, y))).collect())
+}
+
+fn cell_center(cell: IVec2) -> Vec2 {
+    GRID_ORIGIN + cell.as_vec2() * TILE_SIZE
+}
+
+fn neighbor_mask(ground: &HashSet<IVec2>, cell: IVec2) -> usize {
+    [(IVec2::Y, 1), (IVec2::X, 2), (IVec2::NEG_Y, 4), (IVec2::NEG_X, 8)].into_iter()
+        .filter(|(offset, _)| ground.contains(&(cell + *offset)))
+        .map(|(_, bit)| bit)
+        .sum()
+}
+
+/// Re-tiles every plain stone block whenever a block is spawned, moved, resized or removed,
+/// since any of those can change a neighbor's frame. Moving platforms aren't tiled, or
+/// they'd trigger this every tick.
+fn autotile_blocks(
+    mut commands: Commands,
+    atlas: Res<TileAtlas>,
+    ground: Query<(Entity, &Position, &Shape, &SurfaceKind), (With<Block>, Without<Gate>, Without<Cannon>, Without<Magnet>, Without<MovingPlatform>, Without<Checkpoint>, Without<Slope>, Without<Breakable>, Without<Ladder>, Without<Exit>)>,
+    changed: Query<(), (With<Block>, Without<MovingPlatform>, Or<(Added<Block>, Changed<Position>, Changed<Shape>)>)>,
+    mut removed: RemovedComponents<Block>,
+    tiles: Query<Entity, With<TileSprite>>,
+) {
+    if changed.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+    for entity in &tiles {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let blocks: Vec<_> = ground.iter()
+        .filter(|(_, _, _, surface)| **surface == SurfaceKind::Stone)
+        .filter_map(|(entity, position, shape, _)| {
+            grid_cells(position.0, shape.0).map(|cells| (entity, position.0, cells))
+        })
+        .collect();
+    let cells: HashSet<IVec2> = blocks.iter().flat_map(|(_, _, cells)| cells.iter().copied()).collect();
+
+    for (entity, position, block_cells) in &blocks {
+        commands.entity(*entity).with_children(|block| {
+            for cell in block_cells {
+                block.spawn((
+                    SpriteBundle {
+                        texture: atlas.texture.clone(),
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::splat(TILE_SIZE)),
+                            ..default()
+                        },
+                        transform: Transform::from_translation((cell_center(*cell) - *position).extend(0.01)),
+                        ..default()
+                    },
+                    TextureAtlas {
+                        layout: atlas.layout.clone(),
+                        index: neighbor_mask(&cells, *cell),
+                    },
+                    TileSprite,
+                ));
+            }
+        });
+    }
+}
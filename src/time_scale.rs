@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+
+use crate::damage::apply_damage;
+use crate::events::Damaged;
+use crate::level::ResetLevel;
+use crate::player::Player;
+use crate::{ease_factor, flerp, GameState};
+
+/// How slow the game goes for a moment when a player is hurt.
+const DAMAGE_SCALE: f32 = 0.3;
+/// Real seconds the slow motion from a hit lasts, not counting the ramp back.
+const DAMAGE_SLOWMO_SECS: f32 = 0.4;
+/// How quickly the scale eases toward where it's headed, as an exponential rate per real
+/// second.
+const RAMP_RATE: f32 = 12.;
+/// Near enough to normal speed to call it normal.
+const SNAP_EPSILON: f32 = 0.005;
+#[cfg(feature = "debug-tools")]
+const DEBUG_TOGGLE_KEY: KeyCode = KeyCode::F7;
+#[cfg(feature = "debug-tools")]
+const DEBUG_SCALE: f32 = 0.25;
+
+pub struct TimeScalePlugin;
+
+impl Plugin for TimeScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeScale>()
+            .add_systems(FixedUpdate, slow_on_damage.after(apply_damage))
+            .add_systems(Update, apply_time_scale.run_if(not(in_state(GameState::Paused))))
+            .add_systems(OnEnter(GameState::Restarting), reset_time_scale.in_set(ResetLevel));
+        #[cfg(feature = "debug-tools")]
+        app.add_systems(Update, toggle_debug_slowmo.before(apply_time_scale));
+    }
+}
+
+/// How fast the simulation runs compared to real time, 1 being normal. It's applied as
+/// `Time<Virtual>`'s speed, so every fixed tick stays 1/144 s long and only the ticks a
+/// second change: physics, AI, particles and every `GameTimer` slow together with no
+/// per-system scaling, while the UI and anything else on `Time<Real>` doesn't. Presses
+/// in frames with no tick are held for the next one, so input stays responsive.
+#[derive(Resource)]
+pub struct TimeScale {
+    current: f32,
+    /// The slow motion under way, as (scale, real seconds left).
+    effect: Option<(f32, f32)>,
+    #[cfg(feature = "debug-tools")]
+    debug: bool,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self {
+            current: 1.,
+            effect: None,
+            #[cfg(feature = "debug-tools")]
+            debug: false,
+        }
+    }
+}
+
+impl TimeScale {
+    /// Eases down to `scale` and holds it for `secs` of real time before easing back. A
+    /// slower effect replaces a faster one; a faster one doesn't interrupt it.
+    pub fn slow_down(&mut self, scale: f32, secs: f32) {
+        if self.effect.is_some_and(|(current, _)| current < scale) {
+            return;
+        }
+        self.effect = Some((scale.max(0.), secs));
+    }
+
+    fn target(&self) -> f32 {
+        let effect = self.effect.map_or(1., |(scale, _)| scale);
+        #[cfg(feature = "debug-tools")]
+        if self.debug {
+            return effect.min(DEBUG_SCALE);
+        }
+        effect
+    }
+}
+
+fn slow_on_damage(mut damaged: EventReader<Damaged>, players: Query<(), With<Player>>, mut time_scale: ResMut<TimeScale>) {
+    if damaged.read().any(|event| players.contains(event.entity)) {
+        time_scale.slow_down(DAMAGE_SCALE, DAMAGE_SLOWMO_SECS);
+    }
+}
+
+fn apply_time_scale(mut time_scale: ResMut<TimeScale>, mut virtual_time: ResMut<Time<Virtual>>, real_time: Res<Time<Real>>) {
+    let dt = real_time.delta_seconds();
+    if let Some((_, secs)) = &mut time_scale.effect {
+        *secs -= dt;
+        if *secs <= 0. {
+            time_scale.effect = None;
+        }
+    }
+    let target = time_scale.target();
+    let eased = flerp(time_scale.current, target, ease_factor(RAMP_RATE, dt));
+    time_scale.current = if (eased - target).abs() < SNAP_EPSILON { target } else { eased };
+    if virtual_time.relative_speed() != time_scale.current {
+        virtual_time.set_relative_speed(time_scale.current);
+    }
+}
+
+/// A restart drops whatever slow motion was running, but not the debug toggle.
+fn reset_time_scale(mut time_scale: ResMut<TimeScale>, mut virtual_time: ResMut<Time<Virtual>>) {
+    time_scale.effect = None;
+    time_scale.current = time_scale.target();
+    virtual_time.set_relative_speed(time_scale.current);
+}
+
+/// Runs the game at a quarter speed, for watching collisions play out tick by tick.
+#[cfg(feature = "debug-tools")]
+fn toggle_debug_slowmo(kb_input: Res<ButtonInput<KeyCode>>, mut time_scale: ResMut<TimeScale>) {
+    if kb_input.just_pressed(DEBUG_TOGGLE_KEY) {
+        time_scale.debug = !time_scale.debug;
+    }
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+
+/// A gameplay timer. It only moves when ticked, and by convention it's ticked exactly once
+/// per fixed tick, in `FixedUpdate`, with that tick's `Time` delta. That way it pauses with
+/// the simulation and follows any time scale the same way physics does, instead of drifting
+/// against it the way a timer ticked from render time would.
+///
+/// Presentation-only timers (UI animation, debug refreshes) keep using bevy's `Timer`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameTimer {
+    duration: f32,
+    elapsed: f32,
+    repeating: bool,
+    just_finished: bool,
+}
+
+impl GameTimer {
+    /// Finishes once after `secs` and stays finished until reset.
+    pub fn once(secs: f32) -> Self {
+        Self { duration: secs, ..Self::default() }
+    }
+
+    /// Finishes every `secs`, wrapping around each time.
+    pub fn repeating(secs: f32) -> Self {
+        Self { duration: secs, repeating: true, ..Self::default() }
+    }
+
+    pub fn tick(&mut self, dt: f32) -> &mut Self {
+        if !self.repeating && self.elapsed >= self.duration {
+            self.just_finished = false;
+            return self;
+        }
+        self.elapsed += dt;
+        self.just_finished = self.elapsed >= self.duration;
+        if self.just_finished {
+            self.elapsed = if self.repeating && self.duration > 0. {
+                self.elapsed % self.duration
+            } else {
+                self.duration
+            };
+        }
+        self
+    }
+
+    /// A one-shot timer stays finished; a repeating one is only finished on the tick it wraps.
+    pub fn finished(&self) -> bool {
+        if self.repeating {
+            self.just_finished
+        } else {
+            self.elapsed >= self.duration
+        }
+    }
+
+    /// Whether this tick is the one that finished the timer.
+    pub fn just_finished(&self) -> bool {
+        self.just_finished
+    }
+
+    /// How far through the current cycle the timer is, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        if self.duration > 0. {
+            (self.elapsed / self.duration).min(1.)
+        } else {
+            1.
+        }
+    }
+
+    /// Seconds left in the current cycle.
+    pub fn remaining(&self) -> f32 {
+        (self.duration - self.elapsed).max(0.)
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = 0.;
+        self.just_finished = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::damage::Invulnerable;
+    use crate::headless::build_headless_app;
+    use crate::player::Player;
+    use crate::time_scale::TimeScale;
+    use crate::world::{BlockData, WorldData};
+
+    /// Makes the player invulnerable for a second.
+    fn protect(app: &mut App) -> Entity {
+        let mut players = app.world_mut().query_filtered::<Entity, With<Player>>();
+        let player = players.single(app.world());
+        app.world_mut().entity_mut(player).insert(Invulnerable::new(1.));
+        player
+    }
+
+    /// Frames, of 1/144 s each, until `player`'s invulnerability runs out.
+    fn frames_to_expire(app: &mut App, player: Entity) -> usize {
+        (1..=1440).find(|_| {
+            app.update();
+            !app.world().entity(player).contains::<Invulnerable>()
+        }).expect("never ran out")
+    }
+
+    fn level() -> App {
+        let mut app = build_headless_app(WorldData(vec![BlockData::new(Vec2::new(0., -200.), Vec2::new(3000., 50.))]));
+        for _ in 0..144 {
+            app.update();
+        }
+        app
+    }
+
+    #[test]
+    fn timers_hold_still_while_paused() {
+        let mut app = level();
+        let player = protect(&mut app);
+        for _ in 0..72 {
+            app.update();
+        }
+        app.world_mut().resource_mut::<Time<Virtual>>().pause();
+        for _ in 0..288 {
+            app.update();
+        }
+        app.world_mut().resource_mut::<Time<Virtual>>().unpause();
+        // The other half second, with nothing counted while paused.
+        let rest = frames_to_expire(&mut app, player);
+        assert!((72..=74).contains(&rest), "ran out {rest} frames after unpausing");
+    }
+
+    #[test]
+    fn timers_follow_the_time_scale() {
+        let mut app = level();
+        let player = protect(&mut app);
+        let normal = frames_to_expire(&mut app, player);
+        app.world_mut().resource_mut::<TimeScale>().slow_down(0.25, 60.);
+        // Let it ease all the way down first.
+        for _ in 0..288 {
+            app.update();
+        }
+        let player = protect(&mut app);
+        let slowed = frames_to_expire(&mut app, player);
+        assert!(slowed.abs_diff(normal * 4) <= 4, "{slowed} frames at a quarter speed against {normal}");
+    }
+}
@@ -0,0 +1,254 @@
+use bevy::math::bounding::{Aabb2d, BoundingCircle, IntersectsVolume};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::damage::{apply_damage, Invulnerable};
+use crate::events::{DamageEvent, OriginShifted};
+use crate::level::Downed;
+use crate::origin::FollowOrigin;
+use crate::physics::{move_bodies, overlaps, Collider, LayerMask, PhysicsSet, PhysicsWorld, Position, Rotation, Shape, Velocity};
+use crate::player::Player;
+use crate::timer::GameTimer;
+
+/// Seconds a crusher sits at the bottom of its slam before winding back up.
+const SLAM_REST_SECS: f32 = 0.4;
+/// How far into a crusher's bottom face a player's head can already be and still count
+/// as being under it.
+const CRUSH_TOLERANCE: f32 = 1.;
+/// Thickness of the red strip along a crusher's dangerous face.
+pub const CRUSHER_EDGE: f32 = 6.;
+pub const CRUSHER_EDGE_COLOR: Color = Color::srgb(0.75, 0.2, 0.2);
+pub const BLADE_CHAIN_COLOR: Color = Color::srgb(0.45, 0.45, 0.5);
+pub const BLADE_CHAIN_WIDTH: f32 = 4.;
+
+pub struct TrapPlugin;
+
+impl Plugin for TrapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, (
+            (move_crushers, swing_blades).before(move_bodies),
+            crush_players.after(PhysicsSet::Integrate).before(PhysicsSet::Resolve),
+            blade_contact.after(PhysicsSet::Resolve).before(apply_damage),
+        ))
+            .add_systems(FixedPostUpdate, shift_traps.in_set(FollowOrigin));
+    }
+}
+
+/// How a crusher block moves. It rests where it's placed, slams straight down, waits at
+/// the bottom and winds back up, over and over.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct CrusherTrack {
+    /// How far below where it's placed it slams down to, in pixels.
+    pub drop: f32,
+    /// Pixels per second on the way down.
+    pub slam_speed: f32,
+    /// Pixels per second on the way back up.
+    pub retract_speed: f32,
+    /// Seconds it rests at the top between slams.
+    pub wait: f32,
+    /// Extra seconds before the first slam, to stagger a row of crushers.
+    #[serde(default)]
+    pub delay: f32,
+}
+
+#[derive(Clone, Debug)]
+enum CrusherPhase {
+    Waiting(GameTimer),
+    Slamming,
+    Resting(GameTimer),
+    Retracting,
+}
+
+/// A solid block following a `CrusherTrack`. Its top is safe to stand on; its bottom face
+/// kills on the way down, and anyone caught between it and the ground is crushed.
+#[derive(Component)]
+pub struct Crusher {
+    pub track: CrusherTrack,
+    /// The y it rests at, where it was placed.
+    top: f32,
+    phase: CrusherPhase,
+    /// How far it moved this tick, negative on the way down.
+    pub delta: f32,
+}
+
+impl Crusher {
+    pub fn new(track: CrusherTrack, position: Vec2) -> Self {
+        Self {
+            track,
+            top: position.y,
+            phase: CrusherPhase::Waiting(GameTimer::once(track.wait + track.delay)),
+            delta: 0.,
+        }
+    }
+}
+
+/// How a blade swings. The block's position is the pivot it hangs from.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct BladeSwing {
+    /// From the pivot to the middle of the blade, in pixels.
+    pub length: f32,
+    /// Furthest it swings either side of hanging straight down, in degrees.
+    pub arc: f32,
+    /// Seconds for a full swing there and back.
+    pub period: f32,
+    /// How far through its swing it starts, from 0 to 1, to stagger neighbouring blades.
+    #[serde(default)]
+    pub phase: f32,
+}
+
+impl BladeSwing {
+    /// The blade's angle from hanging straight down, `secs` into its swing.
+    fn angle_at(&self, secs: f32) -> f32 {
+        let cycle = secs / self.period.max(f32::EPSILON) + self.phase;
+        self.arc.to_radians() * (cycle * std::f32::consts::TAU).sin()
+    }
+
+    /// Where the middle of the blade is at `angle`.
+    pub fn point_at(&self, pivot: Vec2, angle: f32) -> Vec2 {
+        pivot + Vec2::new(angle.sin(), -angle.cos()) * self.length
+    }
+}
+
+/// A round blade on a chain, following a `BladeSwing` around `pivot`. It isn't solid:
+/// it kills on touch, like spikes, and everything else passes through it. Its rotation
+/// keeps its local up pointing at the pivot, so the chain can hang from it as a child.
+#[derive(Component)]
+pub struct SwingingBlade {
+    pub swing: BladeSwing,
+    pivot: Vec2,
+    secs: f32,
+}
+
+impl SwingingBlade {
+    pub fn new(swing: BladeSwing, pivot: Vec2) -> Self {
+        Self { swing, pivot, secs: 0. }
+    }
+
+    /// Where the blade starts out, and its rotation there.
+    pub fn start(&self) -> (Vec2, f32) {
+        let angle = self.swing.angle_at(0.);
+        (self.swing.point_at(self.pivot, angle), angle)
+    }
+}
+
+fn shift_traps(
+    mut shifted: EventReader<OriginShifted>,
+    mut crushers: Query<&mut Crusher>,
+    mut blades: Query<&mut SwingingBlade>,
+) {
+    for event in shifted.read() {
+        for mut crusher in &mut crushers {
+            crusher.top -= event.offset.y;
+        }
+        for mut blade in &mut blades {
+            blade.pivot -= event.offset;
+        }
+    }
+}
+
+/// Like moving platforms, crushers are moved straight to where their track puts them
+/// rather than by a velocity.
+fn move_crushers(mut crushers: Query<(&mut Crusher, &mut Position)>, time: Res<Time>) {
+    let dt = time.delta_seconds();
+    for (mut crusher, mut position) in &mut crushers {
+        let crusher = &mut *crusher;
+        let bottom = crusher.top - crusher.track.drop.max(0.);
+        let mut y = position.0.y;
+        match &mut crusher.phase {
+            CrusherPhase::Waiting(timer) => {
+                if timer.tick(dt).finished() {
+                    crusher.phase = CrusherPhase::Slamming;
+                }
+            }
+            CrusherPhase::Slamming => {
+                y = (y - crusher.track.slam_speed * dt).max(bottom);
+                if y <= bottom {
+                    crusher.phase = CrusherPhase::Resting(GameTimer::once(SLAM_REST_SECS));
+                }
+            }
+            CrusherPhase::Resting(timer) => {
+                if timer.tick(dt).finished() {
+                    crusher.phase = CrusherPhase::Retracting;
+                }
+            }
+            CrusherPhase::Retracting => {
+                y = (y + crusher.track.retract_speed * dt).min(crusher.top);
+                if y >= crusher.top {
+                    crusher.phase = CrusherPhase::Waiting(GameTimer::once(crusher.track.wait));
+                }
+            }
+        }
+        crusher.delta = y - position.0.y;
+        position.0.y = y;
+    }
+}
+
+/// A crusher coming down on a player drags them down with it, and kills them unless
+/// they're `Invulnerable`. If there's no room underneath to drag them into, they're
+/// crushed whether they're protected or not, rather than being shoved into the floor.
+/// Runs before collisions are resolved, so the push out of the crusher never gets the
+/// chance to bury anyone.
+fn crush_players(
+    crushers: Query<(Entity, &Crusher, &Position, &Shape)>,
+    mut players: Query<(Entity, &mut Position, &mut Velocity, &Shape, Has<Invulnerable>), (With<Player>, Without<Collider>, Without<Crusher>, Without<Downed>)>,
+    physics: PhysicsWorld,
+    mut damage: EventWriter<DamageEvent>,
+    time: Res<Time>,
+) {
+    for (crusher_entity, crusher, crusher_pos, crusher_shape) in &crushers {
+        if crusher.delta >= 0. {
+            continue;
+        }
+        let crusher_aabb = Aabb2d::new(crusher_pos.0, crusher_shape.0 / 2.);
+        for (entity, mut position, mut velocity, shape, invulnerable) in &mut players {
+            let half = shape.0 / 2.;
+            // Only the bottom face: the player's head has to have been under it before
+            // either of them moved. Anyone it reached from the side or is carrying on
+            // top is left to ordinary collisions.
+            let head_before = position.0.y + half.y - velocity.0.y * time.delta_seconds();
+            let bottom_before = crusher_aabb.min.y - crusher.delta;
+            if head_before > bottom_before + CRUSH_TOLERANCE || !overlaps(Aabb2d::new(position.0, half), crusher_aabb) {
+                continue;
+            }
+            let pushed = Vec2::new(position.0.x, crusher_aabb.min.y - half.y);
+            let pinned = physics.overlap_aabb(Aabb2d::new(pushed, half), LayerMask::ALL)
+                .iter()
+                .any(|other| *other != crusher_entity);
+            if pinned {
+                damage.send(DamageEvent::lethal(entity));
+                continue;
+            }
+            position.0 = pushed;
+            velocity.0.y = velocity.0.y.min(crusher.delta / time.delta_seconds());
+            if !invulnerable {
+                damage.send(DamageEvent::lethal(entity));
+            }
+        }
+    }
+}
+
+fn swing_blades(mut blades: Query<(&mut SwingingBlade, &mut Position, &mut Rotation)>, time: Res<Time>) {
+    for (mut blade, mut position, mut rotation) in &mut blades {
+        blade.secs = (blade.secs + time.delta_seconds()) % blade.swing.period.max(f32::EPSILON);
+        let angle = blade.swing.angle_at(blade.secs);
+        position.0 = blade.swing.point_at(blade.pivot, angle);
+        rotation.0 = angle;
+    }
+}
+
+/// Blades are round, so they hit the same whichever way they've swung. A freshly
+/// respawned player can touch one safely while `Invulnerable`, as with spikes.
+fn blade_contact(
+    blades: Query<(&Position, &Shape), With<SwingingBlade>>,
+    players: Query<(Entity, &Position, &Shape), (With<Player>, Without<Invulnerable>, Without<Downed>)>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    for (entity, player_pos, player_shape) in &players {
+        let body = Aabb2d::new(player_pos.0, player_shape.0 / 2.);
+        let hit = blades.iter()
+            .any(|(position, shape)| BoundingCircle::new(position.0, shape.0.min_element() / 2.).intersects(&body));
+        if hit {
+            damage.send(DamageEvent::lethal(entity));
+        }
+    }
+}
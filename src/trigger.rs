@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::debug::DebugLabel;
+use crate::events::{TriggerEnter, TriggerExit};
+use crate::level::{LevelEntity, ResetLevel};
+use crate::physics::{DynamicBody, PhysicsSet, Position, Shape};
+use crate::world::WorldData;
+use crate::GameState;
+
+pub struct TriggerPlugin;
+
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TriggerOverlaps>()
+            .add_systems(OnEnter(GameState::Restarting), spawn_trigger_zones.after(ResetLevel))
+            .add_systems(FixedUpdate, detect_triggers.after(PhysicsSet::Resolve));
+    }
+}
+
+/// One trigger zone from the level file.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TriggerZoneData {
+    pub position: Vec2,
+    pub shape: Vec2,
+    /// What the zone is for, so a system can pick out the zones it cares about.
+    pub tag: String,
+}
+
+/// Trigger zones in the level, stored next to the `WorldData` they belong to.
+#[derive(Component, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct TriggerZoneSpawns(pub Vec<TriggerZoneData>);
+
+/// A region of `Position` and `Shape` that reports dynamic bodies going in and out with
+/// `TriggerEnter` and `TriggerExit`. It isn't a `Collider`, so nothing ever bumps into it.
+#[derive(Component, Clone, Debug)]
+pub struct TriggerZone {
+    pub tag: String,
+}
+
+/// The (zone, body) pairs that overlapped as of the last tick.
+#[derive(Resource, Default)]
+pub struct TriggerOverlaps(HashSet<(Entity, Entity)>);
+
+fn spawn_trigger_zones(mut commands: Commands, world_data: Query<&TriggerZoneSpawns, With<WorldData>>) {
+    let Ok(spawns) = world_data.get_single() else {
+        return;
+    };
+    for data in &spawns.0 {
+        spawn_trigger_zone(&mut commands, data);
+    }
+}
+
+pub fn spawn_trigger_zone(commands: &mut Commands, data: &TriggerZoneData) {
+    commands.spawn((
+        TriggerZone { tag: data.tag.clone() },
+        Position(data.position),
+        Shape(data.shape),
+        LevelEntity,
+        DebugLabel("trigger zones"),
+    ));
+}
+
+/// Sends one `TriggerEnter` when a body starts overlapping a zone and one `TriggerExit`
+/// when it stops, however many ticks it stays in between. A pair where the zone or the
+/// body has been despawned is forgotten without an exit, since there's nothing left for a
+/// reader to look up.
+pub fn detect_triggers(
+    zones: Query<(Entity, &Position, &Shape), With<TriggerZone>>,
+    bodies: Query<(Entity, &Position, &Shape), With<DynamicBody>>,
+    mut overlaps: ResMut<TriggerOverlaps>,
+    mut entered: EventWriter<TriggerEnter>,
+    mut exited: EventWriter<TriggerExit>,
+) {
+    let mut now = HashSet::new();
+    for (zone, zone_pos, zone_shape) in &zones {
+        let zone_aabb = Aabb2d::new(zone_pos.0, zone_shape.0 / 2.);
+        for (body, body_pos, body_shape) in &bodies {
+            if zone_aabb.intersects(&Aabb2d::new(body_pos.0, body_shape.0 / 2.)) {
+                now.insert((zone, body));
+            }
+        }
+    }
+    for &(zone, body) in now.difference(&overlaps.0) {
+        entered.send(TriggerEnter { zone, body });
+    }
+    for &(zone, body) in overlaps.0.difference(&now) {
+        if zones.contains(zone) && bodies.contains(body) {
+            exited.send(TriggerExit { zone, body });
+        }
+    }
+    overlaps.0 = now;
+}
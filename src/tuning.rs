@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use crate::movement::MovementConfig;
+
+const TOGGLE_KEY: KeyCode = KeyCode::F7;
+const SAVE_KEY: KeyCode = KeyCode::F8;
+
+/// The values the panel can change, with how far one press of + or - moves each.
+/// Holding Shift moves ten times as far.
+const TUNABLES: [(&str, f32, fn(&mut MovementConfig) -> &mut f32); 10] = [
+    ("max speed", 36., |config| &mut config.max_speed),
+    ("run speed", 36., |config| &mut config.run_speed),
+    ("accel", 0.5, |config| &mut config.accel),
+    ("decel", 0.5, |config| &mut config.decel),
+    ("run accel", 0.5, |config| &mut config.run_accel),
+    ("air accel", 0.5, |config| &mut config.air_accel),
+    ("air decel", 0.5, |config| &mut config.air_decel),
+    ("jump strength", 36., |config| &mut config.jump_strength),
+    ("gravity", 200., |config| &mut config.gravity),
+    ("squash snappiness", 0.5, |config| &mut config.squash_snappiness),
+];
+
+/// F7 opens a panel for tuning `MovementConfig` while playing: PageUp and PageDown pick a
+/// row, - and + change it, and F8 writes the lot to `config/movement.ron`. The game keeps
+/// running underneath so each change can be felt straight away.
+pub struct TuningPlugin;
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TuningPanel>()
+            .add_systems(Startup, spawn_tuning_panel)
+            .add_systems(Update, (tune_movement, refresh_tuning_panel).chain());
+    }
+}
+
+#[derive(Resource, Default)]
+struct TuningPanel {
+    open: bool,
+    selected: usize,
+    /// Result of the last F8, shown under the rows.
+    status: String,
+}
+
+#[derive(Component)]
+struct TuningText;
+
+fn spawn_tuning_panel(mut commands: Commands) {
+    commands.spawn((TextBundle {
+        text: Text::from_section("", TextStyle {
+            font_size: 16.,
+            ..default()
+        }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.),
+            right: Val::Px(8.),
+            padding: UiRect::all(Val::Px(6.)),
+            ..default()
+        },
+        background_color: Color::srgba(0., 0., 0., 0.6).into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Global(20),
+        ..default()
+    }, TuningText));
+}
+
+fn tune_movement(
+    mut panel: ResMut<TuningPanel>,
+    mut config: ResMut<MovementConfig>,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        panel.open = !panel.open;
+        panel.status.clear();
+    }
+    if !panel.open {
+        return;
+    }
+    let rows = TUNABLES.len();
+    if keys.just_pressed(KeyCode::PageUp) {
+        panel.selected = (panel.selected + rows - 1) % rows;
+    } else if keys.just_pressed(KeyCode::PageDown) {
+        panel.selected = (panel.selected + 1) % rows;
+    }
+    let direction = if keys.just_pressed(KeyCode::Equal) || keys.just_pressed(KeyCode::NumpadAdd) {
+        1.
+    } else if keys.just_pressed(KeyCode::Minus) || keys.just_pressed(KeyCode::NumpadSubtract) {
+        -1.
+    } else {
+        0.
+    };
+    if direction != 0. {
+        let (_, step, field) = TUNABLES[panel.selected];
+        let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        *field(&mut config) += direction * step * if shift { 10. } else { 1. };
+    }
+    if keys.just_pressed(SAVE_KEY) {
+        panel.status = match config.save() {
+            Ok(()) => "saved to config/movement.ron".into(),
+            Err(error) => format!("couldn't save: {error}"),
+        };
+    }
+}
+
+fn refresh_tuning_panel(
+    panel: Res<TuningPanel>,
+    config: Res<MovementConfig>,
+    mut text: Query<(&mut Text, &mut Visibility), With<TuningText>>,
+) {
+    if !panel.is_changed() && !config.is_changed() {
+        return;
+    }
+    let Ok((mut text, mut visibility)) = text.get_single_mut() else {
+        return;
+    };
+    *visibility = if panel.open { Visibility::Inherited } else { Visibility::Hidden };
+    // The rows only read the config, but `field` hands out `&mut`.
+    let mut config = config.clone();
+    let mut value = String::from("Movement tuning\n");
+    for (row, (name, _, field)) in TUNABLES.iter().enumerate() {
+        let cursor = if row == panel.selected { ">" } else { " " };
+        value += &format!("{cursor} {name}: {:.2}\n", field(&mut config));
+    }
+    value += "\nPgUp/PgDn: choose   -/+: change (Shift: x10)   F8: save";
+    if !panel.status.is_empty() {
+        value += &format!("\n{}", panel.status);
+    }
+    text.sections[0].value = value;
+}
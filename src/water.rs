@@ -0,0 +1,204 @@
+use bevy::prelude::*;
+
+use crate::cleanup::{fade_away, FadeStyle};
+use crate::debug::DebugLabel;
+use crate::level::{LevelEntity, ResetLevel};
+use crate::particles::Particle;
+use crate::physics::{gravitate, move_bodies, GlobalGravity, Gravitated, Position, Rotation, Shape, Velocity, ZOrder};
+use crate::timer::GameTimer;
+use crate::world::WorldData;
+use crate::GameState;
+
+/// Share of gravity cancelled out while fully submerged.
+const BUOYANCY: f32 = 0.7;
+/// How quickly vertical velocity bleeds away fully submerged, as an exponential rate per
+/// second.
+const WATER_DRAG: f32 = 4.4;
+/// The same for horizontal velocity, stronger so running into water bogs down.
+const WATER_DRAG_X: f32 = 10.5;
+/// Fastest a fully submerged body sinks, in pixels per second. Shallower bodies can fall
+/// faster, in proportion.
+const WATER_MAX_FALL: f32 = 288.;
+/// Entering the water faster than this splashes, in pixels per second.
+const SPLASH_SPEED: f32 = 720.;
+/// Fraction of velocity kept through a splash.
+const SPLASH_DAMPING: f32 = 0.4;
+/// How hard a current pulls bodies up to its own speed, per second.
+const CURRENT_PULL: f32 = 2.;
+/// Nothing moves faster than this in water, current and swimming combined.
+const WATER_MAX_SPEED: f32 = 1008.;
+const FLECK_INTERVAL_SECS: f32 = 0.1;
+const FLECK_LIFETIME_SECS: f32 = 0.8;
+
+pub struct WaterPlugin;
+
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Restarting), spawn_water.after(ResetLevel))
+            .add_systems(FixedUpdate, (
+                track_water_overlap.after(move_bodies),
+                swim.before(gravitate),
+                spawn_current_flecks,
+            ));
+    }
+}
+
+#[derive(Debug)]
+pub struct WaterData {
+    pub position: Vec2,
+    pub shape: Vec2,
+    /// Flow velocity in pixels per second; bodies inside are pulled toward it.
+    pub current: Option<Vec2>,
+}
+
+/// Water volumes placed in the level, stored next to the `WorldData` they belong to.
+#[derive(Component)]
+pub struct WaterSpawns(pub Vec<WaterData>);
+
+#[derive(Component)]
+pub struct Water {
+    pub current: Option<Vec2>,
+    fleck_timer: GameTimer,
+    flecks_spawned: u32,
+}
+
+/// The water volume a gravitated body is currently overlapping, and what fraction of its
+/// height is under, from just above 0 (feet wet) to 1 (fully submerged).
+#[derive(Component)]
+pub struct InWater {
+    pub volume: Entity,
+    pub submerged: f32,
+}
+
+fn spawn_water(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    world_data: Query<&WaterSpawns, With<WorldData>>,
+) {
+    let Ok(spawns) = world_data.get_single() else {
+        return;
+    };
+    let material = materials.add(Color::srgba(0.2, 0.4, 0.9, 0.4));
+    for data in &spawns.0 {
+        commands.spawn((
+            Water {
+                current: data.current,
+                fleck_timer: GameTimer::repeating(FLECK_INTERVAL_SECS),
+                flecks_spawned: 0,
+            },
+            Position(data.position),
+            Shape(data.shape),
+            Rotation(0.),
+            ZOrder(0.15),
+            ColorMesh2dBundle {
+                mesh: meshes.add(Rectangle::new(data.shape.x, data.shape.y)).into(),
+                material: material.clone(),
+                ..default()
+            },
+            LevelEntity,
+            DebugLabel("water"),
+        ));
+    }
+}
+
+/// Tracks which volume each gravitated body is in and how deep. A body coming in faster
+/// than `SPLASH_SPEED` loses most of its speed to the splash.
+fn track_water_overlap(
+    mut commands: Commands,
+    mut bodies: Query<(Entity, &Position, &Shape, &mut Velocity, Option<&mut InWater>), With<Gravitated>>,
+    water: Query<(Entity, &Position, &Shape), With<Water>>,
+) {
+    for (entity, position, shape, mut velocity, in_water) in &mut bodies {
+        let body = Rect::from_center_size(position.0, shape.0);
+        let submerged = water.iter()
+            .filter_map(|(volume, water_pos, water_shape)| {
+                let overlap = body.intersect(Rect::from_center_size(water_pos.0, water_shape.0));
+                (!overlap.is_empty()).then(|| (volume, overlap.height() / shape.0.y))
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        match (submerged, in_water) {
+            (Some((volume, submerged)), Some(mut in_water)) => {
+                in_water.volume = volume;
+                in_water.submerged = submerged;
+            }
+            (Some((volume, submerged)), None) => {
+                if velocity.0.length() > SPLASH_SPEED {
+                    velocity.0 *= SPLASH_DAMPING;
+                }
+                commands.entity(entity).insert(InWater { volume, submerged });
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<InWater>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// Buoyancy, drag and the fall speed cap all scale with how much of the body is under,
+/// so wading and floating at the surface settle smoothly instead of snapping.
+fn swim(
+    mut bodies: Query<(&mut Velocity, &InWater)>,
+    water: Query<&Water>,
+    gravity: Res<GlobalGravity>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (mut velocity, in_water) in &mut bodies {
+        let Ok(water) = water.get(in_water.volume) else {
+            continue;
+        };
+        let depth = in_water.submerged;
+        velocity.0 *= (-Vec2::new(WATER_DRAG_X, WATER_DRAG) * depth * dt).exp();
+        velocity.0.y -= gravity.0.y * BUOYANCY * depth * dt;
+        velocity.0.y = velocity.0.y.max(-WATER_MAX_FALL / depth);
+        if let Some(current) = water.current {
+            let flow_speed = current.length();
+            let along = velocity.0.dot(current.normalize_or_zero());
+            if along < flow_speed {
+                velocity.0 += current * CURRENT_PULL * depth * dt;
+            }
+        }
+        velocity.0 = velocity.0.clamp_length_max(WATER_MAX_SPEED);
+    }
+}
+
+/// Flecks drifting with the current so its direction reads at a glance.
+fn spawn_current_flecks(
+    mut commands: Commands,
+    mut water: Query<(&mut Water, &Position, &Shape)>,
+    time: Res<Time>,
+) {
+    for (mut water, position, shape) in &mut water {
+        let Some(current) = water.current else {
+            continue;
+        };
+        if !water.fleck_timer.tick(time.delta_seconds()).just_finished() {
+            continue;
+        }
+        water.flecks_spawned += 1;
+        // Low-discrepancy spread over the volume, so flecks fill it evenly without an RNG.
+        let n = water.flecks_spawned as f32;
+        let offset = Vec2::new((n * 0.618_034).fract(), (n * 0.754_878).fract()) - 0.5;
+        commands.spawn((
+            Particle {
+                velocity: current,
+                gravity: 0.,
+            },
+            fade_away(FLECK_LIFETIME_SECS, FadeStyle::Alpha),
+            Position(position.0 + offset * shape.0),
+            Rotation(current.to_angle()),
+            ZOrder(0.16),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgba(0.8, 0.9, 1., 0.6),
+                    custom_size: Some(Vec2::new(8., 2.)),
+                    ..default()
+                },
+                ..default()
+            },
+            LevelEntity,
+        ));
+    }
+}
@@ -0,0 +1,129 @@
+use bevy::math::bounding::{Aabb2d, IntersectsVolume};
+use bevy::prelude::*;
+
+use crate::cleanup::{fade_away, FadeStyle};
+use crate::level::LevelEntity;
+use crate::particles::Particle;
+use crate::physics::{clamp_velocity, gravitate, DynamicBody, Gravitated, Position, Rotation, Shape, Velocity, ZOrder};
+use crate::timer::GameTimer;
+
+pub const WIND_COLOR: Color = Color::srgba(0.85, 0.9, 1., 0.12);
+/// The most horizontal speed wind can give a body, in pixels per second. Past it the wind
+/// only holds the body there, so a long gust can't fling anything through the level.
+const MAX_WIND_SPEED: f32 = 600.;
+/// How quickly the player's wind-given speed dies away out of the wind, as an exponential
+/// rate per second.
+const WIND_DRIFT_DECAY: f32 = 3.;
+const STREAK_INTERVAL_SECS: f32 = 0.08;
+const STREAK_LIFETIME_SECS: f32 = 0.6;
+/// How fast streaks blow across a zone, in pixels per second, whatever its force.
+const STREAK_SPEED: f32 = 500.;
+
+pub struct WindPlugin;
+
+impl Plugin for WindPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, (
+            blow_wind.after(gravitate).before(clamp_velocity),
+            spawn_wind_streaks,
+        ));
+    }
+}
+
+/// A region from a `BlockKind::Wind` block that pushes every body inside it with `force`,
+/// in pixels per second squared, on top of gravity. It never collides.
+#[derive(Component)]
+pub struct WindZone {
+    pub force: Vec2,
+    streak_timer: GameTimer,
+    streaks_spawned: u32,
+}
+
+impl WindZone {
+    pub fn new(force: Vec2) -> Self {
+        Self {
+            force,
+            streak_timer: GameTimer::repeating(STREAK_INTERVAL_SECS),
+            streaks_spawned: 0,
+        }
+    }
+}
+
+/// The part of the player's horizontal velocity the wind gave them. `control_player`
+/// steers the rest, so walking into a headwind is slower rather than cancelling it out,
+/// and a jump across a windy gap drifts.
+#[derive(Component, Default)]
+pub struct WindDrift(pub f32);
+
+/// Vertical wind adds to velocity like a second gravity. Sideways wind does too, up to
+/// `MAX_WIND_SPEED`; for the player it goes through `WindDrift`, which eases back to
+/// nothing once they're out of it.
+fn blow_wind(
+    mut bodies: Query<(&Position, &Shape, &mut Velocity, Option<&mut WindDrift>), Or<(With<Gravitated>, With<DynamicBody>)>>,
+    zones: Query<(&Position, &Shape, &WindZone)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (position, shape, mut velocity, drift) in &mut bodies {
+        let body = Aabb2d::new(position.0, shape.0 / 2.);
+        let force: Vec2 = zones.iter()
+            .filter(|(zone_pos, zone_shape, _)| body.intersects(&Aabb2d::new(zone_pos.0, zone_shape.0 / 2.)))
+            .map(|(_, _, zone)| zone.force)
+            .sum();
+        velocity.0.y += force.y * dt;
+        match drift {
+            Some(mut drift) => {
+                let before = drift.0;
+                drift.0 = if force.x == 0. {
+                    drift.0 * (-WIND_DRIFT_DECAY * dt).exp()
+                } else {
+                    (drift.0 + force.x * dt).clamp(-MAX_WIND_SPEED, MAX_WIND_SPEED)
+                };
+                velocity.0.x += drift.0 - before;
+            }
+            None if force.x != 0. => {
+                let direction = force.x.signum();
+                let along = velocity.0.x * direction;
+                velocity.0.x = (along + force.x.abs() * dt).min(along.max(MAX_WIND_SPEED)) * direction;
+            }
+            None => {}
+        }
+    }
+}
+
+/// Streaks blowing along with the wind so its direction reads at a glance.
+fn spawn_wind_streaks(
+    mut commands: Commands,
+    mut zones: Query<(&mut WindZone, &Position, &Shape)>,
+    time: Res<Time>,
+) {
+    for (mut zone, position, shape) in &mut zones {
+        if zone.force == Vec2::ZERO || !zone.streak_timer.tick(time.delta_seconds()).just_finished() {
+            continue;
+        }
+        zone.streaks_spawned += 1;
+        // The same low-discrepancy spread as the flecks in water currents.
+        let n = zone.streaks_spawned as f32;
+        let offset = Vec2::new((n * 0.618_034).fract(), (n * 0.754_878).fract()) - 0.5;
+        let direction = zone.force.normalize();
+        commands.spawn((
+            Particle {
+                velocity: direction * STREAK_SPEED,
+                gravity: 0.,
+            },
+            fade_away(STREAK_LIFETIME_SECS, FadeStyle::Alpha),
+            Position(position.0 + offset * shape.0),
+            Rotation(direction.to_angle()),
+            ZOrder(0.16),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgba(1., 1., 1., 0.35),
+                    custom_size: Some(Vec2::new(14., 1.5)),
+                    ..default()
+                },
+                ..default()
+            },
+            LevelEntity,
+        ));
+    }
+}
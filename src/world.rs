@@ -0,0 +1,1168 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::blinking::{Blinking, BLINKING_COLOR};
+use crate::boost::{BoostPad, BOOST_PAD_COLOR, BOOST_PAD_SNAPPINESS};
+use crate::boss_bar::ShowBossBar;
+use crate::breakable::{Breakable, BREAKABLE_COLOR};
+use crate::camera_zone::CameraZoneSpawns;
+use crate::cannon::Cannon;
+use crate::checkpoint::{self, Checkpoint};
+use crate::coin::CoinSpawns;
+use crate::crates::{CrateData, CrateSpawns};
+use crate::crumbling::{Crumbling, CRUMBLING_COLOR};
+use crate::cutscene::{CameraSequence, Cutscene, CutsceneStep, LevelIntro, TriggeredCutscenes, TriggeredSequence};
+use crate::endless::{self, ENDLESS_LEVEL};
+use crate::enemy::{ChaseBehavior, EnemyData, EnemySpawns, PatrolRoute};
+use crate::exit::{Exit, EXIT_COLOR};
+use crate::gravity::{GravityFlipper, GravityZone, GRAVITY_FLIP_COLOR, GRAVITY_ZONE_COLOR, GRAVITY_ZONE_TAG};
+use crate::hazard::{Hazard, HazardSpawns, RisingHazard};
+use crate::key::{DoorSpawns, KeySpawns};
+use crate::ladder::{Ladder, LADDER_COLOR};
+use crate::level::{LevelEntity, LevelState, ResetLevel};
+use crate::magnet::{Magnet, MagnetPulse};
+use crate::movement::{Conveyor, StatId, StatModifier};
+use crate::music::{MusicLayer, MusicStem, MusicStems};
+use crate::parallax::ParallaxSpawns;
+use crate::pendulum::{PendulumRope, SwingingPlatform, ROPE_COLOR, ROPE_WIDTH};
+use crate::physics::{pass_direction, Collider, Collision, Gate, OneWayPlatform, Position, Rotation, Shape, Slope, Velocity, ZOrder};
+use crate::pickup::{PickupData, PickupSpawns};
+use crate::player::{PlayerSpawn, SquashStretch, VisShape};
+use crate::platform::{MovingPlatform, PlatformPath};
+use crate::portal::PortalSpawns;
+use crate::safe_room::{SafeRoomData, SafeRoomSpawns};
+use crate::script::{ScriptTrigger, ScriptTriggers, TriggerHints};
+use crate::sfx::{LoopingSfx, SfxKind};
+use crate::slime::Slime;
+use crate::spawn_zone::{EntitySpawn, SpawnTrigger, SpawnTriggers, Zone};
+use crate::spring::{Spring, SPRING_SNAPPINESS};
+use crate::switch::{Activatable, ActivationBehavior, PressurePlate, PLATE_COLOR, PLATE_SNAPPINESS};
+use crate::trap::{BladeSwing, Crusher, CrusherTrack, SwingingBlade, BLADE_CHAIN_COLOR, BLADE_CHAIN_WIDTH, CRUSHER_EDGE, CRUSHER_EDGE_COLOR};
+use crate::trigger::{TriggerZone, TriggerZoneSpawns};
+use crate::water::{WaterData, WaterSpawns};
+use crate::wind::{WindZone, WIND_COLOR};
+use crate::GameState;
+
+const LEVEL_DIR: &str = "assets/levels";
+/// The level the built-in enemies, pickups, triggers and cutscenes were placed for.
+const DEMO_LEVEL: &str = "level1";
+/// Smallest and largest a loaded block can be on either axis.
+const MIN_BLOCK_SIZE: f32 = 1.;
+const MAX_BLOCK_SIZE: f32 = 100_000.;
+/// How far from the origin a loaded block can be, on either axis. Nothing further out can
+/// be reached anyway, and its tile and grid cells would overflow.
+const MAX_BLOCK_DISTANCE: f32 = 1_000_000.;
+
+/// Loads the `CurrentLevel` the first time it's started and spawns its blocks, again on
+/// every restart.
+pub struct WorldPlugin;
+
+impl Plugin for WorldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentLevel>()
+            .init_resource::<LevelManager>()
+            .init_resource::<MaterialPalette>()
+            .add_systems(OnEnter(GameState::Restarting), (
+                init_world.run_if(not(any_with_component::<WorldData>)).before(ResetLevel),
+                spawn_world.after(ResetLevel),
+            ));
+    }
+}
+
+/// The level being played, as its file name in `assets/levels/` without the `.ron`.
+/// Picked on the main menu and loaded by `init_world`.
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub struct CurrentLevel(pub String);
+
+impl Default for CurrentLevel {
+    fn default() -> Self {
+        Self(DEMO_LEVEL.into())
+    }
+}
+
+impl CurrentLevel {
+    pub fn path(&self) -> String {
+        format!("{LEVEL_DIR}/{}.ron", self.0)
+    }
+}
+
+/// Every level in play order. Finishing one moves on to the next; finishing the last is
+/// the end of the game.
+#[derive(Resource)]
+pub struct LevelManager {
+    pub levels: Vec<String>,
+}
+
+impl Default for LevelManager {
+    fn default() -> Self {
+        Self { levels: level_names() }
+    }
+}
+
+impl LevelManager {
+    /// Where `level` is in the play order, if it's in it at all.
+    pub fn index_of(&self, level: &CurrentLevel) -> Option<usize> {
+        self.levels.iter().position(|name| *name == level.0)
+    }
+
+    /// The level after `level`, or `None` after the last one. A level that isn't in the
+    /// list (a missing or renamed file) has nothing after it.
+    pub fn next_after(&self, level: &CurrentLevel) -> Option<CurrentLevel> {
+        let index = self.index_of(level)?;
+        self.levels.get(index + 1).cloned().map(CurrentLevel)
+    }
+}
+
+/// Every level in `assets/levels/`, by name, in alphabetical order.
+pub fn level_names() -> Vec<String> {
+    let entries = match std::fs::read_dir(LEVEL_DIR) {
+        Ok(entries) => entries,
+        Err(error) => {
+            warn!("couldn't list levels in {LEVEL_DIR}: {error}");
+            return Vec::new();
+        }
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+        .filter_map(|path| path.file_stem().and_then(|stem| stem.to_str()).map(String::from))
+        .collect();
+    names.sort();
+    names
+}
+
+#[derive(Component)]
+pub struct Block;
+
+/// Where a block's entry is in `WorldData`, for the editor to find it by and for
+/// breaking it to mark it consumed.
+#[derive(Component)]
+pub struct BlockIndex(pub usize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Default, Deserialize)]
+pub enum BlockKind {
+    #[default]
+    Solid,
+    Gate { passable_from: Collision },
+    Cannon(Cannon),
+    Magnet(Magnet),
+    Slime,
+    Spring(Spring),
+    OneWay,
+    /// Not solid: touching it moves the player's respawn point here.
+    Checkpoint,
+    /// Kills the player on touch.
+    Spikes,
+    /// A ramp, cut corner to corner through the block.
+    Slope { rises_right: bool },
+    /// Solid until the player jumps into it from below, which smashes it.
+    Breakable,
+    /// Carries the player along its top at `speed` pixels per second, rightward if positive.
+    Conveyor { speed: f32 },
+    /// Not solid: the player can climb it.
+    Ladder,
+    /// Not solid: reaching it finishes the level, on touch or on pressing up inside it.
+    Exit { press_up: bool },
+    /// Not solid: pulls whatever is inside it with `gravity`, in pixels per second squared,
+    /// instead of world gravity.
+    GravityZone { gravity: Vec2 },
+    /// Flips the player's gravity when they touch it.
+    GravityFlip,
+    /// Not solid: pushes whatever is inside it with `force`, in pixels per second squared,
+    /// on top of gravity.
+    Wind { force: Vec2 },
+    /// Slams down and winds back up along its track; deadly underneath, safe on top.
+    Crusher(CrusherTrack),
+    /// Not solid: a round blade swinging from the block's position, deadly on touch.
+    Blade(BladeSwing),
+    /// Gives way `delay` seconds after it's first stood on, and comes back `respawn`
+    /// seconds later if that's set.
+    Crumbling { delay: f32, respawn: Option<f32> },
+    /// Solid for `on_duration` seconds out of every `period` and gone the rest, on a clock
+    /// the whole level shares. `phase`, from 0 to 1, shifts it along its period, to
+    /// stagger groups of blocks.
+    Blinking { period: f32, on_duration: f32, phase: f32 },
+    /// A platform hanging from a rope tied at the block's position, swinging once it's
+    /// landed on.
+    Swinging(PendulumRope),
+    /// Not solid: a thin plate that turns `channel` on while a player or a crate is on it,
+    /// or from the first press on if it's `latching`.
+    PressurePlate { channel: u32, latching: bool },
+    /// A door or bridge segment switched by the plates on `channel`.
+    Activatable { channel: u32, behavior: ActivationBehavior },
+    /// Flings the player along its arrow on touch. See `BoostPad` for the settings.
+    BoostPad(BoostPad),
+}
+
+impl BlockKind {
+    /// Whether the block gets a `Collider`, rather than being a region things pass through.
+    pub fn is_solid(self) -> bool {
+        !matches!(self, BlockKind::Checkpoint | BlockKind::Ladder | BlockKind::Exit { .. } | BlockKind::GravityZone { .. } | BlockKind::Wind { .. } | BlockKind::Blade(_) | BlockKind::PressurePlate { .. })
+    }
+
+    /// Whether the block is ground that stays put, for `GroundHeights`. Regions, gates,
+    /// and anything that moves, falls away or blinks out don't count.
+    pub fn is_static_ground(self) -> bool {
+        match self {
+            BlockKind::Solid | BlockKind::Cannon(_) | BlockKind::Magnet(_) | BlockKind::Slime | BlockKind::Spring(_) | BlockKind::OneWay | BlockKind::Spikes | BlockKind::Slope { .. } | BlockKind::Breakable | BlockKind::Conveyor { .. } | BlockKind::GravityFlip | BlockKind::BoostPad(_) => true,
+            BlockKind::Gate { .. } | BlockKind::Checkpoint | BlockKind::Ladder | BlockKind::Exit { .. } | BlockKind::GravityZone { .. } | BlockKind::Wind { .. } | BlockKind::Crusher(_) | BlockKind::Blade(_) | BlockKind::Crumbling { .. } | BlockKind::Blinking { .. } | BlockKind::Swinging(_) | BlockKind::PressurePlate { .. } | BlockKind::Activatable { .. } => false,
+        }
+    }
+
+    pub fn surface(self) -> SurfaceKind {
+        match self {
+            BlockKind::Solid | BlockKind::Gate { .. } | BlockKind::OneWay | BlockKind::Checkpoint | BlockKind::Slope { .. } | BlockKind::Breakable | BlockKind::Ladder | BlockKind::Exit { .. } | BlockKind::GravityZone { .. } | BlockKind::Wind { .. } | BlockKind::Crumbling { .. } | BlockKind::Blinking { .. } | BlockKind::Swinging(_) | BlockKind::Activatable { .. } => SurfaceKind::Stone,
+            BlockKind::Cannon(_) | BlockKind::Magnet(_) | BlockKind::Spring(_) | BlockKind::Spikes | BlockKind::Conveyor { .. } | BlockKind::GravityFlip | BlockKind::Crusher(_) | BlockKind::Blade(_) | BlockKind::PressurePlate { .. } | BlockKind::BoostPad(_) => SurfaceKind::Metal,
+            BlockKind::Slime => SurfaceKind::Slime,
+        }
+    }
+}
+
+/// What a block is made of, as far as footsteps and landing effects are concerned.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum SurfaceKind {
+    Stone,
+    Metal,
+    Ice,
+    Slime,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct BlockData {
+    pub position: Vec2,
+    pub shape: Vec2,
+    #[serde(default)]
+    pub kind: BlockKind,
+    /// Overrides the surface the block's kind would normally have.
+    #[serde(default)]
+    pub surface: Option<SurfaceKind>,
+    /// Makes the block a moving platform. Waypoints are offsets from `position`, and the
+    /// block starts out at the first one.
+    #[serde(default)]
+    pub path: Option<PlatformPath>,
+    /// Overrides the color the block's kind is drawn in. Magnets keep their polarity's.
+    #[serde(default)]
+    pub color: Option<LevelColor>,
+}
+
+/// A color as a level file writes it.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct LevelColor {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl LevelColor {
+    pub fn color(self) -> Color {
+        Color::srgb(self.r, self.g, self.b)
+    }
+}
+
+/// The screen behind everything in a level, where no background layer covers it. Stored
+/// next to the `WorldData`; without one the camera clears to bevy's default.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct LevelClearColor(pub Color);
+
+/// One `ColorMaterial` per color blocks are drawn in, shared by every block of that color
+/// across restarts and endless chunks instead of each spawn adding its own. Blocks that
+/// fade or recolor themselves still get a material of their own.
+#[derive(Resource, Default)]
+pub struct MaterialPalette(HashMap<[u32; 4], Handle<ColorMaterial>>);
+
+impl MaterialPalette {
+    pub fn get(&mut self, materials: &mut Assets<ColorMaterial>, color: Color) -> Handle<ColorMaterial> {
+        let key = color.linear().to_f32_array().map(f32::to_bits);
+        self.0.entry(key).or_insert_with(|| materials.add(color)).clone()
+    }
+}
+
+impl BlockData {
+    pub fn new(position: Vec2, shape: Vec2) -> Self {
+        Self {
+            position,
+            shape,
+            kind: BlockKind::Solid,
+            surface: None,
+            path: None,
+            color: None,
+        }
+    }
+
+    pub fn surface(&self) -> SurfaceKind {
+        let moving = self.path.is_some() && self.kind == BlockKind::Solid;
+        self.surface.unwrap_or(if moving { SurfaceKind::Metal } else { self.kind.surface() })
+    }
+
+    /// The block's shape centered on the origin: a right triangle for a slope, a circle for
+    /// a blade, a rectangle for everything else.
+    pub fn mesh(&self) -> Mesh {
+        let half = self.shape / 2.;
+        match self.kind {
+            BlockKind::Slope { rises_right } => {
+                let peak_x = if rises_right { half.x } else { -half.x };
+                Triangle2d::new(Vec2::new(-half.x, -half.y), Vec2::new(half.x, -half.y), Vec2::new(peak_x, half.y)).into()
+            }
+            BlockKind::Blade(_) => Circle::new(half.min_element()).into(),
+            _ => Rectangle::new(self.shape.x, self.shape.y).into(),
+        }
+    }
+
+    /// Where the block's path runs in the world, if it has one long enough to move along.
+    pub fn placed_path(&self) -> Option<PlatformPath> {
+        self.path.as_ref()
+            .filter(|path| path.waypoints.len() >= 2)
+            .map(|path| path.placed_at(self.position))
+    }
+}
+
+/// Every block in the level, in the order they're listed in its level file.
+#[derive(Component, Default, Deserialize)]
+#[serde(transparent)]
+pub struct WorldData(pub Vec<BlockData>);
+
+#[derive(Bundle)]
+pub struct BlockBundle {
+    block: Block,
+    shape: Shape,
+    position: Position,
+    collider: Collider,
+    rotation: Rotation,
+    z_order: ZOrder,
+}
+
+impl BlockBundle {
+    fn new(position: Vec2, shape: Vec2) -> Self {
+        Self {
+            block: Block,
+            shape: Shape(shape),
+            position: Position(position),
+            collider: Collider,
+            rotation: Rotation(0.),
+            z_order: ZOrder(0.),
+        }
+    }
+}
+
+/// Stands in as the collider for a run of touching blocks, whose own entities only draw
+/// them. Walking across one long box can't catch on the corner of the next tile.
+#[derive(Component)]
+pub struct MergedCollider;
+
+/// How far apart two edges can be and still count as touching.
+const MERGE_TOLERANCE: f32 = 1e-3;
+
+/// A rectangle of touching blocks that can share one collider.
+#[derive(Debug)]
+pub struct MergedBlocks {
+    pub rect: Rect,
+    pub surface: SurfaceKind,
+    /// Indices into `WorldData` of the blocks it covers.
+    pub blocks: Vec<usize>,
+}
+
+/// Greedily merges plain, unmoving `Solid` blocks of the same surface that meet edge to
+/// edge: first into rows of equal height, then stacks rows of equal span. Blocks in
+/// `skip` are left out. Blocks that don't touch anything come back on their own.
+pub fn merge_blocks(blocks: &[BlockData], skip: &HashSet<usize>) -> Vec<MergedBlocks> {
+    let singles = blocks.iter().enumerate()
+        .filter(|(index, block)| !skip.contains(index) && block.kind == BlockKind::Solid && block.path.is_none())
+        .map(|(index, block)| MergedBlocks {
+            rect: Rect::from_center_size(block.position, block.shape),
+            surface: block.surface(),
+            blocks: vec![index],
+        })
+        .collect();
+    merge_runs(merge_runs(singles, 0), 1)
+}
+
+/// One greedy sweep along `axis` (0 for x, 1 for y): sorted so that runs in line sit next
+/// to each other, each one is joined onto the last if they share a surface, line up
+/// across the axis and touch along it.
+fn merge_runs(mut runs: Vec<MergedBlocks>, axis: usize) -> Vec<MergedBlocks> {
+    let across = 1 - axis;
+    runs.sort_by(|a, b| (a.surface as u8).cmp(&(b.surface as u8))
+        .then(a.rect.min[across].total_cmp(&b.rect.min[across]))
+        .then(a.rect.max[across].total_cmp(&b.rect.max[across]))
+        .then(a.rect.min[axis].total_cmp(&b.rect.min[axis])));
+    let touching = |a: f32, b: f32| (a - b).abs() <= MERGE_TOLERANCE;
+    let mut merged: Vec<MergedBlocks> = Vec::with_capacity(runs.len());
+    for run in runs {
+        if let Some(last) = merged.last_mut() {
+            if last.surface == run.surface
+                && touching(last.rect.min[across], run.rect.min[across])
+                && touching(last.rect.max[across], run.rect.max[across])
+                && touching(last.rect.max[axis], run.rect.min[axis]) {
+                last.rect = last.rect.union(run.rect);
+                last.blocks.extend(run.blocks);
+                continue;
+            }
+        }
+        merged.push(run);
+    }
+    merged
+}
+
+/// What a level file holds: its blocks, and the scenery drawn behind them. Blocks can be
+/// listed as rectangles, drawn as a `grid`, or both.
+#[derive(Deserialize)]
+struct LevelFile {
+    #[serde(default)]
+    blocks: WorldData,
+    #[serde(default)]
+    grid: Option<LevelGrid>,
+    #[serde(default)]
+    background: ParallaxSpawns,
+    #[serde(default)]
+    triggers: TriggerZoneSpawns,
+    #[serde(default)]
+    keys: KeySpawns,
+    #[serde(default)]
+    doors: DoorSpawns,
+    #[serde(default)]
+    portals: PortalSpawns,
+    #[serde(default)]
+    camera_zones: CameraZoneSpawns,
+    /// A camera sequence played the first time the level is entered.
+    #[serde(default)]
+    intro: Option<CameraSequence>,
+    #[serde(default)]
+    camera_sequences: Vec<TriggeredSequence>,
+    #[serde(default)]
+    clear_color: Option<LevelColor>,
+}
+
+/// A level drawn as rows of characters, one per `tile_size` square, with the first row
+/// at the top. `#` is solid ground, `-` a one-way ledge, `^` spikes, `o` a coin, `P` where
+/// the player starts, `E` the exit and `.` or a space nothing. Rows can be different
+/// lengths.
+#[derive(Deserialize)]
+struct LevelGrid {
+    tile_size: f32,
+    /// The top-left corner of the first row's first tile.
+    #[serde(default)]
+    origin: Vec2,
+    rows: Vec<String>,
+}
+
+/// What a grid turns into besides blocks.
+#[derive(Default)]
+struct GridContents {
+    blocks: Vec<BlockData>,
+    coins: Vec<Vec2>,
+    spawn: Option<Vec2>,
+}
+
+impl LevelGrid {
+    /// Each row's runs of `#`, `-` and `^` become one block apiece, so a long floor is a
+    /// single collider rather than a tile each. Characters the format doesn't know are
+    /// logged and left empty.
+    fn parse(&self, path: &str) -> GridContents {
+        let mut contents = GridContents::default();
+        let tile = self.tile_size.max(MIN_BLOCK_SIZE);
+        let tile_center = |column: usize, row: usize| {
+            self.origin + Vec2::new((column as f32 + 0.5) * tile, -(row as f32 + 0.5) * tile)
+        };
+        for (row, line) in self.rows.iter().enumerate() {
+            let cells: Vec<char> = line.chars().collect();
+            let mut column = 0;
+            while column < cells.len() {
+                let cell = cells[column];
+                let run = cells[column..].iter().take_while(|other| **other == cell).count();
+                let center = tile_center(column, row);
+                match cell {
+                    '#' | '-' | '^' => {
+                        let width = run as f32 * tile;
+                        let left = center.x - tile / 2.;
+                        // Ledges are a thin slab along the top of their tiles and spikes
+                        // fill the bottom half, so both sit flush with ground beside them.
+                        let (height, y) = match cell {
+                            '-' => (tile / 4., center.y + tile * 3. / 8.),
+                            '^' => (tile / 2., center.y - tile / 4.),
+                            _ => (tile, center.y),
+                        };
+                        let mut block = BlockData::new(Vec2::new(left + width / 2., y), Vec2::new(width, height));
+                        block.kind = match cell {
+                            '-' => BlockKind::OneWay,
+                            '^' => BlockKind::Spikes,
+                            _ => BlockKind::Solid,
+                        };
+                        contents.blocks.push(block);
+                        column += run;
+                        continue;
+                    }
+                    'o' => contents.coins.push(center),
+                    'P' => {
+                        if contents.spawn.is_some() {
+                            warn!("{path}: more than one P in the grid, using the last");
+                        }
+                        contents.spawn = Some(center);
+                    }
+                    'E' => {
+                        let mut exit = BlockData::new(center, Vec2::splat(tile));
+                        exit.kind = BlockKind::Exit { press_up: false };
+                        contents.blocks.push(exit);
+                    }
+                    '.' | ' ' => {}
+                    unknown => warn!("{path}: unknown grid character {unknown:?} at row {row}, column {column}, leaving it empty"),
+                }
+                column += 1;
+            }
+        }
+        contents
+    }
+}
+
+/// Everything read from a level file.
+pub struct LevelContents {
+    pub blocks: WorldData,
+    pub background: ParallaxSpawns,
+    pub triggers: TriggerZoneSpawns,
+    pub keys: KeySpawns,
+    pub doors: DoorSpawns,
+    pub portals: PortalSpawns,
+    pub camera_zones: CameraZoneSpawns,
+    /// Coins from the grid.
+    pub coins: CoinSpawns,
+    pub spawn: PlayerSpawn,
+    pub intro: Option<CameraSequence>,
+    pub camera_sequences: Vec<TriggeredSequence>,
+    pub clear_color: Option<LevelClearColor>,
+}
+
+impl LevelContents {
+    /// The level's camera sequences, as cutscenes started by the trigger zones they name.
+    fn triggered_cutscenes(&self) -> TriggeredCutscenes {
+        TriggeredCutscenes(self.camera_sequences.iter().map(|sequence| (sequence.trigger.clone(), sequence.keyframes.cutscene())).collect())
+    }
+}
+
+/// Reads the level's blocks, background, trigger zones, keys, doors, portals, camera
+/// zones, camera sequences and clear color. A missing or broken file gets a warning and a bare floor under the spawn point,
+/// so the game still starts.
+fn load_world_data(path: &str) -> LevelContents {
+    read_level_file(path).unwrap_or_else(|error| {
+        warn!("couldn't load level {path}: {error}");
+        LevelContents {
+            blocks: WorldData(vec![BlockData::new(Vec2::new(0., -300.), Vec2::new(400., 50.))]),
+            background: ParallaxSpawns::default(),
+            triggers: TriggerZoneSpawns::default(),
+            keys: KeySpawns::default(),
+            doors: DoorSpawns::default(),
+            portals: PortalSpawns::default(),
+            camera_zones: CameraZoneSpawns::default(),
+            coins: CoinSpawns::default(),
+            spawn: PlayerSpawn::default(),
+            intro: None,
+            camera_sequences: Vec::new(),
+            clear_color: None,
+        }
+    })
+}
+
+/// Reads and sanitizes a level file, or says why it couldn't. Grid blocks come after the
+/// listed ones.
+pub fn read_level_file(path: &str) -> Result<LevelContents, String> {
+    let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let mut level = ron::from_str::<LevelFile>(&text).map_err(|error| error.to_string())?;
+    let grid = level.grid.map(|grid| grid.parse(path)).unwrap_or_default();
+    level.blocks.0.extend(grid.blocks);
+    sanitize_blocks(path, &mut level.blocks);
+    level.portals.check_pairs(path);
+    Ok(LevelContents {
+        blocks: level.blocks,
+        background: level.background,
+        triggers: level.triggers,
+        keys: level.keys,
+        doors: level.doors,
+        portals: level.portals,
+        camera_zones: level.camera_zones,
+        coins: CoinSpawns(grid.coins),
+        spawn: grid.spawn.map(PlayerSpawn).unwrap_or_default(),
+        intro: level.intro,
+        camera_sequences: level.camera_sequences,
+        clear_color: level.clear_color.map(|color| LevelClearColor(color.color())),
+    })
+}
+
+/// Drops blocks with non-finite numbers or past `MAX_BLOCK_DISTANCE` and brings every
+/// shape within `MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE`, so a broken level file can't feed NaN or
+/// inverted bounds into physics. Each fix is logged with the block's index in the file.
+fn sanitize_blocks(path: &str, world: &mut WorldData) {
+    let mut index = 0;
+    world.0.retain_mut(|block| {
+        let file_index = index;
+        index += 1;
+        if !block.position.is_finite() || !block.shape.is_finite() {
+            error!("{path}: block {file_index} has a non-finite position or shape, skipping it");
+            return false;
+        }
+        if block.position.abs().max_element() > MAX_BLOCK_DISTANCE {
+            error!("{path}: block {file_index} is at {}, too far out to play, skipping it", block.position);
+            return false;
+        }
+        let shape = block.shape.abs().clamp(Vec2::splat(MIN_BLOCK_SIZE), Vec2::splat(MAX_BLOCK_SIZE));
+        if shape != block.shape {
+            warn!("{path}: block {file_index} is {} in size, using {shape} instead", block.shape);
+            block.shape = shape;
+        }
+        true
+    });
+}
+
+/// Spawns the `WorldData` entity for the `CurrentLevel`, with everything else placed in
+/// it stored alongside. Runs as a level is started from the menu, ahead of the restart
+/// that spawns its content.
+pub fn init_world(
+    mut commands: Commands,
+    level: Res<CurrentLevel>,
+) {
+    if level.0 == ENDLESS_LEVEL {
+        // Generated as it's played; there's no file to read.
+        commands.spawn((
+            endless::start_platform(),
+            ParallaxSpawns::default(),
+            TriggerZoneSpawns::default(),
+            CoinSpawns::default(),
+            PlayerSpawn::default(),
+            ScriptTriggers::default(),
+            TriggerHints::default(),
+        ));
+        return;
+    }
+    let contents = load_world_data(&level.path());
+    let file_cutscenes = contents.triggered_cutscenes();
+    let LevelContents { blocks: world_data, background, triggers: trigger_zones, keys, doors, portals, camera_zones, coins: grid_coins, spawn, intro: file_intro, clear_color, .. } = contents;
+    if level.0 != DEMO_LEVEL {
+        let mut world = commands.spawn((world_data, background, trigger_zones, keys, doors, portals, camera_zones, grid_coins, spawn, ScriptTriggers::default(), TriggerHints::default(), file_cutscenes));
+        if let Some(intro) = file_intro {
+            world.insert(LevelIntro { id: level.0.clone(), cutscene: intro.cutscene() });
+        }
+        if let Some(clear_color) = clear_color {
+            world.insert(clear_color);
+        }
+        return;
+    }
+
+    let enemies = EnemySpawns(vec![EnemyData {
+        position: Vec2::new(-120., -250.),
+        shape: Vec2::new(50., 50.),
+        route: PatrolRoute::Range { min_x: -170., max_x: -40. },
+        speed: 144.,
+        chase: Some(ChaseBehavior {
+            sight_range: 300.,
+            speed: 288.,
+            reckless: false,
+        }),
+        boss_bar: Some(ShowBossBar {
+            name: "Sentry".into(),
+            phases: 3,
+        }),
+    }, EnemyData {
+        position: Vec2::new(60., -250.),
+        shape: Vec2::new(40., 50.),
+        route: PatrolRoute::Waypoints(vec![40., 150., 90.]),
+        speed: 115.,
+        chase: None,
+        boss_bar: None,
+    }]);
+
+    let water = WaterSpawns(vec![WaterData {
+        position: Vec2::new(450., -225.),
+        shape: Vec2::new(400., 100.),
+        current: Some(Vec2::new(432., 0.)),
+    }]);
+
+    let crates = CrateSpawns(vec![CrateData {
+        position: Vec2::new(-60., -255.),
+        shape: Vec2::new(40., 40.),
+    }]);
+
+    let safe_rooms = SafeRoomSpawns(vec![SafeRoomData {
+        position: Vec2::new(-300., -200.),
+        shape: Vec2::new(200., 150.),
+    }]);
+
+    let pickups = PickupSpawns(vec![
+        // Feather: fall slowly for a while.
+        PickupData {
+            position: Vec2::new(100., -200.),
+            color: Color::srgb(0.95, 0.95, 0.8),
+            modifiers: vec![StatModifier { stat: StatId::GravityScale, multiplier: 0.35, duration: Some(6.) }],
+        },
+        // Spring boots: higher jumps, harder to steer.
+        PickupData {
+            position: Vec2::new(-150., -250.),
+            color: Color::srgb(0.9, 0.4, 0.8),
+            modifiers: vec![
+                StatModifier { stat: StatId::JumpStrength, multiplier: 1.4, duration: Some(8.) },
+                StatModifier { stat: StatId::Accel, multiplier: 0.6, duration: Some(8.) },
+            ],
+        },
+    ]);
+
+    // A line of coins along the first stretch, and one over the gap for a brave jump.
+    let mut coins = CoinSpawns(vec![
+        Vec2::new(60., -200.),
+        Vec2::new(100., -200.),
+        Vec2::new(140., -200.),
+        Vec2::new(580., -150.),
+    ]);
+    coins.0.extend(grid_coins.0);
+
+    // Stepping into the river drops a chaser onto the ground behind the player.
+    let spawn_triggers = SpawnTriggers(vec![SpawnTrigger {
+        entities: vec![EntitySpawn::Enemy(EnemyData {
+            position: Vec2::new(150., -250.),
+            shape: Vec2::new(40., 40.),
+            route: PatrolRoute::Range { min_x: 100., max_x: 180. },
+            speed: 144.,
+            chase: Some(ChaseBehavior {
+                sight_range: 400.,
+                speed: 360.,
+                reckless: true,
+            }),
+            boss_bar: None,
+        })],
+        zone: Zone::Region { position: Vec2::new(300., -225.), size: Vec2::new(60., 100.) },
+        once: true,
+        despawn_on_leave: false,
+    }, SpawnTrigger {
+        // A speed boost that only exists while the player is near the slime pit.
+        entities: vec![EntitySpawn::Pickup(PickupData {
+            position: Vec2::new(-300., -240.),
+            color: Color::srgb(0.3, 0.8, 0.9),
+            modifiers: vec![StatModifier { stat: StatId::MaxSpeed, multiplier: 1.5, duration: Some(4.) }],
+        })],
+        zone: Zone::Radius { center: Vec2::new(-300., -250.), radius: 150. },
+        once: false,
+        despawn_on_leave: true,
+    }]);
+
+    // Pan from the end of the river back to the spawn. It stands in for any intro in the
+    // file.
+    let intro = LevelIntro {
+        id: "demo".into(),
+        cutscene: Cutscene(vec![
+            CutsceneStep::FadeOut(0.),
+            CutsceneStep::MoveCameraTo { point: Vec2::new(650., -200.), secs: 0. },
+            CutsceneStep::FadeIn(0.5),
+            CutsceneStep::ShowText("Follow the river".into()),
+            CutsceneStep::Wait(0.5),
+            CutsceneStep::MoveCameraTo { point: Vec2::ZERO, secs: 3. },
+            CutsceneStep::ShowText(String::new()),
+            CutsceneStep::SetCameraTarget(None),
+            CutsceneStep::MovePlayerTo { point: Vec2::new(40., 0.), walk: true },
+        ]),
+    };
+
+    // A hint before the first gap, the lava at the end of the river, and a look at the
+    // sentry the first time the player heads its way.
+    let script_triggers = ScriptTriggers(vec![ScriptTrigger {
+        id: "first_gap".into(),
+        zone: Zone::Region { position: Vec2::new(560., -200.), size: Vec2::new(40., 150.) },
+        once: true,
+    }, ScriptTrigger {
+        // Not `once`: a respawn puts the lava back down, and coming back starts it again.
+        id: "river_end".into(),
+        zone: Zone::Region { position: Vec2::new(650., -150.), size: Vec2::new(100., 300.) },
+        once: false,
+    }, ScriptTrigger {
+        id: "sentry_intro".into(),
+        zone: Zone::Region { position: Vec2::new(-75., -225.), size: Vec2::new(30., 100.) },
+        once: true,
+    }]);
+    let hints = TriggerHints(vec![("first_gap".into(), "Wait for the platform, then jump".into())]);
+    let mut triggered_cutscenes = TriggeredCutscenes(vec![("sentry_intro".into(), Cutscene(vec![
+        CutsceneStep::MoveCameraTo { point: Vec2::new(-120., -200.), secs: 0.8 },
+        CutsceneStep::ShowText("The Sentry".into()),
+        CutsceneStep::Wait(1.2),
+        CutsceneStep::ShowText(String::new()),
+        CutsceneStep::SetCameraTarget(None),
+    ]))]);
+    triggered_cutscenes.0.extend(file_cutscenes.0);
+
+    // Reaching the end of the river sets the lava rising; the cannon's block stays dry.
+    let hazards = HazardSpawns(vec![RisingHazard {
+        speed: 40.,
+        start_y: -600.,
+        max_y: Some(-240.),
+        trigger: "river_end".into(),
+        frame_camera: true,
+    }]);
+
+    let music = MusicStems(vec![
+        MusicStem { path: "music/demo_base.ogg".into(), layer: MusicLayer::Base },
+        MusicStem { path: "music/demo_drums.ogg".into(), layer: MusicLayer::Drums },
+        MusicStem { path: "music/demo_lead.ogg".into(), layer: MusicLayer::Lead },
+    ]);
+
+    let mut world = commands.spawn(((world_data, background, trigger_zones, keys, doors, portals, camera_zones, spawn), enemies, water, safe_rooms, intro, hazards, pickups, coins, spawn_triggers, crates, music, script_triggers, hints, triggered_cutscenes));
+    if let Some(clear_color) = clear_color {
+        world.insert(clear_color);
+    }
+}
+
+fn spawn_world(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut palette: ResMut<MaterialPalette>,
+    world_data: Query<&WorldData>,
+    level_state: Res<LevelState>,
+) {
+    if let Ok(world_data) = world_data.get_single() {
+        spawn_blocks(&mut commands, &mut meshes, &mut materials, &mut palette, world_data, &level_state);
+    }
+}
+
+/// Spawns an entity for every block in `world_data` that hasn't been consumed this attempt.
+/// Runs of touching plain blocks get one `MergedCollider` between them instead of a
+/// collider each.
+pub fn spawn_blocks(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    palette: &mut MaterialPalette,
+    world_data: &WorldData,
+    level_state: &LevelState,
+) {
+    let material_handle = palette.get(materials, Color::oklab(0.8, 0., 0.));
+    let gate_material = palette.get(materials, Color::oklab(0.7, -0.1, 0.1));
+    let arrow_material = palette.get(materials, Color::WHITE);
+    let cannon_material = palette.get(materials, Color::srgb(0.3, 0.3, 0.35));
+    let ice_material = palette.get(materials, Color::srgb(0.7, 0.85, 1.));
+    let slime_material = palette.get(materials, Color::srgb(0.4, 0.85, 0.3));
+    let spring_material = palette.get(materials, Color::srgb(0.95, 0.75, 0.2));
+    let boost_pad_material = palette.get(materials, BOOST_PAD_COLOR);
+    let one_way_material = palette.get(materials, Color::oklab(0.6, 0.02, 0.05));
+    let platform_material = palette.get(materials, Color::srgb(0.55, 0.6, 0.7));
+    let checkpoint_material = palette.get(materials, checkpoint::IDLE_COLOR);
+    let spike_material = palette.get(materials, Color::srgb(0.75, 0.2, 0.2));
+    let breakable_material = palette.get(materials, BREAKABLE_COLOR);
+    let conveyor_material = palette.get(materials, Color::srgb(0.35, 0.35, 0.4));
+    let ladder_material = palette.get(materials, LADDER_COLOR);
+    let exit_material = palette.get(materials, EXIT_COLOR);
+    let gravity_zone_material = palette.get(materials, GRAVITY_ZONE_COLOR);
+    let gravity_flip_material = palette.get(materials, GRAVITY_FLIP_COLOR);
+    let wind_material = palette.get(materials, WIND_COLOR);
+    let crusher_material = palette.get(materials, Color::srgb(0.4, 0.4, 0.45));
+    let crusher_edge_material = palette.get(materials, CRUSHER_EDGE_COLOR);
+    let blade_material = palette.get(materials, Color::srgb(0.8, 0.82, 0.85));
+    let plate_material = palette.get(materials, PLATE_COLOR);
+    let mut merged = HashSet::new();
+    for run in merge_blocks(&world_data.0, &level_state.consumed).into_iter().filter(|run| run.blocks.len() > 1) {
+        commands.spawn((
+            MergedCollider,
+            Position(run.rect.center()),
+            Shape(run.rect.size()),
+            Collider,
+            Rotation(0.),
+            ZOrder(0.),
+            run.surface,
+            LevelEntity,
+        ));
+        merged.extend(run.blocks);
+    }
+    for (index, block) in world_data.0.iter().enumerate() {
+        if level_state.consumed.contains(&index) {
+            continue;
+        }
+        let mut entity = commands.spawn((
+            BlockBundle::new(block.position, block.shape),
+            ColorMesh2dBundle {
+                material: material_handle.clone(),
+                mesh: meshes.add(block.mesh()).into(),
+                ..default()
+            },
+            block.surface(),
+            BlockIndex(index),
+            LevelEntity,
+        ));
+        if merged.contains(&index) {
+            entity.remove::<Collider>();
+        }
+        if block.surface() == SurfaceKind::Ice {
+            entity.insert(ice_material.clone());
+        }
+        if let BlockKind::Cannon(cannon) = block.kind {
+            entity.insert((cannon, Rotation(cannon.direction.to_angle()), cannon_material.clone()));
+        }
+        if block.kind == BlockKind::Slime {
+            entity.insert((Slime, slime_material.clone()));
+        }
+        if block.kind == BlockKind::OneWay {
+            entity.insert((OneWayPlatform, one_way_material.clone()));
+        }
+        if block.kind == BlockKind::Spikes {
+            entity.insert((Hazard, spike_material.clone()));
+        }
+        if let BlockKind::Slope { rises_right } = block.kind {
+            entity.insert(Slope { rises_right });
+        }
+        if block.kind == BlockKind::Breakable {
+            entity.insert((Breakable, breakable_material.clone()));
+        }
+        if let BlockKind::Conveyor { speed } = block.kind {
+            entity.insert((Conveyor { speed }, conveyor_material.clone()));
+        }
+        if block.kind == BlockKind::Checkpoint {
+            entity.remove::<Collider>().insert((Checkpoint::default(), ZOrder(-0.1), checkpoint_material.clone()));
+        }
+        if block.kind == BlockKind::Ladder {
+            entity.remove::<Collider>().insert((Ladder, ZOrder(-0.1), ladder_material.clone()));
+        }
+        if let BlockKind::Exit { press_up } = block.kind {
+            entity.remove::<Collider>().insert((Exit { press_up }, ZOrder(-0.1), exit_material.clone()));
+        }
+        if let BlockKind::GravityZone { gravity } = block.kind {
+            entity.remove::<Collider>().insert((
+                TriggerZone { tag: GRAVITY_ZONE_TAG.into() },
+                GravityZone { gravity },
+                ZOrder(-0.1),
+                gravity_zone_material.clone(),
+            ));
+        }
+        if block.kind == BlockKind::GravityFlip {
+            entity.insert((GravityFlipper, gravity_flip_material.clone()));
+        }
+        if let BlockKind::Wind { force } = block.kind {
+            entity.remove::<Collider>().insert((WindZone::new(force), ZOrder(-0.1), wind_material.clone()));
+        }
+        if let BlockKind::Crusher(track) = block.kind {
+            entity.insert((Crusher::new(track, block.position), crusher_material.clone()));
+            // The deadly bottom face, picked out in red.
+            entity.with_children(|crusher| {
+                crusher.spawn(ColorMesh2dBundle {
+                    material: crusher_edge_material.clone(),
+                    mesh: meshes.add(Rectangle::new(block.shape.x, CRUSHER_EDGE)).into(),
+                    transform: Transform::from_xyz(0., (CRUSHER_EDGE - block.shape.y) / 2., 0.01),
+                    ..default()
+                });
+            });
+        }
+        if let BlockKind::Blade(swing) = block.kind {
+            let blade = SwingingBlade::new(swing, block.position);
+            let (position, angle) = blade.start();
+            entity.remove::<Collider>().insert((Position(position), Rotation(angle), blade, blade_material.clone()));
+            // The chain runs up from the blade to the pivot, which the blade's rotation
+            // always keeps straight above it.
+            entity.with_children(|blade| {
+                blade.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: BLADE_CHAIN_COLOR,
+                        custom_size: Some(Vec2::new(BLADE_CHAIN_WIDTH, swing.length)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., swing.length / 2., -0.01),
+                    ..default()
+                });
+            });
+        }
+        if let BlockKind::Crumbling { delay, respawn } = block.kind {
+            // Its own material, since it fades on its own.
+            entity.insert((Crumbling::new(delay, respawn), materials.add(block.color.map_or(CRUMBLING_COLOR, LevelColor::color))));
+        }
+        if let BlockKind::Blinking { period, on_duration, phase } = block.kind {
+            entity.insert((Blinking::new(period, on_duration, phase), materials.add(block.color.map_or(BLINKING_COLOR, LevelColor::color))));
+        }
+        if let BlockKind::Swinging(rope) = block.kind {
+            let platform = SwingingPlatform::new(rope, block.position);
+            entity.insert((Position(platform.rest()), Velocity(Vec2::ZERO), platform, platform_material.clone()));
+            // Its rotation keeps local up pointing at the pivot, as with a blade's chain.
+            entity.with_children(|platform| {
+                platform.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: ROPE_COLOR,
+                        custom_size: Some(Vec2::new(ROPE_WIDTH, rope.length)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., rope.length / 2., -0.01),
+                    ..default()
+                });
+            });
+        }
+        if let BlockKind::PressurePlate { channel, latching } = block.kind {
+            entity.remove::<Collider>().insert((
+                PressurePlate::new(channel, latching),
+                VisShape(block.shape),
+                SquashStretch::new(block.shape, PLATE_SNAPPINESS),
+                ZOrder(-0.1),
+                plate_material.clone(),
+            ));
+        }
+        if let BlockKind::Activatable { channel, behavior } = block.kind {
+            let switched = Activatable::new(channel, behavior);
+            if !switched.is_solid() {
+                entity.remove::<Collider>();
+            }
+            entity.insert((switched, materials.add(block.color.map_or(behavior.color(), LevelColor::color))));
+        }
+        if let Some(path) = block.placed_path() {
+            entity.insert((Position(path.waypoints[0]), MovingPlatform::new(path)));
+            if block.kind == BlockKind::Solid && block.surface() != SurfaceKind::Ice {
+                entity.insert(platform_material.clone());
+            }
+        }
+        if let BlockKind::Spring(spring) = block.kind {
+            // Springs are drawn pointing up, then turned to face their launch direction.
+            let angle = spring.direction.to_angle() - std::f32::consts::FRAC_PI_2;
+            entity.insert((spring, Rotation(angle), VisShape(block.shape), SquashStretch::new(block.shape, SPRING_SNAPPINESS), spring_material.clone()));
+        }
+        if let BlockKind::BoostPad(pad) = block.kind {
+            // Drawn pointing up and turned like a spring, with an arrow along the boost.
+            let angle = pad.direction.to_angle() - std::f32::consts::FRAC_PI_2;
+            entity.insert((pad, Rotation(angle), VisShape(block.shape), SquashStretch::new(block.shape, BOOST_PAD_SNAPPINESS), boost_pad_material.clone()));
+            let size = block.shape.min_element().max(8.) / 2.;
+            entity.with_children(|pad| {
+                pad.spawn(ColorMesh2dBundle {
+                    material: arrow_material.clone(),
+                    mesh: meshes.add(Triangle2d::new(
+                        Vec2::new(0., size / 2.),
+                        Vec2::new(-size / 2., -size / 2.),
+                        Vec2::new(size / 2., -size / 2.))).into(),
+                    transform: Transform::from_xyz(0., 0., 0.01),
+                    ..default()
+                });
+            });
+        }
+        if let BlockKind::Magnet(magnet) = block.kind {
+            entity.insert((magnet, MagnetPulse::default(), LoopingSfx::new(SfxKind::MagnetHum), materials.add(magnet.color())));
+        }
+        if let BlockKind::Gate { passable_from } = block.kind {
+            entity.insert((Gate { passable_from }, gate_material.clone()));
+            // Arrow pointing the way the gate lets you through.
+            let size = block.shape.min_element().max(8.);
+            entity.with_children(|gate| {
+                gate.spawn(ColorMesh2dBundle {
+                    material: arrow_material.clone(),
+                    mesh: meshes.add(Triangle2d::new(
+                        Vec2::new(size / 2., 0.),
+                        Vec2::new(-size / 2., size / 2.),
+                        Vec2::new(-size / 2., -size / 2.))).into(),
+                    transform: Transform::from_xyz(0., 0., 0.01)
+                        .with_rotation(Quat::from_rotation_z(pass_direction(passable_from).to_angle())),
+                    ..default()
+                });
+            });
+        }
+        let owns_material = matches!(block.kind, BlockKind::Crumbling { .. } | BlockKind::Blinking { .. } | BlockKind::Magnet(_) | BlockKind::Activatable { .. });
+        if let Some(color) = block.color.filter(|_| !owns_material) {
+            entity.insert(palette.get(materials, color.color()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::build_headless_app;
+    use crate::player::Player;
+
+    #[test]
+    fn the_levels_load_from_their_files() {
+        let level = read_level_file("assets/levels/level1.ron").unwrap();
+        let blocks = &level.blocks.0;
+        // Not just the floor `load_world_data` falls back on, which starts the list too.
+        assert!(blocks.len() > 20, "only {} blocks", blocks.len());
+        assert_eq!((blocks[0].position, blocks[0].shape), (Vec2::new(0., -300.), Vec2::new(400., 50.)));
+        assert_eq!(blocks[0].kind, BlockKind::Solid);
+        assert_eq!(blocks[2].surface, Some(SurfaceKind::Ice));
+        assert_eq!(blocks[3].kind, BlockKind::Gate { passable_from: Collision::Right });
+        assert!(read_level_file("assets/levels/level2.ron").is_ok());
+    }
+
+    #[test]
+    fn a_garbage_level_never_turns_into_nan() {
+        let path = std::env::temp_dir().join(format!("platformer-garbage-{}.ron", std::process::id()));
+        std::fs::write(&path, "(blocks: [
+            (position: (0, -200), shape: (-3000, 50)),
+            (position: (NaN, 0), shape: (50, 50)),
+            (position: (100, -100), shape: (inf, 50)),
+            (position: (-100, -100), shape: (0, 0)),
+            (position: (300, -150), shape: (1e30, -1e30)),
+            (position: (1e30, 1e30), shape: (50, 50)),
+        ])").unwrap();
+        let level = read_level_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let blocks = level.blocks.0;
+        assert_eq!(blocks.len(), 3);
+        for block in &blocks {
+            assert!(block.shape.cmpge(Vec2::splat(MIN_BLOCK_SIZE)).all() && block.shape.cmple(Vec2::splat(MAX_BLOCK_SIZE)).all(), "{block:?}");
+        }
+
+        let mut app = build_headless_app(WorldData(blocks));
+        for _ in 0..100 {
+            app.update();
+        }
+        let mut players = app.world_mut().query_filtered::<(&Position, &Velocity), With<Player>>();
+        let (position, velocity) = players.single(app.world());
+        assert!(position.0.is_finite() && velocity.0.is_finite(), "player at {} going {}", position.0, velocity.0);
+        let mut cameras = app.world_mut().query_filtered::<&Transform, With<Camera>>();
+        for camera in cameras.iter(app.world()) {
+            assert!(camera.translation.is_finite(), "camera at {}", camera.translation);
+        }
+    }
+
+    fn tiles(cells: &[(i32, i32)]) -> Vec<BlockData> {
+        cells.iter().map(|&(x, y)| BlockData::new(Vec2::new(x as f32, y as f32) * 50., Vec2::splat(50.))).collect()
+    }
+
+    #[test]
+    fn a_row_of_blocks_is_one_collider() {
+        let row: Vec<_> = (0..10).map(|x| (x, 0)).collect();
+        let merged = merge_blocks(&tiles(&row), &HashSet::new());
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].rect, Rect::new(-25., -25., 475., 25.));
+        assert_eq!(merged[0].blocks.len(), 10);
+    }
+
+    #[test]
+    fn an_l_shape_is_two_colliders() {
+        // A floor of five with a wall of four more standing on its left end.
+        let mut cells: Vec<_> = (0..5).map(|x| (x, 0)).collect();
+        cells.extend((1..5).map(|y| (0, y)));
+        let mut merged = merge_blocks(&tiles(&cells), &HashSet::new());
+        merged.sort_by(|a, b| a.rect.min.y.total_cmp(&b.rect.min.y));
+        let rects: Vec<_> = merged.iter().map(|run| run.rect).collect();
+        assert_eq!(rects, [Rect::new(-25., -25., 225., 25.), Rect::new(-25., 25., 25., 225.)]);
+    }
+
+    fn grid(rows: &[&str]) -> GridContents {
+        LevelGrid { tile_size: 10., origin: Vec2::ZERO, rows: rows.iter().map(|row| row.to_string()).collect() }.parse("test")
+    }
+
+    #[test]
+    fn a_grid_makes_one_block_per_run() {
+        let contents = grid(&["P  o", "####--##"]);
+        let blocks: Vec<_> = contents.blocks.iter().map(|block| (block.kind, block.position, block.shape)).collect();
+        assert_eq!(blocks, [
+            (BlockKind::Solid, Vec2::new(20., -15.), Vec2::new(40., 10.)),
+            // A thin slab along the top of its tiles, flush with the ground beside it.
+            (BlockKind::OneWay, Vec2::new(50., -11.25), Vec2::new(20., 2.5)),
+            (BlockKind::Solid, Vec2::new(70., -15.), Vec2::new(20., 10.)),
+        ]);
+        assert_eq!(contents.spawn, Some(Vec2::new(5., -5.)));
+        assert_eq!(contents.coins, [Vec2::new(35., -5.)]);
+    }
+
+    #[test]
+    fn unknown_grid_characters_are_left_empty() {
+        let contents = grid(&["#?#", "#x"]);
+        let positions: Vec<_> = contents.blocks.iter().map(|block| block.position).collect();
+        assert_eq!(positions, [Vec2::new(5., -5.), Vec2::new(25., -5.), Vec2::new(5., -15.)]);
+    }
+
+    #[test]
+    fn ragged_rows_line_up_on_the_left() {
+        let contents = grid(&["#", "", "###"]);
+        let blocks: Vec<_> = contents.blocks.iter().map(|block| (block.position, block.shape)).collect();
+        assert_eq!(blocks, [
+            (Vec2::new(5., -5.), Vec2::splat(10.)),
+            (Vec2::new(15., -25.), Vec2::new(30., 10.)),
+        ]);
+    }
+}
@@ -0,0 +1,34 @@
+//! Builds the game with no optional features and with each one on its own, so a feature
+//! that leans on another without saying so shows up. Each build is a full compile, so it's
+//! opt-in: `cargo test --test features -- --ignored`.
+
+use std::process::Command;
+
+/// Every feature in `Cargo.toml` besides `default`.
+const FEATURES: [&str; 3] = ["audio", "editor", "debug-tools"];
+
+fn check(features: &[&str]) {
+    let mut command = Command::new(env!("CARGO"));
+    command.current_dir(env!("CARGO_MANIFEST_DIR"))
+        // Its own target dir, so it doesn't wait on the lock of the `cargo test` running it.
+        .args(["check", "--all-targets", "--target-dir", "target/features", "--no-default-features"]);
+    if !features.is_empty() {
+        command.args(["--features", &features.join(",")]);
+    }
+    let status = command.status().expect("couldn't run cargo");
+    assert!(status.success(), "cargo check failed with features {features:?}");
+}
+
+#[test]
+#[ignore = "a full build per feature"]
+fn builds_without_features() {
+    check(&[]);
+}
+
+#[test]
+#[ignore = "a full build per feature"]
+fn builds_with_each_feature_alone() {
+    for feature in FEATURES {
+        check(&[feature]);
+    }
+}